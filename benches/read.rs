@@ -0,0 +1,128 @@
+//! Benchmarks reading a large input file, mapped into memory and with `--no-mmap`, by running the command on it.
+//!
+//! Run with `cargo bench --bench read`. The generated file is 1 GiB, or `FFH_BENCH_MB` megabytes if that's set, and is
+//! written to the system's temporary directory, which needs room for it.
+
+use std::{
+    env, fs,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const SYNTAX: &str = "start,length,name,condition,pattern
+1,1,Type,,
+2,8,Date,^H,[0-9]{8}
+2,10,Account,^D,[0-9]{10}
+12,30,Name,^D,
+42,12,Amount,^D,[0-9]{9}\\.[0-9]{2}
+54,3,Currency,^D,[A-Z]{3}
+57,24,Reference,^D,
+";
+
+const DEFAULT_MEGABYTES: u64 = 1024;
+const RUNS: usize = 3;
+
+fn main() -> anyhow::Result<()> {
+    let megabytes = match env::var("FFH_BENCH_MB") {
+        Ok(megabytes) => megabytes.parse()?,
+        Err(_) => DEFAULT_MEGABYTES,
+    };
+    let dir = env::temp_dir();
+    let syntax = dir.join(format!("ffh-bench-read-{}.csv", std::process::id()));
+    let input = dir.join(format!("ffh-bench-read-{}.txt", std::process::id()));
+    fs::write(&syntax, SYNTAX)?;
+    let bytes = generate(&input, megabytes * 1024 * 1024)?;
+    println!("{} byte(s) in {}", bytes, input.display());
+
+    let cases: [(&str, &[&str], usize); 2] = [
+        // every line is read, but only the last is kept and written
+        (
+            "reading",
+            &["--tail", "1", "--output-format", "jsonl"],
+            RUNS,
+        ),
+        // every line is read and its fields checked, which takes long enough on a large file to time just once
+        ("validating", &["--validate"], 1),
+    ];
+    let result = cases.iter().try_for_each(|(name, options, runs)| {
+        for mapped in [true, false] {
+            let best = (0..*runs)
+                .map(|_| time(options, mapped, &input, &syntax))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .min()
+                .expect("there is at least one run");
+            println!(
+                "{:>10}, {:>9}: {:>10.3?}, {:>8.1} MB/s",
+                name,
+                if mapped { "mapped" } else { "--no-mmap" },
+                best,
+                bytes as f64 / 1_000_000.0 / best.as_secs_f64()
+            );
+        }
+        Ok(())
+    });
+    fs::remove_file(&input)?;
+    fs::remove_file(&syntax)?;
+    result
+}
+
+/// Run the command on `input` with `options`, returning how long it took.
+fn time(options: &[&str], mapped: bool, input: &Path, syntax: &Path) -> anyhow::Result<Duration> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_fixedfile-highlighter"));
+    command.args(options);
+    if !mapped {
+        command.arg("--no-mmap");
+    }
+    command
+        .arg(input)
+        .arg(syntax)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let start = Instant::now();
+    let status = command.status()?;
+    let elapsed = start.elapsed();
+    // problems found in the input, such as the header rule not applying to the one line kept by `--tail`, are only
+    // reported with status 1
+    anyhow::ensure!(
+        matches!(status.code(), Some(0 | 1)),
+        "the command failed: {}",
+        status
+    );
+    Ok(elapsed)
+}
+
+/// Write lines to `path` until it's at least `size` bytes, returning its size.
+fn generate(path: &Path, size: u64) -> anyhow::Result<u64> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut bytes = 0;
+    let mut n = 0;
+    while bytes < size {
+        let line = line(n);
+        writeln!(out, "{}", line)?;
+        bytes += line.len() as u64 + 1;
+        n += 1;
+    }
+    out.flush()?;
+    Ok(bytes)
+}
+
+/// The `n`th line of the generated file: a header, then detail records.
+fn line(n: usize) -> String {
+    if n.is_multiple_of(100) {
+        format!("H{:08}", 20230101 + n % 28)
+    } else {
+        format!(
+            "D{:010}{:<30}{:09}.{:02}{}{:<24}",
+            n,
+            format!("Smith & Sons <{}>", n % 997),
+            n * 7 % 1_000_000_000,
+            n % 100,
+            ["GBP", "EUR", "USD"][n % 3],
+            format!("REF\"{}\"", n)
+        )
+    }
+}
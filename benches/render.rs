@@ -0,0 +1,99 @@
+//! Benchmarks preparing and rendering lines as HTML, on one thread and on every processor.
+//!
+//! Run with `cargo bench`. Criterion isn't used, so each case is simply timed over a generated file of record lines.
+
+use std::time::{Duration, Instant};
+
+use fixedfile_highlighter::{
+    parse_syntax_file, BatchRenderer, PositionOptions, Renderer, ShortLinePolicy,
+};
+
+const SYNTAX: &str = "start,length,name,condition,pattern
+1,1,Type,,
+2,8,Date,^H,[0-9]{8}
+2,10,Account,^D,[0-9]{10}
+12,30,Name,^D,
+42,12,Amount,^D,[0-9]{9}\\.[0-9]{2}
+54,3,Currency,^D,[A-Z]{3}
+57,24,Reference,^D,
+";
+
+const LINES: usize = 100_000;
+const BATCH_LINES: usize = 1_000;
+const RUNS: usize = 3;
+
+fn main() -> anyhow::Result<()> {
+    let records = parse_syntax_file(SYNTAX, None)?;
+    let renderer = Renderer::default();
+    let lines: Vec<String> = (0..LINES).map(line).collect();
+    let processors = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+
+    let mut thread_counts = vec![1, 2, 4, 8, processors];
+    thread_counts.retain(|threads| *threads <= processors);
+    thread_counts.sort_unstable();
+    thread_counts.dedup();
+    let mut single = None;
+    for threads in thread_counts {
+        let batches = BatchRenderer {
+            records: &records,
+            positions: PositionOptions::default(),
+            short_lines: ShortLinePolicy::Truncate,
+            ghost_cells: false,
+            skip_lines: &[],
+            renderer: &renderer,
+            threads,
+        };
+        let best = (0..RUNS)
+            .map(|_| time(&batches, &lines))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .min()
+            .expect("there is at least one run");
+        let single = *single.get_or_insert(best);
+        println!(
+            "{:>2} thread(s): {:>8.3?} for {} lines, {:>9.0} lines/s, {:.2}x",
+            threads,
+            best,
+            LINES,
+            LINES as f64 / best.as_secs_f64(),
+            single.as_secs_f64() / best.as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+/// Prepare and render every line in batches, as the HTML report does, returning how long it took.
+fn time(batches: &BatchRenderer, lines: &[String]) -> anyhow::Result<Duration> {
+    let start = Instant::now();
+    let mut bytes = 0;
+    for (batch, chunk) in lines.chunks(BATCH_LINES).enumerate() {
+        let chunk = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (batch * BATCH_LINES + i, line.clone(), None))
+            .collect();
+        for rendered in batches.render(chunk) {
+            bytes += rendered?.html.len();
+        }
+    }
+    let elapsed = start.elapsed();
+    assert!(bytes > 0);
+    Ok(elapsed)
+}
+
+/// The `n`th line of the generated file: a header, then detail records with names needing escaping.
+fn line(n: usize) -> String {
+    if n.is_multiple_of(100) {
+        format!("H{:08}", 20230101 + n % 28)
+    } else {
+        format!(
+            "D{:010}{:<30}{:09}.{:02}{}{:<24}",
+            n,
+            format!("Smith & Sons <{}>", n % 997),
+            n * 7 % 1_000_000_000,
+            n % 100,
+            ["GBP", "EUR", "USD"][n % 3],
+            format!("REF\"{}\"", n)
+        )
+    }
+}
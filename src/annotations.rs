@@ -0,0 +1,180 @@
+//! How `--validate` writes the violations it finds: as a CSV table, or for CI pipelines, as a SARIF log or GitHub
+//! Actions workflow commands, both of which annotate the input file at the field breaking its constraint.
+
+use std::io::Write;
+
+use crate::{constraints::Violation, json_string};
+
+/// The format `--validate` writes violations in.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidateFormat {
+    /// A CSV row of line, field, value and the constraint broken for each violation
+    Csv,
+    /// A SARIF 2.1.0 log, for code scanning tools such as GitHub's, with each violation a result located at its field
+    Sarif,
+    /// A GitHub Actions `::error` workflow command for each violation, which the run shows as an annotation on the field
+    Github,
+}
+
+/// The constraints a violation can break, with what each checks, as SARIF rules.
+const RULES: [(&str, &str); 5] = [
+    ("charset", "Every character of the field is in its charset."),
+    ("case", "The field's letters are in its case."),
+    ("padding", "The field is padded as its justify and pad say."),
+    ("pattern", "The field's whole value matches its pattern."),
+    ("plugin", "The plugin decoding the field says it's valid."),
+];
+
+/// Writes each violation found, as it's found, in one of the formats of [`ValidateFormat`].
+pub struct ViolationWriter {
+    out: Output,
+    /// The input file, as given, which annotations are located in.
+    input_file: String,
+}
+
+enum Output {
+    // boxed, as a CSV writer has a large buffer of its own
+    Csv(Box<csv::Writer<Box<dyn Write>>>),
+    Sarif {
+        out: Box<dyn Write>,
+        /// Whether a result has been written, so the next needs a comma before it.
+        written: bool,
+    },
+    Github(Box<dyn Write>),
+}
+
+impl ViolationWriter {
+    /// Start writing violations found in `input_file` to `out`, with the CSV header or the start of the SARIF log.
+    pub fn new(
+        mut out: Box<dyn Write>,
+        format: ValidateFormat,
+        input_file: &str,
+    ) -> anyhow::Result<Self> {
+        let out = match format {
+            ValidateFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(out);
+                writer.write_record(["line", "field", "value", "expected"])?;
+                Output::Csv(Box::new(writer))
+            }
+            ValidateFormat::Sarif => {
+                let rules: Vec<String> = RULES
+                    .iter()
+                    .map(|(id, description)| {
+                        format!(
+                            r#"{{"id":{},"shortDescription":{{"text":{}}}}}"#,
+                            json_string(id),
+                            json_string(description)
+                        )
+                    })
+                    .collect();
+                write!(
+                    out,
+                    "{{\n  \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\",\n  \"version\": \"2.1.0\",\n  \"runs\": [{{\n    \"tool\": {{\"driver\": {{\"name\": \"fixedfile-highlighter\", \"version\": {}, \"rules\": [{}]}}}},\n    \"columnKind\": \"unicodeCodePoints\",\n    \"results\": [",
+                    json_string(env!("CARGO_PKG_VERSION")),
+                    rules.join(",")
+                )?;
+                Output::Sarif {
+                    out,
+                    written: false,
+                }
+            }
+            ValidateFormat::Github => Output::Github(out),
+        };
+        Ok(Self {
+            out,
+            input_file: input_file.to_owned(),
+        })
+    }
+
+    /// Write `violation`, found on the `line_index`th (from 0) line.
+    pub fn write(&mut self, line_index: usize, violation: Violation) -> anyhow::Result<()> {
+        // annotations count columns from 1, and end with the field's last column
+        let (line, start, end) = (
+            line_index + 1,
+            violation.columns.start + 1,
+            violation.columns.end.max(violation.columns.start + 1),
+        );
+        let message = format!(
+            "Field '{}' has the value '{}', which breaks its {}.",
+            violation.field, violation.value, violation.expected
+        );
+        match &mut self.out {
+            Output::Csv(writer) => writer.write_record([
+                line.to_string(),
+                violation.field,
+                violation.value,
+                violation.expected,
+            ])?,
+            Output::Sarif { out, written } => {
+                write!(
+                    out,
+                    "{}\n      {{\"ruleId\": {}, \"level\": \"error\", \"message\": {{\"text\": {}}}, \"locations\": [{{\"physicalLocation\": {{\"artifactLocation\": {{\"uri\": {}}}, \"region\": {{\"startLine\": {}, \"startColumn\": {}, \"endColumn\": {}}}}}}}], \"properties\": {{\"field\": {}}}}}",
+                    if *written { "," } else { "" },
+                    json_string(violation.constraint),
+                    json_string(&message),
+                    json_string(&file_uri(&self.input_file)),
+                    line,
+                    start,
+                    end + 1,
+                    json_string(&violation.field)
+                )?;
+                *written = true;
+            }
+            Output::Github(out) => writeln!(
+                out,
+                "::error file={},line={},col={},endColumn={},title={}::{}",
+                escape_property(&self.input_file),
+                line,
+                start,
+                end,
+                escape_property(&format!("{} ({})", violation.field, violation.constraint)),
+                escape_data(&message)
+            )?,
+        }
+        Ok(())
+    }
+
+    /// Finish writing, closing the SARIF log.
+    pub fn finish(self) -> anyhow::Result<()> {
+        match self.out {
+            Output::Csv(mut writer) => writer.flush()?,
+            Output::Sarif { mut out, written } => {
+                write!(out, "{}]\n  }}]\n}}\n", if written { "\n    " } else { "" })?;
+                out.flush()?;
+            }
+            Output::Github(mut out) => out.flush()?,
+        }
+        Ok(())
+    }
+}
+
+/// `path` as the relative URI a SARIF log locates results in: with forward slashes, and characters that can't be in a
+/// URI percent-encoded.
+fn file_uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '\\' => uri.push('/'),
+            c if c.is_ascii_alphanumeric() || "/-._~".contains(c) => uri.push(c),
+            c => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    uri.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    uri
+}
+
+/// `text` as the message of a workflow command, which ends at the end of the line.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// `text` as the value of a property of a workflow command, which ends at a comma, or at `::` before the message.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
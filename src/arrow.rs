@@ -0,0 +1,350 @@
+//! A small writer for the [Arrow IPC file format](https://arrow.apache.org/docs/format/Columnar.html#ipc-file-format),
+//! covering just what is needed to export extracted fields: a non-null `Int64` column of line numbers followed by
+//! nullable `Utf8` columns, written in record batches so that memory use doesn't grow with the input.
+//!
+//! It takes the place of a `polars` feature returning a DataFrame: polars reads these files as they are, with
+//! `read_ipc`, without the highlighter depending on it or on the `arrow` crate.
+
+use std::io::{self, Write};
+
+/// The magic bytes at the start and end of an Arrow file.
+const MAGIC: &[u8] = b"ARROW1";
+/// Marks the start of each encapsulated message.
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+/// `MetadataVersion.V5`
+const METADATA_VERSION: i16 = 4;
+
+/// `MessageHeader` union tags.
+const HEADER_SCHEMA: u8 = 1;
+const HEADER_RECORD_BATCH: u8 = 3;
+
+/// `Type` union tags.
+const TYPE_INT: u8 = 2;
+const TYPE_UTF8: u8 = 5;
+
+/// Writes a table of a line number column and string columns to an Arrow IPC file.
+pub struct ArrowFileWriter<W: Write> {
+    out: W,
+    position: u64,
+    columns: Vec<String>,
+    /// `(offset, metadata length, body length)` of each record batch written, for the footer.
+    blocks: Vec<(u64, u32, u64)>,
+}
+
+impl<W: Write> ArrowFileWriter<W> {
+    /// Start an Arrow file with a `line` column followed by the string `columns`.
+    pub fn new(out: W, columns: Vec<String>) -> io::Result<Self> {
+        let mut writer = Self {
+            out,
+            position: 0,
+            columns,
+            blocks: Vec::new(),
+        };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[0, 0])?;
+        let schema = Table::new()
+            .field(0, Value::Short(METADATA_VERSION))
+            .field(1, Value::Byte(HEADER_SCHEMA))
+            .field(2, Value::Table(writer.schema()))
+            .field(3, Value::Long(0));
+        writer.write_message(&schema, &[])?;
+        Ok(writer)
+    }
+
+    /// Write a record batch. `values` holds one entry per row, each with a value (or null) for every string column.
+    pub fn write_batch(&mut self, lines: &[i64], values: &[Vec<Option<String>>]) -> io::Result<()> {
+        let rows = lines.len();
+        let mut body = Vec::new();
+        let mut nodes = Vec::new();
+        let mut buffers = Vec::new();
+
+        // line numbers, which are never null
+        nodes.push((rows as i64, 0i64));
+        buffers.push(append_buffer(&mut body, &[]));
+        let data: Vec<u8> = lines.iter().flat_map(|l| l.to_le_bytes()).collect();
+        buffers.push(append_buffer(&mut body, &data));
+
+        for column in 0..self.columns.len() {
+            let mut validity = vec![0u8; rows.div_ceil(8)];
+            let mut offsets = Vec::with_capacity((rows + 1) * 4);
+            let mut data = Vec::new();
+            let mut nulls = 0;
+            offsets.extend_from_slice(&0i32.to_le_bytes());
+            for (row, row_values) in values.iter().enumerate() {
+                match &row_values[column] {
+                    Some(value) => {
+                        validity[row / 8] |= 1 << (row % 8);
+                        data.extend_from_slice(value.as_bytes());
+                    }
+                    None => nulls += 1,
+                }
+                let offset = i32::try_from(data.len()).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Too much data in one record batch for an Arrow string column.",
+                    )
+                })?;
+                offsets.extend_from_slice(&offset.to_le_bytes());
+            }
+
+            nodes.push((rows as i64, nulls));
+            // the validity bitmap may be left out when there are no nulls
+            buffers.push(append_buffer(
+                &mut body,
+                if nulls == 0 { &[] } else { &validity },
+            ));
+            buffers.push(append_buffer(&mut body, &offsets));
+            buffers.push(append_buffer(&mut body, &data));
+        }
+
+        let record_batch = Table::new()
+            .field(0, Value::Long(rows as i64))
+            .field(1, Value::Structs(8, pairs(&nodes), nodes.len()))
+            .field(2, Value::Structs(8, pairs(&buffers), buffers.len()));
+        let message = Table::new()
+            .field(0, Value::Short(METADATA_VERSION))
+            .field(1, Value::Byte(HEADER_RECORD_BATCH))
+            .field(2, Value::Table(record_batch))
+            .field(3, Value::Long(body.len() as i64));
+        let offset = self.position;
+        let metadata_length = self.write_message(&message, &body)?;
+        self.blocks
+            .push((offset, metadata_length, body.len() as u64));
+        Ok(())
+    }
+
+    /// Write the end of stream marker and the file footer, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_all(&CONTINUATION.to_le_bytes())?;
+        self.write_all(&0u32.to_le_bytes())?;
+
+        let mut blocks = Vec::new();
+        for (offset, metadata_length, body_length) in &self.blocks {
+            blocks.extend_from_slice(&offset.to_le_bytes());
+            blocks.extend_from_slice(&metadata_length.to_le_bytes());
+            blocks.extend_from_slice(&[0; 4]);
+            blocks.extend_from_slice(&body_length.to_le_bytes());
+        }
+        let footer = Table::new()
+            .field(0, Value::Short(METADATA_VERSION))
+            .field(1, Value::Table(self.schema()))
+            .field(2, Value::Structs(8, Vec::new(), 0))
+            .field(3, Value::Structs(8, blocks, self.blocks.len()));
+        let footer = footer.finish();
+        self.write_all(&footer)?;
+        self.write_all(&(footer.len() as u32).to_le_bytes())?;
+        self.write_all(MAGIC)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn schema(&self) -> Table {
+        let mut fields = vec![Table::new()
+            .field(0, Value::String("line".to_owned()))
+            .field(1, Value::Bool(false))
+            .field(2, Value::Byte(TYPE_INT))
+            .field(
+                3,
+                Value::Table(
+                    Table::new()
+                        .field(0, Value::Int(64))
+                        .field(1, Value::Bool(true)),
+                ),
+            )
+            .field(5, Value::Tables(Vec::new()))];
+        for name in &self.columns {
+            fields.push(
+                Table::new()
+                    .field(0, Value::String(name.clone()))
+                    .field(1, Value::Bool(true))
+                    .field(2, Value::Byte(TYPE_UTF8))
+                    .field(3, Value::Table(Table::new()))
+                    .field(5, Value::Tables(Vec::new())),
+            );
+        }
+        Table::new().field(1, Value::Tables(fields))
+    }
+
+    /// Write an encapsulated message and its body, returning the length of the message's metadata.
+    fn write_message(&mut self, message: &Table, body: &[u8]) -> io::Result<u32> {
+        let metadata = message.finish();
+        self.write_all(&CONTINUATION.to_le_bytes())?;
+        self.write_all(&(metadata.len() as u32).to_le_bytes())?;
+        self.write_all(&metadata)?;
+        self.write_all(body)?;
+        Ok(8 + metadata.len() as u32)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Append `data` to a message body, padded to 8 bytes, returning its `(offset, length)`.
+fn append_buffer(body: &mut Vec<u8>, data: &[u8]) -> (i64, i64) {
+    let offset = body.len();
+    body.extend_from_slice(data);
+    body.resize(body.len().next_multiple_of(8), 0);
+    (offset as i64, data.len() as i64)
+}
+
+/// Serialise `FieldNode` or `Buffer` structs, which are both a pair of longs.
+fn pairs(pairs: &[(i64, i64)]) -> Vec<u8> {
+    pairs
+        .iter()
+        .flat_map(|(a, b)| a.to_le_bytes().into_iter().chain(b.to_le_bytes()))
+        .collect()
+}
+
+/// A value in a flatbuffer table.
+enum Value {
+    Bool(bool),
+    Byte(u8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    String(String),
+    Table(Table),
+    Tables(Vec<Table>),
+    /// A vector of structs with the given alignment, serialised, and the number of them.
+    Structs(usize, Vec<u8>, usize),
+}
+
+impl Value {
+    /// The size (and so alignment) of the value inline in its table.
+    fn inline_size(&self) -> usize {
+        match self {
+            Value::Bool(_) | Value::Byte(_) => 1,
+            Value::Short(_) => 2,
+            Value::Int(_) => 4,
+            Value::Long(_) => 8,
+            // everything else is referenced by a 32-bit offset
+            _ => 4,
+        }
+    }
+}
+
+/// A flatbuffer table, as a list of fields by their index in the schema.
+struct Table(Vec<(u16, Value)>);
+
+impl Table {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn field(mut self, index: u16, value: Value) -> Self {
+        self.0.push((index, value));
+        self
+    }
+
+    /// Serialise this table as the root of a flatbuffer, padded to 8 bytes.
+    fn finish(&self) -> Vec<u8> {
+        let mut buf = vec![0; 4];
+        let root = write_table(&mut buf, self);
+        patch_offset(&mut buf, 0, root);
+        buf.resize(buf.len().next_multiple_of(8), 0);
+        buf
+    }
+}
+
+/// Pad `buf` until its length is a multiple of `alignment`.
+fn align(buf: &mut Vec<u8>, alignment: usize) {
+    buf.resize(buf.len().next_multiple_of(alignment), 0);
+}
+
+/// Point the offset at `at` to `target`. Offsets are relative to their own position, so targets must come later.
+fn patch_offset(buf: &mut [u8], at: usize, target: usize) {
+    buf[at..at + 4].copy_from_slice(&((target - at) as u32).to_le_bytes());
+}
+
+/// Write `table` (its vtable, then its fields, then anything they refer to), returning its position.
+fn write_table(buf: &mut Vec<u8>, table: &Table) -> usize {
+    let mut layout = Vec::with_capacity(table.0.len());
+    let mut size: usize = 4;
+    let mut alignment = 4;
+    for (_, value) in &table.0 {
+        let value_size = value.inline_size();
+        size = size.next_multiple_of(value_size);
+        layout.push(size);
+        size += value_size;
+        alignment = alignment.max(value_size);
+    }
+
+    align(buf, 2);
+    let vtable = buf.len();
+    let slots = table
+        .0
+        .iter()
+        .map(|(i, _)| *i as usize + 1)
+        .max()
+        .unwrap_or(0);
+    let mut entries = vec![0u16; slots];
+    for ((index, _), offset) in table.0.iter().zip(&layout) {
+        entries[*index as usize] = *offset as u16;
+    }
+    buf.extend_from_slice(&(4 + 2 * slots as u16).to_le_bytes());
+    buf.extend_from_slice(&(size as u16).to_le_bytes());
+    for entry in entries {
+        buf.extend_from_slice(&entry.to_le_bytes());
+    }
+
+    align(buf, alignment);
+    let start = buf.len();
+    buf.extend_from_slice(&((start - vtable) as i32).to_le_bytes());
+    buf.resize(start + size, 0);
+
+    let mut references = Vec::new();
+    for ((_, value), offset) in table.0.iter().zip(&layout) {
+        let at = start + offset;
+        match value {
+            Value::Bool(v) => buf[at] = *v as u8,
+            Value::Byte(v) => buf[at] = *v,
+            Value::Short(v) => buf[at..at + 2].copy_from_slice(&v.to_le_bytes()),
+            Value::Int(v) => buf[at..at + 4].copy_from_slice(&v.to_le_bytes()),
+            Value::Long(v) => buf[at..at + 8].copy_from_slice(&v.to_le_bytes()),
+            _ => references.push((at, value)),
+        }
+    }
+
+    for (at, value) in references {
+        let target = match value {
+            Value::String(s) => {
+                align(buf, 4);
+                let target = buf.len();
+                buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                buf.extend_from_slice(s.as_bytes());
+                buf.push(0);
+                target
+            }
+            Value::Table(t) => write_table(buf, t),
+            Value::Tables(tables) => {
+                align(buf, 4);
+                let target = buf.len();
+                buf.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+                let elements = buf.len();
+                buf.resize(elements + 4 * tables.len(), 0);
+                for (i, t) in tables.iter().enumerate() {
+                    let position = write_table(buf, t);
+                    patch_offset(buf, elements + 4 * i, position);
+                }
+                target
+            }
+            Value::Structs(alignment, data, count) => {
+                // the length precedes the (aligned) first element
+                while !(buf.len() + 4).is_multiple_of(*alignment) {
+                    buf.push(0);
+                }
+                let target = buf.len();
+                buf.extend_from_slice(&(*count as u32).to_le_bytes());
+                buf.extend_from_slice(data);
+                target
+            }
+            _ => unreachable!("scalars are written inline"),
+        };
+        patch_offset(buf, at, target);
+    }
+
+    start
+}
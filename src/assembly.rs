@@ -0,0 +1,68 @@
+//! Assembling the physical lines of the input into the logical records rules apply to, for formats that spread one
+//! record over several lines.
+
+use std::borrow::Cow;
+
+use crate::Condition;
+
+/// The character the physical lines of a record are joined with as it's read. [`prepare_line`](crate::prepare_line) takes
+/// them out again, noting where each line started. It's U+FFFF, a noncharacter, which Unicode sets aside for uses such
+/// as this: a line feed would be mistaken for one inside a record of `--record-length` or a line ending in CR, and
+/// no byte of Latin-1 or an EBCDIC code page decodes to U+FFFF, while a line of UTF-8 with one is refused as it's read.
+pub const LINE_BREAK: char = '\u{ffff}';
+
+/// How the physical lines of the input are put together into the logical records the rules apply to. By default, each
+/// line is a record of its own.
+#[derive(Debug, Clone, Default)]
+pub struct Assembly {
+    /// The regex a line matches when its record continues on the next line, from `continues_if` in the syntax file.
+    pub continues_if: Option<Condition>,
+}
+
+impl Assembly {
+    /// Whether lines are ever joined into one record.
+    pub fn joins_lines(&self) -> bool {
+        self.continues_if.is_some()
+    }
+
+    /// Whether the record the physical line `line` is part of continues on the next line.
+    pub fn continues(&self, line: &str) -> bool {
+        self.continues_if
+            .as_ref()
+            .is_some_and(|condition| condition.is_match(line))
+    }
+}
+
+/// Take the line breaks out of the assembled `record`, returning its text and the column (counted in characters, from
+/// 0) each of its physical lines after the first starts at.
+pub fn split_record(record: String) -> (String, Vec<usize>) {
+    if !record.contains(LINE_BREAK) {
+        return (record, Vec::new());
+    }
+    let mut breaks = Vec::new();
+    let mut text = String::with_capacity(record.len());
+    let mut col = 0;
+    for c in record.chars() {
+        if c == LINE_BREAK {
+            breaks.push(col);
+        } else {
+            text.push(c);
+            col += 1;
+        }
+    }
+    (text, breaks)
+}
+
+/// The text of the assembled `record`, without its line breaks, for matching against.
+pub fn record_text(record: &str) -> Cow<'_, str> {
+    if record.contains(LINE_BREAK) {
+        Cow::Owned(record.replace(LINE_BREAK, ""))
+    } else {
+        Cow::Borrowed(record)
+    }
+}
+
+/// The number of physical lines the assembled `record` was read from.
+pub fn physical_lines(record: &str) -> usize {
+    record.matches(LINE_BREAK).count() + 1
+}
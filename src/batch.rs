@@ -0,0 +1,116 @@
+//! Preparing and rendering lines in batches split between threads, with the results kept in line order.
+
+use std::{
+    panic, thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    prepare_line, record_text, redact::redacted, skip_line, Condition, PositionOptions,
+    PreparedLine, RecordList, Renderer, ShortLinePolicy,
+};
+
+/// Prepares lines and renders them as HTML on several threads at once. Lines are independent of each other, so each
+/// thread takes a run of lines of a batch, and the runs are put back together in order.
+pub struct BatchRenderer<'a> {
+    pub records: &'a RecordList,
+    pub positions: PositionOptions,
+    pub short_lines: ShortLinePolicy,
+    /// Mark the padding of short lines as ghost cells.
+    pub ghost_cells: bool,
+    /// Lines matching any of these are left out of highlighting.
+    pub skip_lines: &'a [Condition],
+    pub renderer: &'a Renderer,
+    /// The number of threads to split each batch between.
+    pub threads: usize,
+}
+
+/// A line prepared and rendered as HTML.
+pub struct RenderedLine {
+    /// The line as it was read, which the HTML shows with its sensitive fields masked.
+    pub prepared: PreparedLine,
+    pub html: String,
+    /// The time spent generating the line's regions.
+    pub regions_time: Duration,
+    /// The time spent writing the line as HTML.
+    pub render_time: Duration,
+}
+
+impl BatchRenderer<'_> {
+    /// Prepare and render `lines`, each given with its index (from 0) in its file and a colour to shade it with. The
+    /// results are in the same order as `lines`, with an error in place of any line that couldn't be prepared.
+    pub fn render(
+        &self,
+        lines: Vec<(usize, String, Option<String>)>,
+    ) -> Vec<anyhow::Result<RenderedLine>> {
+        let threads = self.threads.max(1);
+        if threads == 1 || lines.len() < 2 {
+            return self.render_run(lines);
+        }
+        let run_length = lines.len().div_ceil(threads);
+        let mut lines = lines.into_iter();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    let run: Vec<_> = lines.by_ref().take(run_length).collect();
+                    scope.spawn(move || self.render_run(run))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+
+    /// Prepare and render a run of lines on this thread.
+    fn render_run(
+        &self,
+        lines: Vec<(usize, String, Option<String>)>,
+    ) -> Vec<anyhow::Result<RenderedLine>> {
+        lines
+            .into_iter()
+            .map(|(line_index, line, background)| {
+                self.render_line(line_index, line, background.as_deref())
+            })
+            .collect()
+    }
+
+    fn render_line(
+        &self,
+        line_index: usize,
+        line: String,
+        background: Option<&str>,
+    ) -> anyhow::Result<RenderedLine> {
+        let phase = Instant::now();
+        let skipped = !self.skip_lines.is_empty() && {
+            let text = record_text(&line);
+            self.skip_lines.iter().any(|skip| skip.is_match(&text))
+        };
+        let prepared = if skipped {
+            skip_line(line)
+        } else {
+            prepare_line(
+                self.records,
+                line,
+                line_index,
+                self.positions,
+                self.short_lines,
+                self.ghost_cells,
+            )?
+        };
+        let regions_time = phase.elapsed();
+
+        let phase = Instant::now();
+        let mut html = Vec::new();
+        self.renderer
+            .write_html_line(&mut html, line_index, &redacted(&prepared), background)
+            .expect("writing to memory doesn't fail");
+        Ok(RenderedLine {
+            prepared,
+            html: String::from_utf8(html).expect("the line is valid UTF-8"),
+            regions_time,
+            render_time: phase.elapsed(),
+        })
+    }
+}
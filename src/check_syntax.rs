@@ -0,0 +1,539 @@
+//! The `check-syntax` subcommand, which lints a syntax file without an input file: rules that can't be placed or
+//! whose regexes don't compile, rules written for the other mode, and fields that overlap, leave gaps or share a name
+//! with another in the same layout.
+
+use std::fs;
+
+use anyhow::{bail, Context};
+
+use crate::{
+    compiled,
+    document::{self, DocumentFormat},
+    json_string, layouts, load_syntax, parse_delimiter, parse_syntax_file, Args, CheckSyntaxArgs,
+    CheckSyntaxFormat, Condition, Pattern, ProblemsFound, RecordList, SyntaxFormat, ValuePattern,
+    ValueStyle,
+};
+
+/// How serious a finding is. Errors fail the check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A problem with the syntax file, on the line of it that's at fault if there is one.
+struct Finding {
+    severity: Severity,
+    line: Option<u64>,
+    message: String,
+}
+
+/// A rule with a usable position, as the half-open range of columns (or fields) it covers, numbered as in the syntax
+/// file.
+struct Placed<'a> {
+    line: u64,
+    name: &'a str,
+    from: usize,
+    to: usize,
+    record: Option<&'a str>,
+    condition: Option<&'a str>,
+}
+
+impl Placed<'_> {
+    /// Whether the rule can apply to lines of the layout of `record` and `condition`.
+    fn applies_in(&self, record: Option<&str>, condition: Option<&str>) -> bool {
+        self.record.is_none_or(|r| Some(r) == record)
+            && self.condition.is_none_or(|c| Some(c) == condition)
+    }
+}
+
+/// Run the `check-syntax` subcommand, printing what's wrong with the syntax file and failing if any of it is an error.
+/// Each layout of a layouts file is checked in turn.
+pub fn run(args: &Args, check: &CheckSyntaxArgs) -> anyhow::Result<()> {
+    let mut findings = Vec::new();
+    match layouts::read(&check.syntax_file)? {
+        Some(layouts) => {
+            for layout in &layouts {
+                let syntax_file = layout.syntax_file.to_string_lossy();
+                let mut layout_findings = Vec::new();
+                check_file(args, &syntax_file, &mut layout_findings)?;
+                findings.extend(layout_findings.into_iter().map(|f| Finding {
+                    message: format!("layout '{}' ({}): {}", layout.name, syntax_file, f.message),
+                    ..f
+                }));
+            }
+        }
+        None => check_file(args, &check.syntax_file, &mut findings)?,
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+    match check.format {
+        CheckSyntaxFormat::Human => {
+            for finding in &findings {
+                match finding.line {
+                    Some(line) => println!(
+                        "line {}: {}: {}",
+                        line,
+                        finding.severity.name(),
+                        finding.message
+                    ),
+                    None => println!("{}: {}", finding.severity.name(), finding.message),
+                }
+            }
+            if findings.is_empty() {
+                println!("No problems found.");
+            } else {
+                println!("{} error(s), {} warning(s).", errors, warnings);
+            }
+        }
+        CheckSyntaxFormat::Json => {
+            let findings: Vec<String> = findings
+                .iter()
+                .map(|f| {
+                    format!(
+                        r#"{{"severity":"{}","line":{},"message":{}}}"#,
+                        f.severity.name(),
+                        f.line.map_or("null".to_owned(), |line| line.to_string()),
+                        json_string(&f.message)
+                    )
+                })
+                .collect();
+            println!(
+                r#"{{"errors":{},"warnings":{},"findings":[{}]}}"#,
+                errors,
+                warnings,
+                findings.join(",")
+            );
+        }
+    }
+
+    if errors > 0 {
+        bail!(ProblemsFound(format!(
+            "The syntax file has {} error(s).",
+            errors
+        )));
+    }
+    Ok(())
+}
+
+/// Check one syntax file, adding what's wrong with it to `findings`, in the order of its lines.
+fn check_file(args: &Args, syntax_file: &str, findings: &mut Vec<Finding>) -> anyhow::Result<()> {
+    // a regex that doesn't compile would stop the syntax file loading, so each is reported and the file read without
+    // them, for the layout of its rules to be checked all the same
+    let lenient = match args.syntax_format {
+        SyntaxFormat::Csv => {
+            let bytes = fs::read(syntax_file).context("Failed to read from syntax file.")?;
+            // a compiled syntax file was checked as it was compiled
+            if compiled::is_compiled(&bytes) {
+                None
+            } else {
+                let text = String::from_utf8(bytes).context("Failed to read from syntax file.")?;
+                check_rows(args, &text, findings)?
+                    .map(|text| parse_syntax_file(&text, parse_delimiter(args)?))
+            }
+        }
+        SyntaxFormat::Yaml | SyntaxFormat::Toml => {
+            let text =
+                fs::read_to_string(syntax_file).context("Failed to read from syntax file.")?;
+            let format = match args.syntax_format {
+                SyntaxFormat::Yaml => DocumentFormat::Yaml,
+                _ => DocumentFormat::Toml,
+            };
+            // a document that can't be read at all is reported as loading it fails
+            match document::lint(&text, format, parse_delimiter(args)?) {
+                Ok((invalid, records)) if !invalid.is_empty() => {
+                    findings.extend(invalid.into_iter().map(|(line, message)| Finding {
+                        severity: Severity::Error,
+                        line: Some(line),
+                        message,
+                    }));
+                    Some(records.map(|(records, _)| records))
+                }
+                _ => None,
+            }
+        }
+        SyntaxFormat::Copybook => None,
+    };
+
+    let records = match lenient {
+        Some(records) => records,
+        None => load_syntax(args, syntax_file, None).map(|(_, records, _, _)| records),
+    };
+    match records {
+        Ok(records) => check_layouts(&records, args.index_base as usize, findings),
+        Err(e) => findings.push(Finding {
+            severity: Severity::Error,
+            line: None,
+            message: format!("{:#}", e),
+        }),
+    }
+    findings.sort_by_key(|f| f.line);
+    Ok(())
+}
+
+/// Check the rows of a CSV syntax file as written, before it's parsed: every regex, rather than just the first bad
+/// one, and rules placed as in the other mode, which parsing would take as having no position. If a regex doesn't
+/// compile, returns the file with each that doesn't left out, on the same lines, for the rest of it to be read.
+fn check_rows(
+    args: &Args,
+    text: &str,
+    findings: &mut Vec<Finding>,
+) -> anyhow::Result<Option<String>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = Vec::new();
+    let mut invalid_regexes = false;
+    let position = |name: &str| headers.iter().position(|h| h == name);
+    let (name_column, start_column, length_column, field_column) = (
+        position("name"),
+        position("start"),
+        position("length"),
+        position("field"),
+    );
+    let (condition_column, pattern_column, continues_column) = (
+        position("condition"),
+        position("pattern"),
+        position("continues_if"),
+    );
+    let (value_pattern_column, value_style_column) =
+        (position("value_pattern"), position("value_style"));
+
+    for row in reader.records() {
+        let row = row.context("Failed to parse syntax record.")?;
+        let line = row.position().map_or(0, |p| p.line());
+        let column = |index: Option<usize>| {
+            index
+                .and_then(|i| row.get(i))
+                .filter(|value| !value.is_empty())
+        };
+        if let (Some(_), None, Some(name)) = (
+            column(value_style_column),
+            column(value_pattern_column),
+            column(name_column),
+        ) {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                line: Some(line),
+                message: format!(
+                    "rule '{}' gives a `value_style` but no `value_pattern`, so the style is never drawn.",
+                    name
+                ),
+            });
+        }
+        let mut error = |message: String| {
+            findings.push(Finding {
+                severity: Severity::Error,
+                line: Some(line),
+                message,
+            })
+        };
+
+        let mut invalid = Vec::new();
+        if let Some(Err(e)) = column(condition_column).map(Condition::parse) {
+            error(e.to_string());
+            invalid.push(condition_column);
+        }
+        if let Some(Err(e)) = column(pattern_column).map(Pattern::parse) {
+            error(e.to_string());
+            invalid.push(pattern_column);
+        }
+        if let Some(Err(e)) = column(continues_column).map(Condition::parse) {
+            error(e.to_string());
+            invalid.push(continues_column);
+        }
+        if let Some(Err(e)) = column(value_pattern_column).map(ValuePattern::parse) {
+            error(e.to_string());
+            invalid.push(value_pattern_column);
+        }
+        if let Some(Err(e)) = column(value_style_column).map(ValueStyle::parse) {
+            error(e.to_string());
+            invalid.push(value_style_column);
+        }
+        invalid_regexes |= !invalid.is_empty();
+        rows.push((
+            line,
+            row.iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    if invalid.contains(&Some(i)) {
+                        ""
+                    } else {
+                        value
+                    }
+                })
+                .collect::<csv::StringRecord>(),
+        ));
+
+        // rows without a name define record types, which have no position
+        let Some(name) = column(name_column) else {
+            continue;
+        };
+        let placed_by_field = column(field_column).is_some();
+        let placed_by_start = column(start_column).is_some() || column(length_column).is_some();
+        if args.delimiter.is_none() && placed_by_field && !placed_by_start {
+            error(format!(
+                "rule '{}' gives a `field`, as in delimiter mode, but fixed width rules are placed by `start` and `length`.",
+                name
+            ));
+        } else if args.delimiter.is_some() && placed_by_start && !placed_by_field {
+            error(format!(
+                "rule '{}' gives a `start` and `length`, as in fixed width mode, but delimiter mode rules are placed by `field`.",
+                name
+            ));
+        }
+    }
+
+    if !invalid_regexes {
+        return Ok(None);
+    }
+    let write = |row: &csv::StringRecord| -> anyhow::Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(row)?;
+        let bytes = writer
+            .into_inner()
+            .context("Failed to read from syntax file.")?;
+        Ok(String::from_utf8(bytes)?)
+    };
+    let mut text = write(&headers)?;
+    let mut written = 1;
+    for (line, row) in rows {
+        // blank lines, which the reader skips, are written back so every row stays on its line
+        text.push_str(&"\n".repeat(line.saturating_sub(written + 1) as usize));
+        text.push_str(&write(&row)?);
+        written = line;
+    }
+    Ok(Some(text))
+}
+
+/// Check the position of every rule, then the layout of the fields that can apply to the same lines together.
+fn check_layouts(records: &RecordList, index_base: usize, findings: &mut Vec<Finding>) {
+    let (unit, placed) = place_rules(records, index_base, findings);
+
+    // each record type and condition has its own layout, with the rules that apply to every line added to it
+    let mut keys: Vec<(Option<&str>, Option<&str>)> = Vec::new();
+    for rule in &placed {
+        if !keys.contains(&(rule.record, rule.condition)) {
+            keys.push((rule.record, rule.condition));
+        }
+    }
+    // a layout that's part of a more particular one is checked as part of that one
+    let refined = |(record, condition): (Option<&str>, Option<&str>)| {
+        keys.iter().any(|&(r, c)| {
+            (r, c) != (record, condition)
+                && record.is_none_or(|record| Some(record) == r)
+                && condition.is_none_or(|condition| Some(condition) == c)
+        })
+    };
+    let layouts: Vec<(Option<&str>, Option<&str>)> =
+        keys.iter().copied().filter(|&key| !refined(key)).collect();
+
+    let mut reported: Vec<(u64, u64)> = Vec::new();
+    for (record, condition) in layouts {
+        let mut rules: Vec<&Placed> = placed
+            .iter()
+            .filter(|rule| rule.applies_in(record, condition))
+            .collect();
+        let layout = match (record, condition) {
+            (Some(record), Some(condition)) => format!(
+                "record type '{}', on lines matching `{}`",
+                record, condition
+            ),
+            (Some(record), None) => format!("record type '{}'", record),
+            (None, Some(condition)) => format!("lines matching `{}`", condition),
+            (None, None) => "every line".to_owned(),
+        };
+
+        for (i, later) in rules.iter().enumerate() {
+            for earlier in &rules[..i] {
+                if reported.contains(&(earlier.line, later.line)) {
+                    continue;
+                }
+                let same_name = earlier.name == later.name;
+                let overlapping = earlier.from < later.to && later.from < earlier.to;
+                if same_name || overlapping {
+                    reported.push((earlier.line, later.line));
+                }
+                // rules with the same name can overlap too, which is a mistake in the layout of its own
+                if same_name {
+                    findings.push(Finding {
+                        severity: Severity::Warning,
+                        line: Some(later.line),
+                        message: format!(
+                            "rule '{}' has the same name as the rule on line {}, so the two can't be told apart.",
+                            later.name, earlier.line
+                        ),
+                    });
+                }
+                if overlapping {
+                    findings.push(Finding {
+                        severity: Severity::Warning,
+                        line: Some(later.line),
+                        message: format!(
+                            "rule '{}' ({}) overlaps rule '{}' ({}) on line {}.",
+                            later.name,
+                            span(unit, later.from, later.to),
+                            earlier.name,
+                            span(unit, earlier.from, earlier.to),
+                            earlier.line
+                        ),
+                    });
+                }
+            }
+        }
+
+        rules.sort_by_key(|rule| rule.from);
+        let mut covered_to = index_base;
+        for rule in rules {
+            if rule.from > covered_to {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    line: Some(rule.line),
+                    message: format!(
+                        "{} before rule '{}' {} covered by any rule for {}.",
+                        span(unit, covered_to, rule.from),
+                        rule.name,
+                        if rule.from == covered_to + 1 {
+                            "isn't"
+                        } else {
+                            "aren't"
+                        },
+                        layout
+                    ),
+                });
+            }
+            covered_to = covered_to.max(rule.to);
+        }
+    }
+}
+
+/// The rules of `records` that can be placed, with the unit they're placed in, reporting those that can't.
+fn place_rules<'a>(
+    records: &'a RecordList,
+    index_base: usize,
+    findings: &mut Vec<Finding>,
+) -> (&'static str, Vec<Placed<'a>>) {
+    let mut placed = Vec::new();
+    let mut report = |severity: Severity, line: u64, message: String| {
+        findings.push(Finding {
+            severity,
+            line: Some(line),
+            message,
+        })
+    };
+    match records {
+        RecordList::FixedWidth(rules) => {
+            for rule in rules {
+                // where a field placed by a capture group falls depends on the line, so it isn't laid out either
+                if rule.start_capture.is_some() || rule.length_capture.is_some() {
+                    continue;
+                }
+                let (Some(start), Some(length)) = (rule.start, rule.length) else {
+                    report(
+                        Severity::Error,
+                        rule.line,
+                        format!(
+                            "rule '{}' is missing its `start` or `length`, so is never applied.",
+                            rule.name
+                        ),
+                    );
+                    continue;
+                };
+                if start < index_base {
+                    report(
+                        Severity::Error,
+                        rule.line,
+                        format!(
+                            "rule '{}' starts at column {}, but columns are numbered from {}, so it is never applied.",
+                            rule.name, start, index_base
+                        ),
+                    );
+                    continue;
+                }
+                if length == 0 {
+                    report(
+                        Severity::Warning,
+                        rule.line,
+                        format!(
+                            "rule '{}' has a length of 0, so is always empty.",
+                            rule.name
+                        ),
+                    );
+                }
+                // where a field placed from the end falls depends on the length of the line, so it isn't laid out
+                // against the others
+                if rule.from_end {
+                    continue;
+                }
+                placed.push(Placed {
+                    line: rule.line,
+                    name: &rule.name,
+                    from: start,
+                    to: start + length,
+                    record: rule.record.as_ref().map(|r| r.name.as_str()),
+                    condition: rule.condition.as_ref().map(Condition::as_str),
+                });
+            }
+            ("column", placed)
+        }
+        RecordList::Delimiter(_, rules) => {
+            for rule in rules {
+                if rule.field_capture.is_some() {
+                    continue;
+                }
+                let Some(field) = rule.field else {
+                    report(
+                        Severity::Error,
+                        rule.line,
+                        format!(
+                            "rule '{}' is missing its `field`, so is never applied.",
+                            rule.name
+                        ),
+                    );
+                    continue;
+                };
+                if field < index_base {
+                    report(
+                        Severity::Error,
+                        rule.line,
+                        format!(
+                            "rule '{}' is field {}, but fields are numbered from {}, so it is never applied.",
+                            rule.name, field, index_base
+                        ),
+                    );
+                    continue;
+                }
+                placed.push(Placed {
+                    line: rule.line,
+                    name: &rule.name,
+                    from: field,
+                    to: field + 1,
+                    record: rule.record.as_ref().map(|r| r.name.as_str()),
+                    condition: rule.condition.as_ref().map(Condition::as_str),
+                });
+            }
+            ("field", placed)
+        }
+    }
+}
+
+/// The half-open range `from..to` of `unit`s written as inclusive positions, e.g. `columns 3-5`, or `column 3` for one.
+fn span(unit: &str, from: usize, to: usize) -> String {
+    if to <= from + 1 {
+        format!("{} {}", unit, from)
+    } else {
+        format!("{}s {}-{}", unit, from, to - 1)
+    }
+}
@@ -0,0 +1,309 @@
+//! Column modes: whether the columns of a fixed width syntax file count the bytes, characters or graphemes of a line.
+//!
+//! Regions are always kept as character indices into the line, so a [`Columns`] map is used to turn a column in the
+//! chosen mode into the character it falls on.
+
+/// What the columns of a fixed width syntax file count.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnMode {
+    /// Bytes of the line's UTF-8 encoding
+    Bytes,
+    /// Characters (Unicode scalar values)
+    #[default]
+    Chars,
+    /// Graphemes: what a reader sees as one character, such as a letter with its accents
+    Graphemes,
+}
+
+impl ColumnMode {
+    /// The number of columns `line` has in this mode.
+    pub fn count(self, line: &str) -> usize {
+        match self {
+            ColumnMode::Bytes => line.len(),
+            ColumnMode::Chars => line.chars().count(),
+            ColumnMode::Graphemes => grapheme_starts(line).len(),
+        }
+    }
+}
+
+/// The columns of a line in a column mode, mapped onto its characters. Columns beyond the end of the line are taken
+/// to be one character each, as padding would be.
+#[derive(Debug, Clone)]
+pub struct Columns {
+    mode: ColumnMode,
+    /// The byte offset of each character in bytes mode, or the character each grapheme starts at in graphemes mode.
+    starts: Vec<usize>,
+    units: usize,
+    chars: usize,
+}
+
+impl Columns {
+    pub fn new(line: &str, mode: ColumnMode) -> Self {
+        let starts = match mode {
+            ColumnMode::Bytes => line.char_indices().map(|(offset, _)| offset).collect(),
+            ColumnMode::Chars => Vec::new(),
+            ColumnMode::Graphemes => grapheme_starts(line),
+        };
+        let chars = match mode {
+            ColumnMode::Bytes => starts.len(),
+            ColumnMode::Chars | ColumnMode::Graphemes => line.chars().count(),
+        };
+        let units = match mode {
+            ColumnMode::Bytes => line.len(),
+            ColumnMode::Chars => chars,
+            ColumnMode::Graphemes => starts.len(),
+        };
+        Self {
+            mode,
+            starts,
+            units,
+            chars,
+        }
+    }
+
+    /// The number of columns in the line.
+    pub fn units(&self) -> usize {
+        self.units
+    }
+
+    /// The index of the character that `column` (from 0) starts at. In bytes mode, a column in the middle of a
+    /// character starts at the next character, so each character belongs to the column its first byte is in.
+    pub fn char_index(&self, column: usize) -> usize {
+        if column >= self.units {
+            return self.chars + column - self.units;
+        }
+        match self.mode {
+            ColumnMode::Bytes => self.starts.partition_point(|offset| *offset < column),
+            ColumnMode::Chars => column,
+            ColumnMode::Graphemes => self.starts[column],
+        }
+    }
+
+    /// The column (from 0) that the character at `char_index` is in.
+    pub fn column_of(&self, char_index: usize) -> usize {
+        if char_index >= self.chars {
+            return self.units + char_index - self.chars;
+        }
+        match self.mode {
+            ColumnMode::Bytes => self.starts[char_index],
+            ColumnMode::Chars => char_index,
+            ColumnMode::Graphemes => self.starts.partition_point(|start| *start <= char_index) - 1,
+        }
+    }
+}
+
+/// The index of the character each grapheme of `line` starts at.
+///
+/// This follows the main rules of Unicode's extended grapheme clusters without their full tables: combining marks,
+/// variation selectors, emoji modifiers and Hangul vowels and final consonants join the character before them, a
+/// zero width joiner joins the characters either side of it, and regional indicators pair up into flags.
+pub fn grapheme_starts(line: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut previous = None;
+    // the number of regional indicators in a row before this character
+    let mut regional = 0;
+    for (idx, c) in line.chars().enumerate() {
+        let joins = match previous {
+            None => false,
+            Some(p) => {
+                is_extend(c) || p == ZERO_WIDTH_JOINER || (is_regional(c) && regional % 2 == 1)
+            }
+        };
+        if !joins {
+            starts.push(idx);
+        }
+        regional = if is_regional(c) { regional + 1 } else { 0 };
+        previous = Some(c);
+    }
+    starts
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// Characters that continue the grapheme before them.
+const EXTEND: &[(char, char)] = &[
+    ('\u{300}', '\u{36f}'),
+    ('\u{483}', '\u{489}'),
+    ('\u{591}', '\u{5bd}'),
+    ('\u{5bf}', '\u{5bf}'),
+    ('\u{5c1}', '\u{5c2}'),
+    ('\u{5c4}', '\u{5c5}'),
+    ('\u{5c7}', '\u{5c7}'),
+    ('\u{610}', '\u{61a}'),
+    ('\u{64b}', '\u{65f}'),
+    ('\u{670}', '\u{670}'),
+    ('\u{6d6}', '\u{6dc}'),
+    ('\u{6df}', '\u{6e4}'),
+    ('\u{6e7}', '\u{6e8}'),
+    ('\u{6ea}', '\u{6ed}'),
+    ('\u{900}', '\u{903}'),
+    ('\u{93a}', '\u{93c}'),
+    ('\u{93e}', '\u{94f}'),
+    ('\u{951}', '\u{957}'),
+    ('\u{962}', '\u{963}'),
+    ('\u{e31}', '\u{e31}'),
+    ('\u{e34}', '\u{e3a}'),
+    ('\u{e47}', '\u{e4e}'),
+    ('\u{1160}', '\u{11ff}'),
+    ('\u{1ab0}', '\u{1aff}'),
+    ('\u{1dc0}', '\u{1dff}'),
+    ('\u{200c}', '\u{200d}'),
+    ('\u{20d0}', '\u{20ff}'),
+    ('\u{302a}', '\u{302f}'),
+    ('\u{3099}', '\u{309a}'),
+    ('\u{fe00}', '\u{fe0f}'),
+    ('\u{fe20}', '\u{fe2f}'),
+    ('\u{1f3fb}', '\u{1f3ff}'),
+    ('\u{e0020}', '\u{e007f}'),
+    ('\u{e0100}', '\u{e01ef}'),
+];
+
+/// Characters that take up two columns of a terminal: East Asian wide and full-width characters, such as CJK ideographs,
+/// kana, Hangul syllables and full-width forms, and emoji.
+const WIDE: &[(char, char)] = &[
+    ('\u{1100}', '\u{115f}'),
+    ('\u{231a}', '\u{231b}'),
+    ('\u{2329}', '\u{232a}'),
+    ('\u{23e9}', '\u{23ec}'),
+    ('\u{23f0}', '\u{23f0}'),
+    ('\u{23f3}', '\u{23f3}'),
+    ('\u{25fd}', '\u{25fe}'),
+    ('\u{2614}', '\u{2615}'),
+    ('\u{2648}', '\u{2653}'),
+    ('\u{267f}', '\u{267f}'),
+    ('\u{2693}', '\u{2693}'),
+    ('\u{26a1}', '\u{26a1}'),
+    ('\u{26aa}', '\u{26ab}'),
+    ('\u{26bd}', '\u{26be}'),
+    ('\u{26c4}', '\u{26c5}'),
+    ('\u{26ce}', '\u{26ce}'),
+    ('\u{26d4}', '\u{26d4}'),
+    ('\u{26ea}', '\u{26ea}'),
+    ('\u{26f2}', '\u{26f3}'),
+    ('\u{26f5}', '\u{26f5}'),
+    ('\u{26fa}', '\u{26fa}'),
+    ('\u{26fd}', '\u{26fd}'),
+    ('\u{2705}', '\u{2705}'),
+    ('\u{270a}', '\u{270b}'),
+    ('\u{2728}', '\u{2728}'),
+    ('\u{274c}', '\u{274c}'),
+    ('\u{274e}', '\u{274e}'),
+    ('\u{2753}', '\u{2755}'),
+    ('\u{2757}', '\u{2757}'),
+    ('\u{2795}', '\u{2797}'),
+    ('\u{27b0}', '\u{27b0}'),
+    ('\u{27bf}', '\u{27bf}'),
+    ('\u{2b1b}', '\u{2b1c}'),
+    ('\u{2b50}', '\u{2b50}'),
+    ('\u{2b55}', '\u{2b55}'),
+    ('\u{2e80}', '\u{303e}'),
+    ('\u{3041}', '\u{33ff}'),
+    ('\u{3400}', '\u{4dbf}'),
+    ('\u{4e00}', '\u{9fff}'),
+    ('\u{a000}', '\u{a4cf}'),
+    ('\u{a960}', '\u{a97f}'),
+    ('\u{ac00}', '\u{d7a3}'),
+    ('\u{f900}', '\u{faff}'),
+    ('\u{fe10}', '\u{fe19}'),
+    ('\u{fe30}', '\u{fe6f}'),
+    ('\u{ff00}', '\u{ff60}'),
+    ('\u{ffe0}', '\u{ffe6}'),
+    ('\u{16fe0}', '\u{16fe4}'),
+    ('\u{17000}', '\u{18cff}'),
+    ('\u{1b000}', '\u{1b2ff}'),
+    ('\u{1f004}', '\u{1f004}'),
+    ('\u{1f0cf}', '\u{1f0cf}'),
+    ('\u{1f18e}', '\u{1f18e}'),
+    ('\u{1f191}', '\u{1f19a}'),
+    ('\u{1f200}', '\u{1f2ff}'),
+    ('\u{1f300}', '\u{1f320}'),
+    ('\u{1f32d}', '\u{1f335}'),
+    ('\u{1f337}', '\u{1f37c}'),
+    ('\u{1f37e}', '\u{1f393}'),
+    ('\u{1f3a0}', '\u{1f3ca}'),
+    ('\u{1f3cf}', '\u{1f3d3}'),
+    ('\u{1f3e0}', '\u{1f3f0}'),
+    ('\u{1f3f4}', '\u{1f3f4}'),
+    ('\u{1f3f8}', '\u{1f3fa}'),
+    ('\u{1f400}', '\u{1f43e}'),
+    ('\u{1f440}', '\u{1f440}'),
+    ('\u{1f442}', '\u{1f4fc}'),
+    ('\u{1f4ff}', '\u{1f53d}'),
+    ('\u{1f54b}', '\u{1f54e}'),
+    ('\u{1f550}', '\u{1f567}'),
+    ('\u{1f57a}', '\u{1f57a}'),
+    ('\u{1f595}', '\u{1f596}'),
+    ('\u{1f5a4}', '\u{1f5a4}'),
+    ('\u{1f5fb}', '\u{1f64f}'),
+    ('\u{1f680}', '\u{1f6c5}'),
+    ('\u{1f6cc}', '\u{1f6cc}'),
+    ('\u{1f6d0}', '\u{1f6d2}'),
+    ('\u{1f6d5}', '\u{1f6d7}'),
+    ('\u{1f6dc}', '\u{1f6df}'),
+    ('\u{1f6eb}', '\u{1f6ec}'),
+    ('\u{1f6f4}', '\u{1f6fc}'),
+    ('\u{1f7e0}', '\u{1f7eb}'),
+    ('\u{1f7f0}', '\u{1f7f0}'),
+    ('\u{1f90c}', '\u{1f93a}'),
+    ('\u{1f93c}', '\u{1f945}'),
+    ('\u{1f947}', '\u{1f9ff}'),
+    ('\u{1fa70}', '\u{1faff}'),
+    ('\u{20000}', '\u{2fffd}'),
+    ('\u{30000}', '\u{3fffd}'),
+];
+
+/// The number of columns `c` takes up on screen: none for combining marks and the other characters that join the one
+/// before them, two for wide characters such as CJK ideographs, and one for the rest.
+pub fn char_width(c: char) -> usize {
+    if is_extend(c) {
+        0
+    } else if WIDE.iter().any(|(from, to)| (*from..=*to).contains(&c)) {
+        2
+    } else {
+        1
+    }
+}
+
+/// How many columns of the screen each column of the lines of a file is given, for `--display-width`: as many as the
+/// widest character in that column of any line, so that each column starts at the same place on every line, however
+/// wide the characters before it. Columns are counted in characters, as regions are.
+#[derive(Debug, Clone, Default)]
+pub struct DisplayWidths {
+    widths: Vec<usize>,
+}
+
+impl DisplayWidths {
+    /// Widen the columns to fit the characters of `line`.
+    pub fn add_line(&mut self, line: &str) {
+        for (col, c) in line.chars().enumerate() {
+            let width = char_width(c);
+            match self.widths.get_mut(col) {
+                Some(widest) => *widest = (*widest).max(width),
+                None => self.widths.push(width),
+            }
+        }
+    }
+
+    /// The number of screen columns the column `col` (from 0) is given.
+    pub fn width(&self, col: usize) -> usize {
+        self.widths.get(col).copied().unwrap_or(1)
+    }
+
+    /// The spaces to show after the character `c` in the column `col` to fill it.
+    pub fn padding(&self, col: usize, c: char) -> usize {
+        self.width(col).saturating_sub(char_width(c))
+    }
+
+    /// The screen column (from 0) the column `col` (from 0) starts at.
+    pub fn start_of(&self, col: usize) -> usize {
+        (0..col).map(|c| self.width(c)).sum()
+    }
+}
+
+fn is_extend(c: char) -> bool {
+    EXTEND.iter().any(|(from, to)| (*from..=*to).contains(&c))
+}
+
+fn is_regional(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
@@ -0,0 +1,388 @@
+//! Compiled syntax files, written by `--compile-syntax`: the rules of a syntax file, already parsed and checked, in a
+//! compact binary form that loads without any CSV parsing. The original syntax file is kept alongside so reports can
+//! still link to it.
+
+use anyhow::{bail, Context};
+
+use crate::{
+    constraints::parse_pad, place_field_conditions, Anchor, Assembly, Case, Charset, Condition,
+    ControlCheck, Delimiter, DelimiterHighlightRecord, Encoding, FieldType,
+    FixedWidthHighlightRecord, Justify, Pattern, PositionOptions, RecordList, RecordType,
+    ValuePattern, ValueStyle,
+};
+
+/// The bytes every compiled syntax file starts with.
+const MAGIC: &[u8] = b"FFHC";
+/// Bumped whenever the layout below changes, so stale compiled files are rejected rather than misread.
+const VERSION: u8 = 16;
+
+const MODE_FIXED_WIDTH: u8 = 0;
+const MODE_DELIMITER: u8 = 1;
+
+/// Whether `bytes` are a compiled syntax file rather than a CSV one.
+pub fn is_compiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Compile `records` and `assembly`, parsed from the syntax file `source`.
+pub fn compile(records: &RecordList, assembly: &Assembly, source: &str) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::from(MAGIC);
+    out.push(VERSION);
+    write_str(&mut out, source);
+    write_option_str(
+        &mut out,
+        assembly.continues_if.as_ref().map(Condition::as_str),
+    );
+
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            out.push(MODE_FIXED_WIDTH);
+            write_u64(&mut out, fw_records.len() as u64);
+            for record in fw_records {
+                write_u64(&mut out, record.line);
+                write_option_u64(&mut out, record.start.map(|s| s as u64));
+                write_option_u64(&mut out, record.length.map(|l| l as u64));
+                write_option_str(&mut out, record.start_capture.as_deref());
+                write_option_str(&mut out, record.length_capture.as_deref());
+                write_option_str(
+                    &mut out,
+                    record
+                        .start_anchor
+                        .as_ref()
+                        .map(Anchor::to_string)
+                        .as_deref(),
+                );
+                out.push(record.from_end as u8);
+                write_str(&mut out, &record.name);
+                write_option_str(&mut out, record.condition.as_ref().map(Condition::as_str));
+                write_option_str(&mut out, record.charset.as_ref().map(Charset::spec));
+                write_option_str(&mut out, record.case.map(Case::name));
+                write_option_str(&mut out, record.pattern.as_ref().map(Pattern::spec));
+                write_option_str(&mut out, record.justify.map(Justify::name));
+                write_option_str(&mut out, record.pad.map(String::from).as_deref());
+                write_option_str(&mut out, record.encoding.map(Encoding::name));
+                write_option_str(
+                    &mut out,
+                    record.field_type.as_ref().map(FieldType::spec).as_deref(),
+                );
+                write_option_str(&mut out, record.color.as_deref());
+                write_option_str(
+                    &mut out,
+                    record.value_pattern.as_ref().map(ValuePattern::spec),
+                );
+                write_option_str(
+                    &mut out,
+                    record.value_style.as_ref().map(ValueStyle::spec).as_deref(),
+                );
+                out.push(record.sensitive as u8);
+                write_option_str(
+                    &mut out,
+                    record.check.as_ref().map(ControlCheck::spec).as_deref(),
+                );
+                write_record_type(&mut out, record.record.as_ref());
+            }
+        }
+        RecordList::Delimiter(delimiter, d_records) => {
+            out.push(MODE_DELIMITER);
+            out.push(delimiter.is_regex() as u8);
+            write_str(&mut out, delimiter.as_str());
+            write_u64(&mut out, d_records.len() as u64);
+            for record in d_records {
+                write_u64(&mut out, record.line);
+                write_option_u64(&mut out, record.field.map(|f| f as u64));
+                write_option_str(&mut out, record.field_capture.as_deref());
+                write_str(&mut out, &record.name);
+                write_option_str(&mut out, record.condition.as_ref().map(Condition::as_str));
+                write_option_str(&mut out, record.charset.as_ref().map(Charset::spec));
+                write_option_str(&mut out, record.case.map(Case::name));
+                write_option_str(&mut out, record.pattern.as_ref().map(Pattern::spec));
+                write_option_str(&mut out, record.justify.map(Justify::name));
+                write_option_str(&mut out, record.pad.map(String::from).as_deref());
+                write_option_str(&mut out, record.encoding.map(Encoding::name));
+                write_option_str(
+                    &mut out,
+                    record.field_type.as_ref().map(FieldType::spec).as_deref(),
+                );
+                write_option_str(&mut out, record.color.as_deref());
+                write_option_str(
+                    &mut out,
+                    record.value_pattern.as_ref().map(ValuePattern::spec),
+                );
+                write_option_str(
+                    &mut out,
+                    record.value_style.as_ref().map(ValueStyle::spec).as_deref(),
+                );
+                out.push(record.sensitive as u8);
+                write_option_str(
+                    &mut out,
+                    record.check.as_ref().map(ControlCheck::spec).as_deref(),
+                );
+                write_record_type(&mut out, record.record.as_ref());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Load a compiled syntax file, returning the original syntax file, its rules and how lines are assembled into records.
+pub fn load(bytes: &[u8]) -> anyhow::Result<(String, RecordList, Assembly)> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        bail!("Not a compiled syntax file.");
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        bail!(
+            "Compiled syntax file is version {}, but this version of fixedfile-highlighter reads version {}. Compile it again with --compile-syntax.",
+            version,
+            VERSION
+        );
+    }
+    let source = reader.string()?;
+    let assembly = Assembly {
+        continues_if: reader.condition()?,
+    };
+
+    let mut records = match reader.u8()? {
+        MODE_FIXED_WIDTH => {
+            let count = reader.u64()?;
+            let mut records = Vec::new();
+            for _ in 0..count {
+                records.push(FixedWidthHighlightRecord {
+                    line: reader.u64()?,
+                    start: reader.option_u64()?.map(|s| s as usize),
+                    length: reader.option_u64()?.map(|l| l as usize),
+                    start_capture: reader.option_string()?,
+                    length_capture: reader.option_string()?,
+                    start_anchor: reader.anchor()?,
+                    from_end: reader.u8()? != 0,
+                    name: reader.string()?.into(),
+                    condition: reader.condition()?,
+                    charset: reader.charset()?,
+                    case: reader.case()?,
+                    pattern: reader.pattern()?,
+                    justify: reader.justify()?,
+                    pad: reader.pad()?,
+                    encoding: reader.encoding()?,
+                    field_type: reader.field_type()?,
+                    color: reader.option_string()?,
+                    value_pattern: reader.value_pattern()?,
+                    value_style: reader.value_style()?,
+                    sensitive: reader.u8()? != 0,
+                    check: reader.check()?,
+                    record: reader.record_type()?,
+                    type_shift: 0,
+                });
+            }
+            RecordList::FixedWidth(records)
+        }
+        MODE_DELIMITER => {
+            let regex = reader.u8()? != 0;
+            let delimiter = Delimiter::parse(&reader.string()?, regex)
+                .context("Compiled syntax file is corrupt.")?;
+            let count = reader.u64()?;
+            let mut records = Vec::new();
+            for _ in 0..count {
+                records.push(DelimiterHighlightRecord {
+                    line: reader.u64()?,
+                    field: reader.option_u64()?.map(|f| f as usize),
+                    field_capture: reader.option_string()?,
+                    name: reader.string()?.into(),
+                    condition: reader.condition()?,
+                    charset: reader.charset()?,
+                    case: reader.case()?,
+                    pattern: reader.pattern()?,
+                    justify: reader.justify()?,
+                    pad: reader.pad()?,
+                    encoding: reader.encoding()?,
+                    field_type: reader.field_type()?,
+                    color: reader.option_string()?,
+                    value_pattern: reader.value_pattern()?,
+                    value_style: reader.value_style()?,
+                    sensitive: reader.u8()? != 0,
+                    check: reader.check()?,
+                    record: reader.record_type()?,
+                    type_shift: 0,
+                });
+            }
+            RecordList::Delimiter(delimiter, records)
+        }
+        _ => bail!("Compiled syntax file is corrupt."),
+    };
+    place_field_conditions(&mut records, PositionOptions::default())?;
+    Ok((source, records, assembly))
+}
+
+fn write_record_type(out: &mut Vec<u8>, record_type: Option<&RecordType>) {
+    write_option_str(out, record_type.map(|t| t.name.as_str()));
+    if let Some(record_type) = record_type {
+        write_option_str(out, record_type.condition.as_ref().map(Condition::as_str));
+        write_u64(out, record_type.line);
+    }
+}
+
+fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_option_u64(out: &mut Vec<u8>, value: Option<u64>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            write_u64(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_u64(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_option_str(out: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            write_str(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> anyhow::Result<&[u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .context("Compiled syntax file is truncated.")?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn option_u64(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(match self.u8()? {
+            0 => None,
+            _ => Some(self.u64()?),
+        })
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let length = self.u64()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).context("Compiled syntax file is corrupt.")
+    }
+
+    fn option_string(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(match self.u8()? {
+            0 => None,
+            _ => Some(self.string()?),
+        })
+    }
+
+    fn charset(&mut self) -> anyhow::Result<Option<Charset>> {
+        self.option_string()?
+            .map(|spec| Charset::parse(&spec))
+            .transpose()
+    }
+
+    fn pattern(&mut self) -> anyhow::Result<Option<Pattern>> {
+        self.option_string()?
+            .map(|spec| Pattern::parse(&spec))
+            .transpose()
+    }
+
+    fn anchor(&mut self) -> anyhow::Result<Option<Anchor>> {
+        self.option_string()?
+            .and_then(|spec| Anchor::parse(&spec))
+            .transpose()
+    }
+
+    fn value_pattern(&mut self) -> anyhow::Result<Option<ValuePattern>> {
+        self.option_string()?
+            .map(|spec| ValuePattern::parse(&spec))
+            .transpose()
+    }
+
+    fn value_style(&mut self) -> anyhow::Result<Option<ValueStyle>> {
+        self.option_string()?
+            .map(|spec| ValueStyle::parse(&spec))
+            .transpose()
+    }
+
+    fn condition(&mut self) -> anyhow::Result<Option<Condition>> {
+        self.option_string()?
+            .map(|spec| Condition::parse(&spec))
+            .transpose()
+    }
+
+    fn encoding(&mut self) -> anyhow::Result<Option<Encoding>> {
+        self.option_string()?
+            .map(|name| Encoding::parse(&name))
+            .transpose()
+    }
+
+    fn field_type(&mut self) -> anyhow::Result<Option<FieldType>> {
+        self.option_string()?
+            .map(|spec| FieldType::parse(&spec))
+            .transpose()
+    }
+
+    fn check(&mut self) -> anyhow::Result<Option<ControlCheck>> {
+        self.option_string()?
+            .map(|spec| ControlCheck::parse(&spec))
+            .transpose()
+    }
+
+    fn record_type(&mut self) -> anyhow::Result<Option<RecordType>> {
+        self.option_string()?
+            .map(|name| {
+                Ok(RecordType {
+                    name,
+                    condition: self.condition()?,
+                    line: self.u64()?,
+                })
+            })
+            .transpose()
+    }
+
+    fn case(&mut self) -> anyhow::Result<Option<Case>> {
+        Ok(match self.option_string()?.as_deref() {
+            None => None,
+            Some("upper") => Some(Case::Upper),
+            Some("lower") => Some(Case::Lower),
+            Some("any") => Some(Case::Any),
+            Some(_) => bail!("Compiled syntax file is corrupt."),
+        })
+    }
+
+    fn justify(&mut self) -> anyhow::Result<Option<Justify>> {
+        Ok(match self.option_string()?.as_deref() {
+            None => None,
+            Some("left") => Some(Justify::Left),
+            Some("right") => Some(Justify::Right),
+            Some(_) => bail!("Compiled syntax file is corrupt."),
+        })
+    }
+
+    fn pad(&mut self) -> anyhow::Result<Option<char>> {
+        Ok(match self.option_string()? {
+            None => None,
+            Some(pad) => Some(parse_pad(&pad).context("Compiled syntax file is corrupt.")?),
+        })
+    }
+}
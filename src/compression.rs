@@ -0,0 +1,187 @@
+//! Reading compressed input, decompressed as it's read by `gzip` or `zstd` so a large file never has to be
+//! decompressed to disk.
+
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+use anyhow::Context;
+
+use crate::STDIN;
+
+/// How the input file is compressed.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Decided by the file's extension (`.gz` or `.zst`), or failing that by its first bytes
+    Auto,
+    /// Not compressed
+    None,
+    /// Compressed with gzip
+    Gzip,
+    /// Compressed with Zstandard
+    Zstd,
+}
+
+impl Compression {
+    /// The compression the first bytes of a file say it has, [`Compression::None`] if they don't say.
+    fn from_magic(magic: &[u8]) -> Compression {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// The program that decompresses this compression.
+    fn program(self) -> &'static str {
+        match self {
+            Compression::Zstd => "zstd",
+            _ => "gzip",
+        }
+    }
+}
+
+/// How `input_file` is compressed, deciding it for [`Compression::Auto`]. Standard input can't be looked at without
+/// reading it, so is only compressed if `compression` says so.
+pub fn detect(input_file: &str, compression: Compression) -> anyhow::Result<Compression> {
+    if compression != Compression::Auto {
+        return Ok(compression);
+    }
+    if input_file == STDIN {
+        return Ok(Compression::None);
+    }
+    match Path::new(input_file).extension().and_then(|e| e.to_str()) {
+        Some("gz") => return Ok(Compression::Gzip),
+        Some("zst") => return Ok(Compression::Zstd),
+        _ => (),
+    }
+    let mut magic = Vec::with_capacity(4);
+    File::open(input_file)?
+        .take(4)
+        .read_to_end(&mut magic)
+        .context("Failed to read from input file.")?;
+    Ok(Compression::from_magic(&magic))
+}
+
+/// Open `input_file`, or standard input if it's [`STDIN`], to read, decompressing it as it's read if it's compressed.
+/// Standard input is decompressed if `compression` says so, or for [`Compression::Auto`] if its first bytes say it's
+/// compressed.
+pub fn open(input_file: &str, compression: Compression) -> anyhow::Result<Box<dyn Read>> {
+    if input_file != STDIN {
+        return Ok(match detect(input_file, compression)? {
+            Compression::None => Box::new(File::open(input_file)?),
+            compression => Box::new(Decompressor::spawn(compression, Some(input_file), None)?),
+        });
+    }
+    let mut stdin = io::stdin();
+    if compression != Compression::Auto {
+        return Ok(match compression {
+            Compression::None => Box::new(stdin),
+            compression => Box::new(Decompressor::spawn(compression, None, None)?),
+        });
+    }
+    // the bytes read to decide are passed on with the rest of standard input
+    let mut magic = Vec::with_capacity(4);
+    (&mut stdin)
+        .take(4)
+        .read_to_end(&mut magic)
+        .context("Failed to read from standard input.")?;
+    Ok(match Compression::from_magic(&magic) {
+        Compression::None => Box::new(io::Cursor::new(magic).chain(stdin)),
+        compression => Box::new(Decompressor::spawn(compression, None, Some(magic))?),
+    })
+}
+
+/// Reads the output of a program decompressing the input, failing with what the program said if it fails.
+struct Decompressor {
+    program: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+    finished: bool,
+}
+
+impl Decompressor {
+    /// Start decompressing `input_file`, or standard input if there isn't one. `magic` is what has already been read of
+    /// standard input.
+    fn spawn(
+        compression: Compression,
+        input_file: Option<&str>,
+        magic: Option<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        let program = compression.program();
+        let mut command = Command::new(program);
+        command
+            .arg("-dc")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        match input_file {
+            Some(input_file) => {
+                command.arg("--").arg(input_file).stdin(Stdio::null());
+            }
+            None if magic.is_some() => {
+                command.stdin(Stdio::piped());
+            }
+            None => {
+                command.stdin(Stdio::inherit());
+            }
+        }
+        let mut child = command.spawn().with_context(|| {
+            format!(
+                "Failed to run `{}` to decompress the input. Is it installed?",
+                program
+            )
+        })?;
+        if let (Some(magic), Some(mut to_child)) = (magic, child.stdin.take()) {
+            std::thread::spawn(move || {
+                // the program stops reading if it fails, which it reports itself, or if the rest of the input isn't
+                // wanted, so failing to write to it is no error
+                let _ = to_child
+                    .write_all(&magic)
+                    .and_then(|_| io::copy(&mut io::stdin(), &mut to_child));
+            });
+        }
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Self {
+            program,
+            child,
+            stdout,
+            finished: false,
+        })
+    }
+}
+
+impl Read for Decompressor {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            let mut message = String::new();
+            if let Some(mut stderr) = self.child.stderr.take() {
+                stderr.read_to_string(&mut message)?;
+            }
+            if !self.child.wait()?.success() {
+                return Err(io::Error::other(format!(
+                    "`{}` failed to decompress the input: {}",
+                    self.program,
+                    message.trim()
+                )));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for Decompressor {
+    fn drop(&mut self) {
+        // the input may not have been read to the end, e.g. with `--head`
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Config files, `.fixedfile-highlighter.toml`, which give default options so a team can share the same colours,
+//! encoding, delimiter and so on alongside their syntax files. The user's file in their home directory is read first,
+//! then the project's, the nearest in the current directory or above it, with options given on the command line taking
+//! precedence over both. Each file can also have named profiles of options, `[profile.NAME]`, used with `--profile`.
+//!
+//! Each setting is an option of the command line, by its long name, e.g. `output-format = "ansi"` for
+//! `--output-format ansi`. The files are read with the same part of TOML as syntax documents.
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::CommandFactory;
+use fixedfile_highlighter::document::{parse_toml_settings, Setting as TomlSetting, SettingValue};
+use log::info;
+
+use crate::Args;
+
+/// The name of a config file, in the home directory or a project's.
+pub const FILE_NAME: &str = ".fixedfile-highlighter.toml";
+
+/// An option set by a config file.
+struct Setting {
+    /// The option's long name, e.g. `output-format`.
+    option: String,
+    value: SettingValue,
+    /// The line of the file it's set on, from 1.
+    line: u64,
+}
+
+/// The settings of a config file.
+struct ConfigFile {
+    path: PathBuf,
+    /// The settings outside any profile, which are always used.
+    settings: Vec<Setting>,
+    /// The settings of each profile, by its name.
+    profiles: Vec<(String, Vec<Setting>)>,
+}
+
+/// The options the config files give, as command-line arguments to go before those actually given. The top-level
+/// settings of each file are used, then those of `profile`, if one is given. With a subcommand, only the options that
+/// apply to it are used.
+pub fn arguments(profile: Option<&str>, subcommand: bool) -> anyhow::Result<Vec<OsString>> {
+    let files: Vec<ConfigFile> = config_paths()
+        .into_iter()
+        .map(|path| ConfigFile::read(&path))
+        .collect::<anyhow::Result<_>>()?;
+
+    let mut settings: Vec<(&Path, &Setting)> = Vec::new();
+    for file in &files {
+        settings.extend(file.settings.iter().map(|s| (file.path.as_path(), s)));
+    }
+    if let Some(profile) = profile {
+        let mut found = false;
+        for file in &files {
+            for (_, profile_settings) in file.profiles.iter().filter(|(name, _)| name == profile) {
+                found = true;
+                settings.extend(profile_settings.iter().map(|s| (file.path.as_path(), s)));
+            }
+        }
+        if !found {
+            let names: Vec<&str> = files
+                .iter()
+                .flat_map(|f| f.profiles.iter().map(|(name, _)| name.as_str()))
+                .collect();
+            if names.is_empty() {
+                bail!(
+                    "There's no profile `{}`, as no config file ({}) gives any profiles.",
+                    profile,
+                    FILE_NAME
+                );
+            }
+            bail!(
+                "There's no profile `{}` in the config files. The profiles are: {}.",
+                profile,
+                names.join(", ")
+            );
+        }
+    }
+
+    let command = Args::command();
+    let mut arguments = Vec::new();
+    for (path, setting) in settings {
+        let located = || format!("Config file {} line {}", path.display(), setting.line);
+        let Some(arg) = command
+            .get_arguments()
+            .find(|a| a.get_long() == Some(setting.option.as_str()))
+            .filter(|a| !matches!(a.get_id().as_str(), "profile" | "no_config"))
+        else {
+            bail!(
+                "{}: `{}` isn't an option that can be set.",
+                located(),
+                setting.option
+            );
+        };
+        if matches!(arg.get_id().as_str(), "allow_plugins" | "plugin_timeout") {
+            // config files come with the files they're used on, which may not be trusted to run programs
+            bail!(
+                "{}: `{}` can only be given on the command line, as it lets syntax files run programs.",
+                located(),
+                setting.option
+            );
+        }
+        if subcommand && !arg.is_global_set() {
+            continue;
+        }
+        let flag = format!("--{}", setting.option);
+        let takes_values = arg.get_action().takes_values();
+        let value_optional = arg.get_num_args().is_some_and(|n| n.min_values() == 0);
+        let values = match &setting.value {
+            SettingValue::List(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+        let switch = !takes_values || value_optional;
+        for value in values {
+            match value {
+                SettingValue::Text(text) if switch && text == "true" => {
+                    arguments.push(flag.clone().into())
+                }
+                // a flag can't be turned off on the command line, so there's nothing to give
+                SettingValue::Text(text) if switch && text == "false" => (),
+                SettingValue::Text(text) if takes_values => {
+                    arguments.push(format!("{}={}", flag, text).into())
+                }
+                SettingValue::Text(_) => bail!(
+                    "{}: `{}` is a switch, so should be `true` or `false`.",
+                    located(),
+                    setting.option
+                ),
+                SettingValue::List(_) => bail!("{}: arrays can't be nested.", located()),
+                SettingValue::Table(_) => bail!(
+                    "{}: `{}` should be given a value, not a table.",
+                    located(),
+                    setting.option
+                ),
+            }
+        }
+    }
+    Ok(arguments)
+}
+
+/// The config files to read, in order: the user's, in their home directory, then the project's, the nearest in the
+/// current directory or above it.
+fn config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| Path::new(&home).join(FILE_NAME));
+    if let Some(home) = home.as_ref().filter(|home| home.is_file()) {
+        paths.push(home.clone());
+    }
+    if let Ok(current) = std::env::current_dir() {
+        let project = current
+            .ancestors()
+            .map(|dir| dir.join(FILE_NAME))
+            .find(|path| path.is_file());
+        // a project in the home directory shares the user's file, which is only read once
+        if let Some(project) = project.filter(|project| Some(project) != home.as_ref()) {
+            paths.push(project);
+        }
+    }
+    paths
+}
+
+impl ConfigFile {
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        info!("Reading config file {}", path.display());
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}.", path.display()))?;
+        let file_label = format!("Config file {}", path.display());
+        let mut file = Self {
+            path: path.to_owned(),
+            settings: Vec::new(),
+            profiles: Vec::new(),
+        };
+        for setting in parse_toml_settings(&text, &file_label)? {
+            match setting.value {
+                SettingValue::Table(profiles) if setting.key == "profile" => {
+                    for profile in profiles {
+                        let SettingValue::Table(settings) = profile.value else {
+                            bail!(
+                                "{} line {}: a profile should be a table, e.g. `[profile.{}]`.",
+                                file_label,
+                                profile.line,
+                                profile.key
+                            );
+                        };
+                        let settings = settings
+                            .into_iter()
+                            .map(|s| option_setting(s, &file_label))
+                            .collect::<anyhow::Result<_>>()?;
+                        file.profiles.push((profile.key, settings));
+                    }
+                }
+                _ => file.settings.push(option_setting(setting, &file_label)?),
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// The option a setting of the file `file_label` sets.
+fn option_setting(setting: TomlSetting, file_label: &str) -> anyhow::Result<Setting> {
+    if let SettingValue::Table(_) = setting.value {
+        bail!(
+            "{} line {}: the only tables are profiles, e.g. `[profile.audit]`.",
+            file_label,
+            setting.line
+        );
+    }
+    Ok(Setting {
+        // TOML keys are often written with underscores, but options never are
+        option: setting.key.replace('_', "-"),
+        value: setting.value,
+        line: setting.line,
+    })
+}
@@ -0,0 +1,516 @@
+//! Constraints a syntax file can place on the values of a field, which are checked on every line the field applies to.
+
+use std::{fmt, sync::Arc};
+
+use anyhow::{bail, Context};
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{plugin, redact::mask_value, region_value, FieldType, HighlightRegion};
+
+/// The characters a field may contain, from the `charset` column of a syntax file.
+///
+/// This is either a named set (`ascii`, `ascii-printable`, `ebcdic-safe`), or a list of characters and ranges as in a
+/// regex character class, e.g. `A-Z0-9 `. A backslash takes the next character literally, so `\-` is a hyphen.
+#[derive(Debug, Clone)]
+pub struct Charset {
+    spec: String,
+    ranges: Vec<(char, char)>,
+}
+
+/// Characters which are encoded the same in every common EBCDIC code page, so survive conversion to and from one.
+const EBCDIC_SAFE: &str = r#"A-Za-z0-9 .<(+&*);\-/,%_>?:'="#;
+
+impl Charset {
+    pub fn parse(spec: &str) -> anyhow::Result<Charset> {
+        let class = match spec {
+            "ascii" => "\0-\x7f",
+            "ascii-printable" => " -~",
+            "ebcdic-safe" => EBCDIC_SAFE,
+            _ => spec,
+        };
+
+        let mut chars: Vec<char> = Vec::new();
+        let mut literal: Vec<bool> = Vec::new();
+        let mut iter = class.chars();
+        while let Some(c) = iter.next() {
+            if c == '\\' {
+                match iter.next() {
+                    Some(escaped) => {
+                        chars.push(escaped);
+                        literal.push(true);
+                    }
+                    None => bail!("Charset `{}` ends with an unfinished escape.", spec),
+                }
+            } else {
+                chars.push(c);
+                literal.push(false);
+            }
+        }
+
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            // a `-` between two characters makes a range; anywhere else it is itself
+            if i + 2 < chars.len() && chars[i + 1] == '-' && !literal[i + 1] {
+                let (from, to) = (chars[i], chars[i + 2]);
+                if from > to {
+                    bail!(
+                        "Charset `{}` has the range `{}-{}`, which is backwards.",
+                        spec,
+                        from,
+                        to
+                    );
+                }
+                ranges.push((from, to));
+                i += 3;
+            } else {
+                ranges.push((chars[i], chars[i]));
+                i += 1;
+            }
+        }
+        if ranges.is_empty() {
+            bail!("Charset is empty.");
+        }
+        Ok(Charset {
+            spec: spec.to_owned(),
+            ranges,
+        })
+    }
+
+    /// The charset as written in the syntax file.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|(from, to)| *from <= c && c <= *to)
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.spec)
+    }
+}
+
+/// The letter case a field's value must be in, from the `case` column of a syntax file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    /// No lowercase letters.
+    Upper,
+    /// No uppercase letters.
+    Lower,
+    /// Either, which is the same as having no `case`.
+    Any,
+}
+
+impl Case {
+    pub fn name(self) -> &'static str {
+        match self {
+            Case::Upper => "upper",
+            Case::Lower => "lower",
+            Case::Any => "any",
+        }
+    }
+
+    pub fn allows(self, c: char) -> bool {
+        match self {
+            Case::Upper => !c.is_lowercase(),
+            Case::Lower => !c.is_uppercase(),
+            Case::Any => true,
+        }
+    }
+}
+
+/// Which end of a field its value is written against, from the `justify` column of a syntax file. The field is padded
+/// at the other end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Justify {
+    /// Padded on the right, as text usually is.
+    Left,
+    /// Padded on the left, as numbers usually are.
+    Right,
+}
+
+impl Justify {
+    pub fn name(self) -> &'static str {
+        match self {
+            Justify::Left => "left",
+            Justify::Right => "right",
+        }
+    }
+}
+
+/// Read an optional `pad` column: the one character a field is padded with, or `space`.
+pub fn deserialize_pad<'de, D>(deserializer: D) -> Result<Option<char>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => {
+            parse_pad(&spec).map(Some).map_err(serde::de::Error::custom)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// The character a `pad` column gives, which may be written `space` for a space.
+pub fn parse_pad(spec: &str) -> anyhow::Result<char> {
+    if spec == "space" {
+        return Ok(' ');
+    }
+    let mut chars = spec.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => bail!(
+            "Pad `{}` should be one character, e.g. `0`, or `space`.",
+            spec
+        ),
+    }
+}
+
+/// A regex a field's whole value must match, from the `pattern` column of a syntax file.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    spec: String,
+    /// Shared by the region of every line the rule applies to, as a cloned regex would build its matching cache anew.
+    regex: Arc<Regex>,
+}
+
+impl Pattern {
+    pub fn parse(spec: &str) -> anyhow::Result<Pattern> {
+        // checked alone first, so any error points into the pattern as written
+        if let Err(e) = Regex::new(spec) {
+            bail!("Pattern `{}` is not a valid regex: {}", spec, e);
+        }
+        let regex = Regex::new(&format!("^(?:{})$", spec))
+            .with_context(|| format!("Pattern `{}` can't be anchored.", spec))?;
+        Ok(Pattern {
+            spec: spec.to_owned(),
+            regex: Arc::new(regex),
+        })
+    }
+
+    /// The pattern as written in the syntax file.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.spec)
+    }
+}
+
+/// Read an optional `pattern` column, failing on patterns that aren't valid regexes.
+pub fn deserialize_pattern<'de, D>(deserializer: D) -> Result<Option<Pattern>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => Pattern::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Read an optional `charset` column, failing on charsets that can't be parsed.
+pub fn deserialize_charset<'de, D>(deserializer: D) -> Result<Option<Charset>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => Charset::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Why a character of a field breaks its constraints, if it does. Columns from `limit` on have been virtually padded,
+/// so aren't checked, and nor are the quotes around a quoted field.
+pub fn violation(
+    regions: &[HighlightRegion],
+    col: usize,
+    c: char,
+    limit: Option<usize>,
+) -> Option<String> {
+    if limit.is_some_and(|l| col >= l) {
+        return None;
+    }
+    regions
+        .iter()
+        .filter(|r| r.value_columns().contains(&col))
+        .find_map(|r| {
+            if let Some(charset) = r.charset.as_ref().filter(|charset| !charset.contains(c)) {
+                Some(format!(
+                    "'{}' is not in the charset {} of '{}'",
+                    c, charset, r.name
+                ))
+            } else {
+                r.case.filter(|case| !case.allows(c)).map(|case| {
+                    format!(
+                        "'{}' is not {}case, as '{}' must be",
+                        c,
+                        case.name(),
+                        r.name
+                    )
+                })
+            }
+        })
+}
+
+/// Log every character of a line's fields which breaks their constraints, grouped by field, returning the number of
+/// fields logged.
+pub fn log_line(
+    line_index: usize,
+    text: &str,
+    regions: &[HighlightRegion],
+    limit: Option<usize>,
+) -> usize {
+    let mut logged = 0;
+    let chars: Vec<char> = text.chars().collect();
+    let length = limit.map_or(chars.len(), |l| l.min(chars.len()));
+    for r in regions {
+        let columns = r.value_columns();
+        let columns = columns.start..columns.end.min(length);
+        if let Some(charset) = &r.charset {
+            let offending = offending_columns(&chars, r, columns.clone(), |c| charset.contains(c));
+            if !offending.is_empty() {
+                logged += 1;
+                error!(
+                    "Line {}: field '{}' has characters outside its charset {} at {}.",
+                    line_index + 1,
+                    r.name,
+                    charset,
+                    offending
+                );
+            }
+        }
+        if let Some(case) = r.case {
+            let offending = offending_columns(&chars, r, columns, |c| case.allows(c));
+            if !offending.is_empty() {
+                logged += 1;
+                error!(
+                    "Line {}: field '{}' must be {}case, but isn't at {}.",
+                    line_index + 1,
+                    r.name,
+                    case.name(),
+                    offending
+                );
+            }
+        }
+        let misplaced = misplaced_padding(&chars, r, length);
+        if !misplaced.is_empty() {
+            logged += 1;
+            error!(
+                "Line {}: field '{}' has padding at {}, breaking its {}.",
+                line_index + 1,
+                r.name,
+                offending_columns(&chars, r, misplaced.into_iter(), |_| false),
+                padding_spec(r)
+            );
+        }
+        if let Some(FieldType::Plugin { command }) = &r.field_type {
+            let value = region_value(text, r, Some(length));
+            if let (true, Err(reason)) = (is_present(r, length), plugin::decode(command, &value)) {
+                logged += 1;
+                error!(
+                    "Line {}: field '{}' has the value '{}', which its plugin `{}` says isn't valid, as {}.",
+                    line_index + 1,
+                    r.name,
+                    mask_value(r, value),
+                    command,
+                    reason
+                );
+            }
+        }
+        if let Some(pattern) = &r.pattern {
+            let value = region_value(text, r, Some(length));
+            if is_present(r, length) && !pattern.matches(&value) {
+                logged += 1;
+                error!(
+                    "Line {}: field '{}' has the value '{}', which doesn't match its pattern {}.",
+                    line_index + 1,
+                    r.name,
+                    mask_value(r, value),
+                    pattern
+                );
+            }
+        }
+    }
+    logged
+}
+
+/// A field whose value breaks one of its constraints.
+pub struct Violation {
+    pub field: String,
+    /// The 0-based columns of the field.
+    pub columns: std::ops::Range<usize>,
+    pub value: String,
+    /// The kind of constraint broken: `charset`, `case`, `padding`, `pattern` or `plugin`.
+    pub constraint: &'static str,
+    /// The constraint the value breaks, as it should be shown in a report, e.g. ``pattern `[0-9]+` ``.
+    pub expected: String,
+}
+
+/// Every constraint the fields of a line break, once for each field and constraint. Columns from `limit` on have been
+/// virtually padded, so aren't checked.
+pub fn violations(text: &str, regions: &[HighlightRegion], limit: Option<usize>) -> Vec<Violation> {
+    let chars: Vec<char> = text.chars().collect();
+    let length = limit.map_or(chars.len(), |l| l.min(chars.len()));
+    let mut found = Vec::new();
+    for r in regions.iter().filter(|r| is_present(r, length)) {
+        let value = region_value(text, r, Some(length));
+        let mut expected = Vec::new();
+        if let Some(charset) = r
+            .charset
+            .as_ref()
+            .filter(|cs| !value.chars().all(|c| cs.contains(c)))
+        {
+            expected.push(("charset", format!("charset {}", charset)));
+        }
+        if let Some(case) = r.case.filter(|case| !value.chars().all(|c| case.allows(c))) {
+            expected.push(("case", format!("{}case", case.name())));
+        }
+        if !misplaced_padding(&chars, r, length).is_empty() {
+            expected.push(("padding", padding_spec(r)));
+        }
+        if let Some(pattern) = r.pattern.as_ref().filter(|p| !p.matches(&value)) {
+            expected.push(("pattern", format!("pattern {}", pattern)));
+        }
+        if let Some(FieldType::Plugin { command }) = &r.field_type {
+            if let Err(reason) = plugin::decode(command, &value) {
+                expected.push(("plugin", format!("plugin `{}` ({})", command, reason)));
+            }
+        }
+        let value = mask_value(r, value);
+        found.extend(
+            expected
+                .into_iter()
+                .map(|(constraint, expected)| Violation {
+                    field: r.name.to_string(),
+                    columns: r.start..r.end,
+                    value: value.clone(),
+                    constraint,
+                    expected,
+                }),
+        );
+    }
+    found
+}
+
+/// Each column of `text` holding padding that its field's `justify` and `pad` don't allow, with why, in order. Columns
+/// from `limit` on have been virtually padded, so aren't checked.
+pub fn padding_violations(
+    text: &str,
+    regions: &[HighlightRegion],
+    limit: Option<usize>,
+) -> Vec<(usize, String)> {
+    if regions
+        .iter()
+        .all(|r| r.justify.is_none() && r.pad.is_none())
+    {
+        return Vec::new();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let length = limit.map_or(chars.len(), |l| l.min(chars.len()));
+    let mut found: Vec<(usize, String)> = Vec::new();
+    for r in regions {
+        for col in misplaced_padding(&chars, r, length) {
+            if !found.iter().any(|(c, _)| *c == col) {
+                found.push((
+                    col,
+                    format!(
+                        "{} is padding where '{}' shouldn't have any, breaking its {}",
+                        describe_char(chars[col]),
+                        r.name,
+                        padding_spec(r)
+                    ),
+                ));
+            }
+        }
+    }
+    found.sort_by_key(|(col, _)| *col);
+    found
+}
+
+/// The columns of a field's value, within the first `length` columns, holding padding its `justify` and `pad` don't
+/// allow: spaces at the end the field is justified against, or at either end of a field padded with something else.
+/// Any other pad character at the justified end might be part of the value, such as the last zero of `001200`, so
+/// isn't flagged. A field that's nothing but padding is simply empty, so is never flagged either.
+fn misplaced_padding(chars: &[char], region: &HighlightRegion, length: usize) -> Vec<usize> {
+    if region.justify.is_none() && region.pad.is_none() {
+        return Vec::new();
+    }
+    let pad = region.pad.unwrap_or(' ');
+    let columns = region.value_columns();
+    let value = &chars[columns.start.min(length)..columns.end.min(length)];
+    if value.iter().all(|&c| c == pad) || value.iter().all(|&c| c == ' ') {
+        return Vec::new();
+    }
+    let leading = 0..value.iter().take_while(|&&c| c == ' ').count();
+    let trailing = value.len() - value.iter().rev().take_while(|&&c| c == ' ').count()..value.len();
+    let mut misplaced = Vec::new();
+    if pad != ' ' || region.justify == Some(Justify::Left) {
+        misplaced.extend(leading);
+    }
+    if pad != ' ' || region.justify == Some(Justify::Right) {
+        misplaced.extend(trailing);
+    }
+    misplaced.into_iter().map(|i| columns.start + i).collect()
+}
+
+/// A field's `justify` and `pad`, as they should be shown in a message, e.g. ``justify `right` and pad '0'``.
+fn padding_spec(region: &HighlightRegion) -> String {
+    let justify = region.justify.map(|j| format!("justify `{}`", j.name()));
+    let pad = region.pad.map(|pad| format!("pad {}", describe_char(pad)));
+    justify
+        .into_iter()
+        .chain(pad)
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Whether any of a field is within the first `length` columns of its line. An empty field counts if it is, so can be
+/// checked against its pattern, but a missing one doesn't.
+fn is_present(region: &HighlightRegion, length: usize) -> bool {
+    region.start < length || region.start == region.end
+}
+
+/// The columns of `chars` in `columns`, of the field `region`, which aren't `allowed`, listed for a message. The
+/// characters of a sensitive field aren't given.
+fn offending_columns(
+    chars: &[char],
+    region: &HighlightRegion,
+    columns: impl Iterator<Item = usize>,
+    allowed: impl Fn(char) -> bool,
+) -> String {
+    columns
+        .filter(|&col| !allowed(chars[col]))
+        .map(|col| match region.sensitive {
+            true => format!("column {}", col + 1),
+            false => format!("column {} ({})", col + 1, describe_char(chars[col])),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A character as it should be shown in a message: quoted if printable ASCII, or as its code point otherwise.
+fn describe_char(c: char) -> String {
+    if c.is_ascii_graphic() || c == ' ' {
+        format!("'{}'", c)
+    } else {
+        format!("U+{:04X}", c as u32)
+    }
+}
@@ -0,0 +1,587 @@
+//! COBOL copybooks, read as syntax files with `--syntax-format copybook`: the record layouts they describe are turned
+//! into fixed width rules, one for each elementary item.
+//!
+//! This covers the common subset of the data division: level numbers, `PIC` clauses with `USAGE` (`DISPLAY`, `COMP`,
+//! `COMP-3` and friends), `SIGN ... SEPARATE`, `OCCURS` (repeated items are named `NAME(1)`, `NAME(2)`, ...) and
+//! `REDEFINES`, whose items overlap the ones they redefine. Each 01 level record starts at the first column. Condition
+//! names (88 levels), `RENAMES` (66 levels) and `VALUE` clauses don't affect the layout, so are skipped.
+//!
+//! Numeric items are given the `type` of a zoned or packed (`COMP-3`) decimal number, so their values can be shown.
+
+use anyhow::{bail, Context};
+use log::info;
+
+use crate::{FieldType, FixedWidthHighlightRecord, RecordList};
+
+/// How an item's value is stored, which decides how many columns its picture takes up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Usage {
+    Display,
+    /// `COMP`, `COMP-4`, `COMP-5` and `BINARY`.
+    Binary,
+    /// `COMP-3` and `PACKED-DECIMAL`.
+    Packed,
+    /// `COMP-1`, a single precision float.
+    Float,
+    /// `COMP-2`, a double precision float.
+    Double,
+    /// `INDEX` and `POINTER`.
+    Address,
+}
+
+impl Usage {
+    fn parse(word: &str) -> Option<Usage> {
+        Some(match word {
+            "DISPLAY" | "NATIONAL" => Usage::Display,
+            "COMP" | "COMPUTATIONAL" | "COMP-4" | "COMPUTATIONAL-4" | "COMP-5"
+            | "COMPUTATIONAL-5" | "BINARY" => Usage::Binary,
+            "COMP-3" | "COMPUTATIONAL-3" | "PACKED-DECIMAL" => Usage::Packed,
+            "COMP-1" | "COMPUTATIONAL-1" => Usage::Float,
+            "COMP-2" | "COMPUTATIONAL-2" => Usage::Double,
+            "INDEX" | "POINTER" => Usage::Address,
+            _ => return None,
+        })
+    }
+}
+
+/// A data description entry: one statement of the copybook.
+#[derive(Debug)]
+struct Entry {
+    /// The line of the copybook the entry starts on.
+    line: u64,
+    level: u8,
+    name: String,
+    picture: Option<String>,
+    usage: Option<Usage>,
+    sign_separate: bool,
+    occurs: usize,
+    redefines: Option<String>,
+}
+
+/// An entry with those nested under it.
+#[derive(Debug)]
+struct Item {
+    entry: Entry,
+    children: Vec<Item>,
+}
+
+/// Parse a copybook into fixed width rules, with start columns counted from `index_base`.
+pub fn parse(copybook: &str, index_base: usize) -> anyhow::Result<RecordList> {
+    let mut entries = Vec::new();
+    for (line, statement) in statements(copybook) {
+        if let Some(entry) = parse_entry(line, &statement)? {
+            entries.push(entry);
+        }
+    }
+    if entries.is_empty() {
+        bail!("The copybook doesn't describe any data items.");
+    }
+
+    let mut records = Vec::new();
+    for item in nest(entries)? {
+        layout(&item, 0, index_base, None, "", &mut records)?;
+    }
+    Ok(RecordList::FixedWidth(records))
+}
+
+/// Whether the copybook is in fixed format, with sequence numbers (or spaces) in columns 1-6 and an indicator in column
+/// 7, rather than free format.
+fn is_fixed_format(copybook: &str) -> bool {
+    copybook
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.chars().take(6).all(|c| c.is_ascii_digit() || c == ' '))
+}
+
+/// The code of a line, without sequence number, indicator or identification area if it's in fixed format, or `None`
+/// for comments and compiler directives.
+fn code(line: &str, fixed_format: bool) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let code: String = if fixed_format {
+        if matches!(chars.get(6), Some('*' | '/')) {
+            return None;
+        }
+        chars[7.min(chars.len())..72.min(chars.len())]
+            .iter()
+            .collect()
+    } else {
+        line.to_owned()
+    };
+    let trimmed = code.trim();
+    if trimmed.starts_with('*')
+        || matches!(
+            trimmed.to_ascii_uppercase().as_str(),
+            "EJECT" | "SKIP1" | "SKIP2" | "SKIP3"
+        )
+    {
+        return None;
+    }
+    Some(code)
+}
+
+/// Split the copybook into its statements, which end with a period, each with the line it starts on.
+fn statements(copybook: &str) -> Vec<(u64, String)> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let fixed_format = is_fixed_format(copybook);
+    for (idx, line) in copybook.lines().enumerate() {
+        let Some(code) = code(line, fixed_format) else {
+            continue;
+        };
+        let chars: Vec<char> = code.chars().collect();
+        for (i, &c) in chars.iter().enumerate() {
+            if current.trim().is_empty() && !c.is_whitespace() {
+                current.clear();
+                start = idx as u64 + 1;
+            }
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => (),
+                None if c == '\'' || c == '"' => quote = Some(c),
+                // a period followed by a space ends a statement; one followed by a digit is part of a picture
+                None if c == '.' && chars.get(i + 1).is_none_or(|n| n.is_whitespace()) => {
+                    statements.push((start, current.trim().to_owned()));
+                    current.clear();
+                    continue;
+                }
+                None => (),
+            }
+            current.push(c);
+        }
+        current.push(' ');
+    }
+    if !current.trim().is_empty() {
+        statements.push((start, current.trim().to_owned()));
+    }
+    statements
+}
+
+/// Split a statement into words, keeping quoted literals whole.
+fn words(statement: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in statement.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            None => (),
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Words that start a clause, so can't be the name of an item.
+const CLAUSES: &[&str] = &[
+    "PIC",
+    "PICTURE",
+    "USAGE",
+    "OCCURS",
+    "REDEFINES",
+    "VALUE",
+    "VALUES",
+    "SIGN",
+    "SYNC",
+    "SYNCHRONIZED",
+    "JUST",
+    "JUSTIFIED",
+    "BLANK",
+];
+
+/// Parse a data description entry, or return `None` if it doesn't affect the layout.
+fn parse_entry(line: u64, statement: &str) -> anyhow::Result<Option<Entry>> {
+    let words = words(statement);
+    let mut iter = words.iter().map(|w| w.as_str()).peekable();
+    let level: u8 = iter.next().and_then(|w| w.parse().ok()).with_context(|| {
+        format!(
+            "Copybook line {}: expected a level number at the start of `{}`.",
+            line, statement
+        )
+    })?;
+    match level {
+        88 => return Ok(None),
+        66 => {
+            info!(
+                "Copybook line {}: RENAMES (level 66) doesn't affect the layout, so is skipped.",
+                line
+            );
+            return Ok(None);
+        }
+        1..=49 | 77 => (),
+        _ => bail!("Copybook line {}: level {} isn't valid.", line, level),
+    }
+
+    let name = match iter.peek() {
+        Some(word)
+            if !CLAUSES.contains(&word.to_ascii_uppercase().as_str())
+                && Usage::parse(&word.to_ascii_uppercase()).is_none() =>
+        {
+            iter.next().expect("peeked").to_owned()
+        }
+        _ => "FILLER".to_owned(),
+    };
+
+    let mut entry = Entry {
+        line,
+        level,
+        name,
+        picture: None,
+        usage: None,
+        sign_separate: false,
+        occurs: 1,
+        redefines: None,
+    };
+    let missing = |clause: &str| {
+        format!(
+            "Copybook line {}: the {} clause of '{}' is incomplete.",
+            line, clause, entry.name
+        )
+    };
+    let mut picture = None;
+    let mut usage = None;
+    let mut occurs = None;
+    let mut redefines = None;
+    let mut sign_separate = false;
+    while let Some(word) = iter.next() {
+        let upper = word.to_ascii_uppercase();
+        match upper.as_str() {
+            "PIC" | "PICTURE" => {
+                if iter.peek().is_some_and(|w| w.eq_ignore_ascii_case("IS")) {
+                    iter.next();
+                }
+                picture = Some(
+                    iter.next()
+                        .with_context(|| missing("PIC"))?
+                        .to_ascii_uppercase(),
+                );
+            }
+            "USAGE" => {
+                if iter.peek().is_some_and(|w| w.eq_ignore_ascii_case("IS")) {
+                    iter.next();
+                }
+                let word = iter.next().with_context(|| missing("USAGE"))?;
+                usage = Some(Usage::parse(&word.to_ascii_uppercase()).with_context(|| {
+                    format!(
+                        "Copybook line {}: usage `{}` of '{}' isn't supported.",
+                        line, word, entry.name
+                    )
+                })?);
+            }
+            "OCCURS" => {
+                let count: usize = iter
+                    .next()
+                    .and_then(|w| w.parse().ok())
+                    .with_context(|| missing("OCCURS"))?;
+                // a variable number of occurrences takes up the most it can
+                let count = if iter.peek().is_some_and(|w| w.eq_ignore_ascii_case("TO")) {
+                    iter.next();
+                    let most = iter
+                        .next()
+                        .and_then(|w| w.parse().ok())
+                        .with_context(|| missing("OCCURS"))?;
+                    info!(
+                        "Copybook line {}: '{}' occurs a variable number of times, so is laid out {} times, the most it can.",
+                        line, entry.name, most
+                    );
+                    most
+                } else {
+                    count
+                };
+                occurs = Some(count);
+            }
+            "REDEFINES" => {
+                redefines = Some(
+                    iter.next()
+                        .with_context(|| missing("REDEFINES"))?
+                        .to_owned(),
+                );
+            }
+            "SEPARATE" => sign_separate = true,
+            _ => {
+                if let Some(u) = Usage::parse(&upper) {
+                    usage = Some(u);
+                }
+            }
+        }
+    }
+    entry.picture = picture;
+    entry.usage = usage;
+    entry.occurs = occurs.unwrap_or(1);
+    entry.redefines = redefines;
+    entry.sign_separate = sign_separate;
+    if entry.occurs == 0 {
+        bail!(
+            "Copybook line {}: '{}' occurs no times, so takes up no columns.",
+            line,
+            entry.name
+        );
+    }
+    Ok(Some(entry))
+}
+
+/// Nest entries under the groups their level numbers put them in.
+fn nest(entries: Vec<Entry>) -> anyhow::Result<Vec<Item>> {
+    // the groups still open, innermost last
+    let mut open: Vec<Item> = Vec::new();
+    let mut top = Vec::new();
+    let close = |open: &mut Vec<Item>, top: &mut Vec<Item>| {
+        let item = open.pop().expect("an open group");
+        match open.last_mut() {
+            Some(parent) => parent.children.push(item),
+            None => top.push(item),
+        }
+    };
+    for entry in entries {
+        let level = depth(entry.level);
+        while open.last().is_some_and(|o| depth(o.entry.level) >= level) {
+            close(&mut open, &mut top);
+        }
+        if level != 1 && open.is_empty() {
+            bail!(
+                "Copybook line {}: '{}' is at level {}, but isn't inside an 01 level record.",
+                entry.line,
+                entry.name,
+                entry.level
+            );
+        }
+        if let Some(parent) = open.last() {
+            if parent.entry.picture.is_some() {
+                bail!(
+                    "Copybook line {}: '{}' is nested under '{}', which has a picture so can't be a group.",
+                    entry.line,
+                    entry.name,
+                    parent.entry.name
+                );
+            }
+        }
+        open.push(Item {
+            entry,
+            children: Vec::new(),
+        });
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut top);
+    }
+    Ok(top)
+}
+
+/// How deeply an entry of `level` is nested: 77 levels are records of their own, like 01 levels.
+fn depth(level: u8) -> u8 {
+    if level == 77 {
+        1
+    } else {
+        level
+    }
+}
+
+/// The number of columns a picture takes up, stored with `usage`.
+fn picture_size(entry: &Entry, picture: &str, usage: Usage) -> anyhow::Result<usize> {
+    let mut display = 0;
+    let mut digits = 0;
+    let mut chars = picture.chars().peekable();
+    while let Some(symbol) = chars.next() {
+        // a repeat count, e.g. the 5 of `9(5)`
+        let count = if chars.peek() == Some(&'(') {
+            chars.next();
+            let count: String = chars.by_ref().take_while(|c| *c != ')').collect();
+            count.trim().parse::<usize>().with_context(|| {
+                format!(
+                    "Copybook line {}: picture `{}` of '{}' has a repeat count that isn't a number.",
+                    entry.line, picture, entry.name
+                )
+            })?
+        } else {
+            1
+        };
+        let width = match symbol {
+            // an assumed decimal point, sign or scaling position takes up no columns
+            'S' | 'V' | 'P' => 0,
+            // national and DBCS characters take two bytes
+            'N' | 'G' => 2,
+            'X' | 'A' | '9' | 'Z' | '*' | '+' | '-' | '$' | ',' | '.' | 'B' | '0' | '/' | 'C'
+            | 'R' | 'D' | 'E' => 1,
+            _ => bail!(
+                "Copybook line {}: picture `{}` of '{}' has `{}`, which isn't a picture symbol.",
+                entry.line,
+                picture,
+                entry.name,
+                symbol
+            ),
+        };
+        if symbol == '9' {
+            digits += count;
+        }
+        display += width * count;
+    }
+    Ok(match usage {
+        Usage::Display => display + entry.sign_separate as usize,
+        Usage::Packed => digits / 2 + 1,
+        Usage::Binary => match digits {
+            0..=4 => 2,
+            5..=9 => 4,
+            _ => 8,
+        },
+        Usage::Float | Usage::Address => 4,
+        Usage::Double => 8,
+    })
+}
+
+/// The number of columns one occurrence of an item takes up, with `usage` inherited from its group.
+fn size(item: &Item, usage: Option<Usage>) -> anyhow::Result<usize> {
+    let usage = item.entry.usage.or(usage);
+    if item.children.is_empty() {
+        return match (&item.entry.picture, usage.unwrap_or(Usage::Display)) {
+            (Some(picture), usage) => picture_size(&item.entry, picture, usage),
+            (None, Usage::Float | Usage::Address) => Ok(4),
+            (None, Usage::Double) => Ok(8),
+            (None, _) => bail!(
+                "Copybook line {}: '{}' has no picture and nothing nested under it, so its size isn't known.",
+                item.entry.line,
+                item.entry.name
+            ),
+        };
+    }
+    let mut children = Vec::new();
+    let mut end = 0;
+    let mut cursor = 0;
+    for child in &item.children {
+        let start = redefined_start(child, &children)?.unwrap_or(cursor);
+        let length = size(child, usage)? * child.entry.occurs;
+        children.push((child.entry.name.as_str(), start));
+        cursor = cursor.max(start + length);
+        end = end.max(cursor);
+    }
+    Ok(end)
+}
+
+/// How an elementary item's value is read: as a zoned or packed decimal number if it has a numeric picture, with the
+/// digits after its `V` as decimal places.
+fn numeric_type(entry: &Entry, usage: Option<Usage>) -> anyhow::Result<Option<FieldType>> {
+    let Some(picture) = &entry.picture else {
+        return Ok(None);
+    };
+    let mut scale = None;
+    let mut chars = picture.chars().peekable();
+    while let Some(symbol) = chars.next() {
+        let count = if chars.peek() == Some(&'(') {
+            chars.next();
+            let count: String = chars.by_ref().take_while(|c| *c != ')').collect();
+            count.trim().parse::<usize>().with_context(|| {
+                format!(
+                    "Copybook line {}: picture `{}` of '{}' has a repeat count that isn't a number.",
+                    entry.line, picture, entry.name
+                )
+            })?
+        } else {
+            1
+        };
+        match symbol {
+            'S' | 'P' => (),
+            'V' => scale = Some(0),
+            '9' => {
+                if let Some(scale) = &mut scale {
+                    *scale += count;
+                }
+            }
+            _ => return Ok(None),
+        }
+    }
+    let scale = u8::try_from(scale.unwrap_or(0)).unwrap_or(u8::MAX);
+    Ok(match usage.unwrap_or(Usage::Display) {
+        Usage::Display if !entry.sign_separate => Some(FieldType::Zoned { scale }),
+        Usage::Packed => Some(FieldType::Packed { scale }),
+        _ => None,
+    })
+}
+
+/// Where the item `item` redefines starts, looking through the items before it in the same group.
+fn redefined_start(item: &Item, before: &[(&str, usize)]) -> anyhow::Result<Option<usize>> {
+    let Some(redefines) = &item.entry.redefines else {
+        return Ok(None);
+    };
+    match before
+        .iter()
+        .rev()
+        .find(|(name, _)| name.eq_ignore_ascii_case(redefines))
+    {
+        Some((_, start)) => Ok(Some(*start)),
+        None => bail!(
+            "Copybook line {}: '{}' redefines '{}', which isn't an earlier item of the same group.",
+            item.entry.line,
+            item.entry.name,
+            redefines
+        ),
+    }
+}
+
+/// Add a rule for every elementary item of `item`, which starts at the 0-based `offset`. `subscripts` are those of the
+/// groups it occurs within, e.g. `(2)`.
+fn layout(
+    item: &Item,
+    offset: usize,
+    index_base: usize,
+    usage: Option<Usage>,
+    subscripts: &str,
+    records: &mut Vec<FixedWidthHighlightRecord>,
+) -> anyhow::Result<()> {
+    let usage = item.entry.usage.or(usage);
+    let length = size(item, usage)?;
+    for occurrence in 0..item.entry.occurs {
+        let subscripts = match (item.entry.occurs, subscripts) {
+            (1, _) => subscripts.to_owned(),
+            (_, "") => format!("({})", occurrence + 1),
+            (_, s) => format!("{},{})", s.trim_end_matches(')'), occurrence + 1),
+        };
+        let start = offset + occurrence * length;
+        if item.children.is_empty() {
+            records.push(FixedWidthHighlightRecord {
+                line: item.entry.line,
+                start: Some(start + index_base),
+                length: Some(length),
+                start_capture: None,
+                start_anchor: None,
+                length_capture: None,
+                from_end: false,
+                name: format!("{}{}", item.entry.name, subscripts).into(),
+                condition: None,
+                charset: None,
+                case: None,
+                pattern: None,
+                justify: None,
+                pad: None,
+                encoding: None,
+                field_type: numeric_type(&item.entry, usage)?,
+                color: None,
+                value_pattern: None,
+                value_style: None,
+                sensitive: false,
+                check: None,
+                record: None,
+                type_shift: 0,
+            });
+            continue;
+        }
+        let mut children = Vec::new();
+        let mut cursor = start;
+        for child in &item.children {
+            let child_start = redefined_start(child, &children)?.unwrap_or(cursor);
+            layout(child, child_start, index_base, usage, &subscripts, records)?;
+            children.push((child.entry.name.as_str(), child_start));
+            cursor = cursor.max(child_start + size(child, usage)? * child.entry.occurs);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! Delimiters: what separates the fields of a line in delimiter mode, and finding them in a line.
+
+use std::{fmt, ops::Range};
+
+use anyhow::bail;
+use regex::{Match, Regex};
+
+/// What separates the fields of a line in delimiter mode, from `--delimiter`.
+#[derive(Debug, Clone)]
+pub enum Delimiter {
+    /// Some text, usually a single character such as `,`, but possibly several, such as `|~|`.
+    Text(String),
+    /// A regex, such as `\s+` for runs of whitespace, from `--delimiter-regex`.
+    Regex(Regex),
+}
+
+impl Delimiter {
+    /// The delimiter `spec`, which is a regex if `regex` is set.
+    pub fn parse(spec: &str, regex: bool) -> anyhow::Result<Delimiter> {
+        if spec.is_empty() {
+            bail!("The delimiter can't be empty.");
+        }
+        if !regex {
+            return Ok(Delimiter::Text(spec.to_owned()));
+        }
+        match Regex::new(spec) {
+            // a delimiter that could be nothing at all would split a line between every character
+            Ok(regex) if regex.is_match("") => {
+                bail!(
+                    "Delimiter `{}` matches an empty string, so can't separate fields.",
+                    spec
+                )
+            }
+            Ok(regex) => Ok(Delimiter::Regex(regex)),
+            Err(e) => bail!("Delimiter `{}` is not a valid regex: {}", spec, e),
+        }
+    }
+
+    /// The delimiter as given.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Delimiter::Text(text) => text,
+            Delimiter::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    /// Whether the delimiter is a regex.
+    pub fn is_regex(&self) -> bool {
+        matches!(self, Delimiter::Regex(_))
+    }
+
+    /// The columns of each delimiter between the fields of `line`, in order. A delimiter between a pair of `quote`s is
+    /// part of a quoted field, so isn't one; a doubled quote inside a quoted field closes and reopens it, so needs no
+    /// handling.
+    pub fn spans(&self, quote: Option<char>, line: &str) -> Vec<Range<usize>> {
+        let mut spans = Vec::new();
+        let mut quoted = false;
+        let mut next: Option<Match> = None;
+        let mut chars = line.char_indices().enumerate();
+        while let Some((col, (at, c))) = chars.next() {
+            if Some(c) == quote {
+                quoted = !quoted;
+                continue;
+            }
+            if quoted {
+                continue;
+            }
+            let length = match self {
+                Delimiter::Text(text) => {
+                    line[at..].starts_with(text.as_str()).then_some(text.len())
+                }
+                Delimiter::Regex(regex) => {
+                    // the next match is kept until it's passed, so the line is only searched once
+                    if next.is_some_and(|m| m.start() < at) {
+                        next = None;
+                    }
+                    if next.is_none() {
+                        next = regex.find_at(line, at);
+                    }
+                    next.filter(|m| m.start() == at).map(|m| m.len())
+                }
+            };
+            if let Some(length) = length {
+                let width = line[at..at + length].chars().count();
+                spans.push(col..col + width);
+                // the rest of the delimiter can't start another
+                if width > 1 {
+                    chars.nth(width - 2);
+                }
+            }
+        }
+        spans
+    }
+
+    /// The text to extend `line` with to add an empty field to it: the delimiter, or for a regex, the last delimiter
+    /// found in the line. `None` if the delimiter is a regex the line has no match for.
+    pub fn filler(&self, quote: Option<char>, line: &str) -> Option<String> {
+        match self {
+            Delimiter::Text(text) => Some(text.clone()),
+            Delimiter::Regex(_) => self
+                .spans(quote, line)
+                .last()
+                .map(|span| line.chars().skip(span.start).take(span.len()).collect()),
+        }
+    }
+
+    /// The fields of `line`, split at the delimiters [`Delimiter::spans`] finds.
+    pub fn split(&self, quote: Option<char>, line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut spans = self.spans(quote, line).into_iter().peekable();
+        for (col, c) in line.chars().enumerate() {
+            if spans.peek().is_some_and(|span| span.contains(&col)) {
+                if spans.peek().is_some_and(|span| col + 1 == span.end) {
+                    spans.next();
+                    fields.push(String::new());
+                }
+            } else {
+                fields.last_mut().expect("there is always a field").push(c);
+            }
+        }
+        fields
+    }
+}
+
+impl PartialEq for Delimiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_regex() == other.is_regex() && self.as_str() == other.as_str()
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<char> for Delimiter {
+    fn from(delimiter: char) -> Self {
+        Delimiter::Text(delimiter.to_string())
+    }
+}
@@ -0,0 +1,270 @@
+//! Diagnostics on stderr: log messages, the progress of a long run and the summary at its end. They're written for a
+//! person to read, or with `--log-format json`, as a JSON object per line for automation to pick up. `--quiet` leaves
+//! out all of them but the error a run fails with.
+
+use std::{
+    cell::Cell,
+    io::{self, IsTerminal, Write},
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use log::{Log, Metadata, Record};
+
+use crate::{json_string, ProblemCounts};
+
+/// How diagnostics are written on stderr.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Messages for a person to read, with a progress bar on a terminal
+    #[default]
+    Text,
+    /// A JSON object per line: each message, the progress of the run every second and its summary
+    Json,
+}
+
+/// Whether diagnostics are written as JSON, from `--log-format json`.
+static JSON: AtomicBool = AtomicBool::new(false);
+/// Whether diagnostics are left out, from `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// Whether a progress bar is drawn on the last line of stderr, so must be cleared before anything else is written.
+static BAR_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// How long a run goes before its progress is shown, so a small file shows none.
+const PROGRESS_DELAY: Duration = Duration::from_millis(500);
+/// How often the progress bar is drawn again.
+const BAR_INTERVAL: Duration = Duration::from_millis(100);
+/// How often progress is written with `--log-format json`.
+const JSON_INTERVAL: Duration = Duration::from_secs(1);
+/// The number of characters of the progress bar between its brackets.
+const BAR_WIDTH: usize = 30;
+
+/// Writes log messages as `LOG` says, as the pretty logger does or as JSON, clearing the progress bar first.
+struct Logger {
+    pretty: Box<dyn Log>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        !QUIET.load(Ordering::Relaxed) && self.pretty.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        clear_bar();
+        if JSON.load(Ordering::Relaxed) {
+            eprintln!(
+                r#"{{"event":"log","level":"{}","target":{},"message":{}}}"#,
+                record.level().as_str().to_ascii_lowercase(),
+                json_string(record.target()),
+                json_string(&record.args().to_string())
+            );
+        } else {
+            self.pretty.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.pretty.flush();
+    }
+}
+
+/// Start logging, with the levels the `LOG` environment variable gives. Messages are written as text until
+/// [`configure`] is given the options.
+pub fn init() {
+    let mut builder = pretty_env_logger::formatted_builder();
+    if let Ok(filters) = std::env::var("LOG") {
+        builder.parse_filters(&filters);
+    }
+    let pretty = builder.build();
+    let level = pretty.filter();
+    if log::set_boxed_logger(Box::new(Logger {
+        pretty: Box::new(pretty),
+    }))
+    .is_ok()
+    {
+        log::set_max_level(level);
+    }
+}
+
+/// Write diagnostics from here on as `--quiet` and `--log-format` say.
+pub fn configure(quiet: bool, format: LogFormat) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+/// Clear the progress bar, if it's shown, so something else can be written on stderr.
+fn clear_bar() {
+    if BAR_SHOWN.swap(false, Ordering::Relaxed) {
+        eprint!("\r\x1b[K");
+    }
+}
+
+/// Write the error a run failed with, which is written even with `--quiet`.
+pub fn fatal(error: &anyhow::Error) {
+    clear_bar();
+    if JSON.load(Ordering::Relaxed) {
+        eprintln!(
+            r#"{{"event":"error","message":{}}}"#,
+            json_string(
+                &error
+                    .chain()
+                    .map(|cause| cause.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        );
+    } else {
+        eprintln!("Error: {:?}", error);
+    }
+}
+
+/// Write the summary of a run that finished: the number of lines read of `input_file`, and of the problems found of
+/// each kind.
+pub fn summary(input_file: &str, lines: usize, problems: &ProblemCounts) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    clear_bar();
+    if JSON.load(Ordering::Relaxed) {
+        let kinds: Vec<String> = problems
+            .kinds
+            .iter()
+            .map(|(kind, count)| format!("{}:{}", json_string(kind), count))
+            .collect();
+        eprintln!(
+            r#"{{"event":"summary","input_file":{},"lines":{},"errors":{},"warnings":{},"kinds":{{{}}}}}"#,
+            json_string(input_file),
+            lines,
+            problems.errors,
+            problems.warnings,
+            kinds.join(",")
+        );
+    } else {
+        let kinds: Vec<String> = problems
+            .kinds
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        eprintln!(
+            "Read {} line(s) of {}, finding {} error(s) and {} warning(s){}.",
+            lines,
+            input_file,
+            problems.errors,
+            problems.warnings,
+            if kinds.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", kinds.join(", "))
+            }
+        );
+    }
+}
+
+/// The progress of a run through its input, shown on stderr once it's taken long enough to be worth showing: as a bar
+/// if stderr is a terminal, or with `--log-format json`, as a JSON object every second.
+pub struct Progress {
+    /// Bytes read from the input so far, shared with the reader counting them.
+    bytes: Rc<Cell<u64>>,
+    /// The size of the input, if it's known, for the fraction of it read.
+    total: Option<u64>,
+    /// How often progress is shown, or `None` if it isn't.
+    interval: Option<Duration>,
+    started: Instant,
+    /// When progress is next shown.
+    next: Instant,
+}
+
+impl Progress {
+    /// The progress of reading an input of `total` bytes, if that's known, counted in `bytes`. It isn't shown with
+    /// `--quiet`, or as a bar unless stderr is a terminal.
+    pub fn new(bytes: Rc<Cell<u64>>, total: Option<u64>) -> Self {
+        let interval = if QUIET.load(Ordering::Relaxed) {
+            None
+        } else if JSON.load(Ordering::Relaxed) {
+            Some(JSON_INTERVAL)
+        } else {
+            io::stderr().is_terminal().then_some(BAR_INTERVAL)
+        };
+        let started = Instant::now();
+        Self {
+            bytes,
+            total,
+            interval,
+            started,
+            next: started + PROGRESS_DELAY,
+        }
+    }
+
+    /// Progress that isn't shown, such as that of a followed file, which never ends.
+    pub fn hidden() -> Self {
+        let started = Instant::now();
+        Self {
+            bytes: Rc::default(),
+            total: None,
+            interval: None,
+            started,
+            next: started,
+        }
+    }
+
+    /// Show the progress, `lines` lines in, if it's time to.
+    pub fn tick(&mut self, lines: usize) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        // the time is only looked at every so often, as this is called for every line
+        if !lines.is_multiple_of(64) {
+            return;
+        }
+        let now = Instant::now();
+        if now < self.next {
+            return;
+        }
+        self.next = now + interval;
+        let bytes = self.bytes.get();
+        let fraction = self
+            .total
+            .filter(|total| *total > 0)
+            .map(|total| (bytes as f64 / total as f64).min(1.0));
+        let mut err = io::stderr().lock();
+        // progress is best effort, and a run carries on if stderr can't be written to
+        let _ = if JSON.load(Ordering::Relaxed) {
+            writeln!(
+                err,
+                r#"{{"event":"progress","lines":{},"bytes":{},"total_bytes":{},"elapsed_ms":{}}}"#,
+                lines,
+                bytes,
+                self.total
+                    .map_or("null".to_owned(), |total| total.to_string()),
+                (now - self.started).as_millis()
+            )
+        } else {
+            let counted = format!("{} lines, {:.1} MB", lines, bytes as f64 / 1e6);
+            BAR_SHOWN.store(true, Ordering::Relaxed);
+            match fraction {
+                Some(fraction) => {
+                    let filled = (fraction * BAR_WIDTH as f64) as usize;
+                    write!(
+                        err,
+                        "\r\x1b[K[{}{}] {:3.0}% {}",
+                        "#".repeat(filled),
+                        "-".repeat(BAR_WIDTH - filled),
+                        fraction * 100.0,
+                        counted
+                    )
+                }
+                None => write!(err, "\r\x1b[K{}", counted),
+            }
+        };
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        clear_bar();
+    }
+}
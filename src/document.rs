@@ -0,0 +1,1322 @@
+//! Syntax files written as structured YAML or TOML documents, read with `--syntax-format yaml` or `toml`.
+//!
+//! A document lists `fields`, which apply to every line, and `records`, each a record type with a `name`, a
+//! `condition` saying which lines are of that type, and the `fields` of that type. Each field has the same keys as the
+//! columns of a CSV syntax file. Fields are applied in the order they appear in the document. A top-level
+//! `continues_if` joins lines into records as the CSV syntax file's row of the same name does.
+//!
+//! Only the parts of YAML and TOML that a syntax file needs are understood: YAML block mappings and sequences of plain
+//! or quoted scalars, and TOML tables, arrays of tables, inline tables, arrays, strings, integers and booleans.
+
+use anyhow::{bail, Context};
+use log::error;
+
+use crate::{
+    check_placeholders, constraints::parse_pad, expand_delimiter, expand_fixed_width, hex_color,
+    place_field_conditions, placeholder, resolve_anchors, totals, Anchor, Assembly, Case, Charset,
+    Condition, ControlCheck, Delimiter, DelimiterHighlightRecord, Encoding, FieldType,
+    FixedWidthHighlightRecord, Justify, Occurs, Pattern, PositionOptions, RecordList, RecordType,
+    ValuePattern, ValueStyle,
+};
+
+/// The languages a structured syntax file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Yaml,
+    Toml,
+}
+
+/// A value read from a document, with the line it started on.
+#[derive(Debug, Clone)]
+struct Node {
+    value: Value,
+    line: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Null,
+    Str(String),
+    Int(i64),
+    Table(Vec<(String, Node)>),
+    Array(Vec<Node>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "nothing",
+            Value::Str(_) => "a string",
+            Value::Int(_) => "a number",
+            Value::Table(_) => "a table",
+            Value::Array(_) => "a list",
+        }
+    }
+}
+
+/// Parse the structured syntax file `text` into rules, which are in delimiter mode if a delimiter is given, and how
+/// lines are assembled into records.
+pub fn parse(
+    text: &str,
+    format: DocumentFormat,
+    delimiter: Option<Delimiter>,
+) -> anyhow::Result<(RecordList, Assembly)> {
+    let root = match format {
+        DocumentFormat::Yaml => parse_yaml(text)?,
+        DocumentFormat::Toml => parse_toml(text, "Syntax file")?,
+    };
+    build(root, delimiter)
+}
+
+/// The line of each regex of a document that doesn't compile and why, and the document read without them.
+pub type Lint = (Vec<(u64, String)>, anyhow::Result<(RecordList, Assembly)>);
+
+/// Check every regex of the structured syntax file `text` as written, rather than stopping at the first that doesn't
+/// compile as [`parse`] does, returning the line of each that doesn't and why, with the rules and assembly of the
+/// document read with those regexes left out.
+pub fn lint(
+    text: &str,
+    format: DocumentFormat,
+    delimiter: Option<Delimiter>,
+) -> anyhow::Result<Lint> {
+    let mut root = match format {
+        DocumentFormat::Yaml => parse_yaml(text)?,
+        DocumentFormat::Toml => parse_toml(text, "Syntax file")?,
+    };
+    let mut invalid = Vec::new();
+    take_invalid_regexes(&mut root, &mut invalid);
+    Ok((invalid, build(root, delimiter)))
+}
+
+/// Take each regex that doesn't compile out of `node` and the nodes within it, adding its line and why to `invalid`.
+fn take_invalid_regexes(node: &mut Node, invalid: &mut Vec<(u64, String)>) {
+    match &mut node.value {
+        Value::Table(entries) => {
+            entries.retain(|(key, node)| {
+                let Value::Str(spec) = &node.value else {
+                    return true;
+                };
+                let error = match key.as_str() {
+                    "condition" | "continues_if" => Condition::parse(spec).err(),
+                    "pattern" => Pattern::parse(spec).err(),
+                    "value_pattern" => ValuePattern::parse(spec).err(),
+                    _ => None,
+                };
+                match error {
+                    Some(e) => {
+                        invalid.push((node.line, e.to_string()));
+                        false
+                    }
+                    None => true,
+                }
+            });
+            for (_, node) in entries {
+                take_invalid_regexes(node, invalid);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                take_invalid_regexes(item, invalid);
+            }
+        }
+        Value::Null | Value::Str(_) | Value::Int(_) => (),
+    }
+}
+
+/// A setting of a TOML document of settings, read by [`parse_toml_settings`].
+#[derive(Debug, Clone)]
+pub struct Setting {
+    pub key: String,
+    pub value: SettingValue,
+    /// The line the setting starts on, from 1.
+    pub line: u64,
+}
+
+/// The value of a [`Setting`]. Numbers and booleans are given as they're written.
+#[derive(Debug, Clone)]
+pub enum SettingValue {
+    Text(String),
+    List(Vec<SettingValue>),
+    Table(Vec<Setting>),
+}
+
+/// Parse `text`, a TOML document of settings such as a config file, which is a `file`, e.g. `Config file`, as its
+/// errors say. The same parts of TOML are understood as in syntax documents.
+pub fn parse_toml_settings(text: &str, file: &str) -> anyhow::Result<Vec<Setting>> {
+    Ok(match setting_value(parse_toml(text, file)?.value) {
+        SettingValue::Table(settings) => settings,
+        _ => Vec::new(),
+    })
+}
+
+fn setting_value(value: Value) -> SettingValue {
+    match value {
+        Value::Null => SettingValue::Text(String::new()),
+        Value::Str(text) => SettingValue::Text(text),
+        Value::Int(number) => SettingValue::Text(number.to_string()),
+        Value::Array(items) => {
+            SettingValue::List(items.into_iter().map(|n| setting_value(n.value)).collect())
+        }
+        Value::Table(entries) => SettingValue::Table(
+            entries
+                .into_iter()
+                .map(|(key, node)| Setting {
+                    key,
+                    line: node.line,
+                    value: setting_value(node.value),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// A field of the document, not yet checked.
+struct FieldNode {
+    entries: Vec<(String, Node)>,
+    line: u64,
+    record: Option<RecordType>,
+}
+
+fn build(root: Node, delimiter: Option<Delimiter>) -> anyhow::Result<(RecordList, Assembly)> {
+    let Value::Table(entries) = root.value else {
+        bail!("A syntax document should be a table of `fields` and `records`.");
+    };
+
+    let mut fields: Vec<FieldNode> = Vec::new();
+    let mut record_types: Vec<RecordType> = Vec::new();
+    let mut assembly = Assembly::default();
+    for (key, node) in entries {
+        match key.as_str() {
+            "fields" => fields.extend(field_nodes(node, None)?),
+            "continues_if" => {
+                assembly.continues_if = optional_string(&node, &key)?
+                    .map(|spec| Condition::parse(&spec))
+                    .transpose()
+                    .with_context(|| {
+                        format!(
+                            "Syntax file line {}: `continues_if` is invalid.",
+                            node.line
+                        )
+                    })?;
+                if assembly.continues_if.as_ref().is_some_and(Condition::is_field_test) {
+                    bail!(
+                        "Syntax file line {}: `continues_if` should be a regex, as lines are joined before their fields are found.",
+                        node.line
+                    );
+                }
+            }
+            "records" => {
+                for record in list(node, "records")? {
+                    let line = record.line;
+                    let Value::Table(entries) = record.value else {
+                        bail!(
+                            "Syntax file line {}: each of `records` should be a table, not {}.",
+                            line,
+                            record.value.kind()
+                        );
+                    };
+                    let mut name = None;
+                    let mut condition = None;
+                    let mut record_fields = None;
+                    for (key, node) in entries {
+                        match key.as_str() {
+                            "name" => name = Some(string(&node, &key)?),
+                            "condition" => {
+                                condition = optional_string(&node, &key)?
+                                    .map(|spec| Condition::parse(&spec))
+                                    .transpose()
+                                    .with_context(|| {
+                                        format!(
+                                            "Syntax file line {}: `condition` is invalid.",
+                                            node.line
+                                        )
+                                    })?
+                            }
+                            "fields" => record_fields = Some(node),
+                            _ => bail!(
+                                "Syntax file line {}: `{}` isn't a key of a record type.",
+                                node.line,
+                                key
+                            ),
+                        }
+                    }
+                    let Some(name) = name else {
+                        bail!("Syntax file line {}: record type has no `name`.", line);
+                    };
+                    if let Some(earlier) = record_types.iter().find(|t| t.name == name) {
+                        bail!(
+                            "Syntax file line {}: record type '{}' is already defined on line {}.",
+                            line,
+                            name,
+                            earlier.line
+                        );
+                    }
+                    let record_type = RecordType {
+                        name,
+                        condition,
+                        line,
+                    };
+                    match record_fields {
+                        Some(node) => fields.extend(field_nodes(node, Some(&record_type))?),
+                        None => error!(
+                            "Syntax file line {}: record type '{}' has no rules, so is ignored.",
+                            line, record_type.name
+                        ),
+                    }
+                    record_types.push(record_type);
+                }
+            }
+            _ => bail!(
+                "Syntax file line {}: `{}` isn't a key of a syntax document. Use `fields`, `records` and `continues_if`.",
+                node.line,
+                key
+            ),
+        }
+    }
+    // fields are applied in the order they are written
+    fields.sort_by_key(|f| f.line);
+
+    let mut records = match delimiter {
+        None => RecordList::FixedWidth(
+            fields
+                .into_iter()
+                .map(fixed_width_records)
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+        ),
+        Some(delimiter) => RecordList::Delimiter(
+            delimiter,
+            fields
+                .into_iter()
+                .map(delimiter_records)
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+        ),
+    };
+    resolve_anchors(&mut records)?;
+    check_placeholders(&records)?;
+    totals::check_fields(&records)?;
+    place_field_conditions(&mut records, PositionOptions::default())?;
+    Ok((records, assembly))
+}
+
+fn list(node: Node, key: &str) -> anyhow::Result<Vec<Node>> {
+    match node.value {
+        Value::Array(items) => Ok(items),
+        Value::Null => Ok(Vec::new()),
+        other => bail!(
+            "Syntax file line {}: `{}` should be a list, not {}.",
+            node.line,
+            key,
+            other.kind()
+        ),
+    }
+}
+
+fn field_nodes(node: Node, record: Option<&RecordType>) -> anyhow::Result<Vec<FieldNode>> {
+    list(node, "fields")?
+        .into_iter()
+        .map(|field| match field.value {
+            Value::Table(entries) => Ok(FieldNode {
+                entries,
+                line: field.line,
+                record: record.cloned(),
+            }),
+            other => bail!(
+                "Syntax file line {}: each of `fields` should be a table, not {}.",
+                field.line,
+                other.kind()
+            ),
+        })
+        .collect()
+}
+
+fn string(node: &Node, key: &str) -> anyhow::Result<String> {
+    match &node.value {
+        Value::Str(s) => Ok(s.clone()),
+        Value::Int(i) => Ok(i.to_string()),
+        other => bail!(
+            "Syntax file line {}: `{}` should be a string, not {}.",
+            node.line,
+            key,
+            other.kind()
+        ),
+    }
+}
+
+/// A string that may be left empty, as an empty CSV column is.
+fn optional_string(node: &Node, key: &str) -> anyhow::Result<Option<String>> {
+    match &node.value {
+        Value::Null => Ok(None),
+        _ => Ok(Some(string(node, key)?).filter(|s| !s.is_empty())),
+    }
+}
+
+fn number(node: &Node, key: &str) -> anyhow::Result<Option<usize>> {
+    match &node.value {
+        Value::Null => Ok(None),
+        Value::Int(i) if *i >= 0 => Ok(Some(*i as usize)),
+        Value::Str(s) if s.parse::<usize>().is_ok() => Ok(s.parse().ok()),
+        _ => bail!(
+            "Syntax file line {}: `{}` should be a whole number.",
+            node.line,
+            key
+        ),
+    }
+}
+
+/// The value of a key placing a field, such as `start`: a whole number, or the name of the capture group a `${name}`
+/// placeholder takes it from.
+fn number_or_placeholder(
+    node: &Node,
+    key: &str,
+) -> anyhow::Result<(Option<usize>, Option<String>)> {
+    match &node.value {
+        Value::Str(s) if placeholder(s).is_some() => Ok((None, placeholder(s).map(str::to_owned))),
+        _ => Ok((number(node, key)?, None)),
+    }
+}
+
+/// The value of a yes-or-no key such as `from_end` or `sensitive`, which is `false` if it's empty.
+fn flag(node: &Node, key: &str) -> anyhow::Result<bool> {
+    match &node.value {
+        Value::Null => Ok(false),
+        Value::Str(s) if s == "true" => Ok(true),
+        Value::Str(s) if s == "false" => Ok(false),
+        _ => bail!(
+            "Syntax file line {}: `{}` should be `true` or `false`.",
+            node.line,
+            key
+        ),
+    }
+}
+
+/// The keys every field can have, whatever the mode.
+struct Common {
+    name: String,
+    condition: Option<Condition>,
+    charset: Option<Charset>,
+    case: Option<Case>,
+    pattern: Option<Pattern>,
+    justify: Option<Justify>,
+    pad: Option<char>,
+    encoding: Option<Encoding>,
+    field_type: Option<FieldType>,
+    color: Option<String>,
+    value_pattern: Option<ValuePattern>,
+    value_style: Option<ValueStyle>,
+    sensitive: bool,
+    check: Option<ControlCheck>,
+}
+
+/// Read the keys of `field` every field can have, passing the others to `position`.
+fn common(
+    field: &FieldNode,
+    mut position: impl FnMut(&str, &Node) -> anyhow::Result<bool>,
+) -> anyhow::Result<Common> {
+    let mut name = None;
+    let mut common = Common {
+        name: String::new(),
+        condition: None,
+        charset: None,
+        case: None,
+        pattern: None,
+        justify: None,
+        pad: None,
+        encoding: None,
+        field_type: None,
+        color: None,
+        value_pattern: None,
+        value_style: None,
+        sensitive: false,
+        check: None,
+    };
+    for (key, node) in &field.entries {
+        let context = || format!("Syntax file line {}: `{}` is invalid.", node.line, key);
+        match key.as_str() {
+            "name" => name = Some(string(node, key)?),
+            "condition" => {
+                common.condition = optional_string(node, key)?
+                    .map(|spec| Condition::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "charset" => {
+                common.charset = optional_string(node, key)?
+                    .map(|spec| Charset::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "case" => {
+                common.case = match optional_string(node, key)?.as_deref() {
+                    None => None,
+                    Some("upper") => Some(Case::Upper),
+                    Some("lower") => Some(Case::Lower),
+                    Some("any") => Some(Case::Any),
+                    Some(other) => bail!(
+                    "Syntax file line {}: `case` is '{}', but should be `upper`, `lower` or `any`.",
+                    node.line,
+                    other
+                ),
+                }
+            }
+            "pattern" => {
+                common.pattern = optional_string(node, key)?
+                    .map(|spec| Pattern::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "justify" => {
+                common.justify = match optional_string(node, key)?.as_deref() {
+                    None => None,
+                    Some("left") => Some(Justify::Left),
+                    Some("right") => Some(Justify::Right),
+                    Some(other) => bail!(
+                        "Syntax file line {}: `justify` is '{}', but should be `left` or `right`.",
+                        node.line,
+                        other
+                    ),
+                }
+            }
+            "pad" => {
+                common.pad = optional_string(node, key)?
+                    .map(|spec| parse_pad(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "encoding" => {
+                common.encoding = optional_string(node, key)?
+                    .map(|name| Encoding::parse(&name))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "type" => {
+                common.field_type = optional_string(node, key)?
+                    .map(|spec| FieldType::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "color" => {
+                common.color = optional_string(node, key)?
+                    .map(|color| hex_color(&color))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "value_pattern" => {
+                common.value_pattern = optional_string(node, key)?
+                    .map(|spec| ValuePattern::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "value_style" => {
+                common.value_style = optional_string(node, key)?
+                    .map(|spec| ValueStyle::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            "sensitive" => common.sensitive = flag(node, key)?,
+            "check" => {
+                common.check = optional_string(node, key)?
+                    .map(|spec| ControlCheck::parse(&spec))
+                    .transpose()
+                    .with_context(context)?
+            }
+            _ => {
+                if !position(key, node)? {
+                    bail!(
+                        "Syntax file line {}: `{}` isn't a key of a field here.",
+                        node.line,
+                        key
+                    );
+                }
+            }
+        }
+    }
+    match name {
+        Some(name) => common.name = name,
+        None => bail!("Syntax file line {}: field has no `name`.", field.line),
+    }
+    Ok(common)
+}
+
+/// The rules of `field`: one, or one for each occurrence if it has `occurs`.
+fn fixed_width_records(field: FieldNode) -> anyhow::Result<Vec<FixedWidthHighlightRecord>> {
+    let (mut start, mut start_capture, mut start_anchor) = (None, None, None);
+    let (mut length, mut length_capture) = (None, None);
+    let mut from_end = false;
+    let mut occurs = Occurs::default();
+    let common = common(&field, |key, node| {
+        match key {
+            "start" => match &node.value {
+                Value::Str(s) if Anchor::parse(s).is_some() => {
+                    start_anchor = Anchor::parse(s).transpose().with_context(|| {
+                        format!(
+                            "Syntax file line {}: `start` isn't a valid `after:` anchor.",
+                            node.line
+                        )
+                    })?
+                }
+                _ => (start, start_capture) = number_or_placeholder(node, key)?,
+            },
+            "length" => (length, length_capture) = number_or_placeholder(node, key)?,
+            "from_end" => from_end = flag(node, key)?,
+            "occurs" => occurs.count = number(node, key)?,
+            "stride" => occurs.stride = number(node, key)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let record = FixedWidthHighlightRecord {
+        line: field.line,
+        start,
+        length,
+        start_capture,
+        length_capture,
+        start_anchor,
+        from_end,
+        name: common.name.into(),
+        condition: common.condition,
+        charset: common.charset,
+        case: common.case,
+        pattern: common.pattern,
+        justify: common.justify,
+        pad: common.pad,
+        encoding: common.encoding,
+        field_type: common.field_type,
+        color: common.color,
+        value_pattern: common.value_pattern,
+        value_style: common.value_style,
+        sensitive: common.sensitive,
+        check: common.check,
+        record: field.record,
+        type_shift: 0,
+    };
+    expand_fixed_width(record, occurs)
+}
+
+/// The rules of `field`: one, or one for each occurrence if it has `occurs`.
+fn delimiter_records(field: FieldNode) -> anyhow::Result<Vec<DelimiterHighlightRecord>> {
+    let (mut number_of_field, mut field_capture) = (None, None);
+    let mut occurs = Occurs::default();
+    let common = common(&field, |key, node| {
+        match key {
+            "field" => (number_of_field, field_capture) = number_or_placeholder(node, key)?,
+            "occurs" => occurs.count = number(node, key)?,
+            "stride" => occurs.stride = number(node, key)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    })?;
+    let record = DelimiterHighlightRecord {
+        line: field.line,
+        field: number_of_field,
+        field_capture,
+        name: common.name.into(),
+        condition: common.condition,
+        charset: common.charset,
+        case: common.case,
+        pattern: common.pattern,
+        justify: common.justify,
+        pad: common.pad,
+        encoding: common.encoding,
+        field_type: common.field_type,
+        color: common.color,
+        value_pattern: common.value_pattern,
+        value_style: common.value_style,
+        sensitive: common.sensitive,
+        check: common.check,
+        record: field.record,
+        type_shift: 0,
+    };
+    expand_delimiter(record, occurs)
+}
+
+/// A line of a YAML document, without its indentation or comment.
+struct YamlLine {
+    indent: usize,
+    text: String,
+    line: u64,
+}
+
+fn parse_yaml(text: &str) -> anyhow::Result<Node> {
+    let mut lines = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx as u64 + 1;
+        let content = strip_yaml_comment(raw);
+        let trimmed = content.trim_start_matches(' ');
+        if trimmed.trim().is_empty() || trimmed.trim_end() == "---" || trimmed.trim_end() == "..." {
+            continue;
+        }
+        if trimmed.starts_with('\t') {
+            bail!("Syntax file line {}: indent with spaces, not tabs.", line);
+        }
+        lines.push(YamlLine {
+            indent: content.len() - trimmed.len(),
+            text: trimmed.trim_end().to_owned(),
+            line,
+        });
+    }
+    if lines.is_empty() {
+        return Ok(Node {
+            value: Value::Null,
+            line: 1,
+        });
+    }
+
+    let mut pos = 0;
+    let indent = lines[0].indent;
+    let root = yaml_block(&mut lines, &mut pos, indent)?;
+    if let Some(extra) = lines.get(pos) {
+        bail!("Syntax file line {}: unexpected indentation.", extra.line);
+    }
+    Ok(root)
+}
+
+/// `raw` without any comment, which starts with a `#` at the start of the line or after a space, outside quotes.
+fn strip_yaml_comment(raw: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in raw.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' && previous.is_whitespace() => return &raw[..i],
+            None => (),
+        }
+        previous = c;
+    }
+    raw
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Parse the mapping or sequence whose lines start at `indent`.
+fn yaml_block(lines: &mut [YamlLine], pos: &mut usize, indent: usize) -> anyhow::Result<Node> {
+    let line = lines[*pos].line;
+    if is_sequence_item(&lines[*pos].text) {
+        let mut items = Vec::new();
+        while *pos < lines.len()
+            && lines[*pos].indent == indent
+            && is_sequence_item(&lines[*pos].text)
+        {
+            let item_line = lines[*pos].line;
+            let rest = lines[*pos].text[1..].trim_start().to_owned();
+            if rest.is_empty() {
+                *pos += 1;
+                match lines.get(*pos) {
+                    Some(next) if next.indent > indent => {
+                        let inner = next.indent;
+                        items.push(yaml_block(lines, pos, inner)?);
+                    }
+                    _ => items.push(Node {
+                        value: Value::Null,
+                        line: item_line,
+                    }),
+                }
+            } else if reject_flow_collection(&rest, item_line)
+                .and_then(|_| split_yaml_key(&rest))?
+                .is_some()
+            {
+                // `- key: value` starts a mapping, whose other keys line up with `key`
+                let offset = lines[*pos].text.len() - rest.len();
+                lines[*pos].indent += offset;
+                lines[*pos].text = rest;
+                let inner = lines[*pos].indent;
+                items.push(yaml_block(lines, pos, inner)?);
+            } else {
+                items.push(Node {
+                    value: yaml_scalar(&rest, item_line)?,
+                    line: item_line,
+                });
+                *pos += 1;
+            }
+        }
+        return Ok(Node {
+            value: Value::Array(items),
+            line,
+        });
+    }
+
+    let mut entries: Vec<(String, Node)> = Vec::new();
+    while *pos < lines.len() && lines[*pos].indent == indent {
+        let entry_line = lines[*pos].line;
+        reject_flow_collection(&lines[*pos].text, entry_line)?;
+        let Some((key, rest)) = split_yaml_key(&lines[*pos].text)? else {
+            bail!(
+                "Syntax file line {}: expected `key: value`, found `{}`.",
+                entry_line,
+                lines[*pos].text
+            );
+        };
+        if entries.iter().any(|(k, _)| *k == key) {
+            bail!("Syntax file line {}: `{}` is given twice.", entry_line, key);
+        }
+        *pos += 1;
+        let node = if rest.is_empty() {
+            match lines.get(*pos) {
+                // a sequence may sit at the same indentation as its key
+                Some(next)
+                    if next.indent > indent
+                        || (next.indent == indent && is_sequence_item(&next.text)) =>
+                {
+                    let inner = next.indent;
+                    yaml_block(lines, pos, inner)?
+                }
+                _ => Node {
+                    value: Value::Null,
+                    line: entry_line,
+                },
+            }
+        } else {
+            Node {
+                value: yaml_scalar(&rest, entry_line)?,
+                line: entry_line,
+            }
+        };
+        entries.push((key, node));
+    }
+    if let Some(next) = lines.get(*pos) {
+        if next.indent > indent {
+            bail!("Syntax file line {}: unexpected indentation.", next.line);
+        }
+    }
+    Ok(Node {
+        value: Value::Table(entries),
+        line,
+    })
+}
+
+/// Fail on `text` if it's a flow mapping (`{a: 1}`) or sequence (`[1, 2]`), which syntax documents are too simple a
+/// YAML to read, saying how to write it as a block instead.
+fn reject_flow_collection(text: &str, line: u64) -> anyhow::Result<()> {
+    if text.starts_with('{') {
+        bail!(
+            "Syntax file line {}: flow mappings such as `{}` aren't supported in syntax documents. Write each `key: value` on a line of its own, indented under the key they belong to (or after `- ` in a list), or quote the value if it is a string.",
+            line,
+            text
+        );
+    }
+    if text.starts_with('[') {
+        bail!(
+            "Syntax file line {}: flow sequences such as `{}` aren't supported in syntax documents. Write each item on a line of its own, starting with `- `, or quote the value if it is a string.",
+            line,
+            text
+        );
+    }
+    Ok(())
+}
+
+/// Split `text` into a key and the rest of the line, if it is a `key: value` line.
+fn split_yaml_key(text: &str) -> anyhow::Result<Option<(String, String)>> {
+    let (key, rest) = if let Some(quote) = text.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let Some(end) = text[1..].find(quote) else {
+            return Ok(None);
+        };
+        let after = &text[end + 2..];
+        if !(after == ":" || after.starts_with(": ")) {
+            return Ok(None);
+        }
+        (text[1..end + 1].to_owned(), after[1..].trim())
+    } else {
+        let end = match text.find(": ") {
+            Some(end) => end,
+            None if text.ends_with(':') => text.len() - 1,
+            None => return Ok(None),
+        };
+        (text[..end].trim().to_owned(), text[end + 1..].trim())
+    };
+    Ok(Some((key, rest.to_owned())))
+}
+
+fn yaml_scalar(text: &str, line: u64) -> anyhow::Result<Value> {
+    if let Some(inner) = text.strip_prefix('"') {
+        let Some(inner) = inner.strip_suffix('"') else {
+            bail!("Syntax file line {}: unterminated string.", line);
+        };
+        let mut value = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            value.push(match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                Some('/') => '/',
+                Some('0') => '\0',
+                Some(other) => bail!(
+                    "Syntax file line {}: unknown escape `\\{}` in string.",
+                    line,
+                    other
+                ),
+                None => bail!("Syntax file line {}: unterminated string.", line),
+            });
+        }
+        return Ok(Value::Str(value));
+    }
+    if let Some(inner) = text.strip_prefix('\'') {
+        let Some(inner) = inner.strip_suffix('\'') else {
+            bail!("Syntax file line {}: unterminated string.", line);
+        };
+        return Ok(Value::Str(inner.replace("''", "'")));
+    }
+    reject_flow_collection(text, line)?;
+    if text.starts_with(['|', '>', '&', '*', '!']) {
+        bail!(
+            "Syntax file line {}: `{}` isn't supported in syntax documents. Quote the value if it is a string.",
+            line,
+            text
+        );
+    }
+    Ok(match text {
+        "~" | "null" => Value::Null,
+        _ => Value::Str(text.to_owned()),
+    })
+}
+
+/// Reads a TOML document.
+struct TomlParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: u64,
+    /// What the document is, e.g. `Syntax file`, for its errors.
+    file: &'a str,
+}
+
+/// Parse the TOML document `text`, which is a `file`, e.g. `Syntax file`, as its errors say.
+fn parse_toml(text: &str, file: &str) -> anyhow::Result<Node> {
+    let mut parser = TomlParser {
+        chars: text.chars().peekable(),
+        line: 1,
+        file,
+    };
+    let mut root: Vec<(String, Node)> = Vec::new();
+    // the keys of the table that `key = value` lines go into
+    let mut current: Vec<String> = Vec::new();
+    loop {
+        parser.skip_blank_lines();
+        let line = parser.line;
+        match parser.chars.peek() {
+            None => break,
+            Some('[') => {
+                parser.chars.next();
+                let array = parser.chars.next_if_eq(&'[').is_some();
+                let path = parser.key()?;
+                parser.expect(']')?;
+                if array {
+                    parser.expect(']')?;
+                }
+                parser.end_of_line()?;
+
+                let (last, parents) = path.split_last().expect("a key has a part");
+                let parent = table_at(&mut root, parents, line, parser.file)?;
+                match parent.iter_mut().find(|(k, _)| k == last) {
+                    Some((_, node)) if array => match &mut node.value {
+                        Value::Array(items) => items.push(Node {
+                            value: Value::Table(Vec::new()),
+                            line,
+                        }),
+                        _ => bail!(
+                            "{} line {}: `{}` isn't a list of tables.",
+                            parser.file,
+                            line,
+                            last
+                        ),
+                    },
+                    Some(_) => bail!(
+                        "{} line {}: table `{}` is defined twice.",
+                        parser.file,
+                        line,
+                        last
+                    ),
+                    None => {
+                        let table = Node {
+                            value: Value::Table(Vec::new()),
+                            line,
+                        };
+                        let value = if array {
+                            Value::Array(vec![table])
+                        } else {
+                            table.value
+                        };
+                        parent.push((last.clone(), Node { value, line }));
+                    }
+                }
+                current = path;
+            }
+            Some(_) => {
+                let path = parser.key()?;
+                parser.expect('=')?;
+                let value = parser.value()?;
+                parser.end_of_line()?;
+
+                let (last, parents) = path.split_last().expect("a key has a part");
+                let mut full = current.clone();
+                full.extend(parents.iter().cloned());
+                let table = table_at(&mut root, &full, line, parser.file)?;
+                if table.iter().any(|(k, _)| k == last) {
+                    bail!("{} line {}: `{}` is given twice.", parser.file, line, last);
+                }
+                table.push((last.clone(), value));
+            }
+        }
+    }
+    Ok(Node {
+        value: Value::Table(root),
+        line: 1,
+    })
+}
+
+/// The table at `path` from `root`, going into the last table of any list of tables on the way, and creating tables
+/// that don't exist yet.
+fn table_at<'a>(
+    root: &'a mut Vec<(String, Node)>,
+    path: &[String],
+    line: u64,
+    file: &str,
+) -> anyhow::Result<&'a mut Vec<(String, Node)>> {
+    let mut table = root;
+    for key in path {
+        let index = match table.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                table.push((
+                    key.clone(),
+                    Node {
+                        value: Value::Table(Vec::new()),
+                        line,
+                    },
+                ));
+                table.len() - 1
+            }
+        };
+        let node = &mut table[index].1;
+        let value = match &mut node.value {
+            Value::Array(items) => match items.last_mut() {
+                Some(last) => &mut last.value,
+                None => bail!("{} line {}: `{}` isn't a table.", file, line, key),
+            },
+            value => value,
+        };
+        table = match value {
+            Value::Table(entries) => entries,
+            _ => bail!("{} line {}: `{}` isn't a table.", file, line, key),
+        };
+    }
+    Ok(table)
+}
+
+impl TomlParser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+    }
+
+    fn skip_comment(&mut self) {
+        if self.chars.peek() == Some(&'#') {
+            while self.chars.next_if(|c| *c != '\n').is_some() {}
+        }
+    }
+
+    /// Skip spaces, comments and line ends, as are allowed between the values of a list.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.chars.peek() {
+                Some('\n') | Some('\r') => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        self.skip_spaces();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!(
+                "{} line {}: expected `{}`, found `{}`.",
+                self.file,
+                self.line,
+                expected,
+                c
+            ),
+            None => bail!(
+                "{} line {}: expected `{}`, but the file ended.",
+                self.file,
+                self.line,
+                expected
+            ),
+        }
+    }
+
+    fn end_of_line(&mut self) -> anyhow::Result<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        self.chars.next_if_eq(&'\r');
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(c) => bail!(
+                "{} line {}: expected the end of the line, found `{}`.",
+                self.file,
+                self.line,
+                c
+            ),
+        }
+    }
+
+    /// A key, which may be dotted, as its parts.
+    fn key(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.chars.peek() {
+                Some('"') => {
+                    self.next();
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.next();
+                    self.literal_string()?
+                }
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .chars
+                        .next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                    {
+                        part.push(c);
+                    }
+                    if part.is_empty() {
+                        bail!("{} line {}: expected a key.", self.file, self.line);
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.chars.next_if_eq(&'.').is_none() {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Node> {
+        self.skip_spaces();
+        let line = self.line;
+        let value = match self.chars.peek() {
+            Some('"') => {
+                self.next();
+                if self.chars.next_if_eq(&'"').is_some() {
+                    if self.chars.next_if_eq(&'"').is_some() {
+                        self.multiline_string("\"\"\"")?
+                    } else {
+                        String::new()
+                    }
+                } else {
+                    self.basic_string()?
+                }
+            }
+            .into_value(),
+            Some('\'') => {
+                self.next();
+                if self.chars.next_if_eq(&'\'').is_some() {
+                    if self.chars.next_if_eq(&'\'').is_some() {
+                        self.multiline_string("'''")?
+                    } else {
+                        String::new()
+                    }
+                } else {
+                    self.literal_string()?
+                }
+            }
+            .into_value(),
+            Some('[') => {
+                self.next();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank_lines();
+                    if self.chars.next_if_eq(&']').is_some() {
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank_lines();
+                    if self.chars.next_if_eq(&',').is_none() {
+                        self.skip_blank_lines();
+                        self.expect(']')?;
+                        break;
+                    }
+                }
+                Value::Array(items)
+            }
+            Some('{') => {
+                self.next();
+                let mut entries: Vec<(String, Node)> = Vec::new();
+                self.skip_spaces();
+                if self.chars.next_if_eq(&'}').is_none() {
+                    loop {
+                        let path = self.key()?;
+                        if path.len() > 1 {
+                            bail!(
+                                "{} line {}: dotted keys aren't supported in inline tables.",
+                                self.file,
+                                self.line
+                            );
+                        }
+                        self.expect('=')?;
+                        let value = self.value()?;
+                        if entries.iter().any(|(k, _)| *k == path[0]) {
+                            bail!(
+                                "{} line {}: `{}` is given twice.",
+                                self.file,
+                                self.line,
+                                path[0]
+                            );
+                        }
+                        entries.push((path[0].clone(), value));
+                        self.skip_spaces();
+                        if self.chars.next_if_eq(&',').is_none() {
+                            self.expect('}')?;
+                            break;
+                        }
+                    }
+                }
+                Value::Table(entries)
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.' | ':')
+                }) {
+                    word.push(c);
+                }
+                match word.replace('_', "").parse() {
+                    Ok(number) => Value::Int(number),
+                    // booleans are only used by keys that read them as words
+                    Err(_) if word == "true" || word == "false" => Value::Str(word),
+                    Err(_) if word.is_empty() => {
+                        bail!("{} line {}: expected a value.", self.file, line)
+                    }
+                    Err(_) => bail!(
+                        "{} line {}: `{}` isn't supported. Quote it if it is a string.",
+                        self.file,
+                        line,
+                        word
+                    ),
+                }
+            }
+        };
+        Ok(Node { value, line })
+    }
+
+    /// The rest of a `"` string.
+    fn basic_string(&mut self) -> anyhow::Result<String> {
+        let line = self.line;
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.escape()?),
+                Some('\n') | None => {
+                    bail!("{} line {}: unterminated string.", self.file, line)
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    /// The rest of a `'` string, which has no escapes.
+    fn literal_string(&mut self) -> anyhow::Result<String> {
+        let line = self.line;
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(value),
+                Some('\n') | None => {
+                    bail!("{} line {}: unterminated string.", self.file, line)
+                }
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    /// The rest of a string that ends with `end`, `"""` or `'''`, less the line end straight after its start.
+    fn multiline_string(&mut self, end: &str) -> anyhow::Result<String> {
+        let line = self.line;
+        self.chars.next_if_eq(&'\r');
+        if self.chars.peek() == Some(&'\n') {
+            self.next();
+        }
+        let mut value = String::new();
+        while !value.ends_with(end) {
+            match self.next() {
+                Some('\\') if end == "\"\"\"" => value.push(self.escape()?),
+                Some(c) => value.push(c),
+                None => bail!("{} line {}: unterminated string.", self.file, line),
+            }
+        }
+        value.truncate(value.len() - end.len());
+        Ok(value)
+    }
+
+    fn escape(&mut self) -> anyhow::Result<char> {
+        Ok(match self.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+                let digits: String = (0..if u == 'u' { 4 } else { 8 })
+                    .filter_map(|_| self.next())
+                    .collect();
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .with_context(|| {
+                        format!(
+                            "{} line {}: invalid escape `\\{}{}`.",
+                            self.file, self.line, u, digits
+                        )
+                    })?
+            }
+            Some(other) => bail!(
+                "{} line {}: unknown escape `\\{}` in string.",
+                self.file,
+                self.line,
+                other
+            ),
+            None => bail!("{} line {}: unterminated string.", self.file, self.line),
+        })
+    }
+}
+
+trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::Str(self)
+    }
+}
@@ -0,0 +1,773 @@
+//! The `edit` subcommand: a terminal editor for building a fixed width syntax file directly over lines of real data.
+//! A selection is moved and resized over a sample line, and named to make it a field.
+
+use std::{
+    io::{BufReader, Read, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use regex::Regex;
+
+use crate::{
+    open_input, read_syntax_file, Args, BoundedLines, Case, Charset, ControlCheck, EditArgs,
+    Encoding, FieldType, Justify, Pattern, RecordList, ValuePattern, ValueStyle,
+};
+
+/// The most lines of the input loaded as samples.
+const MAX_SAMPLE_LINES: usize = 10_000;
+
+/// Background colours (from the 256 colour palette) given to fields in turn.
+const FIELD_COLORS: [u8; 6] = [153, 194, 223, 189, 225, 230];
+
+/// A field being edited. Positions are 0-based.
+struct Field {
+    start: usize,
+    length: usize,
+    name: String,
+    condition: Option<String>,
+    charset: Option<Charset>,
+    case: Option<Case>,
+    pattern: Option<Pattern>,
+    justify: Option<Justify>,
+    pad: Option<char>,
+    encoding: Option<Encoding>,
+    field_type: Option<FieldType>,
+    color: Option<String>,
+    value_pattern: Option<ValuePattern>,
+    value_style: Option<ValueStyle>,
+    sensitive: bool,
+    check: Option<ControlCheck>,
+}
+
+impl Field {
+    fn end(&self) -> usize {
+        self.start + self.length
+    }
+
+    fn contains(&self, col: usize) -> bool {
+        self.start <= col && col < self.end()
+    }
+
+    fn applies_to(&self, line: &str) -> bool {
+        match &self.condition {
+            // an invalid condition is shown everywhere, rather than hiding the field
+            Some(condition) => Regex::new(condition).map_or(true, |re| re.is_match(line)),
+            None => true,
+        }
+    }
+}
+
+/// A key press, as far as the editor cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Char(char),
+}
+
+/// What text typed at the prompt is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Name,
+    Condition,
+}
+
+/// Everything the editor shows, and the result of each key press on it.
+struct Editor {
+    lines: Vec<String>,
+    fields: Vec<Field>,
+    line: usize,
+    selection_start: usize,
+    selection_length: usize,
+    /// The first column shown, when lines are wider than the terminal.
+    scroll: usize,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+    modified: bool,
+    /// Set after `q` with unsaved changes, so a second `q` quits anyway.
+    confirm_quit: bool,
+    index_base: usize,
+}
+
+/// What the editor should do after a key press.
+enum Action {
+    Continue,
+    Save,
+    Quit,
+}
+
+impl Editor {
+    fn line_width(&self) -> usize {
+        self.lines.get(self.line).map_or(0, |l| l.chars().count())
+    }
+
+    /// The field under the start of the selection which applies to the current line, if any.
+    fn field_at_selection(&self) -> Option<usize> {
+        let line = self.lines.get(self.line).map_or("", |l| l.as_str());
+        self.fields
+            .iter()
+            .position(|f| f.contains(self.selection_start) && f.applies_to(line))
+    }
+
+    fn handle(&mut self, key: Key) -> Action {
+        if let Some((prompt, mut text)) = self.prompt.take() {
+            match key {
+                Key::Enter => self.finish_prompt(prompt, text),
+                Key::Escape => self.status = "Cancelled.".to_owned(),
+                Key::Backspace => {
+                    text.pop();
+                    self.prompt = Some((prompt, text));
+                }
+                Key::Char(c) => {
+                    text.push(c);
+                    self.prompt = Some((prompt, text));
+                }
+                _ => self.prompt = Some((prompt, text)),
+            }
+            return Action::Continue;
+        }
+
+        if key != Key::Char('q') {
+            self.confirm_quit = false;
+        }
+        self.status.clear();
+        let max_start = self.line_width().max(self.selection_start + 1);
+        match key {
+            Key::Left | Key::Char('h') => {
+                self.selection_start = self.selection_start.saturating_sub(1)
+            }
+            Key::Right | Key::Char('l') => {
+                self.selection_start = (self.selection_start + 1).min(max_start - 1)
+            }
+            Key::Up | Key::Char('k') => self.line = self.line.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.line = (self.line + 1).min(self.lines.len() - 1),
+            Key::Char('[') => self.selection_length = (self.selection_length - 1).max(1),
+            Key::Char(']') => self.selection_length += 1,
+            Key::Char('f') => match self.field_at_selection() {
+                Some(i) => {
+                    self.selection_start = self.fields[i].start;
+                    self.selection_length = self.fields[i].length;
+                }
+                None => self.status = "There is no field here.".to_owned(),
+            },
+            Key::Tab => self.jump_to_gap(),
+            Key::Enter => {
+                let name = self
+                    .exact_field()
+                    .map_or(String::new(), |i| self.fields[i].name.clone());
+                self.prompt = Some((Prompt::Name, name));
+            }
+            Key::Char('c') => match self.exact_field() {
+                Some(i) => {
+                    let condition = self.fields[i].condition.clone().unwrap_or_default();
+                    self.prompt = Some((Prompt::Condition, condition));
+                }
+                None => {
+                    self.status =
+                        "Select a field (press f over it) to give it a condition.".to_owned()
+                }
+            },
+            Key::Char('x') => match self.field_at_selection() {
+                Some(i) => {
+                    let field = self.fields.remove(i);
+                    self.status = format!("Removed '{}'.", field.name);
+                    self.modified = true;
+                }
+                None => self.status = "There is no field here.".to_owned(),
+            },
+            Key::Char('w') => return Action::Save,
+            Key::Char('q') => {
+                if self.modified && !self.confirm_quit {
+                    self.status = "There are unsaved changes. Press w to save, or q again to quit without saving.".to_owned();
+                    self.confirm_quit = true;
+                } else {
+                    return Action::Quit;
+                }
+            }
+            _ => (),
+        }
+        Action::Continue
+    }
+
+    /// The field exactly covering the selection, if any.
+    fn exact_field(&self) -> Option<usize> {
+        self.fields
+            .iter()
+            .position(|f| f.start == self.selection_start && f.length == self.selection_length)
+    }
+
+    fn finish_prompt(&mut self, prompt: Prompt, text: String) {
+        let text = text.trim().to_owned();
+        match prompt {
+            Prompt::Name => {
+                if text.is_empty() {
+                    self.status = "A field needs a name.".to_owned();
+                    return;
+                }
+                match self.exact_field() {
+                    Some(i) => {
+                        self.status = format!("Renamed '{}' to '{}'.", self.fields[i].name, text);
+                        self.fields[i].name = text;
+                    }
+                    None => {
+                        let overlaps = self.fields.iter().any(|f| {
+                            f.condition.is_none()
+                                && f.start < self.selection_start + self.selection_length
+                                && self.selection_start < f.end()
+                        });
+                        self.status = if overlaps {
+                            format!("Added '{}', which overlaps another field.", text)
+                        } else {
+                            format!("Added '{}'.", text)
+                        };
+                        self.fields.push(Field {
+                            start: self.selection_start,
+                            length: self.selection_length,
+                            name: text,
+                            condition: None,
+                            charset: None,
+                            case: None,
+                            pattern: None,
+                            justify: None,
+                            pad: None,
+                            encoding: None,
+                            field_type: None,
+                            color: None,
+                            value_pattern: None,
+                            value_style: None,
+                            sensitive: false,
+                            check: None,
+                        });
+                        self.selection_start += self.selection_length;
+                    }
+                }
+            }
+            Prompt::Condition => {
+                let Some(i) = self.exact_field() else {
+                    return;
+                };
+                if let Err(e) = Regex::new(&text) {
+                    self.status = format!("That isn't a valid regex: {}", e);
+                    return;
+                }
+                self.fields[i].condition = (!text.is_empty()).then_some(text);
+                self.status = format!("Updated the condition of '{}'.", self.fields[i].name);
+            }
+        }
+        self.modified = true;
+    }
+
+    /// Select the gap between fields at or after the selection on the current line, or the one after it if that gap
+    /// is already selected, wrapping around to the start of the line.
+    fn jump_to_gap(&mut self) {
+        let line = self.lines.get(self.line).map_or("", |l| l.as_str());
+        let width = self.line_width();
+        let covered = |col: usize| {
+            self.fields
+                .iter()
+                .any(|f| f.contains(col) && f.applies_to(line))
+        };
+        let gap_at = |col: usize| {
+            let end = (col..width).find(|c| covered(*c)).unwrap_or(width);
+            (col, end - col)
+        };
+        let from = self.selection_start.min(width);
+        let mut gaps = (from..width)
+            .chain(0..from)
+            .filter(|c| !covered(*c) && (*c == 0 || *c == from || covered(c - 1)))
+            .map(gap_at)
+            .filter(|(_, length)| *length > 0);
+        let first = gaps.next();
+        let gap = match first {
+            Some(gap) if gap == (self.selection_start, self.selection_length) => {
+                gaps.next().or(first)
+            }
+            _ => first,
+        };
+        match gap {
+            Some((start, length)) => {
+                self.selection_start = start;
+                self.selection_length = length;
+            }
+            None => self.status = "Every column of this line is in a field.".to_owned(),
+        }
+    }
+
+    /// Draw the whole screen for a terminal `width` columns wide and `height` rows high.
+    fn render(&mut self, width: usize, height: usize) -> String {
+        let mut out = String::from("\x1b[H\x1b[2J");
+        let text: Vec<char> = self
+            .lines
+            .get(self.line)
+            .map_or(Vec::new(), |l| l.chars().collect());
+        let line = self.lines.get(self.line).map_or("", |l| l.as_str());
+        let view = width.max(10);
+
+        // keep the selection in view
+        if self.selection_start < self.scroll {
+            self.scroll = self.selection_start;
+        } else if self.selection_start + self.selection_length > self.scroll + view {
+            self.scroll = (self.selection_start + self.selection_length).saturating_sub(view);
+        }
+        let columns = self.scroll..self.scroll + view;
+
+        out.push_str(&format!(
+            "\x1b[1mLine {} of {}\x1b[0m{}\r\n\r\n",
+            self.line + 1,
+            self.lines.len(),
+            if self.modified { "  [modified]" } else { "" }
+        ));
+
+        // a ruler of column numbers, with a mark every ten columns
+        let mut ruler = String::new();
+        let mut ticks = String::new();
+        for col in columns.clone() {
+            let number = col + self.index_base;
+            if number.is_multiple_of(10) {
+                let label = (number / 10 % 10).to_string();
+                ruler.push_str(&label);
+            } else {
+                ruler.push(' ');
+            }
+            ticks.push(if number.is_multiple_of(5) { '+' } else { '.' });
+        }
+        out.push_str(&format!("\x1b[90m{}\r\n{}\x1b[0m\r\n", ruler, ticks));
+
+        let applying: Vec<(usize, &Field)> = self
+            .fields
+            .iter()
+            .filter(|f| f.applies_to(line))
+            .enumerate()
+            .collect();
+        for col in columns.clone() {
+            let c = text.get(col).copied().unwrap_or(' ');
+            let shown = if c == ' ' && col < text.len() {
+                '·'
+            } else {
+                c
+            };
+            let selected =
+                self.selection_start <= col && col < self.selection_start + self.selection_length;
+            let field = applying.iter().rev().find(|(_, f)| f.contains(col));
+            let style = match (selected, field) {
+                (true, _) => "\x1b[7m".to_owned(),
+                (false, Some((i, _))) => {
+                    format!(
+                        "\x1b[38;5;16;48;5;{}m",
+                        FIELD_COLORS[i % FIELD_COLORS.len()]
+                    )
+                }
+                (false, None) if col < text.len() => "\x1b[31m".to_owned(),
+                (false, None) => String::new(),
+            };
+            out.push_str(&format!("{}{}\x1b[0m", style, shown));
+        }
+        out.push_str("\r\n");
+
+        let selection_end = self.selection_start + self.selection_length;
+        let selected_text: String = text
+            .iter()
+            .skip(self.selection_start)
+            .take(self.selection_length)
+            .collect();
+        out.push_str(&format!(
+            "\r\nSelection: columns {}-{} ({} long) \"{}\"",
+            self.selection_start + self.index_base,
+            selection_end - 1 + self.index_base,
+            self.selection_length,
+            selected_text
+        ));
+        if let Some(i) = self.field_at_selection() {
+            let f = &self.fields[i];
+            out.push_str(&format!(
+                "  in \x1b[1m{}\x1b[0m ({}-{})",
+                f.name,
+                f.start + self.index_base,
+                f.end() - 1 + self.index_base
+            ));
+        }
+        out.push_str("\r\n\r\n\x1b[1mFields\x1b[0m\r\n");
+
+        // as many fields as fit, keeping the last rows for the status and help
+        let room = height.saturating_sub(12);
+        for f in self.fields.iter().take(room) {
+            out.push_str(&format!(
+                "  {:>5}-{:<5} {}{}{}\r\n",
+                f.start + self.index_base,
+                f.end() - 1 + self.index_base,
+                f.name,
+                f.condition
+                    .as_ref()
+                    .map_or(String::new(), |c| format!("  \x1b[90mwhen {}\x1b[0m", c)),
+                if f.applies_to(line) {
+                    ""
+                } else {
+                    "  \x1b[90m(not on this line)\x1b[0m"
+                }
+            ));
+        }
+        if self.fields.len() > room {
+            out.push_str(&format!("  ... and {} more\r\n", self.fields.len() - room));
+        }
+
+        out.push_str(&format!("\x1b[{};1H", height.saturating_sub(1)));
+        match &self.prompt {
+            Some((Prompt::Name, text)) => out.push_str(&format!("Field name: {}\x1b[?25h", text)),
+            Some((Prompt::Condition, text)) => out.push_str(&format!(
+                "Condition (regex, empty for none): {}\x1b[?25h",
+                text
+            )),
+            None => {
+                out.push_str(&format!("\x1b[33m{}\x1b[0m\x1b[?25l", self.status));
+                out.push_str(&format!(
+                    "\x1b[{};1H\x1b[90m←→ move  [ ] resize  ↑↓ line  Tab next gap  f select field  Enter name  c condition  x remove  w save  q quit\x1b[0m",
+                    height
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Write the fields as a syntax file.
+fn save(path: &str, fields: &[Field], index_base: usize) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path).context("Failed to write syntax file.")?;
+    let constraints = fields.iter().any(|f| {
+        f.charset.is_some()
+            || f.case.is_some()
+            || f.pattern.is_some()
+            || f.justify.is_some()
+            || f.pad.is_some()
+    });
+    let decoded = fields
+        .iter()
+        .any(|f| f.encoding.is_some() || f.field_type.is_some());
+    let mut header = vec!["start", "length", "name", "condition"];
+    if constraints {
+        header.extend(["charset", "case", "pattern", "justify", "pad"]);
+    }
+    if decoded {
+        header.extend(["encoding", "type"]);
+    }
+    let colored = fields.iter().any(|f| f.color.is_some());
+    if colored {
+        header.push("color");
+    }
+    let value_styled = fields.iter().any(|f| f.value_pattern.is_some());
+    if value_styled {
+        header.extend(["value_pattern", "value_style"]);
+    }
+    let sensitive = fields.iter().any(|f| f.sensitive);
+    if sensitive {
+        header.push("sensitive");
+    }
+    let checked = fields.iter().any(|f| f.check.is_some());
+    if checked {
+        header.push("check");
+    }
+    writer.write_record(&header)?;
+    for f in fields {
+        let mut row = vec![
+            (f.start + index_base).to_string(),
+            f.length.to_string(),
+            f.name.clone(),
+            f.condition.clone().unwrap_or_default(),
+        ];
+        if constraints {
+            row.push(
+                f.charset
+                    .as_ref()
+                    .map_or(String::new(), |c| c.spec().to_owned()),
+            );
+            row.push(f.case.map_or(String::new(), |c| c.name().to_owned()));
+            row.push(
+                f.pattern
+                    .as_ref()
+                    .map_or(String::new(), |p| p.spec().to_owned()),
+            );
+            row.push(f.justify.map_or(String::new(), |j| j.name().to_owned()));
+            row.push(f.pad.map_or(String::new(), String::from));
+        }
+        if decoded {
+            row.push(f.encoding.map_or(String::new(), |e| e.name().to_owned()));
+            row.push(f.field_type.as_ref().map_or(String::new(), FieldType::spec));
+        }
+        if colored {
+            row.push(f.color.clone().unwrap_or_default());
+        }
+        if value_styled {
+            row.push(
+                f.value_pattern
+                    .as_ref()
+                    .map_or(String::new(), |p| p.spec().to_owned()),
+            );
+            row.push(
+                f.value_style
+                    .as_ref()
+                    .map_or(String::new(), ValueStyle::spec),
+            );
+        }
+        if sensitive {
+            row.push(if f.sensitive { "true" } else { "" }.to_owned());
+        }
+        if checked {
+            row.push(f.check.as_ref().map_or(String::new(), ControlCheck::spec));
+        }
+        writer.write_record(&row)?;
+    }
+    writer.flush().context("Failed to write syntax file.")?;
+    Ok(())
+}
+
+/// Split bytes read from the terminal into key presses.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        keys.push(match c {
+            '\x1b' => match (chars.peek().copied(), {
+                let mut ahead = chars.clone();
+                ahead.next();
+                ahead.next()
+            }) {
+                (Some('[') | Some('O'), Some(code @ ('A' | 'B' | 'C' | 'D'))) => {
+                    chars.next();
+                    chars.next();
+                    match code {
+                        'A' => Key::Up,
+                        'B' => Key::Down,
+                        'C' => Key::Right,
+                        _ => Key::Left,
+                    }
+                }
+                _ => Key::Escape,
+            },
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\t' => Key::Tab,
+            c if c.is_control() => continue,
+            c => Key::Char(c),
+        });
+    }
+    keys
+}
+
+/// Run the `edit` subcommand.
+pub fn run(args: &Args, edit: &EditArgs) -> anyhow::Result<()> {
+    if args.delimiter.is_some() {
+        bail!("The editor only builds fixed width syntax files.");
+    }
+    let index_base = args.index_base as usize;
+
+    let mut fields = Vec::new();
+    if Path::new(&edit.syntax_file).exists() {
+        let (_, records, assembly) = read_syntax_file(&edit.syntax_file, None)?;
+        if !records.record_types().is_empty() {
+            bail!("The editor can't edit syntax files that define record types, as it would lose them when saving.");
+        }
+        if assembly.joins_lines() {
+            bail!("The editor can't edit syntax files that give `continues_if`, as it would lose it when saving.");
+        }
+        let RecordList::FixedWidth(fw_records) = records else {
+            unreachable!("read without a delimiter");
+        };
+        for record in fw_records {
+            if record.start_capture.is_some() || record.length_capture.is_some() {
+                bail!(
+                    "Syntax file line {}: rule '{}' is placed by a capture group, which the editor can't show.",
+                    record.line,
+                    record.name
+                );
+            }
+            if let Some(anchor) = &record.start_anchor {
+                bail!(
+                    "Syntax file line {}: rule '{}' starts `{}`, which the editor would replace with a column when saving.",
+                    record.line,
+                    record.name,
+                    anchor
+                );
+            }
+            let (Some(start), Some(length)) = (record.start, record.length) else {
+                bail!(
+                    "Syntax file line {}: rule '{}' needs a start and length to be edited.",
+                    record.line,
+                    record.name
+                );
+            };
+            if record.from_end {
+                bail!(
+                    "Syntax file line {}: rule '{}' is placed from the end of the line, which the editor can't show.",
+                    record.line,
+                    record.name
+                );
+            }
+            if start < index_base {
+                bail!(
+                    "Syntax file line {}: rule '{}' starts before the first column.",
+                    record.line,
+                    record.name
+                );
+            }
+            fields.push(Field {
+                start: start - index_base,
+                length,
+                name: record.name.to_string(),
+                condition: record.condition.map(|c| c.as_str().to_owned()),
+                charset: record.charset,
+                case: record.case,
+                pattern: record.pattern,
+                justify: record.justify,
+                pad: record.pad,
+                encoding: record.encoding,
+                field_type: record.field_type,
+                color: record.color,
+                value_pattern: record.value_pattern,
+                value_style: record.value_style,
+                sensitive: record.sensitive,
+                check: record.check,
+            });
+        }
+    }
+
+    let file = open_input(args, &edit.input_file)?;
+    let mut lines = Vec::new();
+    for line in BoundedLines::new(BufReader::new(file), args.max_line_length, args.encoding)
+        .fixed_length(args.record_length)
+        .line_endings(args)
+        .take(MAX_SAMPLE_LINES)
+    {
+        lines.push(line.context("Failed to read line from input file.")?);
+    }
+    if lines.is_empty() {
+        bail!("The input file is empty, so there is nothing to build a layout over.");
+    }
+
+    let mut editor = Editor {
+        lines,
+        fields,
+        line: 0,
+        selection_start: 0,
+        selection_length: 1,
+        scroll: 0,
+        prompt: None,
+        status: format!("Editing {}.", edit.syntax_file),
+        modified: false,
+        confirm_quit: false,
+        index_base,
+    };
+
+    let terminal = terminal::RawTerminal::enter()?;
+    let result = (|| -> anyhow::Result<()> {
+        let mut stdout = std::io::stdout();
+        let mut stdin = std::io::stdin();
+        let mut buffer = [0u8; 64];
+        loop {
+            let (width, height) = terminal.size();
+            stdout.write_all(editor.render(width, height).as_bytes())?;
+            stdout.flush()?;
+
+            let read = stdin.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            for key in parse_keys(&buffer[..read]) {
+                match editor.handle(key) {
+                    Action::Continue => (),
+                    Action::Save => {
+                        save(&edit.syntax_file, &editor.fields, index_base)?;
+                        editor.modified = false;
+                        editor.status = format!(
+                            "Saved {} field(s) to {}.",
+                            editor.fields.len(),
+                            edit.syntax_file
+                        );
+                    }
+                    Action::Quit => return Ok(()),
+                }
+            }
+        }
+    })();
+    drop(terminal);
+    result
+}
+
+#[cfg(unix)]
+mod terminal {
+    use std::io::Write;
+
+    use anyhow::bail;
+
+    /// The terminal in raw mode on the alternate screen, restored when dropped.
+    pub struct RawTerminal {
+        original: libc::termios,
+    }
+
+    impl RawTerminal {
+        pub fn enter() -> anyhow::Result<Self> {
+            // SAFETY: `termios` is plain data, filled in by `tcgetattr` before it is read.
+            let mut original: libc::termios = unsafe { std::mem::zeroed() };
+            // SAFETY: standard input is a valid descriptor and `original` is a valid `termios` to write to.
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+                bail!("The editor needs to be run in a terminal.");
+            }
+            let mut raw = original;
+            // SAFETY: `raw` is a valid `termios`.
+            unsafe { libc::cfmakeraw(&mut raw) };
+            // SAFETY: standard input is a valid descriptor and `raw` a valid `termios`.
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+                bail!("Failed to put the terminal into raw mode.");
+            }
+            print!("\x1b[?1049h\x1b[?25l");
+            let _ = std::io::stdout().flush();
+            Ok(Self { original })
+        }
+
+        /// The (columns, rows) of the terminal.
+        pub fn size(&self) -> (usize, usize) {
+            // SAFETY: `winsize` is plain data, filled in by `ioctl` before it is read.
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            // SAFETY: standard output is a valid descriptor and `size` a valid `winsize` to write to.
+            if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0
+                && size.ws_col > 0
+            {
+                (size.ws_col as usize, size.ws_row as usize)
+            } else {
+                (80, 24)
+            }
+        }
+    }
+
+    impl Drop for RawTerminal {
+        fn drop(&mut self) {
+            print!("\x1b[?25h\x1b[?1049l");
+            let _ = std::io::stdout().flush();
+            // SAFETY: standard input is a valid descriptor and `original` the `termios` read from it.
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod terminal {
+    use anyhow::bail;
+
+    pub struct RawTerminal;
+
+    impl RawTerminal {
+        pub fn enter() -> anyhow::Result<Self> {
+            bail!("The editor is only available on Unix-like systems.");
+        }
+
+        pub fn size(&self) -> (usize, usize) {
+            (80, 24)
+        }
+    }
+}
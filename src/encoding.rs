@@ -0,0 +1,605 @@
+//! Input encodings (`--encoding`), including the EBCDIC code pages files from z/OS come in, and the `encoding` and
+//! `type` columns of a syntax file, which decode a field's bytes for its tooltip: as text in another encoding, as a
+//! zoned, packed (COMP-3) or written out decimal number, as a date, or by a plugin.
+
+use std::fmt;
+use std::string::FromUtf8Error;
+
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::plugin;
+
+/// The character encoding of an input file.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, which includes ASCII
+    #[default]
+    #[value(name = "utf-8")]
+    Utf8,
+    /// ISO 8859-1, which reads any byte as the character with the same number
+    Latin1,
+    /// EBCDIC code page 37, as used on z/OS in the US and Canada
+    Cp037,
+    /// EBCDIC code page 1047, the Latin-1 code page of z/OS Unix System Services
+    Cp1047,
+}
+
+impl Encoding {
+    /// Parse the name of an encoding, as given in the `encoding` column of a syntax file.
+    pub fn parse(name: &str) -> anyhow::Result<Encoding> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            "cp037" | "ibm037" | "ibm-037" => Encoding::Cp037,
+            "cp1047" | "ibm1047" | "ibm-1047" => Encoding::Cp1047,
+            _ => bail!(
+                "Unknown encoding `{}`. Use `utf-8`, `latin1`, `cp037` or `cp1047`.",
+                name
+            ),
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Latin1 => "latin1",
+            Encoding::Cp037 => "cp037",
+            Encoding::Cp1047 => "cp1047",
+        }
+    }
+
+    /// Whether a line ends at `byte`. EBCDIC lines end in a new line (NL) or line feed (LF) character.
+    pub fn is_line_end(self, byte: u8) -> bool {
+        match self {
+            Encoding::Utf8 | Encoding::Latin1 => byte == b'\n',
+            Encoding::Cp037 | Encoding::Cp1047 => byte == 0x15 || byte == 0x25,
+        }
+    }
+
+    /// The character each byte stands for, in the encodings of one byte per character.
+    fn table(self) -> Option<[char; 256]> {
+        match self {
+            Encoding::Utf8 => None,
+            Encoding::Latin1 => Some(std::array::from_fn(|b| b as u8 as char)),
+            Encoding::Cp037 => Some(CP037),
+            Encoding::Cp1047 => {
+                let mut table = CP037;
+                for (byte, c) in CP1047_CHANGES {
+                    table[byte as usize] = c;
+                }
+                Some(table)
+            }
+        }
+    }
+
+    /// Decode `bytes` as text in this encoding. Only UTF-8 can fail, as every byte is a character in the others.
+    pub fn decode(self, bytes: Vec<u8>) -> Result<String, FromUtf8Error> {
+        match self.table() {
+            None => String::from_utf8(bytes),
+            Some(table) => Ok(bytes.into_iter().map(|b| table[b as usize]).collect()),
+        }
+    }
+
+    /// Encode `text` as the bytes it was decoded from, or `None` if it has a character this encoding doesn't have.
+    pub fn encode(self, text: &str) -> Option<Vec<u8>> {
+        match self.table() {
+            None => Some(text.as_bytes().to_vec()),
+            Some(table) => text
+                .chars()
+                .map(|c| table.iter().position(|t| *t == c).map(|b| b as u8))
+                .collect(),
+        }
+    }
+}
+
+/// Read an optional `encoding` column.
+pub fn deserialize_encoding<'de, D>(deserializer: D) -> Result<Option<Encoding>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .filter(|name| !name.is_empty())
+        .map(|name| Encoding::parse(&name))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// How a field's bytes are read for its tooltip, from the `type` column of a syntax file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// Text, shown as it is.
+    Text,
+    /// A zoned decimal number: a digit per byte, with the sign overpunched on the last, which is a letter or `{`/`}`.
+    /// `scale` is the number of implied decimal places.
+    Zoned { scale: u8 },
+    /// A packed decimal (COMP-3) number: two digits per byte, with the sign in the last half byte.
+    Packed { scale: u8 },
+    /// A number written out in digits, with an optional sign before or after it. `scale` is the number of implied
+    /// decimal places, or if the number is written with a decimal point, `point` is the character it's written as, `.`
+    /// or `,`, and the other may group the digits of the whole number, as in `1.234,56`.
+    Decimal { scale: u8, point: Option<char> },
+    /// A date, and perhaps a time, written as `format` says.
+    Date { format: DateFormat },
+    /// Decoded by a plugin, the program `command` (see [`crate::plugin`]).
+    Plugin { command: String },
+}
+
+impl FieldType {
+    /// Parse a `type`: `text`, `zoned`, `packed` or `decimal`, with the number of implied decimal places in brackets
+    /// if there are any, e.g. `packed(2)`, `decimal(comma)` or `decimal(point)` for a number written with a decimal
+    /// comma or point, `date` with its format, e.g. `date(yyyyMMdd)`, or `plugin` with the command decoding it, e.g.
+    /// `plugin(./decoders/flags.py)`. A colon may be used instead of the brackets, as in `decimal:2`.
+    pub fn parse(spec: &str) -> anyhow::Result<FieldType> {
+        let spec = spec.trim();
+        let (kind, argument) = match spec.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            Some((kind, argument)) => (kind, Some(argument)),
+            None => match spec.split_once(':') {
+                Some((kind, argument)) => (kind, Some(argument)),
+                None => (spec, None),
+            },
+        };
+        let kind = kind.trim().to_lowercase();
+        if kind == "date" {
+            let format = argument.with_context(|| {
+                format!(
+                    "Type `{}` needs the format of the date, e.g. `date(yyyyMMdd)`.",
+                    spec
+                )
+            })?;
+            return Ok(FieldType::Date {
+                format: DateFormat::parse(format.trim())?,
+            });
+        }
+        if kind == "plugin" {
+            let command = argument
+                .map(str::trim)
+                .filter(|command| !command.is_empty())
+                .with_context(|| {
+                    format!(
+                        "Type `{}` needs the command of the plugin, e.g. `plugin(./decoders/flags.py)`.",
+                        spec
+                    )
+                })?;
+            return Ok(FieldType::Plugin {
+                command: command.to_owned(),
+            });
+        }
+        if kind == "decimal" {
+            let point = match argument.map(|a| a.trim().to_lowercase()).as_deref() {
+                Some("comma") => Some(','),
+                Some("point") => Some('.'),
+                _ => None,
+            };
+            if point.is_some() {
+                return Ok(FieldType::Decimal { scale: 0, point });
+            }
+        }
+        let scale = match argument {
+            Some(scale) => scale.trim().parse::<u8>().with_context(|| {
+                format!("The decimal places of type `{}` aren't a number.", spec)
+            })?,
+            None => 0,
+        };
+        Ok(match kind.as_str() {
+            "text" if argument.is_none() => FieldType::Text,
+            "zoned" => FieldType::Zoned { scale },
+            "packed" | "comp-3" => FieldType::Packed { scale },
+            "decimal" => FieldType::Decimal { scale, point: None },
+            _ => bail!(
+                "Unknown type `{}`. Use `text`, `zoned`, `packed`, `decimal`, `date` or `plugin`, e.g. `packed(2)` for two decimal places, `decimal(comma)` for a number written with a decimal comma, `date(yyyyMMdd)` or `plugin(./decoders/flags.py)`.",
+                spec
+            ),
+        })
+    }
+
+    /// The type as it is written in a syntax file.
+    pub fn spec(&self) -> String {
+        match self {
+            FieldType::Text => "text".to_owned(),
+            FieldType::Zoned { scale: 0 } => "zoned".to_owned(),
+            FieldType::Zoned { scale } => format!("zoned({})", scale),
+            FieldType::Packed { scale: 0 } => "packed".to_owned(),
+            FieldType::Packed { scale } => format!("packed({})", scale),
+            FieldType::Decimal {
+                point: Some(','), ..
+            } => "decimal(comma)".to_owned(),
+            FieldType::Decimal { point: Some(_), .. } => "decimal(point)".to_owned(),
+            FieldType::Decimal { scale: 0, .. } => "decimal".to_owned(),
+            FieldType::Decimal { scale, .. } => format!("decimal({})", scale),
+            FieldType::Date { format } => format!("date({})", format.spec()),
+            FieldType::Plugin { command } => format!("plugin({})", command),
+        }
+    }
+
+    /// Whether the field's text is its bytes, so can be shown alongside the value it's decoded to. A packed decimal
+    /// is rarely readable as text.
+    pub fn is_textual(&self) -> bool {
+        !matches!(self, FieldType::Packed { .. })
+    }
+}
+
+/// One part of a [`DateFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DatePart {
+    /// `yyyy`
+    Year,
+    /// `yy`, which is 1970 to 2069
+    ShortYear,
+    /// `MM`
+    Month,
+    /// `dd`
+    Day,
+    /// `DDD`, the day of the year
+    DayOfYear,
+    /// `HH`
+    Hour,
+    /// `mm`
+    Minute,
+    /// `ss`
+    Second,
+    /// Any other character, which must be there as it is.
+    Literal(char),
+}
+
+impl DatePart {
+    /// The letters each part is written as, which are also the number of digits it takes up.
+    const FIELDS: [(&'static str, DatePart); 8] = [
+        ("yyyy", DatePart::Year),
+        ("yy", DatePart::ShortYear),
+        ("MM", DatePart::Month),
+        ("dd", DatePart::Day),
+        ("DDD", DatePart::DayOfYear),
+        ("HH", DatePart::Hour),
+        ("mm", DatePart::Minute),
+        ("ss", DatePart::Second),
+    ];
+}
+
+/// The format of a date field, such as `yyyyMMdd` or `dd/MM/yy HH:mm`, from its `type`.
+///
+/// `yyyy` and `yy` are the year, `MM` the month, `dd` the day of the month, `DDD` the day of the year, and `HH`, `mm`
+/// and `ss` the hour, minute and second. Each is that many digits. Any other character that isn't a letter, or `T`, as
+/// in ISO 8601's `yyyy-MM-ddTHH:mm:ss`, must be there as it is. `iso` is short for `yyyy-MM-dd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFormat {
+    spec: String,
+    parts: Vec<(DatePart, usize)>,
+}
+
+impl DateFormat {
+    pub fn parse(spec: &str) -> anyhow::Result<DateFormat> {
+        let mut parts = Vec::new();
+        let mut rest = if spec.eq_ignore_ascii_case("iso") {
+            "yyyy-MM-dd"
+        } else {
+            spec
+        };
+        'parts: while let Some(c) = rest.chars().next() {
+            for (letters, part) in DatePart::FIELDS {
+                if let Some(after) = rest.strip_prefix(letters) {
+                    parts.push((part, letters.len()));
+                    rest = after;
+                    continue 'parts;
+                }
+            }
+            if c.is_alphabetic() && c != 'T' {
+                bail!(
+                    "Date format `{}` has `{}`, which isn't one of `yyyy`, `yy`, `MM`, `dd`, `DDD`, `HH`, `mm` or `ss`.",
+                    spec,
+                    c
+                );
+            }
+            parts.push((DatePart::Literal(c), 1));
+            rest = &rest[c.len_utf8()..];
+        }
+        let has = |wanted: &[DatePart]| parts.iter().any(|(part, _)| wanted.contains(part));
+        if !has(&[DatePart::Year, DatePart::ShortYear])
+            || !(has(&[DatePart::DayOfYear]) || has(&[DatePart::Month]) && has(&[DatePart::Day]))
+        {
+            bail!(
+                "Date format `{}` needs a year, and either a month and day or a day of the year.",
+                spec
+            );
+        }
+        Ok(DateFormat {
+            spec: spec.to_owned(),
+            parts,
+        })
+    }
+
+    /// The format as written in the syntax file.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    /// The date `text` is in this format, written as `yyyy-MM-dd`, followed by the time as `HH:mm:ss` if the format
+    /// has one. `None` if it isn't a date in this format, or there is no such date.
+    pub fn read(&self, text: &str) -> Option<String> {
+        let mut chars = text.chars();
+        let (mut year, mut month, mut day, mut day_of_year) = (None, None, None, None);
+        let mut time: Option<[u32; 3]> = None;
+        for &(part, width) in &self.parts {
+            if let DatePart::Literal(literal) = part {
+                if chars.next() != Some(literal) {
+                    return None;
+                }
+                continue;
+            }
+            let mut number = 0;
+            for _ in 0..width {
+                number = number * 10 + chars.next()?.to_digit(10)?;
+            }
+            match part {
+                DatePart::Year => year = Some(number as i32),
+                DatePart::ShortYear => {
+                    year = Some(number as i32 + if number < 70 { 2000 } else { 1900 })
+                }
+                DatePart::Month => month = Some(number),
+                DatePart::Day => day = Some(number),
+                DatePart::DayOfYear => day_of_year = Some(number),
+                DatePart::Hour => time.get_or_insert([0; 3])[0] = number,
+                DatePart::Minute => time.get_or_insert([0; 3])[1] = number,
+                DatePart::Second => time.get_or_insert([0; 3])[2] = number,
+                DatePart::Literal(_) => unreachable!("literals are matched above"),
+            }
+        }
+        if chars.next().is_some() {
+            return None;
+        }
+        let date = match (month, day, day_of_year) {
+            (Some(month), Some(day), _) => NaiveDate::from_ymd_opt(year?, month, day)?,
+            (_, _, Some(day_of_year)) => NaiveDate::from_yo_opt(year?, day_of_year)?,
+            _ => return None,
+        };
+        Some(match time {
+            None => date.format("%Y-%m-%d").to_string(),
+            Some([hour, minute, second]) => date
+                .and_hms_opt(hour, minute, second)?
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        })
+    }
+}
+
+/// Read an optional `type` column.
+pub fn deserialize_field_type<'de, D>(deserializer: D) -> Result<Option<FieldType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| FieldType::parse(&spec))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Why a field's value couldn't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    /// The field has characters that aren't in the input's encoding, so its bytes aren't known.
+    NotEncodable(Encoding),
+    /// The field isn't a valid number of its type.
+    NotANumber(FieldType),
+    /// The field isn't a valid date in its format.
+    NotADate(DateFormat),
+    /// The field's plugin says it isn't valid, for this reason.
+    Plugin(String),
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotEncodable(encoding) => {
+                write!(f, "has characters that aren't in {}", encoding.name())
+            }
+            ValueError::NotANumber(field_type) => {
+                write!(f, "isn't a valid `{}` number", field_type.spec())
+            }
+            ValueError::NotADate(format) => {
+                write!(f, "isn't a valid date in the format `{}`", format.spec())
+            }
+            ValueError::Plugin(reason) => write!(f, "isn't valid, as {}", reason),
+        }
+    }
+}
+
+/// The value of a field, whose text is `text` as read from a line in the `input` encoding, decoded as its `encoding`
+/// and `field_type` say. `None` if the field has neither, so is shown as it is.
+pub fn field_value(
+    text: &str,
+    input: Encoding,
+    encoding: Option<Encoding>,
+    field_type: Option<&FieldType>,
+) -> Option<Result<String, ValueError>> {
+    if encoding.is_none() && field_type.is_none_or(|t| *t == FieldType::Text) {
+        return None;
+    }
+    let bytes = || input.encode(text).ok_or(ValueError::NotEncodable(input));
+    let decoded = || -> Result<String, ValueError> {
+        match encoding.filter(|e| *e != input) {
+            None => Ok(text.to_owned()),
+            Some(encoding) => encoding
+                .decode(bytes()?)
+                .map_err(|_| ValueError::NotEncodable(encoding)),
+        }
+    };
+    let not_a_number = || ValueError::NotANumber(field_type.cloned().unwrap_or(FieldType::Text));
+    Some(match field_type.unwrap_or(&FieldType::Text) {
+        FieldType::Text => decoded(),
+        FieldType::Zoned { scale } => {
+            decoded().and_then(|text| zoned_value(&text, *scale).ok_or_else(not_a_number))
+        }
+        FieldType::Packed { scale } => {
+            bytes().and_then(|bytes| packed_value(&bytes, *scale).ok_or_else(not_a_number))
+        }
+        FieldType::Decimal { scale, point } => {
+            decoded().and_then(|text| decimal_value(&text, *scale, *point).ok_or_else(not_a_number))
+        }
+        FieldType::Date { format } => decoded().and_then(|text| {
+            format
+                .read(&text)
+                .ok_or_else(|| ValueError::NotADate(format.clone()))
+        }),
+        FieldType::Plugin { command } => {
+            decoded().and_then(|text| plugin::decode(command, &text).map_err(ValueError::Plugin))
+        }
+    })
+}
+
+/// The number a decimal written out as `text` stands for: digits with an optional `+` or `-` before or after them, as
+/// many European and SAP exports write it, and spaces either side. With a decimal `point`, the digits after it are
+/// the fraction, and the other of `.` and `,`, spaces and `'` may group the digits before it. Otherwise, the last
+/// `scale` digits are.
+fn decimal_value(text: &str, scale: u8, point: Option<char>) -> Option<String> {
+    let text = text.trim_matches(' ');
+    let (negative, number) = if let Some(number) = text.strip_prefix('-') {
+        (true, number)
+    } else if let Some(number) = text.strip_suffix('-') {
+        (true, number)
+    } else {
+        let number = text.strip_prefix('+').or_else(|| text.strip_suffix('+'));
+        (false, number.unwrap_or(text))
+    };
+    let (whole, fraction, scale) = match point {
+        None => (number, "", scale),
+        Some(point) => {
+            let (whole, fraction) = number.split_once(point).unwrap_or((number, ""));
+            let scale = u8::try_from(fraction.chars().count()).ok()?;
+            (whole, fraction, scale)
+        }
+    };
+    let grouped = |c: &char| {
+        point.is_some_and(|point| {
+            matches!(c, ' ' | '\'' | '\u{a0}' | '\u{202f}')
+                || *c == if point == ',' { '.' } else { ',' }
+        })
+    };
+    if whole.starts_with(|c: char| grouped(&c)) || whole.ends_with(|c: char| grouped(&c)) {
+        return None;
+    }
+    let digits: Vec<u8> = whole
+        .chars()
+        .filter(|c| !grouped(c))
+        .chain(fraction.chars())
+        .map(|c| c.to_digit(10).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    format_number(&digits, negative, scale)
+}
+
+/// The number a zoned decimal, decoded as `text`, stands for. The sign of the last digit is overpunched as `{`/`A`-`I`
+/// for positive or `}`/`J`-`R` for negative (or `p`-`y` for negative, as ASCII systems write it).
+fn zoned_value(text: &str, scale: u8) -> Option<String> {
+    let mut digits: Vec<u8> = Vec::new();
+    let mut negative = false;
+    let count = text.chars().count();
+    for (idx, c) in text.chars().enumerate() {
+        let digit = match c {
+            '0'..='9' => c as u8 - b'0',
+            '{' | '}' | 'A'..='I' | 'J'..='R' | 'p'..='y' if idx + 1 == count => {
+                negative = matches!(c, '}' | 'J'..='R' | 'p'..='y');
+                match c {
+                    '{' | '}' => 0,
+                    'A'..='I' => c as u8 - b'A' + 1,
+                    'J'..='R' => c as u8 - b'J' + 1,
+                    _ => c as u8 - b'p',
+                }
+            }
+            _ => return None,
+        };
+        digits.push(digit);
+    }
+    format_number(&digits, negative, scale)
+}
+
+/// The number a packed decimal, whose bytes are `bytes`, stands for. The last half byte is its sign: `D` or `B` for
+/// negative and `A`, `C`, `E` or `F` for positive.
+fn packed_value(bytes: &[u8], scale: u8) -> Option<String> {
+    let (last, rest) = bytes.split_last()?;
+    let mut digits = Vec::new();
+    for byte in rest {
+        digits.push(byte >> 4);
+        digits.push(byte & 0xf);
+    }
+    digits.push(last >> 4);
+    let negative = match last & 0xf {
+        0xb | 0xd => true,
+        0xa | 0xc | 0xe | 0xf => false,
+        _ => return None,
+    };
+    if digits.iter().any(|d| *d > 9) {
+        return None;
+    }
+    format_number(&digits, negative, scale)
+}
+
+/// Write `digits` as a number, with `scale` of them after the decimal point and leading zeros removed.
+fn format_number(digits: &[u8], negative: bool, scale: u8) -> Option<String> {
+    if digits.is_empty() {
+        return None;
+    }
+    let scale = scale as usize;
+    let mut text: String = digits.iter().map(|d| (b'0' + d) as char).collect();
+    if text.len() <= scale {
+        text.insert_str(0, &"0".repeat(scale + 1 - text.len()));
+    }
+    let (whole, fraction) = text.split_at(text.len() - scale);
+    let whole = match whole.trim_start_matches('0') {
+        "" => "0",
+        whole => whole,
+    };
+    let zero = digits.iter().all(|d| *d == 0);
+    let mut number = format!("{}{}", if negative && !zero { "-" } else { "" }, whole);
+    if scale > 0 {
+        number.push('.');
+        number.push_str(fraction);
+    }
+    Some(number)
+}
+
+/// The bytes code page 1047 gives different characters to than code page 37. As z/OS Unix System Services reads it,
+/// NL (0x15) is the line feed that ends its text files' lines, and 0x25 the NEL control, the other way round to 37.
+const CP1047_CHANGES: [(u8, char); 8] = [
+    (0x15, '\n'),
+    (0x25, '\u{85}'),
+    (0x5f, '^'),
+    (0xad, '['),
+    (0xb0, '\u{ac}'),
+    (0xba, '\u{dd}'),
+    (0xbb, '\u{a8}'),
+    (0xbd, ']'),
+];
+
+/// The character each byte stands for in EBCDIC code page 37.
+const CP037: [char; 256] = [
+    '\u{0}', '\u{1}', '\u{2}', '\u{3}', '\u{9c}', '\u{9}', '\u{86}', '\u{7f}', '\u{97}', '\u{8d}',
+    '\u{8e}', '\u{b}', '\u{c}', '\u{d}', '\u{e}', '\u{f}', '\u{10}', '\u{11}', '\u{12}', '\u{13}',
+    '\u{9d}', '\u{85}', '\u{8}', '\u{87}', '\u{18}', '\u{19}', '\u{92}', '\u{8f}', '\u{1c}',
+    '\u{1d}', '\u{1e}', '\u{1f}', '\u{80}', '\u{81}', '\u{82}', '\u{83}', '\u{84}', '\u{a}',
+    '\u{17}', '\u{1b}', '\u{88}', '\u{89}', '\u{8a}', '\u{8b}', '\u{8c}', '\u{5}', '\u{6}',
+    '\u{7}', '\u{90}', '\u{91}', '\u{16}', '\u{93}', '\u{94}', '\u{95}', '\u{96}', '\u{4}',
+    '\u{98}', '\u{99}', '\u{9a}', '\u{9b}', '\u{14}', '\u{15}', '\u{9e}', '\u{1a}', '\u{20}',
+    '\u{a0}', '\u{e2}', '\u{e4}', '\u{e0}', '\u{e1}', '\u{e3}', '\u{e5}', '\u{e7}', '\u{f1}',
+    '\u{a2}', '\u{2e}', '\u{3c}', '\u{28}', '\u{2b}', '\u{7c}', '\u{26}', '\u{e9}', '\u{ea}',
+    '\u{eb}', '\u{e8}', '\u{ed}', '\u{ee}', '\u{ef}', '\u{ec}', '\u{df}', '\u{21}', '\u{24}',
+    '\u{2a}', '\u{29}', '\u{3b}', '\u{ac}', '\u{2d}', '\u{2f}', '\u{c2}', '\u{c4}', '\u{c0}',
+    '\u{c1}', '\u{c3}', '\u{c5}', '\u{c7}', '\u{d1}', '\u{a6}', '\u{2c}', '\u{25}', '\u{5f}',
+    '\u{3e}', '\u{3f}', '\u{f8}', '\u{c9}', '\u{ca}', '\u{cb}', '\u{c8}', '\u{cd}', '\u{ce}',
+    '\u{cf}', '\u{cc}', '\u{60}', '\u{3a}', '\u{23}', '\u{40}', '\u{27}', '\u{3d}', '\u{22}',
+    '\u{d8}', '\u{61}', '\u{62}', '\u{63}', '\u{64}', '\u{65}', '\u{66}', '\u{67}', '\u{68}',
+    '\u{69}', '\u{ab}', '\u{bb}', '\u{f0}', '\u{fd}', '\u{fe}', '\u{b1}', '\u{b0}', '\u{6a}',
+    '\u{6b}', '\u{6c}', '\u{6d}', '\u{6e}', '\u{6f}', '\u{70}', '\u{71}', '\u{72}', '\u{aa}',
+    '\u{ba}', '\u{e6}', '\u{b8}', '\u{c6}', '\u{a4}', '\u{b5}', '\u{7e}', '\u{73}', '\u{74}',
+    '\u{75}', '\u{76}', '\u{77}', '\u{78}', '\u{79}', '\u{7a}', '\u{a1}', '\u{bf}', '\u{d0}',
+    '\u{dd}', '\u{de}', '\u{ae}', '\u{5e}', '\u{a3}', '\u{a5}', '\u{b7}', '\u{a9}', '\u{a7}',
+    '\u{b6}', '\u{bc}', '\u{bd}', '\u{be}', '\u{5b}', '\u{5d}', '\u{af}', '\u{a8}', '\u{b4}',
+    '\u{d7}', '\u{7b}', '\u{41}', '\u{42}', '\u{43}', '\u{44}', '\u{45}', '\u{46}', '\u{47}',
+    '\u{48}', '\u{49}', '\u{ad}', '\u{f4}', '\u{f6}', '\u{f2}', '\u{f3}', '\u{f5}', '\u{7d}',
+    '\u{4a}', '\u{4b}', '\u{4c}', '\u{4d}', '\u{4e}', '\u{4f}', '\u{50}', '\u{51}', '\u{52}',
+    '\u{b9}', '\u{fb}', '\u{fc}', '\u{f9}', '\u{fa}', '\u{ff}', '\u{5c}', '\u{f7}', '\u{53}',
+    '\u{54}', '\u{55}', '\u{56}', '\u{57}', '\u{58}', '\u{59}', '\u{5a}', '\u{b2}', '\u{d4}',
+    '\u{d6}', '\u{d2}', '\u{d3}', '\u{d5}', '\u{30}', '\u{31}', '\u{32}', '\u{33}', '\u{34}',
+    '\u{35}', '\u{36}', '\u{37}', '\u{38}', '\u{39}', '\u{b3}', '\u{db}', '\u{dc}', '\u{d9}',
+    '\u{da}', '\u{9f}',
+];
@@ -0,0 +1,386 @@
+//! The `explain` subcommand, which traces how the rules of a syntax file apply to chosen lines: whether each rule's
+//! condition matched, the region it produced, and why it was left out if it was.
+
+use anyhow::{bail, Context};
+
+use crate::{
+    condition_matches, field_columns, in_record_type, line_length, load_syntax, mask,
+    open_buffered, physical_lines, record_text, select::read_records, unit_name, Args, Captures,
+    ColumnMode, Columns, Condition, ExplainArgs, PositionOptions, RecordList, ShortLinePolicy,
+    SyntaxRule,
+};
+
+/// Parse a `--line` value: a line number, or an inclusive range of them written `FIRST-LAST`.
+fn parse_lines(text: &str) -> anyhow::Result<(usize, usize)> {
+    let (first, last) = text.split_once('-').unwrap_or((text, text));
+    let parse = |n: &str| -> anyhow::Result<usize> {
+        match n.trim().parse() {
+            Ok(0) | Err(_) => bail!(
+                "Line `{}` should be a line number counting from 1, or a range like `40-45`.",
+                text
+            ),
+            Ok(n) => Ok(n),
+        }
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+    if last < first {
+        bail!("Lines `{}` end before they start.", text);
+    }
+    Ok((first, last))
+}
+
+/// Why a rule did or didn't apply to a line.
+enum Outcome {
+    /// The rule belongs to a different record type than the line.
+    OtherRecordType,
+    /// The condition didn't match.
+    NotMatched,
+    /// The rule is missing the position it needs.
+    Unpositioned(&'static str),
+    /// The rule is placed by the named capture group, which didn't capture a number on the line.
+    Uncaptured(String),
+    /// Shifting the rule by this many columns or fields moves it before the first.
+    BeforeFirst(isize),
+    /// The rule is placed from the end of the line, which is too short to hold its start.
+    TooShort,
+    /// The rule applied, as the 0-based `start..end` columns of the line (in the column mode, for fixed width rules).
+    Applied { start: usize, end: usize },
+}
+
+/// A rule, with what happened when it was applied to a line.
+struct Trace {
+    name: String,
+    record: Option<String>,
+    condition: Option<Condition>,
+    /// Where the syntax file puts the rule, as written.
+    position: String,
+    outcome: Outcome,
+    /// Whether the field's value is masked.
+    sensitive: bool,
+}
+
+/// A position as the syntax file writes it: a number, or a `${name}` placeholder with the value it captured.
+fn written(value: Option<usize>, capture: &Option<String>, captures: &Captures) -> Option<String> {
+    match capture {
+        Some(name) => Some(match captures.get(name) {
+            Some(captured) => format!("${{{}}} = {:?}", name, captured),
+            None => format!("${{{}}}", name),
+        }),
+        None => value.map(|value| value.to_string()),
+    }
+}
+
+/// Evaluate every rule against `line`, in the order the renderer does.
+fn trace_line(records: &RecordList, line: &str, positions: PositionOptions) -> Vec<Trace> {
+    let mut traces = Vec::new();
+    let length = line.chars().count();
+    let record_type = records.record_type(line);
+    let captures = Captures::of(records, record_type, line);
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            for record in fw_records {
+                let start = written(record.start, &record.start_capture, &captures);
+                let start = match &record.start_anchor {
+                    Some(anchor) => start.map(|start| format!("{} = {}", anchor, start)),
+                    None => start,
+                };
+                let position = match (
+                    start,
+                    written(record.length, &record.length_capture, &captures),
+                ) {
+                    (Some(start), Some(len)) if record.from_end => {
+                        format!("start {} from the end, length {}", start, len)
+                    }
+                    (Some(start), Some(len)) => format!("start {}, length {}", start, len),
+                    _ => "no position".to_owned(),
+                };
+                let uncaptured = [
+                    (&record.start_capture, record.start_on(&captures)),
+                    (&record.length_capture, record.length_on(&captures)),
+                ]
+                .into_iter()
+                .find_map(|(capture, value)| capture.clone().filter(|_| value.is_none()));
+                let outcome = if !in_record_type(record, record_type) {
+                    Outcome::OtherRecordType
+                } else if !condition_matches(&record.condition, line) {
+                    Outcome::NotMatched
+                } else if let Some(name) = uncaptured {
+                    Outcome::Uncaptured(name)
+                } else if let (Some(_), Some(len)) =
+                    (record.start_on(&captures), record.length_on(&captures))
+                {
+                    match record.start_column(
+                        positions.column_mode.count(line),
+                        &captures,
+                        positions,
+                    ) {
+                        None if record.from_end => Outcome::TooShort,
+                        None => Outcome::BeforeFirst(record.shift(positions)),
+                        Some(start) => Outcome::Applied {
+                            start,
+                            end: start + len,
+                        },
+                    }
+                } else {
+                    Outcome::Unpositioned("a 'start' and 'length'")
+                };
+                traces.push(Trace {
+                    name: record.name.to_string(),
+                    record: record.record.as_ref().map(|r| r.name.clone()),
+                    condition: record.condition.clone(),
+                    position,
+                    outcome,
+                    sensitive: record.sensitive,
+                });
+            }
+        }
+        RecordList::Delimiter(delimiter, d_records) => {
+            let delimiters = delimiter.spans(positions.quote, line);
+            for record in d_records {
+                let position = written(record.field, &record.field_capture, &captures)
+                    .map_or("no position".to_owned(), |f| format!("field {}", f));
+                let outcome = if !in_record_type(record, record_type) {
+                    Outcome::OtherRecordType
+                } else if !condition_matches(&record.condition, line) {
+                    Outcome::NotMatched
+                } else if let (Some(name), None) =
+                    (&record.field_capture, record.field_on(&captures))
+                {
+                    Outcome::Uncaptured(name.clone())
+                } else if let Some(field) = record.field_on(&captures) {
+                    let field = field as isize + 1 - positions.index_base as isize
+                        + record.shift(positions);
+                    if field < 1 {
+                        Outcome::BeforeFirst(record.shift(positions))
+                    } else {
+                        let (start, end) = field_columns(&delimiters, field as usize, length);
+                        Outcome::Applied { start, end }
+                    }
+                } else {
+                    Outcome::Unpositioned("a 'field'")
+                };
+                traces.push(Trace {
+                    name: record.name.to_string(),
+                    record: record.record.as_ref().map(|r| r.name.clone()),
+                    condition: record.condition.clone(),
+                    position,
+                    outcome,
+                    sensitive: record.sensitive,
+                });
+            }
+        }
+    }
+    traces
+}
+
+/// Print the trace of one line.
+fn print_trace(
+    args: &Args,
+    records: &RecordList,
+    positions: PositionOptions,
+    line_index: usize,
+    line: &str,
+    traces: &[Trace],
+) {
+    let columns = match records {
+        RecordList::FixedWidth(_) => Columns::new(line, positions.column_mode),
+        RecordList::Delimiter(..) => Columns::new(line, ColumnMode::Chars),
+    };
+    let length = columns.units();
+    let units = line_length(records, line, positions);
+    // the line as it's shown, with the values of sensitive fields masked
+    let mut shown: Vec<char> = line.chars().collect();
+    for trace in traces.iter().filter(|t| t.sensitive) {
+        if let Outcome::Applied { start, end } = trace.outcome {
+            let (from, to) = (
+                columns.char_index(start.min(length)),
+                columns.char_index(end.min(length)),
+            );
+            let masked = mask(&shown[from..to].iter().collect::<String>());
+            shown.splice(from..to, masked.chars());
+        }
+    }
+    let shown: String = shown.into_iter().collect();
+    println!(
+        "Line {} ({} {}): {:?}",
+        line_index + 1,
+        units,
+        unit_name(records),
+        shown
+    );
+    if !records.record_types().is_empty() {
+        match records.record_type(line) {
+            Some(record_type) => println!(
+                "  record type '{}'{}",
+                record_type.name,
+                record_type
+                    .condition
+                    .as_ref()
+                    .map_or(String::new(), |c| format!(", as it matches `{}`", c))
+            ),
+            None => println!("  of no record type, as it matches none of their conditions"),
+        }
+    }
+    for (i, trace) in traces.iter().enumerate() {
+        let condition = match &trace.condition {
+            Some(c) => format!("condition `{}`", c),
+            None => "no condition".to_owned(),
+        };
+        let record = trace
+            .record
+            .as_ref()
+            .map_or(String::new(), |r| format!("record '{}', ", r));
+        println!(
+            "  {} ({}{}, {}):",
+            trace.name, record, trace.position, condition
+        );
+        match &trace.outcome {
+            Outcome::OtherRecordType => {
+                println!("    not applied: the line isn't of the rule's record type")
+            }
+            Outcome::NotMatched => println!("    not applied: the condition didn't match"),
+            Outcome::Unpositioned(needs) => {
+                println!("    skipped: the rule needs {} to be filled in", needs)
+            }
+            Outcome::Uncaptured(name) => println!(
+                "    skipped: `${{{}}}` didn't capture a number on this line",
+                name
+            ),
+            Outcome::BeforeFirst(shift) => println!(
+                "    skipped: with a shift of {}, it would start before the first {}",
+                shift,
+                match records {
+                    RecordList::FixedWidth(_) => "column",
+                    RecordList::Delimiter(..) => "field",
+                }
+            ),
+            Outcome::TooShort => {
+                println!("    skipped: the line is too short for it to start that far from the end")
+            }
+            Outcome::Applied { start, end } => {
+                let (from, to) = (columns.char_index(*start), columns.char_index(*end));
+                let value: String = shown.chars().skip(from).take(to - from).collect();
+                if end > start {
+                    println!(
+                        "    applied to columns {}-{}: {:?}",
+                        start + args.index_base as usize,
+                        end - 1 + args.index_base as usize,
+                        value
+                    );
+                } else {
+                    println!(
+                        "    applied to an empty field at column {}",
+                        start + args.index_base as usize
+                    );
+                }
+                if *start >= length {
+                    println!(
+                        "    the line ends before it starts, so it is {}",
+                        match args.short_lines {
+                            ShortLinePolicy::Truncate => "not shown",
+                            ShortLinePolicy::Pad => "shown over padding",
+                            ShortLinePolicy::Error => "an error",
+                        }
+                    );
+                } else if *end > length {
+                    println!(
+                        "    the line ends {} column(s) short of it, so it is {}",
+                        end - length,
+                        match args.short_lines {
+                            ShortLinePolicy::Truncate => "cut short",
+                            ShortLinePolicy::Pad => "padded out",
+                            ShortLinePolicy::Error => "an error",
+                        }
+                    );
+                }
+                for earlier in &traces[..i] {
+                    if let Outcome::Applied {
+                        start: earlier_start,
+                        end: earlier_end,
+                    } = earlier.outcome
+                    {
+                        if earlier_start < *end && *start < earlier_end {
+                            println!(
+                                "    overlaps '{}' in columns {}-{}",
+                                earlier.name,
+                                earlier_start.max(*start) + args.index_base as usize,
+                                earlier_end.min(*end) - 1 + args.index_base as usize
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut uncovered = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let delimiters = records
+        .delimiter()
+        .map_or(Vec::new(), |d| d.spans(positions.quote, line));
+    let mut col = 0;
+    while col < chars.len() {
+        let covered = |c: usize| {
+            delimiters.iter().any(|d| d.contains(&c))
+                || traces.iter().any(|t| {
+                    matches!(t.outcome, Outcome::Applied { start, end } if start <= c && c < end)
+                })
+        };
+        if covered(col) {
+            col += 1;
+            continue;
+        }
+        let from = col;
+        while col < chars.len() && !covered(col) {
+            col += 1;
+        }
+        uncovered.push(format!(
+            "{}-{}",
+            from + args.index_base as usize,
+            col - 1 + args.index_base as usize
+        ));
+    }
+    if !uncovered.is_empty() {
+        println!("  not in any field: columns {}", uncovered.join(", "));
+    }
+}
+
+/// Run the `explain` subcommand.
+pub fn run(args: &Args, explain: &ExplainArgs) -> anyhow::Result<()> {
+    let (_, records, positions, assembly) =
+        load_syntax(args, &explain.syntax_file, Some(&explain.input_file))?;
+    let mut wanted = Vec::new();
+    for lines in &explain.lines {
+        wanted.push(parse_lines(lines)?);
+    }
+    let last = wanted.iter().map(|(_, last)| *last).max().unwrap_or(0);
+
+    let file = open_buffered(args, &explain.input_file)?;
+    let mut read = 0;
+    let mut printed = false;
+    for line in read_records(args, file, &assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if idx >= last {
+            break;
+        }
+        // a record joined from several lines is explained if any of them is asked for
+        read = idx + physical_lines(&line);
+        if !wanted
+            .iter()
+            .any(|(first, last)| *first <= read && idx < *last)
+        {
+            continue;
+        }
+        let line = record_text(&line);
+        if printed {
+            println!();
+        }
+        printed = true;
+        let traces = trace_line(&records, &line, positions);
+        print_trace(args, &records, positions, idx, &line, &traces);
+    }
+    if read < last {
+        bail!("The input file only has {} line(s).", read);
+    }
+    Ok(())
+}
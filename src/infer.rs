@@ -0,0 +1,235 @@
+//! The `infer` subcommand, which proposes a draft syntax file for a sample of a file whose layout isn't known: the
+//! delimiter and number of fields of a delimited file, or the column boundaries of a fixed width one, found where the
+//! kind of character in each column changes.
+
+use std::{io::BufReader, io::Write};
+
+use anyhow::{bail, Context};
+use log::info;
+
+use crate::{open_input, parse_delimiter, Args, BoundedLines, Delimiter, InferArgs};
+
+/// The delimiters tried, in order of preference when more than one splits every line alike.
+const CANDIDATE_DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+/// The kinds of character a column of a fixed width file is told apart by. Spaces aren't a kind of their own, as they
+/// pad the values of every kind of field.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Kinds {
+    digit: bool,
+    letter: bool,
+    other: bool,
+}
+
+impl Kinds {
+    fn of(c: char) -> Self {
+        Self {
+            digit: c.is_ascii_digit(),
+            letter: c.is_alphabetic(),
+            other: !(c.is_ascii_digit() || c.is_alphabetic() || c == ' '),
+        }
+    }
+
+    fn add(&mut self, other: Kinds) {
+        self.digit |= other.digit;
+        self.letter |= other.letter;
+        self.other |= other.other;
+    }
+
+    /// What a field holding these kinds of character probably is, for its draft name.
+    fn name(self) -> &'static str {
+        match (self.digit, self.letter, self.other) {
+            (false, false, false) => "filler",
+            (true, false, false) => "number",
+            (false, true, false) => "text",
+            _ => "field",
+        }
+    }
+}
+
+/// Run the `infer` subcommand.
+pub fn run(args: &Args, infer: &InferArgs) -> anyhow::Result<()> {
+    let file = open_input(args, &infer.input_file)?;
+    let mut sample = Vec::new();
+    for line in BoundedLines::new(BufReader::new(file), args.max_line_length, args.encoding)
+        .fixed_length(args.record_length)
+        .line_endings(args)
+    {
+        if sample.len() == infer.lines {
+            break;
+        }
+        let line = line.context("Failed to read line from input file.")?;
+        if !line.trim().is_empty() {
+            sample.push(line);
+        }
+    }
+    if sample.is_empty() {
+        bail!("The sample has no lines to infer a layout from.");
+    }
+
+    let delimiter = match parse_delimiter(args)? {
+        Some(delimiter) => Some(delimiter),
+        None if infer.fixed_width => None,
+        None => detect_delimiter(args, &sample),
+    };
+    let mut out: Vec<u8> = Vec::new();
+    {
+        let mut writer = csv::Writer::from_writer(&mut out);
+        match &delimiter {
+            Some(delimiter) => write_delimited(args, &sample, delimiter, &mut writer)?,
+            None => write_fixed_width(args, &sample, &mut writer)?,
+        }
+        writer.flush()?;
+    }
+
+    match &infer.output {
+        Some(path) => std::fs::write(path, out).context("Failed to write syntax file.")?,
+        None => std::io::stdout()
+            .write_all(&out)
+            .context("Failed to write syntax file.")?,
+    }
+    if args.delimiter.is_none() {
+        if let Some(delimiter) = &delimiter {
+            // the syntax file doesn't say it's for delimiter mode, so the delimiter has to be given whenever it's used
+            let shown = delimiter.as_str().escape_default().to_string();
+            eprintln!(
+                "The fields are separated by `{}`. Give `--delimiter {}` when using the syntax file.",
+                shown,
+                if shown == delimiter.as_str() {
+                    format!("'{}'", shown)
+                } else {
+                    format!("$'{}'", shown)
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The delimiter that splits every line of the sample into the same number of fields, the most it splits them into
+/// if several do. `None` if none does, as the sample is then probably fixed width.
+fn detect_delimiter(args: &Args, sample: &[String]) -> Option<Delimiter> {
+    let mut best: Option<(Delimiter, usize)> = None;
+    for candidate in CANDIDATE_DELIMITERS {
+        let delimiter = Delimiter::from(candidate);
+        let fields = delimiter.split(args.quote, &sample[0]).len();
+        if fields < 2
+            || sample[1..]
+                .iter()
+                .any(|line| delimiter.split(args.quote, line).len() != fields)
+        {
+            continue;
+        }
+        if best.as_ref().is_none_or(|(_, most)| fields > *most) {
+            best = Some((delimiter, fields));
+        }
+    }
+    best.map(|(delimiter, fields)| {
+        info!("Found {} fields separated by `{}`", fields, delimiter);
+        delimiter
+    })
+}
+
+/// Write a rule for each field of a delimited sample, named after the first line if it's a header.
+fn write_delimited(
+    args: &Args,
+    sample: &[String],
+    delimiter: &Delimiter,
+    writer: &mut csv::Writer<&mut Vec<u8>>,
+) -> anyhow::Result<()> {
+    let index_base = args.index_base as usize;
+    let lines: Vec<Vec<String>> = sample
+        .iter()
+        .map(|line| delimiter.split(args.quote, line))
+        .collect();
+    let fields = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let kinds_of = |lines: &[Vec<String>], field: usize| {
+        let mut kinds = Kinds::default();
+        for value in lines.iter().filter_map(|line| line.get(field)) {
+            value.chars().for_each(|c| kinds.add(Kinds::of(c)));
+        }
+        kinds
+    };
+
+    // a first line with no numbers, over fields that are numbers on every other line, names the fields
+    let header = lines.len() > 1
+        && !lines[0]
+            .iter()
+            .any(|value| value.contains(|c: char| c.is_ascii_digit()))
+        && (0..fields).any(|field| kinds_of(&lines[1..], field) == Kinds::of('0'));
+    let body = if header { &lines[1..] } else { &lines[..] };
+    if header {
+        info!("Naming the fields after the header line");
+    }
+
+    writer.write_record(["field", "name"])?;
+    for field in 0..fields {
+        let name = match lines[0].get(field).filter(|_| header) {
+            Some(name) => draft_name(name, field + index_base),
+            None => format!("{}_{}", kinds_of(body, field).name(), field + index_base),
+        };
+        writer.write_record([(field + index_base).to_string(), name])?;
+    }
+    Ok(())
+}
+
+/// Write a rule for each run of columns of a fixed width sample that hold the same kinds of character, which is where
+/// one field ends and the next begins on most files: a code of letters before a number, or a column of spaces between
+/// two fields.
+fn write_fixed_width(
+    args: &Args,
+    sample: &[String],
+    writer: &mut csv::Writer<&mut Vec<u8>>,
+) -> anyhow::Result<()> {
+    let index_base = args.index_base as usize;
+    let mut columns: Vec<Kinds> = Vec::new();
+    for line in sample {
+        for (col, c) in line.chars().enumerate() {
+            if col == columns.len() {
+                columns.push(Kinds::default());
+            }
+            columns[col].add(Kinds::of(c));
+        }
+    }
+
+    writer.write_record(["start", "length", "name"])?;
+    let mut start = 0;
+    for col in 1..=columns.len() {
+        if col < columns.len() && columns[col] == columns[start] {
+            continue;
+        }
+        writer.write_record([
+            (start + index_base).to_string(),
+            (col - start).to_string(),
+            format!("{}_{}", columns[start].name(), start + index_base),
+        ])?;
+        start = col;
+    }
+    info!(
+        "Found {} column(s) over {} line(s)",
+        columns.len(),
+        sample.len()
+    );
+    Ok(())
+}
+
+/// A rule name from a header, e.g. `customer_name` from `Customer Name`, or one from the field's position if the header
+/// is blank.
+fn draft_name(header: &str, field: usize) -> String {
+    let name: String = header
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        format!("field_{}", field)
+    } else {
+        name
+    }
+}
@@ -0,0 +1,286 @@
+//! The `join` subcommand, which matches the lines of two files on a key field, to reconcile pairs of files such as
+//! requests and their responses.
+
+use std::io::BufReader;
+
+use anyhow::{bail, Context};
+
+use crate::{
+    compression, escape_html, field_colors, field_value_styles, file_name, is_skipped, load_syntax,
+    locale::Message, mask_value, prepare_line, redact, region_value, render_options,
+    select::read_records, value_stylesheet, Args, Assembly, JoinArgs, JoinFormat, PositionOptions,
+    PreparedLine, RecordList, ValueStyle,
+};
+
+/// A line of one of the files being joined, ready to render, with its sensitive fields masked.
+struct KeyedLine {
+    index: usize,
+    key: String,
+    /// The key as it's shown: masked, if the key field is sensitive.
+    shown_key: String,
+    prepared: PreparedLine,
+}
+
+/// The lines of both files with one key.
+struct JoinedKey {
+    key: String,
+    shown_key: String,
+    left: Vec<usize>,
+    right: Vec<usize>,
+}
+
+/// Read every line of a file which has the key field.
+fn read_keyed_lines(
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+    key: &str,
+) -> anyhow::Result<Vec<KeyedLine>> {
+    let mut keyed = Vec::new();
+    let file = compression::open(input_file, args.compression)
+        .with_context(|| format!("Failed to open input file '{}'.", input_file))?;
+    for line in read_records(args, BufReader::new(file), assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let mut prepared = prepare_line(records, line, idx, positions, args.short_lines, false)?;
+        let length = prepared.text.chars().count();
+        let Some(region) = prepared
+            .regions
+            .iter()
+            .find(|r| &*r.name == key && r.start < length)
+        else {
+            continue;
+        };
+        let key = region_value(&prepared.text, region, None).trim().to_owned();
+        let shown_key = mask_value(region, key.clone());
+        redact(&mut prepared);
+        keyed.push(KeyedLine {
+            index: idx,
+            key,
+            shown_key,
+            prepared,
+        });
+    }
+    Ok(keyed)
+}
+
+/// Group the lines of both files by key: keys in the order they first appear on the left, then those only on the right.
+fn join_keys(left: &[KeyedLine], right: &[KeyedLine]) -> Vec<JoinedKey> {
+    let mut joined: Vec<JoinedKey> = Vec::new();
+    let mut add = |line: &KeyedLine, i: usize, is_left: bool| {
+        let entry = match joined.iter().position(|j| j.key == line.key) {
+            Some(e) => &mut joined[e],
+            None => {
+                joined.push(JoinedKey {
+                    key: line.key.clone(),
+                    shown_key: line.shown_key.clone(),
+                    left: Vec::new(),
+                    right: Vec::new(),
+                });
+                joined.last_mut().unwrap()
+            }
+        };
+        if is_left {
+            entry.left.push(i);
+        } else {
+            entry.right.push(i);
+        }
+    };
+    for (i, line) in left.iter().enumerate() {
+        add(line, i, true);
+    }
+    for (i, line) in right.iter().enumerate() {
+        add(line, i, false);
+    }
+    joined
+}
+
+/// Run the `join` subcommand.
+pub fn run(args: &Args, join: &JoinArgs) -> anyhow::Result<()> {
+    let (_, left_records, positions, left_assembly) =
+        load_syntax(args, &join.syntax_file, Some(&join.input_file))?;
+    let (_, right_records, _, right_assembly) = load_syntax(
+        args,
+        join.with_syntax.as_deref().unwrap_or(&join.syntax_file),
+        Some(&join.with),
+    )?;
+    if !left_records.field_names().contains(&join.key) {
+        bail!(
+            "There is no field '{}' in the syntax of {}.",
+            join.key,
+            join.input_file
+        );
+    }
+    if !right_records.field_names().contains(&join.key) {
+        bail!(
+            "There is no field '{}' in the syntax of {}.",
+            join.key,
+            join.with
+        );
+    }
+
+    let left = read_keyed_lines(
+        args,
+        &join.input_file,
+        &left_records,
+        positions,
+        &left_assembly,
+        &join.key,
+    )?;
+    let right = read_keyed_lines(
+        args,
+        &join.with,
+        &right_records,
+        positions,
+        &right_assembly,
+        &join.key,
+    )?;
+    let joined = join_keys(&left, &right);
+
+    match join.format {
+        JoinFormat::Html => {
+            let colors: Vec<&str> = field_colors(&left_records)
+                .into_iter()
+                .chain(field_colors(&right_records))
+                .collect();
+            let value_styles: Vec<ValueStyle> = field_value_styles(&left_records)
+                .into_iter()
+                .chain(field_value_styles(&right_records))
+                .collect();
+            print_join_html(args, join, &colors, &value_styles, &left, &right, &joined)
+        }
+        JoinFormat::Csv => write_join_csv(&left_records, &right_records, &left, &right, &joined),
+    }
+}
+
+/// Print the joined lines as an HTML report, with a row for each key and the lines of each file side by side.
+fn print_join_html(
+    args: &Args,
+    join: &JoinArgs,
+    field_colors: &[&str],
+    value_styles: &[ValueStyle],
+    left: &[KeyedLine],
+    right: &[KeyedLine],
+    joined: &[JoinedKey],
+) -> anyhow::Result<()> {
+    let mut render = render_options(args)?;
+    let locale = args.locale;
+    let title = locale.format(
+        Message::JoinTitle,
+        &[&file_name(&join.input_file), &file_name(&join.with)],
+    );
+    println!(r#"<!doctype html><html lang="{}">"#, locale.tag());
+    println!(
+        "<head><meta charset=\"utf8\"><title>{}</title><style>\n{}</style></head>",
+        escape_html(&title),
+        render.stylesheet(field_colors.iter().copied()) + &value_stylesheet(value_styles)
+    );
+    println!("<body>");
+    println!("<h1>{}</h1>", escape_html(&title));
+    println!(
+        r#"<table style="border-collapse:collapse;"><tr><th>{}</th><th>{}</th><th>{}</th></tr>"#,
+        escape_html(&join.key),
+        escape_html(&file_name(&join.input_file)),
+        escape_html(&file_name(&join.with))
+    );
+    for key in joined {
+        println!(r#"<tr style="border-top:1px solid #ddd; vertical-align:top;">"#);
+        println!("<td><code>{}</code></td>", escape_html(&key.shown_key));
+        // each side's lines are numbered from 1, so their ids say which side they're on to be told apart
+        for (lines, side, id_prefix) in [(&key.left, left, "left-"), (&key.right, right, "right-")]
+        {
+            render.id_prefix = id_prefix;
+            if lines.is_empty() {
+                println!(
+                    r#"<td style="color:red;">{}</td>"#,
+                    locale.text(Message::NoMatchingLines)
+                );
+                continue;
+            }
+            println!(r#"<td><pre dir="ltr" style="color:red; margin:0;">"#);
+            for &i in lines {
+                let line = &side[i];
+                render.write_html_line(&mut std::io::stdout(), line.index, &line.prepared, None)?;
+            }
+            println!("</pre></td>");
+        }
+        println!("</tr>");
+    }
+    println!("</table>");
+    println!("</body></html>");
+    Ok(())
+}
+
+/// Write the joined lines as CSV: one row for each pair of lines with the same key, or for each line with no match,
+/// with every field of both lines.
+fn write_join_csv(
+    left_records: &RecordList,
+    right_records: &RecordList,
+    left: &[KeyedLine],
+    right: &[KeyedLine],
+    joined: &[JoinedKey],
+) -> anyhow::Result<()> {
+    let left_names = left_records.field_names();
+    let right_names = right_records.field_names();
+    let mut writer = csv::Writer::from_writer(std::io::stdout().lock());
+
+    let mut header = vec![
+        "key".to_owned(),
+        "left_line".to_owned(),
+        "right_line".to_owned(),
+    ];
+    header.extend(left_names.iter().map(|n| format!("left.{}", n)));
+    header.extend(right_names.iter().map(|n| format!("right.{}", n)));
+    writer
+        .write_record(&header)
+        .context("Failed to write CSV.")?;
+
+    let values = |line: Option<&KeyedLine>, names: &[String]| -> Vec<String> {
+        names
+            .iter()
+            .map(|name| {
+                line.and_then(|line| {
+                    let length = line.prepared.text.chars().count();
+                    line.prepared
+                        .regions
+                        .iter()
+                        .find(|r| *r.name == *name && r.start < length)
+                        .map(|r| region_value(&line.prepared.text, r, None))
+                })
+                .unwrap_or_default()
+            })
+            .collect()
+    };
+
+    for key in joined {
+        // every left line pairs with every right line, and a line with no match pairs with nothing
+        let lefts: Vec<Option<&KeyedLine>> = if key.left.is_empty() {
+            vec![None]
+        } else {
+            key.left.iter().map(|&i| Some(&left[i])).collect()
+        };
+        let rights: Vec<Option<&KeyedLine>> = if key.right.is_empty() {
+            vec![None]
+        } else {
+            key.right.iter().map(|&i| Some(&right[i])).collect()
+        };
+        for l in &lefts {
+            for r in &rights {
+                let mut row = vec![
+                    key.shown_key.clone(),
+                    l.map_or(String::new(), |l| (l.index + 1).to_string()),
+                    r.map_or(String::new(), |r| (r.index + 1).to_string()),
+                ];
+                row.extend(values(*l, &left_names));
+                row.extend(values(*r, &right_names));
+                writer.write_record(&row).context("Failed to write CSV.")?;
+            }
+        }
+    }
+    writer.flush().context("Failed to write CSV.")?;
+    Ok(())
+}
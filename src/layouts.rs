@@ -0,0 +1,135 @@
+//! Layouts files: one syntax file for each version of a feed's layout, such as its v2 and v3 records, with the condition
+//! picking each by the first line of the input, usually its header record. A layouts file is a CSV file with the
+//! columns `layout`, `condition` and `syntax_file`:
+//!
+//! ```csv
+//! layout,condition,syntax_file
+//! v3,^HDR.{8}V3,feed-v3.csv
+//! v2,,feed-v2.csv
+//! ```
+//!
+//! The first layout whose condition matches the first line is used, and a layout without a condition is used if no
+//! earlier one matches. Syntax files are found relative to the layouts file.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{compiled, Condition};
+
+/// One version of a layout.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub name: String,
+    /// The regex the first line of the input must match for the layout to be used. A layout without one is used if no
+    /// earlier layout matches.
+    pub condition: Option<Condition>,
+    /// The syntax file describing the layout, relative to the working directory.
+    pub syntax_file: PathBuf,
+    /// The line of the layouts file the layout was given on.
+    pub line: u64,
+}
+
+/// Whether `text`, the text of a syntax file, is a layouts file rather than a syntax file itself: whether its header
+/// has the columns `layout` and `syntax_file`.
+pub fn is_layouts_file(text: &str) -> bool {
+    let header = text.lines().next().unwrap_or_default();
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(header.as_bytes());
+    reader.records().next().is_some_and(|header| {
+        header.is_ok_and(|header| {
+            header.iter().any(|h| h == "layout") && header.iter().any(|h| h == "syntax_file")
+        })
+    })
+}
+
+/// Read `syntax_file`, returning its layouts if it's a layouts file, or `None` if it's a syntax file itself.
+pub fn read(syntax_file: &str) -> anyhow::Result<Option<Vec<Layout>>> {
+    let bytes = fs::read(syntax_file).context("Failed to read from syntax file.")?;
+    if compiled::is_compiled(&bytes) {
+        return Ok(None);
+    }
+    match std::str::from_utf8(&bytes) {
+        Ok(text) if is_layouts_file(text) => {
+            let dir = Path::new(syntax_file).parent().unwrap_or(Path::new(""));
+            parse(text, dir).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parse the text of a layouts file, whose syntax files are relative to `dir`.
+pub fn parse(text: &str, dir: &Path) -> anyhow::Result<Vec<Layout>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (name_column, condition_column, file_column) =
+        (column("layout"), column("condition"), column("syntax_file"));
+    let mut layouts: Vec<Layout> = Vec::new();
+    for result in reader.records() {
+        let row = result.context("Failed to parse layout.")?;
+        let line = row.position().map_or(0, |p| p.line());
+        let value = |index: Option<usize>| {
+            index
+                .and_then(|i| row.get(i))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        let Some(name) = value(name_column) else {
+            bail!("Layouts file line {}: the layout has no name.", line);
+        };
+        if let Some(earlier) = layouts.iter().find(|l| l.name == name) {
+            bail!(
+                "Layouts file line {}: the layout '{}' is already given on line {}.",
+                line,
+                name,
+                earlier.line
+            );
+        }
+        let Some(syntax_file) = value(file_column) else {
+            bail!(
+                "Layouts file line {}: the layout '{}' has no `syntax_file`.",
+                line,
+                name
+            );
+        };
+        let condition = value(condition_column)
+            .map(|spec| {
+                Condition::parse(spec).with_context(|| {
+                    format!(
+                        "Layouts file line {}: column `condition` is not a valid regex.",
+                        line
+                    )
+                })
+            })
+            .transpose()?;
+        if condition.as_ref().is_some_and(Condition::is_field_test) {
+            bail!(
+                "Layouts file line {}: the condition of '{}' should be a regex, as it's matched before any layout's fields are known.",
+                line,
+                name
+            );
+        }
+        layouts.push(Layout {
+            name: name.to_owned(),
+            condition,
+            syntax_file: dir.join(syntax_file),
+            line,
+        });
+    }
+    if layouts.is_empty() {
+        bail!("The layouts file gives no layouts.");
+    }
+    Ok(layouts)
+}
+
+/// The layout of an input whose first line is `first_line`: the first whose condition it matches or which has none.
+pub fn select<'a>(layouts: &'a [Layout], first_line: &str) -> Option<&'a Layout> {
+    layouts
+        .iter()
+        .find(|l| l.condition.as_ref().is_none_or(|c| c.is_match(first_line)))
+}
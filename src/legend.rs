@@ -0,0 +1,279 @@
+//! The legend added by `--legend`: a table of the colour, name and position of each field, for each record type, so
+//! a printed report can be read without hovering over its fields.
+
+use std::io::{self, Write};
+
+use anyhow::Context;
+
+use crate::{
+    escape_html, foreground_color,
+    locale::{Locale, Message},
+    parse_hex_color,
+    pdf::{Pdf, Style, BLACK},
+    prepare_report_line, record_text, record_type_label, record_type_of, redacted,
+    select::read_selected,
+    Args, Assembly, PositionOptions, PreparedLine, RecordList, Renderer,
+};
+
+/// Where the legend goes in the report.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegendPosition {
+    /// Before the lines of the report
+    Top,
+    /// After the lines of the report
+    Bottom,
+}
+
+/// A field as the legend lists it.
+struct Entry {
+    /// The colours the field has been highlighted in, which can vary between lines when other fields are missing.
+    colors: Vec<String>,
+    name: String,
+    /// The column the field starts at and its length, or in delimiter mode its field number, counted from the index
+    /// base.
+    position: (usize, Option<usize>),
+}
+
+/// The fields seen in each record type, in the order the types are first seen.
+#[derive(Default)]
+pub struct Legend {
+    record_types: Vec<(Option<String>, Vec<Entry>)>,
+}
+
+impl Legend {
+    /// Read the lines of the input to be output to find the fields of each record type, as they will be rendered.
+    pub fn scan(
+        args: &Args,
+        input_file: &str,
+        records: &RecordList,
+        positions: PositionOptions,
+        assembly: &Assembly,
+        render: &Renderer,
+    ) -> anyhow::Result<Self> {
+        let mut legend = Self::default();
+        for line in read_selected(args, input_file, assembly)? {
+            let (idx, line) = line.context("Failed to read line from input file.")?;
+            let record_type = record_type_of(records, &record_text(&line));
+            let prepared =
+                prepare_report_line(args, records, line, idx, positions, args.ghost_cells)?;
+            legend.add_line(
+                records,
+                positions,
+                render,
+                record_type,
+                &redacted(&prepared),
+            );
+        }
+        Ok(legend)
+    }
+
+    /// Add the fields of `prepared`, a line of `record_type`, that haven't been seen in that record type yet.
+    pub fn add_line(
+        &mut self,
+        records: &RecordList,
+        positions: PositionOptions,
+        render: &Renderer,
+        record_type: Option<String>,
+        prepared: &PreparedLine,
+    ) {
+        let index = match self
+            .record_types
+            .iter()
+            .position(|(t, _)| *t == record_type)
+        {
+            Some(index) => index,
+            None => {
+                self.record_types.push((record_type, Vec::new()));
+                self.record_types.len() - 1
+            }
+        };
+        let entries = &mut self.record_types[index].1;
+
+        let colors = render.field_colors(&prepared.text, &prepared.regions);
+        for (region, color) in prepared.regions.iter().zip(colors) {
+            let Some(color) = color else {
+                continue;
+            };
+            let position = match records.delimiter() {
+                None => (
+                    region.start + positions.index_base,
+                    Some(region.end - region.start),
+                ),
+                Some(_) => (
+                    prepared
+                        .delimiters
+                        .iter()
+                        .filter(|d| d.end <= region.start)
+                        .count()
+                        + positions.index_base,
+                    None,
+                ),
+            };
+            match entries
+                .iter_mut()
+                .find(|e| *e.name == *region.name && e.position == position)
+            {
+                Some(entry) if entry.colors.iter().any(|c| c == color) => (),
+                Some(entry) => entry.colors.push(color.to_owned()),
+                None => entries.push(Entry {
+                    colors: vec![color.to_owned()],
+                    name: region.name.to_string(),
+                    position,
+                }),
+            }
+        }
+    }
+
+    /// Write the legend as a section of the report.
+    pub fn write_html(
+        &self,
+        out: &mut dyn Write,
+        records: &RecordList,
+        locale: Locale,
+    ) -> io::Result<()> {
+        let defined_types = !records.record_types().is_empty();
+        writeln!(out, "<h2>{}</h2>", locale.text(Message::Legend))?;
+        for (record_type, entries) in &self.record_types {
+            if entries.is_empty() {
+                continue;
+            }
+            let mut entries: Vec<&Entry> = entries.iter().collect();
+            entries.sort_by_key(|e| e.position);
+
+            writeln!(
+                out,
+                "<h3>{}</h3>",
+                record_type_label(record_type, defined_types, locale)
+            )?;
+            match records.delimiter() {
+                None => {
+                    writeln!(
+                        out,
+                        "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+                        locale.text(Message::Colour),
+                        locale.text(Message::Field),
+                        locale.text(Message::Start),
+                        locale.text(Message::Length)
+                    )?;
+                }
+                Some(_) => {
+                    writeln!(
+                        out,
+                        "<table><tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+                        locale.text(Message::Colour),
+                        locale.text(Message::Field),
+                        locale.text(Message::FieldNumber)
+                    )?;
+                }
+            }
+            for entry in entries {
+                // browsers leave backgrounds out when printing unless told otherwise
+                let swatches: Vec<String> = entry
+                    .colors
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            r#"<code style="background:#{}; color:#{}; border:1px solid #909090; print-color-adjust:exact; -webkit-print-color-adjust:exact;">#{}</code>"#,
+                            c,
+                            foreground_color(c),
+                            escape_html(c)
+                        )
+                    })
+                    .collect();
+                write!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td>",
+                    swatches.join(" "),
+                    escape_html(&entry.name),
+                    entry.position.0
+                )?;
+                if let Some(length) = entry.position.1 {
+                    write!(out, "<td>{}</td>", length)?;
+                }
+                writeln!(out, "</tr>")?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        Ok(())
+    }
+
+    /// Write the legend as a section of a PDF report, with the same columns as the HTML legend's table.
+    pub fn write_pdf(&self, pdf: &mut Pdf, records: &RecordList, locale: Locale) -> io::Result<()> {
+        let defined_types = !records.record_types().is_empty();
+        pdf.heading(locale.text(Message::Legend))?;
+        for (record_type, entries) in &self.record_types {
+            if entries.is_empty() {
+                continue;
+            }
+            let mut entries: Vec<&Entry> = entries.iter().collect();
+            entries.sort_by_key(|e| e.position);
+
+            let label = match record_type {
+                Some(record_type) => record_type.clone(),
+                None if defined_types => locale.text(Message::NoRecordType).to_owned(),
+                None => locale.text(Message::Unconditional).to_owned(),
+            };
+            pdf.heading(&label)?;
+            let mut headers = vec![locale.text(Message::Colour), locale.text(Message::Field)];
+            match records.delimiter() {
+                None => headers.extend([locale.text(Message::Start), locale.text(Message::Length)]),
+                Some(_) => headers.push(locale.text(Message::FieldNumber)),
+            }
+            // each swatch is the colour's code, `#rrggbb`, and a space
+            let widths = [
+                entries
+                    .iter()
+                    .map(|e| e.colors.len() * 8)
+                    .chain([headers[0].chars().count() + 1])
+                    .max()
+                    .unwrap_or(0),
+                entries
+                    .iter()
+                    .map(|e| e.name.chars().count() + 1)
+                    .chain([headers[1].chars().count() + 1])
+                    .max()
+                    .unwrap_or(0),
+                headers.get(2).map_or(0, |h| h.chars().count().max(6) + 1),
+            ];
+            let header: String = headers
+                .iter()
+                .enumerate()
+                .map(|(i, h)| match widths.get(i) {
+                    Some(width) => format!("{:width$}", h, width = width),
+                    None => h.to_string(),
+                })
+                .collect();
+            pdf.row(&[(header, Style::bold(BLACK))])?;
+            for entry in entries {
+                let mut runs = Vec::new();
+                for color in &entry.colors {
+                    let background = parse_hex_color(color);
+                    runs.push((
+                        format!("#{}", color),
+                        Style {
+                            background,
+                            ..Style::plain(
+                                parse_hex_color(foreground_color(color)).unwrap_or(BLACK),
+                            )
+                        },
+                    ));
+                    runs.push((" ".to_owned(), Style::plain(BLACK)));
+                }
+                let mut rest = " ".repeat(widths[0].saturating_sub(entry.colors.len() * 8));
+                rest.push_str(&format!(
+                    "{:width$}{:<width2$}",
+                    entry.name,
+                    entry.position.0,
+                    width = widths[1],
+                    width2 = widths[2]
+                ));
+                if let Some(length) = entry.position.1 {
+                    rest.push_str(&length.to_string());
+                }
+                runs.push((rest, Style::plain(BLACK)));
+                pdf.row(&runs)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+//! Highlight the fields of fixed width (or delimited) records, as the `fixedfile-highlighter` command does.
+//!
+//! A [`Highlighter`] applies the rules of a syntax file to lines, finding the [`HighlightRegion`] of each field, and a
+//! [`Renderer`] writes lines out as HTML with those fields highlighted:
+//!
+//! ```
+//! use fixedfile_highlighter::{Highlighter, Renderer};
+//!
+//! let highlighter = Highlighter::from_syntax("start,length,name,condition\n1,3,id,\n4,5,amount,\n", None)?;
+//! let line = highlighter.prepare("00142.50".to_owned(), 0)?;
+//! assert_eq!(&*line.regions[1].name, "amount");
+//!
+//! let html = Renderer::default().html_line(0, &line);
+//! assert!(html.contains(r#"title="amount""#));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod compiled;
+pub mod constraints;
+pub mod copybook;
+pub mod document;
+pub mod encoding;
+pub mod layouts;
+pub mod locale;
+pub mod plugin;
+pub mod redact;
+pub mod report;
+pub mod suspicious;
+pub mod totals;
+
+mod assembly;
+mod batch;
+mod columns;
+mod delimiter;
+mod region;
+mod render;
+mod segment;
+mod syntax;
+mod value_style;
+
+pub use assembly::{physical_lines, record_text, split_record, Assembly, LINE_BREAK};
+pub use batch::{BatchRenderer, RenderedLine};
+pub use columns::{char_width, grapheme_starts, ColumnMode, Columns, DisplayWidths};
+pub use constraints::{Case, Charset, Justify, Pattern};
+pub use delimiter::Delimiter;
+pub use encoding::{Encoding, FieldType};
+pub use region::{
+    field_columns, generate_highlight_regions_from_records, is_covered, prepare_line, region_value,
+    skip_line, trailing_from, HighlightRegion, Highlighter, PreparedLine, ShortLinePolicy,
+};
+pub use render::{
+    bdi_html, bidi_control_name, escape_html, fields_json, foreground_color, hex_byte, hex_color,
+    is_dark, is_rtl, json_string, markdown_record, markdown_text, parse_hex_color, FieldBoundaries,
+    Overlaps, Renderer, DARK_GREYSCALE, DEUTERANOPIA, GREYSCALE, RAINBOW, TRITANOPIA,
+};
+pub use segment::{segments, Segment, SegmentKind};
+pub use syntax::{
+    check_placeholders, check_syntax_strictly, condition_matches, expand_delimiter,
+    expand_fixed_width, in_record_type, layout_length, line_length, parse_syntax_file,
+    place_field_conditions, placeholder, read_syntax_file, record_type_of, resolve_anchors,
+    rule_applies, unit_message, unit_name, Anchor, Captures, Condition, DelimiterHighlightRecord,
+    FixedWidthHighlightRecord, Occurs, PositionOptions, RecordList, RecordType, RecordTyper,
+    SyntaxRule,
+};
+pub use totals::{CheckResult, ControlCheck, ControlTotals, SortOrder};
+pub use value_style::{value_styles, value_stylesheet, ValuePattern, ValueStyle};
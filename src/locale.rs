@@ -0,0 +1,581 @@
+//! The fixed text of reports, in each language they can be written in (`--locale`).
+
+use std::fmt::Display;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English
+    #[default]
+    En,
+    /// German
+    De,
+    /// French
+    Fr,
+    /// Spanish
+    Es,
+}
+
+impl Locale {
+    /// The language tag of this locale, for the `lang` attribute of reports.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::Es => "es",
+        }
+    }
+
+    /// The text of `message` in this locale.
+    pub fn text(self, message: Message) -> &'static str {
+        message.translations()[self as usize]
+    }
+
+    /// The text of `message` in this locale, with each `{}` replaced in turn by one of `args`.
+    pub fn format(self, message: Message, args: &[&dyn Display]) -> String {
+        let mut parts = self.text(message).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_owned();
+        for (part, arg) in parts.zip(args) {
+            text.push_str(&arg.to_string());
+            text.push_str(part);
+        }
+        text
+    }
+}
+
+/// A piece of fixed text in a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    Title,
+    Note,
+    ShiftNote,
+    RecordShiftNote,
+    Columns,
+    Fields,
+    FooterHint,
+    KeyHint,
+    AnalysedAt,
+    ThisSyntaxFile,
+    RegionsBeyondLine,
+    MissingFromLine,
+    Delimiter,
+    Uncovered,
+    DownloadCsv,
+    DownloadJson,
+    CopyCsv,
+    CopyJson,
+    JumpToLine,
+    MinimapHint,
+    Preflight,
+    Scanned,
+    ShortestAndLongest,
+    NoProblems,
+    RuleUnpositioned,
+    RuleNeverMatched,
+    RuleNeverFits,
+    RuleSometimesFits,
+    Records,
+    Total,
+    Findings,
+    Errors,
+    ErrorsDetail,
+    Warnings,
+    WarningsDetail,
+    File,
+    Name,
+    Size,
+    Bytes,
+    Encoding,
+    LineEndings,
+    Contents,
+    RecordType,
+    Lines,
+    First,
+    Last,
+    Sections,
+    AndMore,
+    Unconditional,
+    FieldProfile,
+    ProfileSummary,
+    NoDeadFields,
+    Field,
+    Blank,
+    ZeroFilled,
+    JoinTitle,
+    NoMatchingLines,
+    MapTitle,
+    SourceField,
+    TargetField,
+    RuleNeverReached,
+    UnusedRules,
+    NoUnusedRules,
+    RuleRecordNeverMatched,
+    NoRecordType,
+    Legend,
+    Colour,
+    Start,
+    Length,
+    FieldNumber,
+    NotCovered,
+    PastEndOfLine,
+    NotEncodable,
+    NotANumber,
+    NotADate,
+    Line,
+    Value,
+    StandardInput,
+    Validation,
+    Valid,
+    Expected,
+    Close,
+    InspectHint,
+    ContinuesLine,
+    FieldStatistics,
+    DistinctValues,
+    MostCommon,
+    Numbers,
+    MoreThan,
+    Parts,
+    PartLines,
+    PreviousPart,
+    NextPart,
+    AllParts,
+    PreviousPage,
+    NextPage,
+    PageLines,
+    FilterLines,
+    SkippedLine,
+    Page,
+    BatchTitle,
+    Failed,
+    CheckFailed,
+    DuplicateKey,
+    NotAscending,
+    NotDescending,
+    PreviousProblem,
+    NextProblem,
+    ProblemLines,
+    AnyField,
+    PreviousMatch,
+    NextMatch,
+    MatchCount,
+    NoMatches,
+    TrailingData,
+    TrailingLines,
+    TrailingLinesDetail,
+}
+
+impl Message {
+    /// This message in each locale, in the order [`Locale`] lists them.
+    fn translations(self) -> [&'static str; 4] {
+        match self {
+            Message::Title => ["Analysis of {}", "Analyse von {}", "Analyse de {}", "Análisis de {}"],
+            Message::Note => ["Note:", "Hinweis:", "Remarque :", "Nota:"],
+            Message::ShiftNote => [
+                "all fields have been shifted by {} {} relative to the syntax file.",
+                "alle Felder wurden gegenüber der Syntaxdatei um {} {} verschoben.",
+                "tous les champs ont été décalés de {} {} par rapport au fichier de syntaxe.",
+                "todos los campos se han desplazado {} {} respecto al archivo de sintaxis.",
+            ],
+            Message::RecordShiftNote => [
+                "the fields of '{}' records have been shifted by {} {} relative to the syntax file.",
+                "die Felder der Datensätze '{}' wurden gegenüber der Syntaxdatei um {} {} verschoben.",
+                "les champs des enregistrements '{}' ont été décalés de {} {} par rapport au fichier de syntaxe.",
+                "los campos de los registros '{}' se han desplazado {} {} respecto al archivo de sintaxis.",
+            ],
+            Message::Columns => ["column(s)", "Spalte(n)", "colonne(s)", "columna(s)"],
+            Message::Fields => ["field(s)", "Feld(er)", "champ(s)", "campo(s)"],
+            Message::FooterHint => [
+                "Hover over text to see the name of the field it is part of. Lines of text are surrounded by '&gt;' and '&lt;' to make them clearer to see. Text in red has not matched any rules.",
+                "Fahren Sie mit der Maus über den Text, um den Namen des zugehörigen Feldes zu sehen. Textzeilen sind zur besseren Lesbarkeit von '&gt;' und '&lt;' umschlossen. Roter Text entspricht keiner Regel.",
+                "Survolez le texte pour voir le nom du champ auquel il appartient. Les lignes sont encadrées par '&gt;' et '&lt;' pour être plus lisibles. Le texte en rouge ne correspond à aucune règle.",
+                "Pase el ratón sobre el texto para ver el nombre del campo al que pertenece. Las líneas están rodeadas por '&gt;' y '&lt;' para verlas con más claridad. El texto en rojo no coincide con ninguna regla.",
+            ],
+            Message::KeyHint => [
+                "Link to a field by adding <code>?line=N&amp;field=name</code> to this page's address. Press <kbd>j</kbd>/<kbd>k</kbd> to move between lines, <kbd>n</kbd>/<kbd>p</kbd> to move between fields and <kbd>g</kbd> to jump to a line.",
+                "Verlinken Sie ein Feld, indem Sie <code>?line=N&amp;field=name</code> an die Adresse dieser Seite anhängen. Mit <kbd>j</kbd>/<kbd>k</kbd> wechseln Sie zwischen Zeilen, mit <kbd>n</kbd>/<kbd>p</kbd> zwischen Feldern und mit <kbd>g</kbd> springen Sie zu einer Zeile.",
+                "Créez un lien vers un champ en ajoutant <code>?line=N&amp;field=name</code> à l'adresse de cette page. Appuyez sur <kbd>j</kbd>/<kbd>k</kbd> pour passer d'une ligne à l'autre, sur <kbd>n</kbd>/<kbd>p</kbd> pour passer d'un champ à l'autre et sur <kbd>g</kbd> pour aller à une ligne.",
+                "Enlace a un campo añadiendo <code>?line=N&amp;field=name</code> a la dirección de esta página. Pulse <kbd>j</kbd>/<kbd>k</kbd> para moverse entre líneas, <kbd>n</kbd>/<kbd>p</kbd> para moverse entre campos y <kbd>g</kbd> para ir a una línea.",
+            ],
+            Message::AnalysedAt => [
+                "Analysed at {} by {} using {}.",
+                "Analysiert am {} von {} mit {}.",
+                "Analysé le {} par {} avec {}.",
+                "Analizado el {} por {} con {}.",
+            ],
+            Message::ThisSyntaxFile => [
+                "this syntax file",
+                "dieser Syntaxdatei",
+                "ce fichier de syntaxe",
+                "este archivo de sintaxis",
+            ],
+            Message::RegionsBeyondLine => [
+                "Matching regions extend beyond the end of line.",
+                "Passende Bereiche reichen über das Zeilenende hinaus.",
+                "Des zones correspondantes dépassent la fin de la ligne.",
+                "Hay regiones que se extienden más allá del final de la línea.",
+            ],
+            Message::MissingFromLine => [
+                "Missing from this line",
+                "Fehlt in dieser Zeile",
+                "Absent de cette ligne",
+                "Falta en esta línea",
+            ],
+            Message::Delimiter => ["Delimiter", "Trennzeichen", "Délimiteur", "Delimitador"],
+            Message::Uncovered => [
+                "{} column(s) not covered by any rule",
+                "{} Spalte(n) ohne Regel",
+                "{} colonne(s) non couverte(s) par une règle",
+                "{} columna(s) sin ninguna regla",
+            ],
+            Message::DownloadCsv => [
+                "Download as CSV",
+                "Als CSV herunterladen",
+                "Télécharger en CSV",
+                "Descargar como CSV",
+            ],
+            Message::DownloadJson => [
+                "Download as JSON",
+                "Als JSON herunterladen",
+                "Télécharger en JSON",
+                "Descargar como JSON",
+            ],
+            Message::CopyCsv => [
+                "Copy fields as a CSV row",
+                "Felder als CSV-Zeile kopieren",
+                "Copier les champs en ligne CSV",
+                "Copiar los campos como fila CSV",
+            ],
+            Message::CopyJson => [
+                "Copy fields as a JSON object",
+                "Felder als JSON-Objekt kopieren",
+                "Copier les champs en objet JSON",
+                "Copiar los campos como objeto JSON",
+            ],
+            Message::JumpToLine => [
+                "Jump to line:",
+                "Zu Zeile springen:",
+                "Aller à la ligne :",
+                "Ir a la línea:",
+            ],
+            Message::MinimapHint => [
+                "Click to jump to a line",
+                "Klicken, um zu einer Zeile zu springen",
+                "Cliquez pour aller à une ligne",
+                "Haga clic para ir a una línea",
+            ],
+            Message::Preflight => ["Preflight", "Vorabprüfung", "Vérification préalable", "Comprobación previa"],
+            Message::Scanned => [
+                "Scanned {} line(s)",
+                "{} Zeile(n) geprüft",
+                "{} ligne(s) analysée(s)",
+                "{} línea(s) analizada(s)",
+            ],
+            Message::ShortestAndLongest => [
+                "; the shortest is line {} ({} {}) and the longest is line {} ({} {})",
+                "; die kürzeste ist Zeile {} ({} {}), die längste Zeile {} ({} {})",
+                " ; la plus courte est la ligne {} ({} {}) et la plus longue la ligne {} ({} {})",
+                "; la más corta es la línea {} ({} {}) y la más larga la línea {} ({} {})",
+            ],
+            Message::NoProblems => [
+                "No problems were found with the layout.",
+                "Im Layout wurden keine Probleme gefunden.",
+                "Aucun problème n'a été trouvé dans la mise en page.",
+                "No se encontraron problemas en el diseño.",
+            ],
+            Message::RuleUnpositioned => [
+                "Rule '{}' is missing its position and will never be applied.",
+                "Regel '{}' fehlt die Position, sie wird nie angewendet.",
+                "La règle '{}' n'a pas de position et ne sera jamais appliquée.",
+                "A la regla '{}' le falta su posición y nunca se aplicará.",
+            ],
+            Message::RuleNeverMatched => [
+                "Rule '{}' has a condition (`{}`) that never matched any line.",
+                "Regel '{}' hat eine Bedingung (`{}`), die auf keine Zeile zutrifft.",
+                "La règle '{}' a une condition (`{}`) qui ne correspond à aucune ligne.",
+                "La regla '{}' tiene una condición (`{}`) que no coincide con ninguna línea.",
+            ],
+            Message::RuleNeverFits => [
+                "Rule '{}' needs {} {} but none of the {} line(s) it applies to are that long.",
+                "Regel '{}' benötigt {} {}, aber keine der {} Zeile(n), für die sie gilt, ist so lang.",
+                "La règle '{}' nécessite {} {} mais aucune des {} ligne(s) auxquelles elle s'applique n'est aussi longue.",
+                "La regla '{}' necesita {} {} pero ninguna de las {} línea(s) a las que se aplica es tan larga.",
+            ],
+            Message::RuleSometimesFits => [
+                "Rule '{}' needs {} {} and does not fit {} of the {} line(s) it applies to (shortest is {}).",
+                "Regel '{}' benötigt {} {} und passt nicht in {} der {} Zeile(n), für die sie gilt (die kürzeste hat {}).",
+                "La règle '{}' nécessite {} {} et ne tient pas dans {} des {} ligne(s) auxquelles elle s'applique (la plus courte fait {}).",
+                "La regla '{}' necesita {} {} y no cabe en {} de las {} línea(s) a las que se aplica (la más corta tiene {}).",
+            ],
+            Message::Records => ["Records", "Datensätze", "Enregistrements", "Registros"],
+            Message::Total => ["Total", "Gesamt", "Total", "Total"],
+            Message::Findings => ["Findings", "Befunde", "Constats", "Hallazgos"],
+            Message::Errors => ["Errors", "Fehler", "Erreurs", "Errores"],
+            Message::ErrorsDetail => [
+                "fields extending beyond the end of their line",
+                "Felder, die über das Ende ihrer Zeile hinausgehen",
+                "champs dépassant la fin de leur ligne",
+                "campos que se extienden más allá del final de su línea",
+            ],
+            Message::Warnings => ["Warnings", "Warnungen", "Avertissements", "Advertencias"],
+            Message::WarningsDetail => [
+                "lines with text not covered by any rule",
+                "Zeilen mit Text, für den keine Regel gilt",
+                "lignes dont du texte n'est couvert par aucune règle",
+                "líneas con texto que no cubre ninguna regla",
+            ],
+            Message::File => ["File", "Datei", "Fichier", "Archivo"],
+            Message::Name => ["Name", "Name", "Nom", "Nombre"],
+            Message::Size => ["Size", "Größe", "Taille", "Tamaño"],
+            Message::Bytes => ["{} bytes", "{} Bytes", "{} octets", "{} bytes"],
+            Message::Encoding => ["Encoding", "Kodierung", "Encodage", "Codificación"],
+            Message::LineEndings => [
+                "{}, {} line endings",
+                "{}, {}-Zeilenenden",
+                "{}, fins de ligne {}",
+                "{}, finales de línea {}",
+            ],
+            Message::Contents => ["Contents", "Inhalt", "Sommaire", "Contenido"],
+            Message::RecordType => ["Record type", "Satzart", "Type d'enregistrement", "Tipo de registro"],
+            Message::Lines => ["Lines", "Zeilen", "Lignes", "Líneas"],
+            Message::First => ["First", "Erste", "Première", "Primera"],
+            Message::Last => ["Last", "Letzte", "Dernière", "Última"],
+            Message::Sections => ["Sections", "Abschnitte", "Sections", "Secciones"],
+            Message::AndMore => ["and {} more", "und {} weitere", "et {} de plus", "y {} más"],
+            Message::Unconditional => ["Unconditional", "Ohne Bedingung", "Sans condition", "Sin condición"],
+            Message::FieldProfile => ["Field profile", "Feldprofil", "Profil des champs", "Perfil de campos"],
+            Message::ProfileSummary => [
+                "Fields that are blank or zero-filled on at least {}% of the lines they apply to:",
+                "Felder, die in mindestens {}% der Zeilen, für die sie gelten, leer oder mit Nullen gefüllt sind:",
+                "Champs vides ou remplis de zéros sur au moins {} % des lignes auxquelles ils s'appliquent :",
+                "Campos vacíos o rellenos de ceros en al menos el {} % de las líneas a las que se aplican:",
+            ],
+            Message::NoDeadFields => [
+                "No field is blank or zero-filled on {}% or more of the lines it applies to.",
+                "Kein Feld ist in {}% oder mehr der Zeilen, für die es gilt, leer oder mit Nullen gefüllt.",
+                "Aucun champ n'est vide ou rempli de zéros sur {} % ou plus des lignes auxquelles il s'applique.",
+                "Ningún campo está vacío o relleno de ceros en el {} % o más de las líneas a las que se aplica.",
+            ],
+            Message::Field => ["Field", "Feld", "Champ", "Campo"],
+            Message::Blank => ["Blank", "Leer", "Vide", "Vacío"],
+            Message::JoinTitle => ["{} joined with {}", "{} verknüpft mit {}", "{} joint à {}", "{} unido con {}"],
+            Message::NoMatchingLines => [
+                "No matching lines",
+                "Keine passenden Zeilen",
+                "Aucune ligne correspondante",
+                "Ninguna línea coincidente",
+            ],
+            Message::MapTitle => [
+                "Mapping from {} to {}",
+                "Zuordnung von {} zu {}",
+                "Correspondance de {} vers {}",
+                "Correspondencia de {} a {}",
+            ],
+            Message::SourceField => ["Source field", "Quellfeld", "Champ source", "Campo de origen"],
+            Message::TargetField => ["Target field", "Zielfeld", "Champ cible", "Campo de destino"],
+            Message::RuleNeverReached => [
+                "Rule '{}' applies to {} line(s), but none of them are long enough to reach it.",
+                "Regel '{}' gilt für {} Zeile(n), aber keine davon ist lang genug, um sie zu erreichen.",
+                "La règle '{}' s'applique à {} ligne(s), mais aucune n'est assez longue pour l'atteindre.",
+                "La regla '{}' se aplica a {} línea(s), pero ninguna es lo bastante larga para alcanzarla.",
+            ],
+            Message::UnusedRules => ["Unused rules", "Ungenutzte Regeln", "Règles inutilisées", "Reglas sin usar"],
+            Message::NoUnusedRules => [
+                "Every rule applied to at least one line.",
+                "Jede Regel wurde auf mindestens eine Zeile angewendet.",
+                "Chaque règle s'est appliquée à au moins une ligne.",
+                "Cada regla se aplicó a al menos una línea.",
+            ],
+            Message::RuleRecordNeverMatched => [
+                "Rule '{}' belongs to record type '{}', but no line is of that type.",
+                "Regel '{}' gehört zur Satzart '{}', aber keine Zeile ist von dieser Satzart.",
+                "La règle '{}' appartient au type d'enregistrement '{}', mais aucune ligne n'est de ce type.",
+                "La regla '{}' pertenece al tipo de registro '{}', pero ninguna línea es de ese tipo.",
+            ],
+            Message::NoRecordType => ["No record type", "Keine Satzart", "Aucun type d'enregistrement", "Sin tipo de registro"],
+            Message::Legend => ["Legend", "Legende", "Légende", "Leyenda"],
+            Message::Colour => ["Colour", "Farbe", "Couleur", "Color"],
+            Message::Start => ["Start", "Beginn", "Début", "Inicio"],
+            Message::Length => ["Length", "Länge", "Longueur", "Longitud"],
+            Message::FieldNumber => ["Field number", "Feldnummer", "Numéro de champ", "Número de campo"],
+            Message::NotCovered => [
+                "Not covered by any rule",
+                "Von keiner Regel erfasst",
+                "Couvert par aucune règle",
+                "No cubierto por ninguna regla",
+            ],
+            Message::PastEndOfLine => [
+                "Past the end of the line, in {}",
+                "Hinter dem Zeilenende, in {}",
+                "Au-delà de la fin de la ligne, dans {}",
+                "Más allá del final de la línea, en {}",
+            ],
+            Message::NotEncodable => [
+                "has characters that aren't in {}",
+                "enthält Zeichen, die es in {} nicht gibt",
+                "contient des caractères absents de {}",
+                "tiene caracteres que no existen en {}",
+            ],
+            Message::NotANumber => [
+                "not a valid {} number",
+                "keine gültige Zahl vom Typ {}",
+                "pas un nombre {} valide",
+                "no es un número {} válido",
+            ],
+            Message::NotADate => [
+                "not a valid date in the format {}",
+                "kein gültiges Datum im Format {}",
+                "pas une date valide au format {}",
+                "no es una fecha válida con el formato {}",
+            ],
+            Message::ZeroFilled => ["Zero-filled", "Mit Nullen gefüllt", "Rempli de zéros", "Relleno de ceros"],
+            Message::Line => ["Line {}", "Zeile {}", "Ligne {}", "Línea {}"],
+            Message::Value => ["Value", "Wert", "Valeur", "Valor"],
+            Message::StandardInput => ["standard input", "Standardeingabe", "entrée standard", "entrada estándar"],
+            Message::Validation => ["Validation", "Prüfung", "Validation", "Validación"],
+            Message::Valid => ["Valid", "Gültig", "Valide", "Válido"],
+            Message::Expected => ["Expected {}", "Erwartet: {}", "Attendu : {}", "Se esperaba {}"],
+            Message::Close => ["Close", "Schließen", "Fermer", "Cerrar"],
+            Message::InspectHint => [
+                "Click a field to inspect it. Hovering over a field outlines it on every line.",
+                "Klicken Sie auf ein Feld, um es zu untersuchen. Fahren Sie über ein Feld, um es in jeder Zeile zu umranden.",
+                "Cliquez sur un champ pour l'inspecter. Survoler un champ l'encadre sur chaque ligne.",
+                "Haga clic en un campo para inspeccionarlo. Al pasar el ratón sobre un campo se resalta en cada línea.",
+            ],
+            Message::ContinuesLine => [
+                "Continues line {}",
+                "Setzt Zeile {} fort",
+                "Suite de la ligne {}",
+                "Continúa la línea {}",
+            ],
+            Message::FieldStatistics => [
+                "Field statistics",
+                "Feldstatistik",
+                "Statistiques des champs",
+                "Estadísticas de campos",
+            ],
+            Message::DistinctValues => [
+                "Distinct values",
+                "Verschiedene Werte",
+                "Valeurs distinctes",
+                "Valores distintos",
+            ],
+            Message::MostCommon => ["Most common", "Häufigste", "Les plus fréquentes", "Más frecuentes"],
+            Message::Numbers => ["Numbers", "Zahlen", "Nombres", "Números"],
+            Message::MoreThan => ["more than {}", "mehr als {}", "plus de {}", "más de {}"],
+            Message::Parts => ["Parts", "Teile", "Parties", "Partes"],
+            Message::PartLines => [
+                "Lines {}–{}",
+                "Zeilen {}–{}",
+                "Lignes {}–{}",
+                "Líneas {}–{}",
+            ],
+            Message::PreviousPart => ["Previous part", "Vorheriger Teil", "Partie précédente", "Parte anterior"],
+            Message::NextPart => ["Next part", "Nächster Teil", "Partie suivante", "Parte siguiente"],
+            Message::PreviousPage => ["Previous page", "Vorherige Seite", "Page précédente", "Página anterior"],
+            Message::NextPage => ["Next page", "Nächste Seite", "Page suivante", "Página siguiente"],
+            Message::PageLines => [
+                "Lines {}–{} of {}",
+                "Zeilen {}–{} von {}",
+                "Lignes {}–{} sur {}",
+                "Líneas {}–{} de {}",
+            ],
+            Message::FilterLines => [
+                "Show only the lines containing…",
+                "Nur Zeilen anzeigen, die Folgendes enthalten…",
+                "N'afficher que les lignes contenant…",
+                "Mostrar solo las líneas que contienen…",
+            ],
+            Message::AllParts => ["All parts", "Alle Teile", "Toutes les parties", "Todas las partes"],
+            Message::SkippedLine => [
+                "Skipped, so not highlighted",
+                "Übersprungen, daher nicht hervorgehoben",
+                "Ignorée, donc non mise en évidence",
+                "Omitida, por lo que no se resalta",
+            ],
+            Message::Page => ["Page {}", "Seite {}", "Page {}", "Página {}"],
+            Message::BatchTitle => [
+                "Reports of {} files",
+                "Berichte zu {} Dateien",
+                "Rapports de {} fichiers",
+                "Informes de {} archivos",
+            ],
+            Message::Failed => ["Failed: {}", "Fehlgeschlagen: {}", "Échec : {}", "Error: {}"],
+            Message::CheckFailed => [
+                "Field '{}' gives {} as its {}, but the records before it come to {}",
+                "Feld '{}' gibt {} als {} an, aber die Datensätze davor ergeben {}",
+                "Le champ '{}' indique {} pour {}, mais les enregistrements précédents donnent {}",
+                "El campo '{}' indica {} como {}, pero los registros anteriores suman {}",
+            ],
+            Message::DuplicateKey => [
+                "Field '{}' repeats {}, the key of line {}",
+                "Feld '{}' wiederholt {}, den Schlüssel von Zeile {}",
+                "Le champ '{}' répète {}, la clé de la ligne {}",
+                "El campo '{}' repite {}, la clave de la línea {}",
+            ],
+            Message::NotAscending => [
+                "Field '{}' gives {}, which is before {} of the record before it in ascending order",
+                "Feld '{}' gibt {} an, das in aufsteigender Reihenfolge vor {} des vorigen Datensatzes liegt",
+                "Le champ '{}' indique {}, qui précède {} de l'enregistrement précédent dans l'ordre croissant",
+                "El campo '{}' indica {}, que va antes de {} del registro anterior en orden ascendente",
+            ],
+            Message::NotDescending => [
+                "Field '{}' gives {}, which is before {} of the record before it in descending order",
+                "Feld '{}' gibt {} an, das in absteigender Reihenfolge vor {} des vorigen Datensatzes liegt",
+                "Le champ '{}' indique {}, qui précède {} de l'enregistrement précédent dans l'ordre décroissant",
+                "El campo '{}' indica {}, que va antes de {} del registro anterior en orden descendente",
+            ],
+            Message::PreviousProblem => [
+                "Previous problem",
+                "Vorheriges Problem",
+                "Problème précédent",
+                "Problema anterior",
+            ],
+            Message::NextProblem => [
+                "Next problem",
+                "Nächstes Problem",
+                "Problème suivant",
+                "Problema siguiente",
+            ],
+            Message::ProblemLines => [
+                "{} line(s) with problems",
+                "{} Zeile(n) mit Problemen",
+                "{} ligne(s) avec des problèmes",
+                "{} línea(s) con problemas",
+            ],
+            Message::AnyField => ["Any field", "Beliebiges Feld", "N'importe quel champ", "Cualquier campo"],
+            Message::PreviousMatch => [
+                "Previous match",
+                "Vorheriger Treffer",
+                "Résultat précédent",
+                "Coincidencia anterior",
+            ],
+            Message::NextMatch => [
+                "Next match",
+                "Nächster Treffer",
+                "Résultat suivant",
+                "Coincidencia siguiente",
+            ],
+            Message::MatchCount => ["{} of {}", "{} von {}", "{} sur {}", "{} de {}"],
+            Message::NoMatches => ["No matches", "Keine Treffer", "Aucun résultat", "Sin coincidencias"],
+            Message::TrailingData => [
+                "Unexpected trailing data, after the line's last field",
+                "Unerwartete Daten nach dem letzten Feld der Zeile",
+                "Données inattendues après le dernier champ de la ligne",
+                "Datos inesperados tras el último campo de la línea",
+            ],
+            Message::TrailingLines => [
+                "Trailing data",
+                "Überzählige Daten",
+                "Données en trop",
+                "Datos sobrantes",
+            ],
+            Message::TrailingLinesDetail => [
+                "lines going on past their last field",
+                "Zeilen, die über ihr letztes Feld hinausgehen",
+                "lignes se poursuivant après leur dernier champ",
+                "líneas que continúan tras su último campo",
+            ],
+        }
+    }
+}
@@ -1,322 +1,4740 @@
+mod annotations;
+mod arrow;
+mod check_syntax;
+mod compression;
+mod config;
+mod diagnostics;
+mod edit;
+mod explain;
+mod infer;
+mod join;
+mod legend;
+mod map;
+mod mapped;
+mod metrics;
+mod pdf;
+mod problems;
+mod rebuild;
+mod reports;
+mod scaffold;
+mod schema_convert;
+mod select;
+mod serve;
+mod split;
+mod stats;
+mod usage;
+mod validate;
+mod xlsx;
+
 use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    ffi::OsString,
     fs::{self, File},
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Seek, Write},
     path::Path,
+    process::ExitCode,
+    rc::Rc,
+    time::{Duration, Instant},
 };
 
+use annotations::{ValidateFormat, ViolationWriter};
 use anyhow::{bail, Context};
 use base64::{engine::general_purpose, Engine};
 use chrono::Local;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use compression::Compression;
+use diagnostics::{LogFormat, Progress};
+use fixedfile_highlighter::{
+    bdi_html, bidi_control_name, check_syntax_strictly, compiled, condition_matches, constraints,
+    copybook,
+    document::{self, DocumentFormat},
+    escape_html, field_columns, fields_json, foreground_color,
+    generate_highlight_regions_from_records, hex_color, in_record_type, is_covered, json_string,
+    layouts::{self, Layout},
+    line_length,
+    locale::{self, Locale, Message},
+    parse_hex_color, parse_syntax_file, physical_lines, place_field_conditions, plugin,
+    prepare_line, read_syntax_file, record_text, record_type_of,
+    redact::{mask, mask_value, redact, redacted, MASK},
+    region_value,
+    report::{
+        stopped_reading, AnsiReport, CsvReport, JsonLines, JsonReport, LineProblem, LineReport,
+        MarkdownReport, ReportLine,
+    },
+    rule_applies, segments, skip_line, suspicious, trailing_from, unit_message, unit_name,
+    value_styles, value_stylesheet, Assembly, BatchRenderer, Captures, Case, Charset, CheckResult,
+    ColumnMode, Columns, Condition, ControlCheck, ControlTotals, Delimiter,
+    DelimiterHighlightRecord, DisplayWidths, Encoding, FieldBoundaries, FieldType,
+    FixedWidthHighlightRecord, HighlightRegion, Justify, Overlaps, Pattern, PositionOptions,
+    PreparedLine, RecordList, RecordType, RecordTyper, Renderer, SegmentKind, ShortLinePolicy,
+    SyntaxRule, ValuePattern, ValueStyle, DARK_GREYSCALE, DEUTERANOPIA, GREYSCALE, LINE_BREAK,
+    RAINBOW, TRITANOPIA,
+};
+use legend::{Legend, LegendPosition};
 use log::{error, info};
-use regex::Regex;
+use mapped::MappedFile;
+use metrics::Metrics;
+use problems::{ProblemCounts, Problems, ProblemsFound, TrailingData, EXIT_FATAL, EXIT_PROBLEMS};
+use select::{read_records, read_selected, LineRange, LineSelection, SelectedLines};
+use split::SplitReport;
+use stats::FieldStats;
+use usage::RuleUsage;
 
 /// Highlight parts of a file given a syntax.
 ///
 /// We parse over a syntax CSV, expecting a header row containing `start,length,name,condition', where:
-///   `start` is the 1-based start column of the character to highlight
+///   `start` is the 1-based start column of the character to highlight (0-based with `--index-base 0`)
 ///   `length` is the number of columns of this field
 ///   `name` is the human readable name for this field
 ///   `condition` (optional) is a regex to restrict this rule applying except to lines that match the regex.
 /// Rules are applied top-to-bottom.
-#[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[derive(Parser, Debug, Clone)]
+#[command(
+    author,
+    version,
+    about,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true,
+    args_override_self = true
+)]
 struct Args {
-    /// The input file to process
-    #[arg(index = 1)]
-    input_file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The input file to process, or `-` to read standard input, which is also read if only the syntax file is given
+    #[arg(index = 1, required = true)]
+    input_file: Option<String>,
 
     /// The syntax file to use
     #[arg(index = 2)]
-    syntax_file: String,
+    syntax_file: Option<String>,
 
-    /// The colours to output the analysed file with. This can be one of a number of inputs: a predefined preset (greyscale [default], rainbow) or; a comma separated list of hex codes.
+    /// With `--batch`, the input files after the first, which are followed by the syntax file.
+    #[arg(index = 3, requires = "batch", hide = true)]
+    more_input_files: Vec<String>,
+
+    /// The colours to output the analysed file with. This can be one of a number of inputs: a predefined preset (greyscale [default], rainbow, or for colour-blind readers, deuteranopia, protanopia or tritanopia) or; a comma separated list of hex codes or CSS colour names.
     #[arg(short = 'c', long = "colors")]
     colors: Option<String>,
 
-    /// Interpret the input file as being delimited by the provided character. The syntax file will now be expected to take the headers: `field`, `name`, `condition`.
-    #[arg(short = 'd', long = "delimiter")]
-    delimiter: Option<char>,
+    /// Pick each field's colour from a hash of its name, so a field is the same colour on every line of the report. Fields with a `color` in the syntax file always have that colour.
+    #[arg(long = "stable-colors")]
+    stable_colors: bool,
+
+    /// Give the HTML report a dark theme: light text on a dark page, with dark greys as the default colours. Text on every field is light or dark to suit its colour, whichever theme is used.
+    #[arg(long = "dark")]
+    dark: bool,
+
+    /// Interpret the input file as being delimited by the provided character, or characters, e.g. `|~|`. The syntax file will now be expected to take the headers: `field`, `name`, `condition`.
+    #[arg(short = 'd', long = "delimiter", global = true)]
+    delimiter: Option<String>,
+
+    /// Read `--delimiter` as a regular expression, e.g. `\s+` for fields separated by runs of whitespace.
+    #[arg(long = "delimiter-regex", global = true, requires = "delimiter")]
+    delimiter_regex: bool,
+
+    /// In delimiter mode, the character fields may be quoted with, e.g. `"`. Delimiters inside a quoted field are part of it, a doubled quote inside one stands for a single quote, and the quotes aren't part of the field's value.
+    #[arg(
+        long = "quote",
+        value_name = "CHAR",
+        global = true,
+        requires = "delimiter"
+    )]
+    quote: Option<char>,
 
     /// Output an HTML snippet, rather than a full file
     #[arg(short = 's', long = "snippet")]
     snippet: bool,
+
+    /// Write the report's styles to this stylesheet and link to it from the report, rather than including them in it, so they can be edited to restyle the report or shared between reports. The link is to the path as given.
+    #[arg(long = "css", value_name = "PATH")]
+    css: Option<String>,
+
+    /// Shift every field by this many columns (or fields, in delimiter mode) without editing the syntax file, e.g. `+1` or `-2`. Shifted output is labelled as such in the report.
+    #[arg(
+        long = "shift",
+        default_value_t = 0,
+        allow_negative_numbers = true,
+        global = true
+    )]
+    shift: isize,
+
+    /// Shift the fields of one record type by this many columns (or fields), on top of `--shift`, e.g. `--shift-record DETAIL=+2` when only detail lines moved. May be given for several record types.
+    #[arg(
+        long = "shift-record",
+        value_name = "TYPE=N",
+        value_parser = parse_record_shift,
+        allow_negative_numbers = true,
+        global = true
+    )]
+    shift_record: Vec<(String, isize)>,
+
+    /// Whether `start` columns and `field` numbers in the syntax file count from 0 or from 1.
+    #[arg(long = "index-base", default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=1), global = true)]
+    index_base: u8,
+
+    /// What the columns of a fixed width syntax file count: bytes of each line's UTF-8 encoding (`bytes`), characters (`chars`), or graphemes (`graphemes`), so a letter with combining accents is one column.
+    #[arg(long = "column-mode", value_enum, default_value_t = ColumnMode::Chars, global = true)]
+    column_mode: ColumnMode,
+
+    /// The character encoding of the input file, which lines are decoded from before rules are applied: `utf-8`, `latin1`, or the EBCDIC code pages `cp037` and `cp1047`, whose lines end in NL or LF.
+    #[arg(long = "encoding", value_enum, default_value_t = Encoding::Utf8, global = true)]
+    encoding: Encoding,
+
+    /// How the input file is compressed: decided by its extension (`.gz` or `.zst`) or first bytes (`auto`), not at all (`none`), or with `gzip` or `zstd`. Compressed input is decompressed as it's read, by the `gzip` or `zstd` program.
+    #[arg(long = "compression", value_enum, default_value_t = Compression::Auto, global = true)]
+    compression: Compression,
+
+    /// How the lines of the input end: as its first line does (`auto`), in a line feed (`lf`), a carriage return and line feed (`crlf`), or a carriage return on its own (`cr`), as in old Mac files. A carriage return before a line feed is never part of a line, so can't shift or lengthen its last field.
+    #[arg(long = "line-ending", value_enum, default_value_t = LineEnding::Auto, global = true)]
+    line_ending: LineEnding,
+
+    /// Count each line that doesn't end as `--line-ending` says, or with `auto`, as the first line does, as an error, which fails the run. The last line may end without a line break.
+    #[arg(long = "strict-line-endings", global = true)]
+    strict_line_endings: bool,
+
+    /// Keep a UTF-8 byte order mark at the start of the input as the first character of its first line, rather than removing it so the line's columns line up with the others'.
+    #[arg(long = "keep-bom", global = true)]
+    keep_bom: bool,
+
+    /// The format of the syntax file: CSV rules (`csv`), a YAML (`yaml`) or TOML (`toml`) document listing fields and record types, or a COBOL copybook (`copybook`) describing a fixed width record, from which rules are derived for each elementary item.
+    #[arg(long = "syntax-format", value_enum, default_value_t = SyntaxFormat::Csv, global = true)]
+    syntax_format: SyntaxFormat,
+
+    /// The layout to use from a layouts file, which gives a syntax file for each version of a layout, rather than the one whose condition the first line of the input matches.
+    #[arg(long = "layout", value_name = "NAME", global = true)]
+    layout: Option<String>,
+
+    /// Scan the input before rendering and report rules that cannot fit the observed lines and conditions that never match. Findings are logged and written as a preflight section at the top of the report.
+    #[arg(long = "preflight")]
+    preflight: bool,
+
+    /// List fields that are blank or zero-filled on at least this percentage of the lines they apply to (100 if no percentage is given, e.g. `--profile-fields=90`), which reveals dead fields and mapping mistakes. Findings are logged and written as a section at the top of the report.
+    #[arg(long = "profile-fields", value_name = "PERCENT", num_args = 0..=1, require_equals = true, default_missing_value = "100", value_parser = clap::value_parser!(u8).range(1..=100))]
+    profile_fields: Option<u8>,
+
+    /// Fail on malformed syntax records, naming the offending line and column, rather than logging an error and skipping them.
+    #[arg(long = "strict-syntax", global = true)]
+    strict_syntax: bool,
+
+    /// Run the decoder plugins named by the `plugin` types of the syntax file. A plugin is a program run with your permissions, so only allow them for syntax files you trust: without this, a syntax file naming one fails to load.
+    #[arg(long = "allow-plugins", global = true)]
+    allow_plugins: bool,
+
+    /// How long a plugin has to answer each value it's sent, in seconds, after which it's stopped and every value of its fields is invalid.
+    #[arg(long = "plugin-timeout", value_name = "SECONDS", default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    plugin_timeout: u64,
+
+    /// What to do with lines that are shorter than the layout: fail (`error`), highlight as far as they go (`truncate`) or virtually pad them for display (`pad`).
+    #[arg(long = "short-lines", value_enum, default_value_t = ShortLinePolicy::Truncate, global = true)]
+    short_lines: ShortLinePolicy,
+
+    /// How to treat text after the end of a line's last field, which the layout doesn't expect: mark it and report it as an error (`error`) or a warning (`warning`), or leave it be (`ignore`).
+    #[arg(long = "trailing-data", value_enum, default_value_t = TrailingData::Warning, global = true)]
+    trailing_data: TrailingData,
+
+    /// Mask the values of these fields in every output, e.g. `--redact PAN,NationalId`, as the `sensitive` column of a syntax file does: each is shown as `*`s but for its last four characters. They're still highlighted and checked against their constraints.
+    #[arg(
+        long = "redact",
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        global = true
+    )]
+    redact: Vec<String>,
+
+    /// Render the columns added by `--short-lines pad` as visibly distinct ghost placeholders, so absent fields stand out.
+    #[arg(long = "ghost-cells")]
+    ghost_cells: bool,
+
+    /// Collapse characters that aren't covered by any rule behind an ellipsis, giving a condensed view of wide records.
+    #[arg(long = "covered-only")]
+    covered_only: bool,
+
+    /// Add a collapsible table of contents listing each record type (a line's `record`, or the conditions it matched) with counts and jump links to its sections.
+    #[arg(long = "toc")]
+    toc: bool,
+
+    /// Add a legend listing the colour, name and position of each field of each record type, so printed copies of the report explain themselves. It goes after the lines (`bottom`, if no position is given) or before them (e.g. `--legend=top`).
+    #[arg(long = "legend", value_enum, value_name = "POSITION", num_args = 0..=1, require_equals = true, default_missing_value = "bottom")]
+    legend: Option<LegendPosition>,
+
+    /// Add a summary dashboard at the top of the report: total records, counts per record type, findings by severity and file metadata.
+    #[arg(long = "dashboard")]
+    dashboard: bool,
+
+    /// Add controls to the end of each line which copy its fields to the clipboard as a CSV row or JSON object.
+    #[arg(long = "copy-buttons")]
+    copy_buttons: bool,
+
+    /// Make the report interactive: clicking a field opens a panel showing its name, start, length, raw value and whether it keeps to its constraints, and hovering over a field outlines the field of the same name on every line.
+    #[arg(long = "interactive")]
+    interactive: bool,
+
+    /// Add a search bar to the report, which steps through the lines with problems, or finds the fields with a value: any field containing it, or a chosen field that is exactly it.
+    #[arg(long = "search", conflicts_with_all = ["snippet", "resume"])]
+    search: bool,
+
+    /// Embed the extracted field values in the report as JSON, with buttons to download them as CSV or JSON.
+    #[arg(long = "embed-data")]
+    embed_data: bool,
+
+    /// Add a clickable minimap strip down the side of the report, colouring each line by record type and marking lines with errors.
+    #[arg(long = "minimap")]
+    minimap: bool,
+
+    /// Give lines a subtle background according to their structure, independent of field colours. `record_type` tints each record type differently, alternating shade between consecutive sections.
+    #[arg(long = "stripe-by", value_enum)]
+    stripe_by: Option<StripeBy>,
+
+    /// Shade every other line slightly, to make long records easier to follow across the screen.
+    #[arg(long = "zebra", conflicts_with = "stripe_by")]
+    zebra: bool,
+
+    /// Show each line's record type name in the gutter beside its line number. Together with `--stripe-by record_type`, lines of each record type are banded in their own colour with their type named alongside.
+    #[arg(long = "record-gutter")]
+    record_gutter: bool,
+
+    /// In delimiter mode, show this character (e.g. `│`) in place of each delimiter so field boundaries are easy to see.
+    #[arg(long = "delimiter-glyph", requires = "delimiter")]
+    delimiter_glyph: Option<char>,
+
+    /// Mark the boundary between adjacent fields, either with a thin border (`border`), an inserted `│` (`glyph`) or by underlining fields in alternating solid, dashed, dotted and double lines (`underline`), so fields can be told apart without their colours, such as by colour-blind readers or on a greyscale printout. None changes the columns fields are counted from.
+    #[arg(long = "field-boundaries", value_enum)]
+    field_boundaries: Option<FieldBoundaries>,
+
+    /// How to draw fields that overlap, such as a composite field and its sub-fields: nest each inside the fields containing it (`nest`), or split the line into segments wherever a field starts or ends, each highlighted as the innermost field covering it and titled with every one (`split`).
+    #[arg(long = "overlaps", value_enum, default_value_t = Overlaps::Nest)]
+    overlaps: Overlaps,
+
+    /// Keep fields lined up down the report when lines have characters that don't take up one column on screen, such as full-width CJK characters (two) and combining accents (none): each column is made as wide as its widest character on any line, padding narrower ones with spaces. Reads the input once before the report. Only for `--output-format html` or `ansi`.
+    #[arg(long = "display-width", conflicts_with = "follow")]
+    display_width: bool,
+
+    /// Show spaces within fields as muted middle dots (`·`) and tabs as arrows (`→`), so how each field is padded can be seen. Only for `--output-format html`, `ansi` or `pdf`.
+    #[arg(long = "show-padding")]
+    show_padding: bool,
+
+    /// Print a line of field names above each record, positioned over the columns they describe, as layout specifications often illustrate.
+    #[arg(long = "labels", conflicts_with = "covered_only")]
+    labels: bool,
+
+    /// Lay the report out in two panes which scroll together: highlighted lines on the left and a table of each line's decoded field values on the right.
+    #[arg(long = "two-pane")]
+    two_pane: bool,
+
+    /// The format to output the analysed file in: an HTML report (`html`), coloured text for a terminal (`ansi`), the fields of each line as JSON (`json`), for tools such as jq, or as JSON Lines with the line's record type and problems (`jsonl`), streamed as the input is read, Markdown (`markdown`), for pasting into issues and wikis, a CSV table of every field's value on every line (`csv`), a paginated PDF of the lines in their fields' colours (`pdf`), for attaching to audits, or an Excel workbook with a sheet of field values for each record type and a sheet of the problems found (`xlsx`).
+    #[arg(long = "output-format", value_enum, default_value_t = OutputFormat::Html)]
+    output_format: OutputFormat,
+
+    /// Write the output to this file rather than to standard output.
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    output: Option<String>,
+
+    /// Split the HTML report into parts of this many lines each, written next to `--output` as `NAME-1.html`, `NAME-2.html` and so on, with `--output` an index linking to them, so very large reports stay quick to open in a browser.
+    #[arg(long = "split-every", value_name = "LINES", requires = "output", conflicts_with_all = ["resume", "follow", "snippet", "embed_data", "two_pane", "minimap", "search"], value_parser = clap::value_parser!(u64).range(1..))]
+    split_every: Option<u64>,
+
+    /// Write a report for each of several input files into this directory, named after the input, e.g. `data.dat.html`, with an `index.html` giving the lines, errors and warnings of each and linking to its report. Any number of input files, directories of them or quoted glob patterns, such as `'incoming/*.dat'`, can then be given before the syntax file.
+    #[arg(long = "batch", value_name = "DIR", conflicts_with_all = ["output", "resume", "follow", "metrics", "error_report", "dry_run", "validate"])]
+    batch: Option<String>,
+
+    /// Save progress to this checkpoint file as the report is written, and if it already exists, carry on from where an interrupted run stopped. The report must be written to a file, with `--output` or by redirecting standard output, which must then be appended to (`>>`) when resuming.
+    #[arg(long = "resume", value_name = "CHECKPOINT", conflicts_with_all = ["embed_data", "two_pane", "selection", "filter"])]
+    resume: Option<String>,
+
+    /// Also compile the syntax file into this file, which can be given as the syntax file of later runs to skip parsing and checking it.
+    #[arg(long = "compile-syntax", value_name = "FFHC")]
+    compile_syntax: Option<String>,
+
+    /// Keep watching the input for new lines as they're appended, like `tail -f`, highlighting each one as it arrives. Only for `--output-format ansi`, `html`, `json`, `jsonl` or `csv`; an HTML report reloads itself every few seconds to show the new lines.
+    #[arg(long = "follow")]
+    follow: bool,
+
+    /// Don't pipe ANSI output through a pager (`$PAGER`, or `less`) when writing to a terminal.
+    #[arg(long = "no-pager")]
+    no_pager: bool,
+
+    /// The language to write the report's fixed text in.
+    #[arg(long = "locale", value_enum, default_value_t = Locale::En)]
+    locale: Locale,
+
+    /// Flag zero-width characters, non-breaking and other unusual spaces, and characters that look like ASCII ones (e.g. Cyrillic `а`) inside fields, marking them visibly in the output and logging where they are.
+    #[arg(long = "check-unicode")]
+    check_unicode: bool,
+
+    /// Mark text no rule covers, and fields that run past the end of their line, in an error colour with a tooltip saying why. The colour is a hex code (e.g. `--show-errors=c000c0`), red if not given.
+    #[arg(long = "show-errors", value_name = "COLOR", num_args = 0..=1, require_equals = true, default_missing_value = "e00000")]
+    show_errors: Option<String>,
+
+    /// Check the configuration without writing a report: parse the syntax file, compile every condition and run the first LINES lines of the input (1000 if not given, e.g. `--dry-run=50`) through the rules, then summarise. Fails if anything would go wrong in a full run.
+    #[arg(long = "dry-run", value_name = "LINES", num_args = 0..=1, require_equals = true, default_missing_value = "1000", conflicts_with_all = ["resume", "follow", "compile_syntax"])]
+    dry_run: Option<usize>,
+
+    /// Check every field against the constraints of its rule (`pattern`, `charset`, `case`, `justify` and `pad`) instead of writing a report, printing each violation as a CSV row of line, field, value and the constraint broken, or as `--validate-format` says. Fails if there are any, for use in batch jobs.
+    #[arg(long = "validate", conflicts_with_all = ["resume", "follow", "dry_run", "compile_syntax", "metrics"])]
+    validate: bool,
+
+    /// How `--validate` writes the violations it finds: as CSV rows (`csv`), as a SARIF log (`sarif`) for code scanning tools, or as GitHub Actions workflow commands (`github`), which annotate the input file at each field that broke its constraint.
+    #[arg(long = "validate-format", value_enum, default_value_t = ValidateFormat::Csv, requires = "validate")]
+    validate_format: ValidateFormat,
+
+    /// Add a section to the end of the report listing rules that never applied to any line: those whose conditions never matched, and those no line was long enough to reach. These are always logged.
+    #[arg(long = "unused-rules")]
+    unused_rules: bool,
+
+    /// Write run metrics to this file as JSON once the report is written: lines processed, lines of each record type, lines each rule matched and applied to, counts of findings and the run's duration, for monitoring batch runs.
+    #[arg(long = "metrics", value_name = "JSON", conflicts_with_all = ["resume", "follow"])]
+    metrics: Option<String>,
+
+    /// Summarise the values of every field once the input has been read: how many lines it's blank on, how many distinct values it has and the N most common (5 if not given, e.g. `--stats=10`), its shortest and longest values and, of those that are numbers, the smallest and largest. Written as a section at the end of the HTML report, and on stderr for the other formats.
+    #[arg(long = "stats", value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "5", conflicts_with_all = ["resume", "follow"])]
+    stats: Option<usize>,
+
+    /// Write every problem found with the input to this file as JSON once it has been read, for CI pipelines: fields past the end of their line, text no rule covers, trailing data, broken constraints, suspicious characters (with `--check-unicode`), control totals that don't match, duplicate keys, values out of order, unexpected line endings (with `--strict-line-endings`) and rules that never applied.
+    #[arg(long = "error-report", value_name = "JSON", conflicts_with_all = ["resume", "follow"])]
+    error_report: Option<String>,
+
+    /// Report processing statistics (lines, bytes, rules evaluated and time spent in each phase) on stderr and in the report footer.
+    #[arg(long = "timing")]
+    timing: bool,
+
+    /// Write nothing on stderr but the error a run fails with: no log messages, progress or summary. Problems found are still written to `--error-report` and give the exit status.
+    #[arg(short = 'q', long = "quiet", global = true)]
+    quiet: bool,
+
+    /// How to write log messages, progress and the summary of a run on stderr: as text, with a progress bar when stderr is a terminal (`text`), or as a JSON object per line, for automation (`json`).
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+
+    /// The longest line, in bytes, to accept before failing; this stops a file without line breaks being read into memory as one enormous line. Use 0 for no limit.
+    #[arg(long = "max-line-length", value_name = "BYTES", default_value_t = DEFAULT_MAX_LINE_LENGTH, global = true)]
+    max_line_length: usize,
+
+    /// Read the input as records of exactly this many bytes, one straight after another, rather than as lines, for files without line breaks. Each byte is a column, so UTF-8 input is read as Latin-1, and bytes that aren't printable are shown in hex.
+    #[arg(long = "record-length", value_name = "BYTES", global = true)]
+    record_length: Option<usize>,
+
+    /// Read the input file through buffered reads rather than mapping it into memory, for a file that may be truncated or rewritten while it's read, such as on a network share.
+    #[arg(long = "no-mmap", global = true)]
+    no_mmap: bool,
+
+    /// The number of threads to generate regions and render lines of the HTML report on, in batches whose lines are written in order. Defaults to the number of processors.
+    #[arg(long = "threads")]
+    threads: Option<usize>,
+
+    /// Only output this range of lines, counted from 1, e.g. `--lines 1000-2000`, or `1000-` to the end of the input. Lines keep their numbers in the input.
+    #[arg(long = "lines", value_name = "FIRST-LAST", group = "selection")]
+    lines: Option<LineRange>,
+
+    /// Only output the first N lines.
+    #[arg(long = "head", value_name = "N", group = "selection")]
+    head: Option<usize>,
+
+    /// Only output the last N lines, which are held in memory until the input ends.
+    #[arg(
+        long = "tail",
+        value_name = "N",
+        group = "selection",
+        conflicts_with = "follow"
+    )]
+    tail: Option<usize>,
+
+    /// Only output N lines chosen at random from the whole input, in the order they appear in it. The same lines are chosen every time the same input is sampled.
+    #[arg(
+        long = "sample",
+        value_name = "N",
+        group = "selection",
+        conflicts_with = "follow"
+    )]
+    sample: Option<usize>,
+
+    /// Only output lines matching this regular expression, e.g. `^D.{9}12345678` for one account's detail records. Lines keep their numbers in the input, and `--head`, `--tail` and `--sample` choose from the lines that match.
+    #[arg(long = "filter", value_name = "REGEX", value_parser = Condition::parse)]
+    filter: Option<Condition>,
+
+    /// Only output lines which don't match `--filter`.
+    #[arg(long = "invert-filter", requires = "filter")]
+    invert_filter: bool,
+
+    /// Leave lines matching this regular expression, such as a file's header and trailer, out of highlighting: they're still output as they are, but no rule is applied to them, so they're never reported as problems. Can be given more than once.
+    #[arg(long = "skip-lines-matching", value_name = "REGEX", value_parser = Condition::parse)]
+    skip_lines_matching: Vec<Condition>,
+
+    /// Show the lines left out by `--skip-lines-matching` dimmed.
+    #[arg(long = "dim-skipped", requires = "skip_lines_matching")]
+    dim_skipped: bool,
+
+    /// Number each line of `--output-format json` output with its 1-based number in the input, writing `{"line": N, "fields": [...]}` objects rather than bare arrays of fields, so lines can still be found in the input after `--filter`, `--lines` and the like. The other formats always number lines this way.
+    #[arg(long = "line-numbers")]
+    line_numbers: bool,
+
+    /// Use the options of this profile of the config files, `[profile.NAME]` in `.fixedfile-highlighter.toml`, as well as the options the files give to every run.
+    #[arg(long = "profile", value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Ignore the config files, using only the options given on the command line.
+    #[arg(long = "no-config", global = true, conflicts_with = "profile")]
+    no_config: bool,
 }
 
-enum RecordList {
-    FixedWidth(Vec<FixedWidthHighlightRecord>),
-    Delimiter(char, Vec<DelimiterHighlightRecord>),
+#[derive(clap::Subcommand, Debug, Clone)]
+enum Command {
+    /// Extract the fields of every line into a table for loading into other tools
+    Extract(ExtractArgs),
+    /// Write the effective rules, with their positions resolved, as a self-contained JSON document for other tools
+    ExportRuleset(ExportRulesetArgs),
+    /// Check a control (or manifest) file against the data file it describes: declared counts and totals, and keys present in both
+    Validate(ValidateArgs),
+    /// Match the lines of two files on a key field, and show them side by side or write them as one table
+    Join(JoinArgs),
+    /// Show how the values of each line would move from one layout to another, given a mapping between their fields
+    Map(MapArgs),
+    /// Write lines back from the values of their fields, as `--output-format csv`, `json` or `jsonl` extracts them, padded and justified as their rules say
+    Rebuild(RebuildArgs),
+    /// Convert a fixed width syntax file into the equivalent delimiter mode one (give `--delimiter` to convert back, with `--widths`)
+    SchemaConvert(SchemaConvertArgs),
+    /// Start a syntax file for a sample with several record types, with a section of rules for each value of a discriminator field
+    Scaffold(ScaffoldArgs),
+    /// Propose a draft syntax file for a sample whose layout isn't known: its delimiter and number of fields, or the column boundaries where the kind of character changes
+    Infer(InferArgs),
+    /// Build a fixed width syntax file interactively, by selecting and naming fields over lines of a sample
+    Edit(EditArgs),
+    /// Preview the report in a browser, served a page at a time from a small web server on this machine, with a search box, and reloaded whenever the syntax or input file is saved
+    Serve(ServeArgs),
+    /// Trace how every rule applies to chosen lines: whether its condition matched, the region it produced, and why it was left out
+    Explain(ExplainArgs),
+    /// Check a syntax file without an input file: rules that can't be placed, invalid regexes, rules written for the other mode, and fields that overlap, leave gaps or share a name
+    CheckSyntax(CheckSyntaxArgs),
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct FixedWidthHighlightRecord {
-    start: Option<usize>,
-    length: Option<usize>,
-    name: String,
-    condition: Option<String>,
+#[derive(clap::Args, Debug, Clone)]
+struct RebuildArgs {
+    /// The fields to rebuild lines from, as `--output-format csv`, `json` or `jsonl` writes them, and maybe edited since
+    extracted_file: String,
+
+    /// The syntax file the fields were extracted with
+    syntax_file: String,
+
+    /// Write the lines to this file rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct DelimiterHighlightRecord {
-    field: Option<usize>,
-    name: String,
-    condition: Option<String>,
+#[derive(clap::Args, Debug, Clone)]
+struct ScaffoldArgs {
+    /// A sample of the file to write a syntax file for
+    input_file: String,
+
+    /// The columns holding the record type, e.g. `1-2`, or its field number in delimiter mode.
+    #[arg(long = "discriminator", value_name = "COLUMNS")]
+    discriminator: String,
+
+    /// Write the syntax file here rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
 }
 
-#[derive(Debug)]
-struct HighlightRegion {
-    start: usize,
-    end: usize,
-    name: String,
-    applied: bool,
+#[derive(clap::Args, Debug, Clone)]
+struct InferArgs {
+    /// A sample of the file to infer a syntax file for
+    input_file: String,
+
+    /// The number of lines of the sample to read.
+    #[arg(long = "lines", value_name = "N", default_value_t = 1000)]
+    lines: usize,
+
+    /// Treat the sample as fixed width rather than looking for a delimiter that separates its fields.
+    #[arg(long = "fixed-width", conflicts_with = "delimiter")]
+    fixed_width: bool,
+
+    /// Write the syntax file here rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
 }
 
-fn main() -> anyhow::Result<()> {
-    pretty_env_logger::init_custom_env("LOG");
-    let args = Args::parse();
+#[derive(clap::Args, Debug, Clone)]
+struct EditArgs {
+    /// A sample of the file to build a syntax file for
+    input_file: String,
 
-    // parse colours
-    let color_preset_greyscale: Vec<String> = vec!["fff".to_owned(), "ccc".to_owned()];
-    let color_preset_rainbow: Vec<String> = vec![
-        "fff".to_owned(),
-        "f88".to_owned(),
-        "ffc088".to_owned(),
-        "a2ff88".to_owned(),
-        "88f9ff".to_owned(),
-        "a288ff".to_owned(),
-        "ff88ba".to_owned(),
-    ];
-
-    let colors = if args.colors.is_some() {
-        let c = args.colors.unwrap();
-        if c.to_lowercase() == "greyscale" || c.to_lowercase() == "grayscale" {
-            color_preset_greyscale
-        } else if c.to_lowercase() == "rainbow" {
-            color_preset_rainbow
-        } else {
-            let mut cs = Vec::new();
-            for color in c.split(',') {
-                cs.push(color.to_owned());
-            }
-            cs
-        }
-    } else {
-        color_preset_greyscale
-    };
+    /// The syntax file to edit, which is created when saved if it doesn't exist
+    syntax_file: String,
+}
 
-    if colors.is_empty() {
-        bail!("No colours have been specified so no output can be produced!");
-    }
+#[derive(clap::Args, Debug, Clone)]
+struct ServeArgs {
+    /// The input file to show
+    input_file: String,
 
-    // parse input file into lines
-    info!("Parsing input file");
-    let file = File::open(&args.input_file).context("Failed to open input file.")?;
-    let lines = BufReader::new(file).lines();
+    /// The syntax file to use, which is read again for every page
+    syntax_file: String,
 
-    // parse syntax file into vec
-    info!("Parsing syntax file");
-    let syntax_file = read_syntax_file(args.syntax_file)?;
-    let records = parse_syntax_file(&syntax_file, args.delimiter)?;
+    /// The port to listen on.
+    #[arg(long = "port", default_value_t = 8080)]
+    port: u16,
 
-    // create highlighted regions and output as HTML
-    info!("Creating regions and outputting");
-    if !args.snippet {
-        println!("<!doctype html><html>");
-        println!(
-            r#"<head><meta charset="utf8"><title>Analysis of {}</title></head>"#,
-            Path::new(&args.input_file)
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-        );
-        println!("<body>");
-    }
-    println!(r#"<pre style="color:red">"#);
-    for (idx, line) in lines.enumerate() {
-        let line = line.context("Failed to read line from input file.")?;
+    /// The address to listen on. Only this machine can see the report unless another is given, such as `0.0.0.0`, when anyone who can reach it can read the input file.
+    #[arg(long = "bind", value_name = "ADDRESS", default_value = "127.0.0.1")]
+    bind: String,
 
-        // produce regions
-        let regions = generate_highlight_regions_from_records(&records, &line)?;
-        produce_html_for_line(idx, line, regions, &colors);
-    }
-    println!("</pre>");
+    /// The number of lines shown on each page.
+    #[arg(long = "page-lines", value_name = "N", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    page_lines: u64,
+}
 
-    println!(r#"<p><small>Hover over text to see the name of the field it is part of. Lines of text are surrounded by '&gt;' and '&lt;' to make them clearer to see. Text in red has not matched any rules.</small></p>"#);
+#[derive(clap::Args, Debug, Clone)]
+struct ExplainArgs {
+    /// The input file to process
+    input_file: String,
 
-    let mut syntax_b64 = String::new();
-    general_purpose::STANDARD_NO_PAD.encode_string(syntax_file, &mut syntax_b64);
-    println!(
-        r#"Analysed at {} by <a href="https://github.com/lilopkins/fixedfile-highlighter" target="_blank" rel="noopener">fixedfile-highlighter</a> using <a href="data:text/csv;base64,{}">this syntax file</a>."#,
-        Local::now(),
-        syntax_b64
-    );
+    /// The syntax file to use
+    syntax_file: String,
 
-    if !args.snippet {
-        println!("</body></html>");
-    }
+    /// A line to explain, counting from 1, or a range of them like `40-45`. Can be given more than once.
+    #[arg(long = "line", value_name = "LINES", required = true)]
+    lines: Vec<String>,
+}
 
-    info!("Done!");
-    Ok(())
+#[derive(clap::Args, Debug, Clone)]
+struct CheckSyntaxArgs {
+    /// The syntax file to check
+    syntax_file: String,
+
+    /// How to write the findings: as lines of text (`human`), or as a JSON document (`json`) for other tools.
+    #[arg(long = "format", value_enum, default_value_t = CheckSyntaxFormat::Human)]
+    format: CheckSyntaxFormat,
 }
 
-fn read_syntax_file<P: AsRef<Path>>(syntax_file: P) -> anyhow::Result<String> {
-    fs::read_to_string(syntax_file).context("Failed to read from syntax file.")
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CheckSyntaxFormat {
+    /// A line of text for each finding
+    Human,
+    /// A JSON document of the findings, with counts of errors and warnings
+    Json,
 }
 
-fn parse_syntax_file(syntax_file: &str, delimiter: Option<char>) -> anyhow::Result<RecordList> {
-    if let Some(delimiter_ch) = delimiter {
-        let mut records = Vec::new();
-        let mut csv_reader = csv::Reader::from_reader(syntax_file.as_bytes());
-        for result in csv_reader.deserialize() {
-            let highlight_record: DelimiterHighlightRecord =
-                result.context("Failed to parse syntax record.")?;
-            records.push(highlight_record);
-        }
-        Ok(RecordList::Delimiter(delimiter_ch, records))
-    } else {
-        let mut records = Vec::new();
-        let mut csv_reader = csv::Reader::from_reader(syntax_file.as_bytes());
-        for result in csv_reader.deserialize() {
-            let highlight_record: FixedWidthHighlightRecord =
-                result.context("Failed to parse syntax record.")?;
-            records.push(highlight_record);
-        }
-        Ok(RecordList::FixedWidth(records))
-    }
+#[derive(clap::Args, Debug, Clone)]
+struct SchemaConvertArgs {
+    /// The syntax file to convert
+    syntax_file: String,
+
+    /// The width of each field when converting a delimiter mode syntax file to fixed width, as `name=width` separated by commas, e.g. `id=6,name=20`.
+    #[arg(long = "widths", value_name = "WIDTHS")]
+    widths: Option<String>,
+
+    /// Write the converted syntax file here rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
 }
 
-fn generate_highlight_regions_from_records(
-    records: &RecordList,
-    line: &String,
-) -> anyhow::Result<Vec<HighlightRegion>> {
-    let mut regions = Vec::new();
+#[derive(clap::Args, Debug, Clone)]
+struct MapArgs {
+    /// The input file to process
+    input_file: String,
 
-    match records {
-        RecordList::FixedWidth(fw_records) => {
-            for record in fw_records {
-                let apply_record_to_this_line = if record.condition.is_some() {
-                    let re = Regex::new(&record.condition.clone().unwrap())
-                        .context("Failed to parse condition regex.")?;
-                    re.is_match(line)
-                } else {
-                    true
-                };
+    /// The syntax file of the input file's layout
+    syntax_file: String,
 
-                if apply_record_to_this_line {
-                    if record.start.is_none() || record.length.is_none() {
-                        error!("Syntax record skipped as fields were not correctly filled in. (needs 'start' and 'length'!)");
-                        continue;
-                    }
+    /// The syntax file of the layout to map onto.
+    #[arg(long = "to", value_name = "SYNTAX_FILE")]
+    to: String,
 
-                    regions.push(HighlightRegion {
-                        start: record.start.unwrap() - 1,
-                        end: record.start.unwrap() + record.length.unwrap() - 1,
-                        name: record.name.clone(),
-                        applied: false,
-                    })
-                }
-            }
-        }
+    /// The target layout is in delimiter mode, with fields delimited by this character, or characters.
+    #[arg(long = "to-delimiter", value_name = "DELIMITER")]
+    to_delimiter: Option<String>,
 
-        RecordList::Delimiter(delimiter, d_records) => {
-            for record in d_records {
-                let apply_record_to_this_line = if record.condition.is_some() {
-                    let re = Regex::new(&record.condition.clone().unwrap())
-                        .context("Failed to parse condition regex.")?;
-                    re.is_match(line)
-                } else {
-                    true
-                };
+    /// A CSV file with `source` and `target` columns, giving the field of the target layout each source field moves to.
+    #[arg(long = "mapping", value_name = "MAPPING_FILE")]
+    mapping: String,
+}
 
-                if apply_record_to_this_line {
-                    if record.field.is_none() {
-                        error!("Syntax record skipped as fields were not correctly filled in. (needs 'field'!)");
-                        continue;
-                    }
+#[derive(clap::Args, Debug, Clone)]
+struct JoinArgs {
+    /// The first input file
+    input_file: String,
 
-                    regions.push(HighlightRegion {
-                        start: if record.field.unwrap() == 1 {
-                            0
-                        } else {
-                            find_nth(delimiter, record.field.unwrap() - 1, line).unwrap_or(0)
-                        },
-                        end: find_nth(delimiter, record.field.unwrap(), line).unwrap_or(line.len()),
-                        name: record.name.clone(),
-                        applied: false,
-                    })
-                }
-            }
-        }
-    }
+    /// The syntax file to read the first input file with
+    syntax_file: String,
+
+    /// The second input file, whose lines are matched to those of the first.
+    #[arg(long = "with", value_name = "INPUT_FILE")]
+    with: String,
 
-    Ok(regions)
+    /// The syntax file to read the second input file with, if it isn't the same as the first's.
+    #[arg(long = "with-syntax", value_name = "SYNTAX_FILE")]
+    with_syntax: Option<String>,
+
+    /// The field, in both files, to match lines on. Lines without it are left out.
+    #[arg(long = "key", value_name = "FIELD")]
+    key: String,
+
+    /// How to write the joined lines.
+    #[arg(long = "format", value_enum, default_value_t = JoinFormat::Html)]
+    format: JoinFormat,
 }
 
-/// Find the `n`th occurrence of `delimiter` in `line`, and return the index of it, or `None` if it wasn't there.
-fn find_nth(delimiter: &char, mut n: usize, line: &str) -> Option<usize> {
-    for (idx, c) in line.chars().enumerate() {
-        if c == *delimiter {
-            n -= 1;
-            if n == 0 {
-                return Some(idx);
-            }
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum JoinFormat {
+    /// An HTML report with the highlighted lines of each key side by side
+    Html,
+    /// A CSV table with a row for each pair of matching lines, holding the fields of both
+    Csv,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct ValidateArgs {
+    /// The control file to check
+    input_file: String,
+
+    /// The syntax file to read the control file with
+    syntax_file: String,
+
+    /// The data file the control file describes.
+    #[arg(long = "with", value_name = "DATA_FILE")]
+    with: String,
+
+    /// The syntax file to read the data file with, if it isn't the same as the control file's.
+    #[arg(long = "with-syntax", value_name = "SYNTAX_FILE")]
+    with_syntax: Option<String>,
+
+    /// A field in both files to match lines on. Every key in the control file must have lines in the data file, and every key in the data file must be in the control file. Without it, the control file describes the data file as a whole.
+    #[arg(long = "join", value_name = "FIELD")]
+    join: Option<String>,
+
+    /// A field of the control file giving the number of lines in the data file (with its key, if joining).
+    #[arg(long = "count", value_name = "FIELD")]
+    count: Option<String>,
+
+    /// A field of the control file giving the sum of a field in the data file, written `CONTROL_FIELD=DATA_FIELD`, or just the name if it is the same in both. Can be given more than once.
+    #[arg(long = "total", value_name = "FIELD")]
+    total: Vec<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct ExportRulesetArgs {
+    /// The syntax file to use
+    syntax_file: String,
+
+    /// Write the ruleset to this file rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct ExtractArgs {
+    /// The input file to process
+    input_file: String,
+
+    /// The syntax file to use
+    syntax_file: String,
+
+    /// The format to write the table in.
+    #[arg(long = "format", value_enum)]
+    format: ExtractFormat,
+
+    /// Write the table to this file rather than to standard output.
+    #[arg(short = 'o', long = "output")]
+    output: Option<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ExtractFormat {
+    /// An Arrow IPC file, which pandas, polars, DuckDB and others read straight into a dataframe
+    Arrow,
+}
+
+/// The default `--max-line-length`, 1 MiB.
+const DEFAULT_MAX_LINE_LENGTH: usize = 1024 * 1024;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SyntaxFormat {
+    /// CSV rules
+    Csv,
+    /// A YAML document of fields and record types
+    Yaml,
+    /// A TOML document of fields and record types
+    Toml,
+    /// A COBOL copybook
+    Copybook,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// An HTML report
+    Html,
+    /// Coloured text for a terminal
+    Ansi,
+    /// A JSON array of the fields of each line, one line of output per line of input
+    Json,
+    /// JSON Lines: an object of each line's number, record type, fields and problems, written as soon as the line is
+    /// read, for consumers to process as the input is
+    Jsonl,
+    /// A Markdown document with each line in a fenced block followed by a table of its fields
+    Markdown,
+    /// A CSV table of the value of every field on every line
+    Csv,
+    /// A paginated PDF of the lines, in their fields' colours
+    Pdf,
+    /// An Excel workbook with a sheet of the field values of each record type and a sheet of the problems found
+    Xlsx,
+}
+
+/// How the lines of the input end, from `--line-ending`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LineEnding {
+    /// As the first line does
+    Auto,
+    /// A line feed
+    Lf,
+    /// A carriage return and line feed
+    Crlf,
+    /// A carriage return on its own
+    Cr,
+}
+
+impl LineEnding {
+    /// The line ending as it's usually written, e.g. `CRLF`.
+    fn name(self) -> &'static str {
+        match self {
+            LineEnding::Auto => "the first line's ending",
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+            LineEnding::Cr => "CR",
         }
     }
-    None
 }
 
-fn produce_html_for_line(
-    line_index: usize,
-    line: String,
-    mut regions: Vec<HighlightRegion>,
-    colors: &Vec<String>,
-) {
-    print!(r#"<span style="color:#909090;">L{:3}&nbsp;&gt;&nbsp;</span>"#, line_index + 1);
-    let mut color_idx = 0;
-    let mut opened_tags = 0;
-    for (col, chr) in line.chars().enumerate() {
-        for r in &regions {
-            if r.start == col {
-                let style = format!("background: #{}; color: #020202;", colors[color_idx]);
-                color_idx = (color_idx + 1) % colors.len();
-                opened_tags += 1;
-                print!(r#"<abbr title="{}" style="{}">"#, r.name, style);
-            }
-        }
-        print!("{}", chr);
-        for r in &mut regions {
-            if r.end == col + 1 {
-                print!("</abbr>");
-                opened_tags -= 1;
-                r.applied = true;
-            }
-        }
+/// The bytes a UTF-8 byte order mark is written as.
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The 0-based index of each line that didn't end as expected, with why, as [`BoundedLines`] finds them.
+type UnexpectedEndings = Rc<RefCell<VecDeque<(usize, String)>>>;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StripeBy {
+    /// Tint lines by their record type
+    #[value(name = "record_type")]
+    RecordType,
+}
+
+/// Scrolls to and outlines the line or field named by the `line` and `field` query parameters, e.g. `?line=120&field=amount`.
+const DEEP_LINK_SCRIPT: &str = r#"<script>
+(function () {
+  var params = new URLSearchParams(window.location.search);
+  var line = params.get("line");
+  if (!line) return;
+  var id = "L" + line;
+  var field = params.get("field");
+  if (field) id += "-" + field.toLowerCase().replace(/[^a-z0-9]+/g, "-").replace(/^-+|-+$/g, "");
+  var el = document.getElementById(id) || document.getElementById("L" + line);
+  if (!el) return;
+  el.scrollIntoView({ block: "center" });
+  el.style.outline = "2px solid #0060df";
+})();
+</script>"#;
+
+/// Key bindings for moving around the report: `j`/`k` for the next/previous line, `n`/`p` for the next/previous field and `g` to jump to a line number.
+const KEYBOARD_SCRIPT: &str = r#"<script>
+(function () {
+  var lines = Array.prototype.slice.call(document.querySelectorAll("pre span[id^='L']"));
+  var fields = Array.prototype.slice.call(document.querySelectorAll("pre abbr[id]"));
+  var current = null;
+
+  function select(el) {
+    if (!el) return;
+    if (current) current.style.outline = "";
+    current = el;
+    el.style.outline = "2px solid #0060df";
+    el.scrollIntoView({ block: "nearest" });
+  }
+
+  function step(list, forwards) {
+    if (!list.length) return;
+    if (!current) return select(forwards ? list[0] : list[list.length - 1]);
+    var lo = 0, hi = list.length;
+    while (lo < hi) {
+      var mid = (lo + hi) >> 1;
+      var pos = current.compareDocumentPosition(list[mid]);
+      if (list[mid] === current || pos & Node.DOCUMENT_POSITION_PRECEDING || pos & Node.DOCUMENT_POSITION_CONTAINS) lo = mid + 1;
+      else hi = mid;
     }
+    var idx = forwards ? lo : lo - 1;
+    if (!forwards && list[idx] === current) idx -= 1;
+    if (idx >= 0 && idx < list.length) select(list[idx]);
+  }
 
-    let problem = opened_tags != 0;
-    if problem {
-        error!(
-            "Line {} was not long enough to fit the matching regions.",
-            line_index + 1
-        );
-        for _ in 0..opened_tags {
-            print!("</abbr>");
-        }
+  document.addEventListener("keydown", function (e) {
+    if (e.ctrlKey || e.metaKey || e.altKey) return;
+    var tag = e.target.tagName;
+    if (tag === "INPUT" || tag === "TEXTAREA" || tag === "SELECT") return;
+    switch (e.key) {
+      case "j": step(lines, true); break;
+      case "k": step(lines, false); break;
+      case "n": step(fields, true); break;
+      case "p": step(fields, false); break;
+      case "g":
+        var line = window.prompt("Jump to line:");
+        if (line) select(document.getElementById("L" + parseInt(line, 10)));
+        break;
+      default: return;
     }
+    e.preventDefault();
+  });
+})();
+</script>"#;
 
-    print!(r#"<span style="color:#909090;">&nbsp;&lt;</span>"#);
-    if problem {
-        print!(r#"&nbsp;<span style="color:red;">Matching regions extend beyond the end of line.</span>"#);
+/// Keeps the raw and decoded panes of `--two-pane` scrolled to the same line.
+const TWO_PANE_SCRIPT: &str = r#"<script>
+(function () {
+  var raw = document.getElementById("ffh-raw");
+  var decoded = document.getElementById("ffh-decoded");
+  var lines = Array.prototype.slice.call(raw.querySelectorAll("span[id^='L']"));
+  var entries = Array.prototype.slice.call(decoded.querySelectorAll("div[id^='D']"));
+  var syncing = null;
+
+  function topmost(list, scrollTop) {
+    var lo = 0, hi = list.length - 1;
+    while (lo < hi) {
+      var mid = (lo + hi + 1) >> 1;
+      if (list[mid].offsetTop <= scrollTop) lo = mid;
+      else hi = mid - 1;
     }
-    
-    println!();
+    return lo;
+  }
 
-    for r in regions {
-        if !r.applied {
-            error!(
-                "Failed to highlight rule '{}' on line {}!",
-                r.name, line_index + 1
-            );
+  function follow(from, fromList, to, toList) {
+    from.addEventListener("scroll", function () {
+      if (syncing === to) { syncing = null; return; }
+      if (!fromList.length) return;
+      var idx = topmost(fromList, from.scrollTop);
+      syncing = from;
+      to.scrollTop = toList[idx].offsetTop;
+    });
+  }
+
+  follow(raw, lines, decoded, entries);
+  follow(decoded, entries, raw, lines);
+})();
+</script>"#;
+
+/// Draws the minimap described by `ffhMinimap` and jumps to the line clicked on.
+const MINIMAP_SCRIPT: &str = r#"<script>
+(function () {
+  var map = ffhMinimap;
+  var canvas = document.getElementById("ffh-minimap");
+  var count = map.lines.length;
+  if (!count) return;
+
+  function lineAt(y) {
+    return Math.min(count - 1, Math.floor((y / canvas.clientHeight) * count));
+  }
+
+  function draw() {
+    var height = canvas.clientHeight;
+    canvas.width = 16;
+    canvas.height = height;
+    var ctx = canvas.getContext("2d");
+    for (var y = 0; y < height; y++) {
+      var type = map.lines[lineAt(y)];
+      ctx.fillStyle = map.colors[type % map.colors.length];
+      ctx.fillRect(0, y, 10, 1);
+    }
+    ctx.fillStyle = "red";
+    map.errors.forEach(function (line) {
+      ctx.fillRect(10, Math.floor((line / count) * height), 6, 2);
+    });
+  }
+
+  canvas.addEventListener("click", function (e) {
+    var el = document.getElementById("L" + (lineAt(e.offsetY) + 1));
+    if (el) el.scrollIntoView({ block: "center" });
+  });
+  canvas.addEventListener("mousemove", function (e) {
+    var line = lineAt(e.offsetY);
+    var type = map.types[map.lines[line]];
+    canvas.title = "Line " + (line + 1) + (type ? " (" + type + ")" : "");
+  });
+  window.addEventListener("resize", draw);
+  draw();
+})();
+</script>"#;
+
+/// Offers the embedded `ffh-data` document for download as JSON or as a `line,field,value` CSV.
+const DOWNLOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var data = JSON.parse(document.getElementById("ffh-data").textContent);
+  function download(name, type, content) {
+    var a = document.createElement("a");
+    a.href = URL.createObjectURL(new Blob([content], { type: type }));
+    a.download = name;
+    document.body.appendChild(a);
+    a.click();
+    a.remove();
+  }
+  function csvCell(value) {
+    value = String(value);
+    return /[",\r\n]/.test(value) ? '"' + value.replace(/"/g, '""') + '"' : value;
+  }
+  document.getElementById("ffh-download-json").addEventListener("click", function () {
+    download("fields.json", "application/json", JSON.stringify(data, null, 2));
+  });
+  document.getElementById("ffh-download-csv").addEventListener("click", function () {
+    var rows = ["line,field,start,length,value"];
+    data.forEach(function (line) {
+      line.fields.forEach(function (f) {
+        rows.push([line.line, f.name, f.start, f.length, f.value].map(csvCell).join(","));
+      });
+    });
+    download("fields.csv", "text/csv", rows.join("\n") + "\n");
+  });
+})();
+</script>"#;
+
+/// Copies the `data-copy` attribute of a clicked copy control to the clipboard.
+const COPY_SCRIPT: &str = r#"<script>
+document.addEventListener("click", function (e) {
+  var el = e.target.closest("[data-copy]");
+  if (!el) return;
+  e.preventDefault();
+  navigator.clipboard.writeText(el.getAttribute("data-copy")).then(function () {
+    var label = el.textContent;
+    el.textContent = "copied";
+    setTimeout(function () { el.textContent = label; }, 1000);
+  });
+});
+</script>"#;
+
+/// Runs the search bar of `--search`: steps through the lines listed in `ffh-problem-lines`, and through the fields whose
+/// value matches the one searched for, any field containing it or a chosen field that is exactly it once trimmed.
+const SEARCH_SCRIPT: &str = r#"<script>
+(function () {
+  var bar = document.getElementById("ffh-search");
+  var field = document.getElementById("ffh-search-field");
+  var value = document.getElementById("ffh-search-value");
+  var current = null;
+
+  function select(el) {
+    if (current) current.style.outline = "";
+    current = el;
+    el.style.outline = "2px solid #0060df";
+    el.scrollIntoView({ block: "center" });
+  }
+
+  // a stepper through `list()`, which shows where it is in `counter`, starting again whenever `list` changes
+  function stepper(list, counter) {
+    var items = null, at = -1;
+    var step = function (forwards) {
+      if (!items) { items = list(); at = -1; }
+      if (!items.length) { counter.textContent = bar.dataset.noneText; return; }
+      at = forwards ? (at + 1) % items.length : (at <= 0 ? items.length : at) - 1;
+      select(items[at]);
+      counter.textContent = bar.dataset.countText.replace("{}", at + 1).replace("{}", items.length);
+    };
+    step.reset = function () { items = null; counter.textContent = ""; };
+    return step;
+  }
+
+  var problems = stepper(function () {
+    return JSON.parse(document.getElementById("ffh-problem-lines").textContent)
+      .map(function (line) { return document.getElementById("L" + line); })
+      .filter(Boolean);
+  }, document.getElementById("ffh-problem-count"));
+
+  var matches = stepper(function () {
+    var wanted = value.value.trim();
+    if (!wanted) return [];
+    var fields = Array.prototype.slice.call(document.querySelectorAll("pre abbr[data-value]"));
+    return fields.filter(function (el) {
+      if (field.value) return el.dataset.field === field.value && el.dataset.value.trim() === wanted;
+      return el.dataset.value.toLowerCase().indexOf(wanted.toLowerCase()) >= 0;
+    });
+  }, document.getElementById("ffh-search-count"));
+
+  document.getElementById("ffh-problem-previous").addEventListener("click", function () { problems(false); });
+  document.getElementById("ffh-problem-next").addEventListener("click", function () { problems(true); });
+  document.getElementById("ffh-search-previous").addEventListener("click", function () { matches(false); });
+  document.getElementById("ffh-search-next").addEventListener("click", function () { matches(true); });
+  field.addEventListener("change", matches.reset);
+  value.addEventListener("input", matches.reset);
+  value.addEventListener("keydown", function (e) {
+    if (e.key !== "Enter") return;
+    matches(!e.shiftKey);
+    e.preventDefault();
+  });
+})();
+</script>"#;
+
+/// Shows the details of a clicked field in the `ffh-inspect` panel, and outlines every field of the same name as the
+/// one hovered over.
+const INSPECT_SCRIPT: &str = r#"<script>
+(function () {
+  var panel = document.getElementById("ffh-inspect");
+  var same = [];
+
+  function closestField(target) {
+    return target.closest ? target.closest("abbr[data-field]") : null;
+  }
+
+  document.addEventListener("click", function (e) {
+    var field = closestField(e.target);
+    if (!field) return;
+    panel.querySelector("[data-inspect=line]").textContent = panel.getAttribute("data-line-text").replace("{}", field.dataset.line);
+    ["field", "start", "length", "value", "check"].forEach(function (key) {
+      panel.querySelector("[data-inspect=" + key + "]").textContent = field.dataset[key];
+    });
+    panel.hidden = false;
+  });
+  document.getElementById("ffh-inspect-close").addEventListener("click", function () {
+    panel.hidden = true;
+  });
+
+  document.addEventListener("mouseover", function (e) {
+    var field = closestField(e.target);
+    same.forEach(function (el) { el.classList.remove("ffh-same"); });
+    same = [];
+    if (!field) return;
+    same = Array.prototype.slice.call(document.querySelectorAll('abbr[data-field="' + CSS.escape(field.dataset.field) + '"]'));
+    same.forEach(function (el) { el.classList.add("ffh-same"); });
+  });
+})();
+</script>"#;
+
+fn main() -> ExitCode {
+    diagnostics::init();
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            diagnostics::fatal(&e);
+            // a run that found problems with its input still finished, unlike one that failed
+            ExitCode::from(if e.is::<ProblemsFound>() {
+                EXIT_PROBLEMS
+            } else {
+                EXIT_FATAL
+            })
+        }
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let matches = Args::command().get_matches_from(&argv);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if !args.no_config {
+        let subcommand = matches.subcommand_name();
+        let configured = config::arguments(args.profile.as_deref(), subcommand.is_some())?;
+        if !configured.is_empty() {
+            // the config files' options go first, so that those given on the command line take precedence, and after
+            // a subcommand's name, as the options before it are the report's
+            let at = subcommand
+                .and_then(|name| argv.iter().position(|a| a == name))
+                .map_or(1, |i| i + 1);
+            let mut argv = argv;
+            argv.splice(at..at, configured);
+            args = Args::parse_from(argv);
+        }
+    }
+    diagnostics::configure(args.quiet, args.log_format);
+    if args.command.is_none() && args.syntax_file.is_none() {
+        // given just the syntax file, the input comes from standard input, e.g. the end of a pipeline
+        if io::stdin().is_terminal() {
+            bail!("No input file was given, and standard input is a terminal. Give the input file before the syntax file, or pipe the input in.");
+        }
+        args.syntax_file = args.input_file.replace(STDIN.to_owned());
+    }
+    if args.record_length == Some(0) {
+        bail!("--record-length must be at least 1 byte.");
+    }
+    if args.filter.as_ref().is_some_and(Condition::is_field_test) {
+        bail!("--filter should be a regex, as lines are selected before their fields are found.");
+    }
+    if args
+        .skip_lines_matching
+        .iter()
+        .any(Condition::is_field_test)
+    {
+        bail!("--skip-lines-matching should be a regex, as skipped lines have no fields.");
+    }
+    if args.record_length.is_some() && args.encoding == Encoding::Utf8 {
+        // records are counted in bytes, which may be anything, so each byte is read as a character of its own
+        args.encoding = Encoding::Latin1;
+    }
+
+    match &args.command {
+        Some(Command::Extract(extract)) => return run_extract(&args, extract),
+        Some(Command::ExportRuleset(export)) => return run_export_ruleset(&args, export),
+        Some(Command::Validate(validate)) => return validate::run(&args, validate),
+        Some(Command::Join(join)) => return join::run(&args, join),
+        Some(Command::Map(map)) => return map::run(&args, map),
+        Some(Command::Rebuild(rebuild)) => return rebuild::run(&args, rebuild),
+        Some(Command::SchemaConvert(convert)) => return schema_convert::run(&args, convert),
+        Some(Command::Scaffold(scaffold)) => return scaffold::run(&args, scaffold),
+        Some(Command::Infer(infer)) => return infer::run(&args, infer),
+        Some(Command::Edit(edit)) => return edit::run(&args, edit),
+        Some(Command::Serve(serve)) => return serve::run(&args, serve),
+        Some(Command::Explain(explain)) => return explain::run(&args, explain),
+        Some(Command::CheckSyntax(check)) => return check_syntax::run(&args, check),
+        None => (),
+    }
+    if args.batch.is_some() {
+        return reports::run(&args);
+    }
+    write_report(&args)?.problems.check()
+}
+
+/// Write the report of the input file, giving what was found in it.
+fn write_report(args: &Args) -> anyhow::Result<RunSummary> {
+    let mut input_file = args.input_file.as_deref().expect("required by clap");
+    let stdin_copy;
+    if input_file == STDIN {
+        if args.resume.is_some() {
+            bail!("--resume can't be used when reading standard input, which can't be read again.");
+        }
+        let rereads = match args.output_format {
+            OutputFormat::Html => rereads_input(args),
+            OutputFormat::Pdf => args.legend == Some(LegendPosition::Top),
+            OutputFormat::Ansi => args.display_width,
+            _ => false,
+        };
+        if rereads {
+            if args.follow {
+                // the copy would wait for the end of standard input, which never comes
+                bail!("--follow can't be used with sections at the top of the report when reading standard input, as they read it before its lines are rendered.");
+            }
+            info!("Copying standard input to a temporary file");
+            stdin_copy = StdinCopy::new()?;
+            input_file = stdin_copy.path()?;
+        }
+    }
+
+    let mut render = render_options(args)?;
+
+    // parse input file into lines
+    info!("Parsing input file");
+    let mut timings = Timings::default();
+    if args.follow
+        && !matches!(
+            args.output_format,
+            OutputFormat::Ansi
+                | OutputFormat::Html
+                | OutputFormat::Json
+                | OutputFormat::Jsonl
+                | OutputFormat::Csv
+        )
+    {
+        bail!("--follow can only be used with --output-format ansi, html, json, jsonl or csv.");
+    }
+    if args.follow && args.snippet {
+        bail!("--follow can't be used with --snippet, as a snippet isn't a page that can reload itself.");
+    }
+    if args.resume.is_some() && args.output_format != OutputFormat::Html {
+        bail!("--resume can only be used with --output-format html.");
+    }
+    if args.split_every.is_some() && args.output_format != OutputFormat::Html {
+        bail!("--split-every can only be used with --output-format html.");
+    }
+    if args.display_width && !matches!(args.output_format, OutputFormat::Html | OutputFormat::Ansi)
+    {
+        bail!("--display-width can only be used with --output-format html or ansi.");
+    }
+    if args.show_padding
+        && !matches!(
+            args.output_format,
+            OutputFormat::Html | OutputFormat::Ansi | OutputFormat::Pdf
+        )
+    {
+        bail!("--show-padding can only be used with --output-format html, ansi or pdf.");
+    }
+    if matches!(args.output_format, OutputFormat::Pdf | OutputFormat::Xlsx)
+        && args.output.is_none()
+        && io::stdout().is_terminal()
+    {
+        bail!(
+            "Refusing to write a {} to a terminal. Redirect the output or use --output.",
+            if args.output_format == OutputFormat::Pdf {
+                "PDF"
+            } else {
+                "workbook"
+            }
+        );
+    }
+    if let Some(sample) = args.dry_run {
+        return run_dry_run(args, input_file, sample).map(|()| RunSummary::default());
+    }
+    if args.validate {
+        return run_validation(args, input_file).map(|()| RunSummary::default());
+    }
+    let checkpoint = match &args.resume {
+        Some(path) => Checkpoint::load(path)?,
+        None => None,
+    };
+    let compressed = compression::detect(input_file, args.compression)
+        .context("Failed to open input file.")?
+        != Compression::None;
+    if compressed && input_file != STDIN {
+        if args.resume.is_some() {
+            bail!("--resume can't be used with compressed input, which can't be read from part way through.");
+        }
+        if args.follow {
+            bail!("--follow can't be used with a compressed input file, which can't be read until it's complete.");
+        }
+    }
+    let reader: Box<dyn BufRead> = if input_file == STDIN || compressed {
+        // reading a pipe already waits for more input, so `--follow` needs nothing more
+        Box::new(BufReader::new(CountingReader::new(
+            open_input(args, input_file)?,
+            timings.bytes.clone(),
+        )))
+    } else {
+        let mut file = File::open(input_file).context("Failed to open input file.")?;
+        let mut offset = 0;
+        if let Some(checkpoint) = &checkpoint {
+            info!("Resuming from line {}", checkpoint.lines + 1);
+            offset = checkpoint.offset;
+            timings.bytes.set(checkpoint.offset);
+            timings.lines = checkpoint.lines;
+            timings.rules_evaluated = checkpoint.rules_evaluated;
+        }
+        // a followed file grows past the end of any mapping of it
+        let mapped = if args.follow || args.no_mmap {
+            None
+        } else {
+            MappedFile::open(&file, offset)
+        };
+        match mapped {
+            Some(mapped) => Box::new(mapped.counted(timings.bytes.clone())),
+            None => {
+                if offset > 0 {
+                    file.seek(std::io::SeekFrom::Start(offset))
+                        .context("Failed to resume from checkpoint.")?;
+                }
+                let file: Box<dyn Read> = if args.follow {
+                    Box::new(FollowReader::new(file))
+                } else {
+                    Box::new(file)
+                };
+                Box::new(BufReader::new(CountingReader::new(
+                    file,
+                    timings.bytes.clone(),
+                )))
+            }
+        }
+    };
+    // parse syntax file into vec
+    info!("Parsing syntax file");
+    let phase = Instant::now();
+    let (syntax_file, records, positions, assembly) = load_syntax(
+        args,
+        args.syntax_file.as_deref().expect("required by clap"),
+        Some(input_file),
+    )?;
+    timings.parse = phase.elapsed();
+    if args.resume.is_some() && assembly.joins_lines() {
+        bail!("--resume can't be used with a syntax file that gives `continues_if`.");
+    }
+    if args.display_width {
+        info!("Finding the width of each column");
+        let phase = Instant::now();
+        render.display_widths = Some(display_widths(args, input_file, &assembly)?);
+        timings.scan = Some(phase.elapsed());
+    }
+    render.record_gutter = record_gutter(args, &records);
+
+    let mut lines = BoundedLines::new(reader, args.max_line_length, args.encoding)
+        .fixed_length(args.record_length)
+        .line_endings(args)
+        .assembled(&assembly);
+    let unexpected_endings = lines.unexpected_endings();
+    let mut first_index = 0;
+    if let Some(checkpoint) = &checkpoint {
+        lines = lines.starting_at(checkpoint.offset, checkpoint.lines);
+        first_index = checkpoint.lines;
+    }
+    let mut lines = SelectedLines::new(lines, LineSelection::new(args))
+        .filtered(args.filter.clone(), args.invert_filter)
+        .starting_at(first_index);
+
+    if let Some(path) = &args.compile_syntax {
+        info!("Writing compiled syntax file");
+        fs::write(path, compiled::compile(&records, &assembly, &syntax_file)?)
+            .context("Failed to write compiled syntax file.")?;
+    }
+    let mut counts = RunCounts {
+        lines: 0,
+        usage: RuleUsage::new(&records, positions),
+        totals: ControlTotals::new(&records, args.encoding),
+        metrics: args.metrics.as_ref().map(|_| Metrics::new(&records)),
+        stats: args.stats.map(|top| FieldStats::new(&records, top)),
+        problems: Problems::new(args, positions)?,
+        progress: if args.follow {
+            // a followed file never ends, so there's no progress through it to show
+            Progress::hidden()
+        } else {
+            let size = (input_file != STDIN && !compressed)
+                .then(|| fs::metadata(input_file).ok().map(|m| m.len()))
+                .flatten();
+            Progress::new(timings.bytes.clone(), size)
+        },
+        unexpected_endings,
+    };
+    if let Some(checkpoint) = &checkpoint {
+        counts.restore(checkpoint)?;
+    }
+
+    let output = match &args.output {
+        Some(path) => Some(
+            File::options()
+                .write(true)
+                .create(true)
+                // a resumed report carries on from what was already written
+                .truncate(checkpoint.is_none())
+                .open(path)
+                .context("Failed to create output file.")?,
+        ),
+        None => None,
+    };
+    let mut resume = match &args.resume {
+        Some(path) => {
+            let report = match &output {
+                Some(file) => file.try_clone().context("Failed to access output file.")?,
+                None => stdout_file()?,
+            };
+            let mut resume = Resume::new(path, input_file, &syntax_file, report)?;
+            if let Some(checkpoint) = &checkpoint {
+                resume.restore(checkpoint)?;
+            }
+            Some(resume)
+        }
+        None => None,
+    };
+
+    let mut out: Box<dyn Write> = match output {
+        Some(file) => Box::new(BufWriter::new(file)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    if args.output_format != OutputFormat::Html {
+        info!("Creating regions and outputting");
+        let mut pager = (args.output_format == OutputFormat::Ansi
+            && args.output.is_none()
+            && !args.no_pager
+            && !args.follow
+            && io::stdout().is_terminal())
+        .then(start_pager)
+        .flatten();
+        if let Some(stdin) = pager.as_mut().and_then(|p| p.stdin.take()) {
+            out = Box::new(BufWriter::new(stdin));
+        }
+        let result = line_report(
+            args, out, input_file, &records, positions, &assembly, &render,
+        )
+        .and_then(|report| {
+            write_lines(
+                report,
+                args,
+                lines,
+                &records,
+                positions,
+                &mut timings,
+                &mut counts,
+            )
+        });
+        if let Some(mut pager) = pager {
+            let _ = pager.wait();
+        }
+        result?;
+        if args.timing {
+            timings.log();
+        }
+        let summary = counts.finish(args)?;
+        info!("Done!");
+        return Ok(summary);
+    }
+
+    let phase = Instant::now();
+    // when resuming, the sections at the top of the report have already been written
+    let preflight = if args.preflight && checkpoint.is_none() {
+        info!("Running preflight scan");
+        Some(run_preflight(
+            args, input_file, &records, positions, &assembly,
+        )?)
+    } else {
+        None
+    };
+
+    let profile = match args.profile_fields {
+        Some(threshold) if checkpoint.is_none() => {
+            info!("Profiling fields");
+            Some(run_field_profile(
+                args, input_file, &records, positions, &assembly, threshold,
+            )?)
+        }
+        _ => None,
+    };
+
+    let scan = if (args.toc || args.dashboard || args.minimap) && checkpoint.is_none() {
+        info!("Scanning input file");
+        Some(scan_input(
+            args, input_file, &records, positions, &assembly,
+        )?)
+    } else {
+        None
+    };
+    let top_legend = if args.legend == Some(LegendPosition::Top) && checkpoint.is_none() {
+        info!("Finding the fields of each record type");
+        Some(Legend::scan(
+            args, input_file, &records, positions, &assembly, &render,
+        )?)
+    } else {
+        None
+    };
+    if preflight.is_some() || profile.is_some() || scan.is_some() || top_legend.is_some() {
+        timings.scan = Some(phase.elapsed());
+    }
+
+    // create highlighted regions and output as HTML
+    info!("Creating regions and outputting");
+    let resuming = checkpoint.is_some();
+    let (first_line, mut stripes) = match checkpoint {
+        Some(checkpoint) => (checkpoint.lines, checkpoint.stripes),
+        None => (0, RecordTypeStripes::default()),
+    };
+    let sections = TopSections {
+        preflight,
+        profile,
+        scan,
+        legend: top_legend,
+    };
+    // a split report's lines are written to its parts, and the sections at the top to the index once they're written
+    let mut split = match (&args.output, args.split_every) {
+        (Some(path), Some(every)) => Some(SplitReport::new(path, every as usize)),
+        _ => None,
+    };
+    let mut index = split
+        .is_some()
+        .then(|| std::mem::replace(&mut out, Box::new(io::sink())));
+    if !resuming && split.is_none() {
+        write_report_top_html(&mut out, args, input_file, &records, &sections)?;
+        write_lines_start_html(&mut out, args)?;
+    }
+    // when resuming, the legend must still cover the lines before the checkpoint, so is found by reading them again
+    let mut legend = match args.legend {
+        Some(LegendPosition::Bottom) if resuming => Some(Legend::scan(
+            args, input_file, &records, positions, &assembly, &render,
+        )?),
+        Some(LegendPosition::Bottom) => Some(Legend::default()),
+        _ => None,
+    };
+    let mut embedded_data = args.embed_data.then(Spool::new);
+    let mut decoded_pane = args.two_pane.then(Spool::new);
+    // the 1-based numbers of the lines with problems, for the search bar to step through
+    let mut problem_lines = args.search.then(Vec::new);
+    if let Some(resume) = &mut resume {
+        if !resuming {
+            resume.save(
+                &mut out,
+                lines.inner(),
+                0,
+                timings.rules_evaluated,
+                &stripes,
+                &counts,
+            )?;
+        }
+    }
+    let batches = BatchRenderer {
+        records: &records,
+        positions,
+        short_lines: args.short_lines,
+        ghost_cells: args.ghost_cells,
+        skip_lines: &args.skip_lines_matching,
+        renderer: &render,
+        threads: args.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |threads| threads.get())
+        }),
+    };
+    let mut idx = first_line;
+    loop {
+        // a batch never runs past a checkpoint, so the input is read up to the checkpoint's line when it's saved, and
+        // when following the input, each line is written as soon as it arrives rather than waiting for a full batch
+        let batch_size = if args.follow {
+            1
+        } else {
+            BATCH_LINES.min(RESUME_INTERVAL - idx % RESUME_INTERVAL)
+        };
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut record_types = Vec::with_capacity(batch_size);
+        for line in lines.by_ref().take(batch_size) {
+            let (line_index, line) = line.context("Failed to read line from input file.")?;
+
+            let record_type = if args.stripe_by.is_some() || (legend.is_some() && !resuming) {
+                record_type_of(&records, &record_text(&line))
+            } else {
+                None
+            };
+            let background = match args.stripe_by {
+                Some(StripeBy::RecordType) => {
+                    Some(stripes.background(record_type.clone(), args.dark))
+                }
+                // every other row of the report, which isn't every other line of the input if lines are left out
+                None if args.zebra && (idx + batch.len()) % 2 == 1 => Some(
+                    if args.dark {
+                        DARK_ZEBRA_COLOR
+                    } else {
+                        ZEBRA_COLOR
+                    }
+                    .to_owned(),
+                ),
+                None => None,
+            };
+            batch.push((line_index, line, background));
+            record_types.push((line_index, record_type));
+        }
+        if batch.is_empty() {
+            break;
+        }
+
+        for (rendered, (line_index, record_type)) in
+            batches.render(batch).into_iter().zip(record_types)
+        {
+            let rendered = rendered?;
+            let prepared = &rendered.prepared;
+            timings.regions += rendered.regions_time;
+            timings.lines += 1;
+            timings.rules_evaluated += records.len() as u64;
+            let found = counts.problems.found();
+            let failed_checks = counts.count_line(&records, line_index, prepared)?;
+            let suspicious = if args.check_unicode {
+                suspicious::log_line(line_index, &prepared.text, &prepared.regions)
+            } else {
+                0
+            };
+            let violations = constraints::log_line(
+                line_index,
+                &prepared.text,
+                &prepared.regions,
+                prepared.ghost_from,
+            );
+            counts.count_findings(violations, suspicious);
+            if let Some(problem_lines) = &mut problem_lines {
+                if counts.problems.found() > found {
+                    problem_lines.push(line_index + 1);
+                }
+            }
+            // the line's been checked, so from here on it's shown with its sensitive fields masked
+            let shown = redacted(prepared);
+            let prepared = shown.as_ref();
+
+            let phase = Instant::now();
+            if let Some(data) = &mut embedded_data {
+                data.push_str(&format!(
+                    r#"{}{{"line":{},"fields":{}}}"#,
+                    if idx > 0 { "," } else { "" },
+                    line_index + 1,
+                    // `</` must not appear inside a script element
+                    fields_json(&prepared.text, &prepared.regions, prepared.ghost_from)
+                        .replace("</", "<\\/")
+                ))?;
+            }
+            if let Some(pane) = &mut decoded_pane {
+                pane.push_str(&decoded_fields_html(
+                    line_index,
+                    &prepared.text,
+                    &prepared.regions,
+                    prepared.ghost_from,
+                ))?;
+            }
+            if let Some(split) = &mut split {
+                split.start_line(&mut out, args, input_file, &records, line_index)?;
+            }
+            out.write_all(rendered.html.as_bytes())?;
+            for failed in &failed_checks {
+                render.write_failed_check_html(&mut out, failed)?;
+            }
+            if let Some(legend) = legend.as_mut().filter(|_| !resuming) {
+                legend.add_line(&records, positions, &render, record_type, prepared);
+            }
+            timings.render += rendered.render_time + phase.elapsed();
+
+            idx += 1;
+        }
+        if args.follow {
+            out.flush().context("Failed to write report.")?;
+        }
+        if let Some(resume) = &mut resume {
+            if idx % RESUME_INTERVAL == 0 {
+                resume.save(
+                    &mut out,
+                    lines.inner(),
+                    idx,
+                    timings.rules_evaluated,
+                    &stripes,
+                    &counts,
+                )?;
+            }
+        }
+    }
+    if let (Some(split), Some(index)) = (&split, index.take()) {
+        split.finish(&mut out, args)?;
+        out = index;
+        let mut top = Vec::new();
+        write_report_top_html(&mut top, args, input_file, &records, &sections)?;
+        out.write_all(split.retarget(&String::from_utf8_lossy(&top)).as_bytes())?;
+        split.write_index_html(&mut out, args.locale)?;
+    } else {
+        writeln!(out, "</pre>")?;
+    }
+    if args.unused_rules {
+        counts.usage.write_html(&mut out, args.locale)?;
+    }
+    if let Some(stats) = &counts.stats {
+        stats.write_html(&mut out, args.locale)?;
+    }
+    if let Some(legend) = legend {
+        legend.write_html(&mut out, &records, args.locale)?;
+    }
+    if let Some(pane) = decoded_pane {
+        write!(
+            out,
+            r#"<div id="ffh-decoded" style="flex:1; overflow:auto; position:relative;">"#
+        )?;
+        pane.write_to(&mut out)?;
+        writeln!(out, "</div>")?;
+        writeln!(out, "</div>")?;
+        writeln!(out, "{}", TWO_PANE_SCRIPT)?;
+    }
+
+    if split.is_none() {
+        write_footer_hint_html(&mut out, args)?;
+    }
+
+    if args.timing {
+        timings.log();
+        writeln!(
+            out,
+            r#"<p><small style="color:#606060;">{}</small></p>"#,
+            escape_html(&timings.to_string())
+        )?;
+    }
+
+    let mut syntax_b64 = String::new();
+    general_purpose::STANDARD_NO_PAD.encode_string(syntax_file, &mut syntax_b64);
+    writeln!(
+        out,
+        "{}",
+        args.locale.format(
+            Message::AnalysedAt,
+            &[
+                &Local::now(),
+                &r#"<a href="https://github.com/lilopkins/fixedfile-highlighter" target="_blank" rel="noopener">fixedfile-highlighter</a>"#,
+                &format!(
+                    r#"<a href="data:{};base64,{}">{}</a>"#,
+                    match args.syntax_format {
+                        SyntaxFormat::Csv => "text/csv",
+                        SyntaxFormat::Yaml => "text/yaml",
+                        SyntaxFormat::Toml => "application/toml",
+                        SyntaxFormat::Copybook => "text/plain",
+                    },
+                    syntax_b64,
+                    args.locale.text(Message::ThisSyntaxFile)
+                ),
+            ]
+        )
+    )?;
+
+    if let Some(data) = embedded_data {
+        write_embedded_data_html(&mut out, data, args.locale)?;
+    }
+    if let Some(problem_lines) = &problem_lines {
+        write_search_html(&mut out, &records, problem_lines, args.locale)?;
+    }
+    if split.is_some() {
+        // the index has no lines for the scripts to work on
+        writeln!(out, "</body></html>")?;
+    } else {
+        write_page_end_html(&mut out, args)?;
+    }
+    out.flush().context("Failed to write report.")?;
+    if let Some(resume) = resume {
+        resume.finish()?;
+    }
+    let summary = counts.finish(args)?;
+
+    info!("Done!");
+    Ok(summary)
+}
+
+/// The hint at the foot of the report's lines, saying how to see the name of each field.
+fn write_footer_hint_html(out: &mut dyn Write, args: &Args) -> io::Result<()> {
+    write!(out, "<p><small>{}", args.locale.text(Message::FooterHint))?;
+    if args.interactive {
+        write!(out, " {}", args.locale.text(Message::InspectHint))?;
+    }
+    if !args.snippet {
+        write!(out, " {}", args.locale.text(Message::KeyHint))?;
+    }
+    writeln!(out, "</small></p>")
+}
+
+/// Write the scripts that work on the report's lines, and close the page.
+fn write_page_end_html(out: &mut dyn Write, args: &Args) -> io::Result<()> {
+    if args.copy_buttons {
+        writeln!(out, "{}", COPY_SCRIPT)?;
+    }
+    if args.interactive {
+        write_inspector_html(out, args.locale)?;
+    }
+    if !args.snippet {
+        writeln!(out, "{}", DEEP_LINK_SCRIPT)?;
+        writeln!(
+            out,
+            "{}",
+            KEYBOARD_SCRIPT.replace(
+                "Jump to line:",
+                &json_string(args.locale.text(Message::JumpToLine))
+                    .trim_matches('"')
+                    .replace("</", "<\\/")
+            )
+        )?;
+        writeln!(out, "</body></html>")?;
+    }
+    Ok(())
+}
+
+/// The sections at the top of the report, found by reading the input before rendering it.
+#[derive(Default)]
+struct TopSections {
+    preflight: Option<PreflightReport>,
+    profile: Option<FieldProfile>,
+    scan: Option<InputScan>,
+    legend: Option<Legend>,
+}
+
+/// Write the start of the report, before the lines.
+fn write_report_top_html(
+    out: &mut dyn Write,
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    sections: &TopSections,
+) -> anyhow::Result<()> {
+    let stylesheet = render_options(args)?.stylesheet(field_colors(records))
+        + &value_stylesheet(&field_value_styles(records));
+    let styles = match &args.css {
+        Some(path) => {
+            fs::write(path, stylesheet)
+                .with_context(|| format!("Failed to write stylesheet {}.", path))?;
+            format!(r#"<link rel="stylesheet" href="{}">"#, escape_html(path))
+        }
+        None => format!("<style>\n{}</style>", stylesheet),
+    };
+    if args.snippet {
+        writeln!(out, "{}", styles)?;
+    } else {
+        writeln!(out, r#"<!doctype html><html lang="{}">"#, args.locale.tag())?;
+        writeln!(
+            out,
+            r#"<head><meta charset="utf8"><title>{}</title>{}{}{}</head>"#,
+            escape_html(&args.locale.format(Message::Title, &[&input_name(args)])),
+            styles,
+            if args.dark { DARK_THEME } else { "" },
+            if args.follow { FOLLOW_REFRESH_HTML } else { "" }
+        )?;
+        writeln!(out, "<body>")?;
+    }
+    for note in shift_notes(args, records) {
+        writeln!(
+            out,
+            "<p><strong>{}</strong> {}</p>",
+            args.locale.text(Message::Note),
+            escape_html(&note)
+        )?;
+    }
+    if let Some(report) = &sections.preflight {
+        write_preflight_html(out, report, args.locale)?;
+    }
+    if let Some(profile) = &sections.profile {
+        write_field_profile_html(out, profile, args.locale)?;
+    }
+    if let Some(scan) = &sections.scan {
+        if args.dashboard {
+            write_dashboard_html(out, input_file, &input_name(args), scan, args)?;
+        }
+        if args.toc {
+            write_toc_html(out, scan, args.locale)?;
+        }
+        if args.minimap {
+            write_minimap_html(out, scan, args.locale)?;
+        }
+    }
+    if let Some(legend) = &sections.legend {
+        legend.write_html(out, records, args.locale)?;
+    }
+    Ok(())
+}
+
+/// Open the preformatted block the report's lines are written into.
+fn write_lines_start_html(out: &mut dyn Write, args: &Args) -> io::Result<()> {
+    if args.two_pane {
+        writeln!(out, r#"<div style="display:flex; gap:1em; height:90vh;">"#)?;
+        writeln!(
+            out,
+            r#"<pre id="ffh-raw" dir="ltr" style="color:red; flex:1; overflow:auto; margin:0; position:relative;">"#
+        )?;
+    } else {
+        writeln!(out, r#"<pre dir="ltr" style="color:red">"#)?;
+    }
+    Ok(())
+}
+
+/// The colours the fields of `records` are given in the syntax file.
+fn field_colors(records: &RecordList) -> Vec<&str> {
+    records
+        .rules()
+        .into_iter()
+        .filter_map(|r| r.color())
+        .collect()
+}
+
+/// The styles the rules of `records` draw fields whose values match their `value_pattern` in.
+fn field_value_styles(records: &RecordList) -> Vec<ValueStyle> {
+    records
+        .rules()
+        .into_iter()
+        .filter(|r| r.value_pattern().is_some())
+        .map(|r| r.value_style().cloned().unwrap_or_default())
+        .collect()
+}
+
+/// The colours and other options to render lines with, as the arguments describe.
+fn render_options(args: &Args) -> anyhow::Result<Renderer> {
+    // parse colours
+    let greyscale = if args.dark { DARK_GREYSCALE } else { GREYSCALE };
+    let color_preset_greyscale: Vec<String> = greyscale.iter().map(|c| c.to_string()).collect();
+    let color_preset_rainbow: Vec<String> = RAINBOW.iter().map(|c| c.to_string()).collect();
+
+    let colors = if let Some(c) = &args.colors {
+        if c.to_lowercase() == "greyscale" || c.to_lowercase() == "grayscale" {
+            color_preset_greyscale
+        } else if c.to_lowercase() == "rainbow" {
+            color_preset_rainbow
+        } else if c.to_lowercase() == "deuteranopia" || c.to_lowercase() == "protanopia" {
+            DEUTERANOPIA.iter().map(|c| c.to_string()).collect()
+        } else if c.to_lowercase() == "tritanopia" {
+            TRITANOPIA.iter().map(|c| c.to_string()).collect()
+        } else {
+            let mut cs = Vec::new();
+            for color in c.split(',') {
+                cs.push(hex_color(color)?);
+            }
+            cs
+        }
+    } else {
+        color_preset_greyscale
+    };
+
+    if colors.is_empty() {
+        bail!("No colours have been specified so no output can be produced!");
+    }
+
+    Ok(Renderer {
+        colors,
+        covered_only: args.covered_only,
+        copy_buttons: args.copy_buttons,
+        delimiter_glyph: args.delimiter_glyph,
+        field_boundaries: args.field_boundaries,
+        labels: args.labels,
+        locale: args.locale,
+        check_unicode: args.check_unicode,
+        error_color: args.show_errors.as_deref().map(hex_color).transpose()?,
+        encoding: args.encoding,
+        stable_colors: args.stable_colors,
+        overlaps: args.overlaps,
+        interactive: args.interactive,
+        searchable: args.search,
+        hex_bytes: args.record_length.is_some(),
+        css_classes: true,
+        dim_skipped: args.dim_skipped,
+        display_widths: None,
+        show_padding: args.show_padding,
+        trailing_data: args.trailing_data != TrailingData::Ignore,
+        // set from the record types once the syntax file is read
+        record_gutter: None,
+        id_prefix: "",
+    })
+}
+
+/// With `--record-gutter`, the width of the record type names shown in the gutter: that of the longest of `records`,
+/// or `None` if they define no record types, when there's nothing to show.
+fn record_gutter(args: &Args, records: &RecordList) -> Option<usize> {
+    if !args.record_gutter {
+        return None;
+    }
+    records
+        .record_types()
+        .iter()
+        .map(|t| t.name.chars().count())
+        .max()
+}
+
+/// The name of the file at `path`, without its directory, for titles.
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map_or(path.into(), |n| n.to_string_lossy())
+        .into_owned()
+}
+
+/// The delimiter given with `--delimiter`, if any, which is a regex with `--delimiter-regex`.
+fn parse_delimiter(args: &Args) -> anyhow::Result<Option<Delimiter>> {
+    args.delimiter
+        .as_deref()
+        .map(|delimiter| Delimiter::parse(delimiter, args.delimiter_regex))
+        .transpose()
+}
+
+/// Read a syntax file as the arguments describe, returning its text, its rules, how their positions are to be read and
+/// how lines are assembled into records. The syntax file can be a layouts file, whose layout is then picked by the
+/// first line of `input_file`, the input the syntax file is read for, if there is one.
+fn load_syntax(
+    args: &Args,
+    syntax_file: &str,
+    input_file: Option<&str>,
+) -> anyhow::Result<(String, RecordList, PositionOptions, Assembly)> {
+    let layout = match layouts::read(syntax_file)? {
+        Some(layouts) => Some(choose_layout(args, &layouts, input_file)?),
+        None => None,
+    };
+    let syntax_file = layout.as_deref().unwrap_or(syntax_file);
+    let syntax_dir = Path::new(syntax_file)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_owned();
+    let (syntax_file, mut records, assembly) = match args.syntax_format {
+        SyntaxFormat::Csv => read_syntax_file(syntax_file, parse_delimiter(args)?)?,
+        SyntaxFormat::Yaml | SyntaxFormat::Toml => {
+            let text =
+                fs::read_to_string(syntax_file).context("Failed to read from syntax file.")?;
+            let format = match args.syntax_format {
+                SyntaxFormat::Yaml => DocumentFormat::Yaml,
+                _ => DocumentFormat::Toml,
+            };
+            let (records, assembly) = document::parse(&text, format, parse_delimiter(args)?)?;
+            (text, records, assembly)
+        }
+        SyntaxFormat::Copybook => {
+            if args.delimiter.is_some() {
+                bail!("A copybook describes a fixed width record, so can't be used with `--delimiter`.");
+            }
+            let text =
+                fs::read_to_string(syntax_file).context("Failed to read from syntax file.")?;
+            let records = copybook::parse(&text, args.index_base as usize)?;
+            (text, records, Assembly::default())
+        }
+    };
+    if args.strict_syntax {
+        check_syntax_strictly(&records, args.index_base as usize)?;
+    }
+    allow_plugins(args, &records, &syntax_dir)?;
+    let positions = position_options(args);
+    records.shift_record_types(&args.shift_record)?;
+    place_field_conditions(&mut records, positions)?;
+    records.redact(&args.redact)?;
+    Ok((syntax_file, records, positions, assembly))
+}
+
+/// Allow the plugins named by the rules of `records`, read from a syntax file in `syntax_dir`, to run, if
+/// `--allow-plugins` says they may, failing on the first if it doesn't.
+fn allow_plugins(args: &Args, records: &RecordList, syntax_dir: &Path) -> anyhow::Result<()> {
+    for rule in records.rules() {
+        let Some(FieldType::Plugin { command }) = rule.field_type() else {
+            continue;
+        };
+        if !args.allow_plugins {
+            bail!(
+                "Syntax file line {}: rule '{}' is decoded by the plugin `{}`, a program that would be run. Give --allow-plugins to run the plugins of a syntax file you trust.",
+                rule.syntax_line(),
+                rule.name(),
+                command
+            );
+        }
+        plugin::allow(
+            command,
+            syntax_dir,
+            Duration::from_secs(args.plugin_timeout),
+        );
+    }
+    Ok(())
+}
+
+/// The syntax file of the layout of `layouts` to read `input_file` with: the one named by `--layout`, or else the one
+/// whose condition the input's first line matches.
+fn choose_layout(
+    args: &Args,
+    layouts: &[Layout],
+    input_file: Option<&str>,
+) -> anyhow::Result<String> {
+    let layout = match (&args.layout, input_file) {
+        (Some(name), _) => match layouts.iter().find(|l| l.name == *name) {
+            Some(layout) => layout,
+            None => bail!(
+                "--layout names the layout '{}', which the layouts file doesn't give. It gives {}.",
+                name,
+                layouts
+                    .iter()
+                    .map(|l| format!("'{}'", l.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
+        (None, None) => bail!(
+            "The syntax file is a layouts file, whose layout is picked by the first line of the input, but there's no input here. Give --layout to pick one."
+        ),
+        (None, Some(STDIN)) => bail!(
+            "The syntax file is a layouts file, whose layout is picked by the first line of the input, which can't be read twice from standard input. Give --layout to pick one."
+        ),
+        (None, Some(input_file)) => {
+            let file = open_buffered(args, input_file)?;
+            let first_line = match read_records(args, file, &Assembly::default()).next() {
+                Some(line) => line.context("Failed to read line from input file.")?.1,
+                None => String::new(),
+            };
+            let Some(layout) = layouts::select(layouts, &first_line) else {
+                bail!(
+                    "The first line of '{}' matches the condition of none of the layouts of the layouts file.",
+                    input_file
+                );
+            };
+            info!(
+                "Reading '{}' with layout '{}', as its first line matches",
+                input_file, layout.name
+            );
+            layout
+        }
+    };
+    Ok(layout.syntax_file.to_string_lossy().into_owned())
+}
+
+/// Where the options place fields.
+/// Parse a `--shift-record` value: a record type's name and the columns (or fields) to shift it by, written `TYPE=N`.
+fn parse_record_shift(text: &str) -> Result<(String, isize), String> {
+    let (name, shift) = text
+        .rsplit_once('=')
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| {
+            format!(
+                "`{}` should be a record type and a shift, like `DETAIL=+2`",
+                text
+            )
+        })?;
+    let shift = shift
+        .parse()
+        .map_err(|_| format!("`{}` isn't a number of columns to shift by", shift))?;
+    Ok((name.to_owned(), shift))
+}
+
+/// The notes saying how the fields of the report have been shifted from where the syntax file puts them, by `--shift`
+/// and `--shift-record`.
+fn shift_notes(args: &Args, records: &RecordList) -> Vec<String> {
+    let units = args.locale.text(unit_message(records));
+    let mut notes = Vec::new();
+    if args.shift != 0 {
+        notes.push(
+            args.locale
+                .format(Message::ShiftNote, &[&format!("{:+}", args.shift), &units]),
+        );
+    }
+    for (name, shift) in args.shift_record.iter().filter(|(_, shift)| *shift != 0) {
+        notes.push(args.locale.format(
+            Message::RecordShiftNote,
+            &[name, &format!("{:+}", shift), &units],
+        ));
+    }
+    notes
+}
+
+fn position_options(args: &Args) -> PositionOptions {
+    PositionOptions {
+        shift: args.shift,
+        index_base: args.index_base as usize,
+        column_mode: args.column_mode,
+        quote: args.quote,
+    }
+}
+
+/// The screen columns each column of the input's lines is given by `--display-width`, found by reading it all.
+fn display_widths(
+    args: &Args,
+    input_file: &str,
+    assembly: &Assembly,
+) -> anyhow::Result<DisplayWidths> {
+    let mut widths = DisplayWidths::default();
+    for line in read_selected(args, input_file, assembly)? {
+        let (_, line) = line.context("Failed to read line from input file.")?;
+        widths.add_line(&record_text(&line));
+    }
+    Ok(widths)
+}
+
+/// Run the `export-ruleset` subcommand, writing the effective rules as JSON.
+///
+/// Positions are resolved as the renderer applies them: `--index-base` and `--shift` are taken into account, and
+/// starts (or fields) are 0-based. The start of a field placed `from_end` counts back from the last column, and it has
+/// no `end`. Rules the renderer would skip are left out, as are rules placed by a `${name}` placeholder, which have no
+/// position until they're applied to a line.
+fn run_export_ruleset(args: &Args, export: &ExportRulesetArgs) -> anyhow::Result<()> {
+    let (_, records, positions, _) = load_syntax(args, &export.syntax_file, None)?;
+
+    let mut rules = Vec::new();
+    match &records {
+        RecordList::FixedWidth(fw_records) => {
+            for record in fw_records {
+                if !record.placeholders().is_empty() {
+                    error!(
+                        "Syntax record '{}' left out as where it is depends on what its line captures.",
+                        record.name
+                    );
+                    continue;
+                }
+                let (Some(start), Some(length)) = (record.start, record.length) else {
+                    error!(
+                        "Syntax record '{}' left out as it needs 'start' and 'length'.",
+                        record.name
+                    );
+                    continue;
+                };
+                let start = start as isize - positions.index_base as isize;
+                // a field placed from the end starts that many columns before the last, shifted the other way
+                let (start, end) = if record.from_end {
+                    (start - record.shift(positions), "null".to_owned())
+                } else {
+                    let start = start + record.shift(positions);
+                    (start, (start + length as isize).to_string())
+                };
+                if start < 0 {
+                    error!(
+                        "Syntax record '{}' left out as it would start before the first column.",
+                        record.name
+                    );
+                    continue;
+                }
+                rules.push(format!(
+                    r#"{{"name":{},"start":{},"length":{},"end":{},"from_end":{},"record":{},"condition":{},"charset":{},"case":{},"pattern":{},"justify":{},"pad":{},"encoding":{},"type":{},"color":{},"value_pattern":{},"value_style":{},"sensitive":{},"check":{},"syntax_line":{}}}"#,
+                    json_string(&record.name),
+                    start,
+                    length,
+                    end,
+                    record.from_end,
+                    record.record.as_ref().map_or("null".to_owned(), |t| json_string(&t.name)),
+                    record.condition.as_ref().map(Condition::as_str).map_or("null".to_owned(), json_string),
+                    record.charset.as_ref().map_or("null".to_owned(), |c| json_string(c.spec())),
+                    record.case.map_or("null".to_owned(), |c| json_string(c.name())),
+                    record.pattern.as_ref().map_or("null".to_owned(), |p| json_string(p.spec())),
+                    record.justify.map_or("null".to_owned(), |j| json_string(j.name())),
+                    record.pad.map_or("null".to_owned(), |p| json_string(&p.to_string())),
+                    record.encoding.map_or("null".to_owned(), |e| json_string(e.name())),
+                    record.field_type.as_ref().map_or("null".to_owned(), |t| json_string(&t.spec())),
+                    record.color.as_deref().map_or("null".to_owned(), json_string),
+                    record.value_pattern.as_ref().map_or("null".to_owned(), |p| json_string(p.spec())),
+                    record.value_style.as_ref().map_or("null".to_owned(), |s| json_string(&s.spec())),
+                    record.sensitive,
+                    record.check.as_ref().map_or("null".to_owned(), |c| json_string(&c.spec())),
+                    record.line
+                ));
+            }
+        }
+        RecordList::Delimiter(_, d_records) => {
+            for record in d_records {
+                if record.field_capture.is_some() {
+                    error!(
+                        "Syntax record '{}' left out as where it is depends on what its line captures.",
+                        record.name
+                    );
+                    continue;
+                }
+                let Some(field) = record.field else {
+                    error!(
+                        "Syntax record '{}' left out as it needs 'field'.",
+                        record.name
+                    );
+                    continue;
+                };
+                let field =
+                    field as isize - positions.index_base as isize + record.shift(positions);
+                if field < 0 {
+                    error!(
+                        "Syntax record '{}' left out as it would refer to a field before the first.",
+                        record.name
+                    );
+                    continue;
+                }
+                rules.push(format!(
+                    r#"{{"name":{},"field":{},"record":{},"condition":{},"charset":{},"case":{},"pattern":{},"justify":{},"pad":{},"encoding":{},"type":{},"color":{},"value_pattern":{},"value_style":{},"sensitive":{},"check":{},"syntax_line":{}}}"#,
+                    json_string(&record.name),
+                    field,
+                    record.record.as_ref().map_or("null".to_owned(), |t| json_string(&t.name)),
+                    record
+                        .condition
+                        .as_ref()
+                        .map(Condition::as_str)
+                        .map_or("null".to_owned(), json_string),
+                    record
+                        .charset
+                        .as_ref()
+                        .map_or("null".to_owned(), |c| json_string(c.spec())),
+                    record.case.map_or("null".to_owned(), |c| json_string(c.name())),
+                    record.pattern.as_ref().map_or("null".to_owned(), |p| json_string(p.spec())),
+                    record.justify.map_or("null".to_owned(), |j| json_string(j.name())),
+                    record.pad.map_or("null".to_owned(), |p| json_string(&p.to_string())),
+                    record.encoding.map_or("null".to_owned(), |e| json_string(e.name())),
+                    record.field_type.as_ref().map_or("null".to_owned(), |t| json_string(&t.spec())),
+                    record.color.as_deref().map_or("null".to_owned(), json_string),
+                    record.value_pattern.as_ref().map_or("null".to_owned(), |p| json_string(p.spec())),
+                    record.value_style.as_ref().map_or("null".to_owned(), |s| json_string(&s.spec())),
+                    record.sensitive,
+                    record.check.as_ref().map_or("null".to_owned(), |c| json_string(&c.spec())),
+                    record.line
+                ));
+            }
+        }
+    }
+
+    let record_types: Vec<String> = records
+        .record_types()
+        .iter()
+        .map(|t| {
+            format!(
+                r#"{{"name":{},"condition":{},"syntax_line":{}}}"#,
+                json_string(&t.name),
+                t.condition
+                    .as_ref()
+                    .map(Condition::as_str)
+                    .map_or("null".to_owned(), json_string),
+                t.line
+            )
+        })
+        .collect();
+
+    let json = format!(
+        "{{\n  \"version\": 1,\n  \"mode\": \"{}\",\n  \"delimiter\": {},\n  \"delimiter_regex\": {},\n  \"record_types\": [{}\n  ],\n  \"rules\": [{}\n  ]\n}}\n",
+        match records {
+            RecordList::FixedWidth(_) => "fixed_width",
+            RecordList::Delimiter(..) => "delimiter",
+        },
+        records
+            .delimiter()
+            .map_or("null".to_owned(), |d| json_string(&d.to_string())),
+        records.delimiter().is_some_and(Delimiter::is_regex),
+        record_types
+            .iter()
+            .map(|t| format!("\n    {}", t))
+            .collect::<Vec<_>>()
+            .join(","),
+        rules
+            .iter()
+            .map(|r| format!("\n    {}", r))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    match &export.output {
+        Some(path) => fs::write(path, json).context("Failed to write ruleset.")?,
+        None => print!("{}", json),
+    }
+    Ok(())
+}
+
+/// Rows per record batch when extracting to Arrow.
+const ARROW_BATCH_ROWS: usize = 65536;
+
+/// Run the `extract` subcommand, writing every line's field values as a table.
+fn run_extract(args: &Args, extract: &ExtractArgs) -> anyhow::Result<()> {
+    let (_, records, positions, assembly) =
+        load_syntax(args, &extract.syntax_file, Some(&extract.input_file))?;
+    let columns = records.field_names();
+
+    let out: Box<dyn Write> = match &extract.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            File::create(path).context("Failed to create output file.")?,
+        )),
+        None => {
+            if std::io::stdout().is_terminal() {
+                bail!("Refusing to write binary {:?} data to a terminal. Redirect the output or use --output.", extract.format);
+            }
+            Box::new(std::io::BufWriter::new(std::io::stdout().lock()))
+        }
+    };
+
+    let file = open_buffered(args, &extract.input_file)?;
+    let lines = read_records(args, file, &assembly);
+
+    match extract.format {
+        ExtractFormat::Arrow => {
+            let mut writer = arrow::ArrowFileWriter::new(out, columns.clone())
+                .context("Failed to write Arrow output.")?;
+            let mut line_numbers = Vec::with_capacity(ARROW_BATCH_ROWS);
+            let mut values = Vec::with_capacity(ARROW_BATCH_ROWS);
+            for line in lines {
+                let (idx, line) = line.context("Failed to read line from input file.")?;
+                if is_skipped(args, &line) {
+                    continue;
+                }
+                let prepared =
+                    prepare_line(&records, line, idx, positions, args.short_lines, false)?;
+                let length = prepared.text.chars().count();
+                let mut row = vec![None; columns.len()];
+                for region in &prepared.regions {
+                    let column = columns
+                        .iter()
+                        .position(|c| *c == *region.name)
+                        .expect("every rule has a column");
+                    if row[column].is_none() && region.start < length {
+                        row[column] = Some(mask_value(
+                            region,
+                            region_value(&prepared.text, region, None),
+                        ));
+                    }
+                }
+                line_numbers.push(idx as i64 + 1);
+                values.push(row);
+
+                if line_numbers.len() == ARROW_BATCH_ROWS {
+                    writer
+                        .write_batch(&line_numbers, &values)
+                        .context("Failed to write Arrow output.")?;
+                    line_numbers.clear();
+                    values.clear();
+                }
+            }
+            if !line_numbers.is_empty() {
+                writer
+                    .write_batch(&line_numbers, &values)
+                    .context("Failed to write Arrow output.")?;
+            }
+            writer.finish().context("Failed to write Arrow output.")?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `line` is left out of highlighting by `--skip-lines-matching`.
+fn is_skipped(args: &Args, line: &str) -> bool {
+    if args.skip_lines_matching.is_empty() {
+        return false;
+    }
+    let text = record_text(line);
+    args.skip_lines_matching
+        .iter()
+        .any(|skip| skip.is_match(&text))
+}
+
+/// Prepare `line` for a report, the `line_index`th (from 0) of the input, or if `--skip-lines-matching` skips it, leave
+/// it as it is.
+fn prepare_report_line(
+    args: &Args,
+    records: &RecordList,
+    line: String,
+    line_index: usize,
+    positions: PositionOptions,
+    ghost_cells: bool,
+) -> anyhow::Result<PreparedLine> {
+    if is_skipped(args, &line) {
+        return Ok(skip_line(line));
+    }
+    prepare_line(
+        records,
+        line,
+        line_index,
+        positions,
+        args.short_lines,
+        ghost_cells,
+    )
+}
+
+/// What is counted over a run, for the report of unused rules, `--metrics`, `--stats`, `--error-report` and the exit
+/// status.
+struct RunCounts {
+    /// The lines output, including those skipped.
+    lines: usize,
+    usage: RuleUsage,
+    totals: ControlTotals,
+    metrics: Option<Metrics>,
+    stats: Option<FieldStats>,
+    problems: Problems,
+    progress: Progress,
+    /// The lines found not to end as expected, with `--strict-line-endings`, which are counted as the lines are.
+    unexpected_endings: Option<UnexpectedEndings>,
+}
+
+impl RunCounts {
+    /// Count a line, giving the control totals it fails, if it's a trailer, and the checks of its keys and sorted fields
+    /// it fails.
+    fn count_line(
+        &mut self,
+        records: &RecordList,
+        line_index: usize,
+        prepared: &PreparedLine,
+    ) -> anyhow::Result<Vec<CheckResult>> {
+        self.lines += 1;
+        self.progress.tick(self.lines);
+        self.count_line_endings(Some(line_index))?;
+        if prepared.skipped {
+            return Ok(Vec::new());
+        }
+        self.usage.count_line(records, &prepared.text);
+        if let Some(metrics) = &mut self.metrics {
+            metrics.count_line(&prepared.text);
+        }
+        if let Some(stats) = &mut self.stats {
+            // the values summarised are shown in the report
+            stats.count_line(&redacted(prepared));
+        }
+        let checks = self.totals.add_line(line_index, prepared);
+        self.problems.count_checks(line_index, &checks)?;
+        self.problems.count_line(line_index, prepared)?;
+        Ok(checks.into_iter().filter(|c| !c.passed).collect())
+    }
+
+    /// Count the lines up to the `line_index`th (from 0), or all of them, found not to end as expected. A record
+    /// joined from several lines has them counted with it.
+    fn count_line_endings(&mut self, line_index: Option<usize>) -> anyhow::Result<()> {
+        let Some(unexpected) = &self.unexpected_endings else {
+            return Ok(());
+        };
+        let mut unexpected = unexpected.borrow_mut();
+        while let Some((index, message)) = unexpected
+            .pop_front_if(|(index, _)| line_index.is_none_or(|line_index| *index <= line_index))
+        {
+            self.problems.count_line_ending(index, &message)?;
+        }
+        Ok(())
+    }
+
+    /// Carry on counting from where `checkpoint` was taken, so what's found covers the lines before it too.
+    fn restore(&mut self, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+        self.lines = checkpoint.lines;
+        self.problems.restore(&checkpoint.problems);
+        self.usage
+            .restore(&checkpoint.rule_usage)
+            .context("Failed to resume from checkpoint.")?;
+        for row in &checkpoint.totals {
+            self.totals.restore(row)?;
+        }
+        Ok(())
+    }
+
+    /// Count the broken constraints and suspicious characters logged for a line.
+    fn count_findings(&mut self, constraint_violations: usize, suspicious_characters: usize) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.count_findings(constraint_violations, suspicious_characters);
+        }
+        self.problems
+            .count_findings(constraint_violations, suspicious_characters);
+    }
+
+    /// Log the rules that never applied, and write the metrics file if one was asked for, and the field statistics if
+    /// the report has no section for them. Then write the error report if one was asked for, and the summary of the run,
+    /// giving what was found.
+    fn finish(mut self, args: &Args) -> anyhow::Result<RunSummary> {
+        self.count_line_endings(None)?;
+        drop(self.progress);
+        self.usage.log();
+        if let Some(stats) = self.stats.as_ref() {
+            if args.output_format != OutputFormat::Html {
+                stats.log();
+            }
+        }
+        if let (Some(path), Some(metrics)) = (&args.metrics, self.metrics) {
+            metrics.write(
+                path,
+                args.input_file.as_deref().unwrap_or_default(),
+                args.syntax_file.as_deref().unwrap_or_default(),
+                &self.usage,
+            )?;
+        }
+        let summary = RunSummary {
+            lines: self.lines,
+            problems: self.problems.finish(&self.usage)?,
+        };
+        diagnostics::summary(
+            match args.input_file.as_deref().unwrap_or_default() {
+                STDIN => "standard input",
+                input_file => input_file,
+            },
+            summary.lines,
+            &summary.problems,
+        );
+        Ok(summary)
+    }
+}
+
+/// What a report found in its input, once it's written.
+#[derive(Debug, Default, Clone)]
+struct RunSummary {
+    lines: usize,
+    problems: ProblemCounts,
+}
+
+/// Processing statistics reported by `--timing`.
+#[derive(Default)]
+struct Timings {
+    lines: usize,
+    /// Bytes read from the input file, shared with the [`CountingReader`] reading it.
+    bytes: Rc<Cell<u64>>,
+    rules_evaluated: u64,
+    parse: Duration,
+    /// Time spent in `--preflight` and other scans of the input, if any ran.
+    scan: Option<Duration>,
+    regions: Duration,
+    render: Duration,
+}
+
+impl Timings {
+    fn log(&self) {
+        eprintln!("{}", self);
+    }
+}
+
+impl std::fmt::Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Processed {} line(s) ({} bytes), evaluating {} rule(s). Time spent: parsing syntax {:.3?}",
+            self.lines,
+            self.bytes.get(),
+            self.rules_evaluated,
+            self.parse
+        )?;
+        if let Some(scan) = self.scan {
+            write!(f, ", scanning input {:.3?}", scan)?;
+        }
+        write!(
+            f,
+            ", generating regions {:.3?}, rendering {:.3?}.",
+            self.regions, self.render
+        )
+    }
+}
+
+/// A reader which counts the bytes read through it.
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R, count: Rc<Cell<u64>>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+/// How often to check a followed file for new data.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Makes an HTML report of a followed file reload itself every few seconds to show the lines written since, keeping
+/// the reader's place, or the end of the report in view if that's where they were.
+const FOLLOW_REFRESH_HTML: &str = r#"<meta http-equiv="refresh" content="3">
+<script>
+(function () {
+  var key = "ffh-follow:" + location.pathname;
+  addEventListener("beforeunload", function () {
+    var atEnd = innerHeight + scrollY >= document.documentElement.scrollHeight - 8;
+    sessionStorage.setItem(key, atEnd ? "end" : String(scrollY));
+  });
+  addEventListener("DOMContentLoaded", function () {
+    var place = sessionStorage.getItem(key);
+    scrollTo(0, place === null || place === "end" ? document.documentElement.scrollHeight : Number(place));
+  });
+})();
+</script>"#;
+
+/// Reads a file that is still being written to, like `tail -f`: at its end, waits for more data to be appended rather than finishing.
+struct FollowReader {
+    file: File,
+    position: u64,
+}
+
+impl FollowReader {
+    fn new(file: File) -> Self {
+        Self { file, position: 0 }
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.file.read(buf)?;
+            if read > 0 {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            if self.file.metadata()?.len() < self.position {
+                error!("Input file was truncated, following it from the start again.");
+                self.file.seek(std::io::SeekFrom::Start(0))?;
+                self.position = 0;
+                continue;
+            }
+            std::thread::sleep(FOLLOW_INTERVAL);
+        }
+    }
+}
+
+/// Reads lines like [`BufRead::lines`], but fails on any line longer than a limit rather than reading it all into memory.
+/// Lines continued by the next are joined into one record, with a [`LINE_BREAK`] between each. A UTF-8 byte order mark
+/// at the start of the input, and the carriage return of a line ending in CRLF, aren't part of any line.
+struct BoundedLines<B> {
+    reader: B,
+    /// The longest line to accept in bytes, or 0 for no limit.
+    max_length: usize,
+    /// The length of every record in bytes, if the input is records of a fixed length rather than lines.
+    record_length: Option<usize>,
+    /// The encoding lines are decoded from.
+    encoding: Encoding,
+    /// How lines are joined into records.
+    assembly: Assembly,
+    /// How lines end, which is `Auto` until the first line ending is found.
+    line_ending: LineEnding,
+    /// Whether a UTF-8 byte order mark at the start of the input is kept as part of the first line.
+    keep_bom: bool,
+    /// The lines that didn't end as expected, if they're being looked for, shared with what counts them as problems.
+    unexpected_endings: Option<UnexpectedEndings>,
+    line_number: usize,
+    /// The byte offset of the next line.
+    offset: u64,
+}
+
+impl<B: BufRead> BoundedLines<B> {
+    fn new(reader: B, max_length: usize, encoding: Encoding) -> Self {
+        Self {
+            reader,
+            max_length,
+            record_length: None,
+            encoding,
+            assembly: Assembly::default(),
+            line_ending: LineEnding::Auto,
+            keep_bom: false,
+            unexpected_endings: None,
+            line_number: 0,
+            offset: 0,
+        }
+    }
+
+    /// End lines, keep a byte order mark, and look for lines that don't end as expected, as `--line-ending`,
+    /// `--keep-bom` and `--strict-line-endings` say.
+    fn line_endings(mut self, args: &Args) -> Self {
+        self.line_ending = args.line_ending;
+        self.keep_bom = args.keep_bom;
+        self.unexpected_endings = args.strict_line_endings.then(Rc::default);
+        self
+    }
+
+    /// The lines found so far that don't end as expected, with `--strict-line-endings`, each taken from the front as
+    /// it's counted.
+    fn unexpected_endings(&self) -> Option<UnexpectedEndings> {
+        self.unexpected_endings.clone()
+    }
+
+    /// Read records of `record_length` bytes rather than lines, if it's given.
+    fn fixed_length(mut self, record_length: Option<usize>) -> Self {
+        self.record_length = record_length;
+        self
+    }
+
+    /// Join lines into records as `assembly` says.
+    fn assembled(mut self, assembly: &Assembly) -> Self {
+        self.assembly = assembly.clone();
+        self
+    }
+
+    /// Continue counting from part way through the input, which `reader` must already be positioned at.
+    fn starting_at(mut self, offset: u64, line_number: usize) -> Self {
+        self.offset = offset;
+        self.line_number = line_number;
+        self
+    }
+
+    /// The byte offset of the next line in the input.
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<B: BufRead> Iterator for BoundedLines<B> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = match self.read_line()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut line_start = 0;
+        while self.assembly.continues(&record[line_start..]) {
+            match self.read_line() {
+                Some(Ok(line)) => {
+                    record.push(LINE_BREAK);
+                    line_start = record.len();
+                    record.push_str(&line);
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    error!(
+                        "Line {} is continued by the next line, but is the last in the input.",
+                        self.line_number - 1
+                    );
+                    break;
+                }
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+impl<B: BufRead> BoundedLines<B> {
+    /// Read the next physical line.
+    fn read_line(&mut self) -> Option<std::io::Result<String>> {
+        if self.offset == 0 && !self.keep_bom {
+            if let Err(e) = self.skip_bom() {
+                return Some(Err(e));
+            }
+        }
+        self.line_number += 1;
+        let line = match self.record_length {
+            Some(length) => self.read_fixed(length)?,
+            None => self.read_delimited()?,
+        };
+        Some(line.and_then(|line| {
+            let line = self.encoding.decode(line).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Line {} isn't valid UTF-8 ({}). If the file is in another encoding, give it with --encoding.",
+                        self.line_number,
+                        e.utf8_error()
+                    ),
+                )
+            })?;
+            if line.contains(LINE_BREAK) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Line {} has the noncharacter U+FFFF, which can't be read as it's what joins the lines of a record.",
+                        self.line_number
+                    ),
+                ));
+            }
+            Ok(line)
+        }))
+    }
+
+    /// Whether the input's lines look to end in a carriage return on its own, as the first line ending in what's been
+    /// read ahead is one. Other input is read up to each line feed.
+    fn ends_in_cr(&mut self) -> bool {
+        let Ok(available) = self.reader.fill_buf() else {
+            return false;
+        };
+        let encoding = self.encoding;
+        match available
+            .iter()
+            .position(|b| *b == b'\r' || encoding.is_line_end(*b))
+        {
+            Some(at) => {
+                available[at] == b'\r'
+                    && available
+                        .get(at + 1)
+                        .is_some_and(|b| !encoding.is_line_end(*b))
+            }
+            None => false,
+        }
+    }
+
+    /// Check that the line just read ends in `ending`, as the lines before it do, noting it if it doesn't and
+    /// `--strict-line-endings` was given. The first line ending found is what the others must be, with `auto`.
+    fn check_ending(&mut self, ending: LineEnding) {
+        if self.line_ending == LineEnding::Auto {
+            self.line_ending = ending;
+            return;
+        }
+        let Some(unexpected) = &self.unexpected_endings else {
+            return;
+        };
+        if ending != self.line_ending {
+            let message = format!(
+                "The line ends in {} rather than {}.",
+                ending.name(),
+                self.line_ending.name()
+            );
+            error!("Line {}: {}", self.line_number, message);
+            unexpected
+                .borrow_mut()
+                .push_back((self.line_number - 1, message));
+        }
+    }
+
+    /// Skip a UTF-8 byte order mark at the start of the input, which Windows programs write, as it isn't part of the
+    /// first line and would push its columns one along. Input in an EBCDIC code page can't start with one.
+    fn skip_bom(&mut self) -> std::io::Result<()> {
+        if matches!(self.encoding, Encoding::Utf8 | Encoding::Latin1)
+            && self.reader.fill_buf()?.starts_with(UTF8_BOM)
+        {
+            info!("Skipping the byte order mark at the start of the input");
+            self.reader.consume(UTF8_BOM.len());
+            self.offset += UTF8_BOM.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Read the next record of `length` bytes. The last is shorter if the input ends part way through it.
+    fn read_fixed(&mut self, length: usize) -> Option<std::io::Result<Vec<u8>>> {
+        let mut record = Vec::with_capacity(length);
+        while record.len() < length {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if available.is_empty() {
+                break;
+            }
+            let taken = available.len().min(length - record.len());
+            record.extend_from_slice(&available[..taken]);
+            self.reader.consume(taken);
+            self.offset += taken as u64;
+        }
+        if record.is_empty() {
+            return None;
+        }
+        if record.len() < length {
+            error!(
+                "Line {} is only {} of the {} bytes of --record-length, as the input ends part way through it.",
+                self.line_number,
+                record.len(),
+                length
+            );
+        }
+        Some(Ok(record))
+    }
+
+    /// Read the bytes of the next line, up to its line break.
+    fn read_delimited(&mut self) -> Option<std::io::Result<Vec<u8>>> {
+        let mut line = Vec::new();
+        // a line ends at its line feed, and a carriage return before it is taken off after, unless lines end in CR
+        let cr = self.line_ending == LineEnding::Cr
+            || self.line_ending == LineEnding::Auto && self.ends_in_cr();
+        let is_end = |encoding: Encoding, byte: u8| {
+            if cr {
+                byte == b'\r'
+            } else {
+                encoding.is_line_end(byte)
+            }
+        };
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            if available.is_empty() {
+                if line.is_empty() {
+                    return None;
+                }
+                break;
+            }
+
+            let (chunk, found_newline) =
+                match available.iter().position(|b| is_end(self.encoding, *b)) {
+                    Some(idx) => (&available[..=idx], true),
+                    None => (available, false),
+                };
+            line.extend_from_slice(chunk);
+            let consumed = chunk.len();
+            self.reader.consume(consumed);
+            self.offset += consumed as u64;
+
+            if self.max_length > 0 && line.len() > self.max_length + 2 {
+                return Some(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "Line {} is longer than the maximum of {} bytes. If this file really has lines this long, raise --max-line-length; otherwise check it is a line-delimited file, or read it in records with --record-length.",
+                        self.line_number, self.max_length
+                    ),
+                )));
+            }
+            if found_newline {
+                break;
+            }
+        }
+
+        let ending = if line.last().is_some_and(|b| is_end(self.encoding, *b)) {
+            line.pop();
+            if cr {
+                // a line feed straight after the carriage return ends the line with it
+                match self.reader.fill_buf() {
+                    Ok(next) if next.first().is_some_and(|b| self.encoding.is_line_end(*b)) => {
+                        self.reader.consume(1);
+                        self.offset += 1;
+                        Some(LineEnding::Crlf)
+                    }
+                    _ => Some(LineEnding::Cr),
+                }
+            } else if line.ends_with(b"\r") {
+                line.pop();
+                Some(LineEnding::Crlf)
+            } else {
+                Some(LineEnding::Lf)
+            }
+        } else {
+            None
+        };
+        if let Some(ending) = ending {
+            self.check_ending(ending);
+        }
+        if self.max_length > 0 && line.len() > self.max_length {
+            return Some(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Line {} is longer than the maximum of {} bytes. If this file really has lines this long, raise --max-line-length; otherwise check it is a line-delimited file, or read it in records with --record-length.",
+                    self.line_number, self.max_length
+                ),
+            )));
+        }
+        Some(Ok(line))
+    }
+}
+
+/// Text accumulated while rendering for output later, which spills to a temporary file once it grows large so memory use stays bounded.
+struct Spool {
+    buffer: Vec<u8>,
+    /// The file spilled to, written through a buffer as fragments are pushed a line's worth at a time.
+    file: Option<(std::path::PathBuf, BufWriter<File>)>,
+}
+
+impl Spool {
+    /// How much is held in memory before spilling to disk.
+    const MEMORY_LIMIT: usize = 8 * 1024 * 1024;
+
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            file: None,
+        }
+    }
+
+    fn push_str(&mut self, text: &str) -> anyhow::Result<()> {
+        if let Some((_, file)) = &mut self.file {
+            file.write_all(text.as_bytes())
+                .context("Failed to write to spool file.")?;
+            return Ok(());
+        }
+
+        self.buffer.extend_from_slice(text.as_bytes());
+        if self.buffer.len() > Self::MEMORY_LIMIT {
+            static SPOOLS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "fixedfile-highlighter-{}-{}.spool",
+                std::process::id(),
+                SPOOLS.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            ));
+            info!("Spilling output to {}", path.display());
+            let mut file = BufWriter::new(
+                File::options()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .context("Failed to create spool file.")?,
+            );
+            file.write_all(&self.buffer)
+                .context("Failed to write to spool file.")?;
+            self.buffer = Vec::new();
+            self.file = Some((path, file));
+        }
+        Ok(())
+    }
+
+    /// Copy everything accumulated to `out`.
+    fn write_to(mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        out.write_all(&self.buffer)?;
+        if let Some((_, file)) = &mut self.file {
+            file.flush().context("Failed to write to spool file.")?;
+            let file = file.get_mut();
+            file.seek(std::io::SeekFrom::Start(0))
+                .context("Failed to read spool file.")?;
+            std::io::copy(file, out).context("Failed to read spool file.")?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        if let Some((path, _)) = self.file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// How many lines are rendered between `--resume` checkpoints.
+const RESUME_INTERVAL: usize = 10_000;
+
+/// How many lines are read at a time to be prepared and rendered across threads. This divides [`RESUME_INTERVAL`].
+const BATCH_LINES: usize = 1_000;
+
+/// Progress through a report, saved by `--resume` so an interrupted run can carry on where it stopped.
+#[derive(Debug)]
+struct Checkpoint {
+    /// The input file, and a hash of the syntax file, the report was started with.
+    input_file: String,
+    syntax: String,
+    /// The byte offset of the next line to render.
+    offset: u64,
+    /// The number of lines rendered.
+    lines: usize,
+    /// The length of the report written so far.
+    output: u64,
+    rules_evaluated: u64,
+    stripes: RecordTypeStripes,
+    /// The number of problems of each kind found in the lines rendered.
+    problems: Vec<(String, u64)>,
+    /// How often each rule applied to the lines rendered, as [`RuleUsage::counts`] gives it.
+    rule_usage: Vec<[u64; 3]>,
+    /// The control totals and the keys and values checked across the lines rendered, as [`ControlTotals::save`]
+    /// writes them.
+    totals: Vec<csv::StringRecord>,
+}
+
+impl Checkpoint {
+    /// Read the checkpoint at `path`, if there is one.
+    fn load(path: &str) -> anyhow::Result<Option<Self>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+
+        let mut checkpoint = Checkpoint {
+            input_file: String::new(),
+            syntax: String::new(),
+            offset: 0,
+            lines: 0,
+            output: 0,
+            rules_evaluated: 0,
+            stripes: RecordTypeStripes::default(),
+            problems: Vec::new(),
+            rule_usage: Vec::new(),
+            totals: Vec::new(),
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .context("Failed to read checkpoint file.")?;
+        for row in reader.records() {
+            let row = row.context("Failed to read checkpoint file.")?;
+            let value = row.get(1).unwrap_or_default();
+            let number = || -> anyhow::Result<u64> {
+                value
+                    .parse()
+                    .with_context(|| format!("Checkpoint file has an invalid {}.", &row[0]))
+            };
+            match &row[0] {
+                "input" => checkpoint.input_file = value.to_owned(),
+                "syntax" => checkpoint.syntax = value.to_owned(),
+                "offset" => checkpoint.offset = number()?,
+                "lines" => checkpoint.lines = number()? as usize,
+                "output" => checkpoint.output = number()?,
+                "rules_evaluated" => checkpoint.rules_evaluated = number()?,
+                "stripe" => {
+                    checkpoint.stripes.sections.push(number()? as usize);
+                    checkpoint.stripes.types.push(row.get(2).map(str::to_owned));
+                }
+                "stripe_previous" => checkpoint.stripes.previous = Some(number()? as usize),
+                "problem" => checkpoint
+                    .problems
+                    .push((row.get(2).unwrap_or_default().to_owned(), number()?)),
+                "rule" => {
+                    let mut counts = [0; 3];
+                    for (i, count) in counts.iter_mut().enumerate() {
+                        *count = row
+                            .get(i + 1)
+                            .and_then(|count| count.parse().ok())
+                            .context("Checkpoint file has an invalid rule.")?;
+                    }
+                    checkpoint.rule_usage.push(counts);
+                }
+                key if key.starts_with("total_") => checkpoint.totals.push(row),
+                key => bail!("Checkpoint file has an unknown entry '{}'.", key),
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+}
+
+/// Writes `--resume` checkpoints, alongside a report being written to a file.
+struct Resume {
+    path: String,
+    /// The file the report is written to, so how much has been written can be found (and trimmed back to).
+    output: File,
+    input_file: String,
+    syntax: String,
+}
+
+impl Resume {
+    fn new(path: &str, input_file: &str, syntax_file: &str, output: File) -> anyhow::Result<Self> {
+        if !output.metadata().is_ok_and(|m| m.is_file()) {
+            bail!("--resume needs the report to be written to a file, with --output or by redirecting standard output.");
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(syntax_file.as_bytes());
+        Ok(Self {
+            path: path.to_owned(),
+            output,
+            input_file: input_file.to_owned(),
+            syntax: hasher.finish(),
+        })
+    }
+
+    /// Check `checkpoint` belongs to this run, and trim the report back to where it was taken.
+    fn restore(&mut self, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+        if checkpoint.input_file != self.input_file {
+            bail!(
+                "Checkpoint {} is for input file '{}', not '{}'.",
+                self.path,
+                checkpoint.input_file,
+                self.input_file
+            );
+        }
+        if checkpoint.syntax != self.syntax {
+            bail!(
+                "Checkpoint {} was taken with a different syntax file.",
+                self.path
+            );
+        }
+        let written = self.output.metadata()?.len();
+        if written < checkpoint.output {
+            bail!("The output file is shorter than when checkpoint {} was taken. When resuming, append to the report (`>>`) rather than overwriting it.", self.path);
+        }
+        // discard anything written after the checkpoint
+        self.output
+            .set_len(checkpoint.output)
+            .context("Failed to resume from checkpoint.")?;
+        self.output
+            .seek(std::io::SeekFrom::Start(checkpoint.output))
+            .context("Failed to resume from checkpoint.")?;
+        Ok(())
+    }
+
+    /// Save a checkpoint after `lines` lines have been rendered to `out`, with the next to read by `reader`, and what
+    /// `counts` found in them.
+    fn save<B: BufRead>(
+        &mut self,
+        out: &mut dyn Write,
+        reader: &BoundedLines<B>,
+        lines: usize,
+        rules_evaluated: u64,
+        stripes: &RecordTypeStripes,
+        counts: &RunCounts,
+    ) -> anyhow::Result<()> {
+        out.flush()?;
+        let output = self.output.stream_position()?;
+
+        // write a new checkpoint then move it into place, so one is always complete
+        let partial = format!("{}.partial", self.path);
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_path(&partial)
+            .context("Failed to write checkpoint file.")?;
+        writer.write_record(["input", &self.input_file])?;
+        writer.write_record(["syntax", &self.syntax])?;
+        writer.write_record(["offset", &reader.offset().to_string()])?;
+        writer.write_record(["lines", &lines.to_string()])?;
+        writer.write_record(["output", &output.to_string()])?;
+        writer.write_record(["rules_evaluated", &rules_evaluated.to_string()])?;
+        for (record_type, sections) in stripes.types.iter().zip(&stripes.sections) {
+            let sections = sections.to_string();
+            match record_type {
+                Some(record_type) => writer.write_record(["stripe", &sections, record_type])?,
+                None => writer.write_record(["stripe", &sections])?,
+            }
+        }
+        if let Some(previous) = stripes.previous {
+            writer.write_record(["stripe_previous", &previous.to_string()])?;
+        }
+        for (kind, count) in counts.problems.kinds() {
+            writer.write_record(["problem", &count.to_string(), kind])?;
+        }
+        for [of_record_type, matched, applied] in counts.usage.counts() {
+            writer.write_record([
+                "rule",
+                &of_record_type.to_string(),
+                &matched.to_string(),
+                &applied.to_string(),
+            ])?;
+        }
+        counts.totals.save(&mut writer)?;
+        writer.flush().context("Failed to write checkpoint file.")?;
+        drop(writer);
+        fs::rename(&partial, &self.path).context("Failed to write checkpoint file.")?;
+        Ok(())
+    }
+
+    /// The report is complete, so the checkpoint is no longer needed.
+    fn finish(self) -> anyhow::Result<()> {
+        fs::remove_file(&self.path).context("Failed to remove checkpoint file.")
+    }
+}
+
+/// Findings from scanning the whole input before rendering it.
+struct PreflightReport {
+    /// The unit line lengths and requirements are measured in.
+    unit: Message,
+    lines: usize,
+    /// The (line index, length) of the shortest line.
+    shortest: Option<(usize, usize)>,
+    /// The (line index, length) of the longest line.
+    longest: Option<(usize, usize)>,
+    rules: Vec<PreflightRule>,
+}
+
+struct PreflightRule {
+    name: String,
+    record: Option<RecordType>,
+    condition: Option<Condition>,
+    /// The length a line needs for this rule to fit, or `None` if the rule is malformed. For a rule placed by a
+    /// capture group, the most any line it applied to needed, or `None` if it never captured a position.
+    required: Option<usize>,
+    /// Whether the rule is placed by a capture group, so what a line needs depends on the line.
+    captured: bool,
+    /// The number of lines of this rule's record type (every line, if it has none).
+    record_lines: usize,
+    /// The number of lines this rule applied to.
+    matched_lines: usize,
+    /// The shortest line this rule applied to.
+    shortest_matched: Option<usize>,
+    /// The number of lines this rule applied to which were too short for it.
+    short_lines: usize,
+}
+
+impl PreflightRule {
+    fn new(
+        name: &str,
+        record: &Option<RecordType>,
+        condition: &Option<Condition>,
+        required: Option<usize>,
+        captured: bool,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            record: record.clone(),
+            condition: condition.clone(),
+            required,
+            captured,
+            record_lines: 0,
+            matched_lines: 0,
+            shortest_matched: None,
+            short_lines: 0,
+        }
+    }
+
+    fn findings(&self, unit: Message, locale: Locale) -> Vec<String> {
+        let mut findings = Vec::new();
+        // a rule placed by a capture group only has a position on the lines it applies to
+        if self.required.is_none() && (!self.captured || self.matched_lines > 0) {
+            findings.push(locale.format(Message::RuleUnpositioned, &[&self.name]));
+            return findings;
+        }
+        let required = self.required.unwrap_or(0);
+        let unit = locale.text(unit);
+        if let (Some(record), 0) = (&self.record, self.record_lines) {
+            findings
+                .push(locale.format(Message::RuleRecordNeverMatched, &[&self.name, &record.name]));
+        } else if self.matched_lines == 0 {
+            if let Some(cond) = &self.condition {
+                findings.push(locale.format(Message::RuleNeverMatched, &[&self.name, cond]));
+            }
+        } else if self.short_lines == self.matched_lines {
+            findings.push(locale.format(
+                Message::RuleNeverFits,
+                &[&self.name, &required, &unit, &self.matched_lines],
+            ));
+        } else if self.short_lines > 0 {
+            findings.push(locale.format(
+                Message::RuleSometimesFits,
+                &[
+                    &self.name,
+                    &required,
+                    &unit,
+                    &self.short_lines,
+                    &self.matched_lines,
+                    &self.shortest_matched.unwrap_or(0),
+                ],
+            ));
+        }
+        findings
+    }
+}
+
+impl PreflightReport {
+    fn findings(&self, locale: Locale) -> Vec<String> {
+        self.rules
+            .iter()
+            .flat_map(|r| r.findings(self.unit, locale))
+            .collect()
+    }
+}
+
+/// The length a line with `captures` needs for each rule to fit, or `None` for a rule that is malformed or wasn't
+/// placed by what the line captured.
+fn required_lengths(
+    records: &RecordList,
+    captures: &Captures,
+    positions: PositionOptions,
+) -> Vec<Option<usize>> {
+    match records {
+        RecordList::FixedWidth(fw_records) => fw_records
+            .iter()
+            .map(|record| {
+                match (record.start_on(captures), record.length_on(captures)) {
+                    // it ends wherever the line does, so only needs enough columns to start on
+                    (Some(start), Some(_)) if record.from_end => {
+                        let start = start as isize - positions.index_base as isize;
+                        Some((start + 1 - record.shift(positions)).max(0) as usize)
+                    }
+                    (Some(start), Some(length)) => {
+                        let start = start as isize - positions.index_base as isize
+                            + record.shift(positions);
+                        Some((start.max(0) as usize) + length)
+                    }
+                    _ => None,
+                }
+            })
+            .collect(),
+        RecordList::Delimiter(_, d_records) => d_records
+            .iter()
+            .map(|record| {
+                record.field_on(captures).map(|field| {
+                    (field as isize + 1 - positions.index_base as isize + record.shift(positions))
+                        .max(1) as usize
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Scan the input file, measuring lines and checking every rule against them, without rendering anything.
+fn run_preflight(
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+) -> anyhow::Result<PreflightReport> {
+    let rules = records
+        .rules()
+        .into_iter()
+        .zip(required_lengths(records, &Captures::default(), positions))
+        .map(|(rule, required)| {
+            PreflightRule::new(
+                rule.name(),
+                &rule.record().cloned(),
+                &rule.condition().cloned(),
+                required,
+                !rule.placeholders().is_empty(),
+            )
+        })
+        .collect();
+
+    let typer = RecordTyper::new(records);
+    let mut report = PreflightReport {
+        unit: unit_message(records),
+        lines: 0,
+        shortest: None,
+        longest: None,
+        rules,
+    };
+
+    for line in read_selected(args, input_file, assembly)? {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        let line = record_text(&line);
+        let length = line_length(records, &line, positions);
+
+        report.lines += 1;
+        if report.shortest.is_none_or(|(_, l)| length < l) {
+            report.shortest = Some((idx, length));
+        }
+        if report.longest.is_none_or(|(_, l)| length > l) {
+            report.longest = Some((idx, length));
+        }
+
+        let record_type = typer.record_type(&line);
+        let captured = if records.places_by_capture() {
+            let captures = Captures::of(records, record_type, &line);
+            required_lengths(records, &captures, positions)
+        } else {
+            Vec::new()
+        };
+        for (i, rule) in report.rules.iter_mut().enumerate() {
+            if rule.record.as_ref().is_some_and(|r| Some(r) != record_type) {
+                continue;
+            }
+            rule.record_lines += 1;
+            if condition_matches(&rule.condition, &line) {
+                rule.matched_lines += 1;
+                if rule.shortest_matched.is_none_or(|l| length < l) {
+                    rule.shortest_matched = Some(length);
+                }
+                let required = if rule.captured {
+                    let required = captured[i];
+                    if required > rule.required {
+                        rule.required = required;
+                    }
+                    required
+                } else {
+                    rule.required
+                };
+                if required.is_some_and(|r| r > length) {
+                    rule.short_lines += 1;
+                }
+            }
+        }
+    }
+
+    let findings = report.findings(Locale::En);
+    if findings.is_empty() {
+        info!("Preflight found no problems.");
+    }
+    for finding in &findings {
+        error!("Preflight: {}", finding);
+    }
+
+    Ok(report)
+}
+
+/// Check the configuration and the first `sample` lines of the input as a full run would, without writing a report, for
+/// `--dry-run`.
+fn run_dry_run(args: &Args, input_file: &str, sample: usize) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let (_, records, positions, assembly) = load_syntax(
+        args,
+        args.syntax_file.as_deref().expect("required by clap"),
+        Some(input_file),
+    )?;
+    let shift = |position: usize, rule: &dyn SyntaxRule| {
+        position as isize - positions.index_base as isize + rule.shift(positions)
+    };
+    // each rule's name, condition and shifted 0-based start (or field), if it has one
+    let rules: Vec<(&str, &Option<Condition>, Option<isize>)> = match &records {
+        RecordList::FixedWidth(fw_records) => fw_records
+            .iter()
+            .map(|r| {
+                let start = r.start.zip(r.length).map(|(start, _)| shift(start, r));
+                (&*r.name, &r.condition, start)
+            })
+            .collect(),
+        RecordList::Delimiter(_, d_records) => d_records
+            .iter()
+            .map(|r| (&*r.name, &r.condition, r.field.map(|field| shift(field, r))))
+            .collect(),
+    };
+
+    let syntax_rules = records.rules();
+    let mut problems = 0;
+    for ((name, _, start), rule) in rules.iter().zip(&syntax_rules) {
+        match start {
+            // placed by what each line captures, so checked on the lines below
+            None if !rule.placeholders().is_empty() => (),
+            None => {
+                error!("Rule '{}' has no position, so would be skipped.", name);
+                problems += 1;
+            }
+            Some(start) if *start < 0 => {
+                error!(
+                    "Rule '{}' would start before the first {}, so would be skipped.",
+                    name,
+                    unit_name(&records).trim_end_matches("(s)")
+                );
+                problems += 1;
+            }
+            Some(_) => (),
+        }
+    }
+
+    let typer = RecordTyper::new(&records);
+    let mut hits = vec![0; rules.len()];
+    let mut lines = 0;
+    let file = open_buffered(args, input_file)?;
+    for line in read_records(args, file, &assembly).take(sample) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let text = record_text(&line);
+        let record_type = typer.record_type(&text);
+        for (hit, rule) in hits.iter_mut().zip(&syntax_rules) {
+            if rule_applies(*rule, record_type, &text) {
+                *hit += 1;
+            }
+        }
+        let prepared = prepare_line(&records, line, idx, positions, args.short_lines, false)?;
+        if args.check_unicode {
+            suspicious::log_line(idx, &prepared.text, &prepared.regions);
+        }
+        problems += constraints::log_line(idx, &prepared.text, &prepared.regions, None);
+        lines += 1;
+    }
+
+    for (((name, condition, _), hits), rule) in rules.iter().zip(&hits).zip(&syntax_rules) {
+        match (hits, rule.record(), condition) {
+            (0, Some(record), _) => info!(
+                "Rule '{}' didn't apply to any line checked of its record type '{}'.",
+                name, record.name
+            ),
+            (0, None, Some(condition)) => info!(
+                "Rule '{}' didn't apply to any line checked, as its condition `{}` never matched.",
+                name, condition
+            ),
+            _ => (),
+        }
+    }
+    eprintln!(
+        "Dry run: parsed {} rule(s) and compiled their conditions, then checked {} line(s) of input, in {:.3?}.",
+        rules.len(),
+        lines,
+        started.elapsed()
+    );
+    if problems > 0 {
+        bail!(ProblemsFound(format!(
+            "Dry run found {} problem(s).",
+            problems
+        )));
+    }
+    Ok(())
+}
+
+/// Check the fields of every line against the constraints of their rules, writing each violation to the output in the
+/// format of `--validate-format`, for `--validate`.
+fn run_validation(args: &Args, input_file: &str) -> anyhow::Result<()> {
+    let (_, records, positions, assembly) = load_syntax(
+        args,
+        args.syntax_file.as_deref().expect("required by clap"),
+        Some(input_file),
+    )?;
+
+    if args.validate_format != ValidateFormat::Csv && input_file == STDIN {
+        bail!("--validate-format sarif and github annotate the input file, so can't read standard input.");
+    }
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).context("Failed to create output file.")?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = ViolationWriter::new(out, args.validate_format, input_file)?;
+    let mut violations = 0;
+    let mut lines = 0;
+    let file = open_buffered(args, input_file)?;
+    for line in read_records(args, file, &assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let prepared = prepare_line(&records, line, idx, positions, args.short_lines, true)?;
+        for violation in
+            constraints::violations(&prepared.text, &prepared.regions, prepared.ghost_from)
+        {
+            writer.write(idx, violation)?;
+            violations += 1;
+        }
+        lines += 1;
+    }
+    writer.finish()?;
+
+    if violations > 0 {
+        bail!(ProblemsFound(format!(
+            "Validation found {} violation(s) in {} line(s).",
+            violations, lines
+        )));
+    }
+    eprintln!("Validation found no violations in {} line(s).", lines);
+    Ok(())
+}
+
+fn write_preflight_html(
+    out: &mut dyn Write,
+    report: &PreflightReport,
+    locale: Locale,
+) -> io::Result<()> {
+    writeln!(out, "<h2>{}</h2>", locale.text(Message::Preflight))?;
+    write!(
+        out,
+        "<p>{}",
+        locale.format(Message::Scanned, &[&report.lines])
+    )?;
+    if let (Some((short_idx, short)), Some((long_idx, long))) = (report.shortest, report.longest) {
+        let unit = locale.text(report.unit);
+        write!(
+            out,
+            "{}",
+            locale.format(
+                Message::ShortestAndLongest,
+                &[
+                    &(short_idx + 1),
+                    &short,
+                    &unit,
+                    &(long_idx + 1),
+                    &long,
+                    &unit
+                ]
+            )
+        )?;
+    }
+    writeln!(out, ".</p>")?;
+
+    let findings = report.findings(locale);
+    if findings.is_empty() {
+        writeln!(out, "<p>{}</p>", locale.text(Message::NoProblems))?;
+    } else {
+        writeln!(out, "<ul>")?;
+        for finding in findings {
+            writeln!(out, "<li>{}</li>", escape_html(&finding))?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+    Ok(())
+}
+
+/// How often each field is blank or zero-filled, from `--profile-fields`.
+struct FieldProfile {
+    /// The percentage of lines at or above which a field is reported.
+    threshold: u8,
+    fields: Vec<FieldUsage>,
+}
+
+struct FieldUsage {
+    name: String,
+    /// The number of lines the field applied to and was present on.
+    lines: usize,
+    blank: usize,
+    zero_filled: usize,
+}
+
+impl FieldUsage {
+    /// Whether the field is blank or zero-filled often enough to be reported.
+    fn is_dead(&self, threshold: u8) -> bool {
+        self.lines > 0 && (self.blank + self.zero_filled) * 100 >= self.lines * threshold as usize
+    }
+}
+
+/// Whether `value` is all zeros, allowing for the spaces, sign and decimal point of a formatted number.
+fn is_zero_filled(value: &str) -> bool {
+    value.contains('0')
+        && value
+            .chars()
+            .all(|c| matches!(c, '0' | ' ' | '.' | '+' | '-'))
+}
+
+/// Scan the input file, counting how often each field is blank or zero-filled.
+fn run_field_profile(
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+    threshold: u8,
+) -> anyhow::Result<FieldProfile> {
+    let mut fields: Vec<FieldUsage> = records
+        .field_names()
+        .into_iter()
+        .map(|name| FieldUsage {
+            name,
+            lines: 0,
+            blank: 0,
+            zero_filled: 0,
+        })
+        .collect();
+
+    for line in read_selected(args, input_file, assembly)? {
+        let (_, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let line = record_text(&line);
+        let length = line.chars().count();
+        let regions = generate_highlight_regions_from_records(records, &line, positions);
+        for usage in &mut fields {
+            // where a field's rules overlap, the first one that applies gives its value
+            let Some(region) = regions
+                .iter()
+                .find(|r| *r.name == usage.name && r.start < length)
+            else {
+                continue;
+            };
+            let value = region_value(&line, region, None);
+            usage.lines += 1;
+            if value.trim().is_empty() {
+                usage.blank += 1;
+            } else if is_zero_filled(&value) {
+                usage.zero_filled += 1;
+            }
+        }
+    }
+
+    for usage in fields.iter().filter(|u| u.is_dead(threshold)) {
+        error!(
+            "Field '{}' is blank on {} and zero-filled on {} of the {} line(s) it applies to.",
+            usage.name, usage.blank, usage.zero_filled, usage.lines
+        );
+    }
+    Ok(FieldProfile { threshold, fields })
+}
+
+fn write_field_profile_html(
+    out: &mut dyn Write,
+    profile: &FieldProfile,
+    locale: Locale,
+) -> io::Result<()> {
+    writeln!(out, "<h2>{}</h2>", locale.text(Message::FieldProfile))?;
+    let dead: Vec<&FieldUsage> = profile
+        .fields
+        .iter()
+        .filter(|u| u.is_dead(profile.threshold))
+        .collect();
+    if dead.is_empty() {
+        writeln!(
+            out,
+            "<p>{}</p>",
+            locale.format(Message::NoDeadFields, &[&profile.threshold])
+        )?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "<p>{}</p>",
+        locale.format(Message::ProfileSummary, &[&profile.threshold])
+    )?;
+    writeln!(
+        out,
+        "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+        locale.text(Message::Field),
+        locale.text(Message::Lines),
+        locale.text(Message::Blank),
+        locale.text(Message::ZeroFilled)
+    )?;
+    for usage in dead {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&usage.name),
+            usage.lines,
+            usage.blank,
+            usage.zero_filled
+        )?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+/// The lines of one record type, for the table of contents and dashboard.
+struct RecordTypeSummary {
+    record_type: Option<String>,
+    count: usize,
+    /// The (first, last) line indexes of each contiguous run of this record type.
+    sections: Vec<(usize, usize)>,
+}
+
+/// What is learnt by reading the whole input before rendering it.
+struct InputScan {
+    lines: usize,
+    record_types: Vec<RecordTypeSummary>,
+    /// Fields which extend beyond the end of their line.
+    errors: usize,
+    /// Lines with text not covered by any rule.
+    warnings: usize,
+    /// Lines going on past their last field, unless `--trailing-data ignore`.
+    trailing: usize,
+    /// The index into `record_types` of each line.
+    line_types: Vec<u32>,
+    /// The indexes of lines with errors.
+    error_lines: Vec<usize>,
+    /// Whether the syntax file defines record types, rather than lines being typed by the conditions they match.
+    defined_types: bool,
+}
+
+/// The most sections listed under each record type in the table of contents.
+const TOC_MAX_SECTIONS: usize = 200;
+
+fn scan_input(
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+) -> anyhow::Result<InputScan> {
+    let mut scan = InputScan {
+        lines: 0,
+        record_types: Vec::new(),
+        errors: 0,
+        warnings: 0,
+        trailing: 0,
+        line_types: Vec::new(),
+        error_lines: Vec::new(),
+        defined_types: !records.record_types().is_empty(),
+    };
+    let mut previous: Option<usize> = None;
+
+    for line in read_selected(args, input_file, assembly)? {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        let line = record_text(&line);
+        scan.lines += 1;
+
+        // skipped lines are still listed, but have no record type or problems
+        let skipped = is_skipped(args, &line);
+        if !skipped {
+            let length = line.chars().count();
+            let regions = generate_highlight_regions_from_records(records, &line, positions);
+            let errors = regions.iter().filter(|r| r.end > length).count();
+            let trailing_from = trailing_from(&regions, length);
+            let trailing = trailing_from.is_some() && args.trailing_data != TrailingData::Ignore;
+            if trailing {
+                scan.trailing += 1;
+            }
+            if errors > 0 || trailing && args.trailing_data == TrailingData::Error {
+                scan.errors += errors;
+                scan.error_lines.push(idx);
+            }
+            let delimiters = records
+                .delimiter()
+                .map_or(Vec::new(), |d| d.spans(positions.quote, &line));
+            if (0..trailing_from.unwrap_or(length))
+                .any(|col| !is_covered(&delimiters, &regions, col))
+            {
+                scan.warnings += 1;
+            }
+        }
+
+        let record_type = if skipped {
+            None
+        } else {
+            record_type_of(records, &line)
+        };
+        let entry_idx = match scan
+            .record_types
+            .iter()
+            .position(|e| e.record_type == record_type)
+        {
+            Some(i) => i,
+            None => {
+                scan.record_types.push(RecordTypeSummary {
+                    record_type,
+                    count: 0,
+                    sections: Vec::new(),
+                });
+                scan.record_types.len() - 1
+            }
+        };
+
+        scan.line_types.push(entry_idx as u32);
+        let entry = &mut scan.record_types[entry_idx];
+        entry.count += 1;
+        if previous == Some(entry_idx) {
+            entry.sections.last_mut().unwrap().1 = idx;
+        } else {
+            entry.sections.push((idx, idx));
+        }
+        previous = Some(entry_idx);
+    }
+
+    Ok(scan)
+}
+
+/// Describe how a record type is shown in the report.
+fn record_type_label(record_type: &Option<String>, defined_types: bool, locale: Locale) -> String {
+    let untyped = if defined_types {
+        Message::NoRecordType
+    } else {
+        Message::Unconditional
+    };
+    record_type
+        .as_ref()
+        .map_or(format!("<em>{}</em>", locale.text(untyped)), |t| {
+            format!("<code>{}</code>", escape_html(t))
+        })
+}
+
+/// Write the dashboard of `scan`, with the metadata of `input_file`, which is shown as `name`.
+fn write_dashboard_html(
+    out: &mut dyn Write,
+    input_file: &str,
+    name: &str,
+    scan: &InputScan,
+    args: &Args,
+) -> anyhow::Result<()> {
+    let (encoding, locale) = (args.encoding, args.locale);
+    let mut file = open_input(args, input_file)?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut has_bom = false;
+    let mut ascii = true;
+    let mut crlf = false;
+    // EBCDIC's own new line character
+    let mut nl = false;
+    let mut previous = 0u8;
+    let mut chunk = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut chunk)
+            .context("Failed to read input file.")?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &chunk[..read];
+        if size == 0 {
+            has_bom = chunk.starts_with(b"\xef\xbb\xbf");
+        }
+        ascii &= chunk.is_ascii();
+        for &b in chunk {
+            crlf |= previous == b'\r' && encoding.is_line_end(b);
+            nl |= b == 0x15 && encoding.is_line_end(b);
+            previous = b;
+        }
+        hasher.update(chunk);
+        size += read;
+    }
+    let encoding = if encoding != Encoding::Utf8 {
+        encoding.name()
+    } else if has_bom {
+        "UTF-8 (with BOM)"
+    } else if ascii {
+        "ASCII"
+    } else {
+        "UTF-8"
+    };
+    let line_endings = if crlf {
+        "CRLF"
+    } else if nl {
+        "NL"
+    } else {
+        "LF"
+    };
+
+    writeln!(
+        out,
+        r#"<section style="display:flex; flex-wrap:wrap; gap:1em;">"#
+    )?;
+
+    writeln!(
+        out,
+        r#"<div><h3>{}</h3><table>"#,
+        locale.text(Message::Records)
+    )?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        locale.text(Message::Total),
+        scan.lines
+    )?;
+    for entry in &scan.record_types {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            record_type_label(&entry.record_type, scan.defined_types, locale),
+            entry.count
+        )?;
+    }
+    writeln!(out, "</table></div>")?;
+
+    writeln!(
+        out,
+        r#"<div><h3>{}</h3><table>"#,
+        locale.text(Message::Findings)
+    )?;
+    writeln!(
+        out,
+        r#"<tr><th style="color:red;">{}</th><td>{}</td><td><small>{}</small></td></tr>"#,
+        locale.text(Message::Errors),
+        scan.errors,
+        locale.text(Message::ErrorsDetail)
+    )?;
+    writeln!(
+        out,
+        r#"<tr><th style="color:#b07000;">{}</th><td>{}</td><td><small>{}</small></td></tr>"#,
+        locale.text(Message::Warnings),
+        scan.warnings,
+        locale.text(Message::WarningsDetail)
+    )?;
+    if args.trailing_data != TrailingData::Ignore {
+        writeln!(
+            out,
+            r#"<tr><th style="color:{};">{}</th><td>{}</td><td><small>{}</small></td></tr>"#,
+            if args.trailing_data == TrailingData::Error {
+                "red"
+            } else {
+                "#b07000"
+            },
+            locale.text(Message::TrailingLines),
+            scan.trailing,
+            locale.text(Message::TrailingLinesDetail)
+        )?;
+    }
+    writeln!(out, "</table></div>")?;
+
+    writeln!(
+        out,
+        r#"<div><h3>{}</h3><table>"#,
+        locale.text(Message::File)
+    )?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        locale.text(Message::Name),
+        escape_html(name)
+    )?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        locale.text(Message::Size),
+        locale.format(Message::Bytes, &[&size])
+    )?;
+    writeln!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        locale.text(Message::Encoding),
+        locale.format(Message::LineEndings, &[&encoding, &line_endings])
+    )?;
+    writeln!(
+        out,
+        "<tr><th>SHA-256</th><td><code>{}</code></td></tr>",
+        hasher.finish()
+    )?;
+    writeln!(out, "</table></div>")?;
+
+    writeln!(out, "</section>")?;
+    Ok(())
+}
+
+/// A streaming SHA-256 hasher, used to fingerprint input files in the dashboard.
+struct Sha256 {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl Sha256 {
+    fn new() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            buffer: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        self.buffer.extend_from_slice(data);
+        let full = self.buffer.len() - self.buffer.len() % 64;
+        let buffer = std::mem::take(&mut self.buffer);
+        for chunk in buffer[..full].chunks(64) {
+            self.compress(chunk);
+        }
+        self.buffer = buffer[full..].to_vec();
+    }
+
+    /// Finish hashing, returning the digest as lowercase hex.
+    fn finish(mut self) -> String {
+        let bits = self.length * 8;
+        let mut tail = std::mem::take(&mut self.buffer);
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        tail.extend_from_slice(&bits.to_be_bytes());
+        for chunk in tail.chunks(64) {
+            self.compress(chunk);
+        }
+        self.state.iter().map(|x| format!("{:08x}", x)).collect()
+    }
+
+    fn compress(&mut self, chunk: &[u8]) {
+        const K: [u32; 64] = [
+            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+            0xc67178f2,
+        ];
+
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[i * 4],
+                chunk[i * 4 + 1],
+                chunk[i * 4 + 2],
+                chunk[i * 4 + 3],
+            ]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let mut v = self.state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7]
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+            v = [
+                t1.wrapping_add(t2),
+                v[0],
+                v[1],
+                v[2],
+                v[3].wrapping_add(t1),
+                v[4],
+                v[5],
+                v[6],
+            ];
+        }
+        for (h, v) in self.state.iter_mut().zip(v) {
+            *h = h.wrapping_add(v);
+        }
+    }
+}
+
+/// The page styles of `--dark`. The panels drawn over the report set their own light backgrounds, so are overridden.
+const DARK_THEME: &str = "<style>body { background: #1e1e1e; color: #e0e0e0; } a { color: #8ab4f8; } #ffh-inspect, #ffh-minimap, #ffh-search { background: #2a2a2a !important; }</style>";
+
+/// The background of every other line with `--zebra`.
+const ZEBRA_COLOR: &str = "#f2f2f2";
+
+/// The background of every other line with `--zebra` and `--dark`.
+const DARK_ZEBRA_COLOR: &str = "#2a2a2a";
+
+/// Pale backgrounds for `--stripe-by record_type`, as pairs of shades to alternate between.
+const STRIPE_COLORS: [(&str, &str); 6] = [
+    ("#eef3fb", "#e1e9f6"),
+    ("#fdf1e6", "#f9e4d0"),
+    ("#eef7ec", "#e0f0dc"),
+    ("#f6eef5", "#ede0eb"),
+    ("#edf6f6", "#dfeeee"),
+    ("#fbf8e6", "#f5f0d0"),
+];
+
+/// Dark backgrounds for `--stripe-by record_type` with `--dark`, in the same hues as [`STRIPE_COLORS`].
+const DARK_STRIPE_COLORS: [(&str, &str); 6] = [
+    ("#1f2633", "#27303f"),
+    ("#33271d", "#3f3024"),
+    ("#212c1f", "#293727"),
+    ("#2e2230", "#392a3b"),
+    ("#1e2e2e", "#263939"),
+    ("#2f2c1c", "#3a3623"),
+];
+
+/// Assigns line backgrounds for `--stripe-by record_type`.
+#[derive(Debug, Default, Clone)]
+struct RecordTypeStripes {
+    /// The record types seen so far, in order of appearance.
+    types: Vec<Option<String>>,
+    /// The number of sections seen so far of each record type.
+    sections: Vec<usize>,
+    previous: Option<usize>,
+}
+
+impl RecordTypeStripes {
+    /// The background for the next line, which is of `record_type`, in dark shades if `dark`.
+    fn background(&mut self, record_type: Option<String>, dark: bool) -> String {
+        let idx = match self.types.iter().position(|t| *t == record_type) {
+            Some(idx) => idx,
+            None => {
+                self.types.push(record_type);
+                self.sections.push(0);
+                self.types.len() - 1
+            }
+        };
+        if self.previous != Some(idx) {
+            self.sections[idx] += 1;
+        }
+        self.previous = Some(idx);
+
+        let palette = if dark {
+            DARK_STRIPE_COLORS
+        } else {
+            STRIPE_COLORS
+        };
+        let (even, odd) = palette[idx % palette.len()];
+        if self.sections[idx] % 2 == 1 {
+            even
+        } else {
+            odd
+        }
+        .to_owned()
+    }
+}
+
+/// The colours record types are shown with in the minimap. Red is kept for errors.
+const MINIMAP_COLORS: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#59a14f", "#b07aa1", "#76b7b2", "#edc948", "#9c755f", "#bab0ac",
+];
+
+fn write_minimap_html(out: &mut dyn Write, scan: &InputScan, locale: Locale) -> io::Result<()> {
+    let types: Vec<String> = scan
+        .record_types
+        .iter()
+        .map(|t| {
+            t.record_type
+                .as_ref()
+                .map_or("null".to_owned(), |t| json_string(t))
+        })
+        .collect();
+    let line_types: Vec<String> = scan.line_types.iter().map(|t| t.to_string()).collect();
+    let error_lines: Vec<String> = scan.error_lines.iter().map(|l| l.to_string()).collect();
+
+    writeln!(
+        out,
+        r#"<style>body {{ margin-right: 24px; }}</style><canvas id="ffh-minimap" style="position:fixed; top:0; right:0; width:16px; height:100vh; cursor:pointer; background:#f4f4f4;" title="{}"></canvas>"#,
+        locale.text(Message::MinimapHint)
+    )?;
+    writeln!(
+        out,
+        r#"<script>var ffhMinimap = {{"types":[{}],"colors":[{}],"lines":[{}],"errors":[{}]}};</script>"#,
+        // `</` must not appear inside a script element
+        types.join(",").replace("</", "<\\/"),
+        MINIMAP_COLORS
+            .iter()
+            .map(|c| json_string(c))
+            .collect::<Vec<_>>()
+            .join(","),
+        line_types.join(","),
+        error_lines.join(",")
+    )?;
+    writeln!(out, "{}", MINIMAP_SCRIPT)?;
+    Ok(())
+}
+
+fn write_toc_html(out: &mut dyn Write, scan: &InputScan, locale: Locale) -> io::Result<()> {
+    writeln!(
+        out,
+        "<details open><summary><strong>{}</strong></summary>",
+        locale.text(Message::Contents)
+    )?;
+    writeln!(
+        out,
+        "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+        locale.text(Message::RecordType),
+        locale.text(Message::Lines),
+        locale.text(Message::First),
+        locale.text(Message::Last),
+        locale.text(Message::Sections)
+    )?;
+    for entry in &scan.record_types {
+        let first = entry.sections.first().map_or(0, |s| s.0) + 1;
+        let last = entry.sections.last().map_or(0, |s| s.1) + 1;
+        writeln!(
+            out,
+            r##"<tr><td>{}</td><td>{}</td><td><a href="#L{}">L{}</a></td><td><a href="#L{}">L{}</a></td><td><details><summary>{}</summary>"##,
+            record_type_label(&entry.record_type, scan.defined_types, locale),
+            entry.count,
+            first,
+            first,
+            last,
+            last,
+            entry.sections.len()
+        )?;
+        for (start, end) in entry.sections.iter().take(TOC_MAX_SECTIONS) {
+            if start == end {
+                write!(out, r##"<a href="#L{}">L{}</a> "##, start + 1, start + 1)?;
+            } else {
+                write!(
+                    out,
+                    r##"<a href="#L{}">L{}&ndash;{}</a> "##,
+                    start + 1,
+                    start + 1,
+                    end + 1
+                )?;
+            }
+        }
+        if entry.sections.len() > TOC_MAX_SECTIONS {
+            write!(
+                out,
+                "{}",
+                locale.format(
+                    Message::AndMore,
+                    &[&(entry.sections.len() - TOC_MAX_SECTIONS)]
+                )
+            )?;
+        }
+        writeln!(out, "</details></td></tr>")?;
+    }
+    writeln!(out, "</table></details>")?;
+    Ok(())
+}
+
+/// The decoded pane's entry for a line: a table of its field names and values.
+fn decoded_fields_html(
+    line_index: usize,
+    line: &str,
+    regions: &[HighlightRegion],
+    limit: Option<usize>,
+) -> String {
+    let mut html = format!(
+        r#"<div id="D{}" style="border-bottom:1px solid #ddd; padding:0.25em 0;"><small style="color:#909090;">L{}</small><table>"#,
+        line_index + 1,
+        line_index + 1
+    );
+    for r in regions {
+        html.push_str(&format!(
+            "<tr><th style=\"text-align:left; font-weight:normal; color:#606060;\">{}</th><td><code>{}</code></td></tr>",
+            escape_html(&r.name),
+            bdi_html(&region_value(line, r, limit))
+        ));
+    }
+    html.push_str("</table></div>");
+    html
+}
+
+/// Write the panel `--interactive` shows a clicked field's details in, with the script that fills it.
+fn write_inspector_html(out: &mut dyn Write, locale: Locale) -> io::Result<()> {
+    writeln!(
+        out,
+        "<style>abbr[data-field] {{ cursor: pointer; }} .ffh-same {{ outline: 1px solid #0060df; }}</style>"
+    )?;
+    write!(
+        out,
+        r#"<div id="ffh-inspect" hidden data-line-text="{}" style="position:fixed; top:16px; right:32px; width:320px; max-height:80vh; overflow:auto; padding:8px 12px; background:#fff; border:1px solid #909090; box-shadow:0 2px 8px rgba(0,0,0,0.2); font-family:sans-serif; font-size:smaller;">"#,
+        escape_html(locale.text(Message::Line))
+    )?;
+    write!(
+        out,
+        r#"<button type="button" id="ffh-inspect-close" style="float:right;" title="{0}" aria-label="{0}">&times;</button><strong data-inspect="line"></strong><dl>"#,
+        locale.text(Message::Close)
+    )?;
+    for (message, key) in [
+        (Message::Field, "field"),
+        (Message::Start, "start"),
+        (Message::Length, "length"),
+        (Message::Value, "value"),
+        (Message::Validation, "check"),
+    ] {
+        write!(
+            out,
+            r#"<dt>{}</dt><dd data-inspect="{}" style="white-space:pre-wrap; font-family:monospace;"></dd>"#,
+            locale.text(message),
+            key
+        )?;
+    }
+    writeln!(out, "</dl></div>")?;
+    writeln!(out, "{}", INSPECT_SCRIPT)
+}
+
+/// Write the search bar of `--search`, with the lines with problems it steps through and the script that runs it.
+fn write_search_html(
+    out: &mut dyn Write,
+    records: &RecordList,
+    problem_lines: &[usize],
+    locale: Locale,
+) -> io::Result<()> {
+    write!(
+        out,
+        r#"<div id="ffh-search" data-count-text="{}" data-none-text="{}" style="position:fixed; bottom:16px; right:32px; padding:6px 8px; background:#fff; border:1px solid #909090; box-shadow:0 2px 8px rgba(0,0,0,0.2); font-family:sans-serif; font-size:smaller;">"#,
+        escape_html(locale.text(Message::MatchCount)),
+        escape_html(locale.text(Message::NoMatches))
+    )?;
+    let button = |id: &str, message: Message, arrow: &str| {
+        format!(
+            r#"<button type="button" id="{0}" title="{1}" aria-label="{1}">{2}</button>"#,
+            id,
+            escape_html(locale.text(message)),
+            arrow
+        )
+    };
+    write!(
+        out,
+        r#"{}{} <span id="ffh-problem-count">{}</span> &nbsp; "#,
+        button("ffh-problem-previous", Message::PreviousProblem, "&#9650;"),
+        button("ffh-problem-next", Message::NextProblem, "&#9660;"),
+        escape_html(&locale.format(Message::ProblemLines, &[&problem_lines.len()]))
+    )?;
+    write!(
+        out,
+        r#"<select id="ffh-search-field"><option value="">{}</option>"#,
+        escape_html(locale.text(Message::AnyField))
+    )?;
+    for name in records.field_names() {
+        write!(out, "<option>{}</option>", escape_html(&name))?;
+    }
+    writeln!(
+        out,
+        r#"</select> <input type="search" id="ffh-search-value" placeholder="{}" size="16">{}{} <span id="ffh-search-count"></span></div>"#,
+        escape_html(locale.text(Message::Value)),
+        button("ffh-search-previous", Message::PreviousMatch, "&#9650;"),
+        button("ffh-search-next", Message::NextMatch, "&#9660;")
+    )?;
+    let lines: Vec<String> = problem_lines.iter().map(usize::to_string).collect();
+    writeln!(
+        out,
+        r#"<script type="application/json" id="ffh-problem-lines">[{}]</script>"#,
+        lines.join(",")
+    )?;
+    writeln!(out, "{}", SEARCH_SCRIPT)
+}
+
+/// Print the extracted data (the comma separated lines of a JSON array) as an embedded JSON document, along with buttons to download it.
+fn write_embedded_data_html(
+    out: &mut dyn Write,
+    data: Spool,
+    locale: Locale,
+) -> anyhow::Result<()> {
+    write!(out, r#"<script type="application/json" id="ffh-data">["#)?;
+    data.write_to(out)?;
+    writeln!(out, "]</script>")?;
+    writeln!(
+        out,
+        r#"<p><button type="button" id="ffh-download-csv">{}</button> <button type="button" id="ffh-download-json">{}</button></p>"#,
+        locale.text(Message::DownloadCsv),
+        locale.text(Message::DownloadJson)
+    )?;
+    writeln!(out, "{}", DOWNLOAD_SCRIPT)?;
+    Ok(())
+}
+
+/// Start the report of a format written a line at a time, `--output-format` anything but `html`, on `out`.
+fn line_report<'a>(
+    args: &'a Args,
+    out: Box<dyn Write>,
+    input_file: &str,
+    records: &'a RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+    render: &'a Renderer,
+) -> anyhow::Result<Box<dyn LineReport + 'a>> {
+    Ok(match args.output_format {
+        OutputFormat::Ansi => Box::new(AnsiReport::new(
+            out,
+            render,
+            &shift_notes(args, records),
+            args.locale,
+            args.follow,
+        )?),
+        OutputFormat::Json | OutputFormat::Jsonl => {
+            let lines = if args.output_format == OutputFormat::Jsonl {
+                JsonLines::Records
+            } else if args.line_numbers {
+                JsonLines::Numbered
+            } else {
+                JsonLines::Fields
+            };
+            Box::new(JsonReport::new(out, lines, args.follow))
+        }
+        OutputFormat::Csv => Box::new(CsvReport::new(out, args.follow)?),
+        OutputFormat::Markdown => Box::new(MarkdownReport::new(
+            out,
+            &args.locale.format(Message::Title, &[&input_name(args)]),
+            args.locale,
+        )?),
+        OutputFormat::Xlsx => Box::new(xlsx::XlsxReport::new(out)),
+        OutputFormat::Pdf => {
+            let mut pdf = pdf::start_pdf(out, args)?;
+            if args.legend == Some(LegendPosition::Top) {
+                info!("Scanning input for the legend");
+                Legend::scan(args, input_file, records, positions, assembly, render)?
+                    .write_pdf(&mut pdf, records, args.locale)
+                    .and_then(|_| pdf.heading(args.locale.text(Message::Lines)))
+                    .context("Failed to write PDF.")?;
+            }
+            Box::new(pdf::PdfReport::new(pdf, args, records, positions)?)
+        }
+        OutputFormat::Html => unreachable!("the HTML report isn't written a line at a time"),
+    })
+}
+
+/// Highlight and check each of `lines`, counting what's found, and write it to `report`, then finish the report. A
+/// report whose reader stops reading, e.g. `head`, ends there without an error.
+fn write_lines<B: BufRead>(
+    mut report: Box<dyn LineReport + '_>,
+    args: &Args,
+    lines: SelectedLines<BoundedLines<B>>,
+    records: &RecordList,
+    positions: PositionOptions,
+    timings: &mut Timings,
+    counts: &mut RunCounts,
+) -> anyhow::Result<()> {
+    // values stop where any padding starts, so are only what the line holds
+    let ghost_cells = args.ghost_cells || !report.shows_lines();
+    let result = (|| -> anyhow::Result<()> {
+        for line in lines {
+            let (idx, line) = line.context("Failed to read line from input file.")?;
+            let phase = Instant::now();
+            let mut prepared =
+                prepare_report_line(args, records, line, idx, positions, ghost_cells)?;
+            timings.regions += phase.elapsed();
+            timings.lines += 1;
+            timings.rules_evaluated += records.len() as u64;
+            let failed_checks = counts.count_line(records, idx, &prepared)?;
+            let suspicious = if args.check_unicode {
+                suspicious::log_line(idx, &prepared.text, &prepared.regions)
+            } else {
+                0
+            };
+            let violations =
+                constraints::log_line(idx, &prepared.text, &prepared.regions, prepared.ghost_from);
+            counts.count_findings(violations, suspicious);
+            // checked, so now shown with its sensitive fields masked
+            redact(&mut prepared);
+
+            let phase = Instant::now();
+            report.write_line(&ReportLine {
+                index: idx,
+                prepared: &prepared,
+                failed_checks: &failed_checks,
+                problems: &counts.problems.take_line_problems(),
+            })?;
+            timings.render += phase.elapsed();
+        }
+        report.finish()
+    })();
+
+    match result {
+        Err(e) if stopped_reading(&e) => Ok(()),
+        r => r,
+    }
+}
+
+/// The input file argument which stands for standard input.
+const STDIN: &str = "-";
+
+/// Open the input file to read, or standard input if it's [`STDIN`], decompressing it if it's compressed.
+fn open_input(args: &Args, input_file: &str) -> anyhow::Result<Box<dyn Read>> {
+    compression::open(input_file, args.compression).context("Failed to open input file.")
+}
+
+/// Open the input file, or standard input, to read lines from: mapped into memory if it's an uncompressed file and
+/// `--no-mmap` isn't given, or else through a buffer.
+fn open_buffered(args: &Args, input_file: &str) -> anyhow::Result<Box<dyn BufRead>> {
+    if input_file != STDIN
+        && !args.no_mmap
+        && compression::detect(input_file, args.compression)? == Compression::None
+    {
+        let file = File::open(input_file).context("Failed to open input file.")?;
+        if let Some(mapped) = MappedFile::open(&file, 0) {
+            return Ok(Box::new(mapped));
+        }
+    }
+    Ok(Box::new(BufReader::new(open_input(args, input_file)?)))
+}
+
+/// The name of the input file to show in reports.
+fn input_name(args: &Args) -> String {
+    match args.input_file.as_deref() {
+        Some(STDIN) | None => args.locale.text(Message::StandardInput).to_owned(),
+        Some(path) => Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    }
+}
+
+/// Whether the sections asked for at the top of the HTML report read the input before its lines are rendered, which
+/// standard input can't be read again for.
+fn rereads_input(args: &Args) -> bool {
+    args.preflight
+        || args.profile_fields.is_some()
+        || args.toc
+        || args.dashboard
+        || args.minimap
+        || args.legend == Some(LegendPosition::Top)
+        || args.display_width
+}
+
+/// Standard input, copied to a temporary file so it can be read more than once. The file is removed when this is
+/// dropped.
+struct StdinCopy {
+    path: std::path::PathBuf,
+}
+
+impl StdinCopy {
+    fn new() -> anyhow::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "fixedfile-highlighter-{}.stdin",
+            std::process::id()
+        ));
+        let mut file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .context("Failed to create a copy of standard input.")?;
+        // from here on, the file is removed however this fails
+        let copy = Self { path };
+        std::io::copy(&mut io::stdin().lock(), &mut file)
+            .context("Failed to copy standard input.")?;
+        Ok(copy)
+    }
+
+    fn path(&self) -> anyhow::Result<&str> {
+        self.path
+            .to_str()
+            .context("The temporary directory's path isn't valid UTF-8.")
+    }
+}
+
+impl Drop for StdinCopy {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Standard output, as a file.
+fn stdout_file() -> anyhow::Result<File> {
+    #[cfg(unix)]
+    let handle = std::os::fd::AsFd::as_fd(&io::stdout()).try_clone_to_owned();
+    #[cfg(windows)]
+    let handle = std::os::windows::io::AsHandle::as_handle(&io::stdout()).try_clone_to_owned();
+    Ok(File::from(
+        handle.context("Failed to access standard output.")?,
+    ))
+}
+
+/// Start the user's pager, as git does: `$PAGER` or `less`, with `LESS=FRX` unless `LESS` is already set so short output doesn't need paging.
+fn start_pager() -> Option<std::process::Child> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+    if pager == "cat" {
+        return None;
+    }
+
+    let mut parts = pager.split_whitespace();
+    let mut command = std::process::Command::new(parts.next()?);
+    command.args(parts).stdin(std::process::Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    match command.spawn() {
+        Ok(child) => Some(child),
+        Err(e) => {
+            error!("Failed to start pager '{}': {}", pager, e);
+            None
         }
     }
 }
@@ -0,0 +1,281 @@
+//! The `map` subcommand, which shows how the values of each line would move from one layout to another, given a
+//! mapping between their fields, for reviewing migrations between layouts.
+
+use anyhow::{bail, Context};
+use log::error;
+
+use crate::{
+    check_syntax_strictly, escape_html, field_colors, field_value_styles, file_name, is_skipped,
+    load_syntax, locale::Message, open_buffered, prepare_line, read_syntax_file, redact,
+    region_value, render_options, select::read_records, value_stylesheet, Args, Delimiter, MapArgs,
+    PositionOptions, RecordList,
+};
+
+/// A row of the mapping file.
+#[derive(Debug, serde::Deserialize)]
+struct Mapping {
+    source: String,
+    target: String,
+}
+
+/// Where a target field's value goes in a line of the target layout.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    /// The 0-based start column and length, in fixed width mode.
+    Columns(usize, usize),
+    /// The 0-based field, in delimiter mode.
+    Field(usize),
+}
+
+/// Where each field of the target layout goes, by the first of its rules with a usable position. Conditions aren't
+/// considered, as they depend on the line being built.
+fn target_slots(records: &RecordList, index_base: usize) -> Vec<(String, Slot)> {
+    let mut slots: Vec<(String, Slot)> = Vec::new();
+    let mut add = |name: &str, slot: Option<Slot>| {
+        if let Some(slot) = slot {
+            if !slots.iter().any(|(n, _)| n == name) {
+                slots.push((name.to_owned(), slot));
+            }
+        }
+    };
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            for record in fw_records {
+                let slot = match (record.start, record.length) {
+                    // a field placed from the end has no fixed columns to map onto
+                    (Some(start), Some(length)) if start >= index_base && !record.from_end => {
+                        Some(Slot::Columns(start - index_base, length))
+                    }
+                    _ => None,
+                };
+                add(&record.name, slot);
+            }
+        }
+        RecordList::Delimiter(_, d_records) => {
+            for record in d_records {
+                let slot = record
+                    .field
+                    .filter(|f| *f >= index_base)
+                    .map(|f| Slot::Field(f - index_base));
+                add(&record.name, slot);
+            }
+        }
+    }
+    slots
+}
+
+/// Build a line of the target layout from the values of its fields, logging values too long for their columns.
+fn build_target_line(
+    line_index: usize,
+    target: &RecordList,
+    slots: &[(String, Slot)],
+    values: &[(String, String)],
+) -> String {
+    match target {
+        RecordList::FixedWidth(_) => {
+            let width = slots
+                .iter()
+                .map(|(_, slot)| match slot {
+                    Slot::Columns(start, length) => start + length,
+                    Slot::Field(_) => 0,
+                })
+                .max()
+                .unwrap_or(0);
+            let mut line = vec![' '; width];
+            for (name, value) in values {
+                let Some((_, Slot::Columns(start, length))) = slots.iter().find(|(n, _)| n == name)
+                else {
+                    continue;
+                };
+                let chars: Vec<char> = value.chars().collect();
+                // padding that doesn't fit is no loss
+                if chars.iter().skip(*length).any(|c| *c != ' ') {
+                    error!(
+                        "Line {}: the value of '{}' is {} characters long, but only {} fit, so it would be cut short.",
+                        line_index + 1,
+                        name,
+                        chars.len(),
+                        length
+                    );
+                }
+                for (i, c) in chars.into_iter().take(*length).enumerate() {
+                    line[start + i] = c;
+                }
+            }
+            line.into_iter().collect()
+        }
+        RecordList::Delimiter(delimiter, _) => {
+            let count = slots
+                .iter()
+                .map(|(_, slot)| match slot {
+                    Slot::Field(field) => field + 1,
+                    Slot::Columns(..) => 0,
+                })
+                .max()
+                .unwrap_or(0);
+            let mut fields = vec![String::new(); count];
+            for (name, value) in values {
+                if let Some((_, Slot::Field(field))) = slots.iter().find(|(n, _)| n == name) {
+                    if value.contains(delimiter.as_str()) {
+                        error!(
+                            "Line {}: the value of '{}' contains the delimiter '{}', which would split it.",
+                            line_index + 1,
+                            name,
+                            delimiter
+                        );
+                    }
+                    fields[*field] = value.clone();
+                }
+            }
+            fields.join(delimiter.as_str())
+        }
+    }
+}
+
+/// Run the `map` subcommand.
+pub fn run(args: &Args, map: &MapArgs) -> anyhow::Result<()> {
+    let (_, source, positions, assembly) =
+        load_syntax(args, &map.syntax_file, Some(&map.input_file))?;
+    let to_delimiter = map
+        .to_delimiter
+        .as_deref()
+        .map(|delimiter| Delimiter::parse(delimiter, false))
+        .transpose()?;
+    let (_, target, _) = read_syntax_file(&map.to, to_delimiter)?;
+    if args.strict_syntax {
+        check_syntax_strictly(&target, args.index_base as usize)?;
+    }
+    let target_positions = PositionOptions {
+        shift: 0,
+        index_base: args.index_base as usize,
+        column_mode: args.column_mode,
+        quote: args.quote,
+    };
+    let slots = target_slots(&target, target_positions.index_base);
+
+    let mut mappings: Vec<Mapping> = Vec::new();
+    let mut reader =
+        csv::Reader::from_path(&map.mapping).context("Failed to read mapping file.")?;
+    for row in reader.deserialize() {
+        mappings.push(row.context("Failed to parse mapping file.")?);
+    }
+    let source_names = source.field_names();
+    for mapping in &mappings {
+        if !source_names.contains(&mapping.source) {
+            bail!(
+                "Mapping file: there is no field '{}' in the source syntax.",
+                mapping.source
+            );
+        }
+        if !slots.iter().any(|(n, _)| *n == mapping.target) {
+            bail!(
+                "Mapping file: there is no positioned field '{}' in the target syntax.",
+                mapping.target
+            );
+        }
+    }
+    for (name, _) in &slots {
+        if !mappings.iter().any(|m| m.target == *name) {
+            error!(
+                "Target field '{}' isn't mapped from any source field, so will be left empty.",
+                name
+            );
+        }
+    }
+
+    let render = render_options(args)?;
+    let target_render = render_options(args)?;
+    let locale = args.locale;
+    let title = locale.format(
+        Message::MapTitle,
+        &[&file_name(&map.syntax_file), &file_name(&map.to)],
+    );
+    println!(r#"<!doctype html><html lang="{}">"#, locale.tag());
+    let colors = field_colors(&source)
+        .into_iter()
+        .chain(field_colors(&target));
+    println!(
+        "<head><meta charset=\"utf8\"><title>{}</title><style>\n{}</style></head>",
+        escape_html(&title),
+        render.stylesheet(colors)
+            + &value_stylesheet(
+                field_value_styles(&source)
+                    .iter()
+                    .chain(&field_value_styles(&target))
+            )
+    );
+    println!("<body>");
+    println!("<h1>{}</h1>", escape_html(&title));
+    println!(
+        "<table><tr><th>{}</th><th></th><th>{}</th></tr>",
+        locale.text(Message::SourceField),
+        locale.text(Message::TargetField)
+    );
+    for mapping in &mappings {
+        println!(
+            "<tr><td>{}</td><td>&rarr;</td><td>{}</td></tr>",
+            escape_html(&mapping.source),
+            escape_html(&mapping.target)
+        );
+    }
+    println!("</table>");
+
+    println!(
+        r#"<table style="border-collapse:collapse; margin-top:1em;"><tr><th>{}</th><th>{}</th></tr>"#,
+        escape_html(&file_name(&map.syntax_file)),
+        escape_html(&file_name(&map.to))
+    );
+    let file = open_buffered(args, &map.input_file)?;
+    for line in read_records(args, file, &assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let mut prepared = prepare_line(&source, line, idx, positions, args.short_lines, false)?;
+        let length = prepared.text.chars().count();
+
+        let mut values: Vec<(String, String)> = Vec::new();
+        // the target fields given the values of sensitive fields, which are masked as their source is
+        let mut sensitive: Vec<&str> = Vec::new();
+        for mapping in &mappings {
+            if let Some(region) = prepared
+                .regions
+                .iter()
+                .find(|r| *r.name == mapping.source && r.start < length)
+            {
+                values.push((
+                    mapping.target.clone(),
+                    region_value(&prepared.text, region, None),
+                ));
+                if region.sensitive {
+                    sensitive.push(&mapping.target);
+                }
+            }
+        }
+        let built = build_target_line(idx, &target, &slots, &values);
+        let mut built = prepare_line(
+            &target,
+            built,
+            idx,
+            target_positions,
+            args.short_lines,
+            false,
+        )?;
+        for region in &mut built.regions {
+            region.sensitive |= sensitive.contains(&region.name.as_ref());
+        }
+        redact(&mut prepared);
+        redact(&mut built);
+
+        println!(r#"<tr style="border-top:1px solid #ddd; vertical-align:top;">"#);
+        for (side, render) in [(prepared, &render), (built, &target_render)] {
+            println!(r#"<td><pre dir="ltr" style="color:red; margin:0;">"#);
+            render.write_html_line(&mut std::io::stdout(), idx, &side, None)?;
+            println!("</pre></td>");
+        }
+        println!("</tr>");
+    }
+    println!("</table>");
+    println!("</body></html>");
+    Ok(())
+}
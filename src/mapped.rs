@@ -0,0 +1,149 @@
+//! Reading an input file through a memory map rather than copying it through a buffer, so the lines of a file of
+//! gigabytes are found in the file's own pages without a read for each few kilobytes of it.
+
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{self, BufRead, Read},
+    rc::Rc,
+};
+
+/// An input file mapped into memory, read like a [`BufReader`](std::io::BufReader) whose buffer is the whole file.
+///
+/// A mapped file that's truncated while it's being read can't be read past its new end, and the process is killed if it
+/// tries, so files that may be rewritten while they're read should be read with `--no-mmap`.
+pub struct MappedFile {
+    map: imp::Map,
+    position: usize,
+    /// Counts the bytes consumed, for `--timing`.
+    count: Option<Rc<Cell<u64>>>,
+}
+
+impl MappedFile {
+    /// Map `file` to be read from `offset`, or `None` if it can't be mapped, such as when it's a pipe or on a system
+    /// without memory maps, when it should be read as usual.
+    pub fn open(file: &File, offset: u64) -> Option<MappedFile> {
+        let map = imp::Map::new(file)?;
+        let position = usize::try_from(offset)
+            .map_or(map.bytes().len(), |offset| offset.min(map.bytes().len()));
+        Some(MappedFile {
+            map,
+            position,
+            count: None,
+        })
+    }
+
+    /// Add the number of bytes consumed to `count`.
+    pub fn counted(mut self, count: Rc<Cell<u64>>) -> Self {
+        self.count = Some(count);
+        self
+    }
+}
+
+impl BufRead for MappedFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&self.map.bytes()[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        let amount = amount.min(self.map.bytes().len() - self.position);
+        self.position += amount;
+        if let Some(count) = &self.count {
+            count.set(count.get() + amount as u64);
+        }
+    }
+}
+
+impl Read for MappedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::{fs::File, os::unix::io::AsRawFd, ptr};
+
+    use log::debug;
+
+    /// The pages of a file mapped read-only into memory, unmapped when dropped.
+    pub struct Map {
+        address: *mut libc::c_void,
+        length: usize,
+    }
+
+    impl Map {
+        pub fn new(file: &File) -> Option<Map> {
+            let metadata = file.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            let length = usize::try_from(metadata.len()).ok()?;
+            if length == 0 {
+                // an empty mapping isn't allowed, and there's nothing to read anyway
+                return Some(Map {
+                    address: ptr::null_mut(),
+                    length,
+                });
+            }
+            // SAFETY: the descriptor is of an open regular file, `length` is its size, and the mapping is private and
+            // read-only, so nothing is written through it.
+            let address = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    length,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if address == libc::MAP_FAILED {
+                debug!(
+                    "Failed to map the input file into memory ({}), reading it instead.",
+                    std::io::Error::last_os_error()
+                );
+                return None;
+            }
+            // SAFETY: `address` and `length` are the mapping just made. This is only advice, so failing is harmless.
+            unsafe { libc::madvise(address, length, libc::MADV_SEQUENTIAL) };
+            Some(Map { address, length })
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            if self.length == 0 {
+                return &[];
+            }
+            // SAFETY: the mapping is `length` readable bytes, which live until it's unmapped when `self` is dropped.
+            unsafe { std::slice::from_raw_parts(self.address as *const u8, self.length) }
+        }
+    }
+
+    impl Drop for Map {
+        fn drop(&mut self) {
+            if self.length > 0 {
+                // SAFETY: `address` and `length` are a mapping made by `mmap`, which nothing borrows any more.
+                unsafe { libc::munmap(self.address, self.length) };
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::fs::File;
+
+    pub struct Map;
+
+    impl Map {
+        pub fn new(_file: &File) -> Option<Map> {
+            None
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            &[]
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Run metrics written by `--metrics`, for schedulers and monitoring to pick up after each run.
+
+use std::{fs, time::Instant};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+
+use crate::{json_string, usage::RuleUsage, Condition, RecordList, RecordTyper};
+
+/// Counts gathered while the report is written, besides those of each rule's [`RuleUsage`].
+pub struct Metrics {
+    started_at: DateTime<Local>,
+    started: Instant,
+    /// The record types of the syntax file, if it defines any.
+    typer: RecordTyper,
+    /// Otherwise, the distinct conditions of the rules, which name record types.
+    conditions: Vec<Condition>,
+    /// Lines of each record type, in the order the types are first seen.
+    record_types: Vec<(Option<String>, u64)>,
+    lines: u64,
+    constraint_violations: u64,
+    suspicious_characters: u64,
+}
+
+impl Metrics {
+    pub fn new(records: &RecordList) -> Self {
+        let mut conditions: Vec<Condition> = Vec::new();
+        for condition in records.rules().into_iter().filter_map(|r| r.condition()) {
+            if !conditions.contains(condition) {
+                conditions.push(condition.clone());
+            }
+        }
+        Self {
+            started_at: Local::now(),
+            started: Instant::now(),
+            typer: RecordTyper::new(records),
+            conditions,
+            record_types: Vec::new(),
+            lines: 0,
+            constraint_violations: 0,
+            suspicious_characters: 0,
+        }
+    }
+
+    /// Count a line under its record type.
+    pub fn count_line(&mut self, line: &str) {
+        self.lines += 1;
+        // named as `record_type_of` names it
+        let record_type = if self.typer.is_empty() {
+            let matched: Vec<&str> = self
+                .conditions
+                .iter()
+                .filter(|c| c.is_match(line))
+                .map(Condition::as_str)
+                .collect();
+            (!matched.is_empty()).then(|| matched.join(" & "))
+        } else {
+            self.typer.record_type(line).map(|t| t.name.clone())
+        };
+        match self
+            .record_types
+            .iter_mut()
+            .find(|(t, _)| *t == record_type)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.record_types.push((record_type, 1)),
+        }
+    }
+
+    /// Count the findings logged for a line.
+    pub fn count_findings(&mut self, constraint_violations: usize, suspicious_characters: usize) {
+        self.constraint_violations += constraint_violations as u64;
+        self.suspicious_characters += suspicious_characters as u64;
+    }
+
+    /// Write the metrics as JSON to `path`.
+    pub fn write(
+        &self,
+        path: &str,
+        input_file: &str,
+        syntax_file: &str,
+        usage: &RuleUsage,
+    ) -> anyhow::Result<()> {
+        let record_types: Vec<String> = self
+            .record_types
+            .iter()
+            .map(|(record_type, count)| {
+                format!(
+                    r#"{{"record_type":{},"lines":{}}}"#,
+                    record_type
+                        .as_deref()
+                        .map_or("null".to_owned(), json_string),
+                    count
+                )
+            })
+            .collect();
+        let rules: Vec<String> = usage
+            .rules
+            .iter()
+            .map(|rule| {
+                format!(
+                    r#"{{"name":{},"condition":{},"matched":{},"applied":{}}}"#,
+                    json_string(&rule.name),
+                    rule.condition
+                        .as_ref()
+                        .map(Condition::as_str)
+                        .map_or("null".to_owned(), json_string),
+                    rule.matched,
+                    rule.applied
+                )
+            })
+            .collect();
+        let list = |items: &[String]| {
+            items
+                .iter()
+                .map(|i| format!("\n    {}", i))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let json = format!(
+            "{{\n  \"version\": 1,\n  \"input_file\": {},\n  \"syntax_file\": {},\n  \"started_at\": {},\n  \"duration_seconds\": {:.3},\n  \"lines\": {},\n  \"record_types\": [{}\n  ],\n  \"rules\": [{}\n  ],\n  \"findings\": {{\"constraint_violations\": {}, \"suspicious_characters\": {}}}\n}}\n",
+            json_string(input_file),
+            json_string(syntax_file),
+            json_string(&self.started_at.to_rfc3339()),
+            self.started.elapsed().as_secs_f64(),
+            self.lines,
+            list(&record_types),
+            list(&rules),
+            self.constraint_violations,
+            self.suspicious_characters
+        );
+        fs::write(path, json).context("Failed to write metrics file.")
+    }
+}
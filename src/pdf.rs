@@ -0,0 +1,599 @@
+//! Writing the report as a PDF, for attaching to audits: the lines with their numbers and fields' colours, set in
+//! Courier on landscape A4 pages. The PDF is written directly, page by page as the lines are read, so no browser is
+//! needed to print the report and a large file never has to be held in memory.
+
+use std::io::{self, Write};
+
+use anyhow::Context;
+
+use crate::{
+    bidi_control_name, foreground_color, input_name, parse_hex_color, record_gutter,
+    render_options, segments, suspicious, value_styles, Args, CheckResult, Legend, LegendPosition,
+    LineReport, Locale, Message, PositionOptions, PreparedLine, RecordList, Renderer, ReportLine,
+    SegmentKind,
+};
+
+const PAGE_WIDTH: f32 = 842.0;
+const PAGE_HEIGHT: f32 = 595.0;
+const MARGIN: f32 = 36.0;
+const FONT_SIZE: f32 = 8.0;
+/// Every glyph of Courier is 600/1000 of the font size wide.
+const CHAR_WIDTH: f32 = FONT_SIZE * 0.6;
+const ROW_HEIGHT: f32 = 10.0;
+/// The characters that fit on a row.
+pub const COLUMNS: usize = ((PAGE_WIDTH - 2.0 * MARGIN) / CHAR_WIDTH) as usize;
+
+/// The object numbers of the objects written before any page.
+const CATALOG: usize = 1;
+const PAGES: usize = 2;
+const REGULAR: usize = 3;
+const BOLD: usize = 4;
+
+pub const BLACK: (u8, u8, u8) = (0, 0, 0);
+/// Line numbers, the end of each line and delimiters, as the HTML report mutes them.
+const MUTED: (u8, u8, u8) = (144, 144, 144);
+/// Virtual padding, and skipped lines when they're dimmed.
+const FAINT: (u8, u8, u8) = (160, 160, 160);
+const VIOLATION: (u8, u8, u8) = (224, 0, 0);
+const SUSPICIOUS: (u8, u8, u8) = (224, 112, 0);
+/// The background of unexpected trailing data, as the HTML report shades it.
+const TRAILING: (u8, u8, u8) = (255, 224, 224);
+
+/// How a run of text on a row is drawn.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub color: (u8, u8, u8),
+    pub background: Option<(u8, u8, u8)>,
+    pub bold: bool,
+    /// The colour of the line under the text, if it's underlined.
+    pub underline: Option<(u8, u8, u8)>,
+}
+
+impl Style {
+    pub fn plain(color: (u8, u8, u8)) -> Self {
+        Self {
+            color,
+            background: None,
+            bold: false,
+            underline: None,
+        }
+    }
+
+    pub fn bold(color: (u8, u8, u8)) -> Self {
+        Self {
+            bold: true,
+            ..Self::plain(color)
+        }
+    }
+}
+
+/// A PDF being written a page at a time, each page headed by the report's title and numbered at its foot.
+pub struct Pdf {
+    out: Box<dyn Write>,
+    /// The number of bytes written so far, which objects are found by.
+    written: usize,
+    /// Where each object starts, by its number less one, or 0 if it's not been written yet.
+    offsets: Vec<usize>,
+    /// The object number of each page, in order.
+    pages: Vec<usize>,
+    title: String,
+    locale: Locale,
+    /// The content of the page being written, if one has been started.
+    content: Option<String>,
+    /// The top of the next row of the page being written.
+    y: f32,
+}
+
+impl Pdf {
+    pub fn new(out: Box<dyn Write>, title: &str, locale: Locale) -> io::Result<Self> {
+        let mut pdf = Self {
+            out,
+            written: 0,
+            offsets: vec![0; BOLD],
+            pages: Vec::new(),
+            title: title.to_owned(),
+            locale,
+            content: None,
+            y: 0.0,
+        };
+        // the comment's bytes above 127 tell programs reading the file that it isn't plain text
+        pdf.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+        pdf.object(
+            CATALOG,
+            format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES).as_bytes(),
+        )?;
+        for (number, font) in [(REGULAR, "Courier"), (BOLD, "Courier-Bold")] {
+            pdf.object(
+                number,
+                format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font
+                )
+                .as_bytes(),
+            )?;
+        }
+        Ok(pdf)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len();
+        Ok(())
+    }
+
+    /// Write object `number`, whose content is `body`.
+    fn object(&mut self, number: usize, body: &[u8]) -> io::Result<()> {
+        self.offsets[number - 1] = self.written;
+        self.write(format!("{} 0 obj\n", number).as_bytes())?;
+        self.write(body)?;
+        self.write(b"\nendobj\n")
+    }
+
+    /// Number a new object, to be written later.
+    fn next_number(&mut self) -> usize {
+        self.offsets.push(0);
+        self.offsets.len()
+    }
+
+    /// Start a new page, finishing the last one, unless `height` more fits on the page being written.
+    fn make_room(&mut self, height: f32) -> io::Result<()> {
+        if self.content.is_some() && self.y - height >= MARGIN {
+            return Ok(());
+        }
+        self.finish_page()?;
+        let mut content = String::new();
+        let top = PAGE_HEIGHT - MARGIN;
+        write_text(
+            &mut content,
+            MARGIN,
+            top - 9.0,
+            10.0,
+            &self.title,
+            Style::bold(BLACK),
+        );
+        self.content = Some(content);
+        self.y = top - 2.0 * ROW_HEIGHT;
+        Ok(())
+    }
+
+    /// Number the page being written, if there is one, and write it.
+    fn finish_page(&mut self) -> io::Result<()> {
+        let Some(mut content) = self.content.take() else {
+            return Ok(());
+        };
+        let number = self
+            .locale
+            .format(Message::Page, &[&(self.pages.len() + 1)]);
+        let x = PAGE_WIDTH - MARGIN - number.chars().count() as f32 * CHAR_WIDTH;
+        write_text(
+            &mut content,
+            x,
+            MARGIN / 2.0,
+            FONT_SIZE,
+            &number,
+            Style::plain(MUTED),
+        );
+
+        let stream = self.next_number();
+        let page = self.next_number();
+        // the line end before `endstream` isn't part of the stream, so isn't counted in its length
+        self.object(
+            stream,
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            )
+            .as_bytes(),
+        )?;
+        self.object(
+            page,
+            format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources << /Font << /F1 {} 0 R /F2 {} 0 R >> >> >>",
+                PAGES, PAGE_WIDTH, PAGE_HEIGHT, stream, REGULAR, BOLD
+            )
+            .as_bytes(),
+        )?;
+        self.pages.push(page);
+        Ok(())
+    }
+
+    /// Write a row of runs of text, one after another from the left margin. Text beyond [`COLUMNS`] runs off the page.
+    pub fn row(&mut self, runs: &[(String, Style)]) -> io::Result<()> {
+        self.make_room(ROW_HEIGHT)?;
+        self.y -= ROW_HEIGHT;
+        let baseline = self.y + 2.0;
+        let content = self.content.as_mut().expect("a page has been started");
+
+        // backgrounds go under all of the row's text
+        let mut x = MARGIN;
+        for (text, style) in runs {
+            let width = text.chars().count() as f32 * CHAR_WIDTH;
+            if let Some(background) = style.background {
+                content.push_str(&format!(
+                    "{} rg {:.2} {:.2} {:.2} {:.2} re f\n",
+                    pdf_color(background),
+                    x,
+                    self.y,
+                    width,
+                    ROW_HEIGHT
+                ));
+            }
+            x += width;
+        }
+        let mut x = MARGIN;
+        for (text, style) in runs {
+            let width = text.chars().count() as f32 * CHAR_WIDTH;
+            write_text(content, x, baseline, FONT_SIZE, text, *style);
+            if let Some(underline) = style.underline {
+                content.push_str(&format!(
+                    "{} RG 0.6 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+                    pdf_color(underline),
+                    x,
+                    baseline - 1.5,
+                    x + width,
+                    baseline - 1.5
+                ));
+            }
+            x += width;
+        }
+        Ok(())
+    }
+
+    /// Write a heading, after a blank row unless it starts a page.
+    pub fn heading(&mut self, text: &str) -> io::Result<()> {
+        self.make_room(3.0 * ROW_HEIGHT)?;
+        if self.y < PAGE_HEIGHT - MARGIN - 2.0 * ROW_HEIGHT {
+            self.y -= ROW_HEIGHT;
+        }
+        self.row(&[(text.to_owned(), Style::bold(BLACK))])
+    }
+
+    /// Write the last page, and the table of where each object is that ends the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.pages.is_empty() && self.content.is_none() {
+            // a PDF has at least one page
+            self.make_room(0.0)?;
+        }
+        self.finish_page()?;
+        let kids: Vec<String> = self.pages.iter().map(|p| format!("{} 0 R", p)).collect();
+        self.object(
+            PAGES,
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.pages.len()
+            )
+            .as_bytes(),
+        )?;
+
+        let xref = self.written;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            CATALOG,
+            xref
+        ));
+        self.write(table.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+/// A colour as the three operands of a PDF colour operator.
+fn pdf_color((r, g, b): (u8, u8, u8)) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0
+    )
+}
+
+/// Add `text` to `content`, starting at `x` on the baseline `y`.
+fn write_text(content: &mut String, x: f32, y: f32, size: f32, text: &str, style: Style) {
+    content.push_str(&format!(
+        "BT /{} {} Tf {} rg {:.2} {:.2} Td ({}) Tj ET\n",
+        if style.bold { "F2" } else { "F1" },
+        size,
+        pdf_color(style.color),
+        x,
+        y,
+        pdf_string(text)
+    ));
+}
+
+/// `text` as the inside of a PDF string in the fonts' encoding, WinAnsiEncoding. Each character is one glyph, so
+/// columns stay lined up; those the encoding doesn't have are shown as `?`.
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        let byte = match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u32 as u8,
+            _ => win_ansi(c).unwrap_or(b'?'),
+        };
+        match byte {
+            b'(' | b')' | b'\\' => {
+                escaped.push('\\');
+                escaped.push(byte as char);
+            }
+            0x20..=0x7e => escaped.push(byte as char),
+            // kept out of the content stream as they are, so it's all ASCII
+            _ => escaped.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    escaped
+}
+
+/// The byte WinAnsiEncoding gives `c` between 128 and 159, where it differs from Latin-1.
+fn win_ansi(c: char) -> Option<u8> {
+    const HIGH: [(char, u8); 27] = [
+        ('€', 0x80),
+        ('‚', 0x82),
+        ('ƒ', 0x83),
+        ('„', 0x84),
+        ('…', 0x85),
+        ('†', 0x86),
+        ('‡', 0x87),
+        ('ˆ', 0x88),
+        ('‰', 0x89),
+        ('Š', 0x8a),
+        ('‹', 0x8b),
+        ('Œ', 0x8c),
+        ('Ž', 0x8e),
+        ('‘', 0x91),
+        ('’', 0x92),
+        ('“', 0x93),
+        ('”', 0x94),
+        ('•', 0x95),
+        ('–', 0x96),
+        ('—', 0x97),
+        ('˜', 0x98),
+        ('™', 0x99),
+        ('š', 0x9a),
+        ('›', 0x9b),
+        ('œ', 0x9c),
+        ('ž', 0x9e),
+        ('Ÿ', 0x9f),
+    ];
+    HIGH.iter().find(|(h, _)| *h == c).map(|(_, byte)| *byte)
+}
+
+/// Start the PDF of the report, titled after the input.
+pub fn start_pdf(out: Box<dyn Write>, args: &Args) -> anyhow::Result<Pdf> {
+    Pdf::new(
+        out,
+        &args.locale.format(Message::Title, &[&input_name(args)]),
+        args.locale,
+    )
+    .context("Failed to write PDF.")
+}
+
+/// The report as a PDF, for `--output-format pdf`: each line numbered, with its fields in the colours the HTML report
+/// gives them, then the legend if `--legend` puts it after the lines.
+pub struct PdfReport<'a> {
+    pdf: Pdf,
+    records: &'a RecordList,
+    positions: PositionOptions,
+    render: Renderer,
+    legend: Option<Legend>,
+    locale: Locale,
+}
+
+impl<'a> PdfReport<'a> {
+    /// Carry on writing `pdf`, started with [`start_pdf`], with the lines of the report.
+    pub fn new(
+        pdf: Pdf,
+        args: &Args,
+        records: &'a RecordList,
+        positions: PositionOptions,
+    ) -> anyhow::Result<Self> {
+        let mut render = render_options(args)?;
+        render.record_gutter = record_gutter(args, records);
+        Ok(Self {
+            pdf,
+            records,
+            positions,
+            render,
+            legend: (args.legend == Some(LegendPosition::Bottom)).then(Legend::default),
+            locale: args.locale,
+        })
+    }
+}
+
+impl LineReport for PdfReport<'_> {
+    fn shows_lines(&self) -> bool {
+        true
+    }
+
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        let prepared = line.prepared;
+        write_pdf_line(
+            &mut self.pdf,
+            line.index,
+            prepared,
+            &self.render,
+            self.locale,
+        )
+        .context("Failed to write PDF.")?;
+        for failed in line.failed_checks {
+            write_failed_check(&mut self.pdf, failed, self.locale)
+                .context("Failed to write PDF.")?;
+        }
+        if let Some(legend) = &mut self.legend {
+            let record_type = prepared.record_type.clone();
+            legend.add_line(
+                self.records,
+                self.positions,
+                &self.render,
+                record_type,
+                prepared,
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        if let Some(legend) = &self.legend {
+            legend
+                .write_pdf(&mut self.pdf, self.records, self.locale)
+                .context("Failed to write PDF.")?;
+        }
+        self.pdf.finish().context("Failed to write PDF.")
+    }
+}
+
+/// Write one line as rows of the PDF: a row for each line a record was joined from, each wrapped onto further rows if
+/// it's wider than the page. Where fields overlap, the one starting last is shown, as in the terminal.
+fn write_pdf_line(
+    pdf: &mut Pdf,
+    line_index: usize,
+    prepared: &PreparedLine,
+    render: &Renderer,
+    locale: Locale,
+) -> io::Result<()> {
+    let regions = &prepared.regions;
+    let colors: Vec<Option<(u8, u8, u8)>> = render
+        .field_colors(&prepared.text, regions)
+        .into_iter()
+        .map(|c| c.and_then(parse_hex_color))
+        .collect();
+    let error_color = render.error_color.as_deref().and_then(parse_hex_color);
+    let length = prepared.text.chars().count();
+    let chars: Vec<char> = prepared.text.chars().collect();
+    let value_styles = value_styles(prepared);
+
+    // each physical line's characters, with how they're drawn
+    let mut rows: Vec<Vec<(char, Style)>> = vec![Vec::new()];
+    for segment in segments(prepared, render.trailing_data) {
+        while rows.len() <= segment.row {
+            rows.push(Vec::new());
+        }
+        let mut style = match segment.kind {
+            SegmentKind::Ghost => Style::plain(FAINT),
+            SegmentKind::Trailing => Style {
+                background: Some(TRAILING),
+                ..Style::plain(VIOLATION)
+            },
+            SegmentKind::Delimiter => Style::bold(MUTED),
+            _ if prepared.skipped => Style::plain(if render.dim_skipped { FAINT } else { BLACK }),
+            SegmentKind::Field | SegmentKind::Violation | SegmentKind::Gap => {
+                match segment.innermost().map(|i| colors[i]) {
+                    Some(Some(background)) => Style {
+                        background: Some(background),
+                        ..Style::plain(
+                            parse_hex_color(foreground_color(&format!(
+                                "{:02x}{:02x}{:02x}",
+                                background.0, background.1, background.2
+                            )))
+                            .unwrap_or(BLACK),
+                        )
+                    },
+                    Some(None) => Style::plain(BLACK),
+                    None => Style::plain(error_color.unwrap_or(BLACK)),
+                }
+            }
+        };
+        // a field whose value matches its value pattern is drawn in its value style, which can't be italic here
+        let value_style = match segment.kind {
+            SegmentKind::Field | SegmentKind::Violation if !prepared.skipped => {
+                segment.innermost().and_then(|i| value_styles[i])
+            }
+            _ => None,
+        };
+        if let Some(value_style) = value_style {
+            if let Some(color) = value_style.color.as_deref().and_then(parse_hex_color) {
+                style.color = color;
+            }
+            style.bold |= value_style.bold;
+            if value_style.underline {
+                style.underline = Some(style.color);
+            }
+        }
+        for col in segment.chars.clone() {
+            let chr = chars[col];
+            let (mut shown, mut style) = (chr, style);
+            if segment.kind == SegmentKind::Ghost && chr == ' ' {
+                shown = '·';
+            }
+            if bidi_control_name(chr).is_some() {
+                // shown rather than reordering the line
+                shown = '¤';
+                style.color = VIOLATION;
+            } else if render.check_unicode && suspicious::in_field(regions, col, chr).is_some() {
+                style.underline = Some(SUSPICIOUS);
+            } else if segment.kind == SegmentKind::Violation {
+                style.underline = Some(VIOLATION);
+            }
+            if let Some(padding_shown) = render.padding_shown(regions, col, chr) {
+                // the fonts have no arrow, so a tab is shown as a guillemet
+                shown = if padding_shown == '→' {
+                    '»'
+                } else {
+                    padding_shown
+                };
+                style.color = FAINT;
+            }
+            rows[segment.row].push((shown, style));
+        }
+    }
+    while rows.len() <= prepared.breaks.len() {
+        rows.push(Vec::new());
+    }
+    let last = rows.last_mut().expect("there is a first row");
+    last.extend(" <".chars().map(|c| (c, Style::plain(MUTED))));
+    if regions.iter().any(|r| r.end > length) {
+        let note = format!(" {}", locale.text(Message::RegionsBeyondLine));
+        last.extend(note.chars().map(|c| (c, Style::plain(VIOLATION))));
+    }
+
+    for (row, cells) in rows.iter().enumerate() {
+        let prefix = if row == 0 {
+            render.gutter(line_index + 1, prepared.record_type.as_deref(), '>')
+        } else {
+            render.gutter(line_index + 1 + row, None, '+')
+        };
+        let width = COLUMNS.saturating_sub(prefix.chars().count()).max(1);
+        let mut chunks = cells.chunks(width);
+        let first = chunks.next().unwrap_or(&[]);
+        pdf.row(&runs(&prefix, first))?;
+        // rows wrapped from a row too wide for the page are indented under it
+        let indent = " ".repeat(prefix.chars().count());
+        for chunk in chunks {
+            pdf.row(&runs(&indent, chunk))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the note under a line that fails a check, such as a trailer's control total that doesn't match the records
+/// before it, wrapped onto further rows if it's wider than the page.
+fn write_failed_check(pdf: &mut Pdf, failed: &CheckResult, locale: Locale) -> io::Result<()> {
+    let prefix = "     ! ";
+    let width = COLUMNS.saturating_sub(prefix.chars().count()).max(1);
+    let note: Vec<(char, Style)> = failed
+        .describe(locale)
+        .chars()
+        .map(|c| (c, Style::plain(VIOLATION)))
+        .collect();
+    for (row, chunk) in note.chunks(width).enumerate() {
+        let prefix = if row == 0 { prefix } else { "       " };
+        pdf.row(&runs(prefix, chunk))?;
+    }
+    Ok(())
+}
+
+/// The runs of text a row of `cells` is drawn in, after its `prefix`.
+fn runs(prefix: &str, cells: &[(char, Style)]) -> Vec<(String, Style)> {
+    let mut runs = vec![(prefix.to_owned(), Style::plain(MUTED))];
+    for &(chr, style) in cells {
+        match runs.last_mut() {
+            Some((text, last)) if *last == style => text.push(chr),
+            _ => runs.push((chr.to_string(), style)),
+        }
+    }
+    runs
+}
@@ -0,0 +1,209 @@
+//! Decoder plugins: programs that decode fields no other `type` can, such as bit flags or a vendor's own date encoding,
+//! from the `plugin` type of a syntax file, e.g. `plugin(./decoders/flags.py)`.
+//!
+//! A plugin is started when the first of its fields is decoded, and kept running for the rest of the run. It's sent the
+//! value of each field to decode as a line of its standard input, with backslashes, carriage returns and line feeds
+//! escaped as `\\`, `\r` and `\n`, and must answer each with a line of its standard output, in turn: `ok`, a tab and
+//! the value to show, or `invalid`, a tab and why the value isn't valid.
+//!
+//! A plugin is a program run with the same permissions as the highlighter, not a sandboxed script, so naming one in a
+//! syntax file isn't enough to run it: each must first be allowed with [`allow`], which the command line does for those
+//! of the syntax file given `--allow-plugins`. It runs in the directory of the syntax file naming it, and a program
+//! given by its path, rather than found on the `PATH` by its name, is relative to that directory too.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::Duration,
+};
+
+use log::error;
+
+/// The most answers remembered, so values repeated down a file aren't sent again, before they're forgotten.
+const CACHE_SIZE: usize = 10_000;
+
+/// The plugins allowed to run, with the directory each runs in and how long it has to answer each value.
+static ALLOWED: Mutex<Vec<Allowed>> = Mutex::new(Vec::new());
+/// Each plugin started so far by its command, or why it couldn't be started or stopped answering. Each is locked by
+/// itself, so waiting for one to answer doesn't hold up threads decoding the fields of the others.
+static RUNNING: Mutex<Vec<(String, SharedPlugin)>> = Mutex::new(Vec::new());
+/// The answer to each command and value.
+static ANSWERS: Mutex<BTreeMap<(String, String), Result<String, String>>> =
+    Mutex::new(BTreeMap::new());
+
+/// A plugin that's been started, or why it couldn't be or stopped answering, shared by the threads decoding its fields.
+type SharedPlugin = Arc<Mutex<Result<Plugin, String>>>;
+
+/// A plugin allowed to run.
+struct Allowed {
+    command: String,
+    dir: PathBuf,
+    timeout: Duration,
+}
+
+/// Allow the plugin `command`, named by a syntax file in `dir`, to be started, running it in `dir`. It's given
+/// `timeout` to answer each value, after which it's stopped and every value of its fields is invalid.
+pub fn allow(command: &str, dir: &Path, timeout: Duration) {
+    let mut allowed = lock(&ALLOWED);
+    allowed.retain(|a| a.command != command);
+    allowed.push(Allowed {
+        command: command.to_owned(),
+        dir: dir.to_owned(),
+        timeout,
+    });
+}
+
+/// Lock `mutex`, carrying on with what it holds if a thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A running plugin.
+struct Plugin {
+    child: Child,
+    stdin: ChildStdin,
+    /// Each line the plugin answers with, read by a thread of its own so an answer can be waited for no longer than
+    /// `timeout`.
+    answers: Receiver<io::Result<String>>,
+    timeout: Duration,
+}
+
+impl Plugin {
+    /// Start the plugin `allowed`, whose command is the program and its arguments separated by spaces.
+    fn start(allowed: &Allowed) -> io::Result<Plugin> {
+        let mut words = allowed.command.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        // a program named without a path is found on the `PATH`, as a shell would
+        let program = if program.contains(['/', '\\']) {
+            allowed.dir.join(program)
+        } else {
+            PathBuf::from(program)
+        };
+        let mut child = Command::new(program)
+            .args(words)
+            .current_dir(&allowed.dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("piped");
+        let stdout = BufReader::new(child.stdout.take().expect("piped"));
+        let (sender, answers) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let failed = line.is_err();
+                if sender.send(line).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Plugin {
+            child,
+            stdin,
+            answers,
+            timeout: allowed.timeout,
+        })
+    }
+
+    /// Send `value` to the plugin, returning the value to show, or why it isn't valid, as the plugin answers.
+    /// A plugin that can't be sent the value or doesn't answer properly, or in time, fails with why.
+    fn decode(&mut self, value: &str) -> Result<Result<String, String>, String> {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n");
+        writeln!(self.stdin, "{}", escaped)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("it couldn't be sent a value: {}", e))?;
+        let answer = match self.answers.recv_timeout(self.timeout) {
+            Ok(Ok(answer)) => answer,
+            Ok(Err(e)) => return Err(format!("its answer couldn't be read: {}", e)),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err("it stopped without answering".to_owned())
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // it's no use any more, and may be stuck for good
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                return Err(format!(
+                    "it didn't answer within {} second(s)",
+                    self.timeout.as_secs_f64()
+                ));
+            }
+        };
+        let answer = answer.trim_end_matches('\r');
+        match answer.split_once('\t') {
+            Some(("ok", shown)) => Ok(Ok(shown.to_owned())),
+            Some(("invalid", reason)) => Ok(Err(reason.to_owned())),
+            _ => Err(format!(
+                "it answered `{}`, rather than `ok` or `invalid`, a tab and the value or why it isn't valid",
+                answer
+            )),
+        }
+    }
+}
+
+/// The plugin `command`, starting it if it isn't running, or why it can't be used.
+fn running(command: &str) -> SharedPlugin {
+    let mut running = lock(&RUNNING);
+    if let Some((_, plugin)) = running.iter().find(|(c, _)| c == command) {
+        return plugin.clone();
+    }
+    let plugin = match lock(&ALLOWED).iter().find(|a| a.command == command) {
+        Some(allowed) => Plugin::start(allowed).map_err(|e| {
+            error!("Failed to start plugin `{}`: {}.", command, e);
+            format!("the plugin `{}` couldn't be started", command)
+        }),
+        None => {
+            error!(
+                "Plugin `{}` wasn't started, as plugins haven't been allowed to run.",
+                command
+            );
+            Err(format!("the plugin `{}` isn't allowed to run", command))
+        }
+    };
+    let plugin = Arc::new(Mutex::new(plugin));
+    running.push((command.to_owned(), plugin.clone()));
+    plugin
+}
+
+/// Decode `value` with the plugin `command`, starting it if it isn't running, returning the value to show, or why it
+/// isn't valid. A plugin that isn't allowed, can't be started or doesn't answer properly is logged, once, and every value
+/// it's given is then invalid.
+pub fn decode(command: &str, value: &str) -> Result<String, String> {
+    let key = (command.to_owned(), value.to_owned());
+    if let Some(answer) = lock(&ANSWERS).get(&key) {
+        return answer.clone();
+    }
+
+    let plugin = running(command);
+    let answer = {
+        let mut plugin = lock(&plugin);
+        match &mut *plugin {
+            Err(reason) => Err(reason.clone()),
+            Ok(running) => match running.decode(value) {
+                Ok(answer) => answer,
+                Err(e) => {
+                    error!("Plugin `{}` failed, as {}.", command, e);
+                    let reason = format!("the plugin `{}` failed", command);
+                    *plugin = Err(reason.clone());
+                    Err(reason)
+                }
+            },
+        }
+    };
+    let mut answers = lock(&ANSWERS);
+    if answers.len() >= CACHE_SIZE {
+        answers.clear();
+    }
+    answers.insert(key, answer.clone());
+    answer
+}
@@ -0,0 +1,415 @@
+//! Problems found with the input as it's highlighted, which decide the exit status of a run and are written as JSON by
+//! `--error-report` for CI pipelines to pick up.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::Context;
+use log::{error, warn};
+
+use crate::{
+    constraints, json_string, suspicious, usage::RuleUsage, Args, CheckResult, ColumnMode, Columns,
+    LineProblem, OutputFormat, PositionOptions, PreparedLine,
+};
+
+/// The exit status of a run that finished, but found problems with its input.
+pub const EXIT_PROBLEMS: u8 = 1;
+/// The exit status of a run that couldn't finish.
+pub const EXIT_FATAL: u8 = 2;
+
+/// The error a run ends with when it finished, but found problems with its input, so exits with [`EXIT_PROBLEMS`]
+/// rather than [`EXIT_FATAL`].
+#[derive(Debug)]
+pub struct ProblemsFound(pub String);
+
+impl fmt::Display for ProblemsFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ProblemsFound {}
+
+/// How text after the end of a line's last field, which the layout doesn't expect, is treated.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingData {
+    /// An error, which fails the run
+    Error,
+    /// A warning
+    #[default]
+    Warning,
+    /// Expected, so neither marked nor reported
+    Ignore,
+}
+
+/// The number of problems found over a run.
+#[derive(Debug, Default, Clone)]
+pub struct ProblemCounts {
+    pub errors: u64,
+    pub warnings: u64,
+    /// The number of problems of each kind, such as `constraint`, errors and warnings alike.
+    pub kinds: BTreeMap<String, u64>,
+}
+
+impl ProblemCounts {
+    /// Add the problems found by another run, such as that of another file of a batch.
+    pub fn add(&mut self, other: &ProblemCounts) {
+        self.errors += other.errors;
+        self.warnings += other.warnings;
+        for (kind, count) in &other.kinds {
+            *self.kinds.entry(kind.clone()).or_default() += count;
+        }
+    }
+
+    /// Fail with [`ProblemsFound`] if any problem found is an error.
+    pub fn check(self) -> anyhow::Result<()> {
+        if self.errors > 0 {
+            return Err(ProblemsFound(format!(
+                "Found {} error(s) and {} warning(s) in the input.",
+                self.errors, self.warnings
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// The problems found over a run: errors, which fail it, and warnings, which don't.
+pub struct Problems {
+    /// Where `--error-report` writes each problem as it's found.
+    report: Option<BufWriter<File>>,
+    index_base: usize,
+    /// What the columns of the syntax file count, which positions in the line are given in.
+    column_mode: ColumnMode,
+    check_unicode: bool,
+    trailing_data: TrailingData,
+    /// Fields past the end of their line, broken constraints, suspicious characters, and lines with trailing data if
+    /// it's an error.
+    errors: u64,
+    /// Lines with text no rule covers, rules that never applied, and lines with trailing data if it's a warning.
+    warnings: u64,
+    /// The errors and warnings of each kind.
+    kinds: BTreeMap<String, u64>,
+    written: u64,
+    /// The problems found with the line being counted, for `--output-format jsonl` and `xlsx` to write with it.
+    line_problems: Option<Vec<LineProblem>>,
+}
+
+impl Problems {
+    pub fn new(args: &Args, positions: PositionOptions) -> anyhow::Result<Self> {
+        let report = match &args.error_report {
+            Some(path) => {
+                let mut report =
+                    BufWriter::new(File::create(path).context("Failed to create error report.")?);
+                write!(
+                    report,
+                    "{{\n  \"version\": 1,\n  \"input_file\": {},\n  \"syntax_file\": {},\n  \"problems\": [",
+                    json_string(args.input_file.as_deref().unwrap_or_default()),
+                    json_string(args.syntax_file.as_deref().unwrap_or_default())
+                )
+                .context("Failed to write error report.")?;
+                Some(report)
+            }
+            None => None,
+        };
+        Ok(Self {
+            report,
+            index_base: positions.index_base,
+            column_mode: positions.column_mode,
+            check_unicode: args.check_unicode,
+            trailing_data: args.trailing_data,
+            errors: 0,
+            warnings: 0,
+            kinds: BTreeMap::new(),
+            written: 0,
+            line_problems: matches!(args.output_format, OutputFormat::Jsonl | OutputFormat::Xlsx)
+                .then(Vec::new),
+        })
+    }
+
+    /// Find the problems with `prepared`, the `line_index`th (from 0) line of the input. Broken constraints and
+    /// suspicious characters are counted by [`Problems::count_findings`] as they're logged, so are only written here.
+    pub fn count_line(&mut self, line_index: usize, prepared: &PreparedLine) -> anyhow::Result<()> {
+        let length = prepared
+            .ghost_from
+            .unwrap_or_else(|| prepared.text.chars().count());
+        // positions are given as the syntax file gives them, in bytes or graphemes if they're what its columns count
+        let columns = Columns::new(&prepared.text, self.column_mode);
+        let index_base = self.index_base;
+        let column = |col: usize| columns.column_of(col) + index_base;
+
+        for r in &prepared.regions {
+            // empty fields have nothing to highlight, but are still present
+            let empty = r.start == r.end && r.start <= length;
+            let applied = r.start < r.end && r.end <= length;
+            if !applied && !empty {
+                self.count("field_past_end", 1);
+                self.write(
+                    Some(line_index),
+                    "field_past_end",
+                    Some(&r.name),
+                    None,
+                    "The line ends before the field does.",
+                )?;
+            }
+        }
+
+        // trailing data is reported by itself, rather than as uncovered text
+        let trailing_from = prepared.trailing_from();
+        let covered_to = trailing_from.unwrap_or(length);
+        let mut uncovered = Vec::new();
+        let mut col = 0;
+        while col < covered_to {
+            if prepared.is_covered(col) {
+                col += 1;
+                continue;
+            }
+            let from = col;
+            while col < covered_to && !prepared.is_covered(col) {
+                col += 1;
+            }
+            uncovered.push((from, col));
+        }
+        if !uncovered.is_empty() {
+            self.count("uncovered", 1);
+        }
+        let trailing = trailing_from
+            .filter(|_| self.trailing_data != TrailingData::Ignore)
+            .map(|from| {
+                let value: String = prepared.text.chars().skip(from).collect();
+                let message = format!(
+                    "Unexpected trailing data from column {}, after the last field.",
+                    column(from)
+                );
+                (value, message)
+            });
+        if let Some((value, message)) = &trailing {
+            let logged = format!(
+                "Line {}: unexpected trailing data '{}' after the last field.",
+                line_index + 1,
+                value
+            );
+            if self.trailing_data == TrailingData::Error {
+                error!("{}", logged);
+            } else {
+                warn!("{}", logged);
+            }
+            self.count("trailing_data", 1);
+            self.write(
+                Some(line_index),
+                "trailing_data",
+                None,
+                Some(value),
+                message,
+            )?;
+        }
+        if self.report.is_none() && self.line_problems.is_none() {
+            return Ok(());
+        }
+        for (from, to) in uncovered {
+            let value: String = prepared.text.chars().skip(from).take(to - from).collect();
+            let message = if to == from + 1 {
+                format!("Column {} isn't covered by any rule.", column(from))
+            } else {
+                format!(
+                    "Columns {}-{} aren't covered by any rule.",
+                    column(from),
+                    column(to) - 1
+                )
+            };
+            self.write(Some(line_index), "uncovered", None, Some(&value), &message)?;
+        }
+
+        for violation in
+            constraints::violations(&prepared.text, &prepared.regions, prepared.ghost_from)
+        {
+            let message = format!(
+                "The value doesn't match the field's {}.",
+                violation.expected
+            );
+            self.write(
+                Some(line_index),
+                "constraint",
+                Some(&violation.field),
+                Some(&violation.value),
+                &message,
+            )?;
+        }
+
+        if self.check_unicode {
+            for (col, c) in prepared.text.chars().enumerate().take(length) {
+                if let Some((name, suspicion)) = suspicious::in_field(&prepared.regions, col, c) {
+                    let field = prepared
+                        .regions
+                        .iter()
+                        .find(|r| r.start <= col && col < r.end);
+                    let message = format!(
+                        "Column {} contains {}.",
+                        column(col),
+                        suspicious::describe(c, name, suspicion)
+                    );
+                    self.write(
+                        Some(line_index),
+                        "suspicious_character",
+                        field.map(|r| &*r.name),
+                        None,
+                        &message,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Count the checks of the `line_index`th (from 0) line that failed, such as the control totals of a trailer,
+    /// writing each.
+    pub fn count_checks(
+        &mut self,
+        line_index: usize,
+        checks: &[CheckResult],
+    ) -> anyhow::Result<()> {
+        for failed in checks.iter().filter(|c| !c.passed) {
+            self.count(failed.check.problem_kind(), 1);
+            let message = format!("{}.", failed.describe(crate::Locale::En));
+            self.write(
+                Some(line_index),
+                failed.check.problem_kind(),
+                Some(&failed.field),
+                Some(&failed.value),
+                &message,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Count the `line_index`th (from 0) line, which doesn't end as `--line-ending` says, writing why.
+    pub fn count_line_ending(&mut self, line_index: usize, message: &str) -> anyhow::Result<()> {
+        self.count("line_ending", 1);
+        self.write(Some(line_index), "line_ending", None, None, message)
+    }
+
+    /// The number of problems found so far, errors and warnings.
+    pub fn found(&self) -> u64 {
+        self.errors + self.warnings
+    }
+
+    /// Count the broken constraints and suspicious characters logged for a line.
+    pub fn count_findings(&mut self, constraint_violations: usize, suspicious_characters: usize) {
+        self.count("constraint", constraint_violations as u64);
+        self.count("suspicious_character", suspicious_characters as u64);
+    }
+
+    /// The number of problems of each kind found so far, for a `--resume` checkpoint.
+    pub fn kinds(&self) -> &BTreeMap<String, u64> {
+        &self.kinds
+    }
+
+    /// Carry on counting from the problems of each kind found before a `--resume` checkpoint, as
+    /// [`Problems::kinds`] gave them.
+    pub fn restore(&mut self, kinds: &[(String, u64)]) {
+        for (kind, found) in kinds {
+            self.count(kind, *found);
+        }
+    }
+
+    /// Count `found` problems of the kind `kind`, as errors or warnings as [`Problems::severity`] says.
+    fn count(&mut self, kind: &str, found: u64) {
+        if found == 0 {
+            return;
+        }
+        if self.severity(kind) == "error" {
+            self.errors += found;
+        } else {
+            self.warnings += found;
+        }
+        match self.kinds.get_mut(kind) {
+            Some(count) => *count += found,
+            None => {
+                self.kinds.insert(kind.to_owned(), found);
+            }
+        }
+    }
+
+    /// `error` or `warning`: whether a problem of the kind `kind` fails the run.
+    fn severity(&self, kind: &str) -> &'static str {
+        // a syntax file needn't describe every column, and a run that leaves out lines, such as with `--head` or
+        // `--filter`, can leave rules unused without anything being wrong with the input
+        if kind == "uncovered"
+            || kind == "unused_rule"
+            || kind == "trailing_data" && self.trailing_data == TrailingData::Warning
+        {
+            "warning"
+        } else {
+            "error"
+        }
+    }
+
+    /// The problems found with the line counted last, leaving none for the next line.
+    pub fn take_line_problems(&mut self) -> Vec<LineProblem> {
+        self.line_problems
+            .as_mut()
+            .map_or(Vec::new(), std::mem::take)
+    }
+
+    /// Write a problem to the error report, if there is one, and keep it for `--output-format jsonl` and `xlsx`.
+    /// Problems with the whole run, rather than one line of it, have no `line_index`.
+    fn write(
+        &mut self,
+        line_index: Option<usize>,
+        kind: &str,
+        field: Option<&str>,
+        value: Option<&str>,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        let problem = LineProblem {
+            kind: kind.to_owned(),
+            severity: self.severity(kind),
+            field: field.map(str::to_owned),
+            value: value.map(str::to_owned),
+            message: message.to_owned(),
+        };
+        if let Some(report) = &mut self.report {
+            write!(
+                report,
+                "{}\n    {{\"line\":{},{}}}",
+                if self.written == 0 { "" } else { "," },
+                line_index.map_or("null".to_owned(), |i| (i + 1).to_string()),
+                problem.json_members()
+            )
+            .context("Failed to write error report.")?;
+            self.written += 1;
+        }
+        if let (Some(problems), Some(_)) = (&mut self.line_problems, line_index) {
+            problems.push(problem);
+        }
+        Ok(())
+    }
+
+    /// Count the rules that never applied and finish the error report, giving the number of problems found.
+    pub fn finish(mut self, usage: &RuleUsage) -> anyhow::Result<ProblemCounts> {
+        for finding in usage.findings(crate::Locale::En) {
+            self.count("unused_rule", 1);
+            self.write(None, "unused_rule", None, None, &finding)?;
+        }
+        if let Some(mut report) = self.report.take() {
+            write!(
+                report,
+                "{}],\n  \"errors\": {},\n  \"warnings\": {}\n}}\n",
+                if self.written == 0 { "" } else { "\n  " },
+                self.errors,
+                self.warnings
+            )
+            .and_then(|_| report.flush())
+            .context("Failed to write error report.")?;
+        }
+        Ok(ProblemCounts {
+            errors: self.errors,
+            warnings: self.warnings,
+            kinds: self.kinds,
+        })
+    }
+}
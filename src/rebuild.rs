@@ -0,0 +1,599 @@
+//! The `rebuild` subcommand, which writes lines back from the values of their fields, as `--output-format csv`, `json`
+//! or `jsonl` extracts them, so a bad field of a rejected file can be fixed in the extraction and the file rebuilt.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    iter::Peekable,
+    str::Chars,
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    generate_highlight_regions_from_records, load_syntax, mask, open_input, region_value, Args,
+    Delimiter, HighlightRegion, Justify, PositionOptions, RebuildArgs, RecordList, MASK,
+};
+
+/// The most times a line is placed again, when placing its fields changes which rules apply to it, before its fields
+/// are taken to be where they'll stay.
+const MAX_PASSES: usize = 8;
+
+/// A line's fields, as they were extracted and maybe edited since.
+struct Extracted {
+    /// The line's number, counting from 1, for messages.
+    line: usize,
+    record_type: Option<String>,
+    fields: Vec<Value>,
+}
+
+/// A field of an extracted line.
+struct Value {
+    name: String,
+    value: String,
+    /// The 0-based start column and length of the field when it was extracted, if the extraction says.
+    columns: Option<(usize, usize)>,
+}
+
+/// Read the lines of a CSV extraction: rows of `line,record_type,field,value`, with a line's fields in consecutive rows.
+fn read_csv(text: &str) -> anyhow::Result<Vec<Extracted>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(line_column), Some(field_column), Some(value_column)) =
+        (column("line"), column("field"), column("value"))
+    else {
+        bail!("The extracted fields should be a CSV table with the columns `line`, `field` and `value`, as `--output-format csv` writes, or JSON.");
+    };
+    let type_column = column("record_type");
+
+    let mut lines: Vec<Extracted> = Vec::new();
+    for result in reader.records() {
+        let row = result.context("Failed to parse extracted fields.")?;
+        let get = |index: usize| row.get(index).unwrap_or_default();
+        let line = get(line_column).trim().parse().with_context(|| {
+            format!(
+                "Extracted fields row {}: `{}` is not a line number.",
+                row.position().map_or(0, |p| p.line()),
+                get(line_column)
+            )
+        })?;
+        let value = Value {
+            name: get(field_column).to_owned(),
+            value: get(value_column).to_owned(),
+            columns: None,
+        };
+        match lines.last_mut() {
+            Some(last) if last.line == line => last.fields.push(value),
+            _ => lines.push(Extracted {
+                line,
+                record_type: type_column
+                    .map(get)
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_owned),
+                fields: vec![value],
+            }),
+        }
+    }
+    Ok(lines)
+}
+
+/// Read the lines of a JSON extraction, one on each line: an array of `{name, start, length, value}` objects, or an
+/// object with that array as its `fields`, and maybe its `line` and `record_type`.
+fn read_json(text: &str) -> anyhow::Result<Vec<Extracted>> {
+    let mut lines = Vec::new();
+    for (index, text) in text.lines().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let json = parse_json(text).map_err(|e| {
+            anyhow::anyhow!(
+                "Extracted fields line {} isn't valid JSON: {}.",
+                index + 1,
+                e
+            )
+        })?;
+        let fields = match &json {
+            Json::Array(fields) => fields,
+            Json::Object(_) => match json.get("fields") {
+                Some(Json::Array(fields)) => fields,
+                _ => bail!(
+                    "Extracted fields line {} has no `fields` array.",
+                    index + 1
+                ),
+            },
+            _ => bail!(
+                "Extracted fields line {} should be an array of fields, or an object with one as its `fields`.",
+                index + 1
+            ),
+        };
+        let line = json
+            .get("line")
+            .and_then(Json::as_usize)
+            .unwrap_or(lines.len() + 1);
+        let mut values = Vec::new();
+        for field in fields {
+            let (Some(name), Some(value)) = (
+                field.get("name").and_then(Json::as_str),
+                field.get("value").and_then(Json::as_str),
+            ) else {
+                bail!(
+                    "Extracted fields line {}: every field should have a `name` and a `value`.",
+                    index + 1
+                );
+            };
+            let start = field.get("start").and_then(Json::as_usize);
+            let length = field.get("length").and_then(Json::as_usize);
+            values.push(Value {
+                name: name.to_owned(),
+                value: value.to_owned(),
+                // starts are written counting from 1
+                columns: start
+                    .zip(length)
+                    .filter(|(start, _)| *start > 0)
+                    .map(|(start, length)| (start - 1, length)),
+            });
+        }
+        lines.push(Extracted {
+            line,
+            record_type: json
+                .get("record_type")
+                .and_then(Json::as_str)
+                .map(str::to_owned),
+            fields: values,
+        });
+    }
+    Ok(lines)
+}
+
+/// `value` written into a field `length` characters long: against the end its `justify` says, and padded at the other
+/// with its `pad`, or spaces. Any padding already around the value, as it was extracted, is dropped first, so an
+/// edited value needn't be padded by hand. `None` if the value doesn't fit.
+fn fit(value: &str, length: usize, justify: Option<Justify>, pad: Option<char>) -> Option<String> {
+    let pad_char = pad.unwrap_or(' ');
+    let content = match justify {
+        Some(Justify::Right) => value.trim_matches(' ').trim_start_matches(pad_char),
+        Some(Justify::Left) => value.trim_matches(' ').trim_end_matches(pad_char),
+        None if pad.is_some() => value.trim_matches(' ').trim_end_matches(pad_char),
+        // without a justification, leading spaces may be part of the value
+        None => value.trim_end_matches(' '),
+    };
+    let padding = length.checked_sub(content.chars().count())?;
+    let padding: String = std::iter::repeat_n(pad_char, padding).collect();
+    Some(match justify {
+        Some(Justify::Right) => padding + content,
+        _ => content.to_owned() + &padding,
+    })
+}
+
+/// [`fit`] `value` into `region`, failing if it's too long.
+fn fit_region(
+    extracted: &Extracted,
+    value: &Value,
+    region: &HighlightRegion,
+) -> anyhow::Result<String> {
+    let length = region.end - region.start;
+    match fit(&value.value, length, region.justify, region.pad) {
+        Some(fitted) => Ok(fitted),
+        None => bail!(
+            "Line {}: the value of '{}', `{}`, is too long for the field, which holds {} characters.",
+            extracted.line,
+            value.name,
+            value.value,
+            length
+        ),
+    }
+}
+
+/// Write `text` into `chars` from `start`, lengthening it with spaces if it's too short.
+fn write_at(chars: &mut Vec<char>, start: usize, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        if chars.len() <= start + i {
+            chars.resize(start + i + 1, ' ');
+        }
+        chars[start + i] = c;
+    }
+}
+
+/// Whether `value`, of a sensitive field, is masked, as `--redact` would have shown it.
+fn is_masked(value: &str) -> bool {
+    value.contains(MASK) && mask(value) == value
+}
+
+/// Whether `region` holds another of the fields given, which is narrower, as a group holds the fields within it.
+fn holds_another(region: &HighlightRegion, regions: &[&HighlightRegion]) -> bool {
+    regions.iter().any(|other| {
+        region.start <= other.start
+            && other.end <= region.end
+            && other.end - other.start < region.end - region.start
+    })
+}
+
+/// The regions of `line` of the fields `extracted` gives, in the order it gives them.
+fn given_regions<'a>(
+    extracted: &Extracted,
+    regions: &'a [HighlightRegion],
+) -> Vec<Option<&'a HighlightRegion>> {
+    extracted
+        .fields
+        .iter()
+        .map(|value| regions.iter().find(|r| *r.name == value.name))
+        .collect()
+}
+
+/// Rebuild a fixed width line. Each field is first placed where it was extracted from, or by the first of its rules
+/// for the line's record type, then the line is placed again, and its fields written where its rules put them, until
+/// they stay put. Wider fields are written first, so where fields overlap, such as a group and the fields within it,
+/// the narrower ones' values are kept.
+fn rebuild_fixed_width(
+    records: &RecordList,
+    positions: PositionOptions,
+    extracted: &Extracted,
+) -> anyhow::Result<String> {
+    let RecordList::FixedWidth(fw_records) = records else {
+        unreachable!("only called for fixed width syntax files");
+    };
+    let mut chars: Vec<char> = Vec::new();
+    for value in &extracted.fields {
+        let rule = fw_records
+            .iter()
+            .filter(|r| *r.name == value.name && !r.from_end)
+            .find(|r| {
+                r.record.as_ref().is_none_or(|t| {
+                    extracted
+                        .record_type
+                        .as_ref()
+                        .is_none_or(|name| t.name == *name)
+                })
+            });
+        let columns = value.columns.or_else(|| {
+            rule.and_then(|r| r.start.zip(r.length))
+                .filter(|(start, _)| *start >= positions.index_base)
+                .map(|(start, length)| (start - positions.index_base, length))
+        });
+        if let Some((start, length)) = columns {
+            let (justify, pad) = rule.map_or((None, None), |r| (r.justify, r.pad));
+            if let Some(fitted) = fit(&value.value, length, justify, pad) {
+                write_at(&mut chars, start, &fitted);
+            }
+        }
+    }
+
+    for _ in 0..MAX_PASSES {
+        let line: String = chars.iter().collect();
+        let regions = generate_highlight_regions_from_records(records, &line, positions);
+        let mut placed: Vec<(&Value, &HighlightRegion)> = extracted
+            .fields
+            .iter()
+            .zip(given_regions(extracted, &regions))
+            .filter_map(|(value, region)| region.map(|r| (value, r)))
+            .collect();
+        placed.sort_by_key(|(_, r)| std::cmp::Reverse(r.end - r.start));
+        let mut next = chars.clone();
+        for (value, region) in placed {
+            write_at(
+                &mut next,
+                region.start,
+                &fit_region(extracted, value, region)?,
+            );
+        }
+        if next == chars {
+            break;
+        }
+        chars = next;
+    }
+    Ok(chars.into_iter().collect())
+}
+
+/// Rebuild a delimited line, with each field in the place the first of its rules for the line's record type gives,
+/// quoting values that hold the delimiter or the quote character.
+fn rebuild_delimited(
+    records: &RecordList,
+    positions: PositionOptions,
+    extracted: &Extracted,
+) -> anyhow::Result<String> {
+    let RecordList::Delimiter(delimiter, d_records) = records else {
+        unreachable!("only called for delimiter mode syntax files");
+    };
+    let Delimiter::Text(delimiter) = delimiter else {
+        bail!("Lines can't be rebuilt with `--delimiter-regex`, as it doesn't say what to write between fields.");
+    };
+    let mut fields: Vec<String> = Vec::new();
+    for value in &extracted.fields {
+        let field = d_records
+            .iter()
+            .filter(|r| *r.name == value.name)
+            .find(|r| {
+                r.record.as_ref().is_none_or(|t| {
+                    extracted
+                        .record_type
+                        .as_ref()
+                        .is_none_or(|name| t.name == *name)
+                })
+            })
+            .and_then(|r| r.field)
+            .filter(|field| *field >= positions.index_base)
+            .map(|field| field - positions.index_base);
+        let Some(field) = field else {
+            bail!(
+                "Line {}: there is no rule numbering the field '{}'.",
+                extracted.line,
+                value.name
+            );
+        };
+        let text = match positions.quote {
+            Some(quote) if value.value.contains(delimiter.as_str()) || value.value.contains(quote) => {
+                let doubled = value.value.replace(quote, &format!("{0}{0}", quote));
+                format!("{0}{1}{0}", quote, doubled)
+            }
+            None if value.value.contains(delimiter.as_str()) => bail!(
+                "Line {}: the value of '{}' contains the delimiter '{}', which would split it. Give `--quote` to quote it.",
+                extracted.line,
+                value.name,
+                delimiter
+            ),
+            _ => value.value.clone(),
+        };
+        if fields.len() <= field {
+            fields.resize(field + 1, String::new());
+        }
+        fields[field] = text;
+    }
+    Ok(fields.join(delimiter))
+}
+
+/// Check `line`, rebuilt from `extracted`, reads back as it: that each field given is on it, with its value. A field
+/// holding narrower fields that were given too is only checked through them.
+fn check_rebuilt(
+    records: &RecordList,
+    positions: PositionOptions,
+    extracted: &Extracted,
+    line: &str,
+) -> anyhow::Result<()> {
+    let regions = generate_highlight_regions_from_records(records, line, positions);
+    let given = given_regions(extracted, &regions);
+    let placed: Vec<&HighlightRegion> = given.iter().flatten().copied().collect();
+    for (value, region) in extracted.fields.iter().zip(given) {
+        let Some(region) = region else {
+            bail!(
+                "Line {}: no rule places '{}' on the rebuilt line, so it can't be written. Check the line's other fields, which decide which rules apply to it.",
+                extracted.line,
+                value.name
+            );
+        };
+        if region.sensitive && is_masked(&value.value) {
+            bail!(
+                "Line {}: the value of '{}' is masked, as the field is sensitive, so the line can't be rebuilt from it.",
+                extracted.line,
+                value.name
+            );
+        }
+        if holds_another(region, &placed) {
+            continue;
+        }
+        let expected = match records {
+            RecordList::FixedWidth(_) => fit_region(extracted, value, region)?,
+            RecordList::Delimiter(..) => value.value.clone(),
+        };
+        let written = region_value(line, region, None);
+        if written != expected {
+            bail!(
+                "Line {}: the value of '{}' is `{}` on the rebuilt line rather than `{}`, as another field given overlaps it.",
+                extracted.line,
+                value.name,
+                written,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run the `rebuild` subcommand.
+pub fn run(args: &Args, rebuild: &RebuildArgs) -> anyhow::Result<()> {
+    let (_, records, positions, _) = load_syntax(args, &rebuild.syntax_file, None)?;
+    let mut text = String::new();
+    open_input(args, &rebuild.extracted_file)?
+        .read_to_string(&mut text)
+        .context("Failed to read extracted fields.")?;
+    let extracted = match text.trim_start().chars().next() {
+        Some('[' | '{') => read_json(&text)?,
+        _ => read_csv(&text)?,
+    };
+
+    let mut out: Box<dyn Write> = match &rebuild.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).context("Failed to create output file.")?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let names = records.field_names();
+    for extracted in &extracted {
+        if let Some(value) = extracted.fields.iter().find(|v| !names.contains(&v.name)) {
+            bail!(
+                "Line {}: there is no field '{}' in the syntax file.",
+                extracted.line,
+                value.name
+            );
+        }
+        let line = match records {
+            RecordList::FixedWidth(_) => rebuild_fixed_width(&records, positions, extracted)?,
+            RecordList::Delimiter(..) => rebuild_delimited(&records, positions, extracted)?,
+        };
+        check_rebuilt(&records, positions, extracted, &line)?;
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// A JSON value, as far as reading extracted fields needs: numbers are kept as they're written.
+enum Json {
+    /// `null`, `true` or `false`, none of which extracted fields are read from.
+    Literal,
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The value of `key`, if this is an object with it.
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(number) => number.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Parse `text` as one JSON value, or say why it isn't one.
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected `{}` after the value", c)),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    skip_whitespace(chars);
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("expected `{}` but found `{}`", expected, c)),
+        None => Err(format!("expected `{}` but the line ended", expected)),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some('{') => {
+            chars.next();
+            let mut members = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                if chars.peek() != Some(&'"') {
+                    return Err("expected a key".to_owned());
+                }
+                let key = parse_string(chars)?;
+                expect(chars, ':')?;
+                members.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(members)),
+                    _ => return Err("expected `,` or `}` in an object".to_owned()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(items)),
+                    _ => return Err("expected `,` or `]` in an array".to_owned()),
+                }
+            }
+        }
+        Some('"') => parse_string(chars).map(Json::String),
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) =
+                chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            {
+                number.push(c);
+            }
+            Ok(Json::Number(number))
+        }
+        Some(c) if c.is_ascii_alphabetic() => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "null" | "true" | "false" => Ok(Json::Literal),
+                _ => Err(format!("unexpected `{}`", word)),
+            }
+        }
+        Some(c) => Err(format!("unexpected `{}`", c)),
+        None => Err("the line ended before its value".to_owned()),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some('"') => text.push('"'),
+                Some('\\') => text.push('\\'),
+                Some('/') => text.push('/'),
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('u') => {
+                    let unit = parse_hex4(chars)?;
+                    // characters outside the basic plane are written as a pair of surrogates
+                    let c = if (0xd800..0xdc00).contains(&unit) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err("a high surrogate isn't followed by a low one".to_owned());
+                        }
+                        let low = parse_hex4(chars)?;
+                        if !(0xdc00..0xe000).contains(&low) {
+                            return Err("a high surrogate isn't followed by a low one".to_owned());
+                        }
+                        char::from_u32(0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00))
+                    } else {
+                        char::from_u32(unit)
+                    };
+                    text.push(c.ok_or("an escape isn't a valid character")?);
+                }
+                _ => return Err("invalid escape in a string".to_owned()),
+            },
+            Some(c) => text.push(c),
+            None => return Err("a string isn't closed".to_owned()),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let digits: String = chars.take(4).collect();
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("`\\u{}` isn't a valid escape", digits))
+}
@@ -0,0 +1,81 @@
+//! Masking sensitive fields, such as card numbers and national IDs, from the `sensitive` column of a syntax file or
+//! `--redact`, so reports can be shared. A line is checked against its constraints before it's masked, and its fields
+//! are still highlighted, but their values are shown as, e.g., `************1234`.
+
+use std::borrow::Cow;
+
+use crate::{HighlightRegion, PreparedLine};
+
+/// What each masked character is shown as.
+pub const MASK: char = '*';
+
+/// The number of characters left showing at the end of a value, as a card number's last four digits are.
+const SHOWN: usize = 4;
+
+/// `value` masked: every character but spaces shown as [`MASK`], except the last four of a value of more than eight,
+/// so a value can still be told from others without being given away.
+pub fn mask(value: &str) -> String {
+    let mut masked: Vec<char> = value.chars().collect();
+    mask_chars(&mut masked);
+    masked.into_iter().collect()
+}
+
+/// Mask `chars` as [`mask`] does.
+fn mask_chars(chars: &mut [char]) {
+    let count = chars.iter().filter(|c| **c != ' ').count();
+    let shown = if count > 2 * SHOWN { SHOWN } else { 0 };
+    let mut seen = 0;
+    for c in chars.iter_mut().filter(|c| **c != ' ') {
+        seen += 1;
+        if seen <= count - shown {
+            *c = MASK;
+        }
+    }
+}
+
+/// `value`, the value of `region`, masked if the field is sensitive.
+pub fn mask_value(region: &HighlightRegion, value: String) -> String {
+    if region.sensitive {
+        mask(&value)
+    } else {
+        value
+    }
+}
+
+/// Mask the values of `prepared`'s sensitive fields, leaving any quotes around them. A masked value can't be checked
+/// or decoded, so those fields lose their constraints and type, which must have been checked already, and their value
+/// style, which would tell something of the value.
+pub fn redact(prepared: &mut PreparedLine) {
+    if !prepared.regions.iter().any(|r| r.sensitive) {
+        return;
+    }
+    let mut chars: Vec<char> = prepared.text.chars().collect();
+    // values stop where any padding of a short line starts
+    let length = prepared.ghost_from.unwrap_or(chars.len()).min(chars.len());
+    for region in prepared.regions.iter_mut().filter(|r| r.sensitive) {
+        let columns = region.value_columns();
+        if columns.start < columns.end.min(length) {
+            mask_chars(&mut chars[columns.start..columns.end.min(length)]);
+        }
+        region.charset = None;
+        region.case = None;
+        region.pattern = None;
+        region.justify = None;
+        region.pad = None;
+        region.encoding = None;
+        region.field_type = None;
+        region.value_style = None;
+    }
+    prepared.text = chars.into_iter().collect();
+}
+
+/// `prepared` as it's shown, with the values of its sensitive fields masked by [`redact`].
+pub fn redacted(prepared: &PreparedLine) -> Cow<'_, PreparedLine> {
+    if prepared.regions.iter().any(|r| r.sensitive) {
+        let mut shown = prepared.clone();
+        redact(&mut shown);
+        Cow::Owned(shown)
+    } else {
+        Cow::Borrowed(prepared)
+    }
+}
@@ -0,0 +1,448 @@
+//! Regions: where the fields of a line are, found by applying the rules of a syntax file to it.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::bail;
+use log::error;
+
+use crate::{
+    layout_length, line_length, parse_syntax_file, place_field_conditions, read_syntax_file,
+    rule_applies, split_record, unit_name, Captures, Case, Charset, Columns, ControlCheck,
+    Delimiter, Encoding, FieldType, Justify, Pattern, PositionOptions, RecordList, SyntaxRule,
+    ValuePattern, ValueStyle,
+};
+
+/// A field found in a line.
+#[derive(Debug, Clone)]
+pub struct HighlightRegion {
+    /// The 0-based column the field starts at.
+    pub start: usize,
+    /// The 0-based column after the end of the field.
+    pub end: usize,
+    /// Where the field is as the syntax file places it, from 0: the columns it covers in `--column-mode`, which `start`
+    /// and `end` are the characters of, so reports give the positions the syntax file does. In delimiter mode, they're
+    /// `start` and `end`.
+    pub columns: Range<usize>,
+    /// The field's name, shared with its rule.
+    pub name: Arc<str>,
+    pub charset: Option<Charset>,
+    pub case: Option<Case>,
+    pub pattern: Option<Pattern>,
+    pub justify: Option<Justify>,
+    pub pad: Option<char>,
+    pub encoding: Option<Encoding>,
+    pub field_type: Option<FieldType>,
+    /// The colour the field is always highlighted in, if its rule gives one.
+    pub color: Option<String>,
+    /// The regex the field's value must match to be drawn in the style alongside it, over its colour, if its rule
+    /// gives one.
+    pub value_style: Option<(ValuePattern, ValueStyle)>,
+    /// Whether the field's value is masked in every output, as its rule or `--redact` says.
+    pub sensitive: bool,
+    /// The control total the field holds, or its check across records, if its rule gives one.
+    pub check: Option<ControlCheck>,
+    /// The character the field is quoted with, if it is. The quotes are part of the field, but not of its value.
+    pub quote: Option<char>,
+}
+
+impl HighlightRegion {
+    /// The columns of the field's value: all of it, or if it's quoted, those between the quotes.
+    pub fn value_columns(&self) -> Range<usize> {
+        if self.quote.is_some() {
+            self.start + 1..self.end - 1
+        } else {
+            self.start..self.end
+        }
+    }
+}
+
+/// What to do with lines that are shorter than the layout.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortLinePolicy {
+    /// Fail when a line is shorter than the layout
+    Error,
+    /// Highlight short lines as far as they go
+    Truncate,
+    /// Pad short lines for display so every field is shown
+    Pad,
+}
+
+/// Find the fields of `line`: a region for each rule that applies to it.
+pub fn generate_highlight_regions_from_records(
+    records: &RecordList,
+    line: &str,
+    positions: PositionOptions,
+) -> Vec<HighlightRegion> {
+    let mut regions = Vec::new();
+    let record_type = records.record_type(line);
+    let captures = Captures::of(records, record_type, line);
+
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            let columns = Columns::new(line, positions.column_mode);
+            for record in fw_records {
+                let apply_record_to_this_line = rule_applies(record, record_type, line);
+
+                if apply_record_to_this_line {
+                    if !record.placeholders().is_empty()
+                        && (record.start_on(&captures).is_none()
+                            || record.length_on(&captures).is_none())
+                    {
+                        error!(
+                            "Syntax record '{}' skipped as its capture group didn't capture a number on this line.",
+                            record.name
+                        );
+                        continue;
+                    }
+                    let (Some(_), Some(length)) =
+                        (record.start_on(&captures), record.length_on(&captures))
+                    else {
+                        error!("Syntax record skipped as fields were not correctly filled in. (needs 'start' and 'length'!)");
+                        continue;
+                    };
+
+                    let Some(start) = record.start_column(
+                        positions.column_mode.count(line),
+                        &captures,
+                        positions,
+                    ) else {
+                        // a field placed from the end just doesn't fit on a line that's too short for it
+                        if !record.from_end {
+                            error!(
+                                "Syntax record '{}' skipped as it would start before the first column.",
+                                record.name
+                            );
+                        }
+                        continue;
+                    };
+
+                    regions.push(HighlightRegion {
+                        start: columns.char_index(start),
+                        end: columns.char_index(start + length),
+                        columns: start..start + length,
+                        name: record.name.clone(),
+                        charset: record.charset.clone(),
+                        case: record.case,
+                        pattern: record.pattern.clone(),
+                        justify: record.justify,
+                        pad: record.pad,
+                        encoding: record.encoding,
+                        field_type: record.field_type.clone(),
+                        color: record.color.clone(),
+                        value_style: record.value_pattern.clone().map(|pattern| {
+                            (pattern, record.value_style.clone().unwrap_or_default())
+                        }),
+                        sensitive: record.sensitive,
+                        check: record.check.clone(),
+                        quote: None,
+                    })
+                }
+            }
+        }
+
+        RecordList::Delimiter(delimiter, d_records) => {
+            let delimiters = delimiter.spans(positions.quote, line);
+            let length = line.chars().count();
+            for record in d_records {
+                let apply_record_to_this_line = rule_applies(record, record_type, line);
+
+                if apply_record_to_this_line {
+                    let Some(field) = record.field_on(&captures) else {
+                        if record.field_capture.is_some() {
+                            error!(
+                                "Syntax record '{}' skipped as its capture group didn't capture a number on this line.",
+                                record.name
+                            );
+                        } else {
+                            error!("Syntax record skipped as fields were not correctly filled in. (needs 'field'!)");
+                        }
+                        continue;
+                    };
+
+                    let field = field as isize + 1 - positions.index_base as isize
+                        + record.shift(positions);
+                    if field < 1 {
+                        error!(
+                            "Syntax record '{}' skipped as it would refer to a field before the first.",
+                            record.name
+                        );
+                        continue;
+                    }
+                    let field = field as usize;
+
+                    let (start, end) = field_columns(&delimiters, field, length);
+                    let quoted = positions.quote.filter(|&q| {
+                        end >= start + 2
+                            && line.chars().nth(start) == Some(q)
+                            && line.chars().nth(end - 1) == Some(q)
+                    });
+
+                    regions.push(HighlightRegion {
+                        start,
+                        end,
+                        columns: start..end,
+                        name: record.name.clone(),
+                        charset: record.charset.clone(),
+                        case: record.case,
+                        pattern: record.pattern.clone(),
+                        justify: record.justify,
+                        pad: record.pad,
+                        encoding: record.encoding,
+                        field_type: record.field_type.clone(),
+                        color: record.color.clone(),
+                        value_style: record.value_pattern.clone().map(|pattern| {
+                            (pattern, record.value_style.clone().unwrap_or_default())
+                        }),
+                        sensitive: record.sensitive,
+                        check: record.check.clone(),
+                        quote: quoted,
+                    })
+                }
+            }
+        }
+    }
+
+    regions
+}
+
+/// A line ready to be rendered, with the short line policy applied.
+#[derive(Clone)]
+pub struct PreparedLine {
+    pub text: String,
+    pub regions: Vec<HighlightRegion>,
+    /// The column from which the line has been virtually padded, if padding is to be shown as ghost cells.
+    pub ghost_from: Option<usize>,
+    /// The name of the line's record type, if the syntax file defines record types and the line is of one.
+    pub record_type: Option<String>,
+    /// The column each physical line after the first starts at, if the line is a record assembled from several.
+    pub breaks: Vec<usize>,
+    /// The columns of each delimiter between the line's fields, in delimiter mode.
+    pub delimiters: Vec<Range<usize>>,
+    /// Whether the line is left out of highlighting, such as a file's header, so has no fields and isn't checked.
+    pub skipped: bool,
+}
+
+impl PreparedLine {
+    /// Whether the character at `col` is (part of) a delimiter between fields.
+    pub fn is_delimiter(&self, col: usize) -> bool {
+        self.delimiters.iter().any(|d| d.contains(&col))
+    }
+
+    /// Whether the character at `col` is covered by any of the line's regions, counting delimiters as covered.
+    pub fn is_covered(&self, col: usize) -> bool {
+        is_covered(&self.delimiters, &self.regions, col)
+    }
+
+    /// The column the line's unexpected trailing data starts at, if it goes on past its last field.
+    pub fn trailing_from(&self) -> Option<usize> {
+        if self.skipped {
+            return None;
+        }
+        trailing_from(&self.regions, self.text.chars().count())
+    }
+}
+
+/// Apply the short line policy to `line`, taking out any [`LINE_BREAK`](crate::LINE_BREAK)s joining the lines it was
+/// assembled from, and generate its regions.
+pub fn prepare_line(
+    records: &RecordList,
+    line: String,
+    line_index: usize,
+    positions: PositionOptions,
+    short_lines: ShortLinePolicy,
+    ghost_cells: bool,
+) -> anyhow::Result<PreparedLine> {
+    let (mut line, breaks) = split_record(line);
+    let mut ghost_from = None;
+    let record_type = records.record_type(&line).map(|t| t.name.clone());
+
+    let have = line_length(records, &line, positions);
+    let needed = layout_length(records, &line, positions);
+    if have < needed {
+        match short_lines {
+            ShortLinePolicy::Error => bail!(
+                "Line {} is shorter than the layout: it has {} {} but needs {}.",
+                line_index + 1,
+                have,
+                unit_name(records),
+                needed
+            ),
+            ShortLinePolicy::Truncate => (),
+            ShortLinePolicy::Pad => {
+                if ghost_cells {
+                    ghost_from = Some(line.chars().count());
+                }
+                if needed - have > MAX_PADDING {
+                    bail!(
+                        "Line {} is shorter than the layout by {} {}, more than the {} it can be padded by.",
+                        line_index + 1,
+                        needed - have,
+                        unit_name(records),
+                        MAX_PADDING
+                    );
+                }
+                pad_line(records, &mut line, needed - have, positions.quote);
+            }
+        }
+    }
+
+    let regions = generate_highlight_regions_from_records(records, &line, positions);
+    let delimiters = records
+        .delimiter()
+        .map_or(Vec::new(), |d| d.spans(positions.quote, &line));
+    Ok(PreparedLine {
+        text: line,
+        regions,
+        ghost_from,
+        record_type,
+        breaks,
+        delimiters,
+        skipped: false,
+    })
+}
+
+/// `line` left out of highlighting: shown as it is, with no rules applied to it, taking out any
+/// [`LINE_BREAK`](crate::LINE_BREAK)s joining the lines it was assembled from.
+pub fn skip_line(line: String) -> PreparedLine {
+    let (text, breaks) = split_record(line);
+    PreparedLine {
+        text,
+        regions: Vec::new(),
+        ghost_from: None,
+        record_type: None,
+        breaks,
+        delimiters: Vec::new(),
+        skipped: true,
+    }
+}
+
+/// The most columns (or fields) a short line is padded by. A layout whose start or length is captured from the line can
+/// ask for any length, which mustn't be allowed to exhaust memory.
+const MAX_PADDING: usize = 1024 * 1024;
+
+/// Virtually extend `line` by `missing` columns (or empty fields in delimiter mode) for display. A line with a regex
+/// delimiter it has no match for can't be extended by fields.
+fn pad_line(records: &RecordList, line: &mut String, missing: usize, quote: Option<char>) {
+    let padding = match records {
+        RecordList::FixedWidth(_) => Some(" ".to_owned()),
+        RecordList::Delimiter(delimiter, _) => delimiter.filler(quote, line),
+    };
+    if let Some(padding) = padding {
+        line.push_str(&padding.repeat(missing));
+    }
+}
+
+/// Whether the character at `col` of a line is covered by any of `regions`, counting its `delimiters` (from
+/// [`Delimiter::spans`](crate::Delimiter::spans)) as covered.
+pub fn is_covered(delimiters: &[Range<usize>], regions: &[HighlightRegion], col: usize) -> bool {
+    delimiters.iter().any(|d| d.contains(&col))
+        || regions.iter().any(|r| r.start <= col && col < r.end)
+}
+
+/// The column just after the last of `regions`, if a line `length` columns long goes on past it: the start of data the
+/// layout doesn't expect. A line no rule applies to has no last field, so its text is only uncovered.
+pub fn trailing_from(regions: &[HighlightRegion], length: usize) -> Option<usize> {
+    let end = regions.iter().map(|r| r.end).max()?;
+    (end < length).then_some(end)
+}
+
+/// The columns `start..end` of the `field`th (from 1) field of a line `length` columns long, between its `delimiters`
+/// (from [`Delimiter::spans`](crate::Delimiter::spans)). A field the line doesn't have is placed just beyond its end.
+pub fn field_columns(delimiters: &[Range<usize>], field: usize, length: usize) -> (usize, usize) {
+    let end = delimiters.get(field - 1).map_or(length, |d| d.start);
+    if field == 1 {
+        (0, end)
+    } else if let Some(previous) = delimiters.get(field - 2) {
+        (previous.end, end)
+    } else {
+        (length, length + 1)
+    }
+}
+
+/// The value of `region` of `line`, stopping at `limit` if given. A quoted field's value is what's between its
+/// quotes, with each doubled quote read as one.
+pub fn region_value(line: &str, region: &HighlightRegion, limit: Option<usize>) -> String {
+    let columns = region.value_columns();
+    let end = limit.map_or(columns.end, |l| columns.end.min(l));
+    let value: String = line
+        .chars()
+        .skip(columns.start)
+        .take(end.saturating_sub(columns.start))
+        .collect();
+    match region.quote {
+        Some(quote) => value.replace(&format!("{0}{0}", quote), &quote.to_string()),
+        None => value,
+    }
+}
+
+/// Highlights lines by the rules of a syntax file.
+pub struct Highlighter {
+    records: RecordList,
+    positions: PositionOptions,
+    short_lines: ShortLinePolicy,
+}
+
+impl Highlighter {
+    /// A highlighter for `records`, counting positions from 1 and highlighting short lines as far as they go.
+    pub fn new(records: RecordList) -> Self {
+        Self {
+            records,
+            positions: PositionOptions::default(),
+            short_lines: ShortLinePolicy::Truncate,
+        }
+    }
+
+    /// A highlighter for the text of a CSV syntax file, which is in delimiter mode if a delimiter is given.
+    pub fn from_syntax(syntax: &str, delimiter: Option<Delimiter>) -> anyhow::Result<Self> {
+        Ok(Self::new(parse_syntax_file(syntax, delimiter)?))
+    }
+
+    /// A highlighter for the syntax file (or compiled syntax file) at `path`.
+    pub fn from_syntax_file<P: AsRef<Path>>(
+        path: P,
+        delimiter: Option<Delimiter>,
+    ) -> anyhow::Result<Self> {
+        let (_, records, _) = read_syntax_file(path, delimiter)?;
+        Ok(Self::new(records))
+    }
+
+    /// Read the positions of rules as `positions` describes, failing if a `field:` condition reads a field that
+    /// isn't placed by a rule above it.
+    pub fn with_positions(mut self, positions: PositionOptions) -> anyhow::Result<Self> {
+        self.positions = positions;
+        place_field_conditions(&mut self.records, positions)?;
+        Ok(self)
+    }
+
+    /// Treat lines shorter than the layout as `short_lines` says.
+    pub fn with_short_lines(mut self, short_lines: ShortLinePolicy) -> Self {
+        self.short_lines = short_lines;
+        self
+    }
+
+    /// The rules lines are highlighted by.
+    pub fn records(&self) -> &RecordList {
+        &self.records
+    }
+
+    /// The fields of `line`.
+    pub fn regions(&self, line: &str) -> Vec<HighlightRegion> {
+        generate_highlight_regions_from_records(&self.records, line, self.positions)
+    }
+
+    /// The fields of `line`, the `line_index`th (from 0) of its file, with short lines treated as configured. Padding
+    /// added to a short line is marked by [`PreparedLine::ghost_from`]. A record assembled from several lines has them
+    /// joined by [`LINE_BREAK`](crate::LINE_BREAK).
+    pub fn prepare(&self, line: String, line_index: usize) -> anyhow::Result<PreparedLine> {
+        prepare_line(
+            &self.records,
+            line,
+            line_index,
+            self.positions,
+            self.short_lines,
+            true,
+        )
+    }
+}
@@ -0,0 +1,1754 @@
+//! Rendering lines as HTML, with their fields highlighted.
+
+use std::{
+    cmp::Reverse,
+    io::{self, Write},
+};
+
+use log::error;
+
+use crate::{
+    char_width, constraints,
+    encoding::{field_value, ValueError},
+    locale::{Locale, Message},
+    region_value, segments,
+    suspicious::{self, Suspicion},
+    value_styles, CheckResult, ColumnMode, Columns, DisplayWidths, Encoding, HighlightRegion,
+    PreparedLine, Segment, SegmentKind, ValueStyle,
+};
+
+/// The default field colours, which alternate between white and grey.
+pub const GREYSCALE: [&str; 2] = ["fff", "ccc"];
+
+/// The default field colours with `--dark`, which alternate between shades of dark grey.
+pub const DARK_GREYSCALE: [&str; 2] = ["333", "4d4d4d"];
+
+/// Field colours in pale shades of the rainbow.
+pub const RAINBOW: [&str; 7] = [
+    "fff", "f88", "ffc088", "a2ff88", "88f9ff", "a288ff", "ff88ba",
+];
+
+/// Field colours for red-green colour blindness (deuteranopia and protanopia): pale shades of the Okabe-Ito palette,
+/// alternating between yellows and blues, which stay distinct without red and green.
+pub const DEUTERANOPIA: [&str; 6] = ["fff", "f2cf7f", "aad9f4", "f7f1a0", "7fb8d8", "e5bcd3"];
+
+/// Field colours for blue-yellow colour blindness (tritanopia), alternating between reds and cyans, which stay
+/// distinct without blue and yellow.
+pub const TRITANOPIA: [&str; 6] = ["fff", "f5a3a3", "a3e0e0", "fbd5d5", "7fcccc", "ddd"];
+
+/// The underline styles of `--field-boundaries underline`, given to fields in turn.
+const UNDERLINES: [&str; 4] = ["solid", "dashed", "dotted", "double"];
+
+/// Renders lines into HTML, with their fields highlighted.
+#[derive(Debug)]
+pub struct Renderer {
+    /// The background colours of fields, as hex codes without the `#`, used in turn.
+    pub colors: Vec<String>,
+    /// Collapse runs of characters outside every region behind an ellipsis.
+    pub covered_only: bool,
+    /// Add copy-as-CSV and copy-as-JSON controls to each line.
+    pub copy_buttons: bool,
+    /// A character to show in place of each delimiter between fields, which are shown muted.
+    pub delimiter_glyph: Option<char>,
+    /// How to mark the boundary between adjacent fields.
+    pub field_boundaries: Option<FieldBoundaries>,
+    /// Print field names above each line.
+    pub labels: bool,
+    /// The language of the text the renderer adds.
+    pub locale: Locale,
+    /// Mark suspicious Unicode characters inside fields.
+    pub check_unicode: bool,
+    /// The colour (a hex code without the `#`) to mark text not covered by any rule, and fields that run past the end
+    /// of the line, in. They aren't marked if this isn't set.
+    pub error_color: Option<String>,
+    /// The encoding lines were decoded from, which fields with an `encoding` or `type` are decoded from again to show
+    /// their values.
+    pub encoding: Encoding,
+    /// Pick each field's colour from its name, so a field is the same colour on every line it's on.
+    pub stable_colors: bool,
+    /// How fields that overlap each other are drawn.
+    pub overlaps: Overlaps,
+    /// Give each field the details the `--interactive` panel shows when it's clicked on.
+    pub interactive: bool,
+    /// Give each field its name and value as `data-` attributes, for the search bar of `--search` to find it by.
+    pub searchable: bool,
+    /// Show characters that aren't printable as the hex value of their byte, for binary records.
+    pub hex_bytes: bool,
+    /// Style lines with the classes of [`Renderer::stylesheet`], which must be included in the page, rather than with
+    /// inline styles. This makes large reports much smaller, and lets them be restyled.
+    pub css_classes: bool,
+    /// Show lines left out of highlighting dimmed, rather than as plain text.
+    pub dim_skipped: bool,
+    /// With `--display-width`, the screen columns each column of the lines is given, so fields line up down the report
+    /// whatever the width of their characters.
+    pub display_widths: Option<DisplayWidths>,
+    /// Show spaces and tabs within fields as `·` and `→`, so how a field is padded can be seen.
+    pub show_padding: bool,
+    /// Mark text after the end of a line's last field as unexpected trailing data.
+    pub trailing_data: bool,
+    /// With `--record-gutter`, the width to show each line's record type name in after its number, which is the
+    /// length of the longest name, so the lines still line up.
+    pub record_gutter: Option<usize>,
+    /// What the id of each line and field starts with, so that the lines of reports on the same page, such as the two
+    /// files of a join, don't share ids.
+    pub id_prefix: &'static str,
+}
+
+impl Default for Renderer {
+    /// The greyscale colours, with nothing else added.
+    fn default() -> Self {
+        Self {
+            colors: GREYSCALE.iter().map(|c| c.to_string()).collect(),
+            covered_only: false,
+            copy_buttons: false,
+            delimiter_glyph: None,
+            field_boundaries: None,
+            labels: false,
+            locale: Locale::default(),
+            check_unicode: false,
+            error_color: None,
+            encoding: Encoding::Utf8,
+            stable_colors: false,
+            overlaps: Overlaps::Nest,
+            interactive: false,
+            searchable: false,
+            hex_bytes: false,
+            css_classes: false,
+            dim_skipped: false,
+            display_widths: None,
+            show_padding: false,
+            trailing_data: false,
+            record_gutter: None,
+            id_prefix: "",
+        }
+    }
+}
+
+impl Renderer {
+    /// A prepared line as a line of HTML.
+    pub fn html_line(&self, line_index: usize, line: &PreparedLine) -> String {
+        let mut out = Vec::new();
+        self.write_html_line(&mut out, line_index, line, None)
+            .expect("writing to memory doesn't fail");
+        String::from_utf8(out).expect("the line is valid UTF-8")
+    }
+
+    /// The stylesheet of the classes lines are styled with when [`css_classes`](Renderer::css_classes) is set: one for
+    /// each of the renderer's colours and of `field_colors`, the colours fields are given in the syntax file, and one
+    /// for each other part of a line.
+    pub fn stylesheet<'a>(&self, field_colors: impl IntoIterator<Item = &'a str>) -> String {
+        let mut colors: Vec<&str> = self.colors.iter().map(String::as_str).collect();
+        for color in field_colors {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+        let mut css = String::new();
+        for color in colors {
+            css.push_str(&format!(
+                ".{} {{ background: #{}; color: #{}; }}\n",
+                color_class(color),
+                color,
+                foreground_color(color)
+            ));
+        }
+        css.push_str(".ffh-iso { unicode-bidi: isolate; }\n");
+        css.push_str(".ffh-edge { box-shadow: inset 1px 0 0 #505050; }\n");
+        css.push_str(".ffh-row { display: inline-block; min-width: 100%; }\n");
+        // before the styles, so a violation's underline replaces a field's
+        for underline in UNDERLINES {
+            css.push_str(&format!(
+                ".ffh-ul-{0} {{ text-decoration: underline {0} #505050; }}\n",
+                underline
+            ));
+        }
+        for style in Style::ALL {
+            css.push_str(&format!(".{} {{ {} }}\n", style.class(), style.css()));
+        }
+        if let Some(color) = &self.error_color {
+            css.push_str(&format!(
+                ".ffh-error {{ color: #{0}; text-decoration: underline wavy #{0}; }}\n",
+                color
+            ));
+        }
+        css
+    }
+
+    /// The attribute styling text in the error colour, `color`.
+    fn error_attr(&self, color: &str) -> String {
+        if self.css_classes {
+            r#"class="ffh-error""#.to_owned()
+        } else {
+            format!(
+                r#"style="color:#{0}; text-decoration:underline wavy #{0};""#,
+                color
+            )
+        }
+    }
+
+    /// Write `prepared`, the `line_index`th (from 0) line of its file, as a line of HTML with its fields highlighted.
+    /// `background` is a colour to shade the whole line with.
+    pub fn write_html_line<W: Write>(
+        &self,
+        out: &mut W,
+        line_index: usize,
+        prepared: &PreparedLine,
+        background: Option<&str>,
+    ) -> io::Result<()> {
+        let line = prepared.text.as_str();
+        let ghost_from = prepared.ghost_from;
+        if self.labels && !prepared.skipped {
+            self.write_label_line(out, line_index, line, &prepared.regions, &prepared.breaks)?;
+        }
+        if let Some(background) = background {
+            if self.css_classes {
+                write!(
+                    out,
+                    r#"<span class="ffh-row" style="background:{};">"#,
+                    background
+                )?;
+            } else {
+                write!(
+                    out,
+                    r#"<span style="display:inline-block; min-width:100%; background:{};">"#,
+                    background
+                )?;
+            }
+        }
+        // the line's record type is shown on hovering over its number, and beside it with `--record-gutter`
+        write!(
+            out,
+            r#"<span id="{}L{}"{} {}>{}</span>"#,
+            self.id_prefix,
+            line_index + 1,
+            prepared
+                .record_type
+                .as_ref()
+                .map_or(String::new(), |t| format!(r#" title="{}""#, escape_html(t))),
+            Style::Muted.attr(self.css_classes),
+            escape_html(&self.gutter(line_index + 1, prepared.record_type.as_deref(), '>'))
+                .replace(' ', "&nbsp;")
+        )?;
+        if prepared.skipped {
+            return self.write_skipped_line(out, line_index, prepared, background.is_some());
+        }
+        let regions = &prepared.regions;
+        let length = line.chars().count();
+        let mut fields = FieldTags {
+            line_index,
+            line,
+            regions,
+            colors: self.field_colors(line, regions),
+            value_styles: value_styles(prepared),
+            // right-to-left text in one field mustn't visually reorder its neighbours
+            isolate: has_bidi_text(line),
+            details: if self.interactive {
+                self.field_details(line_index, prepared)
+            } else if self.searchable {
+                prepared
+                    .regions
+                    .iter()
+                    .map(|r| {
+                        format!(
+                            r#" data-field="{}" data-value="{}""#,
+                            escape_html(&r.name),
+                            escape_html(&region_value(&prepared.text, r, prepared.ghost_from))
+                        )
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            id_prefix: self.id_prefix,
+            anchors: Vec::new(),
+            open: Vec::new(),
+        };
+        let chars: Vec<char> = line.chars().collect();
+        let mut hidden = 0;
+        let mut row = 0;
+        for segment in segments(prepared, self.trailing_data) {
+            // each line a record was joined from is shown on a row of its own
+            while row < segment.row {
+                fields.close_to(out, &[])?;
+                if hidden > 0 {
+                    write_hidden_marker(out, hidden, self.locale, self.css_classes)?;
+                    hidden = 0;
+                }
+                row += 1;
+                self.write_continuation(out, line_index, row)?;
+            }
+            if self.covered_only && matches!(segment.kind, SegmentKind::Gap | SegmentKind::Trailing)
+            {
+                hidden += segment.chars.len();
+                continue;
+            }
+            let wanted = fields.elements_of(self.overlaps, &segment.fields);
+            fields.close_to(out, &wanted)?;
+            if hidden > 0 {
+                write_hidden_marker(out, hidden, self.locale, self.css_classes)?;
+                hidden = 0;
+            }
+            fields.open_to(self, out, wanted, segment.chars.start)?;
+            self.write_segment(out, prepared, &chars, &segment)?;
+        }
+
+        // fields ending with the line are closed before anything written after it
+        let wanted = fields.elements_at(self.overlaps, length);
+        fields.close_to(out, &wanted)?;
+        if hidden > 0 {
+            write_hidden_marker(out, hidden, self.locale, self.css_classes)?;
+        }
+        // empty lines at the end of the record
+        while row < prepared.breaks.len() {
+            fields.close_to(out, &[])?;
+            row += 1;
+            self.write_continuation(out, line_index, row)?;
+        }
+
+        let problem = !fields.open.is_empty();
+        if problem {
+            error!(
+                "Line {} was not long enough to fit the matching regions.",
+                line_index + 1
+            );
+        }
+        if let Some(color) = &self.error_color {
+            self.write_overflow(out, &mut fields, color)?;
+        }
+        fields.close_to(out, &[])?;
+
+        write!(
+            out,
+            r#"<span {}>&nbsp;&lt;</span>"#,
+            Style::Muted.attr(self.css_classes)
+        )?;
+        if self.copy_buttons {
+            write_copy_buttons(
+                out,
+                line,
+                regions,
+                ghost_from,
+                self.locale,
+                self.css_classes,
+            )?;
+        }
+        if problem {
+            write!(
+                out,
+                r#"&nbsp;<span {}>{}</span>"#,
+                Style::Problem.attr(self.css_classes),
+                self.locale.text(Message::RegionsBeyondLine)
+            )?;
+        }
+        if background.is_some() {
+            write!(out, "</span>")?;
+        }
+
+        writeln!(out)?;
+
+        for r in regions {
+            // empty fields have nothing to highlight, but are still present
+            let empty = r.start == r.end && r.start <= length;
+            let applied = r.start < r.end && r.end <= length;
+            if !applied && !empty {
+                error!(
+                    "Failed to highlight rule '{}' on line {}!",
+                    r.name,
+                    line_index + 1
+                );
+            } else if let Some(Err(e)) = self
+                .field_value(line, r)
+                // values a plugin says aren't valid are logged as broken constraints
+                .filter(|value| !matches!(value, Err(ValueError::Plugin(_))))
+            {
+                error!(
+                    "Line {}: field '{}' has the value '{}', which {}.",
+                    line_index + 1,
+                    r.name,
+                    region_value(line, r, None),
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `segment`, a segment of `prepared`, whose characters are `chars`, inside the fields covering it. A segment
+    /// that isn't plain text is wrapped in an element styled for its kind, with a tooltip saying what it is.
+    fn write_segment<W: Write>(
+        &self,
+        out: &mut W,
+        prepared: &PreparedLine,
+        chars: &[char],
+        segment: &Segment,
+    ) -> io::Result<()> {
+        let wrapper = match segment.kind {
+            SegmentKind::Field => None,
+            SegmentKind::Violation => Some((
+                Style::Violation.attr(self.css_classes),
+                escape_html(segment.reason.as_deref().unwrap_or_default()),
+            )),
+            SegmentKind::Delimiter => Some((
+                Style::Delimiter.attr(self.css_classes),
+                self.locale.text(Message::Delimiter).to_owned(),
+            )),
+            SegmentKind::Gap => self.error_color.as_ref().map(|color| {
+                (
+                    self.error_attr(color),
+                    self.locale.text(Message::NotCovered).to_owned(),
+                )
+            }),
+            SegmentKind::Trailing => Some((
+                Style::Trailing.attr(self.css_classes),
+                self.locale.text(Message::TrailingData).to_owned(),
+            )),
+            SegmentKind::Ghost => Some((
+                Style::Ghost.attr(self.css_classes),
+                self.locale.text(Message::MissingFromLine).to_owned(),
+            )),
+        };
+        if let Some((style, title)) = &wrapper {
+            write!(out, r#"<span {} title="{}">"#, style, title)?;
+        }
+        for col in segment.chars.clone() {
+            let chr = chars[col];
+            let wide = self.display_widths.is_some() && char_width(chr) == 2;
+            if wide {
+                write!(out, "<span {}>", Style::Wide.attr(self.css_classes))?;
+            }
+            match segment.kind {
+                SegmentKind::Delimiter | SegmentKind::Ghost => {
+                    let shown = if prepared.is_delimiter(col) {
+                        self.delimiter_shown(prepared, col, chr)
+                    } else if chr == ' ' {
+                        Some('·')
+                    } else {
+                        Some(chr)
+                    };
+                    if let Some(shown) = shown {
+                        write_html_char(out, shown)?;
+                    }
+                }
+                SegmentKind::Trailing => write_html_char(out, chr)?,
+                SegmentKind::Field | SegmentKind::Violation | SegmentKind::Gap => {
+                    self.write_field_char(out, &prepared.regions, col, chr)?
+                }
+            }
+            if wide {
+                write!(out, "</span>")?;
+            }
+            self.write_padding(out, col, chr)?;
+        }
+        if wrapper.is_some() {
+            write!(out, "</span>")?;
+        }
+        Ok(())
+    }
+
+    /// Write `chr`, the character at `col` of a line with `regions`, as a field's text: marked if it can't be seen or
+    /// shouldn't be there, and shown as a stand-in if it's padding and `--show-padding` is set.
+    fn write_field_char<W: Write>(
+        &self,
+        out: &mut W,
+        regions: &[HighlightRegion],
+        col: usize,
+        chr: char,
+    ) -> io::Result<()> {
+        if let Some(name) = bidi_control_name(chr) {
+            write_bidi_control(out, chr, name, self.css_classes)
+        } else if self.hex_bytes && chr.is_control() {
+            write_hex_byte(out, chr, self.encoding, self.css_classes)
+        } else if let Some((name, suspicion)) = self
+            .check_unicode
+            .then(|| suspicious::in_field(regions, col, chr))
+            .flatten()
+        {
+            write_suspicious_char(out, chr, name, suspicion, self.css_classes)
+        } else if let Some(shown) = self.padding_shown(regions, col, chr) {
+            write!(
+                out,
+                "<span {}>{}</span>",
+                Style::Padding.attr(self.css_classes),
+                shown
+            )
+        } else {
+            write_html_char(out, chr)
+        }
+    }
+
+    /// What `--show-padding` shows in place of `chr`, the character at `col`, if it's a space or tab within a field.
+    pub fn padding_shown(
+        &self,
+        regions: &[HighlightRegion],
+        col: usize,
+        chr: char,
+    ) -> Option<char> {
+        if !self.show_padding || !regions.iter().any(|r| r.start <= col && col < r.end) {
+            return None;
+        }
+        match chr {
+            ' ' => Some('·'),
+            '\t' => Some('→'),
+            _ => None,
+        }
+    }
+
+    /// The tooltip of `region` of `line`, unescaped: its name, and if it is decoded from its bytes, its text and the
+    /// value it decodes to, e.g. `Date: 20240119 → 2024-01-19`, or why it can't be.
+    fn field_title(&self, line: &str, region: &HighlightRegion) -> String {
+        let decoded = match self.field_value(line, region) {
+            None => return region.name.to_string(),
+            Some(decoded) => decoded,
+        };
+        let text = region_value(line, region, None);
+        if region.field_type.as_ref().is_some_and(|t| !t.is_textual()) {
+            // the bytes of a packed number aren't worth showing as text
+            return match decoded {
+                Ok(value) => format!("{}: {}", region.name, value),
+                Err(e) => format!("{}: {}", region.name, self.value_error(&e)),
+            };
+        }
+        match decoded {
+            Ok(value) if value == text => format!("{}: {}", region.name, value),
+            Ok(value) => format!("{}: {} → {}", region.name, text, value),
+            Err(e) => format!("{}: {} ({})", region.name, text, self.value_error(&e)),
+        }
+    }
+
+    /// The value of `region` of `line` decoded from its bytes, if it has an `encoding` or `type`.
+    fn field_value(
+        &self,
+        line: &str,
+        region: &HighlightRegion,
+    ) -> Option<Result<String, ValueError>> {
+        let text = region_value(line, region, None);
+        field_value(
+            &text,
+            self.encoding,
+            region.encoding,
+            region.field_type.as_ref(),
+        )
+    }
+
+    /// Why a field couldn't be decoded, in the renderer's language.
+    fn value_error(&self, error: &ValueError) -> String {
+        match error {
+            ValueError::NotEncodable(encoding) => self
+                .locale
+                .format(Message::NotEncodable, &[&encoding.name()]),
+            ValueError::NotANumber(field_type) => self
+                .locale
+                .format(Message::NotANumber, &[&field_type.spec()]),
+            ValueError::NotADate(format) => self
+                .locale
+                .format(Message::NotADate, &[&format!("`{}`", format.spec())]),
+            // in the plugin's own words
+            ValueError::Plugin(reason) => reason.clone(),
+        }
+    }
+
+    /// The colour each of `regions` of `line` is highlighted in, in the same order. A field with its own `color` is
+    /// always that colour. With `stable_colors`, the others take the colour their name picks; otherwise colours are
+    /// given in turn to them in the order they start. Fields that are empty or start beyond the end of the line have
+    /// none.
+    pub fn field_colors<'a>(
+        &'a self,
+        line: &str,
+        regions: &'a [HighlightRegion],
+    ) -> Vec<Option<&'a str>> {
+        let length = line.chars().count();
+        let mut order: Vec<usize> = (0..regions.len())
+            .filter(|&i| regions[i].end > regions[i].start && regions[i].start < length)
+            .collect();
+        order.sort_by_key(|&i| regions[i].start);
+
+        let mut colors = vec![None; regions.len()];
+        let mut n = 0;
+        for i in order {
+            colors[i] = Some(match &regions[i].color {
+                Some(color) => color.as_str(),
+                None if self.stable_colors => {
+                    self.colors[name_hash(&regions[i].name) % self.colors.len()].as_str()
+                }
+                None => {
+                    n += 1;
+                    self.colors[(n - 1) % self.colors.len()].as_str()
+                }
+            });
+        }
+        colors
+    }
+
+    /// The `data-` attributes of each field of `prepared`, the `line_index`th line of its file, that `--interactive`
+    /// shows: its line, name, start and length, its value and whether the value keeps to the field's constraints and
+    /// can be decoded.
+    fn field_details(&self, line_index: usize, prepared: &PreparedLine) -> Vec<String> {
+        prepared
+            .regions
+            .iter()
+            .map(|r| {
+                let mut expected: Vec<String> = constraints::violations(
+                    &prepared.text,
+                    std::slice::from_ref(r),
+                    prepared.ghost_from,
+                )
+                .into_iter()
+                .map(|violation| violation.expected)
+                .collect();
+                // values a plugin says aren't valid are already among the violations
+                if let Some(Err(_)) = self
+                    .field_value(&prepared.text, r)
+                    .filter(|value| !matches!(value, Err(ValueError::Plugin(_))))
+                {
+                    expected.push(match &r.field_type {
+                        Some(field_type) => format!("type `{}`", field_type.spec()),
+                        None => format!("encoding {}", r.encoding.map_or("", Encoding::name)),
+                    });
+                }
+                let check = if expected.is_empty() {
+                    self.locale.text(Message::Valid).to_owned()
+                } else {
+                    self.locale
+                        .format(Message::Expected, &[&expected.join(", ")])
+                };
+                format!(
+                    r#" data-line="{}" data-field="{}" data-start="{}" data-length="{}" data-value="{}" data-check="{}""#,
+                    line_index + 1,
+                    escape_html(&r.name),
+                    r.columns.start + 1,
+                    r.columns.len(),
+                    escape_html(&region_value(&prepared.text, r, prepared.ghost_from)),
+                    escape_html(&check)
+                )
+            })
+            .collect()
+    }
+
+    /// Write a cell in `color` for each column past the end of the line that any field covers, with a tooltip naming
+    /// the fields it belongs to, inside the fields still open, closing them as they end.
+    fn write_overflow<W: Write>(
+        &self,
+        out: &mut W,
+        fields: &mut FieldTags,
+        color: &str,
+    ) -> io::Result<()> {
+        let length = fields.line.chars().count();
+        let end = fields
+            .regions
+            .iter()
+            .filter(|r| r.end > r.start)
+            .map(|r| r.end)
+            .max()
+            .unwrap_or(0);
+        for col in length..end {
+            let wanted = fields.elements_at(self.overlaps, col);
+            fields.close_to(out, &wanted)?;
+            fields.open_to(self, out, wanted, col)?;
+            let names: Vec<String> = fields
+                .regions
+                .iter()
+                .filter(|r| r.start <= col && col < r.end)
+                .map(|r| format!("'{}'", r.name))
+                .collect();
+            if names.is_empty() {
+                write!(out, " ")?;
+            } else {
+                write!(
+                    out,
+                    r#"<span {} title="{}">·</span>"#,
+                    self.error_attr(color),
+                    escape_html(
+                        &self
+                            .locale
+                            .format(Message::PastEndOfLine, &[&names.join(", ")])
+                    )
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The rest of a line left out of highlighting, after its number: its text as it is, dimmed if
+    /// [`dim_skipped`](Renderer::dim_skipped) is set. `background` is whether the line's background is open.
+    fn write_skipped_line<W: Write>(
+        &self,
+        out: &mut W,
+        line_index: usize,
+        prepared: &PreparedLine,
+        background: bool,
+    ) -> io::Result<()> {
+        let open = format!(
+            r#"<span {}title="{}">"#,
+            if self.dim_skipped {
+                format!("{} ", Style::Skipped.attr(self.css_classes))
+            } else {
+                String::new()
+            },
+            self.locale.text(Message::SkippedLine)
+        );
+        let mut breaks = prepared.breaks.iter().copied().peekable();
+        let mut row = 0;
+        write!(out, "{}", open)?;
+        for (col, chr) in prepared.text.chars().enumerate() {
+            while breaks.next_if_eq(&col).is_some() {
+                write!(out, "</span>")?;
+                row += 1;
+                self.write_continuation(out, line_index, row)?;
+                write!(out, "{}", open)?;
+            }
+            let wide = self.display_widths.is_some() && char_width(chr) == 2;
+            if wide {
+                write!(out, "<span {}>", Style::Wide.attr(self.css_classes))?;
+            }
+            match bidi_control_name(chr) {
+                Some(name) => write_bidi_control(out, chr, name, self.css_classes)?,
+                None => write_html_char(out, chr)?,
+            }
+            if wide {
+                write!(out, "</span>")?;
+            }
+            self.write_padding(out, col, chr)?;
+        }
+        write!(out, "</span>")?;
+        for _ in breaks {
+            row += 1;
+            self.write_continuation(out, line_index, row)?;
+        }
+        write!(
+            out,
+            r#"<span {}>&nbsp;&lt;</span>"#,
+            Style::Muted.attr(self.css_classes)
+        )?;
+        if background {
+            write!(out, "</span>")?;
+        }
+        writeln!(out)
+    }
+
+    /// With `--display-width`, the spaces filling out the column `col` after the character `chr` shown in it, so the
+    /// columns after it start where they do on every other line.
+    fn write_padding<W: Write>(&self, out: &mut W, col: usize, chr: char) -> io::Result<()> {
+        if let Some(widths) = &self.display_widths {
+            write!(out, "{:1$}", "", widths.padding(col, chr))?;
+        }
+        Ok(())
+    }
+
+    /// Start the `row`th row after the first of the `line_index`th line, a record joined from several lines, with the
+    /// number of the line of the file it shows.
+    fn write_continuation<W: Write>(
+        &self,
+        out: &mut W,
+        line_index: usize,
+        row: usize,
+    ) -> io::Result<()> {
+        writeln!(out)?;
+        write!(
+            out,
+            r#"<span {} title="{}">{}</span>"#,
+            Style::Muted.attr(self.css_classes),
+            escape_html(
+                &self
+                    .locale
+                    .format(Message::ContinuesLine, &[&(line_index + 1)])
+            ),
+            self.gutter(line_index + 1 + row, None, '+')
+                .replace(' ', "&nbsp;")
+        )
+    }
+
+    /// The text shown before a row of a line: the number of the line of the file it's from, its record type name with
+    /// `--record-gutter`, and `marker`, which is `>` for a line's first row and `+` for the lines joined to it.
+    pub fn gutter(&self, line_number: usize, record_type: Option<&str>, marker: char) -> String {
+        match self.record_gutter {
+            Some(width) => format!(
+                "L{:3} {:<width$} {} ",
+                line_number,
+                record_type.unwrap_or_default(),
+                marker
+            ),
+            None => format!("L{:3} {} ", line_number, marker),
+        }
+    }
+
+    /// Write the note under a line that fails a check: a trailer's control total that doesn't match the records before
+    /// it, a duplicate key, or a value out of order.
+    pub fn write_failed_check_html<W: Write>(
+        &self,
+        out: &mut W,
+        failed: &CheckResult,
+    ) -> io::Result<()> {
+        writeln!(
+            out,
+            r#"<span {}>{:4}&nbsp;!&nbsp;</span><span {}>{}</span>"#,
+            Style::Muted.attr(self.css_classes),
+            "",
+            Style::Problem.attr(self.css_classes),
+            escape_html(&failed.describe(self.locale))
+        )
+    }
+
+    /// What to show for the character `chr` at `col` of a delimiter of `prepared`: itself, or the glyph in place of the
+    /// whole delimiter, shown where it starts.
+    pub fn delimiter_shown(&self, prepared: &PreparedLine, col: usize, chr: char) -> Option<char> {
+        match self.delimiter_glyph {
+            Some(glyph) => prepared
+                .delimiters
+                .iter()
+                .any(|d| d.start == col)
+                .then_some(glyph),
+            None => Some(chr),
+        }
+    }
+
+    /// Write the names of `regions` above `line`, each starting over the first column of its field and cut short to fit.
+    /// Columns are counted in graphemes, as they are displayed, or with `--display-width`, in the screen columns each is given. Only the first row of a record joined from several lines
+    /// is labelled, at `breaks`, as the labels sit above it.
+    fn write_label_line<W: Write>(
+        &self,
+        out: &mut W,
+        line_index: usize,
+        line: &str,
+        regions: &[HighlightRegion],
+        breaks: &[usize],
+    ) -> io::Result<()> {
+        // columns where `--field-boundaries glyph` inserts an extra character, once however many fields start there
+        let mut glyphs: Vec<usize> = if self.field_boundaries == Some(FieldBoundaries::Glyph) {
+            regions
+                .iter()
+                .filter(|r| {
+                    r.start > 0 && r.end > r.start && regions.iter().any(|o| o.end == r.start)
+                })
+                .map(|r| r.start)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        glyphs.sort_unstable();
+        glyphs.dedup();
+        let columns = Columns::new(line, ColumnMode::Graphemes);
+        let screen_col = |col: usize| match &self.display_widths {
+            Some(widths) => widths.start_of(col),
+            None => columns.column_of(col),
+        };
+        let display_col =
+            |col: usize| screen_col(col) + glyphs.iter().filter(|g| **g <= col).count();
+
+        let mut sorted: Vec<&HighlightRegion> =
+            regions.iter().filter(|r| r.end > r.start).collect();
+        sorted.sort_by_key(|r| r.start);
+
+        let mut labels: Vec<Option<char>> = Vec::new();
+        for r in sorted {
+            let start = display_col(r.start);
+            let width = screen_col(r.end) - screen_col(r.start);
+            // a field of only the combining marks of a grapheme has no column of its own
+            if width == 0 || labels.get(start).is_some_and(|c| c.is_some()) {
+                continue;
+            }
+
+            let name: Vec<char> = r.name.chars().collect();
+            let label: Vec<char> = if name.len() <= width {
+                name
+            } else if width == 1 {
+                name[..1].to_vec()
+            } else {
+                name[..width - 1].iter().copied().chain(['…']).collect()
+            };
+
+            if labels.len() < start + label.len() {
+                labels.resize(start + label.len(), None);
+            }
+            for (i, c) in label.into_iter().enumerate() {
+                if labels[start + i].is_some() {
+                    break;
+                }
+                labels[start + i] = Some(c);
+            }
+        }
+
+        if let Some(&first_break) = breaks.first() {
+            labels.truncate(display_col(first_break));
+        }
+        let prefix = self.gutter(line_index + 1, None, '>').chars().count();
+        let text: String = labels.into_iter().map(|c| c.unwrap_or(' ')).collect();
+        writeln!(
+            out,
+            r#"<span {}>{}{}</span>"#,
+            Style::Label.attr(self.css_classes),
+            " ".repeat(prefix),
+            escape_html(text.trim_end())
+        )?;
+        Ok(())
+    }
+}
+
+/// The styles of the parts of a line other than its fields, each as its class in [`Renderer::stylesheet`] and as an
+/// inline style.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Style {
+    /// Line numbers, the end of each line and other annotations.
+    Muted,
+    /// Columns of a short line which are virtually padded.
+    Ghost,
+    /// Delimiters between fields.
+    Delimiter,
+    /// Characters breaking their field's constraints, and fields that can't be decoded.
+    Violation,
+    /// The note that fields run past the end of a line.
+    Problem,
+    /// The field names of `--labels`.
+    Label,
+    /// The links of `--copy-buttons`.
+    CopyLink,
+    /// Bytes shown in hex.
+    HexByte,
+    /// Stand-ins for bidirectional control characters.
+    BidiControl,
+    /// Characters flagged by `--check-unicode`.
+    Suspicious,
+    /// Lines left out of highlighting, when they're dimmed.
+    Skipped,
+    /// Wide characters with `--display-width`, drawn exactly two columns wide.
+    Wide,
+    /// The stand-ins for spaces and tabs within fields with `--show-padding`.
+    Padding,
+    /// Text after the end of a line's last field.
+    Trailing,
+}
+
+impl Style {
+    const ALL: [Style; 14] = [
+        Style::Muted,
+        Style::Ghost,
+        Style::Delimiter,
+        Style::Violation,
+        Style::Problem,
+        Style::Label,
+        Style::CopyLink,
+        Style::HexByte,
+        Style::BidiControl,
+        Style::Suspicious,
+        Style::Skipped,
+        Style::Wide,
+        Style::Padding,
+        Style::Trailing,
+    ];
+
+    fn class(self) -> &'static str {
+        match self {
+            Style::Muted => "ffh-muted",
+            Style::Ghost => "ffh-ghost",
+            Style::Delimiter => "ffh-delimiter",
+            Style::Violation => "ffh-violation",
+            Style::Problem => "ffh-problem",
+            Style::Label => "ffh-label",
+            Style::CopyLink => "ffh-copy",
+            Style::HexByte => "ffh-hex",
+            Style::BidiControl => "ffh-bidi",
+            Style::Suspicious => "ffh-suspicious",
+            Style::Skipped => "ffh-skipped",
+            Style::Wide => "ffh-wide",
+            Style::Padding => "ffh-padding",
+            Style::Trailing => "ffh-trailing",
+        }
+    }
+
+    fn css(self) -> &'static str {
+        match self {
+            Style::Muted => "color:#909090;",
+            Style::Ghost => "color:#a0a0a0; outline:1px dashed #a0a0a0; outline-offset:-1px;",
+            Style::Delimiter => "color:#909090; font-weight:bold;",
+            Style::Violation => "text-decoration:underline wavy #e00000;",
+            Style::Problem => "color:red;",
+            Style::Label => "color:#606060; font-style:italic;",
+            Style::CopyLink => "color:#909090; font-size:smaller;",
+            Style::HexByte => "display:inline-block; width:2ch; font-size:50%; vertical-align:middle; color:#606060; outline:1px solid #c0c0c0; outline-offset:-1px;",
+            Style::BidiControl => "color:#c00000; outline:1px solid #c00000; outline-offset:-1px;",
+            Style::Suspicious => "color:#b05000; text-decoration:underline wavy #e07000; outline:1px dotted #e07000; outline-offset:-1px;",
+            Style::Skipped => "opacity:0.5;",
+            Style::Wide => "display:inline-block; width:2ch; text-align:center;",
+            Style::Padding => "color:#a0a0a0;",
+            Style::Trailing => "color:#b00000; background:#ffe0e0; outline:1px dashed #c00000; outline-offset:-1px;",
+        }
+    }
+
+    /// The attribute giving an element this style: its class if `classes`, or otherwise its inline style.
+    fn attr(self, classes: bool) -> String {
+        if classes {
+            format!(r#"class="{}""#, self.class())
+        } else {
+            format!(r#"style="{}""#, self.css())
+        }
+    }
+}
+
+/// The class of fields highlighted in `color`, a hex code.
+fn color_class(color: &str) -> String {
+    format!("ffh-c-{}", color.to_lowercase())
+}
+
+/// How fields that overlap each other are drawn.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overlaps {
+    /// Draw fields inside the fields containing them, closing and reopening a field where it crosses the end of another
+    Nest,
+    /// Split the line into segments wherever a field starts or ends, each highlighted as the innermost field covering it
+    Split,
+}
+
+/// The `<abbr>` elements highlighting fields that are open while a line is written. Where fields overlap, elements are
+/// closed and reopened as needed to keep them properly nested.
+struct FieldTags<'a> {
+    /// The line's index in its file, from 0.
+    line_index: usize,
+    line: &'a str,
+    regions: &'a [HighlightRegion],
+    /// The colour of each of `regions`, or `None` for those which aren't drawn.
+    colors: Vec<Option<&'a str>>,
+    /// The style drawn over the colour of each of `regions` whose value matches its `value_pattern`.
+    value_styles: Vec<Option<&'a ValueStyle>>,
+    /// Isolate each field from the direction of its neighbours' text.
+    isolate: bool,
+    /// The `data-` attributes of each of `regions` for `--interactive` or `--search`, or nothing without either.
+    details: Vec<String>,
+    /// What each field's id starts with, as [`Renderer::id_prefix`].
+    id_prefix: &'static str,
+    /// The ids given to fields so far, so a name repeated on the line is given a distinct id each time.
+    anchors: Vec<String>,
+    /// The indexes of the regions each open element draws, outermost element first.
+    open: Vec<Vec<usize>>,
+}
+
+impl FieldTags<'_> {
+    /// The elements to have open at column `col`, outermost first, each as the regions it draws. A field is nested
+    /// inside those starting before it, then those ending after it, then those before it in the syntax. When
+    /// splitting, there is at most one element, drawing every field covering the column.
+    fn elements_at(&self, overlaps: Overlaps, col: usize) -> Vec<Vec<usize>> {
+        let mut covering: Vec<usize> = (0..self.regions.len())
+            .filter(|&i| self.regions[i].start <= col && col < self.regions[i].end)
+            .collect();
+        covering.sort_by_key(|&i| (self.regions[i].start, Reverse(self.regions[i].end), i));
+        self.elements_of(overlaps, &covering)
+    }
+
+    /// The elements to have open over fields `covering` part of the line, outermost first, as [`elements_at`] gives
+    /// them for a column they cover.
+    ///
+    /// [`elements_at`]: FieldTags::elements_at
+    fn elements_of(&self, overlaps: Overlaps, covering: &[usize]) -> Vec<Vec<usize>> {
+        let covering: Vec<usize> = covering
+            .iter()
+            .copied()
+            .filter(|&i| self.colors[i].is_some())
+            .collect();
+        match overlaps {
+            Overlaps::Nest => covering.into_iter().map(|i| vec![i]).collect(),
+            Overlaps::Split if covering.is_empty() => Vec::new(),
+            Overlaps::Split => vec![covering],
+        }
+    }
+
+    /// Close the open elements from the first that isn't in `wanted`, so that those still open start `wanted`.
+    fn close_to<W: Write>(&mut self, out: &mut W, wanted: &[Vec<usize>]) -> io::Result<()> {
+        let kept = self
+            .open
+            .iter()
+            .zip(wanted)
+            .take_while(|(open, wanted)| open == wanted)
+            .count();
+        for _ in kept..self.open.len() {
+            write!(out, "</abbr>")?;
+        }
+        self.open.truncate(kept);
+        Ok(())
+    }
+
+    /// Open the elements of `wanted` that aren't already open, at column `col`. A field starting at `col` is given
+    /// its id, and the boundary with a field ending there is marked once; the rest continue fields closed early to
+    /// keep the elements nested.
+    fn open_to<W: Write>(
+        &mut self,
+        renderer: &Renderer,
+        out: &mut W,
+        wanted: Vec<Vec<usize>>,
+        col: usize,
+    ) -> io::Result<()> {
+        let mut boundary = col > 0 && self.regions.iter().any(|r| r.end == col);
+        for element in wanted.into_iter().skip(self.open.len()) {
+            let starts = element.iter().any(|&i| self.regions[i].start == col);
+            self.write_open(renderer, out, &element, col, boundary && starts)?;
+            boundary &= !starts;
+            self.open.push(element);
+        }
+        Ok(())
+    }
+
+    /// Open an element drawing the regions `element`, in the colour of the innermost and with all their titles.
+    fn write_open<W: Write>(
+        &mut self,
+        renderer: &Renderer,
+        out: &mut W,
+        element: &[usize],
+        col: usize,
+        boundary: bool,
+    ) -> io::Result<()> {
+        let innermost = *element.last().expect("an element draws at least one field");
+        let background = self.colors[innermost].expect("only fields with a colour are drawn");
+        // the element is styled both ways, and written with whichever the renderer uses
+        let mut classes = vec![color_class(background)];
+        let mut style = format!(
+            "background: #{}; color: #{};",
+            background,
+            foreground_color(background)
+        );
+        if self.isolate {
+            classes.push("ffh-iso".to_owned());
+            style.push_str(" unicode-bidi: isolate;");
+        }
+        if renderer.field_boundaries == Some(FieldBoundaries::Underline) {
+            let underline = UNDERLINES[innermost % UNDERLINES.len()];
+            classes.push(format!("ffh-ul-{}", underline));
+            style.push_str(&format!(
+                " text-decoration: underline {} #505050;",
+                underline
+            ));
+        }
+        if let Some(value_style) = self.value_styles[innermost] {
+            classes.push(value_style.class());
+            style.push(' ');
+            style.push_str(&value_style.css());
+        }
+        if let Some(Err(_)) = renderer.field_value(self.line, &self.regions[innermost]) {
+            classes.push(Style::Violation.class().to_owned());
+            style.push_str(" text-decoration: underline wavy #e00000;");
+        }
+        if boundary {
+            match renderer.field_boundaries {
+                Some(FieldBoundaries::Border) => {
+                    classes.push("ffh-edge".to_owned());
+                    style.push_str(" box-shadow: inset 1px 0 0 #505050;")
+                }
+                Some(FieldBoundaries::Glyph) => write!(
+                    out,
+                    r#"<span {}>│</span>"#,
+                    Style::Muted.attr(renderer.css_classes)
+                )?,
+                Some(FieldBoundaries::Underline) | None => (),
+            }
+        }
+        // fields split into the same segment as a shorter one are linked to by an empty anchor
+        let mut id = String::new();
+        for &i in element.iter().filter(|&&i| self.regions[i].start == col) {
+            let anchor = self.anchor(i);
+            if i == innermost {
+                id = format!(r#" id="{}""#, anchor);
+            } else {
+                write!(out, r#"<a id="{}"></a>"#, anchor)?;
+            }
+        }
+        let titles: Vec<String> = element
+            .iter()
+            .map(|&i| renderer.field_title(self.line, &self.regions[i]))
+            .collect();
+        let styled = if renderer.css_classes {
+            format!(r#"class="{}""#, classes.join(" "))
+        } else {
+            format!(r#"style="{}""#, style)
+        };
+        write!(
+            out,
+            r#"<abbr{} title="{}" {}{}>"#,
+            id,
+            escape_html(&titles.join(" › ")),
+            styled,
+            self.details.get(innermost).map_or("", String::as_str)
+        )
+    }
+
+    /// The id of the `i`th region, numbered if a field of the same name already has one on the line.
+    fn anchor(&mut self, i: usize) -> String {
+        let anchor = format!(
+            "{}L{}-{}",
+            self.id_prefix,
+            self.line_index + 1,
+            slugify(&self.regions[i].name)
+        );
+        let repeats = self.anchors.iter().filter(|a| **a == anchor).count();
+        self.anchors.push(anchor.clone());
+        if repeats > 0 {
+            format!("{}-{}", anchor, repeats + 1)
+        } else {
+            anchor
+        }
+    }
+}
+
+/// How the boundary between adjacent fields is marked.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldBoundaries {
+    /// Draw a thin line along the start of the field
+    Border,
+    /// Insert a `│` before the field, for display only
+    Glyph,
+    /// Underline each field, alternating solid, dashed, dotted and double lines, so adjacent fields differ even
+    /// without colour
+    Underline,
+}
+
+/// The fields of a line as a JSON array of `{name, start, length, value}` objects, with 1-based starts, placed as the
+/// syntax file places them.
+pub fn fields_json(line: &str, regions: &[HighlightRegion], limit: Option<usize>) -> String {
+    let fields: Vec<String> = regions
+        .iter()
+        .map(|r| {
+            format!(
+                r#"{{"name":{},"start":{},"length":{},"value":{}}}"#,
+                json_string(&r.name),
+                r.columns.start + 1,
+                r.columns.len(),
+                json_string(&region_value(line, r, limit))
+            )
+        })
+        .collect();
+    format!("[{}]", fields.join(","))
+}
+
+/// A line as a section of a Markdown report: a heading with its line number (from 1) and record type, the line in a
+/// fenced block, and a table of its fields' values, which stop at `limit` like [`fields_json`].
+pub fn markdown_record(
+    line_number: usize,
+    prepared: &PreparedLine,
+    limit: Option<usize>,
+    locale: Locale,
+) -> String {
+    let mut section = format!("## {}", locale.format(Message::Line, &[&line_number]));
+    if let Some(record_type) = &prepared.record_type {
+        section.push_str(&format!(" ({})", markdown_text(record_type)));
+    }
+
+    // the fence must be longer than any run of backticks in the line, or the line would close it
+    let fence = "`".repeat(longest_backtick_run(&prepared.text).max(2) + 1);
+    section.push_str(&format!(
+        "\n\n{}text\n{}\n{}\n",
+        fence, prepared.text, fence
+    ));
+
+    if !prepared.regions.is_empty() {
+        section.push_str(&format!(
+            "\n| {} | {} | {} | {} |\n| --- | ---: | ---: | --- |\n",
+            locale.text(Message::Field),
+            locale.text(Message::Start),
+            locale.text(Message::Length),
+            locale.text(Message::Value)
+        ));
+        for r in &prepared.regions {
+            section.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                markdown_text(&r.name),
+                r.columns.start + 1,
+                r.columns.len(),
+                markdown_code(&region_value(&prepared.text, r, limit))
+            ));
+        }
+    }
+    section
+}
+
+/// Escape `text` so that it shows as written in Markdown, including inside a table cell.
+pub fn markdown_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '#' | '!' | '~' | '&' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `text` as an inline code span for a Markdown table cell, so spaces and punctuation show exactly.
+fn markdown_code(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let ticks = "`".repeat(longest_backtick_run(text) + 1);
+    // a span's content loses one space at each end unless it's all spaces, and can't start or end with a backtick
+    let pad = if text.trim_start_matches(' ').is_empty() {
+        ""
+    } else if text.starts_with(['`', ' ']) || text.ends_with(['`', ' ']) {
+        " "
+    } else {
+        ""
+    };
+    // pipes end the cell even inside code, unless escaped
+    let text = text.replace('|', "\\|").replace(['\n', '\r'], " ");
+    format!("{}{}{}{}{}", ticks, pad, text, pad, ticks)
+}
+
+/// The length of the longest run of backticks in `text`.
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// Write the controls which copy a line's fields as CSV or JSON.
+fn write_copy_buttons<W: Write>(
+    out: &mut W,
+    line: &str,
+    regions: &[HighlightRegion],
+    ghost_from: Option<usize>,
+    locale: Locale,
+    classes: bool,
+) -> io::Result<()> {
+    let values: Vec<String> = regions
+        .iter()
+        .map(|r| region_value(line, r, ghost_from))
+        .collect();
+
+    let mut csv_writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(vec![]);
+    let csv_row = match csv_writer.write_record(&values) {
+        Ok(()) => String::from_utf8_lossy(&csv_writer.into_inner().unwrap_or_default())
+            .trim_end()
+            .to_owned(),
+        Err(_) => String::new(),
+    };
+
+    let json_object = format!(
+        "{{{}}}",
+        regions
+            .iter()
+            .zip(&values)
+            .map(|(r, v)| format!("{}:{}", json_string(&r.name), json_string(v)))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    write!(
+        out,
+        r##"&nbsp;<a href="#" {0} title="{1}" data-copy="{2}">csv</a>&nbsp;<a href="#" {0} title="{3}" data-copy="{4}">json</a>"##,
+        Style::CopyLink.attr(classes),
+        locale.text(Message::CopyCsv),
+        escape_html(&csv_row),
+        locale.text(Message::CopyJson),
+        escape_html(&json_object)
+    )
+}
+
+/// Escape text for use in HTML content or a quoted attribute.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match entity(c) {
+            Some(entity) => escaped.push_str(entity),
+            None => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The entity `c` is written as in HTML, if it can't be written as it is.
+fn entity(c: char) -> Option<&'static str> {
+    match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&#39;"),
+        _ => None,
+    }
+}
+
+/// A hash of a field's name which is the same on every run and platform, for picking its colour with `--stable-colors`.
+fn name_hash(name: &str) -> usize {
+    // 32-bit FNV-1a
+    name.bytes().fold(0x811c_9dc5_u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    }) as usize
+}
+
+/// Write one character of a line, escaped, without the allocation [`escape_html`] needs.
+fn write_html_char<W: Write>(out: &mut W, c: char) -> io::Result<()> {
+    match entity(c) {
+        Some(entity) => out.write_all(entity.as_bytes()),
+        None => write!(out, "{}", c),
+    }
+}
+
+/// Parse a colour given as 3 or 6 hex digits.
+pub fn parse_hex_color(color: &str) -> Option<(u8, u8, u8)> {
+    let color = color.trim().trim_start_matches('#');
+    let digits: Vec<u8> = color
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    match digits[..] {
+        [r, g, b] => Some((r * 17, g * 17, b * 17)),
+        [r1, r2, g1, g2, b1, b2] => Some((r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+        _ => None,
+    }
+}
+
+/// Check `color` is a hex code of 3 or 6 digits or a CSS colour name, as it is written into the report's styles,
+/// returning it as a hex code without any leading `#`.
+pub fn hex_color(color: &str) -> anyhow::Result<String> {
+    let code = color.trim().trim_start_matches('#');
+    if parse_hex_color(code).is_some() {
+        return Ok(code.to_owned());
+    }
+    let name = code.to_lowercase();
+    if let Some((_, hex)) = CSS_COLORS.iter().find(|(n, _)| *n == name) {
+        return Ok((*hex).to_owned());
+    }
+    if let Some(c) = code.chars().find(|c| !c.is_ascii_hexdigit()) {
+        if code.chars().all(|c| c.is_ascii_alphabetic()) {
+            anyhow::bail!(
+                "Colour `{}` isn't a CSS colour name like `lightblue`, or a hex code like `ccc` or `f0c0c0`.",
+                color
+            );
+        }
+        anyhow::bail!(
+            "Colour `{}` has `{}`, which isn't a hex digit. Give a hex code like `ccc` or `f0c0c0`, or a CSS colour name like `lightblue`.",
+            color,
+            c
+        );
+    }
+    anyhow::bail!(
+        "Colour `{}` has {} hex digits, but a hex code has 3 (like `ccc`) or 6 (like `f0c0c0`).",
+        color,
+        code.chars().count()
+    );
+}
+
+/// The colour text on a field of `background` colour is written in: near-black, or near-white if the background is
+/// too dark to read black on.
+pub fn foreground_color(background: &str) -> &'static str {
+    if parse_hex_color(background).is_some_and(is_dark) {
+        "f8f8f8"
+    } else {
+        "020202"
+    }
+}
+
+/// Whether white text contrasts more with the colour `(r, g, b)` than black text does.
+pub fn is_dark((r, g, b): (u8, u8, u8)) -> bool {
+    // relative luminance, as WCAG defines it
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance = 0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b);
+    // the luminance at which the contrast ratios against black and white are equal
+    luminance < 0.179
+}
+
+/// The colours of CSS, by name, which can be given wherever a colour is.
+const CSS_COLORS: [(&str, &str); 148] = [
+    ("aliceblue", "f0f8ff"),
+    ("antiquewhite", "faebd7"),
+    ("aqua", "00ffff"),
+    ("aquamarine", "7fffd4"),
+    ("azure", "f0ffff"),
+    ("beige", "f5f5dc"),
+    ("bisque", "ffe4c4"),
+    ("black", "000000"),
+    ("blanchedalmond", "ffebcd"),
+    ("blue", "0000ff"),
+    ("blueviolet", "8a2be2"),
+    ("brown", "a52a2a"),
+    ("burlywood", "deb887"),
+    ("cadetblue", "5f9ea0"),
+    ("chartreuse", "7fff00"),
+    ("chocolate", "d2691e"),
+    ("coral", "ff7f50"),
+    ("cornflowerblue", "6495ed"),
+    ("cornsilk", "fff8dc"),
+    ("crimson", "dc143c"),
+    ("cyan", "00ffff"),
+    ("darkblue", "00008b"),
+    ("darkcyan", "008b8b"),
+    ("darkgoldenrod", "b8860b"),
+    ("darkgray", "a9a9a9"),
+    ("darkgreen", "006400"),
+    ("darkgrey", "a9a9a9"),
+    ("darkkhaki", "bdb76b"),
+    ("darkmagenta", "8b008b"),
+    ("darkolivegreen", "556b2f"),
+    ("darkorange", "ff8c00"),
+    ("darkorchid", "9932cc"),
+    ("darkred", "8b0000"),
+    ("darksalmon", "e9967a"),
+    ("darkseagreen", "8fbc8f"),
+    ("darkslateblue", "483d8b"),
+    ("darkslategray", "2f4f4f"),
+    ("darkslategrey", "2f4f4f"),
+    ("darkturquoise", "00ced1"),
+    ("darkviolet", "9400d3"),
+    ("deeppink", "ff1493"),
+    ("deepskyblue", "00bfff"),
+    ("dimgray", "696969"),
+    ("dimgrey", "696969"),
+    ("dodgerblue", "1e90ff"),
+    ("firebrick", "b22222"),
+    ("floralwhite", "fffaf0"),
+    ("forestgreen", "228b22"),
+    ("fuchsia", "ff00ff"),
+    ("gainsboro", "dcdcdc"),
+    ("ghostwhite", "f8f8ff"),
+    ("gold", "ffd700"),
+    ("goldenrod", "daa520"),
+    ("gray", "808080"),
+    ("green", "008000"),
+    ("greenyellow", "adff2f"),
+    ("grey", "808080"),
+    ("honeydew", "f0fff0"),
+    ("hotpink", "ff69b4"),
+    ("indianred", "cd5c5c"),
+    ("indigo", "4b0082"),
+    ("ivory", "fffff0"),
+    ("khaki", "f0e68c"),
+    ("lavender", "e6e6fa"),
+    ("lavenderblush", "fff0f5"),
+    ("lawngreen", "7cfc00"),
+    ("lemonchiffon", "fffacd"),
+    ("lightblue", "add8e6"),
+    ("lightcoral", "f08080"),
+    ("lightcyan", "e0ffff"),
+    ("lightgoldenrodyellow", "fafad2"),
+    ("lightgray", "d3d3d3"),
+    ("lightgreen", "90ee90"),
+    ("lightgrey", "d3d3d3"),
+    ("lightpink", "ffb6c1"),
+    ("lightsalmon", "ffa07a"),
+    ("lightseagreen", "20b2aa"),
+    ("lightskyblue", "87cefa"),
+    ("lightslategray", "778899"),
+    ("lightslategrey", "778899"),
+    ("lightsteelblue", "b0c4de"),
+    ("lightyellow", "ffffe0"),
+    ("lime", "00ff00"),
+    ("limegreen", "32cd32"),
+    ("linen", "faf0e6"),
+    ("magenta", "ff00ff"),
+    ("maroon", "800000"),
+    ("mediumaquamarine", "66cdaa"),
+    ("mediumblue", "0000cd"),
+    ("mediumorchid", "ba55d3"),
+    ("mediumpurple", "9370db"),
+    ("mediumseagreen", "3cb371"),
+    ("mediumslateblue", "7b68ee"),
+    ("mediumspringgreen", "00fa9a"),
+    ("mediumturquoise", "48d1cc"),
+    ("mediumvioletred", "c71585"),
+    ("midnightblue", "191970"),
+    ("mintcream", "f5fffa"),
+    ("mistyrose", "ffe4e1"),
+    ("moccasin", "ffe4b5"),
+    ("navajowhite", "ffdead"),
+    ("navy", "000080"),
+    ("oldlace", "fdf5e6"),
+    ("olive", "808000"),
+    ("olivedrab", "6b8e23"),
+    ("orange", "ffa500"),
+    ("orangered", "ff4500"),
+    ("orchid", "da70d6"),
+    ("palegoldenrod", "eee8aa"),
+    ("palegreen", "98fb98"),
+    ("paleturquoise", "afeeee"),
+    ("palevioletred", "db7093"),
+    ("papayawhip", "ffefd5"),
+    ("peachpuff", "ffdab9"),
+    ("peru", "cd853f"),
+    ("pink", "ffc0cb"),
+    ("plum", "dda0dd"),
+    ("powderblue", "b0e0e6"),
+    ("purple", "800080"),
+    ("rebeccapurple", "663399"),
+    ("red", "ff0000"),
+    ("rosybrown", "bc8f8f"),
+    ("royalblue", "4169e1"),
+    ("saddlebrown", "8b4513"),
+    ("salmon", "fa8072"),
+    ("sandybrown", "f4a460"),
+    ("seagreen", "2e8b57"),
+    ("seashell", "fff5ee"),
+    ("sienna", "a0522d"),
+    ("silver", "c0c0c0"),
+    ("skyblue", "87ceeb"),
+    ("slateblue", "6a5acd"),
+    ("slategray", "708090"),
+    ("slategrey", "708090"),
+    ("snow", "fffafa"),
+    ("springgreen", "00ff7f"),
+    ("steelblue", "4682b4"),
+    ("tan", "d2b48c"),
+    ("teal", "008080"),
+    ("thistle", "d8bfd8"),
+    ("tomato", "ff6347"),
+    ("turquoise", "40e0d0"),
+    ("violet", "ee82ee"),
+    ("wheat", "f5deb3"),
+    ("white", "ffffff"),
+    ("whitesmoke", "f5f5f5"),
+    ("yellow", "ffff00"),
+    ("yellowgreen", "9acd32"),
+];
+
+/// Encode `text` as a JSON string literal.
+pub fn json_string(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len() + 2);
+    encoded.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\r' => encoded.push_str("\\r"),
+            '\t' => encoded.push_str("\\t"),
+            c if (c as u32) < 0x20 => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+/// Turn a field name into something usable in an element `id`, e.g. `Account Number` becomes `account-number`.
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+/// Whether `c` is a strongly right-to-left character, as used by Hebrew, Arabic and related scripts.
+pub fn is_rtl(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    )
+}
+
+/// The name of `c` if it is an invisible bidirectional formatting character, which can reorder the text around it.
+pub fn bidi_control_name(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{061C}' => "ARABIC LETTER MARK",
+        '\u{200E}' => "LEFT-TO-RIGHT MARK",
+        '\u{200F}' => "RIGHT-TO-LEFT MARK",
+        '\u{202A}' => "LEFT-TO-RIGHT EMBEDDING",
+        '\u{202B}' => "RIGHT-TO-LEFT EMBEDDING",
+        '\u{202C}' => "POP DIRECTIONAL FORMATTING",
+        '\u{202D}' => "LEFT-TO-RIGHT OVERRIDE",
+        '\u{202E}' => "RIGHT-TO-LEFT OVERRIDE",
+        '\u{2066}' => "LEFT-TO-RIGHT ISOLATE",
+        '\u{2067}' => "RIGHT-TO-LEFT ISOLATE",
+        '\u{2068}' => "FIRST STRONG ISOLATE",
+        '\u{2069}' => "POP DIRECTIONAL ISOLATE",
+        _ => return None,
+    })
+}
+
+/// Whether `text` has anything that could reorder it when displayed: right-to-left characters or bidirectional controls.
+fn has_bidi_text(text: &str) -> bool {
+    text.chars()
+        .any(|c| is_rtl(c) || bidi_control_name(c).is_some())
+}
+
+/// Escape `text` for HTML, isolating it in a `<bdi>` if it could otherwise reorder the text around it.
+pub fn bdi_html(text: &str) -> String {
+    if has_bidi_text(text) {
+        format!("<bdi>{}</bdi>", escape_html(text))
+    } else {
+        escape_html(text)
+    }
+}
+
+/// The byte `c` was read from in `encoding`, or its code point if it wasn't read from a byte of its own.
+pub fn hex_byte(c: char, encoding: Encoding) -> u32 {
+    encoding
+        .encode(&c.to_string())
+        .filter(|bytes| bytes.len() == 1)
+        .map_or(c as u32, |bytes| bytes[0] as u32)
+}
+
+/// Write the byte a character that isn't printable was read from as two small hex digits, taking up its column.
+fn write_hex_byte<W: Write>(
+    out: &mut W,
+    c: char,
+    encoding: Encoding,
+    classes: bool,
+) -> io::Result<()> {
+    let byte = hex_byte(c, encoding);
+    write!(
+        out,
+        r#"<span {} title="0x{1:02X}">{1:02X}</span>"#,
+        Style::HexByte.attr(classes),
+        byte
+    )
+}
+
+/// Write a visible stand-in for an invisible bidirectional control character, taking up its column.
+fn write_bidi_control<W: Write>(out: &mut W, c: char, name: &str, classes: bool) -> io::Result<()> {
+    write!(
+        out,
+        r#"<span {} title="U+{:04X} {}">⇄</span>"#,
+        Style::BidiControl.attr(classes),
+        c as u32,
+        name
+    )
+}
+
+/// Write a suspicious character so it stands out: invisible characters and unusual spaces are shown by a stand-in
+/// taking up their column, and lookalikes are underlined.
+fn write_suspicious_char<W: Write>(
+    out: &mut W,
+    c: char,
+    name: &str,
+    suspicion: Suspicion,
+    classes: bool,
+) -> io::Result<()> {
+    let shown = match suspicion {
+        Suspicion::Invisible => "◌".to_owned(),
+        Suspicion::Space => "⍽".to_owned(),
+        Suspicion::Lookalike(_) => escape_html(&c.to_string()),
+    };
+    write!(
+        out,
+        r#"<span {} title="{}">{}</span>"#,
+        Style::Suspicious.attr(classes),
+        escape_html(&suspicious::describe(c, name, suspicion)),
+        shown
+    )
+}
+
+/// Write the ellipsis standing in for `count` characters hidden by `--covered-only`.
+fn write_hidden_marker<W: Write>(
+    out: &mut W,
+    count: usize,
+    locale: Locale,
+    classes: bool,
+) -> io::Result<()> {
+    write!(
+        out,
+        r#"<span {} title="{}">&hellip;</span>"#,
+        Style::Muted.attr(classes),
+        locale.format(Message::Uncovered, &[&count])
+    )
+}
@@ -0,0 +1,422 @@
+//! The formats a report is written in a line at a time, as each line of the input is highlighted and checked: coloured
+//! text for a terminal, JSON, JSON Lines, CSV and Markdown, and any other implementing [`LineReport`]. The HTML report,
+//! with sections around its lines found by reading the input before them, is written by the command itself.
+
+use std::io::{self, Write};
+
+use crate::{
+    bidi_control_name, fields_json, hex_byte, is_dark, is_rtl, json_string,
+    locale::{Locale, Message},
+    markdown_record, markdown_text, parse_hex_color, region_value, segments,
+    suspicious::{self, Suspicion},
+    value_styles, CheckResult, HighlightRegion, PreparedLine, Renderer, Segment, SegmentKind,
+    ValueStyle,
+};
+
+/// A problem found with a line, kept for the outputs that write it alongside the line.
+#[derive(Debug, Clone)]
+pub struct LineProblem {
+    pub kind: String,
+    /// `error` or `warning`.
+    pub severity: &'static str,
+    pub field: Option<String>,
+    pub value: Option<String>,
+    pub message: String,
+}
+
+impl LineProblem {
+    /// The problem as a JSON object.
+    pub fn json(&self) -> String {
+        format!("{{{}}}", self.json_members())
+    }
+
+    /// The members of the problem's JSON object, without its braces, for the error report to add the line to.
+    pub fn json_members(&self) -> String {
+        format!(
+            "\"kind\":{},\"severity\":\"{}\",\"field\":{},\"value\":{},\"message\":{}",
+            json_string(&self.kind),
+            self.severity,
+            self.field.as_deref().map_or("null".to_owned(), json_string),
+            self.value.as_deref().map_or("null".to_owned(), json_string),
+            json_string(&self.message)
+        )
+    }
+}
+
+/// A line of the input to write to a report, once it's been highlighted and checked.
+pub struct ReportLine<'a> {
+    /// The 0-based index of the line in the input.
+    pub index: usize,
+    /// The line, with its sensitive fields masked.
+    pub prepared: &'a PreparedLine,
+    /// The checks across records the line fails, such as a trailer's control totals that don't match.
+    pub failed_checks: &'a [CheckResult],
+    /// The problems found with the line, for the formats that write them alongside it.
+    pub problems: &'a [LineProblem],
+}
+
+/// A format a report is written in a line at a time.
+pub trait LineReport {
+    /// Whether lines are shown as they look, with any columns `--short-lines pad` adds, rather than as the values of
+    /// their fields, which stop where the padding starts.
+    fn shows_lines(&self) -> bool {
+        false
+    }
+
+    /// Write `line` to the report.
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()>;
+
+    /// Write whatever follows the last line, and flush the report.
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Whether `e` is from writing to a reader that stopped reading, e.g. `head`, which isn't a failure of the report.
+pub fn stopped_reading(e: &anyhow::Error) -> bool {
+    let broken_pipe = |e: &io::Error| e.kind() == io::ErrorKind::BrokenPipe;
+    e.downcast_ref::<io::Error>().is_some_and(broken_pipe)
+        || e.downcast_ref::<csv::Error>()
+            .is_some_and(|e| matches!(e.kind(), csv::ErrorKind::Io(e) if broken_pipe(e)))
+}
+
+/// The report as coloured text for a terminal.
+pub struct AnsiReport<'a, W: Write> {
+    out: W,
+    render: &'a Renderer,
+    locale: Locale,
+    /// Flush each line as it's written, for a followed input.
+    follow: bool,
+}
+
+impl<'a, W: Write> AnsiReport<'a, W> {
+    /// Start the report on `out`, drawn as `render` says, with `notes` above the lines, such as how they've been
+    /// shifted.
+    pub fn new(
+        mut out: W,
+        render: &'a Renderer,
+        notes: &[String],
+        locale: Locale,
+        follow: bool,
+    ) -> io::Result<Self> {
+        for note in notes {
+            writeln!(out, "\x1b[1m{}\x1b[0m {}", locale.text(Message::Note), note)?;
+        }
+        Ok(Self {
+            out,
+            render,
+            locale,
+            follow,
+        })
+    }
+}
+
+impl<W: Write> LineReport for AnsiReport<'_, W> {
+    fn shows_lines(&self) -> bool {
+        true
+    }
+
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        // colours are assigned as the HTML renderer assigns them
+        let colors: Vec<Option<(u8, u8, u8)>> = self
+            .render
+            .field_colors(&line.prepared.text, &line.prepared.regions)
+            .into_iter()
+            .map(|c| c.and_then(parse_hex_color))
+            .collect();
+        write_ansi_line(
+            &mut self.out,
+            line.index,
+            line.prepared,
+            &colors,
+            self.render,
+        )?;
+        for failed in line.failed_checks {
+            writeln!(
+                self.out,
+                "\x1b[0;90m     ! \x1b[0;31m{}\x1b[0m",
+                failed.describe(self.locale)
+            )?;
+        }
+        if self.follow {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// Write one line as coloured text, segment by segment, with `colors` the colour of each of its regions. Where fields
+/// overlap, the innermost is shown. A skipped line is shown as it is, or dimmed if `render` dims skipped lines.
+fn write_ansi_line(
+    out: &mut dyn Write,
+    line_index: usize,
+    prepared: &PreparedLine,
+    colors: &[Option<(u8, u8, u8)>],
+    render: &Renderer,
+) -> io::Result<()> {
+    const MUTED: &str = "\x1b[0;90m";
+    const UNMATCHED: &str = "\x1b[0;31m";
+    const TRAILING: &str = "\x1b[0;7;31m";
+    const RESET: &str = "\x1b[0m";
+
+    let regions = &prepared.regions;
+    let chars: Vec<char> = prepared.text.chars().collect();
+    let value_styles = value_styles(prepared);
+
+    write!(
+        out,
+        "{}{}",
+        MUTED,
+        render.gutter(line_index + 1, prepared.record_type.as_deref(), '>')
+    )?;
+    let mut current = String::new();
+    for segment in segments(prepared, render.trailing_data) {
+        let style = match segment.kind {
+            SegmentKind::Ghost => "\x1b[0;2;4m".to_owned(),
+            SegmentKind::Trailing => TRAILING.to_owned(),
+            SegmentKind::Delimiter => "\x1b[0;1;90m".to_owned(),
+            SegmentKind::Field | SegmentKind::Violation | SegmentKind::Gap => {
+                match segment.innermost().map(|i| (colors[i], value_styles[i])) {
+                    Some((Some((r, g, b)), value_style)) => {
+                        let fg = if is_dark((r, g, b)) { 248 } else { 2 };
+                        // a field whose value matches its value pattern is drawn in its value style over its colour
+                        format!(
+                            "\x1b[0;38;2;{3};{3};{3};48;2;{0};{1};{2}m{4}",
+                            r,
+                            g,
+                            b,
+                            fg,
+                            value_style.map_or(String::new(), ValueStyle::ansi)
+                        )
+                    }
+                    Some((None, _)) => RESET.to_owned(),
+                    None if prepared.skipped && render.dim_skipped => "\x1b[0;2m".to_owned(),
+                    None if prepared.skipped => RESET.to_owned(),
+                    None => UNMATCHED.to_owned(),
+                }
+            }
+        };
+        if style != current {
+            write!(out, "{}", style)?;
+            current = style;
+        }
+        for col in segment.chars.clone() {
+            let chr = if segment.kind == SegmentKind::Delimiter && prepared.is_delimiter(col) {
+                match render.delimiter_shown(prepared, col, chars[col]) {
+                    Some(shown) => shown,
+                    None => continue,
+                }
+            } else {
+                chars[col]
+            };
+            write_ansi_char(out, regions, &segment, col, chr, &current, render)?;
+        }
+    }
+    write!(out, "{} <", MUTED)?;
+    if regions
+        .iter()
+        .any(|r| r.end > prepared.text.chars().count())
+    {
+        write!(
+            out,
+            " {}{}",
+            UNMATCHED,
+            render.locale.text(Message::RegionsBeyondLine)
+        )?;
+    }
+    writeln!(out, "{}", RESET)
+}
+
+/// Write `chr`, the character at `col` of a line with `regions`, in `segment`, which is drawn in the style `current`:
+/// marked if it can't be seen or shouldn't be there, and shown as a stand-in if it's padding and `--show-padding` is set.
+fn write_ansi_char(
+    out: &mut dyn Write,
+    regions: &[HighlightRegion],
+    segment: &Segment,
+    col: usize,
+    chr: char,
+    current: &str,
+    render: &Renderer,
+) -> io::Result<()> {
+    if bidi_control_name(chr).is_some() {
+        // show bidirectional controls rather than letting them reorder the line
+        write!(out, "\x1b[7;31m⇄{}", current)?;
+    } else if render.hex_bytes && chr.is_control() {
+        // a byte of a binary record that isn't printable, such as a line feed, would break the line if written as it is
+        write!(
+            out,
+            "\x1b[7m{:02X}{}",
+            hex_byte(chr, render.encoding),
+            current
+        )?;
+    } else if let Some((_, suspicion)) = render
+        .check_unicode
+        .then(|| suspicious::in_field(regions, col, chr))
+        .flatten()
+    {
+        // mark suspicious characters with a wavy underline, with a stand-in for those that can't be seen
+        match suspicion {
+            Suspicion::Invisible => write!(out, "\x1b[4:3;58;2;224;112;0m◌\x1b[24;59m")?,
+            Suspicion::Space => write!(out, "\x1b[4:3;58;2;224;112;0m⍽\x1b[24;59m")?,
+            Suspicion::Lookalike(_) => write!(out, "\x1b[4:3;58;2;224;112;0m{}\x1b[24;59m", chr)?,
+        }
+    } else if segment.kind == SegmentKind::Violation {
+        let shown = render.padding_shown(regions, col, chr).unwrap_or(chr);
+        write!(out, "\x1b[4:3;58;2;224;0;0m{}\x1b[24;59m", shown)?;
+    } else if let Some(shown) = render.padding_shown(regions, col, chr) {
+        write!(out, "\x1b[90m{}{}", shown, current)?;
+    } else {
+        write!(out, "{}", chr)?;
+    }
+    if let Some(widths) = &render.display_widths {
+        write!(out, "{:1$}", "", widths.padding(col, chr))?;
+    }
+    // keep right-to-left text at the end of a field from pulling what follows into it
+    if is_rtl(chr) && regions.iter().any(|r| r.end == col + 1) {
+        write!(out, "\u{200E}")?;
+    }
+    Ok(())
+}
+
+/// How each line is written as JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLines {
+    /// The line's fields as an array of `{name, start, length, value}` objects.
+    Fields,
+    /// The line's number and its fields, as `{"line":N,"fields":[...]}`.
+    Numbered,
+    /// A JSON Lines record of the line's number, record type, fields and problems.
+    Records,
+}
+
+/// The report as a line of JSON for each line of the input.
+pub struct JsonReport<W: Write> {
+    out: W,
+    lines: JsonLines,
+    /// Flush each line as it's written, for a followed input.
+    follow: bool,
+}
+
+impl<W: Write> JsonReport<W> {
+    pub fn new(out: W, lines: JsonLines, follow: bool) -> Self {
+        Self { out, lines, follow }
+    }
+}
+
+impl<W: Write> LineReport for JsonReport<W> {
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        let prepared = line.prepared;
+        let fields = fields_json(&prepared.text, &prepared.regions, prepared.ghost_from);
+        match self.lines {
+            JsonLines::Records => writeln!(
+                self.out,
+                r#"{{"line":{},"record_type":{},"fields":{},"problems":[{}]}}"#,
+                line.index + 1,
+                prepared
+                    .record_type
+                    .as_deref()
+                    .map_or("null".to_owned(), json_string),
+                fields,
+                line.problems
+                    .iter()
+                    .map(LineProblem::json)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?,
+            JsonLines::Numbered => writeln!(
+                self.out,
+                r#"{{"line":{},"fields":{}}}"#,
+                line.index + 1,
+                fields
+            )?,
+            JsonLines::Fields => writeln!(self.out, "{}", fields)?,
+        }
+        // JSON Lines are passed on as each line is read, so a consumer needn't wait for the whole input; writing blocks
+        // while the consumer is behind, so the input is read no faster than it's processed
+        if self.follow || self.lines == JsonLines::Records {
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// The report as a CSV table of the value of every field of every line, `line,record_type,field,value`, converting the
+/// file to CSV with the same syntax file as its report.
+pub struct CsvReport<W: Write> {
+    writer: csv::Writer<W>,
+    /// Flush each line as it's written, for a followed input.
+    follow: bool,
+}
+
+impl<W: Write> CsvReport<W> {
+    pub fn new(out: W, follow: bool) -> csv::Result<Self> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(["line", "record_type", "field", "value"])?;
+        Ok(Self { writer, follow })
+    }
+}
+
+impl<W: Write> LineReport for CsvReport<W> {
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        let prepared = line.prepared;
+        let line_number = (line.index + 1).to_string();
+        let record_type = prepared.record_type.as_deref().unwrap_or_default();
+        for r in &prepared.regions {
+            let value = region_value(&prepared.text, r, prepared.ghost_from);
+            self.writer
+                .write_record([line_number.as_str(), record_type, &r.name, &value])?;
+        }
+        if self.follow {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// The report as a Markdown document, each line in a fenced block followed by a table of its fields' values, to paste
+/// into an issue or a wiki page.
+pub struct MarkdownReport<W: Write> {
+    out: W,
+    locale: Locale,
+}
+
+impl<W: Write> MarkdownReport<W> {
+    /// Start the report on `out`, headed `title`.
+    pub fn new(mut out: W, title: &str, locale: Locale) -> io::Result<Self> {
+        writeln!(out, "# {}", markdown_text(title))?;
+        Ok(Self { out, locale })
+    }
+}
+
+impl<W: Write> LineReport for MarkdownReport<W> {
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        let prepared = line.prepared;
+        write!(
+            self.out,
+            "\n{}",
+            markdown_record(line.index + 1, prepared, prepared.ghost_from, self.locale)
+        )?;
+        for failed in line.failed_checks {
+            writeln!(
+                self.out,
+                "\n**{}**",
+                markdown_text(&failed.describe(self.locale))
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
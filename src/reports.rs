@@ -0,0 +1,280 @@
+//! Batch mode, `--batch`: a report for each of several input files, all highlighted with the same syntax file, written
+//! to a directory with an `index.html` summarising them, for jobs that receive many files at once.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use log::{error, info};
+
+use crate::{
+    escape_html, file_name, serve::encode_component, write_report, Args, Message, OutputFormat,
+    RunSummary, DARK_THEME, STDIN,
+};
+
+/// The name of the index written alongside the reports.
+const INDEX: &str = "index.html";
+
+/// The report of one input file.
+struct Report {
+    input_file: String,
+    /// The report's file name, which it's linked to by relative to the index.
+    file_name: String,
+    /// What was found, or why the report couldn't be written.
+    outcome: Result<RunSummary, String>,
+}
+
+/// Write a report for each input file, then the index. The run fails if any report couldn't be written, and otherwise
+/// if any input has errors, as a run of one input does.
+pub fn run(args: &Args) -> anyhow::Result<()> {
+    let dir = Path::new(args.batch.as_deref().expect("batch mode"));
+    // the syntax file is the last file given, with every input file before it
+    let mut given: Vec<&str> = args
+        .input_file
+        .iter()
+        .chain(&args.syntax_file)
+        .chain(&args.more_input_files)
+        .map(String::as_str)
+        .collect();
+    let syntax_file = given.pop().expect("required by clap");
+    if given.contains(&STDIN) {
+        bail!("--batch can't read standard input. Give the input files before the syntax file.");
+    }
+    let input_files = input_files(&given)?;
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory {}.", dir.display()))?;
+
+    let mut reports: Vec<Report> = Vec::with_capacity(input_files.len());
+    for input_file in input_files {
+        let input_file = input_file.to_string_lossy().into_owned();
+        let report_name = unused_name(&file_name(&input_file), extension(args), &reports);
+        info!("Writing the report of {} to {}", input_file, report_name);
+        let mut file_args = args.clone();
+        file_args.batch = None;
+        file_args.input_file = Some(input_file.clone());
+        file_args.syntax_file = Some(syntax_file.to_owned());
+        file_args.more_input_files.clear();
+        file_args.output = Some(dir.join(&report_name).to_string_lossy().into_owned());
+        let outcome = write_report(&file_args).map_err(|e| {
+            // the rest of the files are still reported, with this one shown as failed in the index
+            error!("Failed to write the report of {}: {:?}", input_file, e);
+            e.chain()
+                .map(|cause| cause.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        reports.push(Report {
+            input_file,
+            file_name: report_name,
+            outcome,
+        });
+    }
+
+    let path = dir.join(INDEX);
+    let mut out = BufWriter::new(
+        File::create(&path)
+            .with_context(|| format!("Failed to create index {}.", path.display()))?,
+    );
+    write_index_html(&mut out, args, &reports)
+        .and_then(|_| out.flush())
+        .context("Failed to write index.")?;
+
+    let failed = reports.iter().filter(|r| r.outcome.is_err()).count();
+    if failed > 0 {
+        bail!(
+            "Failed to write the reports of {} of the {} input files.",
+            failed,
+            reports.len()
+        );
+    }
+    total(&reports).problems.check()
+}
+
+/// The input files named by `given`: each file itself, every file in each directory, and every file matching each
+/// glob pattern, whose wildcards, `*` and `?`, can only be in its file name. Hidden files are left out, as a shell
+/// would, unless a pattern starts with `.`.
+fn input_files(given: &[&str]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for &given in given {
+        let path = Path::new(given);
+        if path.is_dir() {
+            files.extend(files_in(path, |name| !name.starts_with('.'))?);
+        } else if given.contains(['*', '?']) {
+            let pattern = path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let matched = files_in(dir, |name| {
+                (pattern.starts_with('.') || !name.starts_with('.'))
+                    && wildcard_matches(&pattern, name)
+            })?;
+            if matched.is_empty() {
+                bail!("No input files match `{}`.", given);
+            }
+            files.extend(matched);
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+    Ok(files)
+}
+
+/// The files in `dir` whose names are `wanted`, in order of their names.
+fn files_in(dir: &Path, wanted: impl Fn(&str) -> bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory {}.", dir.display()))?
+    {
+        let path = entry
+            .with_context(|| format!("Failed to read directory {}.", dir.display()))?
+            .path();
+        let name = path
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        if path.is_file() && wanted(&name) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, in which `*` matches any run of characters and `?` any one character.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // where the last `*` was, and the character of the name it's matched up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // let the last `*` match one more character, and try again from there
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The extension of the reports, for their output format.
+fn extension(args: &Args) -> &'static str {
+    match args.output_format {
+        OutputFormat::Html => "html",
+        OutputFormat::Ansi => "txt",
+        OutputFormat::Json => "json",
+        OutputFormat::Jsonl => "jsonl",
+        OutputFormat::Markdown => "md",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Pdf => "pdf",
+        OutputFormat::Xlsx => "xlsx",
+    }
+}
+
+/// The file name of the report of the input file `name`, e.g. `data.dat.html`, numbered, as `data.dat-2.html`, if
+/// another input of the same name, from another directory, already has it.
+fn unused_name(name: &str, extension: &str, reports: &[Report]) -> String {
+    let taken = |candidate: &str| {
+        candidate == INDEX || reports.iter().any(|report| report.file_name == candidate)
+    };
+    let mut candidate = format!("{}.{}", name, extension);
+    let mut number = 1;
+    while taken(&candidate) {
+        number += 1;
+        candidate = format!("{}-{}.{}", name, number, extension);
+    }
+    candidate
+}
+
+/// What was found over every report that was written.
+fn total(reports: &[Report]) -> RunSummary {
+    let mut total = RunSummary::default();
+    for summary in reports.iter().filter_map(|r| r.outcome.as_ref().ok()) {
+        total.lines += summary.lines;
+        total.problems.add(&summary.problems);
+    }
+    total
+}
+
+/// Write the index: a table of each input file, as it was given, linked to its report, with its lines, errors and warnings.
+fn write_index_html(out: &mut dyn Write, args: &Args, reports: &[Report]) -> std::io::Result<()> {
+    let locale = args.locale;
+    let title = locale.format(Message::BatchTitle, &[&reports.len()]);
+    writeln!(out, r#"<!doctype html><html lang="{}">"#, locale.tag())?;
+    writeln!(
+        out,
+        r#"<head><meta charset="utf8"><title>{}</title>{}</head>"#,
+        escape_html(&title),
+        if args.dark { DARK_THEME } else { "" }
+    )?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape_html(&title))?;
+    writeln!(
+        out,
+        r#"<table style="border-collapse:collapse;"><tr><th style="text-align:left;">{}</th><th>{}</th><th>{}</th><th>{}</th></tr>"#,
+        locale.text(Message::File),
+        locale.text(Message::Lines),
+        locale.text(Message::Errors),
+        locale.text(Message::Warnings)
+    )?;
+    let row = r#"<tr style="border-top:1px solid #ddd;">"#;
+    for report in reports {
+        // as given, so inputs of the same name in different directories can be told apart
+        let name = escape_html(&report.input_file);
+        match &report.outcome {
+            Ok(summary) => writeln!(
+                out,
+                r#"{}<td><a href="{}">{}</a></td><td style="text-align:right;">{}</td><td style="text-align:right;{}">{}</td><td style="text-align:right;">{}</td></tr>"#,
+                row,
+                // a name can have characters that mean something in a URL, such as `#`, as well as in HTML
+                encode_component(&report.file_name),
+                name,
+                summary.lines,
+                if summary.problems.errors > 0 {
+                    " color:red;"
+                } else {
+                    ""
+                },
+                summary.problems.errors,
+                summary.problems.warnings
+            )?,
+            Err(message) => writeln!(
+                out,
+                r#"{}<td>{}</td><td colspan="3" style="color:red;">{}</td></tr>"#,
+                row,
+                name,
+                escape_html(&locale.format(Message::Failed, &[message]))
+            )?,
+        }
+    }
+    let total = total(reports);
+    writeln!(
+        out,
+        r#"<tr style="border-top:2px solid #999; font-weight:bold;"><td>{}</td><td style="text-align:right;">{}</td><td style="text-align:right;">{}</td><td style="text-align:right;">{}</td></tr>"#,
+        locale.text(Message::Total),
+        total.lines,
+        total.problems.errors,
+        total.problems.warnings
+    )?;
+    writeln!(out, "</table>")?;
+    writeln!(out, "</body></html>")
+}
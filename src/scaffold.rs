@@ -0,0 +1,200 @@
+//! The `scaffold` subcommand, which starts a syntax file for a file with several record types: it finds the distinct
+//! values of a discriminator field in a sample and writes a section of rules for each, conditioned on that value.
+
+use std::{io::BufReader, io::Write};
+
+use anyhow::{bail, Context};
+use log::{error, info};
+use regex::escape;
+
+use crate::{open_input, parse_delimiter, Args, BoundedLines, Delimiter, ScaffoldArgs};
+
+/// More distinct values than this suggest the discriminator is in the wrong place.
+const MANY_RECORD_TYPES: usize = 50;
+
+/// A record type found in the sample.
+struct RecordType {
+    value: String,
+    lines: usize,
+    /// The longest line of this type, in columns or fields.
+    longest: usize,
+}
+
+/// Parse the discriminator position: `START-END` columns (or a single column), or a field number in delimiter mode.
+fn parse_range(text: &str, index_base: usize) -> anyhow::Result<(usize, usize)> {
+    let (start, end) = text.split_once('-').unwrap_or((text, text));
+    let parse = |n: &str| -> anyhow::Result<usize> {
+        let n: usize = n
+            .trim()
+            .parse()
+            .with_context(|| format!("Discriminator `{}` should be a column range, e.g. `1-2`, or a field number in delimiter mode.", text))?;
+        if n < index_base {
+            bail!(
+                "Discriminator `{}` starts before the first column (or field), which is {}.",
+                text,
+                index_base
+            );
+        }
+        Ok(n - index_base)
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if end < start {
+        bail!("Discriminator `{}` ends before it starts.", text);
+    }
+    Ok((start, end + 1))
+}
+
+/// Run the `scaffold` subcommand.
+pub fn run(args: &Args, scaffold: &ScaffoldArgs) -> anyhow::Result<()> {
+    let index_base = args.index_base as usize;
+    let (start, end) = parse_range(&scaffold.discriminator, index_base)?;
+    if args.delimiter.is_some() && end != start + 1 {
+        bail!("In delimiter mode, the discriminator is a single field number.");
+    }
+
+    let delimiter = parse_delimiter(args)?;
+    let mut types: Vec<RecordType> = Vec::new();
+    let file = open_input(args, &scaffold.input_file)?;
+    for line in BoundedLines::new(BufReader::new(file), args.max_line_length, args.encoding)
+        .fixed_length(args.record_length)
+        .line_endings(args)
+    {
+        let line = line.context("Failed to read line from input file.")?;
+        let (value, length) = match &delimiter {
+            Some(delimiter) => {
+                let mut fields = delimiter.split(args.quote, &line);
+                let length = fields.len();
+                ((start < length).then(|| fields.swap_remove(start)), length)
+            }
+            None => {
+                let chars: Vec<char> = line.chars().collect();
+                let value = (chars.len() > start)
+                    .then(|| chars[start..end.min(chars.len())].iter().collect());
+                (value, chars.len())
+            }
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        match types.iter_mut().find(|t| t.value == value) {
+            Some(t) => {
+                t.lines += 1;
+                t.longest = t.longest.max(length);
+            }
+            None => types.push(RecordType {
+                value,
+                lines: 1,
+                longest: length,
+            }),
+        }
+    }
+    if types.is_empty() {
+        bail!("No line of the sample reaches the discriminator.");
+    }
+    if types.len() > MANY_RECORD_TYPES {
+        error!(
+            "Found {} different values, which is a lot of record types. Check the discriminator is in the right place.",
+            types.len()
+        );
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    {
+        let mut writer = csv::Writer::from_writer(&mut out);
+        match &delimiter {
+            Some(delimiter) => {
+                writer.write_record(["field", "name", "condition"])?;
+                writer.write_record([
+                    (start + index_base).to_string(),
+                    "record_type".to_owned(),
+                    String::new(),
+                ])?;
+                let d = match delimiter {
+                    Delimiter::Text(text) => escape(text),
+                    Delimiter::Regex(regex) => format!("(?:{})", regex.as_str()),
+                };
+                // a field before the discriminator, which a single character delimiter can't be part of, and which may
+                // be quoted and so hold the delimiter
+                let single = match delimiter {
+                    Delimiter::Text(text) if text.chars().count() == 1 => Some(&d),
+                    _ => None,
+                };
+                let field = match (args.quote, single) {
+                    (Some(quote), Some(d)) => {
+                        let q = escape(&quote.to_string());
+                        format!("(?:{q}(?:[^{q}]|{q}{q})*{q}|[^{d}{q}]*)", q = q, d = d)
+                    }
+                    (Some(quote), None) => {
+                        let q = escape(&quote.to_string());
+                        format!("(?:{q}(?:[^{q}]|{q}{q})*{q}|[^{q}]*?)", q = q)
+                    }
+                    (None, Some(d)) => format!("[^{}]*", d),
+                    (None, None) => ".*?".to_owned(),
+                };
+                for t in &types {
+                    let before = if start == 0 {
+                        String::new()
+                    } else {
+                        format!("(?:{}{}){{{}}}", field, d, start)
+                    };
+                    let condition = format!("^{}{}(?:{}|$)", before, escape(&t.value), d);
+                    for field in (0..t.longest).filter(|f| *f != start) {
+                        writer.write_record([
+                            (field + index_base).to_string(),
+                            format!("{}_field_{}", t.value.trim(), field + index_base),
+                            condition.clone(),
+                        ])?;
+                    }
+                }
+            }
+            None => {
+                writer.write_record(["start", "length", "name", "condition"])?;
+                writer.write_record([
+                    (start + index_base).to_string(),
+                    (end - start).to_string(),
+                    "record_type".to_owned(),
+                    String::new(),
+                ])?;
+                for t in &types {
+                    let condition = if start == 0 {
+                        format!("^{}", escape(&t.value))
+                    } else {
+                        format!("^.{{{}}}{}", start, escape(&t.value))
+                    };
+                    // a placeholder for the rest of the record on either side of the discriminator, to be split up
+                    if start > 0 {
+                        writer.write_record([
+                            index_base.to_string(),
+                            start.to_string(),
+                            format!("{}_before", t.value.trim()),
+                            condition.clone(),
+                        ])?;
+                    }
+                    if t.longest > end {
+                        writer.write_record([
+                            (end + index_base).to_string(),
+                            (t.longest - end).to_string(),
+                            format!("{}_data", t.value.trim()),
+                            condition,
+                        ])?;
+                    }
+                }
+            }
+        }
+        writer.flush()?;
+    }
+
+    match &scaffold.output {
+        Some(path) => std::fs::write(path, out).context("Failed to write syntax file.")?,
+        None => std::io::stdout()
+            .write_all(&out)
+            .context("Failed to write syntax file.")?,
+    }
+    for t in &types {
+        info!(
+            "Record type '{}': {} line(s), up to {} long",
+            t.value, t.lines, t.longest
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,335 @@
+//! The `schema-convert` subcommand, which converts a fixed width syntax file into the equivalent delimiter mode one,
+//! or back again given the width of each field, so a layout can be reused when a feed changes transport format.
+
+use std::io::Write;
+
+use anyhow::{bail, Context};
+use log::error;
+
+use crate::{
+    load_syntax, Args, ControlCheck, DelimiterHighlightRecord, FieldType,
+    FixedWidthHighlightRecord, RecordList, RecordType, SchemaConvertArgs, SyntaxRule, ValueStyle,
+};
+
+/// Whether two rules can apply to the same line: they do unless they belong to different record types, and if either
+/// is unconditional or their conditions are the same.
+fn can_coexist(a: &impl SyntaxRule, b: &impl SyntaxRule) -> bool {
+    let same_record = match (a.record(), b.record()) {
+        (Some(a), Some(b)) => a.name == b.name,
+        _ => true,
+    };
+    let (a, b) = (a.condition(), b.condition());
+    same_record && (a.is_none() || b.is_none() || a == b)
+}
+
+/// Number each fixed width rule's field by its position among the distinct start columns of the rules that can apply
+/// to the same lines.
+fn to_delimited(
+    fw_records: &[FixedWidthHighlightRecord],
+    index_base: usize,
+) -> Vec<(usize, &FixedWidthHighlightRecord)> {
+    let mut converted = Vec::new();
+    for record in fw_records {
+        if record.start_capture.is_some() {
+            error!(
+                "Syntax record '{}' left out as its start depends on what its line captures, so its field number isn't known.",
+                record.name
+            );
+            continue;
+        }
+        let Some(start) = record.start else {
+            error!(
+                "Syntax record '{}' left out as it has no 'start'.",
+                record.name
+            );
+            continue;
+        };
+        if record.from_end {
+            error!(
+                "Syntax record '{}' left out as it's placed from the end of the line, so its field number isn't known.",
+                record.name
+            );
+            continue;
+        }
+        let mut earlier: Vec<usize> = fw_records
+            .iter()
+            .filter(|other| can_coexist(record, *other) && !other.from_end)
+            .filter_map(|other| other.start)
+            .filter(|s| *s < start)
+            .collect();
+        earlier.sort_unstable();
+        earlier.dedup();
+        converted.push((earlier.len() + index_base, record));
+    }
+    converted
+}
+
+/// Lay delimiter mode rules out as fixed width columns, in field order, each as wide as `widths` gives for the rules
+/// of that field.
+fn to_fixed_width<'a>(
+    d_records: &'a [DelimiterHighlightRecord],
+    widths: &[(String, usize)],
+    index_base: usize,
+) -> anyhow::Result<Vec<(usize, usize, &'a DelimiterHighlightRecord)>> {
+    let width_of = |name: &str| widths.iter().find(|(n, _)| n == name).map(|(_, w)| *w);
+    let missing: Vec<&str> = d_records
+        .iter()
+        .filter(|r| width_of(&r.name).is_none())
+        .map(|r| &*r.name)
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "No width was given for {}. Add them to --widths.",
+            missing.join(", ")
+        );
+    }
+
+    let mut converted = Vec::new();
+    for record in d_records {
+        if record.field_capture.is_some() {
+            error!(
+                "Syntax record '{}' left out as its field depends on what its line captures, so its columns aren't known.",
+                record.name
+            );
+            continue;
+        }
+        let Some(field) = record.field.filter(|f| *f >= index_base) else {
+            error!(
+                "Syntax record '{}' left out as it has no usable 'field'.",
+                record.name
+            );
+            continue;
+        };
+        // each earlier field is as wide as the widest rule for it that can apply to the same lines
+        let mut start = index_base;
+        for earlier in index_base..field {
+            start += d_records
+                .iter()
+                .filter(|other| other.field == Some(earlier))
+                .filter(|other| can_coexist(record, *other))
+                .filter_map(|other| width_of(&other.name))
+                .max()
+                .unwrap_or(0);
+        }
+        converted.push((start, width_of(&record.name).unwrap_or(0), record));
+    }
+    Ok(converted)
+}
+
+/// Parse `--widths`, a comma separated list of `name=width`.
+fn parse_widths(widths: &str) -> anyhow::Result<Vec<(String, usize)>> {
+    widths
+        .split(',')
+        .filter(|w| !w.trim().is_empty())
+        .map(|w| {
+            let (name, width) = w
+                .rsplit_once('=')
+                .with_context(|| format!("Width `{}` should be written `name=width`.", w))?;
+            let width = width
+                .trim()
+                .parse()
+                .with_context(|| format!("Width `{}` isn't a number of columns.", w))?;
+            Ok((name.trim().to_owned(), width))
+        })
+        .collect()
+}
+
+/// The name of the record type a rule belongs to, or nothing.
+fn record_name(record_type: &Option<RecordType>) -> String {
+    record_type
+        .as_ref()
+        .map_or(String::new(), |t| t.name.clone())
+}
+
+/// The row defining `record_type`, with `positions` empty columns between its name and its condition.
+fn definition_row(record_type: &RecordType, positions: usize) -> Vec<String> {
+    let mut row = vec![record_type.name.clone()];
+    row.extend(std::iter::repeat_n(String::new(), positions));
+    row.push(
+        record_type
+            .condition
+            .as_ref()
+            .map_or(String::new(), |c| c.as_str().to_owned()),
+    );
+    row.extend(std::iter::repeat_n(String::new(), 12));
+    row
+}
+
+/// Run the `schema-convert` subcommand.
+pub fn run(args: &Args, convert: &SchemaConvertArgs) -> anyhow::Result<()> {
+    let (_, records, _, assembly) = load_syntax(args, &convert.syntax_file, None)?;
+    if assembly.joins_lines() {
+        error!("`continues_if` has been left out, as lines are continued differently in the new format. Add it back once you know how.");
+    }
+    let index_base = args.index_base as usize;
+    let record_types: Vec<RecordType> = records.record_types().into_iter().cloned().collect();
+
+    let mut out: Vec<u8> = Vec::new();
+    {
+        let mut writer = csv::Writer::from_writer(&mut out);
+        match &records {
+            RecordList::FixedWidth(fw_records) => {
+                if convert.widths.is_some() {
+                    bail!("--widths is only needed to convert a delimiter mode syntax file.");
+                }
+                writer.write_record([
+                    "record",
+                    "field",
+                    "name",
+                    "condition",
+                    "charset",
+                    "case",
+                    "pattern",
+                    "justify",
+                    "pad",
+                    "encoding",
+                    "type",
+                    "color",
+                    "value_pattern",
+                    "value_style",
+                    "sensitive",
+                    "check",
+                ])?;
+                for record_type in &record_types {
+                    writer.write_record(definition_row(record_type, 2))?;
+                }
+                for (field, record) in to_delimited(fw_records, index_base) {
+                    writer.write_record([
+                        record_name(&record.record),
+                        field.to_string(),
+                        record.name.to_string(),
+                        record
+                            .condition
+                            .as_ref()
+                            .map_or(String::new(), |c| c.as_str().to_owned()),
+                        record
+                            .charset
+                            .as_ref()
+                            .map_or(String::new(), |c| c.spec().to_owned()),
+                        record.case.map_or(String::new(), |c| c.name().to_owned()),
+                        record
+                            .pattern
+                            .as_ref()
+                            .map_or(String::new(), |p| p.spec().to_owned()),
+                        record
+                            .justify
+                            .map_or(String::new(), |j| j.name().to_owned()),
+                        record.pad.map_or(String::new(), String::from),
+                        record
+                            .encoding
+                            .map_or(String::new(), |e| e.name().to_owned()),
+                        record
+                            .field_type
+                            .as_ref()
+                            .map_or(String::new(), FieldType::spec),
+                        record.color.clone().unwrap_or_default(),
+                        record
+                            .value_pattern
+                            .as_ref()
+                            .map_or(String::new(), |p| p.spec().to_owned()),
+                        record
+                            .value_style
+                            .as_ref()
+                            .map_or(String::new(), ValueStyle::spec),
+                        if record.sensitive { "true" } else { "" }.to_owned(),
+                        record
+                            .check
+                            .as_ref()
+                            .map_or(String::new(), ControlCheck::spec),
+                    ])?;
+                }
+            }
+            RecordList::Delimiter(_, d_records) => {
+                let Some(widths) = &convert.widths else {
+                    bail!("Converting a delimiter mode syntax file needs the width of each field, given with --widths.");
+                };
+                let widths = parse_widths(widths)?;
+                writer.write_record([
+                    "record",
+                    "start",
+                    "length",
+                    "name",
+                    "condition",
+                    "charset",
+                    "case",
+                    "pattern",
+                    "justify",
+                    "pad",
+                    "encoding",
+                    "type",
+                    "color",
+                    "value_pattern",
+                    "value_style",
+                    "sensitive",
+                    "check",
+                ])?;
+                for record_type in &record_types {
+                    writer.write_record(definition_row(record_type, 3))?;
+                }
+                for (start, length, record) in to_fixed_width(d_records, &widths, index_base)? {
+                    writer.write_record([
+                        record_name(&record.record),
+                        start.to_string(),
+                        length.to_string(),
+                        record.name.to_string(),
+                        record
+                            .condition
+                            .as_ref()
+                            .map_or(String::new(), |c| c.as_str().to_owned()),
+                        record
+                            .charset
+                            .as_ref()
+                            .map_or(String::new(), |c| c.spec().to_owned()),
+                        record.case.map_or(String::new(), |c| c.name().to_owned()),
+                        record
+                            .pattern
+                            .as_ref()
+                            .map_or(String::new(), |p| p.spec().to_owned()),
+                        record
+                            .justify
+                            .map_or(String::new(), |j| j.name().to_owned()),
+                        record.pad.map_or(String::new(), String::from),
+                        record
+                            .encoding
+                            .map_or(String::new(), |e| e.name().to_owned()),
+                        record
+                            .field_type
+                            .as_ref()
+                            .map_or(String::new(), FieldType::spec),
+                        record.color.clone().unwrap_or_default(),
+                        record
+                            .value_pattern
+                            .as_ref()
+                            .map_or(String::new(), |p| p.spec().to_owned()),
+                        record
+                            .value_style
+                            .as_ref()
+                            .map_or(String::new(), ValueStyle::spec),
+                        if record.sensitive { "true" } else { "" }.to_owned(),
+                        record
+                            .check
+                            .as_ref()
+                            .map_or(String::new(), ControlCheck::spec),
+                    ])?;
+                }
+            }
+        }
+        writer.flush()?;
+    }
+    let has_conditions = record_types.iter().any(|t| t.condition.is_some())
+        || match &records {
+            RecordList::FixedWidth(fw_records) => fw_records.iter().any(|r| r.condition.is_some()),
+            RecordList::Delimiter(_, d_records) => d_records.iter().any(|r| r.condition.is_some()),
+        };
+    if has_conditions {
+        error!("Conditions have been copied unchanged. Check they still match, as lines look different in the new format.");
+    }
+
+    match &convert.output {
+        Some(path) => std::fs::write(path, out).context("Failed to write syntax file.")?,
+        None => std::io::stdout()
+            .write_all(&out)
+            .context("Failed to write syntax file.")?,
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Splitting a line into segments: runs of its characters that are drawn alike, being of the same kind and covered by
+//! the same fields. A line's segments are found once, then written out by each renderer (HTML, ANSI text and PDF), so
+//! what's highlighted is decided in one place and each renderer only decides how it looks.
+
+use std::{cmp::Reverse, ops::Range};
+
+use crate::{constraints, PreparedLine};
+
+/// What a segment of a line is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Characters of the fields covering the segment.
+    Field,
+    /// A character of a field breaking its constraints: its charset, case or padding.
+    Violation,
+    /// A delimiter between fields.
+    Delimiter,
+    /// Text no field covers.
+    Gap,
+    /// Text after the end of the line's last field, which the layout doesn't expect.
+    Trailing,
+    /// Columns added to a short line by `--short-lines pad`, which aren't in the line itself.
+    Ghost,
+}
+
+/// A run of a line's characters that are drawn alike.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    /// The characters of the line (counted from 0) in the segment.
+    pub chars: Range<usize>,
+    /// The indexes of the regions covering the segment, outermost first: those starting first, then those ending last,
+    /// then those given first in the syntax. The last is the innermost, which is drawn over the others.
+    pub fields: Vec<usize>,
+    /// Which of the lines a record was joined from (counting from 0) the segment is on.
+    pub row: usize,
+    /// Why the segment breaks its field's constraints, for a [`SegmentKind::Violation`].
+    pub reason: Option<String>,
+}
+
+impl Segment {
+    /// The innermost region covering the segment, if any does.
+    pub fn innermost(&self) -> Option<usize> {
+        self.fields.last().copied()
+    }
+}
+
+/// Split `prepared` into its segments, in order. Text after the last field is a [`SegmentKind::Trailing`] segment if
+/// `trailing_data` is set, or is otherwise a gap.
+pub fn segments(prepared: &PreparedLine, trailing_data: bool) -> Vec<Segment> {
+    let regions = &prepared.regions;
+    let ghost_from = prepared.ghost_from;
+    let trailing_from = prepared.trailing_from().filter(|_| trailing_data);
+    let padding = constraints::padding_violations(&prepared.text, regions, ghost_from);
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut breaks = prepared.breaks.iter().peekable();
+    let mut row = 0;
+    for (col, chr) in prepared.text.chars().enumerate() {
+        while breaks.next_if(|b| **b == col).is_some() {
+            row += 1;
+        }
+        let mut fields: Vec<usize> = (0..regions.len())
+            .filter(|&i| regions[i].start <= col && col < regions[i].end)
+            .collect();
+        fields.sort_by_key(|&i| (regions[i].start, Reverse(regions[i].end), i));
+
+        let mut reason = None;
+        let kind = if ghost_from.is_some_and(|g| col >= g) {
+            SegmentKind::Ghost
+        } else if trailing_from.is_some_and(|t| col >= t) {
+            SegmentKind::Trailing
+        } else if prepared.is_delimiter(col) {
+            SegmentKind::Delimiter
+        } else if fields.is_empty() {
+            SegmentKind::Gap
+        } else {
+            reason = constraints::violation(regions, col, chr, ghost_from).or_else(|| {
+                padding
+                    .iter()
+                    .find(|(c, _)| *c == col)
+                    .map(|(_, reason)| reason.clone())
+            });
+            match reason {
+                Some(_) => SegmentKind::Violation,
+                None => SegmentKind::Field,
+            }
+        };
+
+        match segments.last_mut() {
+            Some(last)
+                if last.kind == kind
+                    && last.fields == fields
+                    && last.row == row
+                    && last.reason == reason =>
+            {
+                last.chars.end = col + 1;
+            }
+            _ => segments.push(Segment {
+                kind,
+                chars: col..col + 1,
+                fields,
+                row,
+                reason,
+            }),
+        }
+    }
+    segments
+}
@@ -0,0 +1,283 @@
+//! Choosing which lines of the input to output, with `--lines`, `--head`, `--tail`, `--sample` and `--filter`, so a
+//! readable report can be made of part of a huge file.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+use crate::{open_buffered, physical_lines, record_text, Args, Assembly, BoundedLines, Condition};
+
+/// A range of lines given to `--lines`, counted from 1, e.g. `1000-2000`, or `1000-` to the end of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineRange {
+    first: usize,
+    last: Option<usize>,
+}
+
+impl FromStr for LineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, last) = s
+            .split_once('-')
+            .ok_or_else(|| format!("'{}' isn't a range of lines such as 1000-2000.", s))?;
+        let first: usize = first
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{}' isn't a line number.", first))?;
+        let last: Option<usize> = match last.trim() {
+            "" => None,
+            last => Some(
+                last.parse()
+                    .map_err(|_| format!("'{}' isn't a line number.", last))?,
+            ),
+        };
+        if first == 0 {
+            return Err("Lines are counted from 1.".to_owned());
+        }
+        if last.is_some_and(|last| last < first) {
+            return Err(format!("The range {} ends before it starts.", s));
+        }
+        Ok(Self { first, last })
+    }
+}
+
+/// Which lines of the input to output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineSelection {
+    /// Every line.
+    All,
+    /// The lines in a range, from 0, the last included.
+    Range { first: usize, last: Option<usize> },
+    /// The first this many lines kept by the filter.
+    Head(usize),
+    /// The last this many lines kept by the filter.
+    Tail(usize),
+    /// This many lines chosen at random from those kept by the filter, in the order they're in.
+    Sample(usize),
+}
+
+impl LineSelection {
+    /// The selection asked for on the command line.
+    pub fn new(args: &Args) -> Self {
+        if let Some(range) = args.lines {
+            Self::Range {
+                first: range.first - 1,
+                last: range.last.map(|last| last - 1),
+            }
+        } else if let Some(n) = args.head {
+            Self::Head(n)
+        } else if let Some(n) = args.tail {
+            Self::Tail(n)
+        } else if let Some(n) = args.sample {
+            Self::Sample(n)
+        } else {
+            Self::All
+        }
+    }
+}
+
+/// The lines selected from the input, each with its index (from 0) in the whole input, so line numbers in the output
+/// are those of the file. A record joined from several lines has the index of the first, and counts as one line when
+/// selecting. Lines not matching the filter are dropped before the selection counts them. `--tail` and
+/// `--sample` only know which lines they want once the input ends, so hold the lines they've chosen so far in memory
+/// until then.
+pub struct SelectedLines<I> {
+    lines: I,
+    selection: LineSelection,
+    /// The expression lines must match to be kept, and whether they must not match it instead.
+    filter: Option<(Condition, bool)>,
+    /// How many lines have been kept by the filter.
+    kept: usize,
+    /// The index of the next line read from `lines`.
+    next_index: usize,
+    /// The lines chosen by `--tail` or `--sample`, once the input has ended.
+    held: Option<std::vec::IntoIter<(usize, String)>>,
+    /// The state of the generator `--sample` chooses lines with.
+    random: u64,
+}
+
+impl<I: Iterator<Item = io::Result<String>>> SelectedLines<I> {
+    /// The seed `--sample` starts from, fixed so that it chooses the same lines on every reading of the same input.
+    const SEED: u64 = 0x5eed_f1e1_d5a3_91e5;
+
+    pub fn new(lines: I, selection: LineSelection) -> Self {
+        Self {
+            lines,
+            selection,
+            filter: None,
+            kept: 0,
+            next_index: 0,
+            held: None,
+            random: Self::SEED,
+        }
+    }
+
+    /// Continue counting from part way through the input, which `lines` must already be positioned at.
+    pub fn starting_at(mut self, index: usize) -> Self {
+        self.next_index = index;
+        self
+    }
+
+    /// Only keep lines matching `filter`, or if `invert` is set, those that don't.
+    pub fn filtered(mut self, filter: Option<Condition>, invert: bool) -> Self {
+        self.filter = filter.map(|filter| (filter, invert));
+        self
+    }
+
+    /// The lines being selected from.
+    pub fn inner(&self) -> &I {
+        &self.lines
+    }
+
+    /// Read the next line of the input, with its index.
+    fn read(&mut self) -> Option<io::Result<(usize, String)>> {
+        let line = self.lines.next()?;
+        let index = self.next_index;
+        self.next_index += line.as_deref().map_or(1, physical_lines);
+        Some(line.map(|line| (index, line)))
+    }
+
+    /// Whether `line` is kept by the filter.
+    fn keeps(&self, line: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|(filter, invert)| filter.is_match(&record_text(line)) != *invert)
+    }
+
+    /// Read the next line of the input the filter keeps, with its index.
+    fn read_kept(&mut self) -> Option<io::Result<(usize, String)>> {
+        loop {
+            match self.read()? {
+                Ok((_, line)) if !self.keeps(&line) => continue,
+                line => {
+                    self.kept += 1;
+                    return Some(line);
+                }
+            }
+        }
+    }
+
+    /// Read the whole input, keeping the lines the selection chooses.
+    fn hold(&mut self) -> io::Result<Vec<(usize, String)>> {
+        let mut held = VecDeque::new();
+        let mut seen: u64 = 0;
+        while let Some(line) = self.read_kept() {
+            let (index, line) = line?;
+            seen += 1;
+            match self.selection {
+                LineSelection::Tail(n) => {
+                    if held.len() == n {
+                        held.pop_front();
+                    }
+                    if n > 0 {
+                        held.push_back((index, line));
+                    }
+                }
+                // reservoir sampling, which keeps each line seen so far with equal chance
+                LineSelection::Sample(n) => {
+                    if held.len() < n {
+                        held.push_back((index, line));
+                    } else {
+                        let j = (self.next_random() % seen) as usize;
+                        if j < n {
+                            held[j] = (index, line);
+                        }
+                    }
+                }
+                _ => unreachable!("only --tail and --sample hold lines"),
+            }
+        }
+        let mut held = Vec::from(held);
+        held.sort_unstable_by_key(|(index, _)| *index);
+        Ok(held)
+    }
+
+    /// The next number from a SplitMix64 generator, which is plenty for choosing lines.
+    fn next_random(&mut self) -> u64 {
+        self.random = self.random.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.random;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl<I: Iterator<Item = io::Result<String>>> Iterator for SelectedLines<I> {
+    type Item = io::Result<(usize, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.selection {
+            LineSelection::All => self.read_kept(),
+            LineSelection::Head(n) => {
+                if self.kept >= n {
+                    return None;
+                }
+                self.read_kept()
+            }
+            LineSelection::Range { first, last } => loop {
+                // the rest of the input needn't be read once the range has ended
+                if last.is_some_and(|last| self.next_index > last) {
+                    return None;
+                }
+                match self.read()? {
+                    Ok((index, ref line)) if index < first || !self.keeps(line) => continue,
+                    line => return Some(line),
+                }
+            },
+            LineSelection::Tail(_) | LineSelection::Sample(_) => {
+                if self.held.is_none() {
+                    match self.hold() {
+                        Ok(held) => self.held = Some(held.into_iter()),
+                        Err(e) => {
+                            self.held = Some(Vec::new().into_iter());
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                self.held.as_mut()?.next().map(Ok)
+            }
+        }
+    }
+}
+
+/// The selected lines of the input file, or of standard input.
+pub type InputLines = SelectedLines<BoundedLines<Box<dyn BufRead>>>;
+
+/// Open the input file, or standard input, and read the lines selected by the command line, joined into records as
+/// `assembly` says.
+pub fn read_selected(
+    args: &Args,
+    input_file: &str,
+    assembly: &Assembly,
+) -> anyhow::Result<InputLines> {
+    Ok(SelectedLines::new(
+        BoundedLines::new(
+            open_buffered(args, input_file)?,
+            args.max_line_length,
+            args.encoding,
+        )
+        .fixed_length(args.record_length)
+        .line_endings(args)
+        .assembled(assembly),
+        LineSelection::new(args),
+    )
+    .filtered(args.filter.clone(), args.invert_filter))
+}
+
+/// Every record of `reader`, joined from its lines as `assembly` says, with the index (from 0) of the line it starts on.
+pub fn read_records<R: BufRead>(
+    args: &Args,
+    reader: R,
+    assembly: &Assembly,
+) -> SelectedLines<BoundedLines<R>> {
+    SelectedLines::new(
+        BoundedLines::new(reader, args.max_line_length, args.encoding)
+            .fixed_length(args.record_length)
+            .line_endings(args)
+            .assembled(assembly),
+        LineSelection::All,
+    )
+}
@@ -0,0 +1,330 @@
+//! The `serve` subcommand: a small web server showing the report of a file a page at a time. Each page is rendered
+//! when it's asked for, with the syntax file read again, and the page reloads itself when the syntax or input file
+//! changes, so a layout can be worked on with the report open beside it.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use log::{error, info};
+
+use crate::{
+    escape_html, load_syntax, locale::Message, open_buffered, prepare_report_line, redact,
+    render_options, select::read_records, write_footer_hint_html, write_lines_start_html,
+    write_page_end_html, write_report_top_html, Args, PreparedLine, ServeArgs, TopSections,
+};
+
+/// Reloads the page when the version of its files, which the server gives at `/version`, is no longer `VERSION`.
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var version = "VERSION";
+  setInterval(function () {
+    fetch("/version", { cache: "no-store" })
+      .then(function (response) { return response.text(); })
+      .then(function (latest) { if (latest !== version) location.reload(); })
+      .catch(function () {});
+  }, 1000);
+})();
+</script>"#;
+
+/// How long to wait for a request before giving up on the connection, so one that's never sent can't stop the server.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A request for a page of the report, or for the version of its files.
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Read a request's line and headers from `stream`, or `None` if the connection closed before it was sent.
+    fn read(stream: &TcpStream) -> anyhow::Result<Option<Request>> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        // the headers aren't needed, but are read so the whole request has been received before it's answered
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+            header.clear();
+        }
+
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_owned();
+        let target = parts.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_component(name), decode_component(value))
+            })
+            .collect();
+        Ok(Some(Request {
+            method,
+            path: decode_component(path),
+            query,
+        }))
+    }
+
+    /// The value of the query parameter `name`, if it was given.
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A part of a URL with its `+`s as spaces and its `%XX` escapes decoded. Escapes that aren't valid are kept as they
+/// are, and bytes that aren't valid UTF-8 are replaced.
+fn decode_component(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| after.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &after[2..];
+            }
+            None => {
+                bytes.push(if byte == b'+' { b' ' } else { byte });
+                rest = after;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// `text` as a query parameter's value, or a segment of a URL's path, with everything but letters, digits and `-._~`
+/// percent-encoded.
+pub fn encode_component(text: &str) -> String {
+    text.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+/// The version of the files the report is made from, which changes whenever either of them is saved.
+fn version(serve: &ServeArgs) -> String {
+    [&serve.syntax_file, &serve.input_file]
+        .iter()
+        .map(|path| {
+            fs::metadata(path).map_or("-".to_owned(), |metadata| {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .unwrap_or_default();
+                format!("{}.{}", modified.as_nanos(), metadata.len())
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The `page`th (from 1) page of the report, of the lines containing `search`, or of every line if it's empty.
+fn page_html(args: &Args, serve: &ServeArgs, page: usize, search: &str) -> anyhow::Result<Vec<u8>> {
+    let (_, records, positions, assembly) =
+        load_syntax(args, &serve.syntax_file, Some(&serve.input_file))?;
+    let render = render_options(args)?;
+    let locale = args.locale;
+
+    let mut out = Vec::new();
+    write_report_top_html(
+        &mut out,
+        args,
+        &serve.input_file,
+        &records,
+        &TopSections::default(),
+    )?;
+    writeln!(
+        out,
+        r#"<form method="get" action="/"><input type="search" name="q" value="{}" placeholder="{}" size="40"></form>"#,
+        escape_html(search),
+        escape_html(locale.text(Message::FilterLines))
+    )?;
+    write_lines_start_html(&mut out, args)?;
+
+    let page_lines = usize::try_from(serve.page_lines).unwrap_or(usize::MAX);
+    let first = (page - 1).saturating_mul(page_lines);
+    let mut matched = 0;
+    for line in read_records(args, open_buffered(args, &serve.input_file)?, &assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        let shown = |line: String| -> anyhow::Result<PreparedLine> {
+            let mut prepared =
+                prepare_report_line(args, &records, line, idx, positions, args.ghost_cells)?;
+            redact(&mut prepared);
+            Ok(prepared)
+        };
+        // lines off the page are only counted, which is quick enough to find how many pages there are on every request
+        let on_page =
+            |matched: usize| first < matched && matched <= first.saturating_add(page_lines);
+        let prepared = if search.is_empty() {
+            matched += 1;
+            if !on_page(matched) {
+                continue;
+            }
+            shown(line)?
+        } else {
+            // lines are searched as they're shown, so the values of sensitive fields can't be found by searching
+            let prepared = shown(line)?;
+            if !prepared.text.contains(search) {
+                continue;
+            }
+            matched += 1;
+            if !on_page(matched) {
+                continue;
+            }
+            prepared
+        };
+        render.write_html_line(&mut out, idx, &prepared, None)?;
+    }
+    writeln!(out, "</pre>")?;
+
+    let pages = matched.div_ceil(page_lines).max(1);
+    let link = |page: usize| {
+        let mut href = format!("/?page={}", page);
+        if !search.is_empty() {
+            href.push_str(&format!("&q={}", encode_component(search)));
+        }
+        escape_html(&href)
+    };
+    let mut links = Vec::new();
+    if page > 1 {
+        links.push(format!(
+            r#"<a href="{}">&larr; {}</a>"#,
+            link(page - 1),
+            locale.text(Message::PreviousPage)
+        ));
+    }
+    links.push(if matched > first {
+        locale.format(
+            Message::PageLines,
+            &[
+                &(first + 1),
+                &matched.min(first.saturating_add(page_lines)),
+                &matched,
+            ],
+        )
+    } else {
+        locale.text(Message::NoMatches).to_owned()
+    });
+    if page < pages {
+        links.push(format!(
+            r#"<a href="{}">{} &rarr;</a>"#,
+            link(page + 1),
+            locale.text(Message::NextPage)
+        ));
+    }
+    writeln!(out, "<p>{}</p>", links.join(" &middot; "))?;
+    write_footer_hint_html(&mut out, args)?;
+    writeln!(out, "{}", RELOAD_SCRIPT.replace("VERSION", &version(serve)))?;
+    write_page_end_html(&mut out, args)?;
+    Ok(out)
+}
+
+/// The page shown in place of the report while it can't be made, such as when the syntax file has a mistake, which
+/// reloads once the file is saved again.
+fn error_html(serve: &ServeArgs, error: &anyhow::Error) -> Vec<u8> {
+    format!(
+        "<!doctype html><html><head><meta charset=\"utf8\"><title>{}</title></head><body>\n<pre style=\"color:red\">{}</pre>\n{}\n</body></html>\n",
+        escape_html(&serve.syntax_file),
+        escape_html(&format!("{:?}", error)),
+        RELOAD_SCRIPT.replace("VERSION", &version(serve))
+    )
+    .into_bytes()
+}
+
+/// Answer one request on `stream`.
+fn respond(args: &Args, serve: &ServeArgs, mut stream: TcpStream) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let Some(request) = Request::read(&stream)? else {
+        return Ok(());
+    };
+    let (status, content_type, body) = match (request.method.as_str(), request.path.as_str()) {
+        ("GET" | "HEAD", "/") => {
+            let page = request
+                .param("page")
+                .and_then(|page| page.parse().ok())
+                .filter(|page| *page > 0)
+                .unwrap_or(1);
+            let search = request.param("q").unwrap_or_default();
+            let body =
+                page_html(args, serve, page, search).unwrap_or_else(|e| error_html(serve, &e));
+            ("200 OK", "text/html; charset=utf-8", body)
+        }
+        ("GET" | "HEAD", "/version") => ("200 OK", "text/plain", version(serve).into_bytes()),
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain", b"Not found\n".to_vec()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Only GET requests are served\n".to_vec(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if request.method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Run the `serve` subcommand, answering requests until it's stopped.
+pub fn run(args: &Args, serve: &ServeArgs) -> anyhow::Result<()> {
+    // the report's pages are of the served files, written as whole pages, and can't be split or follow the input
+    let args = Args {
+        input_file: Some(serve.input_file.clone()),
+        syntax_file: Some(serve.syntax_file.clone()),
+        css: None,
+        snippet: false,
+        two_pane: false,
+        follow: false,
+        ..args.clone()
+    };
+    let listener = TcpListener::bind((serve.bind.as_str(), serve.port))
+        .with_context(|| format!("Failed to listen on {}:{}.", serve.bind, serve.port))?;
+    let address = listener.local_addr()?;
+    println!(
+        "Serving the report of {} at http://{}/",
+        serve.input_file, address
+    );
+    println!("Press Ctrl+C to stop.");
+    for stream in listener.incoming() {
+        let result = stream
+            .context("Failed to accept a connection.")
+            .and_then(|stream| {
+                if let Ok(peer) = stream.peer_addr() {
+                    info!("Answering a request from {}", peer);
+                }
+                respond(&args, serve, stream)
+            });
+        // a request that fails is the browser's problem, not the server's, which carries on
+        if let Err(e) = result {
+            error!("{:?}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! Splitting an HTML report into parts of `--split-every` lines, so a report of millions of lines is never one page a
+//! browser has to load whole. The parts are written next to the report, which becomes an index linking to each.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+
+use crate::{
+    escape_html, write_footer_hint_html, write_lines_start_html, write_page_end_html,
+    write_report_top_html, Args, Locale, Message, RecordList, TopSections,
+};
+
+/// A part of a split report.
+struct Part {
+    /// The part's file name, which it's linked to by relative to the index.
+    file_name: String,
+    /// The 1-based numbers of the first and last lines in the part.
+    first: usize,
+    last: usize,
+}
+
+/// The parts of a split report, written as its lines are.
+pub struct SplitReport {
+    every: usize,
+    /// The index, which the parts are written next to.
+    index: String,
+    parts: Vec<Part>,
+    /// The number of lines written to the last part so far.
+    lines: usize,
+}
+
+impl SplitReport {
+    pub fn new(index: &str, every: usize) -> Self {
+        Self {
+            every,
+            index: index.to_owned(),
+            parts: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    /// The file name of the `number`th (from 1) part: the index's, numbered, as `report-2.html` for `report.html`.
+    fn file_name(&self, number: usize) -> String {
+        let path = Path::new(&self.index);
+        let stem = path
+            .file_stem()
+            .map_or("report".into(), |stem| stem.to_string_lossy());
+        match path.extension() {
+            Some(extension) => format!("{}-{}.{}", stem, number, extension.to_string_lossy()),
+            None => format!("{}-{}", stem, number),
+        }
+    }
+
+    /// Make `out` the part the line `line_index` (from 0) is written to, finishing the last part and starting the next
+    /// if the last is full.
+    pub fn start_line(
+        &mut self,
+        out: &mut Box<dyn Write>,
+        args: &Args,
+        input_file: &str,
+        records: &RecordList,
+        line_index: usize,
+    ) -> anyhow::Result<()> {
+        if self.parts.is_empty() || self.lines == self.every {
+            if !self.parts.is_empty() {
+                self.finish_part(out, args, true)?;
+            }
+            let file_name = self.file_name(self.parts.len() + 1);
+            let path = Path::new(&self.index).with_file_name(&file_name);
+            let file = File::create(&path)
+                .with_context(|| format!("Failed to create report part {}.", path.display()))?;
+            *out = Box::new(BufWriter::new(file));
+            write_report_top_html(out, args, input_file, records, &TopSections::default())?;
+            write_lines_start_html(out, args)?;
+            self.parts.push(Part {
+                file_name,
+                first: line_index + 1,
+                last: line_index + 1,
+            });
+            self.lines = 0;
+        }
+        self.lines += 1;
+        if let Some(part) = self.parts.last_mut() {
+            part.last = line_index + 1;
+        }
+        Ok(())
+    }
+
+    /// Close the last part, linking it to the ones either side of it and the index.
+    fn finish_part(&self, out: &mut dyn Write, args: &Args, has_next: bool) -> anyhow::Result<()> {
+        let number = self.parts.len();
+        writeln!(out, "</pre>")?;
+        let mut links = Vec::new();
+        if number > 1 {
+            links.push(format!(
+                r#"<a href="{}">&larr; {}</a>"#,
+                escape_html(&self.file_name(number - 1)),
+                args.locale.text(Message::PreviousPart)
+            ));
+        }
+        let index_name = Path::new(&self.index)
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        links.push(format!(
+            r#"<a href="{}">{}</a>"#,
+            escape_html(&index_name),
+            args.locale.text(Message::AllParts)
+        ));
+        if has_next {
+            links.push(format!(
+                r#"<a href="{}">{} &rarr;</a>"#,
+                escape_html(&self.file_name(number + 1)),
+                args.locale.text(Message::NextPart)
+            ));
+        }
+        writeln!(out, "<p>{}</p>", links.join(" &middot; "))?;
+        write_footer_hint_html(out, args)?;
+        write_page_end_html(out, args)?;
+        out.flush().context("Failed to write report part.")
+    }
+
+    /// Close the last part, once every line is written.
+    pub fn finish(&self, out: &mut dyn Write, args: &Args) -> anyhow::Result<()> {
+        if self.parts.is_empty() {
+            return Ok(());
+        }
+        self.finish_part(out, args, false)
+    }
+
+    /// Point the links to lines in `html`, such as those of the table of contents, at the parts the lines are in.
+    pub fn retarget(&self, html: &str) -> String {
+        let link = Regex::new(r##"href="#L(\d+)""##).expect("valid regex");
+        link.replace_all(html, |found: &Captures| {
+            let line: usize = found[1].parse().unwrap_or(0);
+            match self
+                .parts
+                .iter()
+                .find(|part| part.first <= line && line <= part.last)
+            {
+                Some(part) => format!(r##"href="{}#L{}""##, escape_html(&part.file_name), line),
+                None => found[0].to_owned(),
+            }
+        })
+        .into_owned()
+    }
+
+    /// Write the list of parts, for the index.
+    pub fn write_index_html(&self, out: &mut dyn Write, locale: Locale) -> io::Result<()> {
+        writeln!(out, "<h2>{}</h2>", locale.text(Message::Parts))?;
+        if self.parts.is_empty() {
+            writeln!(out, "<p>{}</p>", locale.text(Message::NoMatchingLines))?;
+            return Ok(());
+        }
+        writeln!(out, "<ol>")?;
+        for part in &self.parts {
+            writeln!(
+                out,
+                r#"<li><a href="{}">{}</a></li>"#,
+                escape_html(&part.file_name),
+                locale.format(Message::PartLines, &[&part.first, &part.last])
+            )?;
+        }
+        writeln!(out, "</ol>")
+    }
+}
@@ -0,0 +1,250 @@
+//! Statistics of the values of every field over a run, from `--stats`, for working out what an undocumented feed's
+//! fields hold.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::{
+    escape_html,
+    locale::{Locale, Message},
+    region_value, PreparedLine, RecordList,
+};
+
+/// The most distinct values counted for each field. A field with more, such as an id, is only counted as having more,
+/// so a large file doesn't fill memory with every value of it.
+const MAX_DISTINCT: usize = 10_000;
+
+/// What the values of each field were over a run.
+pub struct FieldStats {
+    /// The number of most common values to show for each field.
+    top: usize,
+    fields: Vec<FieldSummary>,
+}
+
+struct FieldSummary {
+    name: String,
+    /// The number of lines the field was present on.
+    lines: u64,
+    blank: u64,
+    /// How often each value occurred, until there are [`MAX_DISTINCT`] of them.
+    values: HashMap<String, u64>,
+    /// Whether there were more distinct values than were counted.
+    overflowed: bool,
+    /// The shortest and longest values, without padding, in characters.
+    lengths: Option<(usize, usize)>,
+    /// The smallest and largest values that are numbers, as written.
+    numbers: Option<(Number, Number)>,
+    /// The number of values that are numbers.
+    numeric: u64,
+}
+
+/// A value that is a number, kept as written so it's shown as it was in the file.
+#[derive(Clone)]
+struct Number {
+    value: f64,
+    text: String,
+}
+
+impl FieldStats {
+    pub fn new(records: &RecordList, top: usize) -> Self {
+        let fields = records
+            .field_names()
+            .into_iter()
+            .map(|name| FieldSummary {
+                name,
+                lines: 0,
+                blank: 0,
+                values: HashMap::new(),
+                overflowed: false,
+                lengths: None,
+                numbers: None,
+                numeric: 0,
+            })
+            .collect();
+        Self { top, fields }
+    }
+
+    /// Count the values of the fields of `prepared`, leaving out any padding added to it.
+    pub fn count_line(&mut self, prepared: &PreparedLine) {
+        let length = prepared
+            .ghost_from
+            .unwrap_or_else(|| prepared.text.chars().count());
+        for field in &mut self.fields {
+            // where a field's rules overlap, the first one that applies gives its value
+            let Some(region) = prepared
+                .regions
+                .iter()
+                .find(|r| *r.name == field.name && r.start < length)
+            else {
+                continue;
+            };
+            field.add(region_value(&prepared.text, region, prepared.ghost_from));
+        }
+    }
+
+    /// Write the statistics to stderr, for output formats with nowhere else to put them.
+    pub fn log(&self) {
+        eprintln!("Field statistics:");
+        for field in &self.fields {
+            eprint!(
+                "  {}: {} line(s), {} blank",
+                field.name, field.lines, field.blank
+            );
+            eprint!(", {} distinct value(s)", field.distinct(Locale::En));
+            if let Some((shortest, longest)) = field.lengths {
+                eprint!(", length {}", range(shortest, longest));
+            }
+            if let Some((smallest, largest)) = &field.numbers {
+                eprint!(
+                    ", {} number(s) from {} to {}",
+                    field.numeric, smallest.text, largest.text
+                );
+            }
+            eprintln!();
+            let common: Vec<String> = field
+                .most_common(self.top)
+                .into_iter()
+                .map(|(value, count)| format!("'{}' ({})", value, count))
+                .collect();
+            if !common.is_empty() {
+                eprintln!("    most common: {}", common.join(", "));
+            }
+        }
+    }
+
+    /// Write the statistics as a section of the report.
+    pub fn write_html(&self, out: &mut dyn Write, locale: Locale) -> io::Result<()> {
+        writeln!(out, "<h2>{}</h2>", locale.text(Message::FieldStatistics))?;
+        writeln!(
+            out,
+            "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            locale.text(Message::Field),
+            locale.text(Message::Lines),
+            locale.text(Message::Blank),
+            locale.text(Message::DistinctValues),
+            locale.text(Message::MostCommon),
+            locale.text(Message::Length),
+            locale.text(Message::Numbers)
+        )?;
+        for field in &self.fields {
+            let common: Vec<String> = field
+                .most_common(self.top)
+                .into_iter()
+                .map(|(value, count)| {
+                    format!("<code>{}</code>&nbsp;({})", escape_html(value), count)
+                })
+                .collect();
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&field.name),
+                field.lines,
+                field.blank,
+                field.distinct(locale),
+                common.join("<br>"),
+                field
+                    .lengths
+                    .map_or(String::new(), |(shortest, longest)| range(shortest, longest)),
+                field.numbers.as_ref().map_or(String::new(), |(smallest, largest)| {
+                    format!(
+                        "{} ({})",
+                        escape_html(&range(&smallest.text, &largest.text)),
+                        field.numeric
+                    )
+                })
+            )?;
+        }
+        writeln!(out, "</table>")?;
+        Ok(())
+    }
+}
+
+impl FieldSummary {
+    fn add(&mut self, value: String) {
+        self.lines += 1;
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            self.blank += 1;
+        }
+
+        let length = trimmed.chars().count();
+        self.lengths = Some(match self.lengths {
+            Some((shortest, longest)) => (shortest.min(length), longest.max(length)),
+            None => (length, length),
+        });
+
+        if let Some(number) = parse_number(trimmed) {
+            self.numeric += 1;
+            self.numbers = Some(match self.numbers.take() {
+                Some((smallest, largest)) => (
+                    if number.value < smallest.value {
+                        number.clone()
+                    } else {
+                        smallest
+                    },
+                    if number.value > largest.value {
+                        number
+                    } else {
+                        largest
+                    },
+                ),
+                None => (number.clone(), number),
+            });
+        }
+
+        if let Some(count) = self.values.get_mut(trimmed) {
+            *count += 1;
+        } else if self.values.len() < MAX_DISTINCT {
+            self.values.insert(trimmed.to_owned(), 1);
+        } else {
+            self.overflowed = true;
+        }
+    }
+
+    /// The number of distinct values, which may be more than were counted.
+    fn distinct(&self, locale: Locale) -> String {
+        if self.overflowed {
+            locale.format(Message::MoreThan, &[&MAX_DISTINCT])
+        } else {
+            self.values.len().to_string()
+        }
+    }
+
+    /// The `top` most common values, with how often each occurred, most common first.
+    fn most_common(&self, top: usize) -> Vec<(&str, u64)> {
+        let mut values: Vec<(&str, u64)> = self
+            .values
+            .iter()
+            .map(|(value, count)| (value.as_str(), *count))
+            .collect();
+        // ties are broken by the value, so the same file always gives the same list
+        values.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        values.truncate(top);
+        values
+    }
+}
+
+/// `value` as a number, if it's written as one: digits with an optional sign and decimal point.
+fn parse_number(value: &str) -> Option<Number> {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if !digits.chars().any(|c| c.is_ascii_digit())
+        || !digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        return None;
+    }
+    value.parse().ok().map(|number| Number {
+        value: number,
+        text: value.to_owned(),
+    })
+}
+
+/// `from` and `to` as a range, or just one of them if they're the same.
+fn range<T: PartialEq + std::fmt::Display>(from: T, to: T) -> String {
+    if from == to {
+        from.to_string()
+    } else {
+        format!("{}–{}", from, to)
+    }
+}
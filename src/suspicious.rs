@@ -0,0 +1,167 @@
+//! Characters which look like something else, or like nothing at all, found by `--check-unicode`. These routinely
+//! make records that look identical compare differently downstream.
+
+use log::error;
+
+use crate::HighlightRegion;
+
+/// Why a character is suspicious.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Suspicion {
+    /// The character takes up no space, so can't be seen.
+    Invisible,
+    /// The character looks like an ordinary space, but isn't one.
+    Space,
+    /// The character looks like this (ASCII) character.
+    Lookalike(char),
+}
+
+/// A suspicious character's name and why it is suspicious, or `None` if it is unremarkable.
+pub fn check(c: char) -> Option<(&'static str, Suspicion)> {
+    use Suspicion::*;
+
+    if c.is_ascii() {
+        return None;
+    }
+    // fullwidth forms of ASCII, e.g. `Ａ`
+    if ('\u{FF01}'..='\u{FF5E}').contains(&c) {
+        let ascii = char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or('?');
+        return Some(("FULLWIDTH FORM", Lookalike(ascii)));
+    }
+
+    Some(match c {
+        '\u{00AD}' => ("SOFT HYPHEN", Invisible),
+        '\u{180E}' => ("MONGOLIAN VOWEL SEPARATOR", Invisible),
+        '\u{200B}' => ("ZERO WIDTH SPACE", Invisible),
+        '\u{200C}' => ("ZERO WIDTH NON-JOINER", Invisible),
+        '\u{200D}' => ("ZERO WIDTH JOINER", Invisible),
+        '\u{2060}' => ("WORD JOINER", Invisible),
+        '\u{FEFF}' => ("ZERO WIDTH NO-BREAK SPACE", Invisible),
+
+        '\u{00A0}' => ("NO-BREAK SPACE", Space),
+        '\u{1680}' => ("OGHAM SPACE MARK", Space),
+        '\u{2000}' => ("EN QUAD", Space),
+        '\u{2001}' => ("EM QUAD", Space),
+        '\u{2002}' => ("EN SPACE", Space),
+        '\u{2003}' => ("EM SPACE", Space),
+        '\u{2004}' => ("THREE-PER-EM SPACE", Space),
+        '\u{2005}' => ("FOUR-PER-EM SPACE", Space),
+        '\u{2006}' => ("SIX-PER-EM SPACE", Space),
+        '\u{2007}' => ("FIGURE SPACE", Space),
+        '\u{2008}' => ("PUNCTUATION SPACE", Space),
+        '\u{2009}' => ("THIN SPACE", Space),
+        '\u{200A}' => ("HAIR SPACE", Space),
+        '\u{202F}' => ("NARROW NO-BREAK SPACE", Space),
+        '\u{205F}' => ("MEDIUM MATHEMATICAL SPACE", Space),
+        '\u{3000}' => ("IDEOGRAPHIC SPACE", Space),
+
+        '\u{2010}' => ("HYPHEN", Lookalike('-')),
+        '\u{2011}' => ("NON-BREAKING HYPHEN", Lookalike('-')),
+        '\u{2012}' => ("FIGURE DASH", Lookalike('-')),
+        '\u{2013}' => ("EN DASH", Lookalike('-')),
+        '\u{2212}' => ("MINUS SIGN", Lookalike('-')),
+        '\u{2018}' => ("LEFT SINGLE QUOTATION MARK", Lookalike('\'')),
+        '\u{2019}' => ("RIGHT SINGLE QUOTATION MARK", Lookalike('\'')),
+        '\u{201C}' => ("LEFT DOUBLE QUOTATION MARK", Lookalike('"')),
+        '\u{201D}' => ("RIGHT DOUBLE QUOTATION MARK", Lookalike('"')),
+        '\u{2024}' => ("ONE DOT LEADER", Lookalike('.')),
+        '\u{2044}' => ("FRACTION SLASH", Lookalike('/')),
+
+        '\u{0391}' => ("GREEK CAPITAL LETTER ALPHA", Lookalike('A')),
+        '\u{0392}' => ("GREEK CAPITAL LETTER BETA", Lookalike('B')),
+        '\u{0395}' => ("GREEK CAPITAL LETTER EPSILON", Lookalike('E')),
+        '\u{0396}' => ("GREEK CAPITAL LETTER ZETA", Lookalike('Z')),
+        '\u{0397}' => ("GREEK CAPITAL LETTER ETA", Lookalike('H')),
+        '\u{0399}' => ("GREEK CAPITAL LETTER IOTA", Lookalike('I')),
+        '\u{039A}' => ("GREEK CAPITAL LETTER KAPPA", Lookalike('K')),
+        '\u{039C}' => ("GREEK CAPITAL LETTER MU", Lookalike('M')),
+        '\u{039D}' => ("GREEK CAPITAL LETTER NU", Lookalike('N')),
+        '\u{039F}' => ("GREEK CAPITAL LETTER OMICRON", Lookalike('O')),
+        '\u{03A1}' => ("GREEK CAPITAL LETTER RHO", Lookalike('P')),
+        '\u{03A4}' => ("GREEK CAPITAL LETTER TAU", Lookalike('T')),
+        '\u{03A5}' => ("GREEK CAPITAL LETTER UPSILON", Lookalike('Y')),
+        '\u{03A7}' => ("GREEK CAPITAL LETTER CHI", Lookalike('X')),
+        '\u{03BF}' => ("GREEK SMALL LETTER OMICRON", Lookalike('o')),
+        '\u{03BD}' => ("GREEK SMALL LETTER NU", Lookalike('v')),
+
+        '\u{0405}' => ("CYRILLIC CAPITAL LETTER DZE", Lookalike('S')),
+        '\u{0406}' => (
+            "CYRILLIC CAPITAL LETTER BYELORUSSIAN-UKRAINIAN I",
+            Lookalike('I'),
+        ),
+        '\u{0408}' => ("CYRILLIC CAPITAL LETTER JE", Lookalike('J')),
+        '\u{0410}' => ("CYRILLIC CAPITAL LETTER A", Lookalike('A')),
+        '\u{0412}' => ("CYRILLIC CAPITAL LETTER VE", Lookalike('B')),
+        '\u{0415}' => ("CYRILLIC CAPITAL LETTER IE", Lookalike('E')),
+        '\u{041A}' => ("CYRILLIC CAPITAL LETTER KA", Lookalike('K')),
+        '\u{041C}' => ("CYRILLIC CAPITAL LETTER EM", Lookalike('M')),
+        '\u{041D}' => ("CYRILLIC CAPITAL LETTER EN", Lookalike('H')),
+        '\u{041E}' => ("CYRILLIC CAPITAL LETTER O", Lookalike('O')),
+        '\u{0420}' => ("CYRILLIC CAPITAL LETTER ER", Lookalike('P')),
+        '\u{0421}' => ("CYRILLIC CAPITAL LETTER ES", Lookalike('C')),
+        '\u{0422}' => ("CYRILLIC CAPITAL LETTER TE", Lookalike('T')),
+        '\u{0423}' => ("CYRILLIC CAPITAL LETTER U", Lookalike('Y')),
+        '\u{0425}' => ("CYRILLIC CAPITAL LETTER HA", Lookalike('X')),
+        '\u{0430}' => ("CYRILLIC SMALL LETTER A", Lookalike('a')),
+        '\u{0435}' => ("CYRILLIC SMALL LETTER IE", Lookalike('e')),
+        '\u{043E}' => ("CYRILLIC SMALL LETTER O", Lookalike('o')),
+        '\u{0440}' => ("CYRILLIC SMALL LETTER ER", Lookalike('p')),
+        '\u{0441}' => ("CYRILLIC SMALL LETTER ES", Lookalike('c')),
+        '\u{0443}' => ("CYRILLIC SMALL LETTER U", Lookalike('y')),
+        '\u{0445}' => ("CYRILLIC SMALL LETTER HA", Lookalike('x')),
+        '\u{0455}' => ("CYRILLIC SMALL LETTER DZE", Lookalike('s')),
+        '\u{0456}' => (
+            "CYRILLIC SMALL LETTER BYELORUSSIAN-UKRAINIAN I",
+            Lookalike('i'),
+        ),
+        '\u{0458}' => ("CYRILLIC SMALL LETTER JE", Lookalike('j')),
+        '\u{0501}' => ("CYRILLIC SMALL LETTER KOMI DE", Lookalike('d')),
+        '\u{051B}' => ("CYRILLIC SMALL LETTER QA", Lookalike('q')),
+        '\u{051D}' => ("CYRILLIC SMALL LETTER WE", Lookalike('w')),
+        _ => return None,
+    })
+}
+
+/// A description of a suspicious character for a tooltip or message, e.g. `U+0430 CYRILLIC SMALL LETTER A, which looks like 'a'`.
+pub fn describe(c: char, name: &str, suspicion: Suspicion) -> String {
+    match suspicion {
+        Suspicion::Invisible => format!("U+{:04X} {}, which is invisible", c as u32, name),
+        Suspicion::Space => format!(
+            "U+{:04X} {}, which looks like an ordinary space",
+            c as u32, name
+        ),
+        Suspicion::Lookalike(like) => {
+            format!("U+{:04X} {}, which looks like '{}'", c as u32, name, like)
+        }
+    }
+}
+
+/// The suspicious character at `col` of a line, if it falls inside one of `regions`. Characters outside every field
+/// aren't part of any value, so aren't flagged.
+pub fn in_field(
+    regions: &[HighlightRegion],
+    col: usize,
+    c: char,
+) -> Option<(&'static str, Suspicion)> {
+    check(c).filter(|_| regions.iter().any(|r| r.start <= col && col < r.end))
+}
+
+/// Log every suspicious character inside a field of a line, returning the number logged.
+pub fn log_line(line_index: usize, text: &str, regions: &[HighlightRegion]) -> usize {
+    let mut logged = 0;
+    for (col, c) in text.chars().enumerate() {
+        if let Some((name, suspicion)) = check(c) {
+            if let Some(r) = regions.iter().find(|r| r.start <= col && col < r.end) {
+                logged += 1;
+                error!(
+                    "Line {}, column {}: field '{}' contains {}.",
+                    line_index + 1,
+                    col + 1,
+                    r.name,
+                    describe(c, name, suspicion)
+                );
+            }
+        }
+    }
+    logged
+}
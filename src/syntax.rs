@@ -0,0 +1,1706 @@
+//! Syntax files: the rules saying where each field of a line is, and reading them from CSV (or compiled) files.
+
+use std::{fmt, fs, path::Path, sync::Arc};
+
+use anyhow::{bail, Context};
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    compiled, constraints, encoding, field_columns, hex_color, locale::Message, totals,
+    value_style, Assembly, Case, Charset, ColumnMode, Columns, ControlCheck, Delimiter, Encoding,
+    FieldType, Justify, Pattern, ValuePattern, ValueStyle,
+};
+
+/// The rules of a syntax file, which are either all fixed width or all for one delimiter.
+pub enum RecordList {
+    FixedWidth(Vec<FixedWidthHighlightRecord>),
+    Delimiter(Delimiter, Vec<DelimiterHighlightRecord>),
+}
+
+impl RecordList {
+    /// The number of rules in the syntax.
+    pub fn len(&self) -> usize {
+        match self {
+            RecordList::FixedWidth(fw_records) => fw_records.len(),
+            RecordList::Delimiter(_, d_records) => d_records.len(),
+        }
+    }
+
+    /// Whether the syntax has no rules at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every rule, in the order of the syntax file, whichever mode it is in.
+    pub fn rules(&self) -> Vec<&dyn SyntaxRule> {
+        match self {
+            RecordList::FixedWidth(fw_records) => {
+                fw_records.iter().map(|r| r as &dyn SyntaxRule).collect()
+            }
+            RecordList::Delimiter(_, d_records) => {
+                d_records.iter().map(|r| r as &dyn SyntaxRule).collect()
+            }
+        }
+    }
+
+    /// Mark the fields named by `names` as sensitive, as `--redact` does, failing on a name no rule gives.
+    pub fn redact(&mut self, names: &[String]) -> anyhow::Result<()> {
+        let known = self.field_names();
+        if let Some(name) = names.iter().find(|name| !known.contains(name)) {
+            bail!("--redact names the field '{}', which no rule gives.", name);
+        }
+        match self {
+            RecordList::FixedWidth(fw_records) => {
+                for record in fw_records
+                    .iter_mut()
+                    .filter(|r| names.iter().any(|name| **name == *r.name))
+                {
+                    record.sensitive = true;
+                }
+            }
+            RecordList::Delimiter(_, d_records) => {
+                for record in d_records
+                    .iter_mut()
+                    .filter(|r| names.iter().any(|name| **name == *r.name))
+                {
+                    record.sensitive = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shift the rules of each record type named by `shifts` by its number of columns (or fields), as `--shift-record`
+    /// does, failing on a record type the syntax file doesn't define.
+    pub fn shift_record_types(&mut self, shifts: &[(String, isize)]) -> anyhow::Result<()> {
+        let known: Vec<String> = self
+            .record_types()
+            .into_iter()
+            .map(|t| t.name.clone())
+            .collect();
+        if let Some((name, _)) = shifts.iter().find(|(name, _)| !known.contains(name)) {
+            bail!(
+                "--shift-record names the record type '{}', which the syntax file doesn't define.",
+                name
+            );
+        }
+        let shift_of = |record: Option<&RecordType>| {
+            shifts
+                .iter()
+                .filter(|(name, _)| record.is_some_and(|r| r.name == *name))
+                .map(|(_, shift)| shift)
+                .sum::<isize>()
+        };
+        match self {
+            RecordList::FixedWidth(fw_records) => {
+                for record in fw_records.iter_mut() {
+                    record.type_shift = shift_of(record.record.as_ref());
+                }
+            }
+            RecordList::Delimiter(_, d_records) => {
+                for record in d_records.iter_mut() {
+                    record.type_shift = shift_of(record.record.as_ref());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The distinct rule names, in the order they first appear.
+    pub fn field_names(&self) -> Vec<String> {
+        let mut distinct: Vec<String> = Vec::new();
+        for rule in self.rules() {
+            if !distinct.iter().any(|n| n == rule.name()) {
+                distinct.push(rule.name().to_owned());
+            }
+        }
+        distinct
+    }
+
+    /// The record types rules belong to, in the order they were defined.
+    pub fn record_types(&self) -> Vec<&RecordType> {
+        let mut types: Vec<&RecordType> = Vec::new();
+        for rule in self.rules() {
+            if let Some(record) = rule.record() {
+                if !types.contains(&record) {
+                    types.push(record);
+                }
+            }
+        }
+        types.sort_by_key(|t| t.line);
+        types
+    }
+
+    /// The record type of `line`: the first it matches the condition of, or `None` if it matches none (or there are no
+    /// record types).
+    pub fn record_type(&self, line: &str) -> Option<&RecordType> {
+        self.record_types()
+            .into_iter()
+            .find(|record_type| condition_matches(&record_type.condition, line))
+    }
+
+    /// The delimiter between fields, if this is a delimiter mode syntax.
+    pub fn delimiter(&self) -> Option<&Delimiter> {
+        match self {
+            RecordList::FixedWidth(_) => None,
+            RecordList::Delimiter(delimiter, _) => Some(delimiter),
+        }
+    }
+
+    /// Whether any rule is placed by a `${name}` placeholder, so where it is depends on the line.
+    pub fn places_by_capture(&self) -> bool {
+        self.rules()
+            .iter()
+            .any(|rule| !rule.placeholders().is_empty())
+    }
+}
+
+/// A rule placing a field by its start column and length.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct FixedWidthHighlightRecord {
+    /// The line of the syntax file this record was read from.
+    #[serde(skip)]
+    pub line: u64,
+    pub start: Option<usize>,
+    pub length: Option<usize>,
+    /// The named capture group whose value on each line is `start`, from a `${name}` placeholder.
+    #[serde(skip)]
+    pub start_capture: Option<String>,
+    /// The named capture group whose value on each line is `length`, from a `${name}` placeholder.
+    #[serde(skip)]
+    pub length_capture: Option<String>,
+    /// The field `start` is the column after, from an `after:NAME` anchor, which [`resolve_anchors`] sets `start` by.
+    #[serde(skip)]
+    pub start_anchor: Option<Anchor>,
+    /// Count `start` back from the end of the line rather than from its start, for fields after a variable length
+    /// middle.
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub from_end: bool,
+    /// The field's name, shared with the region of every line the rule applies to rather than copied into each.
+    pub name: Arc<str>,
+    /// The regex a line must match for the rule to apply to it.
+    #[serde(default, deserialize_with = "deserialize_condition")]
+    pub condition: Option<Condition>,
+    /// The characters the field may contain.
+    #[serde(default, deserialize_with = "constraints::deserialize_charset")]
+    pub charset: Option<Charset>,
+    /// The letter case the field must be in.
+    #[serde(default)]
+    pub case: Option<Case>,
+    /// A regex the field's whole value must match.
+    #[serde(default, deserialize_with = "constraints::deserialize_pattern")]
+    pub pattern: Option<Pattern>,
+    /// Which end of the field its value must be written against.
+    #[serde(default)]
+    pub justify: Option<Justify>,
+    /// The character the field must be padded with.
+    #[serde(default, deserialize_with = "constraints::deserialize_pad")]
+    pub pad: Option<char>,
+    /// The encoding of the field's bytes, if it differs from the line's, for showing its value.
+    #[serde(default, deserialize_with = "encoding::deserialize_encoding")]
+    pub encoding: Option<Encoding>,
+    /// How the field's bytes are read for showing its value, such as a packed decimal number.
+    #[serde(
+        default,
+        rename = "type",
+        deserialize_with = "encoding::deserialize_field_type"
+    )]
+    pub field_type: Option<FieldType>,
+    /// The colour to highlight the field in, as a hex code without the `#`, rather than the next colour in turn.
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color: Option<String>,
+    /// The regex the field's value must match to be drawn in its `value_style`.
+    #[serde(default, deserialize_with = "value_style::deserialize_value_pattern")]
+    pub value_pattern: Option<ValuePattern>,
+    /// How the field is drawn, over its colour, when its value matches its `value_pattern`.
+    #[serde(default, deserialize_with = "value_style::deserialize_value_style")]
+    pub value_style: Option<ValueStyle>,
+    /// Mask the field's value in every output, as it holds data such as card numbers that mustn't be shared.
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub sensitive: bool,
+    /// The control total the field holds, if it's of a trailer, checked against the records before its line, or its
+    /// check across records.
+    #[serde(default, deserialize_with = "totals::deserialize_check")]
+    pub check: Option<ControlCheck>,
+    /// The record type the rule belongs to, so it only applies to lines of that type.
+    #[serde(default, deserialize_with = "deserialize_record_type")]
+    pub record: Option<RecordType>,
+    /// The columns (or fields) the rule is shifted by for its record type, from `--shift-record`, on top of
+    /// [`PositionOptions::shift`].
+    #[serde(skip)]
+    pub type_shift: isize,
+}
+
+impl FixedWidthHighlightRecord {
+    /// The rule's `start` on a line with `captures`: the value of its capture group if it's placed by one.
+    pub fn start_on(&self, captures: &Captures) -> Option<usize> {
+        match &self.start_capture {
+            Some(name) => captures.number(name),
+            None => self.start,
+        }
+    }
+
+    /// The rule's `length` on a line with `captures`: the value of its capture group if it's sized by one.
+    pub fn length_on(&self, captures: &Captures) -> Option<usize> {
+        match &self.length_capture {
+            Some(name) => captures.number(name),
+            None => self.length,
+        }
+    }
+
+    /// The 0-based column the field starts at on a line `line_length` columns long (in the column mode) with
+    /// `captures`, with `positions` applied, or `None` if it has no `start` or would start before the first column.
+    /// With `from_end`, `start` counts back from the last column, which is numbered as the first column is.
+    pub fn start_column(
+        &self,
+        line_length: usize,
+        captures: &Captures,
+        positions: PositionOptions,
+    ) -> Option<usize> {
+        let start = self.start_on(captures)? as isize - positions.index_base as isize;
+        let start = if self.from_end {
+            line_length as isize - 1 - start
+        } else {
+            start
+        } + self.shift(positions);
+        (start >= 0).then_some(start as usize)
+    }
+}
+
+/// A rule placing a field by its number, between delimiters.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct DelimiterHighlightRecord {
+    /// The line of the syntax file this record was read from.
+    #[serde(skip)]
+    pub line: u64,
+    pub field: Option<usize>,
+    /// The named capture group whose value on each line is `field`, from a `${name}` placeholder.
+    #[serde(skip)]
+    pub field_capture: Option<String>,
+    /// The field's name, shared with the region of every line the rule applies to rather than copied into each.
+    pub name: Arc<str>,
+    /// The regex a line must match for the rule to apply to it.
+    #[serde(default, deserialize_with = "deserialize_condition")]
+    pub condition: Option<Condition>,
+    /// The characters the field may contain.
+    #[serde(default, deserialize_with = "constraints::deserialize_charset")]
+    pub charset: Option<Charset>,
+    /// The letter case the field must be in.
+    #[serde(default)]
+    pub case: Option<Case>,
+    /// A regex the field's whole value must match.
+    #[serde(default, deserialize_with = "constraints::deserialize_pattern")]
+    pub pattern: Option<Pattern>,
+    /// Which end of the field its value must be written against.
+    #[serde(default)]
+    pub justify: Option<Justify>,
+    /// The character the field must be padded with.
+    #[serde(default, deserialize_with = "constraints::deserialize_pad")]
+    pub pad: Option<char>,
+    /// The encoding of the field's bytes, if it differs from the line's, for showing its value.
+    #[serde(default, deserialize_with = "encoding::deserialize_encoding")]
+    pub encoding: Option<Encoding>,
+    /// How the field's bytes are read for showing its value, such as a packed decimal number.
+    #[serde(
+        default,
+        rename = "type",
+        deserialize_with = "encoding::deserialize_field_type"
+    )]
+    pub field_type: Option<FieldType>,
+    /// The colour to highlight the field in, as a hex code without the `#`, rather than the next colour in turn.
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color: Option<String>,
+    /// The regex the field's value must match to be drawn in its `value_style`.
+    #[serde(default, deserialize_with = "value_style::deserialize_value_pattern")]
+    pub value_pattern: Option<ValuePattern>,
+    /// How the field is drawn, over its colour, when its value matches its `value_pattern`.
+    #[serde(default, deserialize_with = "value_style::deserialize_value_style")]
+    pub value_style: Option<ValueStyle>,
+    /// Mask the field's value in every output, as it holds data such as card numbers that mustn't be shared.
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub sensitive: bool,
+    /// The control total the field holds, if it's of a trailer, checked against the records before its line, or its
+    /// check across records.
+    #[serde(default, deserialize_with = "totals::deserialize_check")]
+    pub check: Option<ControlCheck>,
+    /// The record type the rule belongs to, so it only applies to lines of that type.
+    #[serde(default, deserialize_with = "deserialize_record_type")]
+    pub record: Option<RecordType>,
+    /// The columns (or fields) the rule is shifted by for its record type, from `--shift-record`, on top of
+    /// [`PositionOptions::shift`].
+    #[serde(skip)]
+    pub type_shift: isize,
+}
+
+impl DelimiterHighlightRecord {
+    /// The rule's `field` on a line with `captures`: the value of its capture group if it's placed by one.
+    pub fn field_on(&self, captures: &Captures) -> Option<usize> {
+        match &self.field_capture {
+            Some(name) => captures.number(name),
+            None => self.field,
+        }
+    }
+}
+
+/// A type of record, such as a header or detail line, which rules can belong to. Record types are defined by rows of a
+/// syntax file with a `record` and a `condition` but no field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordType {
+    pub name: String,
+    /// The regex a line must match to be of this type. A record type without one takes every line that isn't of an
+    /// earlier type.
+    pub condition: Option<Condition>,
+    /// The line of the syntax file the record type was defined on.
+    pub line: u64,
+}
+
+/// What a line must be like to meet a condition, from the `condition` column of a syntax file: a regex the line must
+/// match, or a test of the value of a field placed by an earlier rule, written `field:NAME == "VALUE"`. Regexes are
+/// compiled when the syntax file is read, and fields found once all of it is.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    spec: String,
+    test: Test,
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    Regex(Regex),
+    Field(FieldTest),
+}
+
+/// A `field:` condition: a comparison of the value of a field with a literal, or with a regex for `=~`.
+#[derive(Debug, Clone)]
+struct FieldTest {
+    field: String,
+    operator: Operator,
+    value: String,
+    regex: Option<Regex>,
+    /// The rule placing the field, with the positions it's placed by, found by [`place_field_conditions`].
+    rule: Option<Box<(FieldRule, PositionOptions)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Matches,
+}
+
+/// The rule a `field:` condition reads the value of.
+#[derive(Debug, Clone)]
+enum FieldRule {
+    FixedWidth(FixedWidthHighlightRecord),
+    Delimiter(Delimiter, DelimiterHighlightRecord),
+}
+
+impl FieldTest {
+    fn parse(spec: &str, test: &str) -> anyhow::Result<FieldTest> {
+        let Some((at, operator)) = [
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("=~", Operator::Matches),
+        ]
+        .into_iter()
+        .filter_map(|(text, operator)| Some((test.find(text)?, operator)))
+        .min_by_key(|(at, _)| *at) else {
+            bail!(
+                "Condition `{}` should compare the field with `==`, `!=` or `=~`, as in `field:Type == \"D\"`.",
+                spec
+            );
+        };
+        let field = test[..at].trim();
+        if field.is_empty() {
+            bail!("Condition `{}` doesn't name a field.", spec);
+        }
+        let value = test[at + 2..].trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        let regex = match operator {
+            Operator::Matches => Some(Regex::new(value).map_err(|e| {
+                anyhow::anyhow!("Condition `{}` is not a valid regex: {}", spec, e)
+            })?),
+            _ => None,
+        };
+        Ok(FieldTest {
+            field: field.to_owned(),
+            operator,
+            value: value.to_owned(),
+            regex,
+            rule: None,
+        })
+    }
+
+    /// Whether the field's value on `line`, with spaces around it trimmed, passes the test. A line the field's rule
+    /// doesn't apply to, or too short to hold the field, doesn't.
+    fn is_match(&self, line: &str) -> bool {
+        let Some(placed) = &self.rule else {
+            return false;
+        };
+        let (rule, positions) = placed.as_ref();
+        let value = match rule {
+            FieldRule::FixedWidth(rule) => {
+                if !condition_matches(&rule.condition, line) {
+                    return false;
+                }
+                let columns = Columns::new(line, positions.column_mode);
+                let (Some(start), Some(length)) = (
+                    rule.start_column(columns.units(), &Captures::default(), *positions),
+                    rule.length,
+                ) else {
+                    return false;
+                };
+                if start + length > columns.units() {
+                    return false;
+                }
+                let (from, to) = (
+                    columns.char_index(start),
+                    columns.char_index(start + length),
+                );
+                line.chars().skip(from).take(to - from).collect::<String>()
+            }
+            FieldRule::Delimiter(delimiter, rule) => {
+                if !condition_matches(&rule.condition, line) {
+                    return false;
+                }
+                let delimiters = delimiter.spans(positions.quote, line);
+                let Some(field) = rule.field else {
+                    return false;
+                };
+                let field =
+                    field as isize + 1 - positions.index_base as isize + rule.shift(*positions);
+                if field < 1 || field as usize > delimiters.len() + 1 {
+                    return false;
+                }
+                let (start, end) = field_columns(&delimiters, field as usize, line.chars().count());
+                let value: String = line.chars().skip(start).take(end - start).collect();
+                match positions.quote {
+                    Some(q) if value.len() >= 2 && value.starts_with(q) && value.ends_with(q) => {
+                        value[q.len_utf8()..value.len() - q.len_utf8()]
+                            .replace(&format!("{0}{0}", q), &q.to_string())
+                    }
+                    _ => value,
+                }
+            }
+        };
+        let value = value.trim();
+        match self.operator {
+            Operator::Equal => value == self.value,
+            Operator::NotEqual => value != self.value,
+            Operator::Matches => self.regex.as_ref().is_some_and(|r| r.is_match(value)),
+        }
+    }
+}
+
+impl Condition {
+    pub fn parse(spec: &str) -> anyhow::Result<Condition> {
+        if let Some(test) = spec.strip_prefix("field:") {
+            return Ok(Condition {
+                spec: spec.to_owned(),
+                test: Test::Field(FieldTest::parse(spec, test)?),
+            });
+        }
+        match Regex::new(spec) {
+            Ok(regex) => Ok(Condition {
+                spec: spec.to_owned(),
+                test: Test::Regex(regex),
+            }),
+            Err(e) => bail!("Condition `{}` is not a valid regex: {}", spec, e),
+        }
+    }
+
+    /// The condition as written in the syntax file.
+    pub fn as_str(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match &self.test {
+            Test::Regex(regex) => regex.is_match(line),
+            Test::Field(test) => test.is_match(line),
+        }
+    }
+
+    /// Whether the condition tests the value of a field rather than matching a regex against the whole line.
+    pub fn is_field_test(&self) -> bool {
+        matches!(self.test, Test::Field(_))
+    }
+
+    /// The names of the condition's named capture groups, which rules can be placed by.
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        let regex = match &self.test {
+            Test::Regex(regex) => Some(regex),
+            Test::Field(_) => None,
+        };
+        regex.into_iter().flat_map(|r| r.capture_names().flatten())
+    }
+
+    /// The value of each named capture group that took part in the condition's match on `line`.
+    fn captures(&self, line: &str) -> Vec<(String, String)> {
+        let Test::Regex(regex) = &self.test else {
+            return Vec::new();
+        };
+        let Some(found) = regex.captures(line) else {
+            return Vec::new();
+        };
+        self.capture_names()
+            .filter_map(|name| Some((name.to_owned(), found.name(name)?.as_str().to_owned())))
+            .collect()
+    }
+}
+
+/// The values a line's conditions captured in named groups, which rules placed by `${name}` take their position from.
+/// The condition of the line's record type comes first, then those of the rules that apply to it, in order; the first
+/// value of a name is used.
+#[derive(Debug, Default)]
+pub struct Captures {
+    values: Vec<(String, String)>,
+}
+
+impl Captures {
+    /// What the conditions that apply to `line`, of `record_type`, captured. Nothing is captured if no rule is placed
+    /// by a capture group.
+    pub fn of(records: &RecordList, record_type: Option<&RecordType>, line: &str) -> Self {
+        let mut captures = Captures::default();
+        if !records.places_by_capture() {
+            return captures;
+        }
+        let conditions = record_type
+            .and_then(|t| t.condition.as_ref())
+            .into_iter()
+            .chain(
+                records
+                    .rules()
+                    .into_iter()
+                    .filter(|rule| in_record_type(*rule, record_type))
+                    .filter_map(|rule| rule.condition()),
+            );
+        for condition in conditions {
+            for (name, value) in condition.captures(line) {
+                if captures.get(&name).is_none() {
+                    captures.values.push((name, value));
+                }
+            }
+        }
+        captures
+    }
+
+    /// The value captured in the group called `name`, if it matched.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value captured in the group called `name` as a column or field number, if it matched and is one.
+    pub fn number(&self, name: &str) -> Option<usize> {
+        self.get(name)?.trim().parse().ok()
+    }
+}
+
+/// A `start` given as the column after the end of another field, from `after:NAME`, or with columns between them,
+/// `after:NAME+N`, so a field inserted part way along a layout only moves the rule after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    /// The name of the field the rule starts after.
+    pub field: String,
+    /// The columns between the end of `field` and the start of the rule, negative if it overlaps the end of `field`.
+    pub offset: isize,
+}
+
+impl Anchor {
+    /// Read a `start` written as `after:NAME`, `after:NAME+N` or `after:NAME-N`, or `None` if it isn't one.
+    pub fn parse(value: &str) -> Option<anyhow::Result<Anchor>> {
+        let anchor = value.trim().strip_prefix("after:")?;
+        let (field, offset) = match anchor.rfind(['+', '-']) {
+            Some(i)
+                if i + 1 < anchor.len() && anchor[i + 1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                match anchor[i..].parse::<isize>() {
+                    Ok(offset) => (&anchor[..i], offset),
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+            _ => (anchor, 0),
+        };
+        let field = field.trim();
+        if field.is_empty() {
+            return Some(Err(anyhow::anyhow!(
+                "`{}` should name the field the rule starts after, e.g. `after:OrderId` or `after:OrderId+2`.",
+                value.trim()
+            )));
+        }
+        Some(Ok(Anchor {
+            field: field.to_owned(),
+            offset,
+        }))
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "after:{}", self.field)?;
+        match self.offset {
+            0 => Ok(()),
+            offset if offset > 0 => write!(f, "+{}", offset),
+            offset => write!(f, "{}", offset),
+        }
+    }
+}
+
+/// The capture group name a `${name}` placeholder in a `start`, `length` or `field` column refers to.
+pub fn placeholder(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix("${")?
+        .strip_suffix('}')
+        .filter(|name| !name.is_empty())
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Self) -> bool {
+        self.spec == other.spec
+    }
+}
+
+impl Eq for Condition {}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+/// Read an optional `condition` column, failing on conditions that aren't valid regexes.
+fn deserialize_condition<'de, D>(deserializer: D) -> Result<Option<Condition>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => Condition::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Read the `color` column of a syntax file, which must be a hex code.
+fn deserialize_color<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .filter(|color| !color.trim().is_empty())
+        .map(|color| hex_color(&color))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Read a yes-or-no column such as `from_end` or `sensitive`: `true`, or `false` if it's empty.
+fn deserialize_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)?
+        .as_deref()
+        .map(str::trim)
+    {
+        None | Some("") | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(other) => Err(serde::de::Error::custom(format!(
+            "`{}` should be `true` or `false`.",
+            other
+        ))),
+    }
+}
+
+/// Read an optional `record` column as the name of a record type, which is defined once the whole syntax file is read.
+fn deserialize_record_type<'de, D>(deserializer: D) -> Result<Option<RecordType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?
+        .filter(|name| !name.is_empty())
+        .map(|name| RecordType {
+            name,
+            condition: None,
+            line: 0,
+        }))
+}
+
+/// What every rule has, whether it places its field by columns or between delimiters.
+pub trait SyntaxRule {
+    /// The name of the field the rule describes.
+    fn name(&self) -> &str;
+    /// The regex a line must match for the rule to apply to it, if it has one.
+    fn condition(&self) -> Option<&Condition>;
+    /// The characters the field may contain, if they are restricted.
+    fn charset(&self) -> Option<&Charset>;
+    /// The letter case the field must be in, if it is restricted.
+    fn case(&self) -> Option<Case>;
+    /// The regex the field's whole value must match, if it has one.
+    fn pattern(&self) -> Option<&Pattern>;
+    /// Which end of the field its value must be written against, if it is restricted.
+    fn justify(&self) -> Option<Justify>;
+    /// The character the field must be padded with, if it is restricted.
+    fn pad(&self) -> Option<char>;
+    /// The encoding of the field's bytes, if it differs from the line's.
+    fn encoding(&self) -> Option<Encoding>;
+    /// How the field's bytes are read, if they aren't text.
+    fn field_type(&self) -> Option<&FieldType>;
+    /// The colour the field is always highlighted in, if it has one.
+    fn color(&self) -> Option<&str>;
+    /// The regex the field's value must match to be drawn in its value style, if it has one.
+    fn value_pattern(&self) -> Option<&ValuePattern>;
+    /// How the field is drawn when its value matches its value pattern, if the rule says.
+    fn value_style(&self) -> Option<&ValueStyle>;
+    /// Whether the field's value is masked in every output.
+    fn sensitive(&self) -> bool;
+    /// The control total the field holds, or its check across records, if it has one.
+    fn check(&self) -> Option<&ControlCheck>;
+    /// The record type the rule belongs to, if any.
+    fn record(&self) -> Option<&RecordType>;
+    /// The line of the syntax file the rule was read from.
+    fn syntax_line(&self) -> u64;
+    /// The capture groups the rule is placed by, for each of its columns given by a `${name}` placeholder.
+    fn placeholders(&self) -> Vec<(&'static str, &str)>;
+    /// The columns (or fields) the rule's field is shifted by with `positions`: every rule's shift and its record
+    /// type's own.
+    fn shift(&self, positions: PositionOptions) -> isize;
+}
+
+impl SyntaxRule for FixedWidthHighlightRecord {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn condition(&self) -> Option<&Condition> {
+        self.condition.as_ref()
+    }
+
+    fn charset(&self) -> Option<&Charset> {
+        self.charset.as_ref()
+    }
+
+    fn case(&self) -> Option<Case> {
+        self.case
+    }
+
+    fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    fn justify(&self) -> Option<Justify> {
+        self.justify
+    }
+
+    fn pad(&self) -> Option<char> {
+        self.pad
+    }
+
+    fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    fn field_type(&self) -> Option<&FieldType> {
+        self.field_type.as_ref()
+    }
+
+    fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    fn value_pattern(&self) -> Option<&ValuePattern> {
+        self.value_pattern.as_ref()
+    }
+
+    fn value_style(&self) -> Option<&ValueStyle> {
+        self.value_style.as_ref()
+    }
+
+    fn sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    fn check(&self) -> Option<&ControlCheck> {
+        self.check.as_ref()
+    }
+
+    fn record(&self) -> Option<&RecordType> {
+        self.record.as_ref()
+    }
+
+    fn syntax_line(&self) -> u64 {
+        self.line
+    }
+
+    fn placeholders(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("start", &self.start_capture),
+            ("length", &self.length_capture),
+        ]
+        .into_iter()
+        .filter_map(|(column, name)| Some((column, name.as_deref()?)))
+        .collect()
+    }
+
+    fn shift(&self, positions: PositionOptions) -> isize {
+        positions.shift + self.type_shift
+    }
+}
+
+impl SyntaxRule for DelimiterHighlightRecord {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn condition(&self) -> Option<&Condition> {
+        self.condition.as_ref()
+    }
+
+    fn charset(&self) -> Option<&Charset> {
+        self.charset.as_ref()
+    }
+
+    fn case(&self) -> Option<Case> {
+        self.case
+    }
+
+    fn pattern(&self) -> Option<&Pattern> {
+        self.pattern.as_ref()
+    }
+
+    fn justify(&self) -> Option<Justify> {
+        self.justify
+    }
+
+    fn pad(&self) -> Option<char> {
+        self.pad
+    }
+
+    fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    fn field_type(&self) -> Option<&FieldType> {
+        self.field_type.as_ref()
+    }
+
+    fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    fn value_pattern(&self) -> Option<&ValuePattern> {
+        self.value_pattern.as_ref()
+    }
+
+    fn value_style(&self) -> Option<&ValueStyle> {
+        self.value_style.as_ref()
+    }
+
+    fn sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    fn check(&self) -> Option<&ControlCheck> {
+        self.check.as_ref()
+    }
+
+    fn record(&self) -> Option<&RecordType> {
+        self.record.as_ref()
+    }
+
+    fn syntax_line(&self) -> u64 {
+        self.line
+    }
+
+    fn placeholders(&self) -> Vec<(&'static str, &str)> {
+        self.field_capture
+            .as_deref()
+            .map(|name| ("field", name))
+            .into_iter()
+            .collect()
+    }
+
+    fn shift(&self, positions: PositionOptions) -> isize {
+        positions.shift + self.type_shift
+    }
+}
+
+/// How positions in the syntax file map onto the input.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionOptions {
+    /// Number of columns (or fields) to shift every record by.
+    pub shift: isize,
+    /// The number that refers to the first column (or field) in the syntax file.
+    pub index_base: usize,
+    /// What the columns of a fixed width syntax file count.
+    pub column_mode: ColumnMode,
+    /// The character fields may be quoted with in delimiter mode, so that they can hold the delimiter.
+    pub quote: Option<char>,
+}
+
+impl Default for PositionOptions {
+    /// No shift, with columns and fields counted from 1, and columns counting characters.
+    fn default() -> Self {
+        Self {
+            shift: 0,
+            index_base: 1,
+            column_mode: ColumnMode::Chars,
+            quote: None,
+        }
+    }
+}
+
+/// Read and parse a syntax file, or load a compiled one, returning its (original) text, its rules and how lines are
+/// assembled into records.
+pub fn read_syntax_file<P: AsRef<Path>>(
+    syntax_file: P,
+    delimiter: Option<Delimiter>,
+) -> anyhow::Result<(String, RecordList, Assembly)> {
+    let bytes = fs::read(syntax_file).context("Failed to read from syntax file.")?;
+    if compiled::is_compiled(&bytes) {
+        let (text, records, assembly) = compiled::load(&bytes)?;
+        if records.delimiter() != delimiter.as_ref() {
+            match records.delimiter() {
+                Some(d) if d.is_regex() => bail!("This syntax file was compiled for delimiter mode, so needs `--delimiter '{}' --delimiter-regex`.", d),
+                Some(d) => bail!("This syntax file was compiled for delimiter mode, so needs `--delimiter '{}'`.", d),
+                None => bail!("This syntax file was compiled for fixed width mode, so can't be used with `--delimiter`."),
+            }
+        }
+        return Ok((text, records, assembly));
+    }
+
+    let text = String::from_utf8(bytes).context("Failed to read from syntax file.")?;
+    let (records, assembly) = parse_csv_syntax(&text, delimiter)?;
+    Ok((text, records, assembly))
+}
+
+/// Parse the text of a CSV syntax file: fixed width, or delimiter mode if a delimiter is given.
+pub fn parse_syntax_file(
+    syntax_file: &str,
+    delimiter: Option<Delimiter>,
+) -> anyhow::Result<RecordList> {
+    Ok(parse_csv_syntax(syntax_file, delimiter)?.0)
+}
+
+/// Parse the text of a CSV syntax file into its rules and how lines are assembled into records, which a row giving
+/// just `continues_if` sets.
+fn parse_csv_syntax(
+    syntax_file: &str,
+    delimiter: Option<Delimiter>,
+) -> anyhow::Result<(RecordList, Assembly)> {
+    let mut csv_reader = csv::Reader::from_reader(syntax_file.as_bytes());
+    let headers = csv_reader.headers()?.clone();
+    let name_column = headers.iter().position(|h| h == "name");
+    let record_column = headers.iter().position(|h| h == "record");
+    let condition_column = headers.iter().position(|h| h == "condition");
+    let continues_column = headers.iter().position(|h| h == "continues_if");
+    let placed_columns = ["start", "length", "field"].map(|c| headers.iter().position(|h| h == c));
+    let [occurs_column, stride_column] =
+        ["occurs", "stride"].map(|c| headers.iter().position(|h| h == c));
+    let mut record_types: Vec<RecordType> = Vec::new();
+    let mut assembly = Assembly::default();
+    let mut assembly_line = 0;
+    let mut rows = Vec::new();
+    for result in csv_reader.records() {
+        let row = result.context("Failed to parse syntax record.")?;
+        let line = row.position().map_or(0, |p| p.line());
+        let column = |index: Option<usize>| {
+            index
+                .and_then(|i| row.get(i))
+                .filter(|value| !value.is_empty())
+        };
+        // `continues_if` is about the whole input rather than any one rule
+        if let Some(spec) = column(continues_column) {
+            if column(name_column).is_some() || column(record_column).is_some() {
+                bail!(
+                    "Syntax file line {}: `continues_if` applies to every line, so should be given on a row of its own.",
+                    line
+                );
+            }
+            if assembly.joins_lines() {
+                bail!(
+                    "Syntax file line {}: `continues_if` is already given on line {}.",
+                    line,
+                    assembly_line
+                );
+            }
+            assembly.continues_if = Some(Condition::parse(spec).with_context(|| {
+                format!(
+                    "Syntax file line {}: column `continues_if` is not a valid regex.",
+                    line
+                )
+            })?);
+            if assembly
+                .continues_if
+                .as_ref()
+                .is_some_and(Condition::is_field_test)
+            {
+                bail!(
+                    "Syntax file line {}: `continues_if` should be a regex, as lines are joined before their fields are found.",
+                    line
+                );
+            }
+            assembly_line = line;
+            continue;
+        }
+        // a row naming a record but no field defines that record type
+        if let (Some(record), None) = (column(record_column), column(name_column)) {
+            if let Some(earlier) = record_types.iter().find(|t| t.name == record) {
+                bail!(
+                    "Syntax file line {}: record type '{}' is already defined on line {}.",
+                    line,
+                    record,
+                    earlier.line
+                );
+            }
+            let condition = column(condition_column)
+                .map(Condition::parse)
+                .transpose()
+                .with_context(|| {
+                    format!(
+                        "Syntax file line {}: column `condition` is not a valid regex.",
+                        line
+                    )
+                })?;
+            record_types.push(RecordType {
+                name: record.to_owned(),
+                condition,
+                line,
+            });
+            continue;
+        }
+        let number = |index: Option<usize>, name: &str| {
+            column(index)
+                .map(|value| value.trim().parse::<usize>())
+                .transpose()
+                .with_context(|| {
+                    format!(
+                        "Syntax file line {}: column `{}` should be a number.",
+                        line, name
+                    )
+                })
+        };
+        let occurs = Occurs {
+            count: number(occurs_column, "occurs")?,
+            stride: number(stride_column, "stride")?,
+        };
+        rows.push((line, row, occurs));
+    }
+
+    let define = |line: u64, record: &mut Option<RecordType>| -> anyhow::Result<()> {
+        if let Some(record) = record {
+            *record = record_types
+                .iter()
+                .find(|t| t.name == record.name)
+                .with_context(|| {
+                    format!(
+                        "Syntax file line {}: record type '{}' isn't defined. Define it with a row giving just its `record` and `condition`.",
+                        line, record.name
+                    )
+                })?
+                .clone();
+        }
+        Ok(())
+    };
+    // `${name}` placeholders aren't numbers, so are taken out of the row before it's read and put back after
+    let take_placeholders = |row: &csv::StringRecord| {
+        let mut captures: [Option<String>; 3] = Default::default();
+        let mut row = row.clone();
+        for (capture, column) in captures.iter_mut().zip(placed_columns) {
+            if let Some(name) = column.and_then(|i| row.get(i)).and_then(placeholder) {
+                *capture = Some(name.to_owned());
+            }
+        }
+        if captures.iter().any(Option::is_some) {
+            row = row
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    if placed_columns.contains(&Some(i)) && placeholder(value).is_some() {
+                        ""
+                    } else {
+                        value
+                    }
+                })
+                .collect();
+        }
+        (row, captures)
+    };
+    // an `after:NAME` start is resolved once every rule has been read, so is taken out of the row in the same way
+    let take_anchor = |row: csv::StringRecord,
+                       line: u64|
+     -> anyhow::Result<(csv::StringRecord, Option<Anchor>)> {
+        let [start_column, _, _] = placed_columns;
+        let Some(anchor) = start_column
+            .and_then(|i| row.get(i))
+            .and_then(Anchor::parse)
+        else {
+            return Ok((row, None));
+        };
+        let anchor = anchor.with_context(|| {
+            format!(
+                "Syntax file line {}: column `start` isn't a valid `after:` anchor.",
+                line
+            )
+        })?;
+        let row = row
+            .iter()
+            .enumerate()
+            .map(|(i, value)| if Some(i) == start_column { "" } else { value })
+            .collect();
+        Ok((row, Some(anchor)))
+    };
+    let mut records = if let Some(delimiter_ch) = delimiter {
+        let mut records = Vec::new();
+        for (line, row, occurs) in rows {
+            let (row, [_, _, field_capture]) = take_placeholders(&row);
+            let mut highlight_record: DelimiterHighlightRecord = row
+                .deserialize(Some(&headers))
+                .context("Failed to parse syntax record.")?;
+            highlight_record.line = line;
+            highlight_record.field_capture = field_capture;
+            define(line, &mut highlight_record.record)?;
+            records.extend(expand_delimiter(highlight_record, occurs)?);
+        }
+        RecordList::Delimiter(delimiter_ch, records)
+    } else {
+        let mut records = Vec::new();
+        for (line, row, occurs) in rows {
+            let (row, [start_capture, length_capture, _]) = take_placeholders(&row);
+            let (row, start_anchor) = take_anchor(row, line)?;
+            let mut highlight_record: FixedWidthHighlightRecord =
+                row.deserialize(Some(&headers))
+                    .context("Failed to parse syntax record.")?;
+            highlight_record.line = line;
+            highlight_record.start_capture = start_capture;
+            highlight_record.length_capture = length_capture;
+            highlight_record.start_anchor = start_anchor;
+            define(line, &mut highlight_record.record)?;
+            records.extend(expand_fixed_width(highlight_record, occurs)?);
+        }
+        RecordList::FixedWidth(records)
+    };
+    resolve_anchors(&mut records)?;
+    check_placeholders(&records)?;
+    totals::check_fields(&records)?;
+    place_field_conditions(&mut records, PositionOptions::default())?;
+
+    let used = records.record_types();
+    for record_type in &record_types {
+        if !used.contains(&record_type) {
+            error!(
+                "Syntax file line {}: record type '{}' has no rules, so is ignored.",
+                record_type.line, record_type.name
+            );
+        }
+    }
+    Ok((records, assembly))
+}
+
+/// Set the `start` of each rule of `records` with an `after:NAME` anchor to the column after the end of the field it
+/// names, as placed by the nearest rule above it of no record type or of the same one, as a `field:` condition is.
+/// Rules are resolved top to bottom, so a rule can start after one that's anchored itself.
+pub fn resolve_anchors(records: &mut RecordList) -> anyhow::Result<()> {
+    let RecordList::FixedWidth(rules) = records else {
+        return Ok(());
+    };
+    for i in 0..rules.len() {
+        let (earlier, rest) = rules.split_at_mut(i);
+        let rule = &mut rest[0];
+        let Some(anchor) = &rule.start_anchor else {
+            continue;
+        };
+        if rule.from_end {
+            bail!(
+                "Syntax file line {}: rule '{}' starts `{}`, so can't count back from the end of the line.",
+                rule.line,
+                rule.name,
+                anchor
+            );
+        }
+        let Some(field) = earlier
+            .iter()
+            .rev()
+            .find(|r| *r.name == anchor.field && (r.record.is_none() || r.record == rule.record))
+        else {
+            bail!(
+                "Syntax file line {}: rule '{}' starts `{}`, but no rule above it places a field '{}' on lines of {}.",
+                rule.line,
+                rule.name,
+                anchor,
+                anchor.field,
+                match &rule.record {
+                    Some(record) => format!("record type '{}'", record.name),
+                    None => "every record type".to_owned(),
+                }
+            );
+        };
+        let (Some(start), Some(length), false) = (
+            field.start.filter(|_| field.start_capture.is_none()),
+            field.length.filter(|_| field.length_capture.is_none()),
+            field.from_end,
+        ) else {
+            bail!(
+                "Syntax file line {}: rule '{}' starts `{}`, but '{}' on line {} isn't at a fixed column with a fixed length, so has no end to start after.",
+                rule.line,
+                rule.name,
+                anchor,
+                field.name,
+                field.line
+            );
+        };
+        let Ok(start) = usize::try_from(start as isize + length as isize + anchor.offset) else {
+            bail!(
+                "Syntax file line {}: rule '{}' starts `{}`, which is before the first column.",
+                rule.line,
+                rule.name,
+                anchor
+            );
+        };
+        rule.start = Some(start);
+    }
+    Ok(())
+}
+
+/// Find the rules the `field:` conditions of `records` read the values of, and place them by `positions`. Conditions of
+/// a rule can read a field placed by a rule above it of no record type or of the same one, and conditions of a record
+/// type a field placed by a rule above its definition of no record type. The nearest such rule of the field's name is
+/// used.
+pub fn place_field_conditions(
+    records: &mut RecordList,
+    positions: PositionOptions,
+) -> anyhow::Result<()> {
+    match records {
+        RecordList::FixedWidth(rules) => {
+            for i in 0..rules.len() {
+                let (earlier, rest) = rules.split_at_mut(i);
+                let rule = &mut rest[0];
+                place_conditions(
+                    rule.line,
+                    &mut rule.condition,
+                    &mut rule.record,
+                    earlier,
+                    |r| FieldRule::FixedWidth(r.clone()),
+                    positions,
+                )?;
+            }
+        }
+        RecordList::Delimiter(delimiter, rules) => {
+            for i in 0..rules.len() {
+                let (earlier, rest) = rules.split_at_mut(i);
+                let rule = &mut rest[0];
+                place_conditions(
+                    rule.line,
+                    &mut rule.condition,
+                    &mut rule.record,
+                    earlier,
+                    |r| FieldRule::Delimiter(delimiter.clone(), r.clone()),
+                    positions,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Place the `field:` conditions of a rule read from syntax file line `line`, and of its record type, by the rules
+/// `earlier` than it.
+fn place_conditions<R: SyntaxRule>(
+    line: u64,
+    condition: &mut Option<Condition>,
+    record: &mut Option<RecordType>,
+    earlier: &[R],
+    field_rule: impl Fn(&R) -> FieldRule,
+    positions: PositionOptions,
+) -> anyhow::Result<()> {
+    let record_name = record.as_ref().map(|t| t.name.clone());
+    let place = |condition: &mut Option<Condition>,
+                 line: u64,
+                 record_name: Option<&str>|
+     -> anyhow::Result<()> {
+        let Some(Condition {
+            spec,
+            test: Test::Field(test),
+        }) = condition
+        else {
+            return Ok(());
+        };
+        let Some(rule) = earlier
+            .iter()
+            .rev()
+            .filter(|r| r.syntax_line() < line && r.name() == test.field)
+            .find(|r| {
+                r.record()
+                    .map(|t| t.name.as_str())
+                    .is_none_or(|t| Some(t) == record_name)
+            })
+        else {
+            bail!(
+                "Syntax file line {}: condition `{}` reads field '{}', but no rule above it places a field of that name {}.",
+                line,
+                spec,
+                test.field,
+                match record_name {
+                    Some(record) => format!("in record type '{}' or outside any record type", record),
+                    None => "outside any record type".to_owned(),
+                }
+            );
+        };
+        if !rule.placeholders().is_empty() {
+            bail!(
+                "Syntax file line {}: condition `{}` reads field '{}', which is placed by a capture group, so can't be read by a condition.",
+                line,
+                spec,
+                test.field
+            );
+        }
+        test.rule = Some(Box::new((field_rule(rule), positions)));
+        Ok(())
+    };
+    place(condition, line, record_name.as_deref())?;
+    if let Some(record) = record {
+        place(&mut record.condition, record.line, None)?;
+    }
+    Ok(())
+}
+
+/// How many times a rule's field repeats, from the `occurs` and `stride` columns of a syntax file, for a repeating group
+/// such as twelve monthly amounts declared as one rule.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Occurs {
+    /// The number of times the field occurs, or `None` if it's only once.
+    pub count: Option<usize>,
+    /// The columns (or, in delimiter mode, fields) from the start of one occurrence to the start of the next, if not
+    /// the field's length (or one field).
+    pub stride: Option<usize>,
+}
+
+impl Occurs {
+    /// The name and offset from the rule's own position of each occurrence of the field `name` of the rule on `line`,
+    /// `Name[1]`, `Name[2]` and so on, or just `name` if the field doesn't repeat.
+    fn occurrences(
+        self,
+        line: u64,
+        name: &str,
+        default_stride: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, usize)>> {
+        let Some(count) = self.count else {
+            if self.stride.is_some() {
+                bail!(
+                    "Syntax file line {}: `stride` of rule '{}' is only used with `occurs`.",
+                    line,
+                    name
+                );
+            }
+            return Ok(vec![(name.to_owned(), 0)]);
+        };
+        if count == 0 {
+            bail!(
+                "Syntax file line {}: `occurs` of rule '{}' should be at least 1.",
+                line,
+                name
+            );
+        }
+        let Some(stride) = self.stride.or(default_stride) else {
+            bail!(
+                "Syntax file line {}: rule '{}' occurs {} times but has no fixed length, so needs a `stride`.",
+                line,
+                name,
+                count
+            );
+        };
+        Ok((0..count)
+            .map(|i| (format!("{}[{}]", name, i + 1), i * stride))
+            .collect())
+    }
+}
+
+/// The rules `record` expands to: one for each occurrence of its field, each `stride` columns on from the one before.
+pub fn expand_fixed_width(
+    record: FixedWidthHighlightRecord,
+    occurs: Occurs,
+) -> anyhow::Result<Vec<FixedWidthHighlightRecord>> {
+    if occurs.count.is_some() {
+        if record.start_capture.is_some() || record.length_capture.is_some() {
+            bail!(
+                "Syntax file line {}: rule '{}' is placed by a capture group, so can't be given `occurs`.",
+                record.line,
+                record.name
+            );
+        }
+        if record.from_end {
+            bail!(
+                "Syntax file line {}: rule '{}' counts back from the end of the line, so can't be given `occurs`.",
+                record.line,
+                record.name
+            );
+        }
+    }
+    Ok(occurs
+        .occurrences(record.line, &record.name, record.length)?
+        .into_iter()
+        .map(|(name, offset)| FixedWidthHighlightRecord {
+            start: record.start.map(|start| start + offset),
+            start_anchor: record.start_anchor.clone().map(|anchor| Anchor {
+                offset: anchor.offset + offset as isize,
+                ..anchor
+            }),
+            name: name.into(),
+            ..record.clone()
+        })
+        .collect())
+}
+
+/// The rules `record` expands to: one for each occurrence of its field, each `stride` fields on from the one before.
+pub fn expand_delimiter(
+    record: DelimiterHighlightRecord,
+    occurs: Occurs,
+) -> anyhow::Result<Vec<DelimiterHighlightRecord>> {
+    if occurs.count.is_some() && record.field_capture.is_some() {
+        bail!(
+            "Syntax file line {}: rule '{}' is placed by a capture group, so can't be given `occurs`.",
+            record.line,
+            record.name
+        );
+    }
+    Ok(occurs
+        .occurrences(record.line, &record.name, Some(1))?
+        .into_iter()
+        .map(|(name, offset)| DelimiterHighlightRecord {
+            field: record.field.map(|field| field + offset),
+            name: name.into(),
+            ..record.clone()
+        })
+        .collect())
+}
+
+/// Fail on a `${name}` placeholder that doesn't name a capture group of any condition, of a rule or a record type.
+pub fn check_placeholders(records: &RecordList) -> anyhow::Result<()> {
+    let rules = records.rules();
+    let conditions: Vec<&Condition> = rules
+        .iter()
+        .filter_map(|rule| rule.condition())
+        .chain(
+            records
+                .record_types()
+                .into_iter()
+                .filter_map(|t| t.condition.as_ref()),
+        )
+        .collect();
+    for rule in &rules {
+        for (column, name) in rule.placeholders() {
+            if !conditions
+                .iter()
+                .any(|condition| condition.capture_names().any(|n| n == name))
+            {
+                bail!(
+                    "Syntax file line {}: column `{}` of rule '{}' is `${{{}}}`, which isn't a named capture group of any condition. Name one with `(?P<{}>...)`.",
+                    rule.syntax_line(),
+                    column,
+                    rule.name(),
+                    name,
+                    name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fail on the first syntax record that would otherwise be skipped or misapplied, naming its line and column.
+pub fn check_syntax_strictly(records: &RecordList, index_base: usize) -> anyhow::Result<()> {
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            for record in fw_records {
+                match record.start {
+                    // placed by a capture group, so checked on each line instead
+                    None if record.start_capture.is_some() => (),
+                    None => bail!(
+                        "Syntax file line {}: column `start` is missing for rule '{}'.",
+                        record.line,
+                        record.name
+                    ),
+                    Some(start) if start < index_base => bail!(
+                        "Syntax file line {}: column `start` is {} for rule '{}', but columns are numbered from {}.",
+                        record.line,
+                        start,
+                        record.name,
+                        index_base
+                    ),
+                    _ => (),
+                }
+                match record.length {
+                    None if record.length_capture.is_some() => (),
+                    None => bail!(
+                        "Syntax file line {}: column `length` is missing for rule '{}'.",
+                        record.line,
+                        record.name
+                    ),
+                    Some(0) => bail!(
+                        "Syntax file line {}: column `length` is 0 for rule '{}'.",
+                        record.line,
+                        record.name
+                    ),
+                    _ => (),
+                }
+            }
+        }
+        RecordList::Delimiter(_, d_records) => {
+            for record in d_records {
+                match record.field {
+                    None if record.field_capture.is_some() => (),
+                    None => bail!(
+                        "Syntax file line {}: column `field` is missing for rule '{}'.",
+                        record.line,
+                        record.name
+                    ),
+                    Some(field) if field < index_base => bail!(
+                        "Syntax file line {}: column `field` is {} for rule '{}', but fields are numbered from {}.",
+                        record.line,
+                        field,
+                        record.name,
+                        index_base
+                    ),
+                    _ => (),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The units lines are measured in by [`line_length`], as a report message.
+pub fn unit_message(records: &RecordList) -> Message {
+    match records {
+        RecordList::FixedWidth(_) => Message::Columns,
+        RecordList::Delimiter(..) => Message::Fields,
+    }
+}
+
+/// The name of the units lines are measured in by [`line_length`].
+pub fn unit_name(records: &RecordList) -> &'static str {
+    match records {
+        RecordList::FixedWidth(_) => "column(s)",
+        RecordList::Delimiter(..) => "field(s)",
+    }
+}
+
+/// Whether a rule with `condition` applies to `line`: it does if it has no condition.
+pub fn condition_matches(condition: &Option<Condition>, line: &str) -> bool {
+    condition.as_ref().is_none_or(|cond| cond.is_match(line))
+}
+
+/// Whether `rule` applies to `line`, which is of `record_type` (as [`RecordList::record_type`] finds): it does if it
+/// belongs to that record type, or none, and its own condition matches.
+pub fn rule_applies(rule: &dyn SyntaxRule, record_type: Option<&RecordType>, line: &str) -> bool {
+    in_record_type(rule, record_type) && rule.condition().is_none_or(|cond| cond.is_match(line))
+}
+
+/// Whether `rule` can apply to lines of `record_type`: it can if it belongs to that record type, or none.
+pub fn in_record_type(rule: &dyn SyntaxRule, record_type: Option<&RecordType>) -> bool {
+    rule.record().is_none_or(|r| Some(r) == record_type)
+}
+
+/// The record types of a syntax file in the order they were defined, to find the type of many lines without
+/// collecting them for each.
+pub struct RecordTyper {
+    types: Vec<RecordType>,
+}
+
+impl RecordTyper {
+    pub fn new(records: &RecordList) -> Self {
+        Self {
+            types: records.record_types().into_iter().cloned().collect(),
+        }
+    }
+
+    /// Whether the syntax file defines no record types.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// The record type of `line`, as [`RecordList::record_type`] finds it.
+    pub fn record_type(&self, line: &str) -> Option<&RecordType> {
+        self.types
+            .iter()
+            .find(|t| condition_matches(&t.condition, line))
+    }
+}
+
+/// The length of `line` in the units the layout is measured in: columns in the column mode of `positions`, or fields
+/// in delimiter mode.
+pub fn line_length(records: &RecordList, line: &str, positions: PositionOptions) -> usize {
+    match records {
+        RecordList::FixedWidth(_) => positions.column_mode.count(line),
+        RecordList::Delimiter(delimiter, _) => delimiter.spans(positions.quote, line).len() + 1,
+    }
+}
+
+/// The length (as measured by [`line_length`]) `line` needs for every rule that applies to it to fit.
+pub fn layout_length(records: &RecordList, line: &str, positions: PositionOptions) -> usize {
+    let record_type = records.record_type(line);
+    let captures = Captures::of(records, record_type, line);
+    let mut length = 0;
+    match records {
+        RecordList::FixedWidth(fw_records) => {
+            for record in fw_records {
+                if let (Some(start), Some(len)) =
+                    (record.start_on(&captures), record.length_on(&captures))
+                {
+                    if rule_applies(record, record_type, line) {
+                        let start = start as isize - positions.index_base as isize;
+                        let shift = record.shift(positions);
+                        if record.from_end {
+                            // enough columns to start on, as it ends wherever the line does
+                            length = length.max((start + 1 - shift).max(0) as usize);
+                        } else if start + shift >= 0 {
+                            length = length.max((start + shift) as usize + len);
+                        }
+                    }
+                }
+            }
+        }
+        RecordList::Delimiter(_, d_records) => {
+            for record in d_records {
+                if let Some(field) = record.field_on(&captures) {
+                    if rule_applies(record, record_type, line) {
+                        let field = field as isize + 1 - positions.index_base as isize
+                            + record.shift(positions);
+                        if field >= 1 {
+                            length = length.max(field as usize);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    length
+}
+
+/// The record type of `line`: its name if the syntax file defines record types, or otherwise named by the conditions
+/// of the rules that apply to it. `None` if it is of no type, or only unconditional rules apply.
+pub fn record_type_of(records: &RecordList, line: &str) -> Option<String> {
+    if !records.record_types().is_empty() {
+        return records.record_type(line).map(|t| t.name.clone());
+    }
+
+    let mut matched: Vec<&str> = Vec::new();
+    for cond in records.rules().into_iter().filter_map(|r| r.condition()) {
+        if !matched.contains(&cond.as_str()) && cond.is_match(line) {
+            matched.push(cond.as_str());
+        }
+    }
+
+    if matched.is_empty() {
+        None
+    } else {
+        Some(matched.join(" & "))
+    }
+}
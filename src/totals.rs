@@ -0,0 +1,557 @@
+//! Control totals: fields of a trailer record holding the number of records before it, or the total of one of their
+//! fields, from the `check` column of a syntax file. Each is checked against the records actually read. The same
+//! column also checks a field across records: that no two share a key, or that they're in order.
+
+use std::{cmp::Ordering, collections::HashMap, fmt, io::Write};
+
+use anyhow::{bail, Context};
+use log::error;
+use serde::Deserialize;
+
+use crate::{
+    encoding::field_value,
+    locale::{Locale, Message},
+    redact::mask_value,
+    region_value, Encoding, HighlightRegion, PreparedLine, RecordList,
+};
+
+/// What a field of a trailer record holds, or what a field must keep to across records, from the `check` column of a
+/// syntax file.
+///
+/// The records counted are those since the start of the input, or since the last line with a control total, so a file
+/// of several batches, each with its own trailer, has each batch checked by its trailer. Lines left out of
+/// highlighting aren't counted, and nor is the trailer itself. Keys and order are checked over the same records, so
+/// each batch can be sorted, or have unique keys, on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCheck {
+    /// `count`: the number of records before the trailer.
+    Count,
+    /// `count:NAME`: the number of records before the trailer with the field `NAME`, such as those of one record type.
+    CountOf(String),
+    /// `sum:NAME`: the total of the field `NAME` over the records before the trailer.
+    Sum(String),
+    /// `unique`: no two records have the same value of the field.
+    Unique,
+    /// `sorted:asc` or `sorted:desc`: each record's value of the field is in order after the one before it. Values
+    /// that are both numbers are compared as numbers, and others as text.
+    Sorted(SortOrder),
+}
+
+/// The order of a field's values checked by [`ControlCheck::Sorted`]. Values that are equal are in either order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl ControlCheck {
+    pub fn parse(spec: &str) -> anyhow::Result<ControlCheck> {
+        let spec = spec.trim();
+        let (kind, field) = match spec.split_once(':') {
+            Some((kind, field)) => (kind.trim(), Some(field.trim())),
+            None => (spec, None),
+        };
+        Ok(match (kind, field) {
+            ("count", None) => ControlCheck::Count,
+            ("count", Some(field)) if !field.is_empty() => ControlCheck::CountOf(field.to_owned()),
+            ("sum", Some(field)) if !field.is_empty() => ControlCheck::Sum(field.to_owned()),
+            ("unique", None) => ControlCheck::Unique,
+            ("sorted", Some("asc")) => ControlCheck::Sorted(SortOrder::Ascending),
+            ("sorted", Some("desc")) => ControlCheck::Sorted(SortOrder::Descending),
+            _ => bail!(
+                "Unknown check `{}`. Use `count`, `count:FIELD`, `sum:FIELD`, `unique`, `sorted:asc` or `sorted:desc`, e.g. `sum:Amount`.",
+                spec
+            ),
+        })
+    }
+
+    /// The check as it is written in a syntax file.
+    pub fn spec(&self) -> String {
+        match self {
+            ControlCheck::Count => "count".to_owned(),
+            ControlCheck::CountOf(field) => format!("count:{}", field),
+            ControlCheck::Sum(field) => format!("sum:{}", field),
+            ControlCheck::Unique => "unique".to_owned(),
+            ControlCheck::Sorted(SortOrder::Ascending) => "sorted:asc".to_owned(),
+            ControlCheck::Sorted(SortOrder::Descending) => "sorted:desc".to_owned(),
+        }
+    }
+
+    /// The field the check counts or totals, if it's of one field.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            ControlCheck::Count | ControlCheck::Unique | ControlCheck::Sorted(_) => None,
+            ControlCheck::CountOf(field) | ControlCheck::Sum(field) => Some(field),
+        }
+    }
+
+    /// Whether the check is a control total, making the line with it a trailer, rather than a check of its own field
+    /// across records.
+    pub fn is_total(&self) -> bool {
+        matches!(
+            self,
+            ControlCheck::Count | ControlCheck::CountOf(_) | ControlCheck::Sum(_)
+        )
+    }
+
+    /// The kind of problem a failure of the check is reported as.
+    pub fn problem_kind(&self) -> &'static str {
+        match self {
+            ControlCheck::Unique => "duplicate_key",
+            ControlCheck::Sorted(_) => "out_of_order",
+            _ => "control_total",
+        }
+    }
+}
+
+impl fmt::Display for ControlCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.spec())
+    }
+}
+
+/// Read an optional `check` column, failing on checks that can't be parsed.
+pub fn deserialize_check<'de, D>(deserializer: D) -> Result<Option<ControlCheck>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => ControlCheck::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Fail on a check of a field no rule gives.
+pub fn check_fields(records: &RecordList) -> anyhow::Result<()> {
+    let names = records.field_names();
+    for rule in records.rules() {
+        let Some(check) = rule.check() else {
+            continue;
+        };
+        if let Some(field) = check.field().filter(|f| !names.iter().any(|n| n == f)) {
+            bail!(
+                "Syntax file line {}: the check {} of rule '{}' is of the field '{}', which no rule gives.",
+                rule.syntax_line(),
+                check,
+                rule.name(),
+                field
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A number read from a field: a whole number of its smallest unit, with `scale` decimal places.
+#[derive(Debug, Clone, Copy, Default)]
+struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Read digits with an optional leading `+` or `-`, an optional decimal point, and spaces either side.
+    fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim_matches(' ');
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let mut units: i128 = 0;
+        for c in whole.chars().chain(fraction.chars()) {
+            units = units
+                .checked_mul(10)?
+                .checked_add(c.to_digit(10)? as i128)?;
+        }
+        Some(Decimal {
+            units: if negative { -units } else { units },
+            scale: fraction.len() as u32,
+        })
+    }
+
+    /// The number of units of `scale` decimal places, which is at least this number's.
+    fn units_at(self, scale: u32) -> Option<i128> {
+        self.units
+            .checked_mul(10i128.checked_pow(scale - self.scale)?)
+    }
+
+    fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        Some(Decimal {
+            units: self.units_at(scale)?.checked_add(other.units_at(scale)?)?,
+            scale,
+        })
+    }
+
+    /// Whether the numbers are the same, however many decimal places each is written with.
+    fn equals(self, other: Decimal) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+
+    /// How the numbers compare, however many decimal places each is written with, or `None` if either is too large to
+    /// be written with the other's.
+    fn compare(self, other: Decimal) -> Option<Ordering> {
+        let scale = self.scale.max(other.scale);
+        Some(self.units_at(scale)?.cmp(&other.units_at(scale)?))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = format!(
+            "{:0>width$}",
+            self.units.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (whole, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        let sign = if self.units < 0 { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
+
+/// The outcome of checking one control total of a trailer, or one record's field against those before it.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// The 0-based index of the checked line.
+    pub line_index: usize,
+    /// The field checked: the trailer's field holding the control total, or the key or sorted field.
+    pub field: String,
+    pub check: ControlCheck,
+    /// The field's value: the control total, as the trailer gives it, or the key or sorted value, masked if the field
+    /// is sensitive.
+    pub value: String,
+    /// What the records before the trailer actually come to, as a number, or why they can't be totalled. For a
+    /// duplicate key, the number of the line that had it first, and for a value out of order, the value before it.
+    pub actual: String,
+    pub passed: bool,
+}
+
+impl CheckResult {
+    /// Why the check failed, in `locale`.
+    pub fn describe(&self, locale: Locale) -> String {
+        let value = self.value.trim();
+        match self.check {
+            ControlCheck::Unique => {
+                locale.format(Message::DuplicateKey, &[&self.field, &value, &self.actual])
+            }
+            ControlCheck::Sorted(SortOrder::Ascending) => locale.format(
+                Message::NotAscending,
+                &[&self.field, &value, &self.actual.trim()],
+            ),
+            ControlCheck::Sorted(SortOrder::Descending) => locale.format(
+                Message::NotDescending,
+                &[&self.field, &value, &self.actual.trim()],
+            ),
+            _ => locale.format(
+                Message::CheckFailed,
+                &[&self.field, &value, &self.check, &self.actual],
+            ),
+        }
+    }
+}
+
+/// The number of records with a field that's totalled or counted by a check, and their total.
+struct FieldTotal {
+    name: String,
+    count: u64,
+    sum: Decimal,
+    /// The number of the field's values that aren't numbers, so weren't added to `sum`.
+    unreadable: u64,
+}
+
+/// The records read since the start of the input or the last line with a control total, counted and totalled for the
+/// checks of `records`, with the keys and last values of fields checked across them.
+pub struct ControlTotals {
+    /// The encoding lines are read in, for decoding fields with a `type`.
+    encoding: Encoding,
+    records: u64,
+    fields: Vec<FieldTotal>,
+    /// The values of each field checked to be `unique`, with the index of the line each was first seen on.
+    keys: HashMap<String, HashMap<String, usize>>,
+    /// The last value of each field checked to be `sorted`.
+    previous: HashMap<String, String>,
+    checked: bool,
+}
+
+impl ControlTotals {
+    pub fn new(records: &RecordList, encoding: Encoding) -> Self {
+        let rules = records.rules();
+        let mut fields: Vec<FieldTotal> = Vec::new();
+        for field in rules.iter().filter_map(|r| r.check()?.field()) {
+            if !fields.iter().any(|f| f.name == field) {
+                fields.push(FieldTotal {
+                    name: field.to_owned(),
+                    count: 0,
+                    sum: Decimal::default(),
+                    unreadable: 0,
+                });
+            }
+        }
+        Self {
+            encoding,
+            records: 0,
+            fields,
+            keys: HashMap::new(),
+            previous: HashMap::new(),
+            checked: rules.iter().any(|r| r.check().is_some()),
+        }
+    }
+
+    /// Write what's been counted and totalled, and the keys and last values seen, as rows of a `--resume` checkpoint,
+    /// each an entry starting `total_`.
+    pub fn save<W: Write>(&self, writer: &mut csv::Writer<W>) -> csv::Result<()> {
+        if !self.checked {
+            return Ok(());
+        }
+        writer.write_record(["total_records", &self.records.to_string()])?;
+        for field in &self.fields {
+            writer.write_record([
+                "total_field",
+                &field.count.to_string(),
+                &field.name,
+                &field.sum.to_string(),
+                &field.unreadable.to_string(),
+            ])?;
+        }
+        for (name, keys) in &self.keys {
+            for (key, line_index) in keys {
+                writer.write_record(["total_key", &line_index.to_string(), name, key])?;
+            }
+        }
+        for (name, value) in &self.previous {
+            writer.write_record(["total_previous", name, value])?;
+        }
+        Ok(())
+    }
+
+    /// Carry on from an entry of a `--resume` checkpoint, as [`ControlTotals::save`] wrote it.
+    pub fn restore(&mut self, row: &csv::StringRecord) -> anyhow::Result<()> {
+        let invalid = || format!("Checkpoint file has an invalid {}.", &row[0]);
+        let text = |i: usize| row.get(i).with_context(invalid);
+        let number = |i: usize| -> anyhow::Result<u64> { text(i)?.parse().with_context(invalid) };
+        match &row[0] {
+            "total_records" => self.records = number(1)?,
+            "total_field" => {
+                let name = text(2)?;
+                let Some(field) = self.fields.iter_mut().find(|f| f.name == name) else {
+                    bail!(
+                        "Checkpoint file totals the field '{}', which nothing checks.",
+                        name
+                    );
+                };
+                field.count = number(1)?;
+                field.sum = Decimal::parse(text(3)?).with_context(invalid)?;
+                field.unreadable = number(4)?;
+            }
+            "total_key" => {
+                self.keys
+                    .entry(text(2)?.to_owned())
+                    .or_default()
+                    .insert(text(3)?.to_owned(), number(1)? as usize);
+            }
+            "total_previous" => {
+                self.previous
+                    .insert(text(1)?.to_owned(), text(2)?.to_owned());
+            }
+            key => bail!("Checkpoint file has an unknown entry '{}'.", key),
+        }
+        Ok(())
+    }
+
+    /// Check the control totals of `prepared`, the `line_index`th (from 0) line, if it has any, logging any that
+    /// fail and starting the count again. Otherwise, count it. Either way, check its keys and sorted fields against
+    /// the records before it, returning only those that fail. Skipped lines are neither.
+    pub fn add_line(&mut self, line_index: usize, prepared: &PreparedLine) -> Vec<CheckResult> {
+        if !self.checked || prepared.skipped {
+            return Vec::new();
+        }
+        let length = prepared
+            .ghost_from
+            .unwrap_or_else(|| prepared.text.chars().count());
+        let present: Vec<&HighlightRegion> = prepared
+            .regions
+            .iter()
+            .filter(|r| r.start < length || r.start == r.end)
+            .collect();
+
+        let mut checks: Vec<CheckResult> = present
+            .iter()
+            .filter_map(|r| {
+                let check = r.check.as_ref().filter(|c| !c.is_total())?;
+                self.check_across(line_index, r, check, &prepared.text, length)
+            })
+            .collect();
+        let trailer = present
+            .iter()
+            .any(|r| r.check.as_ref().is_some_and(ControlCheck::is_total));
+        if trailer {
+            checks.extend(present.iter().filter_map(|r| {
+                let check = r.check.as_ref().filter(|c| c.is_total())?;
+                let value = region_value(&prepared.text, r, Some(length));
+                let given = number_value(&prepared.text, r, length, self.encoding);
+                Some(self.check(line_index, r, check, value, given))
+            }));
+        }
+        for failed in checks.iter().filter(|c| !c.passed) {
+            error!("Line {}: {}.", line_index + 1, failed.describe(Locale::En));
+        }
+        if trailer {
+            self.records = 0;
+            for field in &mut self.fields {
+                field.count = 0;
+                field.sum = Decimal::default();
+                field.unreadable = 0;
+            }
+            self.keys.clear();
+            self.previous.clear();
+            return checks;
+        }
+
+        self.records += 1;
+        for field in &mut self.fields {
+            for r in present.iter().filter(|r| *r.name == field.name) {
+                field.count += 1;
+                let number = number_value(&prepared.text, r, length, self.encoding)
+                    .and_then(|number| field.sum.checked_add(number));
+                match number {
+                    Some(sum) => field.sum = sum,
+                    None => field.unreadable += 1,
+                }
+            }
+        }
+        checks
+    }
+
+    /// Check the field `region` of the `line_index`th line, `line`, against the same field of the records before it, as
+    /// `check` says, and remember its value for the records after it. Only a failure is returned.
+    fn check_across(
+        &mut self,
+        line_index: usize,
+        region: &HighlightRegion,
+        check: &ControlCheck,
+        line: &str,
+        length: usize,
+    ) -> Option<CheckResult> {
+        let value = region_value(line, region, Some(length));
+        let actual = match check {
+            ControlCheck::Unique => {
+                let first = *self
+                    .keys
+                    .entry(region.name.to_string())
+                    .or_default()
+                    .entry(value.trim().to_owned())
+                    .or_insert(line_index);
+                (first != line_index).then(|| (first + 1).to_string())?
+            }
+            ControlCheck::Sorted(order) => {
+                let previous = self
+                    .previous
+                    .insert(region.name.to_string(), value.clone())?;
+                let number = |text: &str| decode_number(text, region, self.encoding);
+                let ordering = match (number(&previous), number(&value)) {
+                    (Some(before), Some(after)) => before.compare(after),
+                    _ => None,
+                }
+                .unwrap_or_else(|| previous.trim().cmp(value.trim()));
+                let in_order = match order {
+                    SortOrder::Ascending => ordering != Ordering::Greater,
+                    SortOrder::Descending => ordering != Ordering::Less,
+                };
+                if in_order {
+                    return None;
+                }
+                mask_value(region, previous)
+            }
+            _ => return None,
+        };
+        Some(CheckResult {
+            line_index,
+            field: region.name.to_string(),
+            check: check.clone(),
+            value: mask_value(region, value),
+            actual,
+            passed: false,
+        })
+    }
+
+    /// Check the control total `check` of the field `region` of a trailer, whose value is `value`, the number `given`,
+    /// against the records counted.
+    fn check(
+        &self,
+        line_index: usize,
+        region: &HighlightRegion,
+        check: &ControlCheck,
+        value: String,
+        given: Option<Decimal>,
+    ) -> CheckResult {
+        let field = |name: &str| self.fields.iter().find(|f| f.name == name);
+        let (actual, passed) = match check {
+            ControlCheck::Count => {
+                let actual = Decimal {
+                    units: self.records as i128,
+                    scale: 0,
+                };
+                (actual.to_string(), given.is_some_and(|g| g.equals(actual)))
+            }
+            ControlCheck::CountOf(name) => {
+                let actual = Decimal {
+                    units: field(name).map_or(0, |f| f.count) as i128,
+                    scale: 0,
+                };
+                (actual.to_string(), given.is_some_and(|g| g.equals(actual)))
+            }
+            ControlCheck::Sum(name) => match field(name) {
+                Some(total) if total.unreadable > 0 => (
+                    format!(
+                        "{}, leaving out {} value(s) of '{}' that aren't numbers",
+                        total.sum, total.unreadable, name
+                    ),
+                    false,
+                ),
+                Some(total) => (
+                    total.sum.to_string(),
+                    given.is_some_and(|g| g.equals(total.sum)),
+                ),
+                None => ("0".to_owned(), given.is_some_and(|g| g.units == 0)),
+            },
+            // checked by `check_across` as each record is read, rather than by a trailer
+            ControlCheck::Unique | ControlCheck::Sorted(_) => (String::new(), true),
+        };
+        CheckResult {
+            line_index,
+            field: region.name.to_string(),
+            check: check.clone(),
+            value,
+            actual,
+            passed,
+        }
+    }
+}
+
+/// The number the field `region` of `line` holds, decoded as its `type` says, or `None` if it isn't one.
+fn number_value(
+    line: &str,
+    region: &HighlightRegion,
+    length: usize,
+    encoding: Encoding,
+) -> Option<Decimal> {
+    decode_number(&region_value(line, region, Some(length)), region, encoding)
+}
+
+/// The number `text`, a value of the field `region`, holds, decoded as its `type` says, or `None` if it isn't one.
+fn decode_number(text: &str, region: &HighlightRegion, encoding: Encoding) -> Option<Decimal> {
+    match field_value(text, encoding, region.encoding, region.field_type.as_ref()) {
+        None => Decimal::parse(text),
+        Some(Ok(value)) => Decimal::parse(&value),
+        Some(Err(_)) => None,
+    }
+}
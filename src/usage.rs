@@ -0,0 +1,202 @@
+//! Counting which rules apply to the lines of a run, to find rules that never did: conditions that never matched and
+//! rules that never reached far enough into a line to be shown.
+
+use std::io::{self, Write};
+
+use anyhow::bail;
+use log::warn;
+
+use crate::{
+    escape_html, line_length,
+    locale::{Locale, Message},
+    Captures, Condition, PositionOptions, RecordList, RecordType, RecordTyper, SyntaxRule,
+};
+
+/// How often each rule applied over a run.
+pub struct RuleUsage {
+    pub rules: Vec<RuleCount>,
+    typer: RecordTyper,
+    positions: PositionOptions,
+}
+
+pub struct RuleCount {
+    pub name: String,
+    record: Option<RecordType>,
+    pub condition: Option<Condition>,
+    /// The line length (as measured by [`line_length`]) needed to reach the rule, or `None` if it can never apply.
+    reaches: Option<usize>,
+    /// Whether the rule is placed by a capture group, so how far it reaches depends on the line.
+    captured: bool,
+    /// The number of lines of the rule's record type (every line, if it has none).
+    of_record_type: u64,
+    /// The number of those lines the rule's condition matched.
+    pub matched: u64,
+    /// The number of those lines long enough for the rule to be shown.
+    pub applied: u64,
+}
+
+impl RuleCount {
+    /// Why the rule never applied, or `None` if it did.
+    fn finding(&self, locale: Locale) -> Option<String> {
+        if self.applied > 0 {
+            return None;
+        }
+        if self.reaches.is_none() && !self.captured {
+            return Some(locale.format(Message::RuleUnpositioned, &[&self.name]));
+        }
+        if let (Some(record), 0) = (&self.record, self.of_record_type) {
+            return Some(
+                locale.format(Message::RuleRecordNeverMatched, &[&self.name, &record.name]),
+            );
+        }
+        match (&self.condition, self.matched) {
+            (Some(condition), 0) => {
+                Some(locale.format(Message::RuleNeverMatched, &[&self.name, condition]))
+            }
+            _ => Some(locale.format(Message::RuleNeverReached, &[&self.name, &self.matched])),
+        }
+    }
+}
+
+/// The line length needed to reach each rule on a line with `captures`.
+fn reaches(
+    records: &RecordList,
+    captures: &Captures,
+    positions: PositionOptions,
+) -> Vec<Option<usize>> {
+    let shifted = |position: Option<usize>, rule: &dyn SyntaxRule| {
+        position
+            .map(|p| p as isize - positions.index_base as isize + rule.shift(positions))
+            .filter(|p| *p >= 0)
+            .map(|p| p as usize)
+    };
+    match records {
+        RecordList::FixedWidth(fw_records) => fw_records
+            .iter()
+            .map(|r| {
+                shifted(r.start_on(captures), r)
+                    .filter(|_| r.length_on(captures).is_some())
+                    .map(|s| s + 1)
+            })
+            .collect(),
+        RecordList::Delimiter(_, d_records) => d_records
+            .iter()
+            .map(|r| shifted(r.field_on(captures), r).map(|f| f + 1))
+            .collect(),
+    }
+}
+
+impl RuleUsage {
+    pub fn new(records: &RecordList, positions: PositionOptions) -> Self {
+        let mut counts = Vec::new();
+        for (rule, reaches) in
+            records
+                .rules()
+                .into_iter()
+                .zip(reaches(records, &Captures::default(), positions))
+        {
+            counts.push(RuleCount {
+                name: rule.name().to_owned(),
+                record: rule.record().cloned(),
+                condition: rule.condition().cloned(),
+                reaches,
+                captured: !rule.placeholders().is_empty(),
+                of_record_type: 0,
+                matched: 0,
+                applied: 0,
+            });
+        }
+        Self {
+            rules: counts,
+            typer: RecordTyper::new(records),
+            positions,
+        }
+    }
+
+    /// Count the rules that apply to `line`.
+    pub fn count_line(&mut self, records: &RecordList, line: &str) {
+        let length = line_length(records, line, self.positions);
+        let record_type = self.typer.record_type(line);
+        let captured = if records.places_by_capture() {
+            let captures = Captures::of(records, record_type, line);
+            reaches(records, &captures, self.positions)
+        } else {
+            Vec::new()
+        };
+        for (i, rule) in self.rules.iter_mut().enumerate() {
+            if rule.record.as_ref().is_some_and(|r| Some(r) != record_type) {
+                continue;
+            }
+            rule.of_record_type += 1;
+            if rule.condition.as_ref().is_none_or(|c| c.is_match(line)) {
+                rule.matched += 1;
+                let reaches = if rule.captured {
+                    captured[i]
+                } else {
+                    rule.reaches
+                };
+                if reaches.is_some_and(|r| r <= length) {
+                    rule.applied += 1;
+                }
+            }
+        }
+    }
+
+    /// How often each rule has applied so far, for a `--resume` checkpoint: the number of lines of its record type, of
+    /// those its condition matched, and of those it was shown on.
+    pub fn counts(&self) -> Vec<[u64; 3]> {
+        self.rules
+            .iter()
+            .map(|r| [r.of_record_type, r.matched, r.applied])
+            .collect()
+    }
+
+    /// Carry on counting from how often each rule applied before a `--resume` checkpoint, as [`RuleUsage::counts`]
+    /// gave them.
+    pub fn restore(&mut self, counts: &[[u64; 3]]) -> anyhow::Result<()> {
+        if counts.len() != self.rules.len() {
+            bail!(
+                "The checkpoint counts {} rule(s), but the syntax file has {}.",
+                counts.len(),
+                self.rules.len()
+            );
+        }
+        for (rule, [of_record_type, matched, applied]) in self.rules.iter_mut().zip(counts) {
+            rule.of_record_type = *of_record_type;
+            rule.matched = *matched;
+            rule.applied = *applied;
+        }
+        Ok(())
+    }
+
+    /// Why each rule that never applied didn't.
+    pub fn findings(&self, locale: Locale) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter_map(|r| r.finding(locale))
+            .collect()
+    }
+
+    /// Log every rule that never applied.
+    pub fn log(&self) {
+        for finding in self.findings(Locale::En) {
+            warn!("Unused rule: {}", finding);
+        }
+    }
+
+    /// Write the rules that never applied as a section of the report.
+    pub fn write_html(&self, out: &mut dyn Write, locale: Locale) -> io::Result<()> {
+        writeln!(out, "<h2>{}</h2>", locale.text(Message::UnusedRules))?;
+        let findings = self.findings(locale);
+        if findings.is_empty() {
+            writeln!(out, "<p>{}</p>", locale.text(Message::NoUnusedRules))?;
+            return Ok(());
+        }
+        writeln!(out, "<ul>")?;
+        for finding in findings {
+            writeln!(out, "<li>{}</li>", escape_html(&finding))?;
+        }
+        writeln!(out, "</ul>")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,400 @@
+//! The `validate` subcommand, which checks a control (or manifest) file against the data file it describes: that the
+//! counts and totals it declares add up, and that the keys on each side are present on the other.
+
+use std::io::BufReader;
+
+use anyhow::{bail, Context};
+
+use crate::{
+    compression, generate_highlight_regions_from_records, is_skipped, load_syntax, record_text,
+    region_value, select::read_records, Args, Assembly, PositionOptions, ProblemsFound, RecordList,
+    ValidateArgs,
+};
+
+/// A number as written in a field, e.g. `+0012.50`, held as an integer scaled by a power of ten so that sums are exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Decimal {
+    value: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Parse a field as a number, allowing surrounding spaces, a leading or trailing sign and a decimal point.
+    fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim();
+        let (negative, digits) = if let Some(rest) = text.strip_prefix('-') {
+            (true, rest)
+        } else if let Some(rest) = text.strip_suffix('-') {
+            (true, rest)
+        } else {
+            (
+                false,
+                text.strip_prefix('+')
+                    .or_else(|| text.strip_suffix('+'))
+                    .unwrap_or(text),
+            )
+        };
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut value: i128 = 0;
+        let mut scale = None;
+        for c in digits.chars() {
+            match c {
+                '0'..='9' => {
+                    value = value
+                        .checked_mul(10)?
+                        .checked_add(c as i128 - '0' as i128)?;
+                    scale = scale.map(|s| s + 1);
+                }
+                '.' if scale.is_none() => scale = Some(0),
+                _ => return None,
+            }
+        }
+        Some(Decimal {
+            value: if negative { -value } else { value },
+            scale: scale.unwrap_or(0),
+        })
+    }
+
+    fn rescale(self, scale: u32) -> Option<i128> {
+        self.value
+            .checked_mul(10i128.checked_pow(scale - self.scale)?)
+    }
+
+    fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        Some(Decimal {
+            value: self.rescale(scale)?.checked_add(other.rescale(scale)?)?,
+            scale,
+        })
+    }
+
+    fn equals(self, other: Decimal) -> bool {
+        let scale = self.scale.max(other.scale);
+        self.rescale(scale) == other.rescale(scale)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scale == 0 {
+            return write!(f, "{}", self.value);
+        }
+        let divisor = 10i128.pow(self.scale);
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            if self.value < 0 { "-" } else { "" },
+            (self.value / divisor).abs(),
+            (self.value % divisor).abs(),
+            width = self.scale as usize
+        )
+    }
+}
+
+/// A `--total` pairing: the field of the control file which declares the sum of a field of the data file.
+struct Total {
+    control_field: String,
+    data_field: String,
+}
+
+/// What the control file declares, or the data file adds up to, for one key (or the whole file without `--join`).
+#[derive(Default)]
+struct Tally {
+    /// The line of the file the key first appears on.
+    first_line: usize,
+    /// The lines with the key.
+    lines: usize,
+    /// The values of the `--count` field, in the control file.
+    count: Option<String>,
+    /// Each `--total` field's value in the control file, or the sum of its data field in the data file.
+    totals: Vec<Option<Decimal>>,
+}
+
+/// The fields of a file to tally.
+#[derive(Clone, Copy)]
+struct TallyFields<'a> {
+    key: Option<&'a str>,
+    count: Option<&'a str>,
+    totals: &'a [&'a str],
+    /// Whether to add up the `totals` fields of every line, as for the data file, rather than take them as declared.
+    summing: bool,
+}
+
+/// Read every line of a file, tallying its lines and fields by key, in the order keys first appear.
+///
+/// Lines without the key field (e.g. the header of a data file) aren't counted when joining on a key.
+fn tally_file(
+    args: &Args,
+    input_file: &str,
+    records: &RecordList,
+    positions: PositionOptions,
+    assembly: &Assembly,
+    fields: &TallyFields,
+) -> anyhow::Result<Vec<(Option<String>, Tally)>> {
+    let TallyFields {
+        key,
+        count: count_field,
+        totals: total_fields,
+        summing,
+    } = *fields;
+    let mut tallies: Vec<(Option<String>, Tally)> = Vec::new();
+    let file = compression::open(input_file, args.compression)
+        .with_context(|| format!("Failed to open input file '{}'.", input_file))?;
+    for line in read_records(args, BufReader::new(file), assembly) {
+        let (idx, line) = line.context("Failed to read line from input file.")?;
+        if is_skipped(args, &line) {
+            continue;
+        }
+        let line = record_text(&line);
+        let length = line.chars().count();
+        let regions = generate_highlight_regions_from_records(records, &line, positions);
+        let value = |name: &str| {
+            regions
+                .iter()
+                .find(|r| &*r.name == name && r.start < length)
+                .map(|r| region_value(&line, r, None))
+        };
+
+        let key_value = match key {
+            Some(key) => match value(key) {
+                Some(k) => Some(k.trim().to_owned()),
+                None => continue,
+            },
+            None => None,
+        };
+        let count = count_field.and_then(value);
+        let totals: Vec<Option<String>> = total_fields.iter().map(|f| value(f)).collect();
+        if !summing && count.is_none() && totals.iter().all(Option::is_none) && key.is_none() {
+            // a control file's other lines (e.g. its header) don't declare anything
+            continue;
+        }
+
+        let tally = match tallies.iter().position(|(k, _)| *k == key_value) {
+            Some(i) => &mut tallies[i].1,
+            None => {
+                tallies.push((
+                    key_value,
+                    Tally {
+                        first_line: idx + 1,
+                        totals: vec![None; total_fields.len()],
+                        ..Default::default()
+                    },
+                ));
+                &mut tallies.last_mut().unwrap().1
+            }
+        };
+        tally.lines += 1;
+        if count.is_some() {
+            tally.count = count;
+        }
+        for (i, total) in totals.into_iter().enumerate() {
+            let Some(total) = total else {
+                continue;
+            };
+            let Some(number) = Decimal::parse(&total) else {
+                bail!(
+                    "Line {} of {}: field '{}' should be a number, but is '{}'.",
+                    idx + 1,
+                    input_file,
+                    total_fields[i],
+                    total.trim()
+                );
+            };
+            tally.totals[i] = if summing {
+                Some(
+                    tally.totals[i]
+                        .unwrap_or_default()
+                        .checked_add(number)
+                        .with_context(|| {
+                            format!("The total of '{}' is too large.", total_fields[i])
+                        })?,
+                )
+            } else {
+                Some(number)
+            };
+        }
+    }
+    Ok(tallies)
+}
+
+/// Run the `validate` subcommand.
+pub fn run(args: &Args, validate: &ValidateArgs) -> anyhow::Result<()> {
+    let (_, control_records, positions, control_assembly) =
+        load_syntax(args, &validate.syntax_file, Some(&validate.input_file))?;
+    let (_, data_records, _, data_assembly) = load_syntax(
+        args,
+        validate
+            .with_syntax
+            .as_deref()
+            .unwrap_or(&validate.syntax_file),
+        Some(&validate.with),
+    )?;
+
+    let totals: Vec<Total> = validate
+        .total
+        .iter()
+        .map(|t| match t.split_once('=') {
+            Some((control, data)) => Total {
+                control_field: control.to_owned(),
+                data_field: data.to_owned(),
+            },
+            None => Total {
+                control_field: t.clone(),
+                data_field: t.clone(),
+            },
+        })
+        .collect();
+
+    let control_names = control_records.field_names();
+    let data_names = data_records.field_names();
+    let mut missing: Vec<String> = Vec::new();
+    for name in validate.join.iter().chain(&validate.count) {
+        if !control_names.contains(name) {
+            missing.push(format!("'{}' in the control syntax", name));
+        }
+    }
+    if let Some(key) = &validate.join {
+        if !data_names.contains(key) {
+            missing.push(format!("'{}' in the data syntax", key));
+        }
+    }
+    for total in &totals {
+        if !control_names.contains(&total.control_field) {
+            missing.push(format!("'{}' in the control syntax", total.control_field));
+        }
+        if !data_names.contains(&total.data_field) {
+            missing.push(format!("'{}' in the data syntax", total.data_field));
+        }
+    }
+    if !missing.is_empty() {
+        bail!("There is no field {}.", missing.join(", or "));
+    }
+
+    let control_totals: Vec<&str> = totals.iter().map(|t| t.control_field.as_str()).collect();
+    let data_totals: Vec<&str> = totals.iter().map(|t| t.data_field.as_str()).collect();
+    let control = tally_file(
+        args,
+        &validate.input_file,
+        &control_records,
+        positions,
+        &control_assembly,
+        &TallyFields {
+            key: validate.join.as_deref(),
+            count: validate.count.as_deref(),
+            totals: &control_totals,
+            summing: false,
+        },
+    )?;
+    let data = tally_file(
+        args,
+        &validate.with,
+        &data_records,
+        positions,
+        &data_assembly,
+        &TallyFields {
+            key: validate.join.as_deref(),
+            count: None,
+            totals: &data_totals,
+            summing: true,
+        },
+    )?;
+
+    let mut problems: Vec<String> = Vec::new();
+    let describe = |key: &Option<String>| match key {
+        Some(key) => format!("key '{}'", key),
+        None => "the file".to_owned(),
+    };
+    let no_data = Tally {
+        totals: vec![None; totals.len()],
+        ..Default::default()
+    };
+    for (key, declared) in &control {
+        let actual = match data.iter().find(|(k, _)| k == key) {
+            Some((_, actual)) => actual,
+            None if key.is_some() => {
+                problems.push(format!(
+                    "Control line {}: {} has no lines in {}.",
+                    declared.first_line,
+                    describe(key),
+                    validate.with
+                ));
+                &no_data
+            }
+            None => &no_data,
+        };
+        if let Some(count) = &declared.count {
+            let lines = Decimal {
+                value: actual.lines as i128,
+                scale: 0,
+            };
+            if !Decimal::parse(count).is_some_and(|c| c.equals(lines)) {
+                problems.push(format!(
+                    "Control line {}: '{}' for {} is {}, but {} has {} line(s).",
+                    declared.first_line,
+                    validate.count.as_deref().unwrap_or_default(),
+                    describe(key),
+                    count.trim(),
+                    validate.with,
+                    actual.lines
+                ));
+            }
+        }
+        for (i, total) in totals.iter().enumerate() {
+            let Some(expected) = declared.totals[i] else {
+                continue;
+            };
+            let sum = actual.totals[i].unwrap_or_default();
+            if !expected.equals(sum) {
+                problems.push(format!(
+                    "Control line {}: '{}' for {} is {}, but '{}' in {} adds up to {}.",
+                    declared.first_line,
+                    total.control_field,
+                    describe(key),
+                    expected,
+                    total.data_field,
+                    validate.with,
+                    sum
+                ));
+            }
+        }
+    }
+    if validate.join.is_some() {
+        for (key, actual) in &data {
+            if !control.iter().any(|(k, _)| k == key) {
+                problems.push(format!(
+                    "{} line {}: {} is not in {}.",
+                    validate.with,
+                    actual.first_line,
+                    describe(key),
+                    validate.input_file
+                ));
+            }
+        }
+    }
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        bail!(ProblemsFound(format!(
+            "Validation found {} problem(s).",
+            problems.len()
+        )));
+    }
+    println!(
+        "{} and {} are consistent: {} control {} checked against {} line(s).",
+        validate.input_file,
+        validate.with,
+        control.len(),
+        if validate.join.is_some() {
+            "key(s)"
+        } else {
+            "record(s)"
+        },
+        data.iter().map(|(_, t)| t.lines).sum::<usize>()
+    );
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Conditional styles: a style drawn over a field whose value matches a regex, such as an amount in red when it's
+//! negative, from the `value_pattern` and `value_style` columns of a syntax file, so anomalies stand out from the
+//! structure around them.
+
+use std::{fmt, sync::Arc};
+
+use anyhow::bail;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{hex_color, parse_hex_color, region_value, PreparedLine};
+
+/// The regex a field's value is matched against to be drawn in its `value_style`, from the `value_pattern` column of a
+/// syntax file. Spaces around the value are trimmed first, as they are for a `field:` condition, and a pattern written
+/// with a leading `!` matches the values the regex doesn't.
+#[derive(Debug, Clone)]
+pub struct ValuePattern {
+    spec: String,
+    /// Shared by the region of every line the rule applies to, as a cloned regex would build its matching cache anew.
+    regex: Arc<Regex>,
+    negated: bool,
+}
+
+impl ValuePattern {
+    pub fn parse(spec: &str) -> anyhow::Result<ValuePattern> {
+        let (negated, pattern) = match spec.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, spec),
+        };
+        match Regex::new(pattern) {
+            Ok(regex) => Ok(ValuePattern {
+                spec: spec.to_owned(),
+                regex: Arc::new(regex),
+                negated,
+            }),
+            Err(e) => bail!("Value pattern `{}` is not a valid regex: {}", spec, e),
+        }
+    }
+
+    /// The pattern as written in the syntax file.
+    pub fn spec(&self) -> &str {
+        &self.spec
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        self.regex.is_match(value.trim()) != self.negated
+    }
+}
+
+impl fmt::Display for ValuePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.spec)
+    }
+}
+
+/// How a field whose value matches its `value_pattern` is drawn, over its colour: in a text colour, and bold, italic
+/// or underlined, from the `value_style` column of a syntax file, e.g. `red bold`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueStyle {
+    /// The colour of the text, as a hex code without the `#`.
+    pub color: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Default for ValueStyle {
+    /// Bold red text, for a `value_pattern` given without a `value_style`.
+    fn default() -> Self {
+        ValueStyle {
+            color: Some("e00000".to_owned()),
+            bold: true,
+            italic: false,
+            underline: false,
+        }
+    }
+}
+
+impl ValueStyle {
+    /// Parse a style: a colour, as a hex code or CSS colour name, and any of `bold`, `italic` and `underline`,
+    /// separated by spaces.
+    pub fn parse(spec: &str) -> anyhow::Result<ValueStyle> {
+        let mut style = ValueStyle {
+            color: None,
+            bold: false,
+            italic: false,
+            underline: false,
+        };
+        for word in spec.split_whitespace() {
+            match word.to_lowercase().as_str() {
+                "bold" => style.bold = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                _ if style.color.is_some() => bail!(
+                    "Value style `{}` gives more than one colour; it should be a colour and any of `bold`, `italic` and `underline`.",
+                    spec
+                ),
+                _ => style.color = Some(hex_color(word)?.to_lowercase()),
+            }
+        }
+        if style.color.is_none() && !style.bold && !style.italic && !style.underline {
+            bail!("Value style `{}` is empty.", spec);
+        }
+        Ok(style)
+    }
+
+    /// The style as it would be written in a syntax file.
+    pub fn spec(&self) -> String {
+        let mut words: Vec<&str> = self.color.iter().map(String::as_str).collect();
+        for (set, word) in [
+            (self.bold, "bold"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+        ] {
+            if set {
+                words.push(word);
+            }
+        }
+        words.join(" ")
+    }
+
+    /// The class of elements drawn in the style, when lines are written with classes, e.g. `ffh-v-e00000-b`.
+    pub fn class(&self) -> String {
+        let mut class = format!("ffh-v-{}", self.color.as_deref().unwrap_or("x"));
+        for (set, letter) in [
+            (self.bold, "-b"),
+            (self.italic, "-i"),
+            (self.underline, "-u"),
+        ] {
+            if set {
+                class.push_str(letter);
+            }
+        }
+        class
+    }
+
+    /// The style as CSS declarations.
+    pub fn css(&self) -> String {
+        let mut css = Vec::new();
+        if let Some(color) = &self.color {
+            css.push(format!("color: #{};", color));
+        }
+        if self.bold {
+            css.push("font-weight: bold;".to_owned());
+        }
+        if self.italic {
+            css.push("font-style: italic;".to_owned());
+        }
+        if self.underline {
+            css.push("text-decoration: underline;".to_owned());
+        }
+        css.join(" ")
+    }
+
+    /// The escape sequence drawing text in the style in a terminal, over whatever it's drawn in already.
+    pub fn ansi(&self) -> String {
+        let mut codes = Vec::new();
+        if let Some((r, g, b)) = self.color.as_deref().and_then(parse_hex_color) {
+            codes.push(format!("38;2;{};{};{}", r, g, b));
+        }
+        for (set, code) in [(self.bold, "1"), (self.italic, "3"), (self.underline, "4")] {
+            if set {
+                codes.push(code.to_owned());
+            }
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+/// A rule for each of the distinct `styles`, to include in the stylesheet of lines written with classes.
+pub fn value_stylesheet<'a>(styles: impl IntoIterator<Item = &'a ValueStyle>) -> String {
+    let mut seen: Vec<&ValueStyle> = Vec::new();
+    let mut css = String::new();
+    for style in styles {
+        if !seen.contains(&style) {
+            css.push_str(&format!(".{} {{ {} }}\n", style.class(), style.css()));
+            seen.push(style);
+        }
+    }
+    css
+}
+
+/// The style each region of `prepared` is drawn in, by its index: its `value_style` if its value matches its
+/// `value_pattern`.
+pub fn value_styles(prepared: &PreparedLine) -> Vec<Option<&ValueStyle>> {
+    let length = prepared.text.chars().count();
+    prepared
+        .regions
+        .iter()
+        .map(|r| {
+            let (pattern, style) = r.value_style.as_ref()?;
+            // a field the line is too short to hold has no value to match
+            let present = r.start < prepared.ghost_from.unwrap_or(length).min(length);
+            let value = region_value(&prepared.text, r, prepared.ghost_from);
+            (present && pattern.matches(&value)).then_some(style)
+        })
+        .collect()
+}
+
+/// Read an optional `value_pattern` column, failing on patterns that aren't valid regexes.
+pub fn deserialize_value_pattern<'de, D>(deserializer: D) -> Result<Option<ValuePattern>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.is_empty() => ValuePattern::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Read an optional `value_style` column, failing on styles that can't be parsed.
+pub fn deserialize_value_style<'de, D>(deserializer: D) -> Result<Option<ValueStyle>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(spec) if !spec.trim().is_empty() => ValueStyle::parse(&spec)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        _ => Ok(None),
+    }
+}
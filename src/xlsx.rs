@@ -0,0 +1,580 @@
+//! `--output-format xlsx`: the values of every line's fields as an Excel workbook, with a sheet for each record type
+//! whose columns are its fields, and a sheet of the problems found. The workbook is written by hand, as a zip of
+//! SpreadsheetML parts whose entries are stored uncompressed.
+
+use std::{collections::HashMap, io::Write, sync::Arc};
+
+use anyhow::{bail, Context};
+
+use crate::{region_value, LineProblem, LineReport, ReportLine, Spool};
+
+/// The most rows a worksheet can have. A sheet that fills up is continued on another.
+const MAX_ROWS: usize = 1_048_576;
+
+/// The longest a sheet's name can be.
+const MAX_SHEET_NAME: usize = 31;
+
+/// The name of the sheet of lines that are of no record type.
+const UNTYPED_SHEET: &str = "Lines";
+
+/// The name of the sheet of problems.
+const ERRORS_SHEET: &str = "Errors";
+
+/// The columns of the sheet of problems.
+const ERROR_COLUMNS: [&str; 7] = [
+    "line",
+    "record_type",
+    "kind",
+    "severity",
+    "field",
+    "value",
+    "message",
+];
+
+const CONTENT_TYPES_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+/// The styles of the cells: plain (`0`) and bold, for the header rows (`1`).
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+/// The start of every worksheet, with its header row frozen so it stays in view.
+const SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#;
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+/// `text` escaped to be the content or an attribute of an XML element. Control characters, which XML can't hold, are
+/// written as the `_xHHHH_` escapes Excel reads them back from.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => {
+                escaped.push_str(&format!("_x{:04X}_", c as u32));
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The letters of the `index`th (from 0) column, e.g. `A`, `Z`, `AA`.
+fn column_letters(index: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = index + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).expect("column letters are ASCII")
+}
+
+/// A cell of a row: a number, or text, which is kept exactly as it is, leading zeros and all.
+enum Cell<'a> {
+    Number(usize),
+    Text(&'a str),
+}
+
+/// A row of cells as XML, the `row`th (from 1) of its sheet, with each cell in the column given with it, which must
+/// increase along the row.
+fn row_xml(row: usize, cells: &[(usize, Cell)], style: u8) -> String {
+    let mut xml = format!(r#"<row r="{}">"#, row);
+    let style = if style == 0 {
+        String::new()
+    } else {
+        format!(r#" s="{}""#, style)
+    };
+    for (column, cell) in cells {
+        let reference = format!("{}{}", column_letters(*column), row);
+        match cell {
+            Cell::Number(n) => xml.push_str(&format!(
+                r#"<c r="{}"{}><v>{}</v></c>"#,
+                reference, style, n
+            )),
+            Cell::Text(text) => xml.push_str(&format!(
+                r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                reference,
+                style,
+                escape_xml(text)
+            )),
+        }
+    }
+    xml.push_str("</row>");
+    xml
+}
+
+/// A worksheet being filled. Its rows are spooled, and its header row, whose columns are only all known once every
+/// row is, is written before them when the workbook is finished.
+struct Sheet {
+    name: String,
+    columns: Vec<Arc<str>>,
+    /// The number of columns at the start that aren't fields, such as the line number, so a field of the same name
+    /// is given a column of its own.
+    fixed: usize,
+    rows: Spool,
+    /// The rows written, not counting the header.
+    row_count: usize,
+}
+
+impl Sheet {
+    fn new(name: String, columns: Vec<Arc<str>>, fixed: usize) -> Self {
+        Self {
+            name,
+            columns,
+            fixed,
+            rows: Spool::new(),
+            row_count: 0,
+        }
+    }
+
+    /// The column of the field `name`, which is added if the sheet doesn't have one yet.
+    fn column(&mut self, name: &Arc<str>) -> usize {
+        match self.columns[self.fixed..].iter().position(|c| c == name) {
+            Some(column) => self.fixed + column,
+            None => {
+                self.columns.push(name.clone());
+                self.columns.len() - 1
+            }
+        }
+    }
+
+    /// Whether the sheet has room for another row.
+    fn has_room(&self) -> bool {
+        self.row_count + 1 < MAX_ROWS
+    }
+
+    /// Add a row of `cells`, by column, in any order. Where two cells are in one column, the first is kept.
+    fn push_row(&mut self, mut cells: Vec<(usize, Cell)>) -> anyhow::Result<()> {
+        cells.sort_by_key(|(column, _)| *column);
+        cells.dedup_by_key(|(column, _)| *column);
+        self.row_count += 1;
+        self.rows.push_str(&row_xml(self.row_count + 1, &cells, 0))
+    }
+
+    /// Write the sheet's XML to `out`.
+    fn write_to(self, out: &mut dyn Write) -> anyhow::Result<()> {
+        let header: Vec<(usize, Cell)> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(column, name)| (column, Cell::Text(name)))
+            .collect();
+        write!(out, "{}{}", SHEET_START, row_xml(1, &header, 1))?;
+        self.rows.write_to(out)?;
+        write!(out, "{}", SHEET_END)?;
+        Ok(())
+    }
+}
+
+/// What a sheet holds.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Contents {
+    /// The lines of a record type, or of no record type.
+    Lines(Option<String>),
+    /// The problems found with the lines.
+    Problems,
+}
+
+/// The sheets of a workbook, found as its lines are read.
+#[derive(Default)]
+struct Workbook {
+    sheets: Vec<(Contents, Sheet)>,
+    /// The sheet each kind of row is being added to, which is the last of the sheets of that kind.
+    current: HashMap<Contents, usize>,
+}
+
+impl Workbook {
+    /// A name for a sheet based on `name`, which Excel allows and no other sheet has. The first sheet of problems is
+    /// always named [`ERRORS_SHEET`], which no sheet of lines is given.
+    fn sheet_name(&self, name: &str, contents: &Contents) -> String {
+        let cleaned: String = name
+            .chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .collect();
+        let cleaned = cleaned.trim_matches('\'');
+        let cleaned = if cleaned.is_empty() { "_" } else { cleaned };
+        let taken = |candidate: &str| {
+            *contents != Contents::Problems && candidate.eq_ignore_ascii_case(ERRORS_SHEET)
+                || self
+                    .sheets
+                    .iter()
+                    .any(|(_, s)| s.name.eq_ignore_ascii_case(candidate))
+        };
+        let mut number = 1;
+        loop {
+            let suffix = if number == 1 {
+                String::new()
+            } else {
+                format!(" ({})", number)
+            };
+            let candidate: String = cleaned
+                .chars()
+                .take(MAX_SHEET_NAME - suffix.chars().count())
+                .chain(suffix.chars())
+                .collect();
+            if !taken(&candidate) {
+                return candidate;
+            }
+            number += 1;
+        }
+    }
+
+    /// The sheet the next row of `contents` is added to, started if there's none or the last is full.
+    fn sheet(&mut self, contents: Contents) -> &mut Sheet {
+        let idx = match self.current.get(&contents) {
+            Some(&idx) if self.sheets[idx].1.has_room() => idx,
+            current => {
+                // a sheet continuing a full one has its columns
+                let (columns, fixed) = match (current, &contents) {
+                    (Some(&idx), _) => {
+                        let full = &self.sheets[idx].1;
+                        (full.columns.clone(), full.fixed)
+                    }
+                    (None, Contents::Lines(_)) => (vec!["line".into()], 1),
+                    (None, Contents::Problems) => (
+                        ERROR_COLUMNS.iter().map(|&c| c.into()).collect(),
+                        ERROR_COLUMNS.len(),
+                    ),
+                };
+                let name = match &contents {
+                    Contents::Lines(record_type) => {
+                        self.sheet_name(record_type.as_deref().unwrap_or(UNTYPED_SHEET), &contents)
+                    }
+                    Contents::Problems => self.sheet_name(ERRORS_SHEET, &contents),
+                };
+                self.sheets
+                    .push((contents.clone(), Sheet::new(name, columns, fixed)));
+                self.current.insert(contents, self.sheets.len() - 1);
+                self.sheets.len() - 1
+            }
+        };
+        &mut self.sheets[idx].1
+    }
+
+    /// Write the workbook to `out`, with the sheets of lines in the order their first lines were read, then the
+    /// sheets of problems.
+    fn write_to(mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        // a workbook always has a sheet of lines and of problems, which are left empty if there are none
+        if !self
+            .sheets
+            .iter()
+            .any(|(contents, _)| matches!(contents, Contents::Lines(_)))
+        {
+            self.sheet(Contents::Lines(None));
+        }
+        self.sheet(Contents::Problems);
+        let (mut sheets, problems): (Vec<_>, Vec<_>) = self
+            .sheets
+            .into_iter()
+            .partition(|(contents, _)| *contents != Contents::Problems);
+        sheets.extend(problems);
+        let sheets: Vec<Sheet> = sheets.into_iter().map(|(_, sheet)| sheet).collect();
+        let mut zip = Zip::new(out);
+
+        let mut content_types = CONTENT_TYPES_START.to_owned();
+        let mut workbook = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#.to_owned();
+        let mut rels = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#
+            .to_owned();
+        for (i, sheet) in sheets.iter().enumerate() {
+            let number = i + 1;
+            content_types.push_str(&format!(
+                r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+                number
+            ));
+            workbook.push_str(&format!(
+                r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+                escape_xml(&sheet.name),
+                number,
+                number
+            ));
+            rels.push_str(&format!(
+                r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+                number, number
+            ));
+        }
+        content_types.push_str("</Types>");
+        workbook.push_str("</sheets></workbook>");
+        rels.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#,
+            sheets.len() + 1
+        ));
+
+        zip.entry("[Content_Types].xml", |out| {
+            Ok(out.write_all(content_types.as_bytes())?)
+        })?;
+        zip.entry(
+            "_rels/.rels",
+            |out| Ok(out.write_all(ROOT_RELS.as_bytes())?),
+        )?;
+        zip.entry("xl/workbook.xml", |out| {
+            Ok(out.write_all(workbook.as_bytes())?)
+        })?;
+        zip.entry("xl/_rels/workbook.xml.rels", |out| {
+            Ok(out.write_all(rels.as_bytes())?)
+        })?;
+        zip.entry("xl/styles.xml", |out| Ok(out.write_all(STYLES.as_bytes())?))?;
+        for (i, sheet) in sheets.into_iter().enumerate() {
+            zip.entry(&format!("xl/worksheets/sheet{}.xml", i + 1), |out| {
+                sheet.write_to(out)
+            })?;
+        }
+        zip.finish()
+    }
+}
+
+/// The CRC-32 of each byte, for [`crc32`].
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continue the CRC-32 `crc`, of the bytes before `bytes`, over them.
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Passes what's written on to `out`, counting it and finding its CRC-32.
+struct Checksummed<'a> {
+    out: &'a mut dyn Write,
+    crc: u32,
+    length: u64,
+}
+
+impl Write for Checksummed<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.out.write(buf)?;
+        self.crc = crc32(self.crc, &buf[..written]);
+        self.length += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A zip file being written, of entries stored as they are. Each entry's size and CRC-32 follow it, so it can be
+/// written as it's made, without seeking back.
+struct Zip<'a> {
+    out: &'a mut dyn Write,
+    /// The offset the next entry is written at.
+    offset: u64,
+    /// The central directory's record of each entry written.
+    directory: Vec<u8>,
+    entries: u16,
+}
+
+impl<'a> Zip<'a> {
+    /// The version of the zip format needed to read the file, 2.0.
+    const VERSION: u16 = 20;
+    /// An entry's size and CRC-32 are in a descriptor after it.
+    const DESCRIPTOR_FLAG: u16 = 1 << 3;
+    /// 1980-01-01, the earliest date an entry can have, as an MS-DOS date, so the same report is the same file.
+    const DATE: u16 = (1 << 5) | 1;
+
+    fn new(out: &'a mut dyn Write) -> Self {
+        Self {
+            out,
+            offset: 0,
+            directory: Vec::new(),
+            entries: 0,
+        }
+    }
+
+    /// Add an entry named `name`, whose contents `write` writes.
+    fn entry(
+        &mut self,
+        name: &str,
+        write: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let offset = u32::try_from(self.offset)
+            .context("The workbook is too large for a zip file of its size.")?;
+        let mut header = Vec::new();
+        header.extend(0x0403_4b50u32.to_le_bytes());
+        header.extend(Self::VERSION.to_le_bytes());
+        header.extend(Self::DESCRIPTOR_FLAG.to_le_bytes());
+        // stored, at midnight on the date
+        header.extend(0u16.to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(Self::DATE.to_le_bytes());
+        // the CRC-32 and sizes, which are in the descriptor
+        header.extend([0; 12]);
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(name.as_bytes());
+        self.out.write_all(&header)?;
+
+        let mut contents = Checksummed {
+            out: &mut *self.out,
+            crc: 0,
+            length: 0,
+        };
+        write(&mut contents)?;
+        let (crc, length) = (contents.crc, contents.length);
+        let Ok(size) = u32::try_from(length) else {
+            bail!(
+                "The sheet {} of the workbook is over 4 GiB, too large for a zip file of its size.",
+                name
+            );
+        };
+        let mut descriptor = Vec::new();
+        descriptor.extend(0x0807_4b50u32.to_le_bytes());
+        descriptor.extend(crc.to_le_bytes());
+        descriptor.extend(size.to_le_bytes());
+        descriptor.extend(size.to_le_bytes());
+        self.out.write_all(&descriptor)?;
+
+        let directory = &mut self.directory;
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend(Self::VERSION.to_le_bytes());
+        directory.extend(Self::VERSION.to_le_bytes());
+        directory.extend(Self::DESCRIPTOR_FLAG.to_le_bytes());
+        directory.extend(0u16.to_le_bytes());
+        directory.extend(0u16.to_le_bytes());
+        directory.extend(Self::DATE.to_le_bytes());
+        directory.extend(crc.to_le_bytes());
+        directory.extend(size.to_le_bytes());
+        directory.extend(size.to_le_bytes());
+        directory.extend((name.len() as u16).to_le_bytes());
+        // no extra field, comment, disk number or attributes
+        directory.extend([0; 12]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+
+        self.offset += (header.len() + descriptor.len()) as u64 + length;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Write the central directory, finishing the file.
+    fn finish(self) -> anyhow::Result<()> {
+        let offset = u32::try_from(self.offset)
+            .context("The workbook is too large for a zip file of its size.")?;
+        self.out.write_all(&self.directory)?;
+        let mut end = Vec::new();
+        end.extend(0x0605_4b50u32.to_le_bytes());
+        end.extend([0; 4]);
+        end.extend(self.entries.to_le_bytes());
+        end.extend(self.entries.to_le_bytes());
+        end.extend((self.directory.len() as u32).to_le_bytes());
+        end.extend(offset.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// The report as a workbook of the value of every field of every line, with a sheet for each record type, whose
+/// columns are the line's number and the fields of lines of that type, and a sheet of the problems with the lines.
+pub struct XlsxReport {
+    out: Box<dyn Write>,
+    workbook: Workbook,
+}
+
+impl XlsxReport {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            out,
+            workbook: Workbook::default(),
+        }
+    }
+}
+
+impl LineReport for XlsxReport {
+    fn write_line(&mut self, line: &ReportLine) -> anyhow::Result<()> {
+        let prepared = line.prepared;
+        let record_type = prepared.record_type.as_deref();
+        let sheet = self
+            .workbook
+            .sheet(Contents::Lines(prepared.record_type.clone()));
+        let values: Vec<(usize, String)> = prepared
+            .regions
+            .iter()
+            .map(|r| {
+                (
+                    sheet.column(&r.name),
+                    region_value(&prepared.text, r, prepared.ghost_from),
+                )
+            })
+            .collect();
+        let mut cells = vec![(0, Cell::Number(line.index + 1))];
+        cells.extend(
+            values
+                .iter()
+                .map(|(column, value)| (*column, Cell::Text(value))),
+        );
+        sheet.push_row(cells)?;
+
+        for problem in line.problems {
+            write_problem(
+                self.workbook.sheet(Contents::Problems),
+                line.index,
+                record_type,
+                problem,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.workbook
+            .write_to(&mut self.out)
+            .context("Failed to write workbook.")
+    }
+}
+
+/// Add `problem`, found with the `line_index`th (from 0) line, of `record_type`, to the sheet of problems.
+fn write_problem(
+    errors: &mut Sheet,
+    line_index: usize,
+    record_type: Option<&str>,
+    problem: &LineProblem,
+) -> anyhow::Result<()> {
+    let mut cells = vec![
+        (0, Cell::Number(line_index + 1)),
+        (2, Cell::Text(&problem.kind)),
+        (3, Cell::Text(problem.severity)),
+        (6, Cell::Text(&problem.message)),
+    ];
+    if let Some(record_type) = record_type {
+        cells.push((1, Cell::Text(record_type)));
+    }
+    if let Some(field) = &problem.field {
+        cells.push((4, Cell::Text(field)));
+    }
+    if let Some(value) = &problem.value {
+        cells.push((5, Cell::Text(value)));
+    }
+    errors.push_row(cells)
+}
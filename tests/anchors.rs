@@ -0,0 +1,73 @@
+//! Rules starting after another field rather than at a column.
+
+use fixedfile_highlighter::{parse_syntax_file, Highlighter};
+
+/// The name and columns of each field of `line`.
+fn fields(syntax: &str, line: &str) -> Vec<(String, usize, usize)> {
+    let highlighter = Highlighter::from_syntax(syntax, None).expect("the syntax parses");
+    let prepared = highlighter
+        .prepare(line.to_owned(), 0)
+        .expect("the line is prepared");
+    prepared
+        .regions
+        .iter()
+        .map(|r| (r.name.to_string(), r.start, r.end))
+        .collect()
+}
+
+#[test]
+fn fields_start_after_the_field_named() {
+    let syntax = "start,length,name,occurs
+1,2,Id,
+after:Id+1,3,Amount,2
+after:Amount[2],2,End,
+";
+    assert_eq!(
+        fields(syntax, "12 aaabbbZZ"),
+        vec![
+            ("Id".to_owned(), 0, 2),
+            ("Amount[1]".to_owned(), 3, 6),
+            ("Amount[2]".to_owned(), 6, 9),
+            ("End".to_owned(), 9, 11),
+        ]
+    );
+}
+
+#[test]
+fn anchors_follow_the_record_type() {
+    let syntax = "record,condition,start,length,name
+hdr,^H,,,
+det,^D,,,
+hdr,,1,4,Code
+det,,1,2,Code
+det,,after:Code,3,Value
+";
+    assert_eq!(fields(syntax, "D1abc")[1], ("Value".to_owned(), 2, 5));
+}
+
+#[test]
+fn unresolvable_anchors_are_errors() {
+    for (syntax, message) in [
+        (
+            "start,length,name\nafter:Later,2,A\n1,2,Later\n",
+            "no rule above it",
+        ),
+        (
+            "start,length,name\n1,2,A\nafter:A-4,2,B\n",
+            "before the first column",
+        ),
+        (
+            "start,length,name\nafter:,2,A\n",
+            "isn't a valid `after:` anchor",
+        ),
+        (
+            "start,length,name,from_end\n1,2,A,true\nafter:A,2,B,\n",
+            "no end to start after",
+        ),
+    ] {
+        let Err(error) = parse_syntax_file(syntax, None) else {
+            panic!("{} parsed", syntax);
+        };
+        assert!(error.to_string().contains(message), "{}: {}", syntax, error);
+    }
+}
@@ -0,0 +1,227 @@
+//! Fields extracted into Arrow IPC files with `extract --format arrow`, read back by a reader of the format's own.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// A table of an Arrow file: the name of each column, with its values.
+type Columns = Vec<(String, Vec<Option<String>>)>;
+
+/// A flatbuffer, read just far enough to find the tables, vectors and scalars of Arrow's messages and footer.
+#[derive(Clone, Copy)]
+struct Flatbuffer<'a> {
+    bytes: &'a [u8],
+}
+
+impl Flatbuffer<'_> {
+    fn u32(&self, at: usize) -> usize {
+        u32::from_le_bytes(self.bytes[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    fn i64(&self, at: usize) -> i64 {
+        i64::from_le_bytes(self.bytes[at..at + 8].try_into().unwrap())
+    }
+
+    /// The root table.
+    fn root(&self) -> usize {
+        self.u32(0)
+    }
+
+    /// Where field `field` of the table at `table` is, if it's present.
+    fn field(&self, table: usize, field: usize) -> Option<usize> {
+        let soffset = i32::from_le_bytes(self.bytes[table..table + 4].try_into().unwrap());
+        let vtable = (table as i64 - soffset as i64) as usize;
+        let vtable_size = u16::from_le_bytes(self.bytes[vtable..vtable + 2].try_into().unwrap());
+        let entry = 4 + 2 * field;
+        if entry >= vtable_size as usize {
+            return None;
+        }
+        let offset = u16::from_le_bytes(
+            self.bytes[vtable + entry..vtable + entry + 2]
+                .try_into()
+                .unwrap(),
+        );
+        (offset != 0).then_some(table + offset as usize)
+    }
+
+    /// What the offset at `at` points to: a table, vector or string.
+    fn follow(&self, at: usize) -> usize {
+        at + self.u32(at)
+    }
+
+    /// The number of elements of the vector field `field` of `table`, and where they start.
+    fn vector(&self, table: usize, field: usize) -> (usize, usize) {
+        let vector = self.follow(self.field(table, field).expect("a vector"));
+        (self.u32(vector), vector + 4)
+    }
+
+    fn string(&self, table: usize, field: usize) -> String {
+        let (length, start) = self.vector(table, field);
+        String::from_utf8(self.bytes[start..start + length].to_vec()).unwrap()
+    }
+}
+
+/// Read the Arrow IPC file `file`: each column, with its values from every record batch, and the number of batches.
+fn read_arrow(file: &[u8]) -> (Columns, usize) {
+    assert_eq!(
+        &file[..8],
+        b"ARROW1\0\0",
+        "the file starts with the magic bytes"
+    );
+    assert_eq!(
+        &file[file.len() - 6..],
+        b"ARROW1",
+        "the file ends with the magic bytes"
+    );
+    let footer_length =
+        u32::from_le_bytes(file[file.len() - 10..file.len() - 6].try_into().unwrap()) as usize;
+    let footer = Flatbuffer {
+        bytes: &file[file.len() - 10 - footer_length..file.len() - 10],
+    };
+    let root = footer.root();
+
+    let schema = footer.follow(footer.field(root, 1).expect("a schema"));
+    let (fields, start) = footer.vector(schema, 1);
+    let mut columns: Columns = (0..fields)
+        .map(|i| {
+            let field = footer.follow(start + 4 * i);
+            (footer.string(field, 0), Vec::new())
+        })
+        .collect();
+
+    let (batches, start) = footer.vector(root, 3);
+    for batch in 0..batches {
+        let block = start + 24 * batch;
+        let offset = footer.i64(block) as usize;
+        let metadata_length = footer.u32(block + 8);
+        assert_eq!(
+            &file[offset..offset + 4],
+            &[0xFF; 4],
+            "each message starts with a continuation marker"
+        );
+        let message = Flatbuffer {
+            bytes: &file[offset + 8..offset + metadata_length],
+        };
+        let body = &file[offset + metadata_length..];
+        let root = message.root();
+        assert_eq!(
+            message.bytes[message.field(root, 1).unwrap()],
+            3,
+            "a record batch"
+        );
+        let record_batch = message.follow(message.field(root, 2).unwrap());
+        let rows = message.i64(message.field(record_batch, 0).unwrap()) as usize;
+        let (_, nodes) = message.vector(record_batch, 1);
+        let (_, buffers) = message.vector(record_batch, 2);
+        let buffer = |index: usize| {
+            let at = buffers + 16 * index;
+            let offset = message.i64(at) as usize;
+            &body[offset..offset + message.i64(at + 8) as usize]
+        };
+
+        // the line numbers, which are never null
+        assert_eq!(message.i64(nodes + 8), 0);
+        columns[0].1.extend(
+            buffer(1)
+                .chunks(8)
+                .map(|line| Some(i64::from_le_bytes(line.try_into().unwrap()).to_string())),
+        );
+        for (column, (_, values)) in columns.iter_mut().enumerate().skip(1) {
+            let validity = buffer(3 * column - 1);
+            let offsets: Vec<usize> = buffer(3 * column)
+                .chunks(4)
+                .map(|o| i32::from_le_bytes(o.try_into().unwrap()) as usize)
+                .collect();
+            let data = buffer(3 * column + 1);
+            assert_eq!(offsets.len(), rows + 1);
+            let mut nulls = 0;
+            for row in 0..rows {
+                if validity.is_empty() || validity[row / 8] & (1 << (row % 8)) != 0 {
+                    let value = &data[offsets[row]..offsets[row + 1]];
+                    values.push(Some(String::from_utf8(value.to_vec()).unwrap()));
+                } else {
+                    nulls += 1;
+                    values.push(None);
+                }
+            }
+            assert_eq!(
+                message.i64(nodes + 16 * column + 8),
+                nulls,
+                "the null count"
+            );
+        }
+    }
+    (columns, batches)
+}
+
+/// Run `extract` in `dir` on `input` with `syntax`, writing `data.arrow`.
+fn extract(dir: &Path, syntax: &str, input: &str) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["extract", "input.txt", "syntax.csv"])
+        .args(["--format", "arrow", "-o", "data.arrow"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn every_field_is_a_column_null_where_it_doesnt_apply() {
+    let dir = scratch("columns");
+    let output = extract(
+        &dir,
+        "start,length,name,condition,sensitive
+1,1,Type,,
+2,4,Date,^H,
+2,3,Id,^D,
+5,12,Card,^D,true
+",
+        "H2024\nD00141111111111111111\nD002\n",
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    let (columns, batches) = read_arrow(&fs::read(dir.join("data.arrow")).unwrap());
+    assert_eq!(batches, 1);
+    let some = |values: &[&str]| -> Vec<Option<String>> {
+        values
+            .iter()
+            .map(|v| (!v.is_empty()).then(|| v.to_string()))
+            .collect()
+    };
+    assert_eq!(
+        columns,
+        vec![
+            ("line".to_owned(), some(&["1", "2", "3"])),
+            ("Type".to_owned(), some(&["H", "D", "D"])),
+            ("Date".to_owned(), some(&["2024", "", ""])),
+            ("Id".to_owned(), some(&["", "001", "002"])),
+            // sensitive values are masked, and a field past the end of its line has no value
+            ("Card".to_owned(), some(&["", "********1111", ""])),
+        ]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn long_inputs_are_written_in_several_batches() {
+    let dir = scratch("batches");
+    let lines = 70_000;
+    let input: String = (0..lines).map(|i| format!("{:06}\n", i)).collect();
+    let output = extract(&dir, "start,length,name\n1,6,Id\n", &input);
+    assert!(output.status.success(), "{:?}", output);
+
+    let (columns, batches) = read_arrow(&fs::read(dir.join("data.arrow")).unwrap());
+    assert_eq!(batches, 2);
+    assert_eq!(columns[0].1.len(), lines);
+    assert_eq!(columns[1].1.len(), lines);
+    for row in [0, 65_535, 65_536, lines - 1] {
+        assert_eq!(columns[0].1[row], Some((row + 1).to_string()));
+        assert_eq!(columns[1].1[row], Some(format!("{:06}", row)));
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
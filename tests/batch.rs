@@ -0,0 +1,40 @@
+//! Reports on several input files at once with `--batch`, linked from an index.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn the_index_links_to_reports_whatever_their_names() {
+    let dir = scratch("names");
+    fs::write(dir.join("syntax.csv"), "start,length,name\n1,2,Id\n").unwrap();
+    let awkward = "a b#1?%.txt";
+    fs::write(dir.join(awkward), "AB\n").unwrap();
+    fs::write(dir.join("plain.txt"), "CD\n").unwrap();
+
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .args(["--batch", "reports", awkward, "plain.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let index = fs::read_to_string(dir.join("reports").join("index.html")).unwrap();
+
+    // each link is to the report's file, with the characters that mean something in a URL percent-encoded
+    for (href, report) in [
+        ("a%20b%231%3F%25.txt.html", "a b#1?%.txt.html"),
+        ("plain.txt.html", "plain.txt.html"),
+    ] {
+        assert!(
+            index.contains(&format!(r#"<a href="{}">"#, href)),
+            "{} not in {}",
+            href,
+            index
+        );
+        assert!(dir.join("reports").join(report).is_file(), "{}", report);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,125 @@
+//! Linting syntax files with `check-syntax`.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn every_invalid_regex_is_reported_with_the_layout() {
+    let dir = scratch("csv");
+    let syntax = dir.join("syntax.csv");
+    fs::write(
+        &syntax,
+        "start,length,name,condition,pattern
+1,2,A,^(,
+2,2,B,,[z
+5,2,C,,
+",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .arg("check-syntax")
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for finding in [
+        "line 2: error: Condition `^(` is not a valid regex",
+        "line 3: error: Pattern `[z` is not a valid regex",
+        "line 3: warning: rule 'B' (columns 2-3) overlaps rule 'A' (columns 1-2) on line 2.",
+        "line 4: warning: column 4 before rule 'C' isn't covered by any rule for every line.",
+        "2 error(s), 2 warning(s).",
+    ] {
+        assert!(report.contains(finding), "{} not in {}", finding, report);
+    }
+}
+
+#[test]
+fn every_invalid_regex_of_a_document_is_reported() {
+    let dir = scratch("yaml");
+    let syntax = dir.join("syntax.yaml");
+    fs::write(
+        &syntax,
+        r#"records:
+  - name: detail
+    condition: "^D("
+    fields:
+      - name: Id
+        start: 1
+        length: 4
+        pattern: "[0-9"
+      - name: Amount
+        start: 3
+        length: 4
+        value_pattern: "(?P<"
+"#,
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .args([
+            "check-syntax",
+            "--syntax-format",
+            "yaml",
+            "--format",
+            "json",
+        ])
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.starts_with(r#"{"errors":3,"warnings":1,"#),
+        "{}",
+        report
+    );
+    for finding in [
+        r#"{"severity":"error","line":3,"message":"Condition `^D(` is not a valid regex"#,
+        r#"{"severity":"error","line":8,"message":"Pattern `[0-9` is not a valid regex"#,
+        r#"{"severity":"error","line":12,"message":"Value pattern `(?P<` is not a valid regex"#,
+        r#"{"severity":"warning","line":9,"message":"rule 'Amount' (columns 3-6) overlaps rule 'Id' (columns 1-4) on line 5."}"#,
+    ] {
+        assert!(report.contains(finding), "{} not in {}", finding, report);
+    }
+}
+
+#[test]
+fn rules_with_the_same_name_are_also_checked_for_overlaps() {
+    let dir = scratch("names");
+    let syntax = dir.join("syntax.csv");
+    fs::write(
+        &syntax,
+        "start,length,name
+1,4,Id
+3,4,Id
+7,2,Id
+",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .arg("check-syntax")
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for finding in [
+        "line 3: warning: rule 'Id' has the same name as the rule on line 2, so the two can't be told apart.",
+        "line 3: warning: rule 'Id' (columns 3-6) overlaps rule 'Id' (columns 1-4) on line 2.",
+        "line 4: warning: rule 'Id' has the same name as the rule on line 2, so the two can't be told apart.",
+        "line 4: warning: rule 'Id' has the same name as the rule on line 3, so the two can't be told apart.",
+        "0 error(s), 4 warning(s).",
+    ] {
+        assert!(report.contains(finding), "{} not in {}", finding, report);
+    }
+    // the last rule is beside the others, not over them
+    assert!(!report.contains("(columns 7-8) overlaps"), "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
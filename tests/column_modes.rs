@@ -0,0 +1,86 @@
+//! Columns counted in bytes or graphemes with `--column-mode`, which reports give positions in as the syntax file does.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on a line with a character of two bytes, with `syntax`, in `mode` and with `args`.
+fn run(dir: &Path, syntax: &str, mode: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), "héllo world\n").unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--column-mode", mode])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn fields_are_placed_in_bytes() {
+    let dir = scratch("bytes");
+    let syntax = "start,length,name\n1,3,id\n4,5,amount\n";
+
+    let output = run(
+        &dir,
+        syntax,
+        "bytes",
+        &["--output-format", "json", "--error-report", "problems.json"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"id","start":1,"length":3,"value":"hé"},{"name":"amount","start":4,"length":5,"value":"llo w"}]"#,
+            "\n"
+        )
+    );
+    // the trailing data starts at the 10th character, but the 11th byte
+    let problems = fs::read_to_string(dir.join("problems.json")).unwrap();
+    assert!(
+        problems.contains(r#""value":"orld","message":"Unexpected trailing data from column 9, after the last field."}"#),
+        "{}",
+        problems
+    );
+
+    let output = run(&dir, syntax, "bytes", &["--output-format", "markdown"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for row in ["| id | 1 | 3 | `hé` |", "| amount | 4 | 5 | `llo w` |"] {
+        assert!(report.contains(row), "{} not in {}", row, report);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_are_placed_in_graphemes() {
+    let dir = scratch("graphemes");
+    // the accent is a character of its own, combined with the `e` before it
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name\n1,2,id\n3,3,rest\n",
+    )
+    .unwrap();
+    fs::write(dir.join("input.txt"), "he\u{301}llo\n").unwrap();
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .args(["--column-mode", "graphemes", "--output-format", "json"])
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            "[{\"name\":\"id\",\"start\":1,\"length\":2,\"value\":\"he\u{301}\"},",
+            r#"{"name":"rest","start":3,"length":3,"value":"llo"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
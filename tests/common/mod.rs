@@ -0,0 +1,23 @@
+//! Helpers shared by the integration tests, each of which is a crate of its own that includes this module, so not
+//! every helper is used by every test.
+#![allow(dead_code)]
+
+use std::{fs, path::PathBuf, process::Command};
+
+/// A directory of its own under the system's temporary directory for a test's files, named after the test crate and
+/// `test` so tests running at the same time don't share one.
+pub fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "ffh-{}-{}-{}",
+        env!("CARGO_CRATE_NAME"),
+        test,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).expect("the scratch directory is created");
+    dir
+}
+
+/// The highlighter's binary, ready to be given arguments.
+pub fn highlighter_command() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_fixedfile-highlighter"))
+}
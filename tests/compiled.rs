@@ -0,0 +1,174 @@
+//! Syntax files compiled with `--compile-syntax` and given in place of the files they were compiled from.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// A syntax file of a header and a detail record type, with constraints and checks on its fields.
+const SYNTAX: &str = "start,length,name,record,condition,pattern,charset,justify,pad,type,check
+,,,header,^H,,,,,,
+,,,detail,^D,,,,,,
+1,1,Type,,,,,,,,
+2,8,Date,header,,[0-9]{8},,,,,
+2,5,Id,detail,,,,,,,unique
+7,4,Amount,detail,,,0-9,right,0,zoned(2),
+";
+
+/// Run the highlighter in `dir` with `args`.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    highlighter_command()
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn compiled_syntax_files_read_as_their_source() {
+    let dir = scratch("same");
+    fs::write(dir.join("syntax.csv"), SYNTAX).unwrap();
+    fs::write(dir.join("input.txt"), "H20240101\nD000010150\nD00001x0\n").unwrap();
+
+    let compiling = run(
+        &dir,
+        &[
+            "--output-format",
+            "json",
+            "--compile-syntax",
+            "syntax.ffhc",
+            "input.txt",
+            "syntax.csv",
+        ],
+    );
+    assert_eq!(compiling.status.code(), Some(1), "{:?}", compiling);
+    assert!(fs::read(dir.join("syntax.ffhc"))
+        .unwrap()
+        .starts_with(b"FFHC"));
+
+    // the same fields and problems, found without parsing the CSV
+    let compiled = run(
+        &dir,
+        &["--output-format", "json", "input.txt", "syntax.ffhc"],
+    );
+    assert_eq!(compiled.status.code(), Some(1), "{:?}", compiled);
+    assert_eq!(compiled.stdout, compiling.stdout);
+    assert_eq!(compiled.stderr, compiling.stderr);
+    let stderr = String::from_utf8(compiled.stderr).unwrap();
+    for problem in [
+        "Line 3: Field 'Id' repeats 00001, the key of line 2.",
+        "Line 3: field 'Amount' has characters outside its charset `0-9` at column 7 ('x')",
+    ] {
+        assert!(stderr.contains(problem), "{} not in {}", problem, stderr);
+    }
+
+    for args in [
+        &["export-ruleset"][..],
+        &["--output-format", "ansi", "input.txt"],
+        &["--validate", "input.txt"],
+    ] {
+        let source = run(&dir, &[args, &["syntax.csv"]].concat());
+        let compiled = run(&dir, &[args, &["syntax.ffhc"]].concat());
+        assert_eq!(compiled.status.code(), source.status.code(), "{:?}", args);
+        assert_eq!(
+            String::from_utf8(compiled.stdout).unwrap(),
+            String::from_utf8(source.stdout).unwrap(),
+            "{:?}",
+            args
+        );
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compiled_documents_keep_how_lines_are_joined() {
+    let dir = scratch("joined");
+    fs::write(
+        dir.join("syntax.yaml"),
+        r"continues_if: ^\+
+fields:
+  - name: First
+    start: 1
+    length: 2
+  - name: Rest
+    start: 3
+    length: 3
+",
+    )
+    .unwrap();
+    fs::write(dir.join("input.txt"), "AB123\n+C\nDEF\n").unwrap();
+
+    let compiling = run(
+        &dir,
+        &[
+            "--syntax-format",
+            "yaml",
+            "--output-format",
+            "json",
+            "--compile-syntax",
+            "syntax.ffhc",
+            "input.txt",
+            "syntax.yaml",
+        ],
+    );
+    assert!(compiling.status.success(), "{:?}", compiling);
+
+    let compiled = run(
+        &dir,
+        &["--output-format", "json", "input.txt", "syntax.ffhc"],
+    );
+    assert!(compiled.status.success(), "{:?}", compiled);
+    let fields = String::from_utf8(compiled.stdout).unwrap();
+    assert!(
+        fields.contains(r#"{"name":"First","start":1,"length":2,"value":"+C"},{"name":"Rest","start":3,"length":3,"value":"DEF"}"#),
+        "{}",
+        fields
+    );
+    assert_eq!(fields, String::from_utf8(compiling.stdout).unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stale_and_truncated_compiled_files_are_refused() {
+    let dir = scratch("refused");
+    fs::write(dir.join("syntax.csv"), SYNTAX).unwrap();
+    fs::write(dir.join("input.txt"), "H20240101\n").unwrap();
+    let output = run(
+        &dir,
+        &[
+            "--output-format",
+            "json",
+            "--compile-syntax",
+            "syntax.ffhc",
+            "input.txt",
+            "syntax.csv",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let compiled = fs::read(dir.join("syntax.ffhc")).unwrap();
+
+    // the byte after the magic number is the version of the layout
+    let mut stale = compiled.clone();
+    stale[4] = 1;
+    fs::write(dir.join("stale.ffhc"), stale).unwrap();
+    fs::write(dir.join("truncated.ffhc"), &compiled[..compiled.len() / 2]).unwrap();
+
+    for (file, error) in [
+        (
+            "stale.ffhc",
+            "Compiled syntax file is version 1, but this version of fixedfile-highlighter reads version",
+        ),
+        ("stale.ffhc", "Compile it again with --compile-syntax."),
+        ("truncated.ffhc", "Compiled syntax file is truncated."),
+    ] {
+        let output = run(&dir, &["export-ruleset", file]);
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{} not in {}", error, stderr);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,109 @@
+//! Input compressed with gzip or Zstandard, decompressed as it's read by the `gzip` or `zstd` program.
+
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+use common::{highlighter_command, scratch};
+
+const INPUT: &str = "abcde\nfghij\n";
+
+/// The report of the two lines of [`INPUT`] as JSON.
+const REPORT: &str = concat!(
+    r#"[{"name":"id","start":1,"length":3,"value":"abc"},{"name":"kind","start":4,"length":2,"value":"de"}]"#,
+    "\n",
+    r#"[{"name":"id","start":1,"length":3,"value":"fgh"},{"name":"kind","start":4,"length":2,"value":"ij"}]"#,
+    "\n",
+);
+
+/// Compress [`INPUT`] with `program` into `path` in `dir`.
+fn compress(dir: &Path, program: &str, path: &str) {
+    fs::write(dir.join("input.txt"), INPUT).unwrap();
+    let output = Command::new(program)
+        .current_dir(dir)
+        .args(["-c", "input.txt"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    fs::write(dir.join(path), output.stdout).unwrap();
+}
+
+/// Run the highlighter in `dir` on `input`, writing JSON, with `args`.
+fn run(dir: &Path, input: &str, args: &[&str]) -> Output {
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name\n1,3,id\n4,2,kind\n",
+    )
+    .unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json"])
+        .args(args)
+        .args([input, "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn files_are_decompressed_by_their_extension() {
+    let dir = scratch("extension");
+    compress(&dir, "gzip", "input.txt.gz");
+    compress(&dir, "zstd", "input.txt.zst");
+    for input in ["input.txt.gz", "input.txt.zst"] {
+        let output = run(&dir, input, &[]);
+        assert!(output.status.success(), "{}: {:?}", input, output);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), REPORT);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn files_and_standard_input_are_decompressed_by_their_first_bytes() {
+    let dir = scratch("magic");
+    compress(&dir, "gzip", "gzipped");
+    compress(&dir, "zstd", "zstandard");
+    for input in ["gzipped", "zstandard"] {
+        let output = run(&dir, input, &[]);
+        assert!(output.status.success(), "{}: {:?}", input, output);
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), REPORT);
+    }
+
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .args(["--output-format", "json", "-", "syntax.csv"])
+        .stdin(Stdio::from(fs::File::open(dir.join("gzipped")).unwrap()))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), REPORT);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compression_overrides_detection() {
+    let dir = scratch("override");
+    compress(&dir, "gzip", "input.txt.gz");
+
+    // read as it is, the compressed bytes aren't UTF-8
+    let output = run(&dir, "input.txt.gz", &["--compression", "none"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Line 1 isn't valid UTF-8"), "{}", stderr);
+
+    let output = run(&dir, "input.txt", &["--compression", "gzip"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("`gzip` failed to decompress the input"),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,89 @@
+//! Records spread over several lines, joined back together by a `continues_if` row of the syntax file.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// A record continued on a second line when its 5th column is `+`, with a field on each line.
+const SYNTAX: &str = "start,length,name,continues_if\n,,,^.{4}\\+\n1,4,Id,\n6,3,Name,\n";
+
+/// Three records, the first and last on two lines each.
+const INPUT: &str = "AAAA+\nBBB\nCCCC xyz\nDDDD+\nEEE\n";
+
+/// Run the highlighter in `dir` on [`INPUT`] with [`SYNTAX`] and `args`.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), SYNTAX).unwrap();
+    fs::write(dir.join("input.txt"), INPUT).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn rules_address_columns_of_the_whole_record() {
+    let dir = scratch("columns");
+    let output = run(&dir, &["--output-format", "json", "--line-numbers"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":1,"fields":[{"name":"Id","start":1,"length":4,"value":"AAAA"},{"name":"Name","start":6,"length":3,"value":"BBB"}]}"#,
+            "\n",
+            r#"{"line":3,"fields":[{"name":"Id","start":1,"length":4,"value":"CCCC"},{"name":"Name","start":6,"length":3,"value":"xyz"}]}"#,
+            "\n",
+            r#"{"line":4,"fields":[{"name":"Id","start":1,"length":4,"value":"DDDD"},{"name":"Name","start":6,"length":3,"value":"EEE"}]}"#,
+            "\n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn records_are_selected_by_their_first_line() {
+    let dir = scratch("selected");
+    let output = run(
+        &dir,
+        &[
+            "--output-format",
+            "json",
+            "--line-numbers",
+            "--lines",
+            "4-4",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":4,"fields":[{"name":"Id","start":1,"length":4,"value":"DDDD"},{"name":"Name","start":6,"length":3,"value":"EEE"}]}"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn continuation_lines_are_rows_under_the_first() {
+    let dir = scratch("rows");
+    let output = run(&dir, &[]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for row in [
+        r#"<span id="L1" class="ffh-muted">L&nbsp;&nbsp;1&nbsp;&gt;&nbsp;</span><abbr id="L1-id" title="Id" class="ffh-c-fff">AAAA</abbr>+"#,
+        r#"<span class="ffh-muted" title="Continues line 1">L&nbsp;&nbsp;2&nbsp;+&nbsp;</span><abbr id="L1-name" title="Name" class="ffh-c-ccc">BBB</abbr>"#,
+        r#"<span id="L3" class="ffh-muted">L&nbsp;&nbsp;3&nbsp;&gt;&nbsp;</span>"#,
+        r#"<span class="ffh-muted" title="Continues line 4">L&nbsp;&nbsp;5&nbsp;+&nbsp;</span><abbr id="L4-name" title="Name" class="ffh-c-ccc">EEE</abbr>"#,
+    ] {
+        assert!(report.contains(row), "{} not in {}", row, report);
+    }
+    assert!(!report.contains(r#"id="L2""#), "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
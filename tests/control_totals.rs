@@ -0,0 +1,118 @@
+//! Control totals of trailer records, given by the `check` column, compared with the records before them.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Detail records, some flagged, and trailers counting and totalling them.
+const SYNTAX: &str = "start,length,name,condition,check
+1,1,Type,,
+2,4,Id,^D,
+6,5,Amount,^D,
+11,1,Flag,^D.{9}Y,
+2,4,RecordCount,^T,count
+6,7,Total,^T,sum:Amount
+13,2,Flagged,^T,count:Flag
+";
+
+/// Run the highlighter in `dir` on `input`, writing the problems it finds to `problems.json`, with `args`.
+fn run(dir: &Path, input: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), SYNTAX).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--error-report", "problems.json"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn each_batch_is_checked_by_its_own_trailer() {
+    let dir = scratch("batches");
+    let output = run(
+        &dir,
+        "D000100150\nD000200250Y\nT00020000400 1\nD000300001\nT00010000001 0\n",
+        &["--output-format", "json"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let problems = fs::read_to_string(dir.join("problems.json")).unwrap();
+    assert!(problems.contains(r#""errors": 0"#), "{}", problems);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn totals_that_dont_match_are_problems() {
+    let dir = scratch("mismatched");
+    let output = run(
+        &dir,
+        "D000100150\nD000200250Y\nT00030000401 0\nD000300001\nD000400002\nT00010000003 0\n",
+        &["--output-format", "ansi"],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+
+    let problems = fs::read_to_string(dir.join("problems.json")).unwrap();
+    for problem in [
+        r#"{"line":3,"kind":"control_total","severity":"error","field":"RecordCount","value":"0003","message":"Field 'RecordCount' gives 0003 as its `count`, but the records before it come to 2."}"#,
+        r#"{"line":3,"kind":"control_total","severity":"error","field":"Total","value":"0000401","message":"Field 'Total' gives 0000401 as its `sum:Amount`, but the records before it come to 400."}"#,
+        r#"{"line":3,"kind":"control_total","severity":"error","field":"Flagged","value":" 0","message":"Field 'Flagged' gives 0 as its `count:Flag`, but the records before it come to 1."}"#,
+        // the second batch is counted from the first trailer, and its total of 3 is right
+        r#"{"line":6,"kind":"control_total","severity":"error","field":"RecordCount","value":"0001","message":"Field 'RecordCount' gives 0001 as its `count`, but the records before it come to 2."}"#,
+        r#""errors": 4"#,
+    ] {
+        assert!(
+            problems.contains(problem),
+            "{} not in {}",
+            problem,
+            problems
+        );
+    }
+
+    // each is noted under its line in the report
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains(
+            "Field 'Total' gives 0000401 as its `sum:Amount`, but the records before it come to 400"
+        ),
+        "{}",
+        report
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn values_that_arent_numbers_fail_a_sum() {
+    let dir = scratch("numbers");
+    let output = run(
+        &dir,
+        "D000100150\nD0002002X0\nT00020000400 0\n",
+        &["--output-format", "json"],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let problems = fs::read_to_string(dir.join("problems.json")).unwrap();
+    assert!(
+        problems.contains("Field 'Total' gives 0000400 as its `sum:Amount`, but the records before it come to 150, leaving out 1 value(s) of 'Amount' that aren't numbers."),
+        "{}",
+        problems
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn skipped_lines_arent_counted() {
+    let dir = scratch("skipped");
+    let output = run(
+        &dir,
+        "D000100150\n# a comment\nD000200250Y\nT00020000400 1\n",
+        &["--output-format", "json", "--skip-lines-matching", "^#"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,227 @@
+//! COBOL copybooks read as syntax files with `--syntax-format copybook`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// A customer record in fixed format, with sequence numbers, a comment and a condition name.
+const CUSTOMER: &str = "000100* CUSTOMER RECORD
+000200 01  CUSTOMER-REC.
+000300     05  CUST-ID          PIC 9(6).
+000400     05  CUST-NAME        PIC X(10).
+000500     05  BALANCE          PIC S9(5)V99 COMP-3.
+000600     05  STATUS-CODE      PIC X.
+000700         88  ACTIVE       VALUE 'A'.
+000800     05  PHONES OCCURS 2 TIMES.
+000900         10  PHONE        PIC X(4).
+001000     05  CREATED          PIC 9(8).
+001100     05  CREATED-PARTS REDEFINES CREATED.
+001200         10  CREATED-YEAR PIC 9(4).
+001300         10  CREATED-MD   PIC 9(4).
+001400     05  COUNTER          PIC S9(4) COMP.
+001500     05  FILLER           PIC X(2).
+";
+
+/// An order record in free format, with a variable number of lines, each with a separate sign and parts of its own.
+const ORDER: &str = "      * free format, but with a comment
+ 01 ORDER-REC.
+   05 ORDER-NO PIC X(4).
+   05 LINE-COUNT PIC 99.
+   05 ORDER-LINE OCCURS 1 TO 3 TIMES DEPENDING ON LINE-COUNT.
+     10 ITEM PIC X(3).
+     10 QTY PIC S9(3) SIGN LEADING SEPARATE.
+     10 PARTS OCCURS 2.
+        15 PART PIC X.
+   05 AMOUNT PIC 9(3)V99.
+";
+
+/// Run the highlighter in `dir` with `args`, reading `copybook` as the syntax file `record.cpy`.
+fn run(dir: &Path, copybook: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("record.cpy"), copybook).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(args)
+        .args(["--syntax-format", "copybook"])
+        .arg("record.cpy")
+        .output()
+        .unwrap()
+}
+
+/// The rules `export-ruleset` derives from `copybook`, one a line.
+fn ruleset(dir: &Path, copybook: &str) -> String {
+    let output = run(dir, copybook, &["export-ruleset"]);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The exported rule named `name`, from its 0-based start.
+fn rule<'a>(ruleset: &'a str, name: &str) -> &'a str {
+    let prefix = format!(r#"{{"name":"{}","#, name);
+    let line = ruleset
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with(&prefix))
+        .unwrap_or_else(|| panic!("no rule '{}' in {}", name, ruleset));
+    &line[prefix.len()..]
+}
+
+#[test]
+fn each_elementary_item_is_a_rule() {
+    let dir = scratch("fixed");
+    let ruleset = ruleset(&dir, CUSTOMER);
+
+    for (name, position, field_type) in [
+        ("CUST-ID", r#""start":0,"length":6,"#, r#""type":"zoned","#),
+        ("CUST-NAME", r#""start":6,"length":10,"#, r#""type":null,"#),
+        // seven digits packed two to a byte, with the sign in the last half
+        (
+            "BALANCE",
+            r#""start":16,"length":4,"#,
+            r#""type":"packed(2)","#,
+        ),
+        (
+            "STATUS-CODE",
+            r#""start":20,"length":1,"#,
+            r#""type":null,"#,
+        ),
+        ("PHONE(1)", r#""start":21,"length":4,"#, r#""type":null,"#),
+        ("PHONE(2)", r#""start":25,"length":4,"#, r#""type":null,"#),
+        ("CREATED", r#""start":29,"length":8,"#, r#""type":"zoned","#),
+        // the redefining items overlap the item they redefine
+        (
+            "CREATED-YEAR",
+            r#""start":29,"length":4,"#,
+            r#""type":"zoned","#,
+        ),
+        (
+            "CREATED-MD",
+            r#""start":33,"length":4,"#,
+            r#""type":"zoned","#,
+        ),
+        // four binary digits fit in a halfword
+        ("COUNTER", r#""start":37,"length":2,"#, r#""type":null,"#),
+        ("FILLER", r#""start":39,"length":2,"#, r#""type":null,"#),
+    ] {
+        let rule = rule(&ruleset, name);
+        assert!(rule.starts_with(position), "{}: {}", name, rule);
+        assert!(rule.contains(field_type), "{}: {}", name, rule);
+    }
+    // groups and condition names aren't fields
+    for name in ["CUSTOMER-REC", "PHONES", "CREATED-PARTS", "ACTIVE"] {
+        assert!(
+            !ruleset.contains(&format!(r#""name":"{}""#, name)),
+            "{} in {}",
+            name,
+            ruleset
+        );
+    }
+    // each rule is traced back to the line of the copybook declaring it
+    assert!(
+        rule(&ruleset, "PHONE(2)").contains(r#""syntax_line":9}"#),
+        "{}",
+        ruleset
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn variable_and_nested_occurrences_take_up_the_most_they_can() {
+    let dir = scratch("occurs");
+    let ruleset = ruleset(&dir, ORDER);
+
+    for (name, position) in [
+        ("ORDER-NO", r#""start":0,"length":4,"#),
+        ("LINE-COUNT", r#""start":4,"length":2,"#),
+        ("ITEM(1)", r#""start":6,"length":3,"#),
+        // the separate sign takes a column of its own
+        ("QTY(1)", r#""start":9,"length":4,"#),
+        ("PART(1,1)", r#""start":13,"length":1,"#),
+        ("PART(1,2)", r#""start":14,"length":1,"#),
+        ("ITEM(2)", r#""start":15,"length":3,"#),
+        ("PART(3,2)", r#""start":32,"length":1,"#),
+        ("AMOUNT", r#""start":33,"length":5,"#),
+    ] {
+        let rule = rule(&ruleset, name);
+        assert!(rule.starts_with(position), "{}: {}", name, rule);
+    }
+    assert!(rule(&ruleset, "AMOUNT").contains(r#""type":"zoned(2)","#));
+    assert!(!ruleset.contains(r#""name":"ITEM(4)""#), "{}", ruleset);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lines_are_read_with_the_derived_rules() {
+    let dir = scratch("read");
+    fs::write(
+        dir.join("input.dat"),
+        "A00102ABC+001XYDEF-002ZZ         12345\n",
+    )
+    .unwrap();
+
+    let output = run(&dir, ORDER, &["--output-format", "json", "input.dat"]);
+    assert!(output.status.success(), "{:?}", output);
+    let fields = String::from_utf8(output.stdout).unwrap();
+    for field in [
+        r#"{"name":"ORDER-NO","start":1,"length":4,"value":"A001"}"#,
+        r#"{"name":"QTY(1)","start":10,"length":4,"value":"+001"}"#,
+        r#"{"name":"PART(1,2)","start":15,"length":1,"value":"Y"}"#,
+        r#"{"name":"QTY(2)","start":19,"length":4,"value":"-002"}"#,
+        r#"{"name":"ITEM(3)","start":25,"length":3,"value":"   "}"#,
+        r#"{"name":"AMOUNT","start":34,"length":5,"value":"12345"}"#,
+    ] {
+        assert!(fields.contains(field), "{} not in {}", field, fields);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mistakes_are_reported_with_their_line() {
+    let dir = scratch("mistakes");
+
+    for (copybook, error) in [
+        (
+            " 01 R.\n   05 A PIC X(2)Q.\n",
+            "Copybook line 2: picture `X(2)Q` of 'A' has `Q`, which isn't a picture symbol.",
+        ),
+        (
+            " 01 R.\n   05 A PIC 9(X).\n",
+            "Copybook line 2: picture `9(X)` of 'A' has a repeat count that isn't a number.",
+        ),
+        (
+            " 01 R.\n   05 A PIC X USAGE FOO.\n",
+            "Copybook line 2: usage `FOO` of 'A' isn't supported.",
+        ),
+        (
+            " 05 A PIC X.\n",
+            "Copybook line 1: 'A' is at level 5, but isn't inside an 01 level record.",
+        ),
+        (
+            " 01 R.\n   05 A PIC X.\n   05 B REDEFINES NOPE PIC X.\n",
+            "Copybook line 3: 'B' redefines 'NOPE', which isn't an earlier item of the same group.",
+        ),
+        (
+            " 01 R.\n   05 A PIC X.\n     10 B PIC X.\n",
+            "Copybook line 3: 'B' is nested under 'A', which has a picture so can't be a group.",
+        ),
+        (
+            " 01 R.\n   05 A.\n",
+            "Copybook line 2: 'A' has no picture and nothing nested under it, so its size isn't known.",
+        ),
+        (
+            "      * nothing but a comment\n",
+            "The copybook doesn't describe any data items.",
+        ),
+    ] {
+        let output = run(&dir, copybook, &["export-ruleset"]);
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{} not in {}", error, stderr);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,41 @@
+//! Delimiters shown as a glyph of their own with `--delimiter-glyph`.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn every_report_shows_the_glyph_in_place_of_the_delimiter() {
+    let dir = scratch("reports");
+    fs::write(dir.join("syntax.csv"), "field,name\n1,A\n2,B\n").unwrap();
+    fs::write(dir.join("input.txt"), "ab|~|cd\n").unwrap();
+
+    for (format, shown) in [
+        ("ansi", "ab\x1b[0;1;90m│\x1b["),
+        (
+            "html",
+            r#">ab</abbr><span class="ffh-delimiter" title="Delimiter">│</span>"#,
+        ),
+    ] {
+        let output = highlighter_command()
+            .current_dir(&dir)
+            .args(["--delimiter", "|~|", "--delimiter-glyph", "│"])
+            .args(["--output-format", format, "input.txt", "syntax.csv"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let report = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            report.contains(shown),
+            "{}: {} not in {}",
+            format,
+            shown,
+            report
+        );
+        assert!(!report.contains('~'), "{}: {}", format, report);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,81 @@
+//! Delimiters of several characters, and delimiters matched by a regex with `--delimiter-regex`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` with three fields, delimited by `delimiter`, with `args`.
+fn run(dir: &Path, input: &str, delimiter: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), "field,name\n1,a\n2,b\n3,c\n").unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--delimiter", delimiter])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn delimiters_of_several_characters_are_taken_literally() {
+    let dir = scratch("literal");
+    let output = run(&dir, "x|~|yy|~|z\n", "|~|", &["--output-format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"a","start":1,"length":1,"value":"x"},{"name":"b","start":5,"length":2,"value":"yy"},{"name":"c","start":10,"length":1,"value":"z"}]"#,
+            "\n"
+        )
+    );
+
+    let output = run(&dir, "x|~|yy|~|z\n", "|~|", &[]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains(r#"<abbr id="L1-a" title="a" class="ffh-c-fff">x</abbr><span class="ffh-delimiter" title="Delimiter">|~|</span><abbr id="L1-b""#),
+        "{}",
+        report
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn regex_delimiters_match_spans_of_any_length() {
+    let dir = scratch("regex");
+    let output = run(
+        &dir,
+        "x   yy\tz\n",
+        r"\s+",
+        &["--delimiter-regex", "--output-format", "json"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"a","start":1,"length":1,"value":"x"},{"name":"b","start":5,"length":2,"value":"yy"},{"name":"c","start":8,"length":1,"value":"z"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn invalid_regex_delimiters_are_fatal() {
+    let dir = scratch("invalid");
+    let output = run(&dir, "x\n", "(", &["--delimiter-regex"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Delimiter `(` is not a valid regex"),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,67 @@
+//! Diagnostics written on stderr for automation to read, or left out.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn json_log_format_and_quiet() {
+    let dir = scratch("json");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(
+        &syntax,
+        "start,length,name,pattern\n1,1,type,\n2,3,amount,[0-9]+\n",
+    )
+    .unwrap();
+    fs::write(&input, "D001\nD0x2\nD003X\n").unwrap();
+    let run = |options: &[&str]| {
+        highlighter_command()
+            .env_remove("LOG")
+            .args(options)
+            .args(["--output-format", "csv"])
+            .arg(&input)
+            .arg(&syntax)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--log-format", "json"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // every line is an object, down to the error the run fails with
+    assert!(
+        stderr.lines().all(|line| line.starts_with("{\"event\":")),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(r#""event":"log","level":"error""#),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            r#""lines":3,"errors":1,"warnings":1,"kinds":{"constraint":1,"trailing_data":1}}"#
+        ),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            r#"{"event":"error","message":"Found 1 error(s) and 1 warning(s) in the input."}"#
+        ),
+        "{}",
+        stderr
+    );
+
+    let output = run(&["--quiet"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: Found 1 error(s) and 1 warning(s) in the input.\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
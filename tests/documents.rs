@@ -0,0 +1,355 @@
+//! Syntax files written as YAML or TOML documents.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// The record types of the README in YAML.
+const YAML: &str = r#"records:
+  - name: header
+    condition: ^H
+    fields:
+      - name: Type
+        start: 1
+        length: 1
+      - name: Date
+        start: 2
+        length: 8
+        pattern: "[0-9]{8}"
+  - name: detail
+    condition: ^D
+    fields:
+      - name: Type
+        start: 1
+        length: 1
+      - name: Id
+        start: 2
+        length: 5
+      - name: Amount
+        start: 7
+        length: 4
+"#;
+
+/// The same record types in TOML, with inline tables and arrays of tables.
+const TOML: &str = r#"[[records]]
+name = "header"
+condition = "^H"
+fields = [
+  { name = "Type", start = 1, length = 1 },
+  { name = "Date", start = 2, length = 8, pattern = "[0-9]{8}" },
+]
+
+[[records]]
+name = "detail"
+condition = "^D"
+
+[[records.fields]]
+name = "Type"
+start = 1
+length = 1
+
+[[records.fields]]
+name = "Id"
+start = 2
+length = 5
+
+[[records.fields]]
+name = "Amount"
+start = 7
+length = 4
+"#;
+
+/// The same record types as CSV rules.
+const CSV: &str = "start,length,name,record,condition,pattern
+,,,header,^H,
+,,,detail,^D,
+1,1,Type,header,,
+2,8,Date,header,,[0-9]{8}
+1,1,Type,detail,,
+2,5,Id,detail,,
+7,4,Amount,detail,,
+";
+
+/// Run the highlighter in `dir` with `args`, reading `document` as the syntax file `syntax.<format>`.
+fn run(dir: &Path, format: &str, document: &str, args: &[&str]) -> Output {
+    let syntax = format!("syntax.{}", format);
+    fs::write(dir.join(&syntax), document).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(args)
+        .args(["--syntax-format", format])
+        .arg(syntax)
+        .output()
+        .unwrap()
+}
+
+/// The rules `export-ruleset` reads from `document`, less the syntax file line each came from.
+fn ruleset(dir: &Path, format: &str, document: &str) -> String {
+    let output = run(dir, format, document, &["export-ruleset"]);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| match line.find(r#""syntax_line":"#) {
+            Some(at) => &line[..at],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Check `document` fails to load with `error`.
+fn assert_fails(dir: &Path, format: &str, document: &str, error: &str) {
+    let output = run(dir, format, document, &["export-ruleset"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(error), "{} not in {}", error, stderr);
+}
+
+#[test]
+fn yaml_and_toml_read_as_the_same_rules_as_csv() {
+    let dir = scratch("same");
+    let csv = ruleset(&dir, "csv", CSV);
+    assert!(
+        csv.contains(r#"{"name":"Date","start":1,"length":8,"end":9,"#),
+        "{}",
+        csv
+    );
+    assert_eq!(ruleset(&dir, "yaml", YAML), csv);
+    assert_eq!(ruleset(&dir, "toml", TOML), csv);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn yaml_comments_quotes_and_markers_are_read() {
+    let dir = scratch("yaml");
+    let ruleset = ruleset(
+        &dir,
+        "yaml",
+        r##"---
+# a comment on a line of its own
+fields:
+- name: Type   # a list can sit at its key's indentation
+  start: 1
+  length: 1
+- name: 'It''s'
+  start: 2
+  length: 3
+  pattern: "[A-Z]{3}"
+- name: "Tab\tbed"
+  start: 5
+  length: 2
+  condition: "#"
+  occurs: 2
+...
+"##,
+    );
+    for rule in [
+        r#"{"name":"Type","start":0,"length":1,"#,
+        r#"{"name":"It's","start":1,"length":3,"#,
+        r#""pattern":"[A-Z]{3}""#,
+        r#"{"name":"Tab\tbed[1]","start":4,"length":2,"#,
+        r#"{"name":"Tab\tbed[2]","start":6,"length":2,"#,
+        r##""condition":"#""##,
+    ] {
+        assert!(ruleset.contains(rule), "{} not in {}", rule, ruleset);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn toml_comments_and_strings_are_read() {
+    let dir = scratch("toml");
+    let ruleset = ruleset(
+        &dir,
+        "toml",
+        r#"# a comment on a line of its own
+[[fields]]
+name = "Tab\tbed"  # a basic string, with escapes
+start = 1
+length = 2
+
+[[fields]]
+name = 'C:\path'
+start = 3
+length = 1
+pattern = """
+[a-z]"""
+"#,
+    );
+    for rule in [
+        r#"{"name":"Tab\tbed","start":0,"length":2,"#,
+        r#"{"name":"C:\\path","start":2,"length":1,"#,
+        r#""pattern":"[a-z]""#,
+    ] {
+        assert!(ruleset.contains(rule), "{} not in {}", rule, ruleset);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn documents_join_lines_and_read_delimiter_mode() {
+    let dir = scratch("read");
+    fs::write(dir.join("input.txt"), "AB123\n+C\nDEF\n").unwrap();
+    let output = run(
+        &dir,
+        "yaml",
+        r"continues_if: ^\+
+fields:
+  - name: First
+    start: 1
+    length: 2
+  - name: Rest
+    start: 3
+    length: 3
+",
+        &["--output-format", "json", "input.txt"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r#"[{"name":"First","start":1,"length":2,"value":"AB"},{"name":"Rest","start":3,"length":3,"value":"123"}]
+[{"name":"First","start":1,"length":2,"value":"+C"},{"name":"Rest","start":3,"length":3,"value":"DEF"}]
+"#
+    );
+
+    fs::write(dir.join("input.txt"), "1|20\n2|x\n").unwrap();
+    let output = run(
+        &dir,
+        "toml",
+        r#"fields = [
+  { name = "Id", field = 1 },
+  { name = "Amount", field = 2, pattern = "^[0-9]+$" },
+]
+"#,
+        &["--delimiter", "|", "--validate", "input.txt"],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "line,field,value,expected\n2,Amount,x,pattern `^[0-9]+$`\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn yaml_mistakes_are_reported_with_their_line() {
+    let dir = scratch("yaml-mistakes");
+    for (document, error) in [
+        (
+            "fields:\n\t- name: A\n",
+            "Syntax file line 2: indent with spaces, not tabs.",
+        ),
+        (
+            "fields:\n  - name: A\n    name: B\n",
+            "Syntax file line 3: `name` is given twice.",
+        ),
+        (
+            "fields:\n  - name: A\n      start: 1\n",
+            "Syntax file line 3: unexpected indentation.",
+        ),
+        (
+            "fields:\n  - name: \"A\n",
+            "Syntax file line 2: unterminated string.",
+        ),
+        (
+            "fields:\n  - name: A\n    start: one\n    length: 1\n",
+            "Syntax file line 3: `start` should be a whole number.",
+        ),
+        (
+            "fields:\n  - name: A\n    start: 1\n    length: 1\n    colour: red\n",
+            "Syntax file line 5: `colour` isn't a key of a field here.",
+        ),
+        (
+            "fields: A\n",
+            "Syntax file line 1: `fields` should be a list, not a string.",
+        ),
+        (
+            "records:\n  - condition: ^H\n    fields:\n      - name: A\n        start: 1\n        length: 1\n",
+            "Syntax file line 2: record type has no `name`.",
+        ),
+        (
+            "records:\n  - name: h\n    fields:\n      - name: A\n        start: 1\n        length: 1\n  - name: h\n",
+            "Syntax file line 7: record type 'h' is already defined on line 2.",
+        ),
+        (
+            "encoding: latin1\n",
+            "Syntax file line 1: `encoding` isn't a key of a syntax document. Use `fields`, `records` and `continues_if`.",
+        ),
+        (
+            "fields:\n  - name: A\n    start: 1\n    length: 1\n    pattern: [A-Z]\n",
+            "Syntax file line 5: flow sequences such as `[A-Z]` aren't supported in syntax documents.",
+        ),
+    ] {
+        assert_fails(&dir, "yaml", document, error);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn toml_mistakes_are_reported_with_their_line() {
+    let dir = scratch("toml-mistakes");
+    for (document, error) in [
+        ("a = 1\na = 2\n", "Syntax file line 2: `a` is given twice."),
+        (
+            "[[fields]]\nname = \"A\n",
+            "Syntax file line 2: unterminated string.",
+        ),
+        (
+            "[[fields]]\nname = '''A\nB\n",
+            "Syntax file line 2: unterminated string.",
+        ),
+        (
+            "[[fields]]\nname = \"A\" x\n",
+            "Syntax file line 2: expected the end of the line, found `x`.",
+        ),
+        (
+            "fields = 3\n",
+            "Syntax file line 1: `fields` should be a list, not a number.",
+        ),
+    ] {
+        assert_fails(&dir, "toml", document, error);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn flow_mappings_say_to_write_blocks_instead() {
+    let dir = scratch("flow");
+    let syntax = dir.join("syntax.yaml");
+    let input = dir.join("input.txt");
+    fs::write(
+        &syntax,
+        "fields:
+  - {name: Type, start: 1, length: 1}
+",
+    )
+    .unwrap();
+    fs::write(&input, "H\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--syntax-format", "yaml"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Syntax file line 2: flow mappings such as `{name: Type, start: 1, length: 1}` aren't supported in syntax documents. Write each `key: value` on a line of its own"
+        ),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,86 @@
+//! Input from mainframes in the EBCDIC code pages 37 and 1047, with numbers in zoned and packed decimal.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` in `encoding`, with `syntax` and `args`.
+fn run(dir: &Path, encoding: &str, syntax: &str, input: &[u8], args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.dat"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--encoding", encoding])
+        .args(args)
+        .args(["input.dat", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn code_page_1047_has_its_own_brackets_and_line_feed() {
+    let dir = scratch("cp1047");
+    let syntax = "start,length,name\n1,4,Text\n";
+    // `[`, `A`, `^` and `]`, then NL and LF, each inside a record of its own rather than ending it
+    let input = b"\xad\xc1\x5f\xbd\xc2\x15\x25\xc3";
+
+    for (encoding, values) in [
+        ("cp1047", ["[A^]", "B\\n\u{85}C"]),
+        ("cp037", ["\u{dd}A\u{ac}\u{a8}", "B\u{85}\\nC"]),
+    ] {
+        let output = run(
+            &dir,
+            encoding,
+            syntax,
+            input,
+            &["--record-length", "4", "--output-format", "json"],
+        );
+        assert!(output.status.success(), "{:?}", output);
+        let fields = String::from_utf8(output.stdout).unwrap();
+        let expected: String = values
+            .iter()
+            .map(|value| {
+                format!(
+                    "[{{\"name\":\"Text\",\"start\":1,\"length\":4,\"value\":\"{}\"}}]\n",
+                    value
+                )
+            })
+            .collect();
+        assert_eq!(fields, expected, "{}", encoding);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn zoned_and_packed_numbers_are_decoded() {
+    let dir = scratch("numbers");
+    let syntax = "start,length,name,type\n1,4,Amount,zoned(2)\n5,3,Total,packed\n";
+    // +0150 with its sign overpunched on the last digit, and -12345 packed into three bytes, on lines ending in NL
+    // and then LF
+    let input = b"\xf0\xf1\xf5\xc0\x12\x34\x5d\x15\xf0\xf0\xf0\xd1\x00\x00\x1c\x25";
+
+    for encoding in ["cp037", "cp1047"] {
+        let output = run(&dir, encoding, syntax, input, &[]);
+        assert!(output.status.success(), "{:?}", output);
+        let report = String::from_utf8(output.stdout).unwrap();
+        for title in [
+            r#"title="Amount: 015{ → 1.50""#,
+            r#"title="Total: -12345""#,
+            r#"title="Amount: 000J → -0.01""#,
+            r#"title="Total: 1""#,
+        ] {
+            assert!(
+                report.contains(title),
+                "{}: {} not in {}",
+                encoding,
+                title,
+                report
+            );
+        }
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
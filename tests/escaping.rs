@@ -0,0 +1,207 @@
+//! Adversarial input, which must come out of the HTML report as text and never as markup.
+
+mod common;
+
+use std::fs;
+
+use fixedfile_highlighter::{Delimiter, FieldBoundaries, Highlighter, Renderer, ShortLinePolicy};
+
+use common::{highlighter_command, scratch};
+
+/// Text which breaks out of content, double-quoted and single-quoted attributes if it isn't escaped.
+const NASTY: &str = r#"<script>alert('x')</script>&"'"#;
+
+fn render(syntax: &str, delimiter: Option<Delimiter>, line: &str, renderer: &Renderer) -> String {
+    let highlighter = Highlighter::from_syntax(syntax, delimiter)
+        .expect("the syntax parses")
+        .with_short_lines(ShortLinePolicy::Pad);
+    let prepared = highlighter
+        .prepare(line.to_owned(), 0)
+        .expect("the line is prepared");
+    renderer.html_line(0, &prepared)
+}
+
+/// Check nothing from `NASTY` got through as markup, and every field opened was closed.
+fn assert_inert(html: &str) {
+    assert!(!html.contains("<script>alert"), "unescaped tag in {}", html);
+    assert!(!html.contains("</script>&"), "unescaped tag in {}", html);
+    assert!(!html.contains("'x'"), "unescaped quotes in {}", html);
+    assert!(!html.contains("&\""), "unescaped ampersand in {}", html);
+    assert_eq!(
+        html.matches("<abbr").count(),
+        html.matches("</abbr>").count(),
+        "unbalanced fields in {}",
+        html
+    );
+}
+
+#[test]
+fn line_content_is_escaped() {
+    let html = render(
+        "start,length,name\n1,40,all\n",
+        None,
+        NASTY,
+        &Renderer::default(),
+    );
+    assert_inert(&html);
+    assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;&amp;&quot;&#39;"));
+}
+
+#[test]
+fn field_names_are_escaped_in_titles() {
+    let syntax = "start,length,name\n1,3,\"x\"\" onmouseover=\"\"alert('x')\"\"\"\n";
+    let html = render(syntax, None, "abcdef", &Renderer::default());
+    assert_inert(&html);
+    assert!(!html.contains("\" onmouseover"));
+    assert!(html.contains(r#"title="x&quot; onmouseover=&quot;alert(&#39;x&#39;)&quot;""#));
+}
+
+#[test]
+fn field_names_are_escaped_in_labels() {
+    let syntax = "start,length,name\n1,40,<script>alert('x')</script>\n";
+    let renderer = Renderer {
+        labels: true,
+        ..Renderer::default()
+    };
+    let html = render(syntax, None, &"a".repeat(40), &renderer);
+    assert_inert(&html);
+    assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"));
+}
+
+#[test]
+fn uncovered_and_overflowing_text_is_escaped() {
+    let syntax = "start,length,name\n1,2,<b>\n20,60,&amp;\n";
+    let renderer = Renderer {
+        error_color: Some("e00000".to_owned()),
+        ..Renderer::default()
+    };
+    let html = render(syntax, None, NASTY, &renderer);
+    assert_inert(&html);
+    assert!(!html.contains("<b>"));
+    // a name that is already an entity is escaped again, so it shows as written
+    assert!(html.contains("&amp;amp;"));
+}
+
+#[test]
+fn hidden_text_and_boundaries_are_escaped() {
+    let syntax = "start,length,name\n1,3,a\n4,3,\"b\"\"\"\n";
+    let renderer = Renderer {
+        covered_only: true,
+        field_boundaries: Some(FieldBoundaries::Glyph),
+        ..Renderer::default()
+    };
+    let html = render(syntax, None, NASTY, &renderer);
+    assert_inert(&html);
+    assert!(html.contains("&lt;sc"));
+    assert!(html.contains(r#"title="b&quot;""#));
+}
+
+#[test]
+fn delimiters_and_padding_are_escaped() {
+    let syntax = "field,name\n1,\"<i>\"\n2,'\n4,&\n";
+    let renderer = Renderer::default();
+    // the third and fourth fields are missing, so are padded with ghost cells of the delimiter
+    let html = render(syntax, Some('<'.into()), "a&b<\"c\"", &renderer);
+    assert_inert(&html);
+    assert!(!html.contains("<i>"));
+    assert!(html.contains("a&amp;b"));
+    assert!(html.contains("&quot;c&quot;"));
+    assert!(!html.contains("><<"), "unescaped delimiter in {}", html);
+
+    let glyph = Renderer {
+        delimiter_glyph: Some('&'),
+        ..Renderer::default()
+    };
+    let html = render("field,name\n1,a\n2,b\n", Some('|'.into()), "x|y", &glyph);
+    assert_inert(&html);
+    assert!(html.contains(">&amp;</span>"));
+}
+
+#[test]
+fn copied_values_are_escaped() {
+    let renderer = Renderer {
+        copy_buttons: true,
+        ..Renderer::default()
+    };
+    let html = render(
+        "start,length,name\n1,40,\"n\"\"\"\n",
+        None,
+        NASTY,
+        &renderer,
+    );
+    assert_inert(&html);
+    assert!(!html.contains(r#"data-copy="{""#));
+}
+
+#[test]
+fn report_escapes_file_names_and_findings() {
+    let dir = scratch("report");
+    let input = dir.join("<b>&'x'.txt");
+    let syntax = dir.join("syntax.csv");
+    fs::write(&input, format!("{}\n", NASTY)).unwrap();
+    fs::write(
+        &syntax,
+        "start,length,name,condition\n1,3,<script>alert('x')</script>,^<never\n",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .args(["--preflight", "--dashboard", "--unused-rules", "--legend"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .expect("the report is written");
+    // the rule never applies, which is only a warning
+    assert!(output.status.success(), "{:?}", output);
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert_inert(&html);
+    assert!(!html.contains("<b>"));
+    assert!(html.contains("&lt;b&gt;&amp;&#39;x&#39;.txt"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn colours_must_be_hex_codes() {
+    let dir = scratch("colours");
+    let input = dir.join("input.txt");
+    let syntax = dir.join("syntax.csv");
+    fs::write(&input, "abc\n").unwrap();
+    fs::write(&syntax, "start,length,name\n1,3,a\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--colors", r#"fff;" onmouseover="alert(1)"#])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let output = highlighter_command()
+        .args(["--colors", "#fff,c0c0c0", "--show-errors=#e00000"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let html = String::from_utf8(output.stdout).unwrap();
+    assert!(html.contains("background: #fff;"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn record_type_gutter_is_escaped() {
+    let syntax = "record,condition,start,length,name\n<b>,^,,,\n<b>,,1,2,all\n";
+    let renderer = Renderer {
+        record_gutter: Some(3),
+        ..Renderer::default()
+    };
+    let html = render(syntax, None, "ab", &renderer);
+    assert!(!html.contains("<b>"), "unescaped record type in {}", html);
+    assert!(
+        html.contains("L&nbsp;&nbsp;1&nbsp;&lt;b&gt;&nbsp;&gt;&nbsp;"),
+        "no record type in the gutter of {}",
+        html
+    );
+}
@@ -0,0 +1,48 @@
+//! The exit status of a run, which says whether problems were found with the input.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+/// A syntax file of a header and a detail record type.
+const SYNTAX: &str = "record,condition,start,length,name
+hdr,^H,,,
+det,^D,,,
+hdr,,1,1,type
+hdr,,2,4,date
+det,,1,1,type
+det,,2,4,amount
+";
+
+#[test]
+fn rules_left_unused_by_a_selection_are_warnings() {
+    let dir = scratch("unused");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    let report = dir.join("problems.json");
+    fs::write(&syntax, SYNTAX).unwrap();
+    fs::write(&input, "H2024\nD0001\n").unwrap();
+
+    for selection in [["--head", "1"], ["--filter", "^D"]] {
+        let output = highlighter_command()
+            .args(selection)
+            .arg("--error-report")
+            .arg(&report)
+            .arg(&input)
+            .arg(&syntax)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let report = fs::read_to_string(&report).unwrap();
+        assert!(
+            report.contains(r#""kind":"unused_rule","severity":"warning""#),
+            "{}",
+            report
+        );
+        assert!(report.contains(r#""errors": 0"#), "{}", report);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
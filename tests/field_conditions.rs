@@ -0,0 +1,105 @@
+//! Conditions testing the value of a field, such as `field:Type == "D"`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+use fixedfile_highlighter::{Highlighter, PositionOptions};
+
+/// Run the highlighter in `dir` on `input` with `syntax`, writing JSON.
+fn run(dir: &Path, syntax: &str, input: &str) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json", "--trailing-data", "ignore"])
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn conditions_read_the_fields_placed_above_them() {
+    let highlighter = Highlighter::from_syntax(
+        "start,length,name,condition\n1,1,Type,\n2,3,Id,field:Type == \"D\"\n",
+        None,
+    )
+    .expect("the syntax parses")
+    .with_positions(PositionOptions::default())
+    .expect("the conditions are placed");
+    let names = |line: &str| -> Vec<String> {
+        highlighter
+            .regions(line)
+            .iter()
+            .map(|r| r.name.to_string())
+            .collect()
+    };
+    assert_eq!(names("D001"), ["Type", "Id"]);
+    assert_eq!(names("H001"), ["Type"]);
+}
+
+#[test]
+fn fields_are_compared_and_matched() {
+    let dir = scratch("operators");
+    let output = run(
+        &dir,
+        "start,length,name,condition\n1,2,Type,\n3,3,Detail,field:Type == \"D2\"\n3,3,Other,field:Type != D2\n6,1,Flag,field:Type =~ ^D[0-9]$\n",
+        "D2abcX\nH1defY\nD9ghiZ\n",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"Type","start":1,"length":2,"value":"D2"},{"name":"Detail","start":3,"length":3,"value":"abc"},{"name":"Flag","start":6,"length":1,"value":"X"}]"#,
+            "\n",
+            r#"[{"name":"Type","start":1,"length":2,"value":"H1"},{"name":"Other","start":3,"length":3,"value":"def"}]"#,
+            "\n",
+            r#"[{"name":"Type","start":1,"length":2,"value":"D9"},{"name":"Other","start":3,"length":3,"value":"ghi"},{"name":"Flag","start":6,"length":1,"value":"Z"}]"#,
+            "\n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn record_types_are_told_apart_by_a_field() {
+    let dir = scratch("record-types");
+    let output = run(
+        &dir,
+        "record,start,length,name,condition\n,1,2,RecordType,\nheader,,,,field:RecordType == \"H1\"\nheader,3,8,Date,\ndetail,,,,field:RecordType == \"D2\"\ndetail,3,5,Id,\n",
+        "H120261015\nD200042\n",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"RecordType","start":1,"length":2,"value":"H1"},{"name":"Date","start":3,"length":8,"value":"20261015"}]"#,
+            "\n",
+            r#"[{"name":"RecordType","start":1,"length":2,"value":"D2"},{"name":"Id","start":3,"length":5,"value":"00042"}]"#,
+            "\n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_field_must_be_placed_above_the_condition() {
+    let dir = scratch("below");
+    let output = run(
+        &dir,
+        "start,length,name,condition\n3,3,Detail,field:Type == \"D2\"\n1,2,Type,\n",
+        "D2abc\n",
+    );
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Syntax file line 2: condition `field:Type == \"D2\"` reads field 'Type', but no rule above it places a field of that name outside any record type."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,52 @@
+//! Numbers and dates written for other locales, shown in tooltips as they're read.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn european_numbers_and_dates_are_normalised() {
+    let dir = scratch("european");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(
+        &syntax,
+        "start,length,name,type
+1,10,Amount,decimal(comma)
+11,10,ValueDate,date(dd/MM/yyyy)
+21,7,Fee,decimal(2)
+28,19,Created,date(yyyy-MM-ddTHH:mm:ss)
+47,10,Posted,date(iso)
+57,6,Rate,decimal(point)
+",
+    )
+    .unwrap();
+    fs::write(
+        &input,
+        " 1.234,56-31/12/2024 12345-2024-01-19T10:20:302024-02-301,5.00\n",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for title in [
+        r#"title="Amount:  1.234,56- → -1234.56""#,
+        r#"title="ValueDate: 31/12/2024 → 2024-12-31""#,
+        r#"title="Fee:  12345- → -123.45""#,
+        r#"title="Created: 2024-01-19T10:20:30 → 2024-01-19 10:20:30""#,
+        // there's no 30th of February
+        r#"title="Posted: 2024-02-30 (not a valid date in the format `iso`)""#,
+        // grouping separators are left out wherever they are in the whole number
+        r#"title="Rate: 1,5.00 → 15.00""#,
+    ] {
+        assert!(report.contains(title), "{} not in {}", title, report);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,93 @@
+//! Fields placed counted back from the end of the line with the `from_end` column.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` with `syntax`, writing JSON with line numbers, with `args`.
+fn run(dir: &Path, syntax: &str, input: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json", "--line-numbers"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn trailing_fields_follow_the_end_of_each_line() {
+    let dir = scratch("trailing");
+    let output = run(
+        &dir,
+        "start,length,name,from_end\n1,3,id,\n8,8,checksum,true\n",
+        "ABCmiddle12345678\nABClonger middle87654321\n",
+        &["--trailing-data", "ignore"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = report.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"line":1,"fields":[{"name":"id","start":1,"length":3,"value":"ABC"},{"name":"checksum","start":10,"length":8,"value":"12345678"}]}"#,
+            r#"{"line":2,"fields":[{"name":"id","start":1,"length":3,"value":"ABC"},{"name":"checksum","start":17,"length":8,"value":"87654321"}]}"#,
+        ]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_last_column_is_the_index_base() {
+    let dir = scratch("base");
+    let syntax = "start,length,name,from_end\n1,1,last,true\n";
+
+    let output = run(&dir, syntax, "abcd\n", &["--trailing-data", "ignore"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(r#"{"name":"last","start":4,"length":1,"value":"d"}"#));
+
+    let output = run(
+        &dir,
+        syntax,
+        "abcd\n",
+        &["--trailing-data", "ignore", "--index-base", "0"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(r#"{"name":"last","start":3,"length":1,"value":"c"}"#));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_are_left_out_of_lines_too_short_for_them() {
+    let dir = scratch("short");
+    let output = run(
+        &dir,
+        "start,length,name,from_end\n1,2,id,\n8,8,checksum,true\n",
+        "AB\nAB12345678\n",
+        &[],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":1,"fields":[{"name":"id","start":1,"length":2,"value":"AB"}]}"#,
+            "\n",
+            r#"{"line":2,"fields":[{"name":"id","start":1,"length":2,"value":"AB"},{"name":"checksum","start":3,"length":8,"value":"12345678"}]}"#,
+            "\n"
+        )
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("skipped"), "{}", stderr);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,89 @@
+//! Syntax positions and field numbers counted from 0 or 1 with `--index-base`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` with `syntax`, writing JSON, with `args`.
+fn run(dir: &Path, syntax: &str, input: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+/// The value of each field of a JSON report of one line.
+fn values(output: &Output) -> Vec<String> {
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .split(r#""value":""#)
+        .skip(1)
+        .map(|rest| rest[..rest.find('"').unwrap()].to_owned())
+        .collect()
+}
+
+#[test]
+fn columns_are_counted_from_the_base() {
+    let dir = scratch("columns");
+    let syntax = "start,length,name\n0,3,id\n3,2,kind\n";
+
+    let output = run(&dir, syntax, "abcde\n", &["--index-base", "0"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(values(&output), ["abc", "de"]);
+
+    // counted from 1, the first rule starts before the line does and the second a column earlier
+    let output = run(&dir, syntax, "abcde\n", &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(values(&output), ["cd"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Syntax record 'id' skipped as it would start before the first column."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_are_numbered_from_the_base() {
+    let dir = scratch("fields");
+    let syntax = "field,name\n0,a\n1,b\n";
+
+    let output = run(&dir, syntax, "x|y\n", &["-d", "|", "--index-base", "0"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(values(&output), ["x", "y"]);
+
+    let output = run(&dir, syntax, "x|y\n", &["-d", "|"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(values(&output), ["x"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Syntax record 'a' skipped as it would refer to a field before the first."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_0_and_1_are_bases() {
+    let dir = scratch("bases");
+    let output = run(
+        &dir,
+        "start,length,name\n1,1,a\n",
+        "a\n",
+        &["--index-base", "2"],
+    );
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,116 @@
+//! Matching the lines of two files on a key field with `join`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run `join` in `dir` on requests and their acknowledgements, with `args`.
+fn join(dir: &Path, args: &[&str]) -> Output {
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name\n1,4,reference\n5,5,detail\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("requests.dat"),
+        "R001alpha\nR002bravo\nR003delta\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("acks.dat"),
+        "R001ok   \nR003ok   \nR003dup  \nR004late \n",
+    )
+    .unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["join", "requests.dat", "syntax.csv", "--with", "acks.dat"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn csv_has_a_row_for_each_pair_of_lines() {
+    let dir = scratch("csv");
+    let output = join(&dir, &["--key", "reference", "--format", "csv"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            "key,left_line,right_line,left.reference,left.detail,right.reference,right.detail\n",
+            "R001,1,1,R001,alpha,R001,ok   \n",
+            "R002,2,,R002,bravo,,\n",
+            "R003,3,2,R003,delta,R003,ok   \n",
+            "R003,3,3,R003,delta,R003,dup  \n",
+            "R004,,4,,,R004,late \n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn html_shows_the_lines_of_each_key_side_by_side() {
+    let dir = scratch("html");
+    let output = join(&dir, &["--key", "reference"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains("<h1>requests.dat joined with acks.dat</h1>"),
+        "{}",
+        report
+    );
+
+    // the row of each key, in order, with the lines of both files or a note that there are none
+    let rows: Vec<&str> = report.split("<td><code>").skip(1).collect();
+    assert_eq!(rows.len(), 4, "{}", report);
+    for (row, (key, requests, acks)) in rows.iter().zip([
+        ("R001", 1, 1),
+        ("R002", 1, 0),
+        ("R003", 1, 2),
+        ("R004", 0, 1),
+    ]) {
+        assert!(row.starts_with(key), "{}", row);
+        let cells: Vec<&str> = row.split("<td").skip(1).collect();
+        assert_eq!(cells.len(), 2, "{}", row);
+        for (cell, lines) in cells.iter().zip([requests, acks]) {
+            assert_eq!(cell.matches("-reference\"").count(), lines, "{}", cell);
+            assert_eq!(cell.contains("No matching lines"), lines == 0, "{}", cell);
+        }
+    }
+    assert!(
+        rows[2].contains(
+            r#"<abbr id="right-L3-detail" title="detail" class="ffh-c-ccc">dup  </abbr>"#
+        ),
+        "{}",
+        rows[2]
+    );
+    // line 3 of each file has ids of its own
+    for id in [
+        r#"<span id="left-L3""#,
+        r#"<abbr id="left-L3-detail""#,
+        r#"<span id="right-L3""#,
+    ] {
+        assert!(rows[2].contains(id), "{} not in {}", id, rows[2]);
+    }
+    assert!(!report.contains(r#"id="L"#), "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_key_must_be_a_field() {
+    let dir = scratch("key");
+    let output = join(&dir, &["--key", "nope"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("There is no field 'nope' in the syntax of requests.dat."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,106 @@
+//! Byte order marks and line endings, which aren't part of any field.
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{highlighter_command, scratch};
+
+/// Write a syntax file of an `Id` and a `Name` to `dir`, returning its path.
+fn syntax(dir: &std::path::Path) -> PathBuf {
+    let syntax = dir.join("syntax.csv");
+    fs::write(&syntax, "start,length,name\n1,3,Id\n4,5,Name\n").unwrap();
+    syntax
+}
+
+/// Extract the fields of `input` as CSV with `args`, returning the output.
+fn extract(input: &[u8], args: &[&str], test: &str) -> std::process::Output {
+    let dir = scratch(test);
+    let syntax = syntax(&dir);
+    let file = dir.join("input.txt");
+    fs::write(&file, input).unwrap();
+    highlighter_command()
+        .args(["--output-format", "csv"])
+        .args(args)
+        .arg(&file)
+        .arg(&syntax)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn byte_order_mark_and_crlf_are_not_part_of_fields() {
+    let output = extract(b"\xef\xbb\xbf001Alice\r\n002Bob  \r\n", &[], "bom");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1,,Id,001\n"), "{}", stdout);
+    assert!(stdout.contains("1,,Name,Alice\n"), "{}", stdout);
+    assert!(stdout.contains("2,,Name,Bob  \n"), "{}", stdout);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("trailing"));
+}
+
+#[test]
+fn byte_order_mark_can_be_kept() {
+    let output = extract(b"\xef\xbb\xbf001Alice\r\n", &["--keep-bom"], "keep");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1,,Id,\u{feff}00\n"), "{}", stdout);
+}
+
+#[test]
+fn carriage_returns_alone_end_lines() {
+    let output = extract(b"001Alice\r002Bob  \r", &[], "cr");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1,,Name,Alice\n"), "{}", stdout);
+    assert!(stdout.contains("2,,Id,002\n"), "{}", stdout);
+}
+
+#[test]
+fn mixed_line_endings_fail_the_run_when_strict() {
+    let input = b"001Alice\r\n002Bob  \n003Carol\r\n";
+    let output = extract(input, &[], "lenient");
+    assert!(output.status.success(), "{:?}", output);
+
+    let dir = scratch("strict");
+    let report = dir.join("problems.json");
+    let output = extract(
+        input,
+        &[
+            "--strict-line-endings",
+            "--error-report",
+            report.to_str().unwrap(),
+        ],
+        "strict",
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = fs::read_to_string(report).unwrap();
+    assert!(
+        report.contains(r#"{"line":2,"kind":"line_ending","severity":"error""#),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("The line ends in LF rather than CRLF."),
+        "{}",
+        report
+    );
+    assert!(report.contains(r#""errors": 1"#), "{}", report);
+
+    let output = extract(
+        input,
+        &["--line-ending", "lf", "--strict-line-endings"],
+        "lf",
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Line 1: The line ends in CRLF rather than LF."),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Line 3: The line ends in CRLF rather than LF."),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,110 @@
+//! Showing how each line would move from one layout to another with `map`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run `map` in `dir` on `input.dat` from `old.csv`, with `args`.
+fn map(dir: &Path, args: &[&str]) -> Output {
+    highlighter_command()
+        .current_dir(dir)
+        .args(["map", "input.dat", "old.csv"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Write a source layout of an id and a name, two lines of it, and a mapping of each onto `ref` and `short` to `dir`.
+fn write_files(dir: &Path) {
+    fs::write(dir.join("old.csv"), "start,length,name\n1,4,id\n5,6,name\n").unwrap();
+    fs::write(dir.join("input.dat"), "0001ALICE \n0002BOB   \n").unwrap();
+    fs::write(
+        dir.join("mapping.csv"),
+        "source,target\nid,ref\nname,short\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn values_are_shown_in_the_fields_they_move_to() {
+    let dir = scratch("fixed");
+    write_files(&dir);
+    fs::write(
+        dir.join("new.csv"),
+        "start,length,name\n1,6,ref\n7,3,short\n10,2,extra\n",
+    )
+    .unwrap();
+
+    let output = map(&dir, &["--to", "new.csv", "--mapping", "mapping.csv"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    for expected in [
+        "<h1>Mapping from old.csv to new.csv</h1>",
+        "<tr><td>id</td><td>&rarr;</td><td>ref</td></tr>",
+        "<tr><td>name</td><td>&rarr;</td><td>short</td></tr>",
+        // the source line, as it is
+        r#"<abbr id="L1-id" title="id" class="ffh-c-fff">0001</abbr><abbr id="L1-name" title="name" class="ffh-c-ccc">ALICE </abbr>"#,
+        // the target line: padded, cut short, and with the field nothing maps to left empty
+        r#"<abbr id="L1-ref" title="ref" class="ffh-c-fff">0001  </abbr><abbr id="L1-short" title="short" class="ffh-c-ccc">ALI</abbr><abbr id="L1-extra" title="extra" class="ffh-c-fff">  </abbr>"#,
+        r#"<abbr id="L2-short" title="short" class="ffh-c-ccc">BOB</abbr>"#,
+    ] {
+        assert!(report.contains(expected), "{} not in {}", expected, report);
+    }
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for expected in [
+        "Target field 'extra' isn't mapped from any source field, so will be left empty.",
+        "Line 1: the value of 'short' is 6 characters long, but only 3 fit, so it would be cut short.",
+    ] {
+        assert!(stderr.contains(expected), "{} not in {}", expected, stderr);
+    }
+    // BOB fits once its padding is dropped
+    assert!(!stderr.contains("Line 2:"), "{}", stderr);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn values_can_move_to_a_delimited_layout() {
+    let dir = scratch("delimited");
+    write_files(&dir);
+    fs::write(dir.join("new.csv"), "field,name\n1,ref\n2,short\n").unwrap();
+
+    let output = map(
+        &dir,
+        &[
+            "--to",
+            "new.csv",
+            "--to-delimiter",
+            ",",
+            "--mapping",
+            "mapping.csv",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    let expected = r#"<abbr id="L1-ref" title="ref" class="ffh-c-fff">0001</abbr><span class="ffh-delimiter" title="Delimiter">,</span><abbr id="L1-short" title="short" class="ffh-c-ccc">ALICE </abbr>"#;
+    assert!(report.contains(expected), "{} not in {}", expected, report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mappings_must_name_fields_of_the_layouts() {
+    let dir = scratch("unknown");
+    write_files(&dir);
+    fs::write(dir.join("new.csv"), "start,length,name\n1,6,ref\n").unwrap();
+    fs::write(dir.join("mapping.csv"), "source,target\nid,ref\nnope,ref\n").unwrap();
+
+    let output = map(&dir, &["--to", "new.csv", "--mapping", "mapping.csv"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Mapping file: there is no field 'nope' in the source syntax."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
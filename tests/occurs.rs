@@ -0,0 +1,105 @@
+//! Repeating groups declared once with `occurs` and `stride`, and expanded into a field per occurrence.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` with `syntax`, writing JSON, with `args`.
+fn run(dir: &Path, syntax: &str, input: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn occurrences_are_a_stride_apart() {
+    let dir = scratch("stride");
+    let output = run(
+        &dir,
+        "start,length,name,occurs,stride\n1,2,Id,,\n3,3,Amount,3,4\n",
+        "AB111 222 333\n",
+        &[],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"Id","start":1,"length":2,"value":"AB"},{"name":"Amount[1]","start":3,"length":3,"value":"111"},{"name":"Amount[2]","start":7,"length":3,"value":"222"},{"name":"Amount[3]","start":11,"length":3,"value":"333"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn occurrences_follow_on_without_a_stride() {
+    let dir = scratch("adjacent");
+    let output = run(
+        &dir,
+        "start,length,name,occurs,stride\n1,2,Id,,\n3,3,Amount,3,\n",
+        "AB111222333\n",
+        &[],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"Id","start":1,"length":2,"value":"AB"},{"name":"Amount[1]","start":3,"length":3,"value":"111"},{"name":"Amount[2]","start":6,"length":3,"value":"222"},{"name":"Amount[3]","start":9,"length":3,"value":"333"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn occurrences_are_consecutive_fields_in_delimiter_mode() {
+    let dir = scratch("delimited");
+    let output = run(
+        &dir,
+        "field,name,occurs\n1,Id,\n2,Amount,2\n",
+        "a|1|2\n",
+        &["--delimiter", "|"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"Id","start":1,"length":1,"value":"a"},{"name":"Amount[1]","start":3,"length":1,"value":"1"},{"name":"Amount[2]","start":5,"length":1,"value":"2"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mistakes_are_reported_with_their_line() {
+    let dir = scratch("mistakes");
+    for (syntax, message) in [
+        (
+            "start,length,name,occurs,stride\n1,2,Id,,4\n",
+            "Syntax file line 2: `stride` of rule 'Id' is only used with `occurs`.",
+        ),
+        (
+            "start,length,name,occurs,stride\n1,2,Id,0,\n",
+            "Syntax file line 2: `occurs` of rule 'Id' should be at least 1.",
+        ),
+    ] {
+        let output = run(&dir, syntax, "AB\n", &[]);
+        assert_eq!(output.status.code(), Some(2), "{}: {:?}", syntax, output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
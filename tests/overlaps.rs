@@ -0,0 +1,94 @@
+//! Fields that overlap, nested inside each other or split into segments with `--overlaps`.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{highlighter_command, scratch};
+
+/// The HTML of the line `abcdef` highlighted in `dir` with `syntax` and `args`.
+fn line_html(dir: &Path, syntax: &str, args: &[&str]) -> String {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), "abcdef\n").unwrap();
+    let output = highlighter_command()
+        .current_dir(dir)
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    let start = report.find(r#"<abbr id="L1-"#).unwrap();
+    let end = start + report[start..].find(r#"<span class="ffh-muted">"#).unwrap();
+    report[start..end].to_owned()
+}
+
+/// Whether every `abbr` in `html` is closed, and closed inside the element it was opened in.
+fn is_well_formed(html: &str) -> bool {
+    let mut depth = 0usize;
+    for (i, _) in html.match_indices('<') {
+        if html[i..].starts_with("<abbr") {
+            depth += 1;
+        } else if html[i..].starts_with("</abbr>") {
+            match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            }
+        }
+    }
+    depth == 0
+}
+
+#[test]
+fn contained_fields_are_nested() {
+    let dir = scratch("contained");
+    let syntax = "start,length,name\n1,6,outer\n3,2,inner\n";
+
+    let html = line_html(&dir, syntax, &[]);
+    assert_eq!(
+        html,
+        r#"<abbr id="L1-outer" title="outer" class="ffh-c-fff">ab<abbr id="L1-inner" title="inner" class="ffh-c-ccc">cd</abbr>ef</abbr>"#
+    );
+
+    let html = line_html(&dir, syntax, &["--overlaps", "split"]);
+    assert_eq!(
+        html,
+        concat!(
+            r#"<abbr id="L1-outer" title="outer" class="ffh-c-fff">ab</abbr>"#,
+            r#"<abbr id="L1-inner" title="outer › inner" class="ffh-c-ccc">cd</abbr>"#,
+            r#"<abbr title="outer" class="ffh-c-fff">ef</abbr>"#
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_overlapping_in_part_are_still_well_formed() {
+    let dir = scratch("partial");
+    let syntax = "start,length,name\n1,4,a\n3,4,b\n";
+
+    // the part of `b` outside `a` is carried on in an element of its own
+    let html = line_html(&dir, syntax, &[]);
+    assert!(is_well_formed(&html), "{}", html);
+    assert_eq!(
+        html,
+        concat!(
+            r#"<abbr id="L1-a" title="a" class="ffh-c-fff">ab<abbr id="L1-b" title="b" class="ffh-c-ccc">cd</abbr></abbr>"#,
+            r#"<abbr title="b" class="ffh-c-ccc">ef</abbr>"#
+        )
+    );
+
+    let html = line_html(&dir, syntax, &["--overlaps", "split"]);
+    assert!(is_well_formed(&html), "{}", html);
+    assert_eq!(
+        html,
+        concat!(
+            r#"<abbr id="L1-a" title="a" class="ffh-c-fff">ab</abbr>"#,
+            r#"<abbr id="L1-b" title="a › b" class="ffh-c-ccc">cd</abbr>"#,
+            r#"<abbr title="b" class="ffh-c-ccc">ef</abbr>"#
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
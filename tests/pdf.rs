@@ -0,0 +1,144 @@
+//! Reports written directly as PDF with `--output-format pdf`.
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{highlighter_command, scratch};
+
+/// A syntax file of a type and a date.
+const SYNTAX: &str = "start,length,name\n1,1,Type\n2,4,Date\n";
+
+/// The PDF report of `input` in `dir`, with `args`.
+fn report(dir: &Path, input: &str, args: &[&str]) -> Vec<u8> {
+    fs::write(dir.join("syntax.csv"), SYNTAX).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    let output = highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "pdf"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output.stdout
+}
+
+/// `pdf` as text, with each byte that isn't ASCII, such as those of the binary comment after the header, as a `?` so
+/// positions in the text are those in the file.
+fn ascii(pdf: &[u8]) -> String {
+    pdf.iter()
+        .map(|&b| if b.is_ascii() { b as char } else { '?' })
+        .collect()
+}
+
+/// Check `pdf` is well formed: each object is where the cross-reference table says, the table is where the trailer
+/// says, and each stream is as long as its dictionary says.
+fn assert_well_formed(pdf: &[u8]) {
+    let text = ascii(pdf);
+    assert!(text.starts_with("%PDF-1.4\n"), "{}", text);
+    assert!(text.ends_with("%%EOF\n"), "{}", text);
+
+    let startxref: usize = text
+        .rsplit("startxref\n")
+        .next()
+        .and_then(|tail| tail.lines().next())
+        .and_then(|offset| offset.parse().ok())
+        .expect("a startxref offset");
+    assert!(pdf[startxref..].starts_with(b"xref\n"));
+    let mut xref = text[startxref..].lines().skip(1);
+    let count: usize = xref
+        .next()
+        .and_then(|header| header.strip_prefix("0 "))
+        .and_then(|count| count.parse().ok())
+        .expect("a cross-reference table of every object");
+    for (object, entry) in xref.take(count).enumerate().skip(1) {
+        let offset: usize = entry[..10].parse().unwrap();
+        let expected = format!("{} 0 obj\n", object);
+        assert!(
+            pdf[offset..].starts_with(expected.as_bytes()),
+            "object {} isn't at {}",
+            object,
+            offset
+        );
+    }
+    assert!(
+        text.contains(&format!("<< /Size {} /Root 1 0 R >>", count)),
+        "{}",
+        text
+    );
+
+    for (at, _) in text.match_indices("<< /Length ") {
+        let rest = &text[at + "<< /Length ".len()..];
+        let length: usize = rest[..rest.find(' ').unwrap()].parse().unwrap();
+        let start = at + text[at..].find("stream\n").unwrap() + "stream\n".len();
+        assert!(
+            pdf[start + length..].starts_with(b"\nendstream"),
+            "the stream at {} isn't {} bytes long",
+            start,
+            length
+        );
+    }
+}
+
+#[test]
+fn reports_are_well_formed_pdf_with_every_field() {
+    let dir = scratch("fields");
+    let pdf = report(&dir, "H2024\nD1999\n", &[]);
+    assert_well_formed(&pdf);
+
+    let text = ascii(&pdf);
+    for shown in [
+        "(Analysis of input.txt) Tj",
+        "(L  1 > ) Tj",
+        "(H) Tj",
+        "(2024) Tj",
+        "(L  2 > ) Tj",
+        "(1999) Tj",
+        "(Page 1) Tj",
+        "/Count 1 >>",
+    ] {
+        assert!(text.contains(shown), "{} not in {}", shown, text);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn long_reports_are_paginated_with_a_legend_at_the_end() {
+    let dir = scratch("pages");
+    let input: String = (1..=120).map(|i| format!("D{:04}\n", i)).collect();
+    let pdf = report(&dir, &input, &["--legend"]);
+    assert_well_formed(&pdf);
+
+    let text = ascii(&pdf);
+    assert!(text.contains("/Count 3 >>"), "{}", text);
+    for shown in [
+        "(Page 3) Tj",
+        "(0120) Tj",
+        "(Legend) Tj",
+        "(Date  2      4) Tj",
+    ] {
+        assert!(text.contains(shown), "{} not in {}", shown, text);
+    }
+    // the legend follows the last line
+    assert!(text.find("(0120) Tj").unwrap() < text.find("(Legend) Tj").unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn text_is_escaped_and_encoded_for_the_fonts() {
+    let dir = scratch("text");
+    let pdf = report(&dir, "H(\\)x\nDé€✓x\n", &[]);
+    assert_well_formed(&pdf);
+
+    let text = ascii(&pdf);
+    // parentheses and backslashes are escaped, and characters outside ASCII written as their WinAnsiEncoding
+    // codes, or `?` where it has none
+    for shown in [r"(\(\\\)x) Tj", r"(\351\200?x) Tj"] {
+        assert!(text.contains(shown), "{} not in {}", shown, text);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,90 @@
+//! Rules placed by `${name}` placeholders, taking their position from a named capture group of a condition.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on `input` with `syntax`, writing JSON with line numbers, with `args`.
+fn run(dir: &Path, syntax: &str, input: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json", "--line-numbers"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn lengths_are_taken_from_the_line() {
+    let dir = scratch("length");
+    let output = run(
+        &dir,
+        "start,length,name,condition\n1,2,Length,^(?P<len>\\d{2})\n3,${len},Payload,\n",
+        "03abc\n05abcde\nxxabc\n",
+        &[],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":1,"fields":[{"name":"Length","start":1,"length":2,"value":"03"},{"name":"Payload","start":3,"length":3,"value":"abc"}]}"#,
+            "\n",
+            r#"{"line":2,"fields":[{"name":"Length","start":1,"length":2,"value":"05"},{"name":"Payload","start":3,"length":5,"value":"abcde"}]}"#,
+            "\n",
+            r#"{"line":3,"fields":[]}"#,
+            "\n",
+        )
+    );
+    // the last line has no length, so the payload is skipped rather than guessed at
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Syntax record 'Payload' skipped as its capture group didn't capture a number on this line."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn field_numbers_are_taken_from_the_line() {
+    let dir = scratch("field");
+    let output = run(
+        &dir,
+        "field,name,condition\n1,Which,^(?P<f>\\d)\n${f},Picked,\n",
+        "2|a|b\n3|a|b\n",
+        &["--delimiter", "|", "--trailing-data", "ignore"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":1,"fields":[{"name":"Which","start":1,"length":1,"value":"2"},{"name":"Picked","start":3,"length":1,"value":"a"}]}"#,
+            "\n",
+            r#"{"line":2,"fields":[{"name":"Which","start":1,"length":1,"value":"3"},{"name":"Picked","start":5,"length":1,"value":"b"}]}"#,
+            "\n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn placeholders_must_name_a_capture_group() {
+    let dir = scratch("unknown");
+    let output = run(&dir, "start,length,name\n1,${nope},A\n", "abc\n", &[]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Syntax file line 2: column `length` of rule 'A' is `${nope}`, which isn't a named capture group of any condition. Name one with `(?P<nope>...)`."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,151 @@
+//! Decoder plugins, which are programs, so only run when they're allowed to be.
+#![cfg(unix)]
+
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt, path::Path, time::Instant};
+
+use common::{highlighter_command, scratch};
+
+/// Write a plugin to `dir` that leaves a file `started` when it's run, and answers each value `zz` as invalid and
+/// every other as its flags.
+fn write_plugin(dir: &Path) {
+    let plugin = dir.join("decode.sh");
+    fs::write(
+        &plugin,
+        "#!/bin/sh
+touch started
+while read value; do
+    if [ \"$value\" = zz ]; then
+        printf 'invalid\\tnot hex\\n'
+    else
+        printf 'ok\\tflags %s\\n' \"$value\"
+    fi
+done
+",
+    )
+    .unwrap();
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn plugins_only_run_when_allowed() {
+    let dir = scratch("allowed");
+    let decoders = dir.join("decoders");
+    fs::create_dir_all(&decoders).unwrap();
+    write_plugin(&decoders);
+    let syntax = decoders.join("syntax.csv");
+    fs::write(
+        &syntax,
+        "start,length,name,type\n1,2,Flags,plugin(./decode.sh)\n",
+    )
+    .unwrap();
+    let input = dir.join("input.txt");
+    fs::write(&input, "01\nzz\n").unwrap();
+
+    let output = highlighter_command()
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Give --allow-plugins"), "{}", stderr);
+    assert!(!decoders.join("started").exists(), "the plugin was run");
+
+    // the plugin is relative to the syntax file, and runs beside it, wherever the highlighter is run from
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .arg("--allow-plugins")
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(decoders.join("started").exists(), "the plugin wasn't run");
+    // the invalid value is a broken constraint
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("flags 01"), "{}", report);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("which its plugin `./decode.sh` says isn't valid, as not hex"),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn plugins_that_dont_answer_are_stopped() {
+    let dir = scratch("timeout");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(
+        &syntax,
+        "start,length,name,type\n1,2,Flags,plugin(sleep 60)\n",
+    )
+    .unwrap();
+    fs::write(&input, "01\n02\n").unwrap();
+
+    let started = Instant::now();
+    let output = highlighter_command()
+        .args(["--allow-plugins", "--plugin-timeout", "1"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(
+        started.elapsed().as_secs() < 30,
+        "the run waited for the plugin"
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Plugin `sleep 60` failed, as it didn't answer within 1 second(s)."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_files_cant_allow_plugins() {
+    let dir = scratch("config");
+    write_plugin(&dir);
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name,type\n1,2,Flags,plugin(./decode.sh)\n",
+    )
+    .unwrap();
+    fs::write(dir.join("input.txt"), "01\n").unwrap();
+
+    // a project's config file comes with the files it's used on, so is no more trusted than its syntax files
+    for (setting, option) in [
+        ("allow-plugins = true", "allow-plugins"),
+        ("plugin_timeout = 60", "plugin-timeout"),
+    ] {
+        fs::write(
+            dir.join(".fixedfile-highlighter.toml"),
+            format!("{}\n", setting),
+        )
+        .unwrap();
+        let output = highlighter_command()
+            .current_dir(&dir)
+            .env("HOME", &dir)
+            .args(["input.txt", "syntax.csv"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?}", output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error = format!(
+            "line 1: `{}` can only be given on the command line, as it lets syntax files run programs.",
+            option
+        );
+        assert!(stderr.contains(&error), "{}", stderr);
+        assert!(!dir.join("started").exists(), "the plugin was run");
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,75 @@
+//! Delimited fields quoted with `--quote`, which may hold the delimiter and doubled quotes.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on lines of three comma delimited fields, some quoted, with `args`.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    fs::write(
+        dir.join("syntax.csv"),
+        "field,name,pattern\n1,Id,\n2,Name,\n3,Amount,^[0-9]+$\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("input.txt"),
+        "1,\"Smith, John\",10\n2,\"Say \"\"hi\"\"\",20\n3,plain,30\n",
+    )
+    .unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--delimiter", ","])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn quoted_fields_hold_delimiters_and_doubled_quotes() {
+    let dir = scratch("values");
+    let output = run(&dir, &["--quote", "\"", "--output-format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"Id","start":1,"length":1,"value":"1"},{"name":"Name","start":3,"length":13,"value":"Smith, John"},{"name":"Amount","start":17,"length":2,"value":"10"}]"#,
+            "\n",
+            r#"[{"name":"Id","start":1,"length":1,"value":"2"},{"name":"Name","start":3,"length":12,"value":"Say \"hi\""},{"name":"Amount","start":16,"length":2,"value":"20"}]"#,
+            "\n",
+            r#"[{"name":"Id","start":1,"length":1,"value":"3"},{"name":"Name","start":3,"length":5,"value":"plain"},{"name":"Amount","start":9,"length":2,"value":"30"}]"#,
+            "\n",
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn quotes_are_highlighted_as_part_of_the_field() {
+    let dir = scratch("html");
+    let output = run(&dir, &["--quote", "\""]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    let row = r#"<abbr id="L1-name" title="Name" class="ffh-c-ccc">&quot;Smith, John&quot;</abbr><span class="ffh-delimiter" title="Delimiter">,</span><abbr id="L1-amount" title="Amount" class="ffh-c-fff">10</abbr>"#;
+    assert!(report.contains(row), "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn without_quote_the_delimiter_splits_quoted_fields() {
+    let dir = scratch("unquoted");
+    let output = run(&dir, &["--output-format", "json"]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Line 1: field 'Amount' has the value ' John\"', which doesn't match its pattern `^[0-9]+$`."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
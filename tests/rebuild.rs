@@ -0,0 +1,96 @@
+//! Lines rebuilt from the fields extracted from them.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+const SYNTAX: &str = "record,condition,start,length,name,justify,pad
+hdr,^H,,,,,
+det,^D,,,,,
+hdr,,1,1,type,,
+hdr,,2,8,date,,
+det,,1,1,type,,
+det,,2,5,amount,right,0
+det,,7,6,name,left,
+";
+
+const INPUT: &str = "H20260101\nD00142Alice \nD00007Bob   \n";
+
+#[test]
+fn extracted_fields_rebuild_the_lines() {
+    let dir = scratch("round-trip");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(&syntax, SYNTAX).unwrap();
+    fs::write(&input, INPUT).unwrap();
+
+    for format in ["csv", "json", "jsonl"] {
+        let extracted = dir.join(format!("fields.{}", format));
+        let output = highlighter_command()
+            .args(["--output-format", format, "-o"])
+            .arg(&extracted)
+            .arg(&input)
+            .arg(&syntax)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+
+        let output = highlighter_command()
+            .arg("rebuild")
+            .arg(&extracted)
+            .arg(&syntax)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            INPUT,
+            "{}",
+            format
+        );
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn edited_values_are_padded_and_justified() {
+    let dir = scratch("edited");
+    let syntax = dir.join("syntax.csv");
+    let extracted = dir.join("fields.csv");
+    fs::write(&syntax, SYNTAX).unwrap();
+    fs::write(
+        &extracted,
+        "line,record_type,field,value\n2,det,type,D\n2,det,amount,150\n2,det,name,Al\n",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .arg("rebuild")
+        .arg(&extracted)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "D00150Al    \n");
+
+    fs::write(
+        &extracted,
+        "line,record_type,field,value\n2,det,type,D\n2,det,name,Alexandra\n",
+    )
+    .unwrap();
+    let output = highlighter_command()
+        .arg("rebuild")
+        .arg(&extracted)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("too long for the field"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
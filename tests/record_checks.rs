@@ -0,0 +1,62 @@
+//! Keys that must be unique and fields that must be sorted, checked across records.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn duplicate_keys_and_values_out_of_order_are_errors() {
+    let dir = scratch("keys");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    let problems = dir.join("problems.json");
+    fs::write(
+        &syntax,
+        "start,length,name,condition,check
+1,1,Type,,
+2,4,Id,^D,unique
+6,5,Amount,^D,sorted:desc
+2,4,RecordCount,^T,count
+",
+    )
+    .unwrap();
+    // the trailer starts the keys and order again, so the last two records are checked only against each other
+    fs::write(
+        &input,
+        "D000100500\nD000200400\nD000100450\nT0003\nD000100100\nD000200200\n",
+    )
+    .unwrap();
+
+    let output = highlighter_command()
+        .args(["--output-format", "ansi", "--error-report"])
+        .arg(&problems)
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains("Field 'Id' repeats 0001, the key of line 1"),
+        "{}",
+        report
+    );
+
+    let problems = fs::read_to_string(&problems).unwrap();
+    for problem in [
+        r#"{"line":3,"kind":"duplicate_key","severity":"error","field":"Id","value":"0001","#,
+        r#"{"line":3,"kind":"out_of_order","severity":"error","field":"Amount","value":"00450","#,
+        r#"{"line":6,"kind":"out_of_order","severity":"error","field":"Amount","value":"00200","#,
+    ] {
+        assert!(
+            problems.contains(problem),
+            "{} not in {}",
+            problem,
+            problems
+        );
+    }
+    assert!(problems.contains(r#""errors": 3"#), "{}", problems);
+    fs::remove_dir_all(&dir).unwrap();
+}
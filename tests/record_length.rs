@@ -0,0 +1,85 @@
+//! Input read as records of a fixed number of bytes with `--record-length`, which may have any byte in them.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on records of 4 bytes of `input`, with `args`.
+fn run(dir: &Path, input: &[u8], args: &[&str]) -> Output {
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name\n1,3,Id\n4,1,Flag\n",
+    )
+    .unwrap();
+    fs::write(dir.join("input.dat"), input).unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--record-length", "4"])
+        .args(args)
+        .args(["input.dat", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn line_feeds_and_carriage_returns_are_bytes_of_a_record() {
+    let dir = scratch("breaks");
+    let input = b"AAAA\nB\rCCCCC";
+
+    let output = run(&dir, input, &["--output-format", "json", "--line-numbers"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"{"line":1,"fields":[{"name":"Id","start":1,"length":3,"value":"AAA"},{"name":"Flag","start":4,"length":1,"value":"A"}]}"#,
+            "\n",
+            r#"{"line":2,"fields":[{"name":"Id","start":1,"length":3,"value":"\nB\r"},{"name":"Flag","start":4,"length":1,"value":"C"}]}"#,
+            "\n",
+            r#"{"line":3,"fields":[{"name":"Id","start":1,"length":3,"value":"CCC"},{"name":"Flag","start":4,"length":1,"value":"C"}]}"#,
+            "\n",
+        )
+    );
+
+    // shown as the hex of their bytes, rather than breaking the line
+    let output = run(&dir, input, &["--output-format", "html"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains(r#"<abbr id="L2-id" title="Id" class="ffh-c-fff"><span class="ffh-hex" title="0x0A">0A</span>B<span class="ffh-hex" title="0x0D">0D</span></abbr>"#),
+        "{}",
+        report
+    );
+
+    let output = run(&dir, input, &["--output-format", "ansi"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(report.lines().count(), 3, "{}", report);
+    assert!(!report.contains('\r'), "{}", report);
+    assert!(report.contains("\x1b[7m0A"), "{}", report);
+    assert!(report.contains("\x1b[7m0D"), "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lines_with_the_character_joining_lines_are_refused() {
+    let dir = scratch("noncharacter");
+    fs::write(dir.join("syntax.csv"), "start,length,name\n1,2,Id\n").unwrap();
+    fs::write(dir.join("input.txt"), "AB\nC\u{ffff}\n").unwrap();
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Line 2 has the noncharacter U+FFFF, which can't be read as it's what joins the lines of a record."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
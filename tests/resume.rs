@@ -0,0 +1,134 @@
+//! Reports carried on with `--resume` from the checkpoint an interrupted run left.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// The line after the first checkpoint, which is taken every 10,000 lines, that the first run is interrupted at.
+const INTERRUPTED_AT: usize = 15_000;
+
+/// Write a report of `lines` with `syntax` in `dir`, interrupting the run part way through by making a line after the
+/// first checkpoint too long to read, then resume it from the checkpoint with the input as it should be. Gives the
+/// output of the resumed run.
+fn interrupt_and_resume(dir: &Path, syntax: &str, lines: &[String], args: &[&str]) -> Output {
+    let syntax_file = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    let checkpoint = dir.join("checkpoint.csv");
+    let report = dir.join("report.html");
+    fs::write(&syntax_file, syntax).unwrap();
+    let run = || {
+        highlighter_command()
+            .args(["--max-line-length", "80", "--resume"])
+            .arg(&checkpoint)
+            .arg("--output")
+            .arg(&report)
+            .args(args)
+            .arg(&input)
+            .arg(&syntax_file)
+            .output()
+            .unwrap()
+    };
+
+    let mut interrupted = lines.to_vec();
+    interrupted[INTERRUPTED_AT] = "X".repeat(100);
+    fs::write(&input, interrupted.join("\n") + "\n").unwrap();
+    let output = run();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    assert!(checkpoint.exists(), "no checkpoint was left");
+
+    fs::write(&input, lines.join("\n") + "\n").unwrap();
+    let output = run();
+    assert!(
+        !checkpoint.exists(),
+        "the checkpoint was left after the report was finished"
+    );
+    output
+}
+
+#[test]
+fn problems_after_the_checkpoint_fail_the_run() {
+    let dir = scratch("problems");
+    let mut lines = vec!["D0001".to_owned(); 25_000];
+    lines[20_000] = "D1".to_owned();
+    let output = interrupt_and_resume(
+        &dir,
+        "start,length,name\n1,1,type\n2,4,amount\n",
+        &lines,
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 field_past_end"), "{}", stderr);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_summary_covers_the_lines_before_the_checkpoint() {
+    let dir = scratch("summary");
+    let syntax = "record,condition,start,length,name
+hdr,^H,,,
+det,^D,,,
+hdr,,1,1,type
+det,,1,1,type
+det,,2,4,amount
+";
+    let mut lines = vec!["D0001".to_owned(); 25_000];
+    lines[0] = "H".to_owned();
+    lines[5_000] = "D1".to_owned();
+    let output = interrupt_and_resume(&dir, syntax, &lines, &["--unused-rules"]);
+    // the only problem was found before the checkpoint
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Read 25000 line(s) of"), "{}", stderr);
+    assert!(
+        stderr.contains("finding 1 error(s) and 0 warning(s): 1 field_past_end."),
+        "{}",
+        stderr
+    );
+    // the header's rule applied before the checkpoint, so isn't unused
+    let report = fs::read_to_string(dir.join("report.html")).unwrap();
+    assert!(
+        report.contains("Every rule applied to at least one line."),
+        "{}",
+        &report[report.len() - 2000..]
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn control_totals_and_keys_carry_on_across_the_checkpoint() {
+    let dir = scratch("totals");
+    let syntax = "start,length,name,condition,check
+1,1,Type,,
+2,6,Id,^D,unique
+8,3,Amount,^D,
+2,6,RecordCount,^T,count
+8,9,Total,^T,sum:Amount
+";
+    let mut lines: Vec<String> = (0..30_000)
+        .map(|i| format!("D{:06}{:03}", i, i % 7))
+        .collect();
+    let total: usize = (0..30_000).map(|i| i % 7).sum();
+    lines.push(format!("T{:06}{:09}", 30_000, total));
+    let output = interrupt_and_resume(&dir, syntax, &lines, &[]);
+    // the count and total include the records before the checkpoint
+    assert!(output.status.success(), "{:?}", output);
+
+    // a key first seen before the checkpoint is still known after it
+    lines[20_000] = lines[5_000].clone();
+    let output = interrupt_and_resume(&dir, syntax, &lines, &[]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Line 20001: Field 'Id' repeats 005000, the key of line 5001."),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("1 duplicate_key"), "{}", stderr);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,79 @@
+//! Lines split into the segments each renderer draws.
+
+use fixedfile_highlighter::{
+    segments, Delimiter, Highlighter, Segment, SegmentKind, ShortLinePolicy,
+};
+
+fn split(syntax: &str, delimiter: Option<Delimiter>, line: &str) -> Vec<Segment> {
+    let highlighter = Highlighter::from_syntax(syntax, delimiter)
+        .expect("the syntax parses")
+        .with_short_lines(ShortLinePolicy::Pad);
+    let prepared = highlighter
+        .prepare(line.to_owned(), 0)
+        .expect("the line is prepared");
+    segments(&prepared, true)
+}
+
+/// The kind and characters of each segment.
+fn kinds(segments: &[Segment]) -> Vec<(SegmentKind, std::ops::Range<usize>)> {
+    segments.iter().map(|s| (s.kind, s.chars.clone())).collect()
+}
+
+#[test]
+fn fields_gaps_violations_and_trailing_data() {
+    let segments = split(
+        "start,length,name,charset\n1,3,a,0-9\n5,2,b,\n",
+        None,
+        "1x3 bcXY",
+    );
+    assert_eq!(
+        kinds(&segments),
+        vec![
+            (SegmentKind::Field, 0..1),
+            (SegmentKind::Violation, 1..2),
+            (SegmentKind::Field, 2..3),
+            (SegmentKind::Gap, 3..4),
+            (SegmentKind::Field, 4..6),
+            (SegmentKind::Trailing, 6..8),
+        ]
+    );
+    assert!(segments[1].reason.is_some());
+    assert_eq!(segments[1].fields, vec![0]);
+    assert!(segments[3].fields.is_empty());
+    assert_eq!(segments[4].innermost(), Some(1));
+}
+
+#[test]
+fn nested_fields_are_given_outermost_first() {
+    let segments = split("start,length,name\n3,2,inner\n1,6,outer\n", None, "abcdef");
+    assert_eq!(
+        kinds(&segments),
+        vec![
+            (SegmentKind::Field, 0..2),
+            (SegmentKind::Field, 2..4),
+            (SegmentKind::Field, 4..6),
+        ]
+    );
+    assert_eq!(segments[0].fields, vec![1]);
+    assert_eq!(segments[1].fields, vec![1, 0]);
+    assert_eq!(segments[1].innermost(), Some(0));
+}
+
+#[test]
+fn delimiters_and_padding() {
+    let segments = split("field,name\n1,a\n2,b\n3,c\n", Some('|'.into()), "xy|z");
+    let kinds = kinds(&segments);
+    assert_eq!(
+        kinds[..3],
+        [
+            (SegmentKind::Field, 0..2),
+            (SegmentKind::Delimiter, 2..3),
+            (SegmentKind::Field, 3..4),
+        ]
+    );
+    // the missing third field is padded with ghost cells
+    assert!(kinds[3..]
+        .iter()
+        .all(|(kind, _)| *kind == SegmentKind::Ghost));
+    assert!(!kinds[3..].is_empty());
+}
@@ -0,0 +1,96 @@
+//! The report served a page at a time.
+
+mod common;
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    process::{Child, Stdio},
+};
+
+use common::{highlighter_command, scratch};
+
+/// A running server, stopped when it's dropped.
+struct Server {
+    child: Child,
+    address: String,
+}
+
+impl Server {
+    /// Serve `input` with `syntax` on a port the system picks.
+    fn start(input: &PathBuf, syntax: &PathBuf) -> Server {
+        let mut child = highlighter_command()
+            .args(["serve", "--port", "0", "--page-lines", "2"])
+            .arg(input)
+            .arg(syntax)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("the server starts");
+        let mut line = String::new();
+        BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let address = line
+            .trim_end()
+            .rsplit("http://")
+            .next()
+            .unwrap()
+            .trim_end_matches('/')
+            .to_owned();
+        Server { child, address }
+    }
+
+    /// The response to a GET request for `target`.
+    fn get(&self, target: &str) -> String {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+fn pages_are_searched_and_follow_the_syntax_file() {
+    let dir = scratch("pages");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(&syntax, "start,length,name\n1,1,type\n2,3,amount\n").unwrap();
+    fs::write(&input, "D001\nD002\nD103\nX104\nD105\n").unwrap();
+    let server = Server::start(&input, &syntax);
+
+    let page = server.get("/?page=2");
+    assert!(page.starts_with("HTTP/1.1 200 OK"), "{}", page);
+    assert!(page.contains("Lines 3–4 of 5"), "{}", page);
+    assert!(page.contains(r#"<span id="L3""#), "{}", page);
+    assert!(!page.contains(r#"<span id="L2""#), "{}", page);
+    assert!(page.contains("/?page=3"), "{}", page);
+
+    let found = server.get("/?q=D1");
+    assert!(found.contains("Lines 1–2 of 2"), "{}", found);
+    assert!(found.contains(r#"<span id="L5""#), "{}", found);
+
+    // the syntax file is read again for each page, and a mistake in it is shown in place of the report
+    let version = server.get("/version");
+    fs::write(&syntax, "start,length,name\n1,1,type\n2,3\n").unwrap();
+    let broken = server.get("/");
+    assert!(
+        broken.contains("Failed to parse syntax record"),
+        "{}",
+        broken
+    );
+    assert_ne!(server.get("/version"), version);
+
+    assert!(server.get("/elsewhere").starts_with("HTTP/1.1 404"));
+    fs::remove_dir_all(&dir).unwrap();
+}
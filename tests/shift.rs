@@ -0,0 +1,99 @@
+//! Shifting the fields of every record, or of one record type, from where the syntax file puts them.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+const SYNTAX: &str = "start,length,name,record,condition
+,,,header,^H
+,,,detail,^D
+1,1,Type,,
+2,4,Date,header,
+2,3,Id,detail,
+5,2,Amount,detail,
+";
+
+#[test]
+fn record_shifts_move_only_the_rules_of_their_type() {
+    let dir = scratch("record");
+    let syntax = dir.join("syntax.csv");
+    fs::write(&syntax, SYNTAX).unwrap();
+
+    let output = highlighter_command()
+        .args([
+            "export-ruleset",
+            "--shift",
+            "1",
+            "--shift-record",
+            "detail=+2",
+        ])
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let ruleset = String::from_utf8(output.stdout).unwrap();
+    for rule in [
+        r#"{"name":"Type","start":1,"length":1,"end":2,"#,
+        r#"{"name":"Date","start":2,"length":4,"end":6,"#,
+        r#"{"name":"Id","start":4,"length":3,"end":7,"#,
+        r#"{"name":"Amount","start":7,"length":2,"end":9,"#,
+    ] {
+        assert!(ruleset.contains(rule), "{} not in {}", rule, ruleset);
+    }
+}
+
+#[test]
+fn record_shifts_are_noted_in_the_report() {
+    let dir = scratch("note");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(&syntax, SYNTAX).unwrap();
+    // the detail line has gained two columns after its type
+    fs::write(&input, "H2024\nD  00112\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--output-format", "ansi", "--shift-record", "detail=2"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains(
+            "the fields of 'detail' records have been shifted by +2 column(s) relative to the syntax file."
+        ),
+        "{}",
+        report
+    );
+    assert!(
+        !report.contains("all fields have been shifted"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn record_shifts_must_name_a_record_type() {
+    let dir = scratch("unknown");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(&syntax, SYNTAX).unwrap();
+    fs::write(&input, "H2024\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--output-format", "ansi", "--shift-record", "trailer=1"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("--shift-record names the record type 'trailer'"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,32 @@
+//! Lines shorter than the layout, with each `--short-lines` policy.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn padding_is_bounded() {
+    let dir = scratch("bounded");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    fs::write(&syntax, "start,length,name\n1,1,Type\n99999999,1,Far\n").unwrap();
+    fs::write(&input, "H\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--output-format", "ansi", "--short-lines", "pad"])
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Line 1 is shorter than the layout by 99999998 column(s), more than the 1048576 it can be padded by."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,96 @@
+//! Syntax records that would be skipped or misapplied, which fail the run with `--strict-syntax`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// Run the highlighter in `dir` on a line with `syntax`, writing JSON, with `args`.
+fn run(dir: &Path, syntax: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("syntax.csv"), syntax).unwrap();
+    fs::write(dir.join("input.txt"), "abcde\n").unwrap();
+    highlighter_command()
+        .current_dir(dir)
+        .args(["--output-format", "json"])
+        .args(args)
+        .args(["input.txt", "syntax.csv"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn malformed_records_are_named_by_line_and_column() {
+    let dir = scratch("malformed");
+    for (syntax, message) in [
+        (
+            "start,length,name\n1,3,id\n,2,kind\n",
+            "Syntax file line 3: column `start` is missing for rule 'kind'.",
+        ),
+        (
+            "start,length,name\n1,3,id\n4,,kind\n",
+            "Syntax file line 3: column `length` is missing for rule 'kind'.",
+        ),
+        (
+            "start,length,name\n1,3,id\n4,0,kind\n",
+            "Syntax file line 3: column `length` is 0 for rule 'kind'.",
+        ),
+        (
+            "start,length,name\n0,3,id\n",
+            "Syntax file line 2: column `start` is 0 for rule 'id', but columns are numbered from 1.",
+        ),
+    ] {
+        let output = run(&dir, syntax, &["--strict-syntax"]);
+        assert_eq!(output.status.code(), Some(2), "{}: {:?}", syntax, output);
+        assert!(output.stdout.is_empty(), "{:?}", output);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn malformed_records_are_otherwise_skipped() {
+    let dir = scratch("skipped");
+    let output = run(&dir, "start,length,name\n1,3,id\n,2,kind\n", &[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        concat!(
+            r#"[{"name":"id","start":1,"length":3,"value":"abc"}]"#,
+            "\n"
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_before_the_first_fail_in_delimiter_mode() {
+    let dir = scratch("fields");
+    let output = run(
+        &dir,
+        "field,name\n0,a\n",
+        &["--strict-syntax", "--delimiter", "|"],
+    );
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Syntax file line 2: column `field` is 0 for rule 'a', but fields are numbered from 1."
+        ),
+        "{}",
+        stderr
+    );
+
+    // the same field is fine counted from 0
+    let output = run(
+        &dir,
+        "field,name\n0,a\n",
+        &["--strict-syntax", "--delimiter", "|", "--index-base", "0"],
+    );
+    assert!(output.status.success(), "{:?}", output);
+
+    fs::remove_dir_all(dir).unwrap();
+}
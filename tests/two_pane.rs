@@ -0,0 +1,52 @@
+//! The decoded values of every line in a pane beside the report with `--two-pane`.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn a_pane_too_large_for_memory_is_spilled_and_read_back_in_full() {
+    let dir = scratch("spilled");
+    fs::write(
+        dir.join("syntax.csv"),
+        "start,length,name\n1,6,Id\n7,20,Text\n",
+    )
+    .unwrap();
+    // enough lines that their decoded values are more than the 8 MiB held in memory
+    let lines = 60_000;
+    let input: String = (0..lines)
+        .map(|i| format!("{:06}{}\n", i, "x".repeat(20)))
+        .collect();
+    fs::write(dir.join("input.txt"), input).unwrap();
+
+    let child = highlighter_command()
+        .current_dir(&dir)
+        .env("LOG", "info")
+        .args(["--two-pane", "-o", "report.html", "input.txt", "syntax.csv"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let spool = std::env::temp_dir().join(format!("fixedfile-highlighter-{}-0.spool", child.id()));
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Spilling output to"), "{}", stderr);
+    assert!(!spool.exists(), "{} wasn't removed", spool.display());
+
+    let report = fs::read_to_string(dir.join("report.html")).unwrap();
+    let pane = &report[report.find(r#"<div id="ffh-decoded""#).unwrap()..];
+    let mut from = 0;
+    for line in 1..=lines {
+        let at = pane[from..]
+            .find(&format!(r#"<div id="D{}" "#, line))
+            .unwrap_or_else(|| {
+                panic!("line {} isn't in the pane, after the lines before it", line)
+            });
+        from += at;
+    }
+    assert!(pane.contains("<code>059999</code>"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,148 @@
+//! Checking a control file against the data file it describes with `validate`.
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{highlighter_command, scratch};
+
+/// A control file of a count and a total for each batch.
+const CONTROL_SYNTAX: &str = "start,length,name
+1,2,batch
+3,3,record_count
+6,6,batch_total
+";
+
+/// A data file of an amount in each batch.
+const DETAIL_SYNTAX: &str = "start,length,name
+1,2,batch
+3,5,amount
+";
+
+/// Run `validate` in `dir` on `control.dat` against `detail.dat`, with `args`.
+fn validate(dir: &Path, args: &[&str]) -> Output {
+    highlighter_command()
+        .current_dir(dir)
+        .args(["validate", "control.dat", "control.csv"])
+        .args(["--with", "detail.dat", "--with-syntax", "detail.csv"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Write the syntax files, the control file `control` and a data file of three lines in two batches to `dir`.
+fn write_files(dir: &Path, control: &str) {
+    fs::write(dir.join("control.csv"), CONTROL_SYNTAX).unwrap();
+    fs::write(dir.join("detail.csv"), DETAIL_SYNTAX).unwrap();
+    fs::write(dir.join("control.dat"), control).unwrap();
+    fs::write(dir.join("detail.dat"), "B100100\nB100050\nB200099\n").unwrap();
+}
+
+#[test]
+fn matching_counts_and_totals_are_consistent() {
+    let dir = scratch("consistent");
+    write_files(&dir, "B1002000150\nB2001000099\n");
+
+    let output = validate(
+        &dir,
+        &[
+            "--join",
+            "batch",
+            "--count",
+            "record_count",
+            "--total",
+            "batch_total=amount",
+        ],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "control.dat and detail.dat are consistent: 2 control key(s) checked against 3 line(s).\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn every_mismatch_and_missing_key_is_a_problem() {
+    let dir = scratch("mismatched");
+    write_files(&dir, "B1003000150\nB3001000099\n");
+
+    let output = validate(
+        &dir,
+        &[
+            "--join",
+            "batch",
+            "--count",
+            "record_count",
+            "--total",
+            "batch_total=amount",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "Control line 1: 'record_count' for key 'B1' is 003, but detail.dat has 2 line(s).
+Control line 2: key 'B3' has no lines in detail.dat.
+Control line 2: 'record_count' for key 'B3' is 001, but detail.dat has 0 line(s).
+Control line 2: 'batch_total' for key 'B3' is 99, but 'amount' in detail.dat adds up to 0.
+detail.dat line 3: key 'B2' is not in control.dat.
+"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Validation found 5 problem(s)."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn without_a_join_the_control_file_describes_the_whole_data_file() {
+    let dir = scratch("whole");
+    let args = ["--count", "record_count", "--total", "batch_total=amount"];
+    write_files(&dir, "B1003000249\n");
+
+    let output = validate(&dir, &args);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("1 control record(s) checked against 3 line(s)"),
+        "{}",
+        stdout
+    );
+
+    fs::write(dir.join("control.dat"), "B1004000200\n").unwrap();
+    let output = validate(&dir, &args);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "Control line 1: 'record_count' for the file is 004, but detail.dat has 3 line(s).
+Control line 1: 'batch_total' for the file is 200, but 'amount' in detail.dat adds up to 249.
+"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn totals_must_name_fields_of_both_files() {
+    let dir = scratch("unknown");
+    write_files(&dir, "B1002000150\n");
+
+    let output = validate(&dir, &["--total", "batch_total=nope"]);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("There is no field 'nope' in the data syntax."),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,36 @@
+//! Values drawn in a style when they match their field's pattern.
+
+use fixedfile_highlighter::{value_styles, Highlighter, ValueStyle};
+
+const SYNTAX: &str = "start,length,name,value_pattern,value_style
+1,5,amount,^-,blue italic
+6,3,code,!^[A-Z]+$,
+";
+
+/// Whether each field of `line` is drawn in a style, and which.
+fn styles(line: &str) -> Vec<Option<ValueStyle>> {
+    let highlighter = Highlighter::from_syntax(SYNTAX, None).expect("the syntax parses");
+    let prepared = highlighter
+        .prepare(line.to_owned(), 0)
+        .expect("the line is prepared");
+    value_styles(&prepared)
+        .into_iter()
+        .map(|s| s.cloned())
+        .collect()
+}
+
+#[test]
+fn matching_values_are_styled() {
+    let blue_italic = ValueStyle::parse("blue italic").unwrap();
+    assert_eq!(styles("  -12ABC"), vec![Some(blue_italic), None]);
+    assert_eq!(styles("   12aBC"), vec![None, Some(ValueStyle::default())]);
+}
+
+#[test]
+fn styles_are_parsed() {
+    let style = ValueStyle::parse("Bold #FF0000 underline").unwrap();
+    assert_eq!(style.spec(), "ff0000 bold underline");
+    assert_eq!(style.class(), "ffh-v-ff0000-b-u");
+    assert!(ValueStyle::parse("red blue").is_err());
+    assert!(ValueStyle::parse(" ").is_err());
+}
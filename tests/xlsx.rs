@@ -0,0 +1,110 @@
+//! The fields of every line written as an Excel workbook.
+
+mod common;
+
+use std::{collections::HashMap, fs};
+
+use common::{highlighter_command, scratch};
+
+fn u16_at(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) as usize
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The entries of a zip file of stored entries, by name, checking each against its CRC-32.
+fn unzip(bytes: &[u8]) -> HashMap<String, String> {
+    let end = bytes.len() - 22;
+    assert_eq!(
+        u32_at(bytes, end),
+        0x0605_4b50,
+        "no end of central directory"
+    );
+    let mut at = u32_at(bytes, end + 16) as usize;
+    let mut entries = HashMap::new();
+    for _ in 0..u16_at(bytes, end + 10) {
+        assert_eq!(u32_at(bytes, at), 0x0201_4b50, "no central directory entry");
+        let crc = u32_at(bytes, at + 16);
+        let size = u32_at(bytes, at + 20) as usize;
+        let name_length = u16_at(bytes, at + 28);
+        let offset = u32_at(bytes, at + 42) as usize;
+        let name = String::from_utf8(bytes[at + 46..at + 46 + name_length].to_vec()).unwrap();
+        let start = offset + 30 + u16_at(bytes, offset + 26) + u16_at(bytes, offset + 28);
+        let contents = &bytes[start..start + size];
+        assert_eq!(crc32(contents), crc, "{} doesn't match its CRC-32", name);
+        entries.insert(name, String::from_utf8(contents.to_vec()).unwrap());
+        at += 46 + name_length;
+    }
+    entries
+}
+
+#[test]
+fn each_record_type_has_a_sheet() {
+    let dir = scratch("sheets");
+    let syntax = dir.join("syntax.csv");
+    let input = dir.join("input.txt");
+    let workbook = dir.join("fields.xlsx");
+    fs::write(
+        &syntax,
+        "record,condition,start,length,name,pattern
+header,^H,,,,
+detail,^D,,,,
+header,,1,1,type,
+header,,2,8,date,
+detail,,1,1,type,
+detail,,2,5,amount,[0-9]+
+detail,,7,3,line,
+",
+    )
+    .unwrap();
+    fs::write(&input, "H20260101\nD00150A&B\nD12x45\n").unwrap();
+
+    let output = highlighter_command()
+        .args(["--output-format", "xlsx", "-o"])
+        .arg(&workbook)
+        .arg(&input)
+        .arg(&syntax)
+        .output()
+        .unwrap();
+    // the last line breaks its pattern and ends early
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+
+    let entries = unzip(&fs::read(&workbook).unwrap());
+    let names = &entries["xl/workbook.xml"];
+    assert!(
+        names.contains(r#"<sheet name="header" sheetId="1" r:id="rId1"/><sheet name="detail" sheetId="2" r:id="rId2"/><sheet name="Errors" sheetId="3" r:id="rId3"/>"#),
+        "{}",
+        names
+    );
+    let details = &entries["xl/worksheets/sheet2.xml"];
+    // a field named `line` has a column of its own, and values keep their leading zeros
+    for cell in [
+        r#"<c r="D1" s="1" t="inlineStr"><is><t xml:space="preserve">line</t></is></c>"#,
+        r#"<c r="A2"><v>2</v></c>"#,
+        r#"<c r="C2" t="inlineStr"><is><t xml:space="preserve">00150</t></is></c>"#,
+        r#"<c r="D2" t="inlineStr"><is><t xml:space="preserve">A&amp;B</t></is></c>"#,
+    ] {
+        assert!(details.contains(cell), "{} not in {}", cell, details);
+    }
+    let errors = &entries["xl/worksheets/sheet3.xml"];
+    assert!(errors.contains(">field_past_end<"), "{}", errors);
+    assert!(errors.contains(">12x45<"), "{}", errors);
+    fs::remove_dir_all(&dir).unwrap();
+}
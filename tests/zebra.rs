@@ -0,0 +1,34 @@
+//! Every other row of a report shaded with `--zebra`.
+
+mod common;
+
+use std::fs;
+
+use common::{highlighter_command, scratch};
+
+#[test]
+fn rows_alternate_when_lines_are_left_out() {
+    let dir = scratch("filter");
+    fs::write(dir.join("syntax.csv"), "start,length,name\n1,2,Id\n").unwrap();
+    fs::write(dir.join("input.txt"), "A1\nB2\nA3\nB4\nA5\n").unwrap();
+    let output = highlighter_command()
+        .current_dir(&dir)
+        .args(["--zebra", "--filter", "^A", "input.txt", "syntax.csv"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+
+    // lines 1, 3 and 5 are shown, and the second of them is shaded, though each has an odd number
+    let shaded = r#"<span class="ffh-row" style="background:#f2f2f2;">"#;
+    for (line, is_shaded) in [(1, false), (3, true), (5, false)] {
+        let row = report
+            .lines()
+            .find(|row| row.contains(&format!(r#"<span id="L{}" "#, line)))
+            .unwrap_or_else(|| panic!("line {} not in {}", line, report));
+        assert_eq!(row.starts_with(shaded), is_shaded, "{}", row);
+    }
+    assert_eq!(report.matches(shaded).count(), 1, "{}", report);
+
+    fs::remove_dir_all(dir).unwrap();
+}